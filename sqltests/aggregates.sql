@@ -0,0 +1,77 @@
+-- Where aggregate functions can be used : HAVING without GROUP BY aggregates the whole table into a
+-- single group, while the clauses evaluated before the aggregation (like WHERE) reject aggregates
+-- when the statement is planned.
+
+CREATE TABLE t (id INT PRIMARY KEY, v INT);
+----
+Created table t
+
+INSERT INTO t VALUES (1, 10), (2, 20), (3, 30);
+----
+Inserted 3 rows
+
+-- The whole table is a single group, which HAVING keeps or drops.
+SELECT COUNT(*) FROM t HAVING COUNT(*) > 10;
+----
+?
+
+SELECT COUNT(*), SUM(v) FROM t HAVING COUNT(*) > 2;
+----
+?,?
+3,60
+
+SELECT 'many' FROM t HAVING MAX(v) - MIN(v) >= 20;
+----
+?
+many
+
+-- An empty table is a single group too.
+SELECT COUNT(*) FROM t WHERE v > 100 HAVING COUNT(*) = 0;
+----
+?
+0
+
+SELECT id FROM t HAVING COUNT(*) > 1;
+----
+error: Value 42803: Column id must appear in GROUP BY or be used in an aggregate function
+
+SELECT COUNT(*) FROM t HAVING v > 1;
+----
+error: Value 42803: Column v must appear in GROUP BY or be used in an aggregate function
+
+SELECT id FROM t WHERE COUNT(*) > 1;
+----
+error: Value 42803: Aggregate functions can't be used in WHERE : count(TRUE) > 1 (use HAVING to filter on aggregates)
+
+SELECT id FROM t WHERE v > (SELECT AVG(v) FROM t);
+----
+id
+3
+
+SELECT SUM(COUNT(*)) FROM t;
+----
+error: Value 42803: Aggregate functions can't be nested : sum(count(TRUE))
+
+SELECT COUNT(*) FROM t HAVING SUM(MAX(v)) > 1;
+----
+error: Value 42803: Aggregate functions can't be nested : sum(max(v))
+
+SELECT id FROM t GROUP BY COUNT(*);
+----
+error: Value 42803: Aggregate functions can't be used in GROUP BY : count(TRUE)
+
+SELECT t.id FROM t JOIN t AS u ON COUNT(*) > 1;
+----
+error: Value 42803: Aggregate functions can't be used in JOIN predicate : count(TRUE) > 1
+
+UPDATE t SET v = MAX(v);
+----
+error: Value 42803: Aggregate functions can't be used in UPDATE SET : max(v)
+
+DELETE FROM t WHERE SUM(v) > 1;
+----
+error: Value 42803: Aggregate functions can't be used in WHERE : sum(v) > 1 (use HAVING to filter on aggregates)
+
+INSERT INTO t VALUES (4, COUNT(*));
+----
+error: Value 42803: Aggregate functions can't be used in VALUES : count(TRUE)
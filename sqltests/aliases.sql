@@ -0,0 +1,92 @@
+-- The WHERE clause can't reference the aliases of the selections, since it's evaluated before them.
+-- HAVING (like ORDER BY and GROUP BY) can.
+
+CREATE TABLE orders (id INT PRIMARY KEY, customer STRING, price INT, qty INT);
+----
+Created table orders
+
+INSERT INTO orders VALUES (1, 'alice', 30, 5), (2, 'alice', 20, 2), (3, 'bob', 60, 3), (4, 'carol', 10, 4);
+----
+Inserted 4 rows
+
+-- A reference to an alias from WHERE is reported as such.
+SELECT id, price * qty AS total FROM orders WHERE total > 100;
+----
+error: Value 42703: Unknown column total : aliases of the selections can't be referenced in WHERE, repeat the expression or use a subquery
+
+SELECT id, price * qty AS total FROM orders WHERE price * qty > 100 ORDER BY id;
+----
+id,total
+1,150
+3,180
+
+-- Other unknown columns are reported as usual.
+SELECT id, price * qty AS total FROM orders WHERE totl > 100;
+----
+error: Value 42703: Unknown column totl
+
+-- A name which is both a column and an alias refers to the column in WHERE.
+SELECT id, qty AS price FROM orders WHERE price > 25 ORDER BY id;
+----
+id,price
+1,5
+3,3
+
+SELECT customer, sum(price * qty) AS total FROM orders GROUP BY customer HAVING total > 100 ORDER BY customer;
+----
+customer,total
+alice,190
+bob,180
+
+SELECT customer AS name, count(*) AS n FROM orders GROUP BY name HAVING n > 1 AND name != 'bob';
+----
+name,n
+alice,2
+
+-- With lenient aliases, the references to aliases from WHERE are substituted by the aliased
+-- expressions.
+SET lenient_aliases = TRUE;
+----
+Set lenient_aliases
+
+SELECT id, price * qty AS total FROM orders WHERE total > 100 ORDER BY id;
+----
+id,total
+1,150
+3,180
+
+SELECT id, price * qty AS total FROM orders WHERE total > 100 AND id > 1 OR total < 50 ORDER BY id;
+----
+id,total
+2,40
+3,180
+4,40
+
+SELECT id, qty AS price FROM orders WHERE price > 25 ORDER BY id;
+----
+id,price
+1,5
+3,3
+
+SELECT customer, sum(price * qty) AS total FROM orders WHERE total > 100 GROUP BY customer;
+----
+error: Value 42803: Alias total refers to an aggregate, which can't be used in WHERE : use HAVING instead
+
+-- Subqueries are always planned strictly.
+SELECT id FROM orders WHERE qty = (SELECT max(qty) AS m FROM orders WHERE m > 0);
+----
+error: Value 42703: Unknown column m : aliases of the selections can't be referenced in WHERE, repeat the expression or use a subquery
+
+-- The plan built by the lenient session isn't cached. So the same statement still fails, once
+-- aliases aren't lenient.
+SET lenient_aliases = FALSE;
+----
+Set lenient_aliases
+
+SELECT id, price * qty AS total FROM orders WHERE total > 100 ORDER BY id;
+----
+error: Value 42703: Unknown column total : aliases of the selections can't be referenced in WHERE, repeat the expression or use a subquery
+
+SET lenient_aliases = 1;
+----
+error: Value 22023: lenient_aliases must be a boolean, got 1
@@ -0,0 +1,188 @@
+-- ALTER TABLE renames a table or one of its columns. The rows and the index entries are stored by the
+-- ids of the table and its columns, so they're kept as they are.
+
+CREATE TABLE customers (id INT PRIMARY KEY, email TEXT UNIQUE, name TEXT);
+----
+Created table customers
+
+CREATE INDEX lower_name ON customers ((LOWER(name)));
+----
+Created index lower_name
+
+CREATE TABLE orders (id INT PRIMARY KEY, customer INT REFERENCES customers, parent INT REFERENCES orders);
+----
+Created table orders
+
+INSERT INTO customers VALUES (1, 'ada@example.com', 'Ada'), (2, 'bob@example.com', 'Bob');
+----
+Inserted 2 rows
+
+INSERT INTO orders VALUES (1, 1, NULL), (2, 2, 1);
+----
+Inserted 2 rows
+
+ALTER TABLE customers RENAME TO clients;
+----
+Altered table clients
+
+SELECT * FROM clients ORDER BY id;
+----
+id,email,name
+1,ada@example.com,Ada
+2,bob@example.com,Bob
+
+SELECT * FROM customers;
+----
+error: Value 42P01: Table customers doesn't exist
+
+-- The references to the table are renamed as well, and still enforced.
+DESCRIBE orders;
+----
+name,type,nullable,default,primary_key,unique,index,references,on_delete
+id,INTEGER,FALSE,,TRUE,FALSE,FALSE,,
+customer,INTEGER,TRUE,NULL,FALSE,FALSE,FALSE,clients,RESTRICT
+parent,INTEGER,TRUE,NULL,FALSE,FALSE,FALSE,orders,RESTRICT
+
+INSERT INTO orders VALUES (3, 3, NULL);
+----
+error: Value 23503: Referenced primary key 3 doesn't exist in table clients
+
+DELETE FROM clients WHERE id = 2;
+----
+error: Value 23503: Primary key 2 of table clients is referenced by row 2 of table orders
+
+-- The references of a table to itself too.
+ALTER TABLE orders RENAME TO purchases;
+----
+Altered table purchases
+
+DESCRIBE purchases;
+----
+name,type,nullable,default,primary_key,unique,index,references,on_delete
+id,INTEGER,FALSE,,TRUE,FALSE,FALSE,,
+customer,INTEGER,TRUE,NULL,FALSE,FALSE,FALSE,clients,RESTRICT
+parent,INTEGER,TRUE,NULL,FALSE,FALSE,FALSE,purchases,RESTRICT
+
+INSERT INTO purchases VALUES (3, 1, 4);
+----
+error: Value 23503: Referenced primary key 4 doesn't exist in table purchases
+
+ALTER TABLE purchases RENAME TO clients;
+----
+error: Value 42P07: Table clients already exists
+
+ALTER TABLE missing RENAME TO other;
+----
+error: Value 42P01: Table missing doesn't exist
+
+-- Renaming a column renames it in the expression indexes as well, which are still used.
+ALTER TABLE clients RENAME COLUMN name TO full_name;
+----
+Altered table clients
+
+SELECT name FROM clients;
+----
+error: Value 42703: Unknown column name
+
+EXPLAIN SELECT id FROM clients WHERE LOWER(full_name) = 'bob';
+----
+Projection: #0 as id
+└─ IndexLookup: clients column lower_name ('bob')
+
+SELECT id, full_name FROM clients WHERE LOWER(full_name) = 'bob';
+----
+id,full_name
+2,Bob
+
+INSERT INTO clients VALUES (3, 'cy@example.com', 'Cy');
+----
+Inserted 1 row
+
+SELECT id FROM clients WHERE LOWER(full_name) = 'cy';
+----
+id
+3
+
+-- The unique constraints are kept.
+ALTER TABLE clients RENAME COLUMN email TO address;
+----
+Altered table clients
+
+INSERT INTO clients VALUES (4, 'ada@example.com', 'Other Ada');
+----
+error: Value 23505: Unique value ada@example.com already exists for column address in table clients (row 1)
+
+-- The new name can't be taken by another column or index, or be the system column.
+ALTER TABLE clients RENAME COLUMN address TO full_name;
+----
+error: Value 42701: Table clients already has a column or an index named full_name
+
+ALTER TABLE clients RENAME COLUMN address TO lower_name;
+----
+error: Value 42701: Table clients already has a column or an index named lower_name
+
+ALTER TABLE clients RENAME COLUMN address TO _version;
+----
+error: Value 42939: Column name _version is reserved for the system column
+
+ALTER TABLE clients RENAME COLUMN missing TO other;
+----
+error: Value 42703: Column missing doesn't exist in table clients
+
+-- Renaming a column to its own name does nothing.
+ALTER TABLE clients RENAME COLUMN id TO id;
+----
+Altered table clients
+
+SELECT * FROM clients ORDER BY id;
+----
+id,address,full_name
+1,ada@example.com,Ada
+2,bob@example.com,Bob
+3,cy@example.com,Cy
+
+DESCRIBE clients;
+----
+name,type,nullable,default,primary_key,unique,index,references,on_delete
+id,INTEGER,FALSE,,TRUE,FALSE,FALSE,,
+address,STRING,TRUE,NULL,FALSE,TRUE,FALSE,,
+full_name,STRING,TRUE,NULL,FALSE,FALSE,FALSE,,
+
+-- The primary key column can be renamed too.
+ALTER TABLE clients RENAME COLUMN id TO client_id;
+----
+Altered table clients
+
+SELECT client_id, address FROM clients WHERE client_id = 2;
+----
+client_id,address
+2,bob@example.com
+
+-- A table created under the old name gets a new id, so it doesn't see the rows of the renamed one.
+CREATE TABLE customers (id INT PRIMARY KEY, name TEXT);
+----
+Created table customers
+
+SELECT * FROM customers;
+----
+id,name
+
+DROP TABLE customers;
+----
+Dropped table customers
+
+DROP TABLE clients;
+----
+error: Value 2BP01: Table clients is referenced by column customer of table purchases
+
+DROP TABLE purchases;
+----
+Dropped table purchases
+
+DROP TABLE clients;
+----
+Dropped table clients
+
+SHOW TABLES;
+----
+table
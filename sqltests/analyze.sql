@@ -0,0 +1,129 @@
+-- ANALYZE collects the statistics of the tables, which the planner estimates the number of rows
+-- produced by the plan nodes from.
+
+CREATE TABLE digits (d INT PRIMARY KEY);
+----
+Created table digits
+
+INSERT INTO digits VALUES (0), (1), (2), (3), (4), (5), (6), (7), (8), (9);
+----
+Inserted 10 rows
+
+CREATE TABLE orders (id INT PRIMARY KEY, state STRING INDEX, customer INT INDEX, total FLOAT);
+----
+Created table orders
+
+-- 95 of the orders are shipped, and 5 are pending.
+INSERT INTO orders SELECT a.d * 10 + b.d, CASE WHEN a.d * 10 + b.d < 95 THEN 'shipped' ELSE 'pending' END, b.d, a.d * 10.0 FROM digits a, digits b;
+----
+Inserted 100 rows
+
+-- Without statistics, an equality comparison of an indexed column looks up the index.
+EXPLAIN SELECT id FROM orders WHERE state = 'shipped';
+----
+Projection: #0 as id
+└─ IndexLookup: orders column state ('shipped')
+
+ANALYZE orders;
+----
+Analyzed 1 table
+
+-- With them, the lookup of a value most rows have scans the table instead. The lookup of a value
+-- few rows have still looks up the index.
+EXPLAIN SELECT id FROM orders WHERE state = 'shipped';
+----
+Projection: #0 as id (estimated rows=50)
+└─ Scan: orders (#1 = 'shipped') (estimated rows=50)
+
+EXPLAIN SELECT id FROM orders WHERE customer = 3;
+----
+Projection: #0 as id (estimated rows=10)
+└─ IndexLookup: orders column customer (3) (estimated rows=10)
+
+SELECT COUNT(*) FROM orders WHERE state = 'shipped';
+----
+?
+95
+
+-- Ranges are estimated from the min and the max of a column.
+EXPLAIN SELECT id FROM orders WHERE id >= 80 AND total < 50.0;
+----
+Projection: #0 as id (estimated rows=11)
+└─ Scan: orders key [80, +inf) (#3 < 50.0) (estimated rows=11)
+
+EXPLAIN SELECT customer, COUNT(*) FROM orders GROUP BY customer;
+----
+Projection: #0 as customer, #1 (estimated rows=10)
+└─ Aggregation: count(#1) group by #0 (estimated rows=10)
+   └─ Projection: #2, TRUE (estimated rows=100)
+      └─ Scan: orders (estimated rows=100)
+
+-- The hash table of a join is built over the side estimated to have fewer rows.
+ANALYZE;
+----
+Analyzed 2 tables
+
+EXPLAIN SELECT o.id FROM orders o JOIN digits d ON o.customer = d.d;
+----
+Projection: #0 as id (estimated rows=100)
+└─ HashJoin: inner on left #2 = right #0 (estimated rows=100)
+   └─ Scan: orders as o (estimated rows=100)
+   └─ Scan: digits as d (estimated rows=10)
+
+EXPLAIN SELECT o.id FROM digits d JOIN orders o ON o.customer = d.d;
+----
+Projection: #2 as id (estimated rows=100)
+└─ HashJoin: inner on left #0 = right #2, build left (estimated rows=100)
+   └─ Scan: digits as d (estimated rows=10)
+   └─ Scan: orders as o (estimated rows=100)
+
+SELECT COUNT(*) FROM digits d JOIN orders o ON o.customer = d.d WHERE d.d < 2;
+----
+?
+20
+
+-- EXPLAIN ANALYZE <table> explains an ANALYZE.
+EXPLAIN ANALYZE orders;
+----
+Analyze: orders
+
+EXPLAIN ANALYZE;
+----
+Analyze: digits, orders
+
+-- Once many rows are written since, the statistics are ignored.
+INSERT INTO orders SELECT 100 + a.d * 10 + b.d, 'pending', 0, 0.0 FROM digits a, digits b;
+----
+Inserted 100 rows
+
+EXPLAIN SELECT id FROM orders WHERE state = 'pending';
+----
+Projection: #0 as id
+└─ IndexLookup: orders column state ('pending')
+
+ANALYZE orders;
+----
+Analyzed 1 table
+
+EXPLAIN SELECT id FROM orders WHERE state = 'pending';
+----
+Projection: #0 as id (estimated rows=100)
+└─ Scan: orders (#1 = 'pending') (estimated rows=100)
+
+-- Truncating a table drops its statistics.
+TRUNCATE TABLE orders;
+----
+Deleted 200 rows
+
+EXPLAIN SELECT id FROM orders WHERE state = 'shipped';
+----
+Projection: #0 as id
+└─ IndexLookup: orders column state ('shipped')
+
+ANALYZE missing;
+----
+error: Value 42P01: Table missing doesn't exist
+
+ANALYZE orders, digits;
+----
+error: ParseAt 42601: line 1, column 15: Unexpected token ,
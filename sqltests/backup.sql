@@ -0,0 +1,175 @@
+-- !backup writes a consistent snapshot of the database to a file, which !restore loads back.
+
+CREATE TABLE users (id INT PRIMARY KEY, email STRING UNIQUE, team INT INDEX, admin BOOLEAN DEFAULT FALSE, score FLOAT, born DATE, seen TIMESTAMP);
+----
+Created table users
+
+-- A self-referencing table, whose rows reference rows with greater keys.
+CREATE TABLE employees (id INT PRIMARY KEY, manager INT REFERENCES employees);
+----
+Created table employees
+
+CREATE TABLE posts (id INT PRIMARY KEY, author INT REFERENCES users ON DELETE CASCADE, title STRING NOT NULL);
+----
+Created table posts
+
+CREATE INDEX lower_email ON users ((LOWER(email)));
+----
+Created index lower_email
+
+INSERT INTO users VALUES (1, 'Alice@X.com', 1, TRUE, 1.5, DATE '1990-01-02', TIMESTAMP '2024-05-06 07:08:09'), (2, 'bob@x.com', NULL, NULL, NULL, NULL, NULL);
+----
+Inserted 2 rows
+
+INSERT INTO users (id, email) VALUES (3, 'carol@x.com');
+----
+Inserted 1 row
+
+INSERT INTO employees VALUES (3, NULL), (2, 3), (1, 2);
+----
+Inserted 3 rows
+
+INSERT INTO posts VALUES (1, 1, 'Hello'), (2, 1, 'Again'), (3, 2, 'Hi');
+----
+Inserted 3 rows
+
+!backup snapshot
+----
+Backed up 3 tables (9 rows)
+
+-- The data changes after the backup.
+DELETE FROM users WHERE id = 1;
+----
+Deleted 1 row
+
+INSERT INTO users (id, email) VALUES (4, 'dave@x.com');
+----
+Inserted 1 row
+
+DROP TABLE employees;
+----
+Dropped table employees
+
+-- Restoring into a database which isn't empty needs --force.
+!restore snapshot
+----
+error: Value: needs --force
+
+!restore snapshot --force
+----
+Restored 3 tables (9 rows)
+
+SELECT * FROM users;
+----
+id,email,team,admin,score,born,seen
+1,Alice@X.com,1,TRUE,1.5,1990-01-02,2024-05-06 07:08:09
+2,bob@x.com,,,,,
+3,carol@x.com,,FALSE,,,
+
+SELECT * FROM employees;
+----
+id,manager
+1,2
+2,3
+3,
+
+SELECT * FROM posts;
+----
+id,author,title
+1,1,Hello
+2,1,Again
+3,2,Hi
+
+-- The indexes and the constraints are restored too.
+EXPLAIN SELECT id FROM users WHERE LOWER(email) = 'alice@x.com';
+----
+Projection: #0 as id
+└─ IndexLookup: users column lower_email ('alice@x.com')
+
+SELECT id FROM users WHERE LOWER(email) = 'alice@x.com';
+----
+id
+1
+
+SELECT id FROM users WHERE team = 1;
+----
+id
+1
+
+DESCRIBE users;
+----
+name,type,nullable,default,primary_key,unique,index,references,on_delete
+id,INTEGER,FALSE,,TRUE,FALSE,FALSE,,
+email,STRING,TRUE,NULL,FALSE,TRUE,FALSE,,
+team,INTEGER,TRUE,NULL,FALSE,FALSE,TRUE,,
+admin,BOOLEAN,TRUE,FALSE,FALSE,FALSE,FALSE,,
+score,FLOAT,TRUE,NULL,FALSE,FALSE,FALSE,,
+born,DATE,TRUE,NULL,FALSE,FALSE,FALSE,,
+seen,TIMESTAMP,TRUE,NULL,FALSE,FALSE,FALSE,,
+
+INSERT INTO users (id, email) VALUES (5, 'bob@x.com');
+----
+error: Value: Unique value bob@x.com already exists for column email in table users (row 2)
+
+INSERT INTO posts VALUES (4, 9, 'Nobody');
+----
+error: Value
+
+DELETE FROM users WHERE id = 1;
+----
+Deleted 1 row
+
+SELECT * FROM posts;
+----
+id,author,title
+3,2,Hi
+
+-- Restoring into an empty database.
+DROP TABLE posts;
+----
+Dropped table posts
+
+DROP TABLE employees;
+----
+Dropped table employees
+
+DROP TABLE users;
+----
+Dropped table users
+
+!restore snapshot
+----
+Restored 3 tables (9 rows)
+
+SELECT COUNT(*) FROM users;
+----
+?
+3
+
+SELECT * FROM employees;
+----
+id,manager
+1,2
+2,3
+3,
+
+-- Backups can't be taken or restored in a transaction.
+BEGIN;
+----
+Began transaction 21
+
+!backup again
+----
+error: Value: in a transaction
+
+ROLLBACK;
+----
+Rolled back transaction 21
+
+!restore missing
+----
+error: Internal: No such file or directory (os error 2)
+
+!frobnicate
+----
+error: Value: Unknown command
@@ -0,0 +1,130 @@
+-- DATE and TIMESTAMP values : typed literals, ordering, arithmetic and indexed range queries.
+
+CREATE TABLE events (at TIMESTAMP PRIMARY KEY, day DATE INDEX, name STRING);
+----
+Created table events
+
+INSERT INTO events VALUES
+  (TIMESTAMP '2024-01-15 10:30:00', DATE '2024-01-15', 'standup'),
+  (TIMESTAMP '2024-01-15 09:00:00.25', DATE '2024-01-15', 'coffee'),
+  (TIMESTAMP '1969-12-31 23:59:59', DATE '1969-12-31', 'before epoch'),
+  (TIMESTAMP '2024-02-29T18:00:00', DATE '2024-02-29', 'leap day'),
+  (TIMESTAMP '2023-12-31', DATE '2023-12-31', 'new year''s eve');
+----
+Inserted 5 rows
+
+SELECT * FROM events;
+----
+at,day,name
+1969-12-31 23:59:59,1969-12-31,before epoch
+2023-12-31 00:00:00,2023-12-31,new year's eve
+2024-01-15 09:00:00.25,2024-01-15,coffee
+2024-01-15 10:30:00,2024-01-15,standup
+2024-02-29 18:00:00,2024-02-29,leap day
+
+SELECT name FROM events ORDER BY at DESC LIMIT 2;
+----
+name
+leap day
+standup
+
+-- Range queries over the primary key, and lookups of the index.
+EXPLAIN SELECT name FROM events WHERE at >= TIMESTAMP '2024-01-01' AND at < TIMESTAMP '2024-01-15 10:00:00';
+----
+Projection: #2 as name
+└─ Scan: events key [TIMESTAMP '2024-01-01 00:00:00', TIMESTAMP '2024-01-15 10:00:00')
+
+SELECT name FROM events WHERE at >= TIMESTAMP '2024-01-01' AND at < TIMESTAMP '2024-01-15 10:00:00';
+----
+name
+coffee
+
+EXPLAIN SELECT name FROM events WHERE day = DATE '2024-01-15';
+----
+Projection: #2 as name
+└─ IndexLookup: events column day (DATE '2024-01-15')
+
+SELECT name FROM events WHERE day >= DATE '2024-01-01' AND day <= DATE '2024-12-31' ORDER BY at;
+----
+name
+coffee
+standup
+leap day
+
+SELECT name FROM events WHERE day = DATE '2024-01-15' ORDER BY name;
+----
+name
+coffee
+standup
+
+-- Subtracting yields microseconds (days for dates), and adding an integer offsets the value.
+SELECT at - TIMESTAMP '2024-01-15 00:00:00' AS since, day - DATE '2024-01-01' AS days FROM events WHERE name = 'standup';
+----
+since,days
+37800000000,14
+
+SELECT at + 86400000000 AS next, day - 366 AS before, 1 + day AS after FROM events WHERE name = 'leap day';
+----
+next,before,after
+2024-03-01 18:00:00,2023-02-28,2024-03-01
+
+SELECT MIN(at), MAX(day) FROM events;
+----
+?,?
+1969-12-31 23:59:59,2024-02-29
+
+SELECT DATE '2024-01-15' = DATE '2024-01-15', TIMESTAMP '2024-01-15 00:00:00' < TIMESTAMP '2024-01-15 00:00:00.000001';
+----
+?,?
+TRUE,TRUE
+
+-- The literals are validated when they're parsed.
+SELECT DATE '2023-02-29';
+----
+error: ParseAt: line 1, column 13: Invalid date '2023-02-29', expected YYYY-MM-DD
+
+SELECT TIMESTAMP '2024-01-15 24:00:00';
+----
+error: ParseAt: line 1, column 18: Invalid timestamp '2024-01-15 24:00:00', expected YYYY-MM-DD HH:MM:SS[.ffffff]
+
+SELECT DATE 20240115;
+----
+error: ParseAt: line 1, column 13: Expected string, got 20240115
+
+SELECT DATE '9999-12-31' + 1;
+----
+error: Value: Date out of range
+
+SELECT DATE '2024-01-15' = TIMESTAMP '2024-01-15 00:00:00';
+----
+error: Value: Can't compare
+
+SELECT at + 1.5 FROM events;
+----
+error: Value: Can't add
+
+-- NOW( ) returns the same time for all its calls in a statement.
+SELECT NOW( ) = NOW( ), NOW( ) - NOW( ), NOW( ) > TIMESTAMP '2024-01-01';
+----
+?,?,?
+TRUE,0,TRUE
+
+CREATE TABLE log (id INT PRIMARY KEY, at TIMESTAMP NOT NULL DEFAULT NOW( ), checked TIMESTAMP);
+----
+Created table log
+
+INSERT INTO log (id, checked) VALUES (1, NOW( )), (2, NOW( ));
+----
+Inserted 2 rows
+
+SELECT COUNT(DISTINCT checked), MIN(at) >= MIN(checked) FROM log;
+----
+?,?
+1,TRUE
+
+DESCRIBE log;
+----
+name,type,nullable,default,primary_key,unique,index,references,on_delete
+id,INTEGER,FALSE,,TRUE,FALSE,FALSE,,
+at,TIMESTAMP,FALSE,now(),FALSE,FALSE,FALSE,,
+checked,TIMESTAMP,TRUE,NULL,FALSE,FALSE,FALSE,,
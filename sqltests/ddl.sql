@@ -0,0 +1,98 @@
+-- Schemas are validated upfront : duplicate columns, and tables which already exist (or don't), are
+-- rejected with errors naming them. IF [NOT] EXISTS turns the latter into no-ops.
+
+CREATE TABLE t (a INT PRIMARY KEY, a TEXT);
+----
+error: Value 42701: Table t has multiple columns named a
+
+CREATE TABLE t (a INT PRIMARY KEY, b TEXT);
+----
+Created table t
+
+CREATE TABLE t (a INT PRIMARY KEY);
+----
+error: Value 42P07: Table t already exists
+
+-- The existing table is left as it is, even though the definitions differ.
+CREATE TABLE IF NOT EXISTS t (c INT PRIMARY KEY);
+----
+Created table t
+
+DESCRIBE t;
+----
+name,type,nullable,default,primary_key,unique,index,references,on_delete
+a,INTEGER,FALSE,,TRUE,FALSE,FALSE,,
+b,STRING,TRUE,NULL,FALSE,FALSE,FALSE,,
+
+CREATE TABLE IF NOT EXISTS u (id INT PRIMARY KEY);
+----
+Created table u
+
+SHOW TABLES;
+----
+table
+t
+u
+
+INSERT INTO t (a, a) VALUES (1, 2);
+----
+error: Value 42701: Column a is given multiple times
+
+INSERT INTO t (a, b) VALUES (1, 'x');
+----
+Inserted 1 row
+
+DROP TABLE missing;
+----
+error: Value 42P01: Table missing doesn't exist
+
+DROP TABLE IF EXISTS missing;
+----
+Dropped table missing
+
+DROP TABLE IF EXISTS u;
+----
+Dropped table u
+
+SHOW TABLES;
+----
+table
+t
+
+-- A table dropped within a transaction doesn't exist for the rest of it.
+BEGIN;
+----
+Began transaction 11
+
+DROP TABLE t;
+----
+Dropped table t
+
+DROP TABLE IF EXISTS t;
+----
+Dropped table t
+
+CREATE TABLE IF NOT EXISTS t (id INT PRIMARY KEY);
+----
+Created table t
+
+COMMIT;
+----
+Committed transaction 11
+
+DESCRIBE t;
+----
+name,type,nullable,default,primary_key,unique,index,references,on_delete
+id,INTEGER,FALSE,,TRUE,FALSE,FALSE,,
+
+CREATE TABLE IF EXISTS v (id INT PRIMARY KEY);
+----
+error: ParseAt 42601: line 1, column 17: Expected token NOT, got EXISTS
+
+DROP TABLE IF NOT EXISTS t;
+----
+error: ParseAt 42601: line 1, column 15: Expected token EXISTS, got NOT
+
+EXPLAIN DROP TABLE IF EXISTS t;
+----
+DropTable: t (if exists)
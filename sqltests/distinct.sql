@@ -0,0 +1,86 @@
+-- SELECT DISTINCT drops the duplicate rows, and DISTINCT aggregates skip the duplicate values.
+
+CREATE TABLE items (id INT PRIMARY KEY, category STRING, price INT);
+----
+Created table items
+
+INSERT INTO items VALUES (1, 'a', 10), (2, 'b', 10), (3, 'a', 20), (4, 'a', 10), (5, NULL, NULL), (6, NULL, 20);
+----
+Inserted 6 rows
+
+-- NULLs are duplicates of each other.
+SELECT DISTINCT category, price FROM items ORDER BY category, price DESC;
+----
+category,price
+,
+,20
+a,20
+a,10
+b,10
+
+SELECT DISTINCT * FROM items WHERE id < 3;
+----
+id,category,price
+1,a,10
+2,b,10
+
+-- Ordering by some of the selections only, or not at all.
+SELECT DISTINCT category, price FROM items ORDER BY price;
+----
+category,price
+,
+a,10
+b,10
+a,20
+,20
+
+SELECT DISTINCT price FROM items WHERE price IS NOT NULL;
+----
+price
+10
+20
+
+SELECT DISTINCT category FROM items WHERE category IS NOT NULL ORDER BY category DESC LIMIT 1 OFFSET 1;
+----
+category
+a
+
+SELECT DISTINCT category FROM items ORDER BY price;
+----
+error: Value: ORDER BY expression
+
+EXPLAIN SELECT DISTINCT category, price FROM items ORDER BY category, price;
+----
+Distinct: sorted
+└─ Order: #0 asc, #1 asc
+   └─ Projection: #1 as category, #2 as price
+      └─ Scan: items
+
+EXPLAIN SELECT DISTINCT price FROM items;
+----
+Distinct
+└─ Projection: #2 as price
+   └─ Scan: items
+
+SELECT COUNT(DISTINCT price), COUNT(price), SUM(DISTINCT price) FROM items;
+----
+?,?,?
+2,5,30
+
+SELECT category, COUNT(DISTINCT price) FROM items GROUP BY category ORDER BY category;
+----
+category,?
+,1
+a,2
+b,1
+
+EXPLAIN SELECT COUNT(DISTINCT price) FROM items;
+----
+Projection: #0
+└─ Aggregation: count(DISTINCT #0)
+   └─ Projection: #2
+      └─ Scan: items
+
+SELECT upper(DISTINCT category) FROM items;
+----
+error: Value: DISTINCT category can only be the argument of an aggregate function
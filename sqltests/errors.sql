@@ -0,0 +1,149 @@
+-- Every error carries a code (see ErrorCode), which tells the kind of the error apart.
+
+CREATE TABLE users (id INT PRIMARY KEY, email STRING UNIQUE NOT NULL);
+----
+Created table users
+
+CREATE TABLE posts (id INT PRIMARY KEY, author INT REFERENCES users);
+----
+Created table posts
+
+INSERT INTO users VALUES (1, 'a@x.com');
+----
+Inserted 1 row
+
+INSERT INTO posts VALUES (1, 1), (3, 1);
+----
+Inserted 2 rows
+
+SELEC 1;
+----
+error: ParseAt 42601: line 1, column 1: Unexpected token selec
+
+SELECT * FROM missing;
+----
+error: Value 42P01: Table missing doesn't exist
+
+SELECT missing FROM users;
+----
+error: Value 42703: Unknown column missing
+
+SELECT nope(1);
+----
+error: Value 42883: Unknown function nope
+
+SELECT id FROM users, posts;
+----
+error: Value 42702: Ambiguous column id
+
+CREATE TABLE users (id INT PRIMARY KEY);
+----
+error: Value 42P07: Table users already exists
+
+CREATE TABLE bad (id INT);
+----
+error: Value 42P16: Table bad has no primary key
+
+INSERT INTO users VALUES (1, 'b@x.com');
+----
+error: Value 23505: Primary key 1 already exists in table users
+
+INSERT INTO users VALUES (2, 'a@x.com');
+----
+error: Value 23505: Unique value a@x.com already exists for column email in table users (row 1)
+
+INSERT INTO users VALUES (2, NULL);
+----
+error: Value 23502: Column email can't be NULL
+
+INSERT INTO posts VALUES (2, 9);
+----
+error: Value 23503: Referenced primary key 9 doesn't exist in table users
+
+DELETE FROM users WHERE id = 1;
+----
+error: Value 23503: Primary key 1 of table users is referenced by row 1 of table posts
+
+SELECT 1 / 0;
+----
+error: Value 22012: Division by zero
+
+SELECT 9223372036854775807 + 1;
+----
+error: Value 22003: Integer overflow
+
+SELECT 1 + 'a';
+----
+error: Value 42804: Can't add 1 (INTEGER) and 'a' (STRING)
+
+SELECT id, COUNT(*) FROM users;
+----
+error: Value 42803: Column id must appear in GROUP BY or be used in an aggregate function
+
+SELECT (SELECT id FROM posts);
+----
+error: Value 21000: Scalar subquery returned more than one row
+
+SELECT id FROM users ORDER BY 3;
+----
+error: Value 42P10: ORDER BY position 3 is out of range, there are 1 selections
+
+SET work_memory = 0;
+----
+error: Value 22023: work_memory must be a positive integer (of bytes), got 0
+
+SET unknown = 1;
+----
+error: Value 42704: Unknown session variable unknown
+
+COMMIT;
+----
+error: Value 25P01: Not in a transaction
+
+BEGIN;
+----
+Began transaction 12
+
+BEGIN;
+----
+error: Value 25001: Already in a transaction
+
+ROLLBACK;
+----
+Rolled back transaction 12
+
+BEGIN READ ONLY;
+----
+Began read-only transaction at version 13
+
+INSERT INTO users VALUES (3, 'c@x.com');
+----
+error: ReadOnly 25006: Read-only transaction
+
+ROLLBACK;
+----
+Rolled back transaction 13
+
+-- Conflicting writes of concurrent transactions.
+-- connection: a
+BEGIN;
+----
+Began transaction 13
+
+UPDATE users SET email = 'a1@x.com' WHERE id = 1;
+----
+Updated 1 row
+
+-- connection: b
+BEGIN;
+----
+Began transaction 14
+
+UPDATE users SET email = 'a2@x.com' WHERE id = 1;
+----
+error: Serialization 40001: Serialization failure, retry the transaction
+
+-- connection: a
+COMMIT;
+----
+Committed transaction 13
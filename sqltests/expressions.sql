@@ -0,0 +1,76 @@
+-- Evaluation of expressions : arithmetic, comparisons, three-valued logic, and the operators and
+-- functions built on them.
+
+SELECT 1 + 2 * 3, (1 + 2) * 3, 7 / 2, 7 % 3, 2 ^ 10, -5;
+----
+?,?,?,?,?,?
+7,9,3,1,1024,-5
+
+SELECT 7.0 / 2, 1.5 + 1, 2 ^ 0.5;
+----
+?,?,?
+3.5,2.5,1.4142135623730951
+
+SELECT 1 / 0.0;
+----
+error: Value: Division by zero
+
+SELECT 1 / 0;
+----
+error: Value: Division by zero
+
+SELECT 9223372036854775807 + 1;
+----
+error: Value: Integer overflow
+
+SELECT 1 = 1, 1 != 1, 1 < 2, 2 <= 1, 'a' < 'b', 1 <> 2;
+----
+?,?,?,?,?,?
+TRUE,FALSE,TRUE,FALSE,TRUE,TRUE
+
+SELECT TRUE AND NULL, FALSE AND NULL, TRUE OR NULL, FALSE OR NULL, NOT NULL;
+----
+?,?,?,?,?
+,FALSE,TRUE,,
+
+SELECT NULL = NULL, NULL IS NULL, 1 IS NOT NULL;
+----
+?,?,?
+,TRUE,TRUE
+
+SELECT 'abc' LIKE 'a%', 'abc' LIKE '_b_', 'abc' LIKE 'b%', 'a%c' LIKE 'a\%c' ESCAPE '\';
+----
+?,?,?,?
+TRUE,TRUE,FALSE,TRUE
+
+SELECT 2 IN (SELECT 2), 4 IN (SELECT 2), 4 NOT IN (SELECT 2), 4 IN (SELECT NULL);
+----
+?,?,?,?
+TRUE,FALSE,TRUE,
+
+SELECT CASE WHEN 1 > 2 THEN 'a' WHEN 2 > 1 THEN 'b' ELSE 'c' END;
+----
+?
+b
+
+SELECT CASE 3 WHEN 1 THEN 'one' WHEN 3 THEN 'three' END, CASE 4 WHEN 1 THEN 'one' END;
+----
+?,?
+three,
+
+SELECT CASE WHEN 1 THEN 'a' END;
+----
+error: Value: CASE condition 1 (INTEGER) must be a boolean
+
+SELECT 1 + 'a';
+----
+error: Value: Can't add 1 (INTEGER) and 'a' (STRING)
+
+SELECT NOT 1;
+----
+error: Value: Can't negate 1 (INTEGER)
+
+SELECT INFINITY, -INFINITY, NAN = NAN;
+----
+?,?,?
+inf,-inf,FALSE
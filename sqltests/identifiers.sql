@@ -0,0 +1,111 @@
+-- Unquoted identifiers are case-insensitive (they're lowercased), while quoted ones are kept as they
+-- are. So, a quoted identifier can be a keyword, or differ from another one only by its case.
+
+CREATE TABLE "order" ("limit" INT PRIMARY KEY, "Name" TEXT, name TEXT, "first name" TEXT);
+----
+Created table order
+
+INSERT INTO "order" ("limit", "Name", name, "first name") VALUES (1, 'Upper', 'lower', 'Ada'), (2, 'Other', 'other', 'Bob');
+----
+Inserted 2 rows
+
+SELECT "limit", "Name", name, "first name" FROM "order" ORDER BY "limit";
+----
+limit,Name,name,first name
+1,Upper,lower,Ada
+2,Other,other,Bob
+
+-- Unquoted, NAME is name (and not "Name").
+SELECT NAME, Name FROM "ORDER" ORDER BY "limit";
+----
+error: Value 42P01: Table ORDER doesn't exist
+
+SELECT NAME, Name FROM "order" ORDER BY "limit";
+----
+name,name
+lower,lower
+other,other
+
+SELECT "NAME" FROM "order";
+----
+error: Value 42703: Unknown column NAME
+
+-- A keyword must be quoted to be used as an identifier.
+SELECT limit FROM "order";
+----
+error: ParseAt 42601: line 1, column 8: Expected expression operand, found LIMIT
+
+-- The aliases follow the same rules.
+SELECT "limit" AS "Select", name AS Label FROM "order" ORDER BY "Select" DESC;
+----
+Select,label
+2,other
+1,lower
+
+SELECT o."Name" FROM "order" AS "O" JOIN "order" AS o USING ("limit") WHERE "O"."limit" = 2;
+----
+Name
+Other
+
+UPDATE "order" SET "Name" = 'Changed', "first name" = NULL WHERE "limit" = 1;
+----
+Updated 1 row
+
+-- The functions are unquoted identifiers as well.
+SELECT UPPER("Name"), Lower(name) FROM "order" WHERE "limit" = 1;
+----
+?,?
+CHANGED,lower
+
+SELECT "UPPER"(name) FROM "order";
+----
+error: Value 42883: Unknown function UPPER
+
+-- The expression of the index is stored as SQL, which keeps the identifiers quoted.
+CREATE INDEX "Lower Name" ON "order" ((LOWER("Name")));
+----
+Created index Lower Name
+
+INSERT INTO "order" VALUES (3, 'MiXeD', 'x', NULL);
+----
+Inserted 1 row
+
+EXPLAIN SELECT "limit", name FROM "order" WHERE LOWER("Name") = 'mixed';
+----
+Projection: #0 as limit, #2 as name
+└─ IndexLookup: order column Lower Name ('mixed')
+
+SELECT "limit", name FROM "order" WHERE LOWER("Name") = 'mixed';
+----
+limit,name
+3,x
+
+DESCRIBE "order";
+----
+name,type,nullable,default,primary_key,unique,index,references,on_delete
+limit,INTEGER,FALSE,,TRUE,FALSE,FALSE,,
+Name,STRING,TRUE,NULL,FALSE,FALSE,FALSE,,
+name,STRING,TRUE,NULL,FALSE,FALSE,FALSE,,
+first name,STRING,TRUE,NULL,FALSE,FALSE,FALSE,,
+
+CREATE TABLE "Order" ("Key" INT PRIMARY KEY, "order" INT REFERENCES "order");
+----
+Created table Order
+
+INSERT INTO "Order" VALUES (1, 3);
+----
+Inserted 1 row
+
+SELECT * FROM "Order" JOIN "order" ON "Order"."order" = "order"."limit";
+----
+Key,order,limit,Name,name,first name
+1,3,3,MiXeD,x,
+
+DROP TABLE "Order";
+----
+Dropped table Order
+
+SHOW TABLES;
+----
+table
+order
@@ -0,0 +1,121 @@
+-- Expression indexes, looked up by the filters comparing the same expression with constants.
+
+CREATE TABLE users (id INT PRIMARY KEY, email STRING, age INT);
+----
+Created table users
+
+INSERT INTO users VALUES (1, 'Alice@X.com', 30), (2, 'bob@x.com', 40), (3, 'ALICE@x.COM', NULL);
+----
+Inserted 3 rows
+
+-- The existing rows are indexed.
+CREATE INDEX lower_email ON users ((LOWER(email)));
+----
+Created index lower_email
+
+CREATE INDEX age_plus_one ON users (users.age + 1);
+----
+Created index age_plus_one
+
+EXPLAIN SELECT * FROM users WHERE LOWER(email) = 'alice@x.com';
+----
+Projection: #0 as id, #1 as email, #2 as age
+└─ IndexLookup: users column lower_email ('alice@x.com')
+
+SELECT * FROM users WHERE lower(email) = 'alice@x.com';
+----
+id,email,age
+1,Alice@X.com,30
+3,ALICE@x.COM,
+
+SELECT id FROM users WHERE LOWER(email) = 'bob@x.com' OR LOWER(email) = 'nobody' AND id > 0;
+----
+id
+2
+
+EXPLAIN SELECT * FROM users WHERE age + 1 = 41 AND id > 1;
+----
+Projection: #0 as id, #1 as email, #2 as age
+└─ Filter: #0 > 1
+   └─ IndexLookup: users column age_plus_one (41)
+
+-- Filters not matching an index expression scan the table.
+EXPLAIN SELECT * FROM users WHERE UPPER(email) = 'BOB@X.COM';
+----
+Projection: #0 as id, #1 as email, #2 as age
+└─ Scan: users (upper(#1) = 'BOB@X.COM')
+
+EXPLAIN SELECT * FROM users WHERE 1 + age = 41;
+----
+Projection: #0 as id, #1 as email, #2 as age
+└─ Scan: users (1 + #2 = 41)
+
+-- The index follows the changes of the rows.
+UPDATE users SET email = 'carol@x.com' WHERE id = 1;
+----
+Updated 1 row
+
+SELECT id FROM users WHERE LOWER(email) = 'alice@x.com';
+----
+id
+3
+
+SELECT id FROM users WHERE LOWER(email) = 'carol@x.com';
+----
+id
+1
+
+DELETE FROM users WHERE id = 3;
+----
+Deleted 1 row
+
+SELECT id FROM users WHERE LOWER(email) = 'alice@x.com';
+----
+id
+
+INSERT INTO users VALUES (4, 'Dave@x.com', 40);
+----
+Inserted 1 row
+
+SELECT id FROM users WHERE age + 1 = 41;
+----
+id
+2
+4
+
+-- The expression must be deterministic, and reference the table's columns only.
+CREATE INDEX r ON users ((age + RANDOM( )));
+----
+error: Value: must be deterministic
+
+CREATE INDEX c ON users ((COUNT(age)));
+----
+error: Value: must be deterministic
+
+CREATE INDEX s ON users ((id IN (SELECT id FROM users)));
+----
+error: Value: must be deterministic
+
+CREATE INDEX m ON users ((LOWER(name)));
+----
+error: Value: Column name doesn't exist
+
+CREATE INDEX lower_email ON users (email);
+----
+error: Value: already has a column or an index named lower_email
+
+CREATE INDEX email ON users (email);
+----
+error: Value: already has a column or an index named email
+
+CREATE INDEX t ON users ((LOWER(age)));
+----
+error: Value: Function lower expects a string, got age (INTEGER)
+
+TRUNCATE users;
+----
+Deleted 3 rows
+
+SELECT id FROM users WHERE LOWER(email) = 'carol@x.com';
+----
+id
@@ -0,0 +1,139 @@
+-- USING (<columns>) joins on the equality of the named columns, present on both sides. NATURAL joins
+-- on all the columns the sides have in common. Either way, SELECT * emits each join column once,
+-- ahead of the other columns.
+
+CREATE TABLE users (id INT PRIMARY KEY, name TEXT, team INT);
+----
+Created table users
+
+CREATE TABLE orders (order_id INT PRIMARY KEY, id INT, total INT);
+----
+Created table orders
+
+CREATE TABLE teams (team INT PRIMARY KEY, title TEXT);
+----
+Created table teams
+
+INSERT INTO users VALUES (1, 'alice', 1), (2, 'bob', 2), (3, 'carol', NULL);
+----
+Inserted 3 rows
+
+INSERT INTO orders VALUES (10, 1, 250), (11, 1, 75), (12, 2, 120), (13, 4, 10);
+----
+Inserted 4 rows
+
+INSERT INTO teams VALUES (1, 'red'), (3, 'blue');
+----
+Inserted 2 rows
+
+SELECT * FROM users JOIN orders USING (id) ORDER BY order_id;
+----
+id,name,team,order_id,total
+1,alice,1,10,250
+1,alice,1,11,75
+2,bob,2,12,120
+
+-- The unqualified join column references the merged column, while the qualified ones still
+-- reference the columns of each side.
+SELECT id, users.id, orders.id, name FROM users JOIN orders USING (id) WHERE id = 2;
+----
+id,id,id,name
+2,2,2,bob
+
+-- The join columns resolve against the aliases of the tables.
+SELECT u.name, o.total FROM users u JOIN orders o USING (id) WHERE o.total > 100 ORDER BY o.total;
+----
+name,total
+bob,120
+alice,250
+
+-- The join column is kept from the side whose rows are all emitted.
+SELECT * FROM users LEFT JOIN orders USING (id) ORDER BY id, order_id;
+----
+id,name,team,order_id,total
+1,alice,1,10,250
+1,alice,1,11,75
+2,bob,2,12,120
+3,carol,,,
+
+SELECT * FROM users RIGHT JOIN orders USING (id) ORDER BY order_id;
+----
+id,name,team,order_id,total
+1,alice,1,10,250
+1,alice,1,11,75
+2,bob,2,12,120
+4,,,13,10
+
+SELECT * FROM users NATURAL JOIN teams;
+----
+team,id,name,title
+1,1,alice,red
+
+SELECT * FROM users NATURAL LEFT JOIN teams ORDER BY id;
+----
+team,id,name,title
+1,1,alice,red
+2,2,bob,
+,3,carol,
+
+-- Qualified wildcards still emit all the columns of their tables.
+SELECT orders.*, users.* FROM users JOIN orders USING (id) ORDER BY order_id;
+----
+order_id,id,total,id,name,team
+10,1,250,1,alice,1
+11,1,75,1,alice,1
+12,2,120,2,bob,2
+
+-- Joins nest : the join column of the inner join is a column of its side.
+SELECT * FROM users JOIN orders USING (id) JOIN teams USING (team) ORDER BY order_id;
+----
+team,id,name,order_id,total,title
+1,1,alice,10,250,red
+1,1,alice,11,75,red
+
+EXPLAIN SELECT * FROM users JOIN orders USING (id);
+----
+Projection: #0 as id, #1 as name, #2 as team, #4 as order_id, #6 as total
+└─ HashJoin: inner using (id) on left #0 = right #1
+   └─ Scan: users
+   └─ Scan: orders
+
+EXPLAIN SELECT * FROM users NATURAL LEFT JOIN teams;
+----
+Projection: #2 as team, #0 as id, #1 as name, #5 as title
+└─ HashJoin: outer using (team) on left #2 = right #0
+   └─ Scan: users
+   └─ Scan: teams
+
+SELECT * FROM users JOIN orders USING (name);
+----
+error: Value 42703: Column name of USING doesn't exist on the right side of the join (orders)
+
+SELECT * FROM users u RIGHT JOIN orders o USING (total);
+----
+error: Value 42703: Column total of USING doesn't exist on the left side of the join (users AS u)
+
+SELECT * FROM users JOIN orders USING (id, id);
+----
+error: Value 42701: Column id is listed multiple times in USING
+
+SELECT * FROM orders NATURAL RIGHT JOIN teams;
+----
+error: Value 42703: NATURAL JOIN of orders and teams has no columns in common
+
+SELECT * FROM users NATURAL CROSS JOIN teams;
+----
+error: ParseAt 42601: line 1, column 29: Expected token JOIN, got CROSS
+
+SELECT * FROM users u JOIN orders o USING (users.id);
+----
+error: ParseAt 42601: line 1, column 49: Unexpected token .
+
+-- The join columns must be comparable.
+CREATE TABLE labels (id INT PRIMARY KEY, team TEXT);
+----
+Created table labels
+
+SELECT * FROM users JOIN labels USING (team);
+----
+error: Value 42804: Can't compare users.team (INTEGER) and labels.team (STRING)
@@ -0,0 +1,119 @@
+-- Numeric semantics : checked integer arithmetic, truncating division, the sign of the remainder,
+-- integer and float exponentiation, and exact comparisons between integers and floats.
+
+SELECT 9223372036854775807 + 1;
+----
+error: Value 22003: Integer overflow in 9223372036854775807 + 1
+
+SELECT -9223372036854775807 - 2;
+----
+error: Value 22003: Integer overflow in -9223372036854775807 - 2
+
+SELECT 4611686018427387904 * 2;
+----
+error: Value 22003: Integer overflow in 4611686018427387904 * 2
+
+SELECT (-9223372036854775807 - 1) / -1;
+----
+error: Value 22003: Integer overflow in -9223372036854775808 / -1
+
+SELECT -(-9223372036854775807 - 1);
+----
+error: Value 22003: Integer overflow in -(-9223372036854775808)
+
+SELECT (-9223372036854775807 - 1) % -1, 9223372036854775807 + 1.0;
+----
+?,?
+0,9223372036854776000
+
+SELECT 9223372036854775808;
+----
+error: ParseAt 42601: line 1, column 8: Integer literal 9223372036854775808 is out of range
+
+SELECT 1 / 2, -7 / 2, 7 / -2, -7 % 2, 7 % -2, -7 % -2, -7.5 % 2;
+----
+?,?,?,?,?,?,?
+0,-3,-3,-1,1,-1,-1.5
+
+SELECT 7 % 0;
+----
+error: Value 22012: Division by zero
+
+SELECT 7.5 % 0;
+----
+error: Value 22012: Division by zero
+
+SELECT NULL / 0, 1 + NULL;
+----
+?,?
+,
+
+SELECT 2 ^ 62, (-2) ^ 63, 2 ^ -1, 2 ^ 0.5, 1 ^ 5000000000;
+----
+?,?,?,?,?
+4611686018427387904,-9223372036854775808,0.5,1.4142135623730951,1
+
+SELECT 2 ^ 63;
+----
+error: Value 22003: Integer overflow in 2 ^ 63
+
+SELECT 1.5 + 1, 1 / 2.0, 3 * 0.5;
+----
+?,?,?
+2.5,0.5,1.5
+
+SELECT 0!, 20!;
+----
+?,?
+1,2432902008176640000
+
+SELECT 21!;
+----
+error: Value 22003: Factorial of 21 is out of range (the largest one is of 20)
+
+SELECT (-1)!;
+----
+error: Value 22023: Can't take the factorial of negative number -1
+
+-- 9007199254740993 (2^53 + 1) converts to the float 9007199254740992.0, but isn't equal to it.
+SELECT 9007199254740993 = 9007199254740992.0, 9007199254740993 > 9007199254740992.0, 9007199254740992 = 9007199254740992.0;
+----
+?,?,?
+FALSE,TRUE,TRUE
+
+SELECT 9223372036854775807 < 9223372036854775807.0, 9223372036854775807 = 9223372036854775807.0, -9223372036854775807 - 1 = -9223372036854775808.0;
+----
+?,?,?
+TRUE,FALSE,TRUE
+
+SELECT 1 < 1.5, 2 > 1.5, -1 > -1.5, 1 = 1.0, 1 < INFINITY, 1 > -INFINITY, 1 = NAN, 1 < NAN;
+----
+?,?,?,?,?,?,?,?
+TRUE,TRUE,TRUE,TRUE,TRUE,TRUE,FALSE,FALSE
+
+SELECT 9007199254740993 IN (SELECT 9007199254740992.0), 9007199254740992 IN (SELECT 9007199254740992.0), 2.0 IN (SELECT 2);
+----
+?,?,?
+FALSE,TRUE,TRUE
+
+CREATE TABLE big (id INT PRIMARY KEY, value INT);
+----
+Created table big
+
+CREATE TABLE approximate (id INT PRIMARY KEY, value FLOAT);
+----
+Created table approximate
+
+INSERT INTO big VALUES (1, 9007199254740992), (2, 9007199254740993);
+----
+Inserted 2 rows
+
+INSERT INTO approximate VALUES (1, 9007199254740992.0);
+----
+Inserted 1 row
+
+-- The hash join matches the integers equal to the floats, exactly.
+SELECT big.id FROM big JOIN approximate ON big.value = approximate.value;
+----
+id
+1
@@ -0,0 +1,109 @@
+-- ORDER BY, GROUP BY and HAVING can refer to the selections by their aliases, and ORDER BY and GROUP
+-- BY by their positions as well.
+
+CREATE TABLE sales (id INT PRIMARY KEY, region STRING, amount INT);
+----
+Created table sales
+
+INSERT INTO sales VALUES (1, 'north', 10), (2, 'south', 40), (3, 'north', 25), (4, 'east', 5), (5, 'south', 1);
+----
+Inserted 5 rows
+
+-- By position.
+SELECT region, amount FROM sales ORDER BY 2 DESC;
+----
+region,amount
+south,40
+north,25
+north,10
+east,5
+south,1
+
+SELECT * FROM sales ORDER BY 2, 3 DESC;
+----
+id,region,amount
+4,east,5
+3,north,25
+1,north,10
+2,south,40
+5,south,1
+
+SELECT region, SUM(amount) FROM sales GROUP BY 1 ORDER BY 2;
+----
+region,?
+east,5
+north,35
+south,41
+
+SELECT region FROM sales ORDER BY 3;
+----
+error: Value: ORDER BY position 3 is out of range
+
+SELECT region FROM sales ORDER BY 0;
+----
+error: Value: ORDER BY position 0 is out of range
+
+SELECT region FROM sales ORDER BY 'region';
+----
+error: Value: ORDER BY expects an expression or the position of a selection, got 'region'
+
+SELECT region FROM sales GROUP BY 1.5;
+----
+error: Value: GROUP BY expects an expression or the position of a selection, got 1.5
+
+-- By alias.
+SELECT region, SUM(amount) AS total FROM sales GROUP BY region ORDER BY total DESC;
+----
+region,total
+south,41
+north,35
+east,5
+
+SELECT region AS r, COUNT(*) AS n FROM sales GROUP BY r HAVING n > 1 ORDER BY r;
+----
+r,n
+north,2
+south,2
+
+SELECT region, SUM(amount) AS total FROM sales GROUP BY region HAVING total >= 10 AND total <= 40;
+----
+region,total
+north,35
+
+-- Otherwise, by the columns of the FROM clause (even if they aren't selected).
+SELECT region FROM sales ORDER BY amount LIMIT 2;
+----
+region
+south
+east
+
+-- An alias shadows a column of the same name.
+SELECT id, -amount AS amount FROM sales ORDER BY amount LIMIT 2;
+----
+id,amount
+2,-40
+3,-25
+
+SELECT DISTINCT region AS r FROM sales ORDER BY r DESC;
+----
+r
+south
+north
+east
+
+SELECT DISTINCT region FROM sales ORDER BY 1;
+----
+region
+east
+north
+south
+
+-- An alias given to multiple selections is ambiguous, when it's referenced.
+SELECT id AS x, amount AS x FROM sales WHERE id = 1;
+----
+x,x
+1,10
+
+SELECT id AS x, amount AS x FROM sales ORDER BY x;
+----
+error: Value: ORDER BY reference x is ambiguous
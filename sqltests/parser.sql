@@ -0,0 +1,98 @@
+-- Parsing of statements : keywords are case-insensitive, identifiers are lowercased unless quoted,
+-- and errors report where they happened.
+
+CREATE TABLE people (id INT PRIMARY KEY, name STRING NOT NULL, age INT);
+----
+Created table people
+
+create table Pets (ID int primary key, Owner int references people);
+----
+Created table pets
+
+SELECT * FROM pets;
+----
+id,owner
+
+CREATE TABLE "Quoted" ("Id" INT PRIMARY KEY);
+----
+Created table Quoted
+
+SELECT "Id" FROM "Quoted";
+----
+Id
+
+SELECT id FROM "Quoted";
+----
+error: Value: Unknown column id
+
+INSERT INTO people VALUES (1, 'Alice', 30), (2, 'Bob', NULL);
+----
+Inserted 2 rows
+
+INSERT INTO people (id, name) VALUES (3, 'It''s me');
+----
+Inserted 1 row
+
+SELECT * FROM people
+  WHERE age IS NULL
+  ORDER BY id DESC;
+----
+id,name,age
+3,It's me,
+2,Bob,
+
+SELECT id, name AS who FROM people ORDER BY name LIMIT 2 OFFSET 1;
+----
+id,who
+2,Bob
+3,It's me
+
+SELECT FROM people;
+----
+error: ParseAt: line 1, column 8: Expected expression operand, found FROM
+
+SELECT * FROM people WHERE;
+----
+error: ParseAt: line 1, column 27: Expected expression operand, found ;
+
+SELECT 1 +;
+----
+error: ParseAt: line 1, column 11: Expected expression operand, found ;
+
+SELECT 'unterminated;
+----
+error: ParseAt: line 1, column 8: Unexpected end of string literal
+
+-- Expressions can be nested upto 64 levels deep (the selection itself being the first level).
+SELECT (((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((1))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))) AS deep;
+----
+deep
+1
+
+SELECT ((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((1))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))));
+----
+error: ParseAt: line 1, column 72: Expression is nested too deeply, the maximum depth is 64
+
+SELECT * FROM people WHERE id = 1 id = 2;
+----
+error: ParseAt: line 1, column 35: Unexpected token id
+
+CREATE TABLE broken (id INT);
+----
+error: Value: Table broken has no primary key
+
+INSERT INTO missing VALUES (1);
+----
+error: Value: Table missing doesn't exist
+
+SELECT unknown FROM people;
+----
+error: Value: Unknown column unknown
+
+DROP TABLE pets;
+----
+Dropped table pets
+
+DROP TABLE pets;
+----
+error: Value: Table pets doesn't exist
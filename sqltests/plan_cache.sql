@@ -0,0 +1,89 @@
+-- The plans of the executed statements are cached, by their normalized text and the schema version.
+
+CREATE TABLE t (id INT PRIMARY KEY, name STRING);
+----
+Created table t
+
+INSERT INTO t VALUES (1, 'a'), (2, 'b');
+----
+Inserted 2 rows
+
+SELECT * FROM t WHERE id = 2;
+----
+id,name
+2,b
+
+-- The same statement hits the cache, even if it's spaced differently.
+SELECT *   FROM t
+  WHERE id = 2 ;
+----
+id,name
+2,b
+
+STATUS;
+----
+name,value
+...
+plan_cache_hits,1
+plan_cache_misses,3
+plan_cache_entries,3
+
+-- Another session shares the cache.
+-- connection: other
+SELECT * FROM t WHERE id = 2;
+----
+id,name
+2,b
+
+-- connection: default
+
+-- A DDL statement changes the schema version, so the cached plans are stale. Reusing one against the
+-- new schema would read the dropped table's columns.
+DROP TABLE t;
+----
+Dropped table t
+
+CREATE TABLE t (id INT PRIMARY KEY, size INT, name STRING);
+----
+Created table t
+
+INSERT INTO t VALUES (2, 10, 'c');
+----
+Inserted 1 row
+
+SELECT * FROM t WHERE id = 2;
+----
+id,size,name
+2,10,c
+
+SELECT * FROM t WHERE id = 2;
+----
+id,size,name
+2,10,c
+
+STATUS;
+----
+name,value
+...
+plan_cache_hits,3
+plan_cache_misses,7
+plan_cache_entries,6
+
+-- Statements calling NOW( ) aren't cached, since it's bound to the time when they run.
+SELECT NOW( ) > TIMESTAMP '2024-01-01';
+----
+?
+TRUE
+
+SELECT NOW( ) > TIMESTAMP '2024-01-01';
+----
+?
+TRUE
+
+STATUS;
+----
+name,value
+...
+plan_cache_hits,3
+plan_cache_misses,7
+plan_cache_entries,6
@@ -0,0 +1,291 @@
+-- Secondary indexes with many rows sharing a value, and unique indexes with NULLs.
+
+CREATE TABLE tasks (id INT PRIMARY KEY, state STRING INDEX, owner STRING UNIQUE, priority INT INDEX);
+----
+Created table tasks
+
+INSERT INTO tasks VALUES
+  (1, 'open', 'ann', 1), (2, 'open', 'bob', 2), (3, 'open', NULL, 1), (4, 'open', 'dan', 3),
+  (5, 'open', 'eve', 1), (6, 'open', NULL, 2), (7, 'done', 'gus', 1), (8, 'done', NULL, 1),
+  (9, 'done', 'ivy', 3), (10, 'done', 'jon', 1), (11, 'blocked', 'kat', 2), (12, 'blocked', NULL, 1);
+----
+Inserted 12 rows
+
+-- A lookup finds every row having the value.
+EXPLAIN SELECT id FROM tasks WHERE state = 'open';
+----
+Projection: #0 as id
+└─ IndexLookup: tasks column state ('open')
+
+SELECT id FROM tasks WHERE state = 'open';
+----
+id
+1
+2
+3
+4
+5
+6
+
+SELECT id FROM tasks WHERE priority = 1 OR priority = 3;
+----
+id
+1
+3
+4
+5
+7
+8
+9
+10
+12
+
+-- A prefix match scans the entries of the matching values.
+EXPLAIN SELECT id FROM tasks WHERE state LIKE 'do%';
+----
+Projection: #0 as id
+└─ IndexRangeScan: tasks column state ['do', 'dp')
+
+SELECT id FROM tasks WHERE state LIKE 'do%' OR state LIKE 'b%';
+----
+id
+7
+8
+9
+10
+11
+12
+
+-- Updating a row moves its entry to the new value, leaving the other rows having the old one.
+UPDATE tasks SET state = 'done' WHERE id = 3;
+----
+Updated 1 row
+
+SELECT id FROM tasks WHERE state = 'open';
+----
+id
+1
+2
+4
+5
+6
+
+SELECT id FROM tasks WHERE state = 'done';
+----
+id
+3
+7
+8
+9
+10
+
+UPDATE tasks SET state = 'blocked', priority = priority + 1 WHERE state = 'done';
+----
+Updated 5 rows
+
+SELECT id, priority FROM tasks WHERE state = 'blocked';
+----
+id,priority
+3,2
+7,2
+8,2
+9,4
+10,2
+11,2
+12,1
+
+SELECT id FROM tasks WHERE state = 'done';
+----
+id
+
+SELECT id FROM tasks WHERE priority = 2;
+----
+id
+2
+3
+6
+7
+8
+10
+11
+
+-- Deleting a row removes only its own entry.
+DELETE FROM tasks WHERE id = 2;
+----
+Deleted 1 row
+
+SELECT id FROM tasks WHERE priority = 2;
+----
+id
+3
+6
+7
+8
+10
+11
+
+SELECT id FROM tasks WHERE state LIKE 'op%';
+----
+id
+1
+4
+5
+6
+
+-- Unique values are looked up like the other indexed values.
+SELECT id FROM tasks WHERE owner = 'dan';
+----
+id
+4
+
+SELECT id FROM tasks WHERE owner LIKE '%';
+----
+id
+1
+4
+5
+7
+9
+10
+11
+
+-- NULLs don't conflict with each other in a unique column.
+INSERT INTO tasks VALUES (13, 'open', NULL, 1), (14, 'open', NULL, 1);
+----
+Inserted 2 rows
+
+UPDATE tasks SET owner = NULL WHERE id = 1;
+----
+Updated 1 row
+
+SELECT id FROM tasks WHERE owner IS NULL;
+----
+id
+1
+3
+6
+8
+12
+13
+14
+
+INSERT INTO tasks VALUES (15, 'open', 'dan', 1);
+----
+error: Value 23505: Unique value dan already exists for column owner in table tasks (row 4)
+
+-- A value released by another row can be taken.
+INSERT INTO tasks VALUES (15, 'open', 'ann', 1);
+----
+Inserted 1 row
+
+UPDATE tasks SET owner = 'eve' WHERE id = 15;
+----
+error: Value 23505: Unique value eve already exists for column owner in table tasks (row 5)
+
+-- The rows of a single insert conflict with each other.
+INSERT INTO tasks VALUES (16, 'open', 'zed', 1), (17, 'open', 'zed', 1);
+----
+error: Value 23505: Unique value zed already exists for column owner in table tasks (row 16)
+
+SELECT id FROM tasks WHERE owner = 'zed';
+----
+id
+
+-- A transaction sees its own writes when checking uniqueness.
+BEGIN;
+----
+Began transaction 12
+
+UPDATE tasks SET owner = NULL WHERE id = 5;
+----
+Updated 1 row
+
+INSERT INTO tasks VALUES (16, 'open', 'eve', 1);
+----
+Inserted 1 row
+
+COMMIT;
+----
+Committed transaction 12
+
+SELECT id, owner FROM tasks WHERE owner = 'eve' OR id = 5;
+----
+id,owner
+5,
+16,eve
+
+-- The rows a transaction inserted conflict with its later writes (which roll it back).
+BEGIN;
+----
+Began transaction 13
+
+INSERT INTO tasks VALUES (17, 'open', 'fay', 1);
+----
+Inserted 1 row
+
+UPDATE tasks SET owner = 'fay' WHERE id = 16;
+----
+error: Value: Unique value fay already exists
+
+SELECT id FROM tasks WHERE owner = 'fay' OR id = 17;
+----
+id
+
+-- Concurrent transactions taking the same unique value conflict.
+-- connection: a
+BEGIN;
+----
+Began transaction 14
+
+INSERT INTO tasks VALUES (20, 'open', 'kim', 1);
+----
+Inserted 1 row
+
+-- connection: b
+BEGIN;
+----
+Began transaction 15
+
+INSERT INTO tasks VALUES (21, 'open', 'kim', 1);
+----
+error: Serialization
+
+-- connection: a
+COMMIT;
+----
+Committed transaction 14
+
+-- Concurrent transactions sharing a non-unique value don't conflict.
+-- connection: a
+BEGIN;
+----
+Began transaction 16
+
+INSERT INTO tasks VALUES (22, 'review', NULL, 5);
+----
+Inserted 1 row
+
+-- connection: b
+BEGIN;
+----
+Began transaction 17
+
+INSERT INTO tasks VALUES (23, 'review', NULL, 5);
+----
+Inserted 1 row
+
+COMMIT;
+----
+Committed transaction 17
+
+-- connection: a
+COMMIT;
+----
+Committed transaction 16
+
+SELECT id, owner FROM tasks WHERE state = 'review' OR owner = 'kim';
+----
+id,owner
+20,kim
+22,
+23,
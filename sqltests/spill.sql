@@ -0,0 +1,186 @@
+-- ORDER BY and GROUP BY spill their rows to disk, once they exceed the work memory. The results
+-- match the ones computed in memory.
+
+CREATE TABLE digits (d INT PRIMARY KEY);
+----
+Created table digits
+
+INSERT INTO digits VALUES (0), (1), (2), (3), (4), (5), (6), (7), (8), (9);
+----
+Inserted 10 rows
+
+CREATE TABLE t (id INT PRIMARY KEY, grp INT, score INT);
+----
+Created table t
+
+INSERT INTO t SELECT a.d * 100 + b.d * 10 + c.d, (a.d * 100 + b.d * 10 + c.d) % 37, (a.d * 100 + b.d * 10 + c.d) * 7919 % 1000 FROM digits a, digits b, digits c;
+----
+Inserted 1000 rows
+
+-- In memory.
+-- Sorting, with ties kept in their scan order.
+SELECT id, grp FROM t ORDER BY grp DESC LIMIT 12 OFFSET 494;
+----
+id,grp
+314,18
+351,18
+388,18
+425,18
+462,18
+499,18
+536,18
+573,18
+610,18
+647,18
+684,18
+721,18
+
+SELECT id, grp, score FROM t ORDER BY score, grp DESC LIMIT 10;
+----
+id,grp,score
+0,0,0
+679,13,1
+358,25,2
+37,0,3
+716,13,4
+395,25,5
+74,0,6
+753,13,7
+432,25,8
+111,0,9
+
+SELECT COUNT(*) FROM t WHERE id IN (SELECT id FROM t ORDER BY score LIMIT 300);
+----
+?
+300
+
+-- Grouping.
+SELECT grp, COUNT(*), SUM(score), MIN(id), MAX(id) FROM t GROUP BY grp HAVING grp < 5 OR grp > 33;
+----
+grp,?,?,?,?
+0,28,1134,0,999
+1,27,25866,1,963
+2,27,23679,2,964
+3,27,21492,3,965
+4,27,19305,4,966
+34,27,7695,34,996
+35,27,5508,35,997
+36,27,3321,36,998
+
+SELECT id % 500 AS k, COUNT(*), COUNT(DISTINCT grp), AVG(score) FROM t GROUP BY k HAVING k % 61 = 0;
+----
+k,?,?,?
+0,2,2,250
+61,2,2,309
+122,2,2,368
+183,2,2,427
+244,2,2,486
+305,2,2,545
+366,2,2,604
+427,2,2,663
+488,2,2,722
+
+SELECT COUNT(*), COUNT(DISTINCT score), SUM(score) FROM t;
+----
+?,?,?
+1000,1000,499500
+
+-- Grouping and then sorting.
+SELECT score % 100 AS k, COUNT(*) AS c FROM t GROUP BY k ORDER BY c DESC, k LIMIT 5;
+----
+k,c
+0,10
+1,10
+2,10
+3,10
+4,10
+
+-- Spilled.
+SET work_memory = 1024;
+----
+Set work_memory
+
+-- Sorting, with ties kept in their scan order.
+SELECT id, grp FROM t ORDER BY grp DESC LIMIT 12 OFFSET 494;
+----
+id,grp
+314,18
+351,18
+388,18
+425,18
+462,18
+499,18
+536,18
+573,18
+610,18
+647,18
+684,18
+721,18
+
+SELECT id, grp, score FROM t ORDER BY score, grp DESC LIMIT 10;
+----
+id,grp,score
+0,0,0
+679,13,1
+358,25,2
+37,0,3
+716,13,4
+395,25,5
+74,0,6
+753,13,7
+432,25,8
+111,0,9
+
+SELECT COUNT(*) FROM t WHERE id IN (SELECT id FROM t ORDER BY score LIMIT 300);
+----
+?
+300
+
+-- Grouping.
+SELECT grp, COUNT(*), SUM(score), MIN(id), MAX(id) FROM t GROUP BY grp HAVING grp < 5 OR grp > 33;
+----
+grp,?,?,?,?
+0,28,1134,0,999
+1,27,25866,1,963
+2,27,23679,2,964
+3,27,21492,3,965
+4,27,19305,4,966
+34,27,7695,34,996
+35,27,5508,35,997
+36,27,3321,36,998
+
+SELECT id % 500 AS k, COUNT(*), COUNT(DISTINCT grp), AVG(score) FROM t GROUP BY k HAVING k % 61 = 0;
+----
+k,?,?,?
+0,2,2,250
+61,2,2,309
+122,2,2,368
+183,2,2,427
+244,2,2,486
+305,2,2,545
+366,2,2,604
+427,2,2,663
+488,2,2,722
+
+SELECT COUNT(*), COUNT(DISTINCT score), SUM(score) FROM t;
+----
+?,?,?
+1000,1000,499500
+
+-- Grouping and then sorting.
+SELECT score % 100 AS k, COUNT(*) AS c FROM t GROUP BY k ORDER BY c DESC, k LIMIT 5;
+----
+k,c
+0,10
+1,10
+2,10
+3,10
+4,10
+
+SET work_memory = 0;
+----
+error: Value: work_memory must be a positive integer (of bytes), got 0
+
+SET work_memory = 'lots';
+----
+error: Value: work_memory must be a positive integer (of bytes), got lots
@@ -0,0 +1,62 @@
+-- The rows of a query are streamed to the client in batches (of 1024 rows), as they're computed.
+-- So the server holds a batch at a time, however many rows the query results in.
+
+CREATE TABLE digits (d INT PRIMARY KEY);
+----
+Created table digits
+
+INSERT INTO digits VALUES (0), (1), (2), (3), (4), (5), (6), (7), (8), (9);
+----
+Inserted 10 rows
+
+CREATE TABLE thirds (t INT PRIMARY KEY);
+----
+Created table thirds
+
+INSERT INTO thirds VALUES (0), (1), (2);
+----
+Inserted 3 rows
+
+-- 300000 rows, computed by a join (so they aren't read from the storage engine).
+SELECT t * 100000 + a.d * 10000 + b.d * 1000 + c.d * 100 + e.d * 10 + f.d AS n
+  FROM thirds CROSS JOIN digits a CROSS JOIN digits b CROSS JOIN digits c CROSS JOIN digits e CROSS JOIN digits f;
+----
+n
+0
+1
+2
+...
+299999
+
+-- The size (in bytes) of the largest batch bounds the memory held by the streaming. The transaction
+-- of the query has ended, once its rows have all been read.
+!status
+----
+...
+active_txns,0
+...
+streamed_queries,1
+streamed_batches,293
+streamed_rows,300000
+streamed_max_batch_size,20488
+
+-- An empty result has no batches.
+SELECT * FROM digits WHERE d > 9;
+----
+d
+
+-- A query failing midway fails as a whole, even though some of its rows have been sent. Its
+-- transaction is rolled back.
+SELECT 10 / (5 - d) FROM digits;
+----
+error: Value 22012: Division by zero
+
+!status
+----
+...
+active_txns,0
+...
+streamed_queries,3
+streamed_batches,294
+streamed_rows,300005
+streamed_max_batch_size,20488
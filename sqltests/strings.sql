@@ -0,0 +1,168 @@
+-- The concatenation operator and the string functions. Strings are handled as characters, not
+-- bytes.
+
+CREATE TABLE words (id INT PRIMARY KEY, word STRING, suffix STRING);
+----
+Created table words
+
+INSERT INTO words VALUES (1, 'héllo', 'wörld'), (2, '日本語', 'テキスト'), (3, '', ''), (4, NULL, 'x'), (5, '🦀 crab', NULL);
+----
+Inserted 5 rows
+
+-- || concatenates strings. NULL propagates through a chain of concatenations.
+SELECT id, word || ' ' || suffix AS joined FROM words;
+----
+id,joined
+1,héllo wörld
+2,日本語 テキスト
+3, 
+4,
+5,
+
+SELECT 'a' || 'b' || 'c', '' || '', 'x' || NULL || 'y', NULL || NULL;
+----
+?,?,?,?
+abc,"",,
+
+SELECT LENGTH(word || suffix) FROM words WHERE id = 2;
+----
+?
+7
+
+-- || binds like + : tighter than comparisons.
+SELECT 'ab' || 'c' = 'abc';
+----
+?
+TRUE
+
+SELECT 'a' || 'b' LIKE 'a%';
+----
+?
+TRUE
+
+-- Other values aren't converted to strings.
+SELECT 'a' || 1;
+----
+error: Value 42804: Can't concatenate 'a' (STRING) and 1 (INTEGER)
+
+SELECT id || 'a' FROM words;
+----
+error: Value 42804: Can't concatenate id (INTEGER) and 'a' (STRING)
+
+SELECT NULL || 1;
+----
+error: Value 42804: Can't concatenate NULL (NULL) and 1 (INTEGER)
+
+-- SUBSTR counts characters from 1. Out of range positions are clamped.
+SELECT SUBSTR('héllo', 2, 3), SUBSTR('日本語', 2), SUBSTR('🦀 crab', 1, 1);
+----
+?,?,?
+éll,本語,🦀
+
+SELECT SUBSTR('abc', 0, 2), SUBSTR('abc', -5, 10), SUBSTR('abc', 5), SUBSTR('abc', 2, 0), SUBSTR('', 1, 3);
+----
+?,?,?,?,?
+a,abc,"","",""
+
+SELECT SUBSTR('abc', 9223372036854775807, 9223372036854775807), SUBSTR('abc', -9223372036854775807, 9223372036854775807);
+----
+?,?
+"",""
+
+SELECT SUBSTR(NULL, 1, 2), SUBSTR('abc', NULL), SUBSTR('abc', 1, NULL);
+----
+?,?,?
+,,
+
+SELECT SUBSTR('abc', 1, -1);
+----
+error: Value 22011: Function substr expects a non-negative length, got -1
+
+SELECT SUBSTR('abc', 'a');
+----
+error: Value 42804: Function substr expects an integer position, got 'a' (STRING)
+
+SELECT SUBSTR('abc');
+----
+error: Value 42883: Function substr takes 2 to 3 arguments, got 1
+
+SELECT id, SUBSTR(word, 2, 2) FROM words;
+----
+id,?
+1,él
+2,本語
+3,""
+4,
+5, c
+
+-- TRIM, LTRIM and RTRIM remove spaces (or the given characters) from the ends.
+SELECT '[' || TRIM('  a b  ') || ']', '[' || LTRIM('  a b  ') || ']', '[' || RTRIM('  a b  ') || ']';
+----
+?,?,?
+[a b],[a b  ],[  a b]
+
+SELECT TRIM('xxhéllox', 'x'), LTRIM('¡¡héllo!!', '¡!'), RTRIM('日本語語語', '語'), TRIM('aaa', 'a'), TRIM('', 'a'), TRIM('abc', '');
+----
+?,?,?,?,?,?
+héllo,héllo!!,日本,"","",abc
+
+SELECT TRIM(NULL), TRIM('a', NULL), LTRIM(word) FROM words WHERE id = 4;
+----
+?,?,?
+,,
+
+SELECT TRIM(1);
+----
+error: Value 42804: Function trim expects a string, got 1 (INTEGER)
+
+-- REPLACE replaces every occurrence of a substring.
+SELECT REPLACE('héllo héllo', 'é', 'e'), REPLACE('日本語', '本', ''), REPLACE('aaa', 'a', 'aa'), REPLACE('abc', '', 'x');
+----
+?,?,?,?
+hello hello,日語,aaaaaa,abc
+
+SELECT REPLACE(NULL, 'a', 'b'), REPLACE('a', NULL, 'b'), REPLACE('a', 'a', NULL);
+----
+?,?,?
+,,
+
+-- Even when the substring is empty.
+SELECT REPLACE('abc', '', NULL) IS NULL, REPLACE(NULL, '', 'x') IS NULL;
+----
+?,?
+TRUE,TRUE
+
+SELECT REPLACE('a', 'a', 1);
+----
+error: Value 42804: Function replace expects a string, got 1 (INTEGER)
+
+SELECT REPLACE('abc', '', 1);
+----
+error: Value 42804: Function replace expects a string, got 1 (INTEGER)
+
+-- POSITION returns the position of a substring (counting characters), or 0.
+SELECT POSITION('lo' IN 'héllo'), POSITION('語' IN '日本語'), POSITION('z' IN 'abc'), POSITION('' IN 'abc'), POSITION('a', 'cba');
+----
+?,?,?,?,?
+4,3,0,1,3
+
+SELECT POSITION(NULL IN 'a'), POSITION('a' IN word) FROM words WHERE id = 4;
+----
+?,?
+,
+
+-- The functions compose, and can be used in filters.
+SELECT id FROM words WHERE SUBSTR(word || suffix, 1, 2) = 'hé' OR UPPER(TRIM(word)) = '日本語';
+----
+id
+1
+2
+
+EXPLAIN SELECT 'a' || 'b', SUBSTR(word, 1, 1) || suffix FROM words;
+----
+Projection: 'ab', substr(#1, 1, 1) || #2
+└─ Scan: words
+
+SELECT 'a' | 'b';
+----
+error: ParseAt 42601: line 1, column 12: Unexpected character |
@@ -0,0 +1,88 @@
+-- Isolation of concurrent transactions, each run in its own connection.
+
+CREATE TABLE accounts (id INT PRIMARY KEY, balance INT);
+----
+Created table accounts
+
+INSERT INTO accounts VALUES (1, 100), (2, 50);
+----
+Inserted 2 rows
+
+-- connection: a
+BEGIN;
+----
+Began transaction 3
+
+UPDATE accounts SET balance = balance - 10 WHERE id = 1;
+----
+Updated 1 row
+
+-- connection: b
+BEGIN;
+----
+Began transaction 4
+
+-- b doesn't see the uncommitted write of a.
+SELECT * FROM accounts;
+----
+id,balance
+1,100
+2,50
+
+-- Writing the same row conflicts, which rolls the transaction back.
+UPDATE accounts SET balance = balance + 10 WHERE id = 1;
+----
+error: Serialization
+
+ROLLBACK;
+----
+error: Value: Not in a transaction
+
+-- connection: a
+COMMIT;
+----
+Committed transaction 3
+
+-- connection: b
+SELECT * FROM accounts;
+----
+id,balance
+1,90
+2,50
+
+-- A statement planned against a schema changed concurrently fails, and the transaction must be
+-- retried.
+BEGIN;
+----
+Began transaction 5
+
+SELECT * FROM accounts WHERE id = 2;
+----
+id,balance
+2,50
+
+-- connection: a
+CREATE TABLE audit (id INT PRIMARY KEY);
+----
+Created table audit
+
+-- connection: b
+UPDATE accounts SET balance = 0 WHERE id = 2;
+----
+error: Serialization: Serialization failure, retry the transaction
+
+ROLLBACK;
+----
+Rolled back transaction 5
+
+BEGIN READ ONLY;
+----
+Began read-only transaction at version 7
+
+INSERT INTO accounts VALUES (3, 0);
+----
+error: ReadOnly: Read-only transaction
+
+COMMIT;
+----
+Committed transaction 7
@@ -0,0 +1,99 @@
+-- TRUNCATE deletes all the rows of a table (and their index entries) at once.
+
+CREATE TABLE users (id INT PRIMARY KEY, email STRING UNIQUE, team INT INDEX);
+----
+Created table users
+
+CREATE TABLE posts (id INT PRIMARY KEY, author INT REFERENCES users ON DELETE CASCADE);
+----
+Created table posts
+
+INSERT INTO users VALUES (1, 'a@x', 1), (2, 'b@x', 1), (3, 'c@x', 2);
+----
+Inserted 3 rows
+
+INSERT INTO posts VALUES (1, 1);
+----
+Inserted 1 row
+
+-- The rows are referenced, whatever the referential action is.
+TRUNCATE TABLE users;
+----
+error: Value: Primary key 1 of table users is referenced by row 1 of table posts
+
+DELETE FROM posts;
+----
+Deleted 1 row
+
+-- A concurrent transaction keeps seeing the rows.
+-- connection: reader
+BEGIN READ ONLY;
+----
+Began read-only transaction at version 7
+
+-- connection: default
+TRUNCATE users;
+----
+Deleted 3 rows
+
+SELECT COUNT(*) FROM users;
+----
+?
+0
+
+SELECT * FROM users WHERE team = 1;
+----
+id,email,team
+
+-- The unique index entries are gone too.
+INSERT INTO users VALUES (4, 'a@x', 1);
+----
+Inserted 1 row
+
+SELECT * FROM users WHERE email = 'a@x';
+----
+id,email,team
+4,a@x,1
+
+-- connection: reader
+SELECT COUNT(*) FROM users;
+----
+?
+3
+
+SELECT * FROM users WHERE team = 1;
+----
+id,email,team
+1,a@x,1
+2,b@x,1
+
+COMMIT;
+----
+Committed transaction 7
+
+-- connection: default
+TRUNCATE TABLE missing;
+----
+error: Value: Table missing doesn't exist
+
+-- In a transaction, the truncation is rolled back like any other write.
+BEGIN;
+----
+Began transaction 10
+
+TRUNCATE TABLE users;
+----
+Deleted 1 row
+
+ROLLBACK;
+----
+Rolled back transaction 10
+
+SELECT * FROM users;
+----
+id,email,team
+4,a@x,1
+
+TRUNCATE TABLE posts;
+----
+Deleted 0 rows
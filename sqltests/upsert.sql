@@ -0,0 +1,166 @@
+-- INSERT ... ON CONFLICT (<column>) skips the rows whose value in the column (the primary key or a
+-- unique column) already exists (DO NOTHING), or updates the existing rows instead (DO UPDATE).
+
+CREATE TABLE stock (sku INT PRIMARY KEY, code TEXT UNIQUE, name TEXT NOT NULL, quantity INT INDEX, updated INT DEFAULT 0);
+----
+Created table stock
+
+INSERT INTO stock (sku, code, name, quantity) VALUES (1, 'A1', 'bolt', 10), (2, 'A2', 'nut', 20), (3, 'A3', 'washer', 30);
+----
+Inserted 3 rows
+
+-- Half of the keys exist : those rows are skipped.
+INSERT INTO stock (sku, code, name, quantity) VALUES (1, 'B1', 'bolt', 99), (4, 'A4', 'screw', 40), (2, 'B2', 'nut', 99), (5, 'A5', 'rivet', 50), (3, 'B3', 'washer', 99), (6, 'A6', 'pin', 60)
+  ON CONFLICT (sku) DO NOTHING;
+----
+Inserted 3 rows
+
+SELECT * FROM stock ORDER BY sku;
+----
+sku,code,name,quantity,updated
+1,A1,bolt,10,0
+2,A2,nut,20,0
+3,A3,washer,30,0
+4,A4,screw,40,0
+5,A5,rivet,50,0
+6,A6,pin,60,0
+
+-- DO UPDATE references the rows which weren't inserted as excluded, and the existing rows by the
+-- (unqualified or qualified) names of their columns.
+INSERT INTO stock (sku, code, name, quantity) VALUES (1, 'A1', 'bolt', 5), (7, 'A7', 'clip', 70), (2, 'A2', 'nut', 15)
+  ON CONFLICT (sku) DO UPDATE SET quantity = quantity + excluded.quantity, updated = stock.updated + 1;
+----
+Inserted 3 rows
+
+SELECT * FROM stock ORDER BY sku;
+----
+sku,code,name,quantity,updated
+1,A1,bolt,15,1
+2,A2,nut,35,1
+3,A3,washer,30,0
+4,A4,screw,40,0
+5,A5,rivet,50,0
+6,A6,pin,60,0
+7,A7,clip,70,0
+
+-- The index on the updated column is kept up to date.
+SELECT sku FROM stock WHERE quantity = 15;
+----
+sku
+1
+
+SELECT sku FROM stock WHERE quantity = 20;
+----
+sku
+
+-- A unique column can be the conflict column too.
+INSERT INTO stock VALUES (100, 'A3', 'big washer', 1, 0) ON CONFLICT (code) DO UPDATE SET name = excluded.name;
+----
+Inserted 1 row
+
+SELECT * FROM stock WHERE code = 'A3';
+----
+sku,code,name,quantity,updated
+3,A3,big washer,30,0
+
+-- The primary key can be updated as well.
+INSERT INTO stock VALUES (6, 'A6', 'pin', 0, 0) ON CONFLICT (sku) DO UPDATE SET sku = 60;
+----
+Inserted 1 row
+
+SELECT sku, code FROM stock WHERE code = 'A6';
+----
+sku,code
+60,A6
+
+-- NULLs don't conflict.
+INSERT INTO stock VALUES (8, NULL, 'nail', 80, 0), (9, NULL, 'tack', 90, 0) ON CONFLICT (code) DO NOTHING;
+----
+Inserted 2 rows
+
+-- The updated row must satisfy the constraints : on a violation, none of the statement's rows are
+-- written.
+INSERT INTO stock VALUES (10, 'A10', 'hook', 1, 0), (1, 'A1', 'bolt', 1, 0) ON CONFLICT (sku) DO UPDATE SET name = NULL;
+----
+error: Value 23502: Column name can't be NULL
+
+INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (sku) DO UPDATE SET code = 'A2';
+----
+error: Value 23505: Unique value A2 already exists for column code in table stock (row 2)
+
+-- The rows conflicting on another unique column aren't handled.
+INSERT INTO stock VALUES (11, 'A1', 'bolt', 1, 0) ON CONFLICT (sku) DO NOTHING;
+----
+error: Value 23505: Unique value A1 already exists for column code in table stock (row 1)
+
+SELECT COUNT(*) FROM stock WHERE sku = 10 OR sku = 11;
+----
+?
+0
+
+-- A row can't be updated twice by the same statement, but it can be skipped.
+INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0), (1, 'A1', 'bolt', 2, 0) ON CONFLICT (sku) DO UPDATE SET quantity = excluded.quantity;
+----
+error: Value 21000: ON CONFLICT DO UPDATE can't affect the row with primary key 1 a second time : the inserted rows have duplicate values in column sku
+
+INSERT INTO stock VALUES (12, 'A12', 'cap', 1, 0), (12, 'A12', 'cap', 2, 0) ON CONFLICT (sku) DO NOTHING;
+----
+Inserted 1 row
+
+INSERT INTO stock VALUES (13, 'A13', 'cap', 1, 0), (13, 'A13', 'cap', 2, 0) ON CONFLICT (sku) DO UPDATE SET quantity = excluded.quantity;
+----
+error: Value 21000: ON CONFLICT DO UPDATE can't affect the row with primary key 13 a second time : the inserted rows have duplicate values in column sku
+
+-- Planning errors.
+INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (name) DO NOTHING;
+----
+error: Value 42P10: ON CONFLICT column name is neither the primary key nor a unique column of table stock
+
+INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (missing) DO NOTHING;
+----
+error: Value 42703: Column missing doesn't exist in table stock
+
+INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (sku) DO UPDATE SET quantity = excluded.missing;
+----
+error: Value 42703: Unknown column excluded.missing
+
+INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (sku) DO UPDATE SET quantity = excluded.name;
+----
+error: Value 42804: Column quantity is of type INTEGER, but excluded.name is of type STRING
+
+INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (sku) DO UPDATE SET _version = 1;
+----
+error: Value 428C9: Column _version is a system column, and can't be written
+
+INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (sku) DO SOMETHING;
+----
+error: ParseAt 42601: line 1, column 71: Expected NOTHING / UPDATE keyword, got something
+
+-- The upsert can read from a query.
+INSERT INTO stock (sku, code, name, quantity) SELECT sku, code, name, 0 FROM stock WHERE sku <= 2 ON CONFLICT (sku) DO UPDATE SET quantity = excluded.quantity;
+----
+Inserted 2 rows
+
+EXPLAIN INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (sku) DO UPDATE SET quantity = quantity + excluded.quantity;
+----
+Insert: stock, on conflict #0 do update (#3=#3 + #9)
+└─ Values: (1, 'A1', 'bolt', 1, 0)
+
+EXPLAIN INSERT INTO stock VALUES (1, 'A1', 'bolt', 1, 0) ON CONFLICT (code) DO NOTHING;
+----
+Insert: stock, on conflict #1 do nothing
+└─ Values: (1, 'A1', 'bolt', 1, 0)
+
+SELECT * FROM stock ORDER BY sku;
+----
+sku,code,name,quantity,updated
+1,A1,bolt,0,1
+2,A2,nut,0,1
+3,A3,big washer,30,0
+4,A4,screw,40,0
+5,A5,rivet,50,0
+7,A7,clip,70,0
+8,,nail,80,0
+9,,tack,90,0
+12,A12,cap,1,0
+60,A6,pin,60,0
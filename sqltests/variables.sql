@@ -0,0 +1,145 @@
+-- The session variables are set by SET, and read by SHOW. They're kept by the session, so they only
+-- apply to its connection.
+
+SHOW ALL;
+----
+name,value,default
+lenient_aliases,FALSE,FALSE
+statement_timeout,0,0
+work_memory,67108864,67108864
+
+SHOW statement_timeout;
+----
+statement_timeout
+0
+
+SET statement_timeout = 5000;
+----
+Set statement_timeout
+
+SET lenient_aliases = TRUE;
+----
+Set lenient_aliases
+
+SHOW statement_timeout;
+----
+statement_timeout
+5000
+
+SHOW ALL;
+----
+name,value,default
+lenient_aliases,TRUE,FALSE
+statement_timeout,5000,0
+work_memory,67108864,67108864
+
+-- connection: other
+-- Another connection still sees the defaults, and its changes don't apply to the first one.
+SHOW ALL;
+----
+name,value,default
+lenient_aliases,FALSE,FALSE
+statement_timeout,0,0
+work_memory,67108864,67108864
+
+SET statement_timeout = 10;
+----
+Set statement_timeout
+
+SHOW statement_timeout;
+----
+statement_timeout
+10
+
+-- connection: default
+SHOW statement_timeout;
+----
+statement_timeout
+5000
+
+-- Setting a variable to its default resets it.
+SET statement_timeout = 0;
+----
+Set statement_timeout
+
+SHOW statement_timeout;
+----
+statement_timeout
+0
+
+-- connection: other
+SHOW statement_timeout;
+----
+statement_timeout
+10
+
+-- A new connection starts with the defaults.
+-- connection: new
+SHOW ALL;
+----
+name,value,default
+lenient_aliases,FALSE,FALSE
+statement_timeout,0,0
+work_memory,67108864,67108864
+
+SET statement_timeout = 7;
+----
+Set statement_timeout
+
+-- The values are validated.
+SET statement_timeout = -1;
+----
+error: Value 22023: statement_timeout must be a non-negative integer (of milliseconds), got -1
+
+SET statement_timeout = 'long';
+----
+error: Value 22023: statement_timeout must be a non-negative integer (of milliseconds), got long
+
+SET statement_timeout = NULL;
+----
+error: Value 22023: statement_timeout must be a non-negative integer (of milliseconds), got NULL
+
+SET lenient_aliases = 'yes';
+----
+error: Value 22023: lenient_aliases must be a boolean, got yes
+
+SET work_memory = 1.5;
+----
+error: Value 22023: work_memory must be a positive integer (of bytes), got 1.5
+
+SET unknown = 1;
+----
+error: Value 42704: Unknown session variable unknown, expected one of : lenient_aliases, statement_timeout, work_memory
+
+SHOW unknown;
+----
+error: Value 42704: Unknown session variable unknown, expected one of : lenient_aliases, statement_timeout, work_memory
+
+-- A failed SET leaves the variable as it was.
+SHOW statement_timeout;
+----
+statement_timeout
+7
+
+-- The variables aren't transactional.
+BEGIN;
+----
+...
+
+SET lenient_aliases = TRUE;
+----
+Set lenient_aliases
+
+ROLLBACK;
+----
+...
+
+SHOW lenient_aliases;
+----
+lenient_aliases
+TRUE
+
+-- Quoted, a variable's name is still matched exactly.
+SHOW "Lenient_Aliases";
+----
+error: Value 42704: Unknown session variable Lenient_Aliases, expected one of : lenient_aliases, statement_timeout, work_memory
@@ -0,0 +1,106 @@
+-- SELECT * selects all the columns of the FROM clause, and table.* the columns of a single table (or
+-- alias), which can be mixed with other selections.
+
+CREATE TABLE users (id INT PRIMARY KEY, name STRING);
+----
+Created table users
+
+CREATE TABLE orders (id INT PRIMARY KEY, user_id INT, total INT);
+----
+Created table orders
+
+INSERT INTO users VALUES (1, 'alice'), (2, 'bob'), (3, 'carol');
+----
+Inserted 3 rows
+
+INSERT INTO orders VALUES (10, 1, 250), (11, 1, 75), (12, 2, 120);
+----
+Inserted 3 rows
+
+SELECT * FROM users u JOIN orders o ON u.id = o.user_id ORDER BY o.id;
+----
+id,name,id,user_id,total
+1,alice,10,1,250
+1,alice,11,1,75
+2,bob,12,2,120
+
+-- The columns are labeled with their names, even if they're ambiguous across the tables.
+SELECT u.*, o.total FROM users u JOIN orders o ON u.id = o.user_id ORDER BY o.id;
+----
+id,name,total
+1,alice,250
+1,alice,75
+2,bob,120
+
+SELECT o.*, u.* FROM users u JOIN orders o ON u.id = o.user_id ORDER BY o.id;
+----
+id,user_id,total,id,name
+10,1,250,1,alice
+11,1,75,1,alice
+12,2,120,2,bob
+
+SELECT o.id AS order_id, u.* FROM users u LEFT JOIN orders o ON u.id = o.user_id ORDER BY u.id, o.id;
+----
+order_id,id,name
+10,1,alice
+11,1,alice
+12,2,bob
+,3,carol
+
+SELECT o.* FROM users u RIGHT JOIN orders o ON u.id = o.user_id WHERE u.name = 'alice' ORDER BY 1;
+----
+id,user_id,total
+10,1,250
+11,1,75
+
+-- The table name qualifies the columns, when the table isn't aliased.
+SELECT users.*, 1 + 1 AS two FROM users ORDER BY 1 DESC LIMIT 1;
+----
+id,name,two
+3,carol,2
+
+-- Positions (and aliases) refer to the expanded selections.
+SELECT u.*, o.total AS total FROM users u JOIN orders o ON u.id = o.user_id ORDER BY 3 DESC, total;
+----
+id,name,total
+1,alice,250
+2,bob,120
+1,alice,75
+
+SELECT DISTINCT u.* FROM users u JOIN orders o ON u.id = o.user_id ORDER BY 2;
+----
+id,name
+1,alice
+2,bob
+
+SELECT u.*, COUNT(*) FROM users u JOIN orders o ON u.id = o.user_id GROUP BY u.id, u.name ORDER BY 1;
+----
+id,name,?
+1,alice,2
+2,bob,1
+
+-- The qualifier must match a table (or alias) of the FROM clause.
+SELECT x.* FROM users u;
+----
+error: Value: Unknown table x referenced by x.*
+
+SELECT users.* FROM users u;
+----
+error: Value: Unknown table users referenced by users.*
+
+SELECT u.* FROM users u WHERE u.* = 1;
+----
+error: Value: u.* can only be used as a selection
+
+SELECT u.* AS everything FROM users u;
+----
+error: ParseAt: line 1, column 12: Unexpected token AS
+
+-- A bare * can't be mixed with other selections.
+SELECT *, id FROM users;
+----
+error: ParseAt: line 1, column 9: * can't be selected along with other selections, select table.* instead
+
+SELECT id, * FROM users;
+----
+error: ParseAt: line 1, column 12: * can't be selected along with other selections, select table.* instead
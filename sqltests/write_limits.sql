@@ -0,0 +1,75 @@
+-- The uncommitted writes of a transaction are capped : a write exceeding the limits fails, and its
+-- transaction is rolled back. The writes below the limits (and the committed ones) are unaffected.
+-- write limits: 8 1024
+
+CREATE TABLE t (id INT PRIMARY KEY, name TEXT);
+----
+Created table t
+
+INSERT INTO t VALUES (1, 'a'), (2, 'b'), (3, 'c');
+----
+Inserted 3 rows
+
+-- A single statement exceeding the limits.
+INSERT INTO t VALUES (4, 'd'), (5, 'e'), (6, 'f'), (7, 'g'), (8, 'h'), (9, 'i'), (10, 'j'), (11, 'k'), (12, 'l');
+----
+error: Value 54000: Transaction 3 exceeded its write limits (8 keys, 1024 bytes) : split the writes into smaller transactions
+
+SELECT COUNT(*) FROM t;
+----
+?
+3
+
+-- The limits apply to the whole transaction, rather than to each statement.
+BEGIN;
+----
+Began transaction 4
+
+INSERT INTO t VALUES (4, 'd'), (5, 'e'), (6, 'f'), (7, 'g');
+----
+Inserted 4 rows
+
+INSERT INTO t VALUES (8, 'h'), (9, 'i'), (10, 'j'), (11, 'k'), (12, 'l');
+----
+error: Value 54000: Transaction 4 exceeded its write limits (8 keys, 1024 bytes) : split the writes into smaller transactions
+
+-- The transaction has been rolled back.
+COMMIT;
+----
+error: Value 25P01: Not in a transaction
+
+SELECT COUNT(*) FROM t;
+----
+?
+3
+
+-- Overwriting the same keys counts their sizes again, though not as other keys.
+BEGIN;
+----
+Began transaction 5
+
+UPDATE t SET name = 'aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa' WHERE id <= 3;
+----
+Updated 3 rows
+
+UPDATE t SET name = 'bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb' WHERE id <= 3;
+----
+error: Value 54000: Transaction 5 exceeded its write limits (8 keys, 1024 bytes) : split the writes into smaller transactions
+
+ROLLBACK;
+----
+error: Value 25P01: Not in a transaction
+
+-- Splitting the writes into smaller transactions gets them through.
+INSERT INTO t VALUES (4, 'd'), (5, 'e'), (6, 'f'), (7, 'g');
+----
+Inserted 4 rows
+
+INSERT INTO t VALUES (8, 'h'), (9, 'i'), (10, 'j'), (11, 'k'), (12, 'l');
+----
+Inserted 5 rows
+
+SELECT COUNT(*) FROM t;
+----
+?
+12
@@ -0,0 +1,563 @@
+#![allow(non_snake_case)]
+
+use std::{
+  io::{IsTerminal, Read, Write},
+  process::ExitCode,
+  sync::{Arc, Mutex},
+  thread
+};
+use distributed_sql_based_database_in_rust::{
+  client::{Canceller, Client, Execution, RowStream},
+  raft::types::NodeId,
+  result::{Error, ErrorCode, Result},
+  server::StatementResult,
+  sql::{csv, execution::Columns, json, types::{DataType, Row, Value}}
+};
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+const DEFAULT_ADDRESS: &str= "127.0.0.1:9705";
+
+// File (in the home directory) the history of the interactive shell is saved to.
+const HISTORY_FILE: &str= ".sql_history";
+
+const HELP: &str= "\
+Statements are terminated by ; and can span multiple lines.
+
+COPY <table> FROM STDIN; is followed by the rows to load (as CSV lines), upto a line with just \\.
+
+Ctrl-C cancels the running statement (stopping the rows of a query from being streamed), or else
+discards the input which hasn't been executed yet.
+
+Commands :
+  !status                   Shows the status of the server
+  !table <name>             Shows the schema of a table
+  !add-node <id> <address>  Adds a node (with the given Raft address) to the cluster
+  !remove-node <id>         Removes a node from the cluster
+  !transfer [<id>]          Transfers the leadership to a node (or to the most up-to-date one)
+  !backup <path>            Backs up the database to a file (on the server)
+  !restore <path> [--force] Restores a backup from a file (on the server), into an empty database
+                            unless --force drops the existing tables first
+  !help                     Shows this help";
+
+/*
+  A SQL shell, which executes statements on a server.
+
+  Usage : client [address] [-e <statements>] [--format table|csv|json]
+
+  The address can be of any node of the cluster : the statements needing the leader are forwarded
+  to it by the node, or the shell is redirected to it (see ClientRouting).
+
+  Statements are read interactively, unless they're given using -e or piped through stdin. In that
+  case, the shell exits with a non-zero code on the first error.
+
+  The rows of queries are shown as an ASCII table by default, or else as CSV (see csv::formatRows)
+  or JSON (see json::formatRows). The results of other statements are shown as messages.
+
+  The rows are streamed by the server. As CSV or JSON, they're shown as they arrive. As an ASCII
+  table, they're shown once they've all arrived, since the widths of the columns depend on all of
+  them.
+*/
+fn main( ) -> ExitCode {
+  match run( ) {
+    Ok(( )) => ExitCode::SUCCESS,
+    Err(error) => {
+      printError(&error);
+      ExitCode::FAILURE
+    }
+  }
+}
+
+// Prints the error along with its code (see ErrorCode), which tells the kind of the error apart.
+fn printError(error: &Error) {
+  eprintln!("{} (SQLSTATE {})", error, error.code( ));
+}
+
+fn run( ) -> Result<( )> {
+  let mut address= DEFAULT_ADDRESS.to_string( );
+  let mut statements= None;
+  let mut format= Format::Table;
+
+  let mut args= std::env::args( ).skip(1);
+  while let Some(arg)= args.next( ) {
+    match arg.as_str( ) {
+      "-e" => statements= Some(args.next( ).ok_or_else(| | Error::Value(ErrorCode::InvalidParameterValue, "-e expects the statements to execute".to_string( )))?),
+      "--format" => format= match args.next( ).as_deref( ) {
+        Some("table") => Format::Table,
+        Some("csv") => Format::Csv,
+        Some("json") => Format::Json,
+        _ => return Err(Error::Value(ErrorCode::InvalidParameterValue, "--format expects table, csv or json".to_string( )))
+      },
+      _ => address= arg
+    }
+  }
+
+  let mut shell= Shell { client: Client::connect(&address)?, format, running: Arc::default( ) };
+
+  match statements {
+    Some(statements) => shell.executeScript(&statements),
+    None if std::io::stdin( ).is_terminal( ) => shell.repl( ),
+    None => {
+      let mut script= String::new( );
+      std::io::stdin( ).read_to_string(&mut script)?;
+      shell.executeScript(&script)
+    }
+  }
+}
+
+struct Shell {
+  client: Client,
+  format: Format,
+
+  // Cancels the running statement (if any) on an interrupt, in the interactive shell (see
+  // handleInterrupts( )).
+  running: Arc<Mutex<Option<Canceller>>>
+}
+
+// The format the rows of queries are shown in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+  Table,
+  Csv,
+  Json
+}
+
+impl Shell {
+  // Executes the statements and commands of a script, stopping at the first error.
+  fn executeScript(&mut self, script: &str) -> Result<( )> {
+    let (inputs, remainder)= split(script);
+
+    for input in inputs.into_iter( ).chain(remainder.map(Input::Statement)) {
+      self.execute(input)?;}
+
+    Ok(( ))
+  }
+
+  /*
+    Runs the interactive shell, until the input ends (Ctrl-D). Errors are printed, and don't end the
+    shell.
+
+    Ctrl-C discards the (possibly multi-line) input which hasn't been executed yet. While a statement
+    is running, it cancels the statement instead.
+  */
+  fn repl(&mut self) -> Result<( )> {
+    let mut editor= DefaultEditor::new( ).map_err(|error| Error::Internal(error.to_string( )))?;
+    handleInterrupts(self.running.clone( ))?;
+
+    let historyPath= std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(HISTORY_FILE));
+    if let Some(historyPath)= &historyPath {
+      let _= editor.load_history(historyPath);}
+
+    println!("Connected to the server. Enter !help for help.");
+
+    // The input which isn't a complete statement yet.
+    let mut buffer= String::new( );
+
+    loop {
+      let prompt= if buffer.is_empty( ) { "sql> " } else { "  -> " };
+
+      match editor.readline(prompt) {
+        Ok(line) => {
+          buffer.push_str(&line);
+          buffer.push('\n');
+
+          let (inputs, remainder)= split(&buffer);
+          for input in inputs {
+            let _= editor.add_history_entry(input.text( ));
+
+            if let Err(error)= self.execute(input) {
+              printError(&error);}
+          }
+
+          buffer= remainder.map(str::to_string).unwrap_or_default( );
+        },
+
+        Err(ReadlineError::Interrupted) => buffer.clear( ),
+        Err(ReadlineError::Eof) => break,
+        Err(error) => return Err(Error::Internal(error.to_string( )))
+      }
+    }
+
+    if let Some(historyPath)= &historyPath {
+      let _= editor.save_history(historyPath);}
+
+    Ok(( ))
+  }
+
+  fn execute(&mut self, input: Input) -> Result<( )> {
+    match input {
+      Input::Statement(statement) => {
+        *self.running.lock( ).unwrap( )= Some(self.client.canceller( )?);
+        let result= self.executeStatement(statement);
+        *self.running.lock( ).unwrap( )= None;
+        result?
+      },
+      Input::Copy { statement, data } => println!("{}", render(self.client.copy(statement, data.lines( ))?, self.format)),
+      Input::Command(command) => self.executeCommand(command)?
+    }
+
+    Ok(( ))
+  }
+
+  fn executeStatement(&mut self, statement: &str) -> Result<( )> {
+    match self.client.stream(statement)? {
+      Execution::Result(result) => println!("{}", render(result, self.format)),
+      Execution::Rows(rows) => printRows(rows, self.format)?
+    }
+
+    Ok(( ))
+  }
+
+  fn executeCommand(&mut self, command: &str) -> Result<( )> {
+    let mut words= command.split_whitespace( );
+
+    let parseNodeId= |id: &str| id.parse( ).map(NodeId).map_err(|_| Error::Value(ErrorCode::InvalidParameterValue, format!("Invalid node id {}", id)));
+
+    match (words.next( ), words.next( ), words.next( ), words.next( )) {
+      (Some("!status"), None, ..) => {
+        for row in self.client.status( )?.rows( ) {
+          println!("{:<18} : {}", row[0].to_string( ), row[1]);}
+      },
+
+      (Some("!table"), Some(table), None, _) => println!("{}", self.client.getTable(table)?),
+
+      (Some("!add-node"), Some(id), Some(address), None) => {
+        self.client.addNode(parseNodeId(id)?, address)?;
+        println!("Added node {}", id);
+      },
+
+      (Some("!remove-node"), Some(id), None, _) => {
+        self.client.removeNode(parseNodeId(id)?)?;
+        println!("Removed node {}", id);
+      },
+
+      (Some("!transfer"), target, None, _) => {
+        let target= target.map(parseNodeId).transpose( )?;
+        self.client.transferLeadership(target)?;
+
+        match target {
+          Some(target) => println!("Transferred the leadership to node {}", target),
+          None => println!("Transferred the leadership")
+        }
+      },
+
+      (Some("!backup"), Some(path), None, _) => {
+        let summary= self.client.backup(path)?;
+        println!("Backed up {} tables ({} rows) as of version {} to {}", summary.tables, summary.rows, summary.version, path);
+      },
+
+      (Some("!restore"), Some(path), force @ (None | Some("--force")), None) => {
+        let summary= self.client.restore(path, force.is_some( ))?;
+        println!("Restored {} tables ({} rows) from {}", summary.tables, summary.rows, path);
+      },
+
+      (Some("!help"), None, ..) => println!("{}", HELP),
+
+      _ => return Err(Error::Value(ErrorCode::UndefinedObject, format!("Unknown command {}, enter !help for help", command)))
+    }
+
+    Ok(( ))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input<'a> {
+  Statement(&'a str),
+
+  // A COPY ... FROM STDIN statement, along with its rows (CSV lines).
+  Copy { statement: &'a str, data: &'a str },
+
+  // A shell command (like !help), which is a line starting with !.
+  Command(&'a str)
+}
+
+impl<'a> Input<'a> {
+  fn text(&self) -> &'a str {
+    match self {
+      Input::Statement(text) | Input::Copy { statement: text, .. } | Input::Command(text) => text
+    }
+  }
+}
+
+/*
+  Splits the input into statements (terminated by ;) and commands. Semicolons inside string
+  literals, quoted identifiers and comments don't terminate a statement.
+
+  A COPY ... FROM STDIN statement is followed by its rows, on the lines upto a line with just \.
+
+  Returns the remainder of the input as well, if it's an incomplete statement.
+*/
+fn split(input: &str) -> (Vec<Input<'_>>, Option<&str>) {
+  let mut inputs= Vec::new( );
+
+  // Start of the current statement, and whether it has anything except whitespaces and comments.
+  let mut start= 0;
+  let mut hasContent= false;
+
+  let mut characters= input.char_indices( ).peekable( );
+  while let Some((index, character))= characters.next( ) {
+    match character {
+      '!' if !hasContent => {
+        let end= input[index..].find('\n').map_or(input.len( ), |length| index + length);
+        inputs.push(Input::Command(input[index..end].trim( )));
+
+        while characters.next_if(|(index, _)| *index < end).is_some( ) { }
+        start= end;
+      },
+
+      ';' => {
+        let statement= input[start..=index].trim( );
+
+        if hasContent && isCopyFromStdin(statement) {
+          let dataStart= input[index..].find('\n').map_or(input.len( ), |offset| index + offset + 1);
+          let Some(dataLength)= findCopyEnd(&input[dataStart..]) else {
+            return (inputs, Some(&input[start..]))
+          };
+
+          let dataEnd= dataStart + dataLength;
+          inputs.push(Input::Copy { statement, data: &input[dataStart..dataEnd] });
+
+          // Skips the rows, along with the line ending them.
+          let end= input[dataEnd..].find('\n').map_or(input.len( ), |offset| dataEnd + offset + 1);
+          while characters.next_if(|(index, _)| *index < end).is_some( ) { }
+
+          start= end;
+          hasContent= false;
+          continue
+        }
+
+        if hasContent {
+          inputs.push(Input::Statement(statement));}
+
+        start= index + 1;
+        hasContent= false;
+      },
+
+      '\'' | '"' => {
+        hasContent= true;
+
+        // A doubled quote is an escaped one, so it just closes and reopens the quoted text.
+        if !characters.any(|(_, next)| next == character) {
+          return (inputs, Some(&input[start..]))}
+      },
+
+      '-' if characters.next_if(|(_, next)| *next == '-').is_some( ) => {
+        while characters.next_if(|(_, next)| *next != '\n').is_some( ) { }},
+
+      '/' if characters.next_if(|(_, next)| *next == '*').is_some( ) => {
+        let mut previous= ' ';
+        let terminated= characters.any(|(_, next)| {
+          let isEnd= previous == '*' && next == '/';
+          previous= next;
+          isEnd
+        });
+
+        // An unterminated block comment continues on the following lines.
+        if !terminated {
+          return (inputs, Some(&input[start..]))}
+      },
+
+      character if character.is_whitespace( ) => { },
+      _ => hasContent= true
+    }
+  }
+
+  (inputs, Some(&input[start..]).filter(|_| hasContent))
+}
+
+fn isCopyFromStdin(statement: &str) -> bool {
+  let words= statement.trim_end_matches(';')
+                      .split_whitespace( )
+                      .map(str::to_uppercase)
+                      .collect::<Vec<_>>( );
+
+  words.first( ).is_some_and(|word| word == "COPY") && words.ends_with(&["FROM".to_string( ), "STDIN".to_string( )])
+}
+
+// Returns the offset of the line ending the rows of a COPY (a line with just \.), if there's one.
+fn findCopyEnd(data: &str) -> Option<usize> {
+  let mut offset= 0;
+  for line in data.split_inclusive('\n') {
+    if line.trim( ) == "\\." {
+      return Some(offset)}
+
+    offset += line.len( );
+  }
+
+  None
+}
+
+/*
+  Cancels the running statement (see Shell::running) on an interrupt (Ctrl-C), instead of the shell
+  being terminated. The signal is awaited on a thread of its own.
+
+  NOTE : While the input is read, the terminal is in raw mode, so Ctrl-C doesn't raise an interrupt
+  (see Shell::repl( )).
+*/
+fn handleInterrupts(running: Arc<Mutex<Option<Canceller>>>) -> Result<( )> {
+  let runtime= tokio::runtime::Builder::new_current_thread( ).enable_all( ).build( )?;
+
+  thread::spawn(move || runtime.block_on(async {
+    while tokio::signal::ctrl_c( ).await.is_ok( ) {
+      let canceller= running.lock( ).unwrap( ).clone( );
+      if let Some(Err(error))= canceller.map(|canceller| canceller.cancel( )) {
+        printError(&error);}
+    }
+  }));
+
+  Ok(( ))
+}
+
+/*
+  Prints the rows of a query as CSV or JSON as they arrive, or else as an ASCII table once they've
+  all arrived.
+
+  If the query fails midway, the rows printed before the failure are left as they are (so a JSON
+  array is left unterminated).
+*/
+fn printRows(mut rows: RowStream, format: Format) -> Result<( )> {
+  let mut stdout= std::io::stdout( ).lock( );
+
+  match format {
+    Format::Csv => {
+      writeln!(stdout, "{}", csv::formatHeader(rows.columns( )))?;
+      while let Some(batch)= rows.nextBatch( )? {
+        for row in &batch {
+          writeln!(stdout, "{}", csv::formatRow(row))?;}
+        stdout.flush( )?;
+      }
+    },
+
+    Format::Json => {
+      let keys= json::formatKeys(rows.columns( ));
+      loop {
+        // The error is shown on a line of its own.
+        let batch= match rows.nextBatch( ) {
+          Ok(Some(batch)) => batch,
+          Ok(None) => break,
+          Err(error) if rows.count( ) > 0 => {
+            writeln!(stdout)?;
+            return Err(error)
+          },
+          Err(error) => return Err(error)
+        };
+
+        for (index, row) in batch.iter( ).enumerate( ) {
+          let separator= match rows.count( ) - batch.len( ) as u64 + index as u64 {
+            0 => "[\n",
+            _ => ",\n"
+          };
+          write!(stdout, "{}{}", separator, json::formatObject(&keys, row))?;
+        }
+        stdout.flush( )?;
+      }
+      writeln!(stdout, "{}", if rows.count( ) == 0 { "[]" } else { "\n]" })?;
+    },
+
+    Format::Table => writeln!(stdout, "{}", render(rows.collect( )?, format))?
+  }
+
+  Ok(( ))
+}
+
+// Renders the result of a statement, as it's shown to the user.
+fn render(result: StatementResult, format: Format) -> String {
+  match result {
+    StatementResult::Begin { version, stale: true, .. } =>
+      format!("Began stale read-only transaction at version {} (the leader is unreachable, so the data may be out of date)", version),
+    StatementResult::Begin { version, readonly: true, .. } => format!("Began read-only transaction at version {}", version),
+    StatementResult::Begin { version, readonly: false, .. } => format!("Began transaction {}", version),
+    StatementResult::Commit { version } => format!("Committed transaction {}", version),
+    StatementResult::Rollback { version } => format!("Rolled back transaction {}", version),
+
+    StatementResult::CreateTable { name } => format!("Created table {}", name),
+    StatementResult::CreateIndex { name } => format!("Created index {}", name),
+    StatementResult::DropTable { name } => format!("Dropped table {}", name),
+    StatementResult::AlterTable { name } => format!("Altered table {}", name),
+
+    StatementResult::Analyze { count } => match count {
+      1 => "Analyzed 1 table".to_string( ),
+      count => format!("Analyzed {} tables", count)
+    },
+
+    StatementResult::Insert { count } => format!("Inserted {}", pluralizeRows(count)),
+    StatementResult::Update { count } => format!("Updated {}", pluralizeRows(count)),
+    StatementResult::Delete { count } => format!("Deleted {}", pluralizeRows(count)),
+
+    StatementResult::CopyIn { table } => format!("Copying rows into table {}", table),
+    StatementResult::Copy { count } => format!("Copied {}", pluralizeRows(count)),
+
+    StatementResult::Set { variable } => format!("Set {}", variable),
+
+    StatementResult::Query { columns, rows } => match format {
+      Format::Table => renderTable(&columns, &rows),
+      Format::Csv => csv::formatRows(&columns, &rows),
+      Format::Json => json::formatRows(&columns, &rows)
+    },
+
+    StatementResult::Explain(plan) => plan
+  }
+}
+
+fn pluralizeRows(count: u64) -> String {
+  match count {
+    1 => "1 row".to_string( ),
+    count => format!("{} rows", count)
+  }
+}
+
+/*
+  Renders the rows of a query as an ASCII table, followed by the number of rows :
+
+    +----+-------+
+    | id | name  |
+    +----+-------+
+    |  1 | Alice |
+    |  2 | NULL  |
+    +----+-------+
+    (2 rows)
+
+  Numeric columns are right aligned. So are the numbers of columns of an unknown type (like those
+  which are always NULL). Unlabeled columns are shown as ?.
+*/
+fn renderTable(columns: &Columns, rows: &[Row]) -> String {
+  let header: Vec<String>= columns.iter( )
+                                  .map(|column| column.label.clone( ).unwrap_or_else(| | "?".to_string( )))
+                                  .collect( );
+  let cells: Vec<Vec<String>>= rows.iter( )
+                                   .map(|row| row.iter( ).map(Value::to_string).collect( ))
+                                   .collect( );
+
+  let mut widths: Vec<usize>= header.iter( ).map(|label| label.chars( ).count( )).collect( );
+  for row in &cells {
+    for (width, cell) in widths.iter_mut( ).zip(row) {
+      *width= (*width).max(cell.chars( ).count( ));}
+  }
+
+  let separator= widths.iter( )
+                       .map(|width| "-".repeat(width + 2))
+                       .fold("+".to_string( ), |line, dashes| line + &dashes + "+");
+
+  let renderLine= |cells: Vec<(String, bool)>| {
+    cells.into_iter( )
+         .zip(&widths)
+         .map(|((cell, alignRight), width)| match alignRight {
+           true => format!(" {:>width$} |", cell, width= width),
+           false => format!(" {:<width$} |", cell, width= width)
+         })
+         .fold("|".to_string( ), |line, cell| line + &cell)
+  };
+
+  let mut lines= vec![separator.clone( ), renderLine(header.into_iter( ).map(|label| (label, false)).collect( )), separator.clone( )];
+  for (row, cells) in rows.iter( ).zip(cells) {
+    let alignments= row.iter( ).zip(columns).map(|(value, column)| match column.valueType.dataType {
+      Some(dataType) => matches!(dataType, DataType::Integer | DataType::Float),
+      None => matches!(value, Value::Integer(_) | Value::Float(_))
+    });
+    lines.push(renderLine(cells.into_iter( ).zip(alignments).collect( )));
+  }
+  if !rows.is_empty( ) {
+    lines.push(separator);}
+
+  lines.push(format!("({})", pluralizeRows(rows.len( ) as u64)));
+  lines.join("\n")
+}
@@ -0,0 +1,49 @@
+#![allow(non_snake_case)]
+
+use distributed_sql_based_database_in_rust::{
+  result::{Error, ErrorCode, Result},
+  server::{Config, Server},
+  sql::execution::WorkMemory,
+  storage::{data_dir::DataDir, engine::{instrumented::InstrumentedEngine, memory::Memory}}
+};
+use tokio::{net::TcpListener, signal::unix::{signal, SignalKind}};
+
+/*
+  Runs a node of the database, configured by the file whose path is given as the only argument
+  (see server::Config for its format).
+
+  The data directory is opened (and locked) before anything else, and stays locked until the
+  process exits.
+
+  NOTE : Only the in-memory storage engine exists yet. So, the SQL data and the Raft log of a node
+  don't survive restarts (and the raft/, data/ and snapshots/ directories stay empty).
+
+  Both the storage engines are instrumented, so that their metrics show up in the node's status.
+*/
+#[tokio::main]
+async fn main( ) -> Result<( )> {
+  tracing_subscriber::fmt::init( );
+
+  let configPath= std::env::args( ).nth(1)
+                                  .ok_or_else(| | Error::Value(ErrorCode::InvalidParameterValue, "Usage : server <config file>".to_string( )))?;
+  let config= Config::parse(&std::fs::read_to_string(configPath)?)?;
+
+  let dataDir= DataDir::open(&config.dataDirectory)?;
+  let spillDirectory= config.spillDirectory.unwrap_or_else(| | dataDir.spillDirectory( ));
+
+  let server= Server::new(config.id, config.clusterConfig, config.join, config.replicationLimits, config.maxMessageSize, config.tickInterval,
+                         config.clientRouting, config.readYourWritesTimeout, WorkMemory::new(config.workMemory, spillDirectory)?, config.rowBatchSize, config.writeLimits, config.logCacheEntries, config.syncPolicy,
+                         Box::new(InstrumentedEngine::new(Memory::new( ))), InstrumentedEngine::new(Memory::new( )))?;
+  let listener= TcpListener::bind(&config.listenSQL).await?;
+
+  // Shuts down gracefully on SIGTERM or Ctrl-C (handing the leadership over first, if the node leads).
+  let mut sigterm= signal(SignalKind::terminate( ))?;
+  let shutdown= async move {
+    tokio::select! {
+      _ = sigterm.recv( ) => { },
+      _ = tokio::signal::ctrl_c( ) => { }
+    }
+  };
+
+  server.serve(listener, shutdown).await
+}
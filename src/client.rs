@@ -0,0 +1,375 @@
+use std::{mem, net::{SocketAddr, TcpStream, ToSocketAddrs}, thread, time::Duration};
+use crate::{
+  raft::types::NodeId,
+  result::{Error, ErrorCode, Result},
+  sql::{engine::{BackupSummary, PreparedStatementHandle, Status, COPY_BATCH_SIZE}, execution::Columns, schema::Table, types::{Row, Value}},
+  server::{readFrame, writeFrame, Request, Response, StatementResult}
+};
+
+/*
+  A client of the database server. It holds a connection (and so a session) to the server, so the
+  statements it executes are part of the same transaction until it's ended.
+
+  NOTE : The client is synchronous, and blocks until the server responds.
+
+  If the connection breaks (like when the server goes down), the request fails. The next request is
+  sent over a new connection to the address the client was created with (so, in a new session).
+
+  The errors the server responds with carry their codes (see Error::code( )), so the kinds of errors
+  can be told apart, and the retryable ones are told by Error::isRetryable( ).
+
+  The rows of a query are streamed by the server (see ResultFrames). They can be read as they arrive
+  (see stream( )), or materialized into a StatementResult (see execute( )).
+*/
+pub struct Client {
+  stream: TcpStream,
+
+  // The addresses the client was created with.
+  addresses: Vec<SocketAddr>,
+
+  // Whether the connection has broken, and must be reestablished before the next request.
+  broken: bool,
+
+  // Cancels the statements running in the connection, once it's been asked for (see canceller( )).
+  canceller: Option<Canceller>
+}
+
+// Number of times a statement is redirected to the leader (see Client::execute( )) before giving up,
+// and the time waited before retrying, when the leader it's redirected to is unreachable.
+const MAX_REDIRECTS: usize= 10;
+const REDIRECT_RETRY_INTERVAL: Duration= Duration::from_millis(100);
+
+impl Client {
+  pub fn connect(address: impl ToSocketAddrs) -> Result<Self> {
+    let addresses: Vec<SocketAddr>= address.to_socket_addrs( )?.collect( );
+    Ok(Self { stream: TcpStream::connect(&*addresses)?, addresses, broken: false, canceller: None })
+  }
+
+  // Executes a SQL statement. The rows of a query are materialized (see stream( ), to read them as
+  // they arrive instead).
+  pub fn execute(&mut self, statement: &str) -> Result<StatementResult> {
+    match self.stream(statement)? {
+      Execution::Result(result) => Ok(result),
+      Execution::Rows(rows) => rows.collect( )
+    }
+  }
+
+  /*
+    Executes a SQL statement. If it results in the rows of a query, they're returned as a stream.
+
+    If the server redirects the statement to the leader (see ClientRouting::Redirect), the client
+    reconnects to the leader and executes it there. Redirection only happens outside transactions,
+    so no transaction is lost by reconnecting.
+    NOTE : The statements prepared on the previous connection are lost though.
+
+    The server may be unaware of a leader change for a while, and redirect to a leader which has gone
+    down. The client then reconnects to its original address, and retries after a while.
+  */
+  pub fn stream(&mut self, statement: &str) -> Result<Execution<'_>> {
+    let mut redirects= 0;
+    loop {
+      // The cancel key is asked for beforehand, since the connection is busy while the rows are
+      // streamed (see RowStream::abort( )).
+      self.canceller( )?;
+
+      match self.request(Request::Execute(statement.to_string( ))) {
+        Err(Error::NotLeader { leaderAddress }) if redirects < MAX_REDIRECTS => {
+          redirects += 1;
+
+          let stream= match TcpStream::connect(&leaderAddress) {
+            Ok(stream) => stream,
+            Err(_) => {
+              thread::sleep(REDIRECT_RETRY_INTERVAL);
+              TcpStream::connect(&*self.addresses)?
+            }
+          };
+          self.reconnect(stream);
+        },
+
+        Ok(Response::Execute(result)) => return Ok(Execution::Result(result)),
+        Ok(Response::QueryHeader(columns)) => return Ok(Execution::Rows(RowStream { client: self, columns, count: 0, ended: false })),
+        Ok(response) => return Err(Error::Internal(format!("Unexpected response {:?} to a statement", response))),
+        Err(error) => return Err(error)
+      }
+    }
+  }
+
+  /*
+    Executes a COPY ... FROM STDIN statement, and then sends the given CSV lines as its rows (in
+    batches of COPY_BATCH_SIZE lines).
+
+    If a row fails to load, the COPY is aborted and none of the rows are loaded.
+  */
+  pub fn copy<'a>(&mut self, statement: &str, lines: impl IntoIterator<Item= &'a str>) -> Result<StatementResult> {
+    match self.execute(statement)? {
+      StatementResult::CopyIn { .. } => { },
+      result => return Err(Error::Value(ErrorCode::InvalidParameterValue, format!("Expected a COPY ... FROM STDIN statement, but got a statement resulting in {:?}", result)))
+    }
+
+    let mut lines= lines.into_iter( ).peekable( );
+    while lines.peek( ).is_some( ) {
+      let batch= lines.by_ref( ).take(COPY_BATCH_SIZE).map(str::to_string).collect( );
+
+      match self.request(Request::CopyData(batch))? {
+        Response::CopyData => { },
+        response => return Err(Error::Internal(format!("Unexpected response {:?} to COPY data", response)))
+      }
+    }
+
+    match self.request(Request::CopyEnd)? {
+      Response::Execute(result) => Ok(result),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to the end of a COPY", response)))
+    }
+  }
+
+  // Prepares a SQL statement, with parameter placeholders (? or $1, $2, ...). Returns the handle of
+  // the prepared statement, along with the number of parameters it takes.
+  pub fn prepare(&mut self, statement: &str) -> Result<(PreparedStatementHandle, u32)> {
+    match self.request(Request::Prepare(statement.to_string( )))? {
+      Response::Prepared { handle, parameterCount } => Ok((handle, parameterCount)),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a prepare request", response)))
+    }
+  }
+
+  // Executes a prepared statement, binding the given values to its parameters.
+  pub fn executePrepared(&mut self, handle: PreparedStatementHandle, parameters: Vec<Value>) -> Result<StatementResult> {
+    match self.request(Request::ExecutePrepared { handle, parameters })? {
+      Response::Execute(result) => Ok(result),
+      Response::QueryHeader(columns) => RowStream { client: self, columns, count: 0, ended: false }.collect( ),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a prepared statement", response)))
+    }
+  }
+
+  // Removes a prepared statement.
+  pub fn deallocate(&mut self, handle: PreparedStatementHandle) -> Result<( )> {
+    match self.request(Request::Deallocate(handle))? {
+      Response::Deallocated => Ok(( )),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a deallocate request", response)))
+    }
+  }
+
+  // Returns the schema of a table.
+  pub fn getTable(&mut self, table: &str) -> Result<Table> {
+    match self.request(Request::GetTable(table.to_string( )))? {
+      Response::Table(table) => Ok(table),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a table request", response)))
+    }
+  }
+
+  // Returns the status of the node the client is connected to.
+  pub fn status(&mut self) -> Result<Status> {
+    match self.request(Request::GetStatus)? {
+      Response::Status(status) => Ok(*status),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a status request", response)))
+    }
+  }
+
+  // Backs up the database to a file at the given path, on the server.
+  pub fn backup(&mut self, path: &str) -> Result<BackupSummary> {
+    match self.request(Request::Backup(path.to_string( )))? {
+      Response::BackedUp(summary) => Ok(summary),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a backup request", response)))
+    }
+  }
+
+  // Restores the backup in the file at the given path, on the server. Unless forced, the database
+  // must be empty.
+  pub fn restore(&mut self, path: &str, force: bool) -> Result<BackupSummary> {
+    match self.request(Request::Restore { path: path.to_string( ), force })? {
+      Response::Restored(summary) => Ok(summary),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a restore request", response)))
+    }
+  }
+
+  // Returns the cancel key of the connection, which cancels the statement running in it (see
+  // cancel( )).
+  pub fn cancelKey(&mut self) -> Result<u64> {
+    match self.request(Request::GetCancelKey)? {
+      Response::CancelKey(key) => Ok(key),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a cancel key request", response)))
+    }
+  }
+
+  // Returns the canceller of the connection, which cancels the statement running in it (from another
+  // thread, or from a RowStream).
+  pub fn canceller(&mut self) -> Result<Canceller> {
+    if let Some(canceller)= &self.canceller {
+      return Ok(canceller.clone( ))}
+
+    let cancelKey= self.cancelKey( )?;
+    let canceller= Canceller { address: self.stream.peer_addr( )?, cancelKey };
+    self.canceller= Some(canceller.clone( ));
+    Ok(canceller)
+  }
+
+  // Cancels the statement running in the connection with the given cancel key. Since that
+  // connection is busy running the statement, the cancellation is sent over a connection of its own.
+  pub fn cancel(address: impl ToSocketAddrs, cancelKey: u64) -> Result<( )> {
+    match Self::connect(address)?.request(Request::Cancel(cancelKey))? {
+      Response::Cancelled => Ok(( )),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a cancellation", response)))
+    }
+  }
+
+  // Adds a node (listening for Raft messages on the given address) to the cluster. Returns once the
+  // change is committed.
+  pub fn addNode(&mut self, id: NodeId, address: &str) -> Result<( )> {
+    self.changeMembership(Request::AddNode { id, address: address.to_string( ) })
+  }
+
+  // Removes a node from the cluster. Returns once the change is committed.
+  pub fn removeNode(&mut self, id: NodeId) -> Result<( )> {
+    self.changeMembership(Request::RemoveNode(id))
+  }
+
+  // Transfers the leadership of the cluster to the given node (or to the most up-to-date one, if none
+  // is given). Returns once the leader has stepped down for the target's term.
+  pub fn transferLeadership(&mut self, target: Option<NodeId>) -> Result<( )> {
+    match self.request(Request::TransferLeadership(target))? {
+      Response::LeadershipTransferred => Ok(( )),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a leadership transfer", response)))
+    }
+  }
+
+  fn changeMembership(&mut self, request: Request) -> Result<( )> {
+    match self.request(request)? {
+      Response::MembershipChanged => Ok(( )),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a membership change", response)))
+    }
+  }
+
+  fn request(&mut self, request: Request) -> Result<Response> {
+    if self.broken {
+      let stream= TcpStream::connect(&*self.addresses)?;
+      self.reconnect(stream);
+    }
+
+    if let Err(error)= writeFrame(&mut self.stream, &request) {
+      self.broken= true;
+      return Err(error)
+    }
+    self.readResponse( )
+  }
+
+  // Reads the next response (or response frame, see ResultFrames).
+  fn readResponse(&mut self) -> Result<Response> {
+    let response= readFrame::<Result<Response>>(&mut self.stream)
+                    .and_then(|response| response.ok_or_else(| | Error::Internal("Server closed the connection".to_string( ))));
+
+    if response.is_err( ) {
+      self.broken= true;}
+    response?
+  }
+
+  // Switches to a new connection (and so to a new session).
+  fn reconnect(&mut self, stream: TcpStream) {
+    self.stream= stream;
+    self.broken= false;
+    self.canceller= None;
+  }
+}
+
+// The result of a statement executed by Client::stream( ).
+pub enum Execution<'a> {
+  Result(StatementResult),
+  Rows(RowStream<'a>)
+}
+
+/*
+  The rows of a query, as they're streamed by the server (see ResultFrames). They're read in batches,
+  as they arrive. The query can still fail after some of its rows have been read.
+
+  The stream must be read to its end (or aborted) before the client sends another request. If it's
+  dropped before that, the connection is left in the middle of the stream : so it's reestablished by
+  the next request, and the server rolls back the query once it fails to send the rows.
+*/
+pub struct RowStream<'a> {
+  client: &'a mut Client,
+  columns: Columns,
+
+  // Number of rows read so far.
+  count: u64,
+  ended: bool
+}
+
+impl RowStream<'_> {
+  pub fn columns(&self) -> &Columns {
+    &self.columns
+  }
+
+  pub fn count(&self) -> u64 {
+    self.count
+  }
+
+  // Reads the next batch of rows. Returns None, once all the rows have been read.
+  pub fn nextBatch(&mut self) -> Result<Option<Vec<Row>>> {
+    if self.ended {
+      return Ok(None)}
+
+    let response= self.client.readResponse( );
+    self.ended= !matches!(response, Ok(Response::Rows(_)));
+
+    match response? {
+      Response::Rows(batch) => {
+        self.count += batch.len( ) as u64;
+        Ok(Some(batch))
+      },
+
+      Response::QueryEnd { count } if count == self.count => Ok(None),
+
+      response => {
+        self.client.broken= true;
+        Err(Error::Internal(format!("Unexpected response {:?} while reading {} rows of a query", response, self.count)))
+      }
+    }
+  }
+
+  // Reads the rest of the rows, materializing the result of the query.
+  pub fn collect(mut self) -> Result<StatementResult> {
+    let mut rows= Vec::new( );
+    while let Some(batch)= self.nextBatch( )? {
+      rows.extend(batch);}
+
+    Ok(StatementResult::Query { columns: mem::take(&mut self.columns), rows })
+  }
+
+  // Aborts the stream : the query is cancelled on the server, and the rows it sent before the
+  // cancellation took effect are skipped.
+  pub fn abort(mut self) -> Result<( )> {
+    if self.ended {
+      return Ok(( ))}
+
+    match &self.client.canceller {
+      Some(canceller) => canceller.cancel( )?,
+      None => return Err(Error::Internal("The connection has no canceller".to_string( )))
+    }
+
+    loop {
+      match self.nextBatch( ) {
+        Ok(Some(_)) => { },
+        Ok(None) | Err(Error::Cancelled(_)) => return Ok(( )),
+        Err(error) => return Err(error)
+      }
+    }
+  }
+}
+
+impl Drop for RowStream<'_> {
+  fn drop(&mut self) {
+    if !self.ended {
+      self.client.broken= true;}
+  }
+}
+
+// Cancels the statement running in a connection (see Client::canceller( )).
+#[derive(Debug, Clone)]
+pub struct Canceller {
+  address: SocketAddr,
+  cancelKey: u64
+}
+
+impl Canceller {
+  pub fn cancel(&self) -> Result<( )> {
+    Client::cancel(self.address, self.cancelKey)
+  }
+}
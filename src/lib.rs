@@ -0,0 +1,10 @@
+#![allow(non_snake_case, unused, clippy::upper_case_acronyms)]
+
+pub mod raft;
+pub mod storage;
+pub mod server;
+pub mod client;
+pub mod sql;
+pub mod result;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
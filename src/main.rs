@@ -1,10 +0,0 @@
-#![allow(non_snake_case, unused)]
-
-mod raft;
-mod storage;
-mod server;
-mod sql;
-mod result;
-
-#[tokio::main]
-async fn main( ) { }
@@ -0,0 +1,84 @@
+use std::collections::{BTreeMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::result::{Error, Result};
+use super::types::NodeId;
+
+/*
+  Describes the members of the cluster, by mapping the id of each node to its network address (like
+  127.0.0.1:9605). Every member is a voter.
+
+  The membership is changed one node at a time, through configuration entries in the log (see
+  Log::appendConfig( )). Since the majorities of the old and the new configuration always overlap,
+  they can't elect different leaders in the same term.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClusterConfig {
+  pub nodes: BTreeMap<NodeId, String>
+}
+
+impl ClusterConfig {
+  pub fn new(nodes: BTreeMap<NodeId, String>) -> Self {
+    Self { nodes }
+  }
+
+  // Returns the network address of the given node.
+  pub fn getAddress(&self, nodeId: NodeId) -> Result<&str> {
+    self.nodes.get(&nodeId)
+              .map(String::as_str)
+              .ok_or_else(| | Error::Internal(format!("Node {} isn't a member of the cluster", nodeId)))
+  }
+
+  // Returns the ids of all the nodes in the cluster, except the given one.
+  pub fn getPeersOf(&self, nodeId: NodeId) -> HashSet<NodeId> {
+    self.nodes.keys( )
+              .filter(|id| **id != nodeId)
+              .copied( )
+              .collect( )
+  }
+
+  // Returns whether the given node is a member of the cluster.
+  pub fn isMember(&self, nodeId: NodeId) -> bool {
+    self.nodes.contains_key(&nodeId)
+  }
+
+  // A quorum refers to the minimum number of group members in a decision-making process that must
+  // be present in order for the proceedings to be valid.
+  pub fn quorum(&self) -> usize {
+    (self.nodes.len( ) / 2) + 1
+  }
+
+  // Returns whether the given nodes form a quorum. Nodes which aren't members aren't counted.
+  pub fn isQuorum<'a>(&self, nodes: impl IntoIterator<Item= &'a NodeId>) -> bool {
+    nodes.into_iter( ).filter(|node| self.isMember(**node)).count( ) >= self.quorum( )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config(size: u64) -> ClusterConfig {
+    ClusterConfig::new((1..=size).map(|id| (NodeId(id), format!("node-{}", id))).collect( ))
+  }
+
+  #[test]
+  fn quorum( ) {
+    // Cluster size, its quorum, and the number of failed nodes it tolerates.
+    let cases= [(1, 1, 0), (2, 2, 0), (3, 2, 1), (4, 3, 1), (5, 3, 2)];
+
+    for (size, quorum, toleratedFailures) in cases {
+      let config= config(size);
+      let nodes= config.nodes.keys( ).copied( ).collect::<Vec<_>>( );
+
+      assert_eq!(config.quorum( ), quorum, "quorum of {} nodes", size);
+      assert_eq!(nodes.len( ) - config.quorum( ), toleratedFailures, "failures tolerated by {} nodes", size);
+
+      assert!(config.isQuorum(&nodes[..quorum]), "{} of {} nodes form a quorum", quorum, size);
+      assert!(!config.isQuorum(&nodes[..quorum - 1]), "{} of {} nodes don't form a quorum", quorum - 1, size);
+
+      // Nodes which aren't members don't count.
+      let outsiders= [NodeId(size + 1), NodeId(size + 2)];
+      assert!(!config.isQuorum(nodes[..quorum - 1].iter( ).chain(&outsiders)), "outsiders counted in a cluster of {} nodes", size);
+    }
+  }
+}
@@ -1,5 +1,10 @@
-use crate::{result::Result, storage::engine::StorageEngine};
-use super::types::{LogEntryIndex, NodeId, Term};
+use std::{collections::{BTreeMap, VecDeque}, ops::{Bound, RangeBounds}};
+use serde::{Deserialize, Serialize};
+use crate::{
+  result::{Error, Result},
+  storage::{engine::{StorageEngine, StorageEngineStatus, WriteBatch}, sync::{SyncPolicy, SyncStatus, Syncer}}
+};
+use super::{cluster_config::ClusterConfig, message::RequestId, types::{LogEntryIndex, NodeId, Term}};
 
 /*
   Represents the distributed immutable append-only commit log.
@@ -16,6 +21,38 @@ use super::types::{LogEntryIndex, NodeId, Term};
   in future AppendEntries RPCs (including heartbeats) so that the other servers eventually find out.
   Once a follower learns that a log entry is committed, it applies the entry to its local state
   machine (in log order).
+
+  NOTE : Uncommitted entries of a follower can get overwritten, if they conflict with the leader's
+  log (see splice( )).
+
+  To keep the log from growing forever, it's compacted : the state machine's state after applying
+  the entries upto an index is stored as a snapshot, and those entries are removed. A follower
+  missing the removed entries is sent the snapshot instead (see installSnapshot( )).
+
+  The membership of the cluster is stored in the log as well : every change is a configuration
+  entry carrying the new configuration, and the snapshot carries the configuration as of its last
+  entry. A node uses the latest configuration in its log, whether it's committed or not (so it
+  reverts to the previous one, if the entry gets removed). A new cluster starts with a
+  configuration entry at index 1 (see bootstrap( )), so that the log holds the whole history of the
+  membership.
+
+  The hot path of replication (the leader sending the recently appended entries, and the state
+  machine applying them) reads the tail of the log, while the consistency checks look up the terms
+  of the entries. So, the log keeps in memory :
+
+  (a) a cache of the most recently stored entries (see DEFAULT_CACHE_CAPACITY), which serves the
+      reads of the tail without going to the storage engine.
+
+  (b) the terms of all the entries after the snapshot, which serve the term lookups.
+
+  Both follow the stored entries : they're appended to along with the log, truncated along with it
+  (like when splice( ) removes conflicting entries), and pruned when it's compacted.
+
+  Every write to the log (like the batch of entries appended by splice( )) is a commit, which is
+  flushed according to the sync policy (see Syncer). Under the interval policy, the node flushes the
+  log on its ticks (see flushIfDue( )). A node acknowledges the entries it appended once they're
+  committed to the log : so under the interval and never policies, a crash can lose entries the
+  leader counted as replicated.
 */
 pub struct Log {
   storageEngine: Box<dyn StorageEngine>,
@@ -24,19 +61,816 @@ pub struct Log {
   lastStoredEntryIndex: LogEntryIndex,
 
   // Active term when the last entry was stored.
-  lastStoredEntryTerm: Term
+  lastStoredEntryTerm: Term,
+
+  // Index of the last committed entry.
+  commitIndex: LogEntryIndex,
+
+  // Term of the last committed entry.
+  commitTerm: Term,
+
+  // Index and term of the last entry covered by the snapshot (0, if there's no snapshot). The
+  // entries upto this index have been removed.
+  snapshotIndex: LogEntryIndex,
+  snapshotTerm: Term,
+
+  // The configurations in the log by the index of the entry carrying them, starting with the one
+  // as of the snapshot (at the snapshot index). The last one is in effect.
+  configs: BTreeMap<LogEntryIndex, ClusterConfig>,
+
+  // The most recently stored entries (upto cacheCapacity of them), which are contiguous and end at
+  // the last stored entry. A capacity of 0 disables the cache.
+  cache: VecDeque<LogEntry>,
+  cacheCapacity: usize,
+
+  // Terms of the entries after the snapshot index : the term of the entry at index i is at position
+  // i - snapshotIndex - 1.
+  terms: VecDeque<Term>,
+
+  syncer: Syncer,
+
+  metrics: LogMetrics
+}
+
+// Number of the most recently stored entries, which are cached in memory by default.
+pub const DEFAULT_CACHE_CAPACITY: usize= 1024;
+
+// Counters of the log, reported along with the node's status. They're kept in memory, so they start
+// from zero when the node restarts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LogMetrics {
+  pub entriesAppended: u64,
+
+  // Number of times an entry spliced from the leader conflicted with a stored one (see splice( )),
+  // and number of (uncommitted) entries removed due to that or to a truncation.
+  pub conflicts: u64,
+  pub entriesRemoved: u64,
+
+  // Number of reads of entries (see get( ) and scan( )) served from the cache, and number of the
+  // ones which had to go to the storage engine.
+  pub cacheHits: u64,
+  pub cacheMisses: u64
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+  pub index: LogEntryIndex,
+
+  // Term in which the entry was received by the leader.
+  pub term: Term,
+
+  // State machine command. None represents a noop command (like the one a newly elected leader
+  // appends to its log).
+  pub command: Option<Vec<u8>>,
+
+  // The new configuration of the cluster, if it's a configuration entry (which has no command).
+  pub config: Option<ClusterConfig>,
+
+  // Id of the client request, which the command was proposed for. The state-machine driver uses it
+  // to detect retried requests, which mustn't be applied again.
+  pub requestId: Option<RequestId>
+}
+
+// The state of the state machine after applying the entries upto (and including) an index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+  // Index and term of the last entry covered by the snapshot.
+  pub index: LogEntryIndex,
+  pub term: Term,
+
+  // The configuration of the cluster, as of the last entry covered by the snapshot.
+  pub config: ClusterConfig,
+
+  // The serialized state of the state machine, along with the recently applied client requests
+  // (taken by the state-machine driver).
+  pub state: Vec<u8>
+}
+
+// Keys, against which the log data is stored in the storage engine.
+enum Key {
+  Entry(LogEntryIndex),
+
+  // Metadata keys.
+  CommitIndex,
+  TermAndCastVote,
+  Snapshot
+}
+
+impl Key {
+  fn encode(&self) -> Vec<u8> {
+    match self {
+      // The index is encoded in big-endian format, so that the entries are ordered by their index
+      // in the storage engine.
+      Key::Entry(index) => [&[0x00], index.to_be_bytes( ).as_slice( )].concat( ),
+
+      Key::CommitIndex => vec![0x01],
+      Key::TermAndCastVote => vec![0x02],
+      Key::Snapshot => vec![0x03]
+    }
+  }
 }
 
 impl Log {
-  pub fn setCurrentTermAndCastVote(&mut self, term: Term, castVote: Option<NodeId>) -> Result<( )> {
-    unimplemented!( )
+  pub fn new(mut storageEngine: Box<dyn StorageEngine>, cacheCapacity: usize, syncPolicy: SyncPolicy) -> Result<Self> {
+    let (snapshotIndex, snapshotTerm, config)=
+      match storageEngine.get(&Key::Snapshot.encode( ))? {
+        Some(snapshot) => {
+          let snapshot: Snapshot= bincode::deserialize(&snapshot)?;
+          (snapshot.index, snapshot.term, snapshot.config)
+        },
+        None => (0, 0, ClusterConfig::default( ))
+      };
+
+    // Without any entries after the snapshot, the snapshot's last entry is the last stored one.
+    let (mut lastStoredEntryIndex, mut lastStoredEntryTerm)= (snapshotIndex, snapshotTerm);
+    let mut configs= BTreeMap::from([(snapshotIndex, config)]);
+
+    let (mut cache, mut terms)= (VecDeque::new( ), VecDeque::new( ));
+
+    for entry in Self::scanEntries(storageEngine.as_mut( ), ..) {
+      let entry= entry?;
+
+      (lastStoredEntryIndex, lastStoredEntryTerm)= (entry.index, entry.term);
+      if let Some(config)= &entry.config {
+        configs.insert(entry.index, config.clone( ));}
+
+      terms.push_back(entry.term);
+      Self::cacheEntry(&mut cache, cacheCapacity, entry);
+    }
+
+    let (commitIndex, commitTerm)=
+      match storageEngine.get(&Key::CommitIndex.encode( ))? {
+        Some(commitIndexAndTerm) => bincode::deserialize(&commitIndexAndTerm)?,
+        None => (0, 0)
+      };
+
+    Ok(Self {
+      storageEngine,
+      lastStoredEntryIndex, lastStoredEntryTerm,
+      commitIndex, commitTerm,
+      snapshotIndex, snapshotTerm,
+      configs,
+      cache, cacheCapacity,
+      terms,
+      syncer: Syncer::new(syncPolicy),
+      metrics: LogMetrics::default( )
+    })
+  }
+
+  /*
+    Checks the invariants between the indices of the log and the applied index of the state
+    machine, on startup : the snapshot index <= the applied index (once the state machine is caught
+    up) <= the commit index <= the last stored index.
+
+    A violation means that the stored state is corrupt (like when the Raft log and the SQL data
+    don't belong together, or the log was truncated by hand). The node mustn't serve it, so it fails
+    to start instead.
+
+    NOTE : The state machine can be behind the snapshot (it's restored from the snapshot then), but
+    never beyond the commit index, since only the committed entries are applied (after the commit
+    index is stored).
+  */
+  pub fn checkIntegrity(&self, appliedIndex: LogEntryIndex) -> Result<( )> {
+    const REMEDY: &str= "Restore the node's data directory from a backup, or wipe it and add the node to the cluster again";
+
+    if self.commitIndex > self.lastStoredEntryIndex {
+      return Err(Error::Corruption(format!(
+        "The Raft log is committed upto index {}, but its last entry is at index {} (the entries after it were lost). {}",
+        self.commitIndex, self.lastStoredEntryIndex, REMEDY
+      )))}
+
+    if self.snapshotIndex > self.commitIndex {
+      return Err(Error::Corruption(format!(
+        "The snapshot of the Raft log covers the entries upto index {}, beyond the commit index {}. {}",
+        self.snapshotIndex, self.commitIndex, REMEDY
+      )))}
+
+    if appliedIndex > self.commitIndex {
+      return Err(Error::Corruption(format!(
+        "The state machine has applied the entries upto index {}, beyond the commit index {} of the Raft log (so the SQL data and the Raft log don't belong together). {}",
+        appliedIndex, self.commitIndex, REMEDY
+      )))}
+
+    Ok(( ))
+  }
+
+  /*
+    Starts the log of a new cluster with the given configuration : it's stored as a configuration
+    entry at index 1 (in term 0), which is the same on every node bootstrapped with that
+    configuration. Does nothing, if the log isn't empty (like after a restart).
+
+    NOTE : A node joining an existing cluster isn't bootstrapped. It starts with an empty
+    configuration, and receives the entries (or the snapshot) carrying the membership from the
+    leader.
+  */
+  pub fn bootstrap(&mut self, config: ClusterConfig) -> Result<( )> {
+    if self.lastStoredEntryIndex > 0 {
+      return Ok(( ))}
+
+    let entry= LogEntry { index: 1, term: 0, command: None, config: Some(config), requestId: None };
+    self.appendEntry(entry)?;
+
+    Ok(( ))
+  }
+
+  // Returns the configuration in effect (the latest one in the log, even if it's uncommitted) along
+  // with the index of the entry carrying it.
+  pub fn getConfig(&self) -> (LogEntryIndex, &ClusterConfig) {
+    let (index, config)= self.configs.last_key_value( )
+                                     .expect("The log always has a configuration");
+    (*index, config)
+  }
+
+  /*
+    Durably stores the current term and the vote cast by this node in that term (flushing them,
+    whatever the sync policy). Returns the previously stored ones.
+
+    The term can't move back, and the vote cast in a term can't be changed - a node voting for two
+    candidates in the same term (like across a restart) could get both of them elected.
+
+    NOTE : The callers store the term and vote before acting on them (like before responding to a
+    vote request), so that they're never lost in a crash.
+  */
+  pub fn setCurrentTermAndCastVote(&mut self, term: Term, castVote: Option<NodeId>) -> Result<(Term, Option<NodeId>)> {
+    let (previousTerm, previousCastVote)= self.getCurrentTermAndCastVote( )?;
+
+    if term < previousTerm {
+      return Err(Error::Internal(format!("Can't move the current term back from {} to {}", previousTerm, term)))}
+
+    if let Some(previousCastVote)= previousCastVote.filter(|previousCastVote| (term == previousTerm) && (castVote != Some(*previousCastVote))) {
+      return Err(Error::Internal(format!("Already voted for {} in term {}", previousCastVote, term)))}
+
+    self.storageEngine.set(&Key::TermAndCastVote.encode( ), bincode::serialize(&(term, castVote))?)?;
+    self.flush( )?;
+
+    Ok((previousTerm, previousCastVote))
   }
 
+  // Returns the stored current term and the vote cast in that term. Returns term 0 with no vote,
+  // if nothing was stored yet.
   pub fn getCurrentTermAndCastVote(&mut self) -> Result<(Term, Option<NodeId>)> {
-    unimplemented!( )
+    match self.storageEngine.get(&Key::TermAndCastVote.encode( ))? {
+      Some(termAndCastVote) => Ok(bincode::deserialize(&termAndCastVote)?),
+      None => Ok((0, None))
+    }
   }
 
   pub fn getLastStoredEntryIndexAndTerm(&self) -> (LogEntryIndex, Term) {
     (self.lastStoredEntryIndex, self.lastStoredEntryTerm)
   }
-}
\ No newline at end of file
+
+  pub fn getCommitIndexAndTerm(&self) -> (LogEntryIndex, Term) {
+    (self.commitIndex, self.commitTerm)
+  }
+
+  // Returns the status of the storage engine storing the log.
+  pub fn status(&self) -> Result<StorageEngineStatus> {
+    self.storageEngine.status( )
+  }
+
+  pub fn syncStatus(&self) -> Result<SyncStatus> {
+    self.syncer.status( )
+  }
+
+  // Flushes the writes to the log, whatever the sync policy.
+  pub fn flush(&mut self) -> Result<( )> {
+    let Self { syncer, storageEngine, .. }= self;
+    syncer.flush(| | storageEngine.flush( ))
+  }
+
+  // Flushes the unflushed writes to the log under the interval sync policy, once the interval has
+  // passed since the last flush. Called on every tick of the node.
+  pub fn flushIfDue(&mut self) -> Result<( )> {
+    let Self { syncer, storageEngine, .. }= self;
+    syncer.flushIfDue(| | storageEngine.flush( ))
+  }
+
+  pub fn metrics(&self) -> &LogMetrics {
+    &self.metrics
+  }
+
+  // Returns the index and term of the last entry covered by the snapshot.
+  pub fn getSnapshotIndexAndTerm(&self) -> (LogEntryIndex, Term) {
+    (self.snapshotIndex, self.snapshotTerm)
+  }
+
+  // Returns the snapshot (if any).
+  pub fn getSnapshot(&mut self) -> Result<Option<Snapshot>> {
+    self.storageEngine.get(&Key::Snapshot.encode( ))?
+      .map(|snapshot| bincode::deserialize(&snapshot))
+      .transpose( )
+      .map_err(Error::from)
+  }
+
+  /*
+    Compacts the log : stores the given state of the state machine (after applying the entries upto
+    and including the given index) as the snapshot, and removes those entries.
+
+    Only committed entries can be compacted. Compacting upto an index covered by the snapshot
+    already is a no-op.
+  */
+  pub fn snapshot(&mut self, upToIndex: LogEntryIndex, stateMachineState: Vec<u8>) -> Result<( )> {
+    if upToIndex <= self.snapshotIndex {
+      return Ok(( ))}
+
+    if upToIndex > self.commitIndex {
+      return Err(Error::Internal(format!(
+        "Can't compact the log upto index {}, beyond the commit index {}", upToIndex, self.commitIndex
+      )))}
+
+    let term= self.getTerm(upToIndex)?
+                  .ok_or_else(| | Error::Internal(format!("Entry at index {} not found", upToIndex)))?;
+
+    // The configurations before the snapshot index aren't needed anymore.
+    let config= self.configs.range(..=upToIndex).next_back( )
+                            .map(|(_, config)| config.clone( ))
+                            .unwrap_or_default( );
+    self.configs= self.configs.split_off(&(upToIndex + 1));
+    self.configs.insert(upToIndex, config.clone( ));
+
+    let snapshot= Snapshot { index: upToIndex, term, config, state: stateMachineState };
+
+    let mut batch= vec![(Key::Snapshot.encode( ), Some(bincode::serialize(&snapshot)?))];
+    for removedEntryIndex in (self.snapshotIndex + 1)..=upToIndex {
+      batch.push((Key::Entry(removedEntryIndex).encode( ), None));}
+    self.writeBatch(batch)?;
+
+    self.pruneUpto(upToIndex);
+
+    self.snapshotIndex= upToIndex;
+    self.snapshotTerm= term;
+
+    Ok(( ))
+  }
+
+  /*
+    Installs a snapshot received from the leader, which covers entries the log is missing. The
+    entries covered by the snapshot are committed.
+
+    If the log has the last entry covered by the snapshot, the entries following it are retained.
+    Otherwise, the whole log is discarded.
+
+    NOTE : The caller is responsible for restoring the state machine from the snapshot.
+  */
+  pub fn installSnapshot(&mut self, snapshot: Snapshot) -> Result<( )> {
+    if snapshot.index <= self.commitIndex {
+      return Err(Error::Internal(format!(
+        "Can't install snapshot upto index {}, since the entries upto {} are committed already",
+        snapshot.index, self.commitIndex
+      )))}
+
+    let (index, term)= (snapshot.index, snapshot.term);
+
+    let lastRemovedEntryIndex= match self.has(index, term)? {
+      true => index,
+      false => self.lastStoredEntryIndex
+    };
+
+    let mut batch= vec![
+      (Key::Snapshot.encode( ), Some(bincode::serialize(&snapshot)?)),
+      (Key::CommitIndex.encode( ), Some(bincode::serialize(&(index, term))?))
+    ];
+    for removedEntryIndex in (self.snapshotIndex + 1)..=lastRemovedEntryIndex {
+      batch.push((Key::Entry(removedEntryIndex).encode( ), None));}
+    self.writeBatch(batch)?;
+
+    // Only the configurations in the retained entries are kept.
+    self.configs= match lastRemovedEntryIndex == index {
+      true => self.configs.split_off(&(index + 1)),
+      false => BTreeMap::new( )
+    };
+    self.configs.insert(index, snapshot.config);
+
+    match lastRemovedEntryIndex == index {
+      true => self.pruneUpto(index),
+
+      false => {
+        self.cache.clear( );
+        self.terms.clear( );
+
+        self.lastStoredEntryIndex= index;
+        self.lastStoredEntryTerm= term;
+      }
+    }
+
+    self.snapshotIndex= index;
+    self.snapshotTerm= term;
+
+    self.commitIndex= index;
+    self.commitTerm= term;
+
+    Ok(( ))
+  }
+
+  /*
+    Commits entries upto and including the given index, and returns the new commit index.
+
+    The entry at the given index must exist in the log. Committing an index lower than the current
+    commit index is a no-op (the current commit index is returned), since a node can receive stale
+    commit indices from the leader (because of message reordering).
+  */
+  pub fn commit(&mut self, index: LogEntryIndex) -> Result<LogEntryIndex> {
+    if index <= self.commitIndex {
+      return Ok(self.commitIndex)}
+
+    let term= self.getTerm(index)?
+                  .ok_or_else(| | Error::Internal(format!(
+                    "Can't commit index {}, since the last stored entry is at index {}",
+                    index, self.lastStoredEntryIndex
+                  )))?;
+
+    self.writeBatch(vec![(Key::CommitIndex.encode( ), Some(bincode::serialize(&(index, term))?))])?;
+
+    self.commitIndex= index;
+    self.commitTerm= term;
+
+    Ok(self.commitIndex)
+  }
+
+  // Appends a command (received in the given term) to the log and returns the index of the newly
+  // stored entry.
+  pub fn append(&mut self, term: Term, command: Option<Vec<u8>>, requestId: Option<RequestId>) -> Result<LogEntryIndex> {
+    self.appendEntry(LogEntry { index: self.lastStoredEntryIndex + 1, term, command, config: None, requestId })
+  }
+
+  // Appends a configuration entry (received in the given term) to the log and returns its index.
+  // The configuration takes effect right away, without waiting for the entry to get committed.
+  pub fn appendConfig(&mut self, term: Term, config: ClusterConfig) -> Result<LogEntryIndex> {
+    self.appendEntry(LogEntry { index: self.lastStoredEntryIndex + 1, term, command: None, config: Some(config), requestId: None })
+  }
+
+  // Returns the entry stored at the given index (if it exists). Entries covered by the snapshot don't
+  // exist anymore.
+  pub fn get(&mut self, index: LogEntryIndex) -> Result<Option<LogEntry>> {
+    if index <= self.snapshotIndex || index > self.lastStoredEntryIndex {
+      return Ok(None)}
+
+    if index >= self.cacheStartIndex( ) {
+      self.metrics.cacheHits += 1;
+      return Ok(Some(self.cache[(index - self.cacheStartIndex( )) as usize].clone( )))
+    }
+
+    self.metrics.cacheMisses += 1;
+    self.storageEngine.get(&Key::Entry(index).encode( ))?
+      .map(|entry| bincode::deserialize(&entry))
+      .transpose( )
+      .map_err(Error::from)
+  }
+
+  // Returns the term of the entry at the given index, including the last entry covered by the
+  // snapshot (if the entry exists).
+  pub fn getTerm(&mut self, index: LogEntryIndex) -> Result<Option<Term>> {
+    if index == self.snapshotIndex {
+      return Ok(Some(self.snapshotTerm))}
+
+    if index < self.snapshotIndex {
+      return Ok(None)}
+
+    Ok(self.terms.get((index - self.snapshotIndex - 1) as usize).copied( ))
+  }
+
+  // Returns whether an entry exists at the given index with the given term.
+  pub fn has(&mut self, index: LogEntryIndex, term: Term) -> Result<bool> {
+    // The (non-existent) entry at index 0 is considered to be present in every log.
+    if index == 0 && term == 0 {
+      return Ok(true)}
+
+    // The entries covered by the snapshot are committed, and so they match the leader's log.
+    if index < self.snapshotIndex {
+      return Ok(true)}
+
+    Ok(self.getTerm(index)?.is_some_and(|entryTerm| entryTerm == term))
+  }
+
+  // Iterates over the entries, whose indices lie in the given range. The cached entries in the range
+  // are served from the cache, and only the ones before them are read from the storage engine.
+  pub fn scan<R: RangeBounds<LogEntryIndex>>(&mut self, range: R) -> impl Iterator<Item= Result<LogEntry>> + '_ {
+    // The first and last index of the range, within the stored entries.
+    let first= match range.start_bound( ) {
+      Bound::Included(index) => *index,
+      Bound::Excluded(index) => index.saturating_add(1),
+      Bound::Unbounded => 0
+    }.max(self.snapshotIndex + 1);
+
+    let last= match range.end_bound( ) {
+      Bound::Included(index) => *index,
+      Bound::Excluded(index) => index.saturating_sub(1),
+      Bound::Unbounded => LogEntryIndex::MAX
+    }.min(self.lastStoredEntryIndex);
+
+    let cacheStartIndex= self.cacheStartIndex( );
+
+    let stored= (first < cacheStartIndex && first <= last).then(| | (first, last.min(cacheStartIndex - 1)));
+    match stored {
+      Some(_) => self.metrics.cacheMisses += 1,
+      None => self.metrics.cacheHits += 1
+    }
+
+    let cached= match first.max(cacheStartIndex) <= last {
+      true => (first.max(cacheStartIndex) - cacheStartIndex) as usize..(last - cacheStartIndex + 1) as usize,
+      false => 0..0
+    };
+
+    let storedEntries= stored.map(|(first, last)| Self::scanEntries(self.storageEngine.as_mut( ), first..=last));
+    let cachedEntries= self.cache.range(cached).cloned( ).map(Ok);
+
+    storedEntries.into_iter( ).flatten( ).chain(cachedEntries)
+  }
+
+  // Iterates over the committed entries after the given applied index. Used by the state-machine
+  // driver to fetch the entries it's yet to apply.
+  // NOTE : If the applied index is behind the snapshot, the state machine must be restored from the
+  // snapshot first.
+  pub fn scanUnapplied(&mut self, appliedIndex: LogEntryIndex) -> impl Iterator<Item= Result<LogEntry>> + '_ {
+    let commitIndex= self.commitIndex;
+    self.scan((Bound::Excluded(appliedIndex), Bound::Included(commitIndex)))
+  }
+
+  /*
+    Splices a set of entries (received from the leader) into the log and returns the index of the
+    last stored entry.
+
+    The entries must be contiguous, and the first entry must either be right after the last stored
+    entry or overlap with the existing log. Entries already present in the log (same index and term)
+    are skipped. On finding the first conflicting entry (same index but different term), the
+    existing entry along with all the ones that follow it are removed from the log, and the rest of
+    the given entries are appended.
+  */
+  pub fn splice(&mut self, entries: Vec<LogEntry>) -> Result<LogEntryIndex> {
+    let Some(firstEntry)= entries.first( ) else {
+      return Ok(self.lastStoredEntryIndex)};
+
+    if firstEntry.index == 0 {
+      return Err(Error::Internal("Can't splice entry with index 0".into( )))}
+
+    if firstEntry.index > self.lastStoredEntryIndex + 1 {
+      return Err(Error::Internal(format!(
+        "Spliced entries starting at index {} are disjoint with the log ending at index {}",
+        firstEntry.index, self.lastStoredEntryIndex
+      )))}
+
+    for adjacentEntries in entries.windows(2) {
+      let (previous, next)= (&adjacentEntries[0], &adjacentEntries[1]);
+
+      if next.index != previous.index + 1 {
+        return Err(Error::Internal(format!(
+          "Spliced entries aren't contiguous : index {} is followed by {}", previous.index, next.index
+        )))}
+
+      if next.term < previous.term {
+        return Err(Error::Internal(format!(
+          "Spliced entries have regressing terms : term {} is followed by {}", previous.term, next.term
+        )))}
+    }
+
+    // The removal of the conflicting entries and the appending of the new ones, are written (and
+    // committed) together.
+    let mut batch= Vec::new( );
+
+    // Skip the entries which are already present in the log and find the first one which isn't.
+    let mut entriesToAppend= entries.as_slice( );
+    while let Some(entry)= entriesToAppend.first( ) {
+      if entry.index > self.lastStoredEntryIndex {
+        break}
+
+      if !self.has(entry.index, entry.term)? {
+        // Found a conflicting entry. Remove it along with all the entries that follow it.
+        self.metrics.conflicts += 1;
+        self.removeEntriesAfter(entry.index - 1, &mut batch)?;
+        break
+      }
+
+      entriesToAppend= &entriesToAppend[1..];
+    }
+
+    if entriesToAppend.is_empty( ) {
+      return Ok(self.lastStoredEntryIndex)}
+
+    for entry in entriesToAppend {
+      self.storeEntry(entry, &mut batch)?;}
+    self.writeBatch(batch)?;
+
+    Ok(self.lastStoredEntryIndex)
+  }
+
+  // Removes all the entries after the given index and returns the index of the new last stored
+  // entry. Does nothing if there are no entries after the given index.
+  // NOTE : Committed entries can never be removed.
+  pub fn truncate(&mut self, index: LogEntryIndex) -> Result<LogEntryIndex> {
+    if index >= self.lastStoredEntryIndex {
+      return Ok(self.lastStoredEntryIndex)}
+
+    let mut batch= Vec::new( );
+    self.removeEntriesAfter(index, &mut batch)?;
+    self.writeBatch(batch)?;
+
+    Ok(index)
+  }
+}
+
+impl Log {
+  // Adds the removal of all the entries after the given index to the batch, and makes the entry at
+  // the given index the last stored entry.
+  // NOTE : The caller is responsible for writing the batch.
+  fn removeEntriesAfter(&mut self, index: LogEntryIndex, batch: &mut WriteBatch) -> Result<( )> {
+    if index < self.commitIndex {
+      return Err(Error::Internal(format!(
+        "Can't truncate the log to index {}, below the commit index {}", index, self.commitIndex
+      )))}
+
+    let lastStoredEntryTerm=
+      if index == 0 { 0 }
+      else {
+        self.getTerm(index)?
+            .ok_or_else(| | Error::Internal(format!("Entry at index {} not found", index)))?
+      };
+
+    for removedEntryIndex in (index + 1)..=self.lastStoredEntryIndex {
+      batch.push((Key::Entry(removedEntryIndex).encode( ), None));}
+    self.metrics.entriesRemoved += self.lastStoredEntryIndex - index;
+
+    // The configurations carried by the removed entries are reverted, and the removed entries are
+    // dropped from the cache and the terms.
+    self.configs.split_off(&(index + 1));
+
+    while self.cache.back( ).is_some_and(|entry| entry.index > index) {
+      self.cache.pop_back( );}
+    self.terms.truncate((index - self.snapshotIndex) as usize);
+
+    self.lastStoredEntryIndex= index;
+    self.lastStoredEntryTerm= lastStoredEntryTerm;
+
+    Ok(( ))
+  }
+
+  // Stores the given entry as the last one, and returns its index.
+  fn appendEntry(&mut self, entry: LogEntry) -> Result<LogEntryIndex> {
+    let mut batch= Vec::new( );
+    self.storeEntry(&entry, &mut batch)?;
+    self.writeBatch(batch)?;
+
+    Ok(entry.index)
+  }
+
+  // Writes the batch to the storage engine, as a commit flushed according to the sync policy.
+  fn writeBatch(&mut self, batch: WriteBatch) -> Result<( )> {
+    self.storageEngine.writeBatch(batch)?;
+
+    let Self { syncer, storageEngine, .. }= self;
+    syncer.commit(| | storageEngine.flush( ))
+  }
+
+  // Adds the given entry to the batch, and makes it the last stored entry.
+  // NOTE : The caller is responsible for writing the batch.
+  fn storeEntry(&mut self, entry: &LogEntry, batch: &mut WriteBatch) -> Result<( )> {
+    batch.push((Key::Entry(entry.index).encode( ), Some(bincode::serialize(entry)?)));
+
+    if let Some(config)= &entry.config {
+      self.configs.insert(entry.index, config.clone( ));}
+
+    self.terms.push_back(entry.term);
+    Self::cacheEntry(&mut self.cache, self.cacheCapacity, entry.clone( ));
+
+    self.lastStoredEntryIndex= entry.index;
+    self.lastStoredEntryTerm= entry.term;
+    self.metrics.entriesAppended += 1;
+
+    Ok(( ))
+  }
+
+  // Adds the given entry (stored as the last one) to the cache, evicting the oldest cached entry if
+  // the cache is full.
+  fn cacheEntry(cache: &mut VecDeque<LogEntry>, cacheCapacity: usize, entry: LogEntry) {
+    if cacheCapacity == 0 {
+      return}
+
+    if cache.len( ) == cacheCapacity {
+      cache.pop_front( );}
+    cache.push_back(entry);
+  }
+
+  // Returns the index of the first cached entry (the one after the last stored entry, if the cache is
+  // empty). The entries from there onwards are cached.
+  fn cacheStartIndex(&self) -> LogEntryIndex {
+    self.cache.front( ).map_or(self.lastStoredEntryIndex + 1, |entry| entry.index)
+  }
+
+  // Drops the entries upto and including the given index (which are getting compacted) from the
+  // cache and the terms.
+  fn pruneUpto(&mut self, index: LogEntryIndex) {
+    while self.cache.front( ).is_some_and(|entry| entry.index <= index) {
+      self.cache.pop_front( );}
+    self.terms.drain(..(index - self.snapshotIndex) as usize);
+  }
+
+  fn scanEntries<R: RangeBounds<LogEntryIndex>>(storageEngine: &mut dyn StorageEngine,
+                                                range: R) -> impl Iterator<Item= Result<LogEntry>> + '_
+  {
+    let start= match range.start_bound( ) {
+      Bound::Included(index) => Bound::Included(Key::Entry(*index).encode( )),
+      Bound::Excluded(index) => Bound::Excluded(Key::Entry(*index).encode( )),
+      Bound::Unbounded => Bound::Included(Key::Entry(0).encode( ))
+    };
+
+    let end= match range.end_bound( ) {
+      Bound::Included(index) => Bound::Included(Key::Entry(*index).encode( )),
+      Bound::Excluded(index) => Bound::Excluded(Key::Entry(*index).encode( )),
+      Bound::Unbounded => Bound::Included(Key::Entry(LogEntryIndex::MAX).encode( ))
+    };
+
+    storageEngine.scan((start, end))
+                 .map(|item| item.and_then(|(_, entry)| bincode::deserialize(&entry).map_err(Error::from)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::storage::engine::{instrumented::InstrumentedEngine, memory::Memory};
+
+  // Returns a log of entries of the given terms (at indices starting from 1).
+  fn newLog(terms: &[Term]) -> Result<Log> {
+    let mut log= Log::new(Box::new(Memory::new( )), DEFAULT_CACHE_CAPACITY, SyncPolicy::default( ))?;
+    for (index, term) in terms.iter( ).enumerate( ) {
+      log.appendEntry(entry(index as LogEntryIndex + 1, *term))?;}
+
+    Ok(log)
+  }
+
+  fn entry(index: LogEntryIndex, term: Term) -> LogEntry {
+    LogEntry { index, term, command: Some(index.to_be_bytes( ).to_vec( )), config: None, requestId: None }
+  }
+
+  // Returns the number of flushes (and of write batches) of the log's storage engine, which must be
+  // instrumented.
+  fn flushesAndBatches(log: &Log) -> Result<(u64, u64)> {
+    let metrics= log.status( )?.metrics.ok_or_else(| | Error::Internal("Storage engine isn't instrumented".to_string( )))?;
+    Ok((metrics.flushes, metrics.writeBatches))
+  }
+
+  // Returns the terms of the stored entries.
+  fn terms(log: &mut Log) -> Result<Vec<Term>> {
+    log.scan(..).map(|entry| entry.map(|entry| entry.term)).collect( )
+  }
+
+  #[test]
+  fn spliceRejectsDisjointEntries( ) -> Result<( )> {
+    let mut log= newLog(&[1, 1, 2])?;
+
+    assert!(matches!(log.splice(vec![entry(5, 2), entry(6, 2)]), Err(Error::Internal(_))));
+    assert_eq!(log.getLastStoredEntryIndexAndTerm( ), (3, 2));
+    assert_eq!(terms(&mut log)?, vec![1, 1, 2]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn spliceSkipsMatchingEntries( ) -> Result<( )> {
+    let mut log= newLog(&[1, 1, 2])?;
+
+    assert_eq!(log.splice(vec![entry(2, 1), entry(3, 2)])?, 3);
+
+    // Entries matching a prefix of the log don't truncate the entries after them, since the
+    // AppendEntries carrying them might have been delayed.
+    assert_eq!(log.splice(vec![entry(1, 1)])?, 3);
+
+    assert_eq!(terms(&mut log)?, vec![1, 1, 2]);
+    assert_eq!(log.metrics( ).conflicts, 0);
+    Ok(( ))
+  }
+
+  #[test]
+  fn spliceOverwritesDivergentSuffix( ) -> Result<( )> {
+    let mut log= newLog(&[1, 1, 1, 1])?;
+
+    assert_eq!(log.splice(vec![entry(2, 1), entry(3, 2)])?, 3);
+
+    assert_eq!(log.getLastStoredEntryIndexAndTerm( ), (3, 2));
+    assert_eq!(terms(&mut log)?, vec![1, 1, 2]);
+    assert_eq!(log.getTerm(4)?, None);
+    assert_eq!(log.metrics( ).conflicts, 1);
+
+    // The entries are appended after the overwritten ones.
+    assert_eq!(log.splice(vec![entry(4, 2), entry(5, 3)])?, 5);
+    assert_eq!(terms(&mut log)?, vec![1, 1, 2, 2, 3]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn flushesOncePerAppendEntries( ) -> Result<( )> {
+    let mut log= Log::new(Box::new(InstrumentedEngine::new(Memory::new( ))), DEFAULT_CACHE_CAPACITY, SyncPolicy::Always)?;
+    let (flushes, batches)= flushesAndBatches(&log)?;
+
+    // The 10k entries of an AppendEntries are written in a single batch, and flushed once.
+    let entries= (1..=10_000).map(|index| entry(index, 1)).collect( );
+    assert_eq!(log.splice(entries)?, 10_000);
+    assert_eq!(flushesAndBatches(&log)?, (flushes + 1, batches + 1));
+
+    // Whereas appending them one at a time flushes each of them.
+    for index in 10_001..=20_000 {
+      log.appendEntry(entry(index, 1))?;}
+    assert_eq!(flushesAndBatches(&log)?, (flushes + 10_001, batches + 10_001));
+
+    assert_eq!(log.getLastStoredEntryIndexAndTerm( ), (20_000, 1));
+    Ok(( ))
+  }
+}
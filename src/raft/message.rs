@@ -1,6 +1,9 @@
-use super::types::Term;
+use serde::{Deserialize, Serialize};
+use crate::result::{Error, Result};
+use super::{log::{LogEntry, Snapshot}, node::Status, types::{LogEntryIndex, NodeId, ReadSequence, Term}};
 
 // Represents a message exchanged between nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
   pub currentTermOfSender: Term,
 
@@ -10,13 +13,207 @@ pub struct Message {
   pub payload: MessagePayload
 }
 
-pub enum MessageAddress { }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MessageAddress {
+  // A specific node in the cluster.
+  Node(NodeId),
 
+  // All the peers of the sender.
+  // NOTE : Can only be used as the destination of a message.
+  Broadcast,
+
+  // A client connected to the local node.
+  Client
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MessagePayload {
   // Represents the periodic heartbeat sent from leader to its followers.
-  Heartbeat { },
+  Heartbeat {
+    // The leader's commit index and the term of that entry. A follower having the entry, can
+    // commit upto it.
+    commitIndex: LogEntryIndex,
+    commitTerm: Term,
+
+    // The leader's latest read sequence number.
+    readSequence: ReadSequence
+  },
+
+  // Sent by a follower, acknowledging the leader's heartbeat.
+  HeartbeatResponse {
+    readSequence: ReadSequence
+  },
+
+  // Sent by the leader, to replicate log entries to a follower.
+  AppendEntries {
+    // Index and term of the entry immediately preceding the sent entries. The follower accepts the
+    // entries only if its log contains this entry (log consistency check).
+    prevLogIndex: LogEntryIndex,
+    prevLogTerm: Term,
+
+    entries: Vec<LogEntry>,
+
+    leaderCommit: LogEntryIndex
+  },
+
+  // Sent by the leader to a follower, in place of the entries it's missing which have been compacted
+  // away. The follower responds with an AppendEntriesResponse.
+  InstallSnapshot {
+    snapshot: Snapshot
+  },
+
+  AppendEntriesResponse {
+    success: bool,
+
+    // The index of the last entry stored by the follower.
+    lastLogIndex: LogEntryIndex
+  },
+
+  // Sent by a candidate, to solicit votes from its peers.
+  RequestVote {
+    // Index and term of the candidate's last stored entry. A node only votes for a candidate whose
+    // log is atleast as up-to-date as its own.
+    lastLogIndex: LogEntryIndex,
+    lastLogTerm: Term
+  },
+
+  RequestVoteResponse {
+    voteGranted: bool
+  },
+
+  /*
+    Sent by a follower whose election timed out, before it becomes a candidate. It asks its peers
+    whether they would vote for it in the given (prospective) term, without the sender or the peers
+    changing their terms. The message itself carries the sender's actual current term.
+
+    Only if a quorum agrees, the follower starts a real election. So a node which is partitioned off
+    the cluster, doesn't keep incrementing its term and disrupt the leader once it rejoins.
+  */
+  PreVote {
+    term: Term,
+
+    lastLogIndex: LogEntryIndex,
+    lastLogTerm: Term
+  },
+
+  PreVoteResponse {
+    voteGranted: bool
+  },
+
+  // Sent by the leader to the target of a leadership transfer, once the target's log is up-to-date.
+  // The target starts an election right away (without a pre-vote), which it wins since its log is
+  // current.
+  TimeoutNow,
+
+  ClientRequest {
+    id: RequestId,
+    request: Request
+  },
+
+  ResponseToClient {
+    id: RequestId,
+    response: Result<Response>
+  }
+}
+
+// Uniquely identifies a client request.
+pub type RequestId= Vec<u8>;
 
-  ClientRequest { },
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+  // A read-only state machine command, which doesn't need to go through the log.
+  Query(Vec<u8>),
 
-  ResponseToClient { }
-}
\ No newline at end of file
+  /*
+    A read-only state machine command, which is executed by the node receiving it against its own
+    state machine, without involving the leader. So it's served even if there's no (reachable)
+    leader, but its result may be stale.
+
+    It's executed once the state machine has applied the entries upto the given index (like the
+    entries of the client's own writes, see Response::Mutate), and then as of whatever it has
+    applied.
+  */
+  LocalQuery {
+    command: Vec<u8>,
+    index: LogEntryIndex
+  },
+
+  // A state machine command, which gets replicated through the log.
+  Mutate(Vec<u8>),
+
+  // Adds a node (listening for Raft messages on the given address) to the cluster / removes a node
+  // from the cluster. It's answered once the configuration entry carrying the new membership gets
+  // committed.
+  AddNode {
+    id: NodeId,
+    address: String
+  },
+  RemoveNode(NodeId),
+
+  // Transfers the leadership to the given node (or to the most up-to-date peer, if none is given).
+  // It's answered once the leader steps down for the target's term, or with an error if the target
+  // doesn't get elected within the transfer timeout (see Leader::transferLeadership( )).
+  TransferLeadership(Option<NodeId>),
+
+  // Returns the status of the node receiving the request. It's answered by that node itself (even
+  // if it's a follower), without going through the log.
+  Status
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+  Query(Vec<u8>),
+
+  // The result of a state machine command, along with the index of the entry it was applied at. Once
+  // a node's state machine has applied the entry, its replica reflects the command.
+  Mutate {
+    result: Vec<u8>,
+    index: LogEntryIndex
+  },
+
+  Status(Box<Status>),
+  TransferLeadership
+}
+
+impl Message {
+  // Returns whether the message carries a client request / response.
+  pub fn isClientMessage(&self) -> bool {
+    matches!(self.payload, MessagePayload::ClientRequest { .. } | MessagePayload::ResponseToClient { .. })
+  }
+
+  // Rejects messages with obviously malformed sender / receiver and payload combinations.
+  pub fn validate(&self) -> Result<( )> {
+    let invalid= |reason: &str| Err(Error::Internal(format!("Invalid message {:?} : {}", self, reason)));
+
+    match (&self.from, &self.to) {
+      (MessageAddress::Broadcast, _) => return invalid("the sender can't be a broadcast address"),
+      (from, to) if from == to => return invalid("the sender and receiver are the same"),
+      _ => { }
+    }
+
+    match &self.payload {
+      MessagePayload::ClientRequest { .. } if self.to == MessageAddress::Broadcast =>
+        invalid("client requests can't be broadcasted"),
+
+      MessagePayload::ResponseToClient { .. } if self.to == MessageAddress::Broadcast =>
+        invalid("responses to clients can't be broadcasted"),
+
+      MessagePayload::ClientRequest { .. } | MessagePayload::ResponseToClient { .. } => Ok(( )),
+
+      // Rest of the messages are exchanged only between nodes.
+      _ if (self.from == MessageAddress::Client) || (self.to == MessageAddress::Client) =>
+        invalid("clients can only send requests and receive responses"),
+
+      // Responses are always sent back to a specific node.
+      MessagePayload::AppendEntriesResponse { .. }
+      | MessagePayload::HeartbeatResponse { .. }
+      | MessagePayload::RequestVoteResponse { .. }
+      | MessagePayload::PreVoteResponse { .. } if self.to == MessageAddress::Broadcast => invalid("responses can't be broadcasted"),
+
+      // Only the target of a leadership transfer is made to start an election.
+      MessagePayload::TimeoutNow if self.to == MessageAddress::Broadcast => invalid("TimeoutNow can't be broadcasted"),
+
+      _ => Ok(( ))
+    }
+  }
+}
@@ -1,5 +1,9 @@
 pub mod types;
+pub mod cluster_config;
 pub mod message;
 pub mod node;
 pub mod log;
-pub mod state_machine_driver;
\ No newline at end of file
+pub mod state_machine_driver;
+pub mod transport;
+pub mod server;
+pub mod simulation;
@@ -1,7 +1,13 @@
-use super::{follower::Follower, getRandomElectionTimeout, GenericNode, Role};
-use crate::{raft::{node::leader::Leader, types::{NodeId, Term, Ticks}}, result::Result};
+use super::{follower::Follower, GenericNode, Node, Role, RoleName};
+use crate::{
+  raft::{
+    message::{Message, MessageAddress, MessagePayload, Request},
+    node::leader::Leader, types::{NodeId, Term, Ticks}
+  },
+  result::{Error, Result}
+};
 use std::collections::HashSet;
-use tracing::info;
+use tracing::{debug, info, warn};
 
 /*
   To begin an election, a follower increments its current term and transitions to candidate state.
@@ -26,9 +32,9 @@ pub struct Candidate {
 }
 
 impl Candidate {
-  pub fn new( ) -> Self {
+  pub fn new(electionTimeout: Ticks) -> Self {
     Self {
-      electionTimeout: getRandomElectionTimeout( ),
+      electionTimeout,
       ..Default::default( )
     }
   }
@@ -38,28 +44,127 @@ impl Role for Candidate { }
 
 impl GenericNode<Candidate> {
   // Start new term and campaign for leadership.
+  // NOTE : The term and the node's vote for itself are stored first, so that the node stays in the
+  // previous term if storing them fails.
   pub(in crate::raft) fn startNewTerm(&mut self) -> Result<( )> {
     let newTerm = self.currentTerm + 1;
     info!("Starting campaign for new term {}", newTerm);
 
+    let castVote= Some(self.id);
+    let (previousTerm, _)= self.log.setCurrentTermAndCastVote(newTerm, castVote)?;
+    if previousTerm != self.currentTerm {
+      return Err(Error::Internal(format!("Stored term {} diverged from the current term {}", previousTerm, self.currentTerm)))}
+
     self.currentTerm = newTerm;
-    self.role = Candidate::new( );
+    self.role = Candidate::new(self.randomElectionTimeout( ));
     self.role.receivedVotes.insert(self.id); // Node votes for itself.
 
-    let castVote= Some(self.id);
-    self.log.setCurrentTermAndCastVote(newTerm, castVote);
+    // Solicit votes from the peers.
+    let (lastLogIndex, lastLogTerm)= self.log.getLastStoredEntryIndexAndTerm( );
+    self.send(MessageAddress::Broadcast, MessagePayload::RequestVote { lastLogIndex, lastLogTerm })
+  }
+
+  // Processes a message received by the candidate.
+  pub fn processMessage(mut self, message: Message) -> Result<Node> {
+    message.validate( )?;
+
+    // The node discovered a new term. It steps into that term as a leaderless follower.
+    if message.currentTermOfSender > self.currentTerm {
+      return self.becomeFollower(message.currentTermOfSender, None)?.processMessage(message)}
+
+    // Messages from previous terms are stale and thus ignored.
+    // NOTE : Client requests and responses aren't bound to terms.
+    if (message.currentTermOfSender < self.currentTerm) && !message.isClientMessage( ) {
+      debug!("Dropping message {:?} from stale term", message);
+      return Ok(self.into( ))
+    }
+
+    // Another node won the election in the current term, and is asserting its authority.
+    if let (MessageAddress::Node(leader),
+            MessagePayload::Heartbeat { .. } | MessagePayload::AppendEntries { .. } | MessagePayload::InstallSnapshot { .. })=
+      (message.from, &message.payload)
+    {
+      return self.becomeFollower(message.currentTermOfSender, Some(leader))?.processMessage(message)}
+
+    match (message.from, message.payload) {
+      // The candidate has already voted for itself in the current term.
+      (from @ MessageAddress::Node(_), MessagePayload::RequestVote { .. }) =>
+        self.send(from, MessagePayload::RequestVoteResponse { voteGranted: false })?,
+
+      // There is no leader in the current term, so the candidate would vote for the sender in a later
+      // term (if its log is up-to-date).
+      (from @ MessageAddress::Node(_), MessagePayload::PreVote { term, lastLogIndex, lastLogTerm }) => {
+        let voteGranted= (term > self.currentTerm) && self.isLogUpToDate(lastLogIndex, lastLogTerm);
+        self.send(from, MessagePayload::PreVoteResponse { voteGranted })?;
+      },
+
+      // Pre-votes may arrive after the node has started the election.
+      (_, MessagePayload::PreVoteResponse { .. }) => { },
+
+      (MessageAddress::Node(voter), MessagePayload::RequestVoteResponse { voteGranted }) => {
+        if voteGranted {
+          self.role.receivedVotes.insert(voter);}
+
+        if self.hasWonElection( ) {
+          return Ok(self.becomeLeader( )?.into( ))}
+      },
+
+      (MessageAddress::Client, MessagePayload::ClientRequest { id, request: Request::Status }) =>
+        self.respondWithStatus(id, RoleName::Candidate, None)?,
+
+      (MessageAddress::Client, MessagePayload::ClientRequest { id, request: Request::LocalQuery { command, index } }) =>
+        self.queryLocally(id, command, index)?,
 
-    todo!( )
+      // There is no leader to serve the request during the election, so it waits for the election's
+      // outcome.
+      (MessageAddress::Client, MessagePayload::ClientRequest { id, request }) =>
+        self.queueRequest(id, request),
+
+      (from, payload) => warn!("Candidate received unexpected message {:?} from {:?}", payload, from)
+    }
+
+    Ok(self.into( ))
   }
 
-  //
-  pub(in crate::raft) fn becomeLeader(mut self) -> Result<GenericNode<Leader>> {
+  // Moves the logical clock by a tick. If the election times out without a winner, a new election
+  // is started in the next term.
+  pub fn tick(mut self) -> Result<Node> {
+    self.role.electionDuration += 1;
+
+    if self.role.electionDuration >= self.role.electionTimeout {
+      self.startNewTerm( )?;
+
+      // In a single node cluster, the node wins the election just by voting for itself.
+      if self.hasWonElection( ) {
+        return Ok(self.becomeLeader( )?.into( ))}
+    }
+
+    Ok(self.into( ))
+  }
+
+  // Returns whether the candidate has received votes from a quorum of the nodes.
+  pub(in crate::raft) fn hasWonElection(&self) -> bool {
+    self.log.getConfig( ).1.isQuorum(&self.role.receivedVotes)
+  }
+
+  // Transitions the node from a candidate to the leader, after it wins the election.
+  pub(in crate::raft) fn becomeLeader(self) -> Result<GenericNode<Leader>> {
     info!("Won election in term {} | Becoming leader", self.currentTerm);
 
-    unimplemented!( );
+    let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+    let leader= Leader::new(&self.peers( ), lastLogIndex);
+    let mut node= self.changeRole(leader);
+
+    // Assert authority, so that the peers don't start new elections.
+    node.broadcastHeartbeat( )?;
 
-    let node= self.changeRole(Leader::new( ));
-    unimplemented!( );
+    // Entries from previous terms can't be committed by counting replicas. So a noop entry is
+    // appended, which commits those entries when it gets committed.
+    node.role.termStartIndex= node.propose(None, None)?;
+
+    // The requests queued during the election are served by the node itself.
+    for queued in std::mem::take(&mut node.queuedRequests) {
+      node.serveClientRequest(MessageAddress::Client, queued.id, queued.request)?;}
 
     Ok(node)
   }
@@ -87,7 +192,11 @@ impl GenericNode<Candidate> {
         info!("Lost election in the current term {} | Following leader {}", currentTerm, leader);
 
         let castVote = Some(self.id);
-        Ok(self.changeRole(Follower::new(Some(leader), castVote)))
+        let electionTimeout= self.randomElectionTimeout( );
+        let mut node= self.changeRole(Follower::new(Some(leader), castVote, electionTimeout));
+        node.forwardQueuedRequests( )?;
+
+        Ok(node)
       }
 
       // CASE (b) - The node discovered a new term (in which case it'll step into the term as a
@@ -98,11 +207,141 @@ impl GenericNode<Candidate> {
 
         info!("Discovered new term {} | Becoming a leaderless follower", currentTerm);
 
+        self.log.setCurrentTermAndCastVote(currentTerm, None)?;
         self.currentTerm = currentTerm;
-        self.log.setCurrentTermAndCastVote(currentTerm, None);
 
-        Ok(self.changeRole(Follower::new(None, None)))
+        let electionTimeout= self.randomElectionTimeout( );
+        Ok(self.changeRole(Follower::new(None, None, electionTimeout)))
       }
     }
   }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use crate::{
+    raft::{message::{Message, MessageAddress, MessagePayload}, types::NodeId},
+    result::{Error, Result}
+  };
+  use super::super::{tests::{message, TestCluster}, Node, ELECTION_TIMEOUT_RANGE};
+
+  // Makes the node a candidate of the next term : its election timer runs out, and the pre-vote
+  // succeeds. Its requests for votes are left in flight.
+  fn campaign(cluster: &mut TestCluster, id: u64) -> Result<( )> {
+    let from= MessageAddress::Node(NodeId(id));
+    cluster.tickUntil(id, ELECTION_TIMEOUT_RANGE.end as u64, |cluster| cluster.inflight.iter( ).any(|message| message.from == from))?;
+    cluster.deliver(|message| matches!(message.payload, MessagePayload::PreVote { .. } | MessagePayload::PreVoteResponse { .. }))?;
+
+    assert!(matches!(cluster.node(id), Node::Candidate(_)));
+    Ok(( ))
+  }
+
+  fn to(id: u64) -> impl Fn(&Message) -> bool {
+    move |message| message.to == MessageAddress::Node(NodeId(id))
+  }
+
+  #[test]
+  fn winsElectionWithQuorum( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(3)?;
+    campaign(&mut cluster, 1)?;
+    assert_eq!(cluster.node(1).currentTerm( ), 1);
+
+    // Node 3 doesn't hear of the election. The vote of node 2, along with its own, is a quorum.
+    cluster.dropMessages(to(3));
+    cluster.deliver(to(2))?;
+    assert!(matches!(cluster.node(1), Node::Candidate(_)));
+
+    cluster.deliver(to(1))?;
+    assert!(matches!(cluster.node(1), Node::Leader(_)));
+    assert_eq!(cluster.node(1).currentTerm( ), 1);
+
+    // The leader asserts its authority over node 3 as well.
+    cluster.deliverAll( )?;
+    assert_eq!(cluster.node(3).leader( ), Some(NodeId(1)));
+    assert_eq!(cluster.node(3).currentTerm( ), 1);
+    Ok(( ))
+  }
+
+  #[test]
+  fn stepsDownOnAppendEntriesOfHigherTerm( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(3)?;
+    campaign(&mut cluster, 1)?;
+    cluster.dropMessages(|_| true);
+
+    // Node 3 has been elected in a later term.
+    let appendEntries= MessagePayload::AppendEntries { prevLogIndex: 1, prevLogTerm: 0, entries: vec![ ], leaderCommit: 1 };
+    cluster.receive(message(3, 1, 2, appendEntries))?;
+
+    assert!(matches!(cluster.node(1), Node::Follower(_)));
+    assert_eq!(cluster.node(1).currentTerm( ), 2);
+    assert_eq!(cluster.node(1).leader( ), Some(NodeId(3)));
+    assert_eq!(cluster.nodeMut(1).logMut( ).getCurrentTermAndCastVote( )?, (2, None));
+
+    // And the entries are accepted.
+    assert_eq!(Vec::from(cluster.inflight.clone( )), vec![
+      message(1, 3, 2, MessagePayload::AppendEntriesResponse { success: true, lastLogIndex: 1 })
+    ]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn campaignsAgainOnElectionTimeout( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(3)?;
+    campaign(&mut cluster, 1)?;
+
+    // None of the peers hear of the election, so it times out without a winner.
+    cluster.dropMessages(|_| true);
+    for _ in 1..ELECTION_TIMEOUT_RANGE.start {
+      cluster.tick(1)?;}
+    assert_eq!(cluster.node(1).currentTerm( ), 1);
+
+    assert!(cluster.tickUntil(1, ELECTION_TIMEOUT_RANGE.end as u64, |cluster| cluster.node(1).currentTerm( ) == 2)?);
+    assert!(matches!(cluster.node(1), Node::Candidate(_)));
+    assert_eq!(cluster.nodeMut(1).logMut( ).getCurrentTermAndCastVote( )?, (2, Some(NodeId(1))));
+
+    // It campaigns in the new term, without another pre-vote.
+    let requestVote= MessagePayload::RequestVote { lastLogIndex: 1, lastLogTerm: 0 };
+    assert_eq!(Vec::from(cluster.inflight.clone( )), vec![message(1, 2, 2, requestVote.clone( )), message(1, 3, 2, requestVote)]);
+
+    cluster.deliverAll( )?;
+    assert!(matches!(cluster.node(1), Node::Leader(_)));
+    assert_eq!(cluster.node(1).currentTerm( ), 2);
+    Ok(( ))
+  }
+
+  #[test]
+  fn staysInPreviousTermIfStoringItFails( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(3)?;
+
+    let from1= |message: &Message| message.from == MessageAddress::Node(NodeId(1));
+    cluster.tickUntil(1, ELECTION_TIMEOUT_RANGE.end as u64, |cluster| cluster.inflight.iter( ).any(from1))?;
+    cluster.deliver(|message| matches!(message.payload, MessagePayload::PreVote { .. }))?;
+
+    // The pre-vote succeeds, but the new term and the vote for itself can't be stored. So the node
+    // fails, without asking for any votes.
+    cluster.engine(1).failFlushes(true);
+    assert!(matches!(cluster.deliverAll( ), Err(Error::Internal(_))));
+    assert!(!cluster.inflight.iter( ).any(|message| matches!(message.payload, MessagePayload::RequestVote { .. })));
+
+    // It restarts as a follower of the previous term, without a vote.
+    cluster.restart(1)?;
+    assert!(matches!(cluster.node(1), Node::Follower(_)));
+    assert_eq!(cluster.node(1).currentTerm( ), 0);
+    assert_eq!(cluster.nodeMut(1).logMut( ).getCurrentTermAndCastVote( )?, (0, None));
+    Ok(( ))
+  }
+
+  #[test]
+  fn keepsVoteForItselfAcrossRestart( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(3)?;
+    campaign(&mut cluster, 1)?;
+    cluster.dropMessages(|_| true);
+
+    // Having voted for itself in term 1, the node refuses node 2 in that term after a restart.
+    cluster.restart(1)?;
+    assert_eq!(cluster.node(1).currentTerm( ), 1);
+
+    cluster.receive(message(2, 1, 1, MessagePayload::RequestVote { lastLogIndex: 1, lastLogTerm: 0 }))?;
+    assert_eq!(Vec::from(cluster.inflight.clone( )), vec![message(1, 2, 1, MessagePayload::RequestVoteResponse { voteGranted: false })]);
+    Ok(( ))
+  }
+}
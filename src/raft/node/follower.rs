@@ -1,13 +1,15 @@
-use std::collections::HashSet;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
 use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error, info, warn};
 use crate::{
   raft::{
-    log::Log, message::Message, state_machine_driver::StateMachineInstruction,
-    types::{NodeId, Ticks}
+    log::Log, message::{Message, MessageAddress, MessagePayload, Request},
+    state_machine_driver::StateMachineInstruction, types::{LogEntryIndex, NodeId, Term, Ticks}
   },
-  result::Result
+  result::{Error, Result}
 };
-use super::{getRandomElectionTimeout, GenericNode, Role};
+use super::{candidate::Candidate, leader::ReplicationLimits, GenericNode, Node, RandomSource, Role, RoleName, ELECTION_TIMEOUT_RANGE};
 
 /*
   A follower replicates state from the leader.
@@ -22,10 +24,17 @@ use super::{getRandomElectionTimeout, GenericNode, Role};
   in order to maintain their authority. If a follower receives no communication over a period of
   time (called the election timeout), then it assumes there is no leader and begins an election to
   choose a new leader.
+
+  Before starting an election though, the follower runs a pre-vote : it asks its peers whether they
+  would vote for it in the next term. The election is started only if a quorum of the nodes (
+  including itself) would. A peer refuses, if it's still hearing from a leader.
+
+  The leader transferring its leadership to the follower makes it start an election right away (see
+  MessagePayload::TimeoutNow).
 */
 #[derive(Default)]
 pub struct Follower {
-  leader: Option<NodeId>,
+  pub(in crate::raft) leader: Option<NodeId>,
 
   // Cast vote represents the node that this node voted for in the current term.
   castVote: Option<NodeId>,
@@ -33,18 +42,21 @@ pub struct Follower {
   timeSinceLeaderSentHeartbeat: Ticks,
   electionTimeout: Ticks,
 
+  // The nodes which agreed to vote for this node in the next term, while it's running a pre-vote.
+  preVotes: Option<HashSet<NodeId>>,
+
   // Id of requests sent by the client, directly to this node.
   // NOTE : These requests are forwarded to the leader / rejected during leader or term change.
   pub(in crate::raft) requestsFromClient: HashSet<Vec<u8>>
 }
 
 impl Follower {
-  pub fn new(leader: Option<u8>, castVote: Option<u8>) -> Self {
+  pub fn new(leader: Option<NodeId>, castVote: Option<NodeId>, electionTimeout: Ticks) -> Self {
     Self {
       leader,
       castVote,
 
-      electionTimeout: getRandomElectionTimeout( ),
+      electionTimeout,
 
       ..Default::default( )
     }
@@ -54,24 +66,530 @@ impl Follower {
 impl Role for Follower { }
 
 impl GenericNode<Follower> {
-  pub fn newAsLeaderless(nodeId: u8,
-                         peers: HashSet<u8>,
+  pub fn newAsLeaderless(nodeId: NodeId,
                          mut log: Log,
                          messageSender: UnboundedSender<Message>,
-                         stateMachineDriverInstructionsSender: UnboundedSender<StateMachineInstruction>) -> Result<GenericNode>
+                         stateMachineDriverInstructionsSender: UnboundedSender<StateMachineInstruction>,
+                         replicationLimits: ReplicationLimits,
+                         mut random: RandomSource) -> Result<GenericNode>
   {
     let (newlyDiscoveredTerm, castVoteInNewlyDiscoveredTerm)= log.getCurrentTermAndCastVote( )?;
+    let electionTimeout= random.gen_range(ELECTION_TIMEOUT_RANGE);
 
     Ok(GenericNode {
-      role: Follower::new(None, castVoteInNewlyDiscoveredTerm),
+      role: Follower::new(None, castVoteInNewlyDiscoveredTerm, electionTimeout),
       currentTerm: newlyDiscoveredTerm,
 
       id: nodeId,
-      peers,
       messageSender,
 
       log,
-      stateMachineInstructor: stateMachineDriverInstructionsSender
+      stateMachineInstructor: stateMachineDriverInstructionsSender,
+      pendingSnapshot: None,
+
+      replicationLimits,
+      rejectedProposals: 0,
+      queuedRequests: Vec::new( ),
+
+      random
     })
   }
+
+  // Processes a message received by the follower.
+  pub fn processMessage(mut self, message: Message) -> Result<Node> {
+    message.validate( )?;
+
+    // The node discovered a new term. It steps into that term as a leaderless follower.
+    if message.currentTermOfSender > self.currentTerm {
+      self= self.becomeFollower(message.currentTermOfSender, None)?;}
+
+    // Requests from previous terms are rejected (the response carries the current term, making the
+    // sender step down). Other stale messages are ignored.
+    // NOTE : Client requests and responses aren't bound to terms.
+    if (message.currentTermOfSender < self.currentTerm) && !message.isClientMessage( ) {
+      debug!("Rejecting message {:?} from stale term", message);
+
+      match message.payload {
+        MessagePayload::AppendEntries { .. } | MessagePayload::InstallSnapshot { .. } => {
+          let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+          self.send(message.from, MessagePayload::AppendEntriesResponse { success: false, lastLogIndex })?;
+        },
+
+        MessagePayload::RequestVote { .. } =>
+          self.send(message.from, MessagePayload::RequestVoteResponse { voteGranted: false })?,
+
+        MessagePayload::PreVote { .. } =>
+          self.send(message.from, MessagePayload::PreVoteResponse { voteGranted: false })?,
+
+        _ => { }
+      }
+      return Ok(self.into( ))
+    }
+
+    match (message.from, message.payload) {
+      (MessageAddress::Node(leader), MessagePayload::Heartbeat { commitIndex, commitTerm, readSequence }) => {
+        self= self.acknowledgeLeader(leader)?;
+
+        // The leader's commit index is only applicable, if the node has the committed entry.
+        if self.log.has(commitIndex, commitTerm)? {
+          self.commit(commitIndex, HashMap::new( ))?;}
+
+        self.send(message.from, MessagePayload::HeartbeatResponse { readSequence })?;
+      },
+
+      (MessageAddress::Node(leader), MessagePayload::AppendEntries { prevLogIndex, prevLogTerm, entries, leaderCommit }) => {
+        self= self.acknowledgeLeader(leader)?;
+
+        // Log consistency check - the entries are accepted only if the log contains the entry
+        // immediately preceding them.
+        if self.log.has(prevLogIndex, prevLogTerm)? {
+          // Index upto which the log is guaranteed to match the leader's log.
+          let matchedIndex= prevLogIndex + entries.len( ) as u64;
+
+          self.log.splice(entries)?;
+          self.commit(leaderCommit.min(matchedIndex), HashMap::new( ))?;
+
+          self.send(message.from, MessagePayload::AppendEntriesResponse { success: true, lastLogIndex: matchedIndex })?;
+        }
+        else {
+          let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+          self.send(message.from, MessagePayload::AppendEntriesResponse { success: false, lastLogIndex })?;
+        }
+      },
+
+      // The leader has compacted away the entries the node is missing.
+      (MessageAddress::Node(leader), MessagePayload::InstallSnapshot { snapshot }) => {
+        self= self.acknowledgeLeader(leader)?;
+
+        // The node might have caught up already (like when the snapshot was sent again, before the
+        // response to the previous one reached the leader).
+        let lastLogIndex= snapshot.index;
+
+        let (commitIndex, _)= self.log.getCommitIndexAndTerm( );
+        if snapshot.index > commitIndex {
+          info!("Installing snapshot upto index {} from leader {}", snapshot.index, leader);
+
+          self.log.installSnapshot(snapshot.clone( ))?;
+          self.stateMachineInstructor.send(StateMachineInstruction::Restore { snapshot })
+                                     .map_err(|_| Error::Internal("State-machine driver has shut down".into( )))?;
+        }
+
+        self.send(message.from, MessagePayload::AppendEntriesResponse { success: true, lastLogIndex })?;
+      },
+
+      (MessageAddress::Node(candidate), MessagePayload::RequestVote { lastLogIndex, lastLogTerm }) => {
+        let voteGranted= self.shouldVoteFor(candidate, lastLogIndex, lastLogTerm);
+
+        // The vote is stored before it's granted, so that the node doesn't vote for another
+        // candidate in the same term after a restart.
+        if voteGranted {
+          info!("Voting for {} in term {}", candidate, self.currentTerm);
+
+          let (previousTerm, _)= self.log.setCurrentTermAndCastVote(self.currentTerm, Some(candidate))?;
+          if previousTerm != self.currentTerm {
+            return Err(Error::Internal(format!("Stored term {} diverged from the current term {}", previousTerm, self.currentTerm)))}
+
+          self.role.castVote= Some(candidate);
+
+          // Granting a vote resets the election timer.
+          self.role.timeSinceLeaderSentHeartbeat= 0;
+        }
+
+        self.send(message.from, MessagePayload::RequestVoteResponse { voteGranted })?;
+      },
+
+      // The vote is granted only if the node isn't following a leader and would vote for the sender
+      // in the prospective term. Nothing is recorded, and the election timer isn't reset.
+      (from @ MessageAddress::Node(_), MessagePayload::PreVote { term, lastLogIndex, lastLogTerm }) => {
+        let voteGranted= (term > self.currentTerm) && self.role.leader.is_none( ) && self.isLogUpToDate(lastLogIndex, lastLogTerm);
+        self.send(from, MessagePayload::PreVoteResponse { voteGranted })?;
+      },
+
+      (MessageAddress::Node(voter), MessagePayload::PreVoteResponse { voteGranted }) => {
+        // Votes may arrive after the pre-vote has ended.
+        let Some(preVotes)= &mut self.role.preVotes else {
+          return Ok(self.into( ))};
+
+        if voteGranted {
+          preVotes.insert(voter);}
+
+        if self.log.getConfig( ).1.isQuorum(&*preVotes) {
+          return self.startElection( )}
+      },
+
+      // The leader is transferring its leadership to the node, whose log has caught up. The node
+      // skips the pre-vote, since the peers still hear from the leader (and would refuse it).
+      (MessageAddress::Node(leader), MessagePayload::TimeoutNow) if self.isMember( ) => {
+        info!("Leader {} is transferring its leadership in term {} | Starting election", leader, self.currentTerm);
+        return self.startElection( )
+      },
+
+      (MessageAddress::Client, MessagePayload::ClientRequest { id, request: Request::Status }) => {
+        let leader= self.role.leader;
+        self.respondWithStatus(id, RoleName::Follower, leader)?;
+      },
+
+      (MessageAddress::Client, MessagePayload::ClientRequest { id, request: Request::LocalQuery { command, index } }) =>
+        self.queryLocally(id, command, index)?,
+
+      // Requests from clients are forwarded to the leader. They're queued if there is no known leader.
+      (MessageAddress::Client, MessagePayload::ClientRequest { id, request }) => match self.role.leader {
+        Some(leader) => self.forwardRequest(leader, id, request)?,
+        None => self.queueRequest(id, request)
+      },
+
+      // Relay the leader's responses to the forwarded client requests.
+      (MessageAddress::Node(_), MessagePayload::ResponseToClient { id, response }) => {
+        if self.role.requestsFromClient.remove(&id) {
+          self.send(MessageAddress::Client, MessagePayload::ResponseToClient { id, response })?;}
+      },
+
+      // Votes may arrive after the node lost the election.
+      (_, MessagePayload::RequestVoteResponse { .. }) => { },
+
+      (from, payload) => warn!("Follower received unexpected message {:?} from {:?}", payload, from)
+    }
+
+    Ok(self.into( ))
+  }
+
+  /*
+    Moves the logical clock by a tick. If the leader doesn't send a heartbeat within the election
+    timeout, then the node assumes there is no leader and runs a pre-vote. If the pre-vote doesn't
+    succeed within the (newly randomized) election timeout, it's run again.
+
+    A node in a single node cluster doesn't need a pre-vote, and starts the election right away. A
+    node which isn't a member of the cluster never campaigns.
+  */
+  pub fn tick(mut self) -> Result<Node> {
+    self.role.timeSinceLeaderSentHeartbeat += 1;
+
+    if self.role.timeSinceLeaderSentHeartbeat >= self.role.electionTimeout {
+      if !self.isMember( ) {
+        self.role.timeSinceLeaderSentHeartbeat= 0;
+        return Ok(self.into( ))
+      }
+
+      if self.quorom( ) <= 1 {
+        return self.startElection( )}
+
+      self.startPreVote( )?;
+    }
+
+    Ok(self.into( ))
+  }
+
+  // Asks the peers whether they would vote for the node in the next term.
+  fn startPreVote(&mut self) -> Result<( )> {
+    info!("Election timed out in term {} | Starting pre-vote", self.currentTerm);
+
+    // Requests forwarded to the leader can't be tracked anymore, since it's assumed to be gone.
+    self.abortRequestsFromClient( )?;
+    self.role.leader= None;
+
+    self.role.preVotes= Some(HashSet::from([self.id]));
+    self.role.timeSinceLeaderSentHeartbeat= 0;
+    self.role.electionTimeout= self.randomElectionTimeout( );
+
+    let term= self.currentTerm + 1;
+    let (lastLogIndex, lastLogTerm)= self.log.getLastStoredEntryIndexAndTerm( );
+    self.send(MessageAddress::Broadcast, MessagePayload::PreVote { term, lastLogIndex, lastLogTerm })
+  }
+
+  // Becomes a candidate and starts the election.
+  fn startElection(self) -> Result<Node> {
+    let node= self.becomeCandidate( )?;
+
+    // In a single node cluster, the node wins the election just by voting for itself.
+    if node.hasWonElection( ) {
+      return Ok(node.becomeLeader( )?.into( ))}
+
+    Ok(node.into( ))
+  }
+
+  // Transitions the node from a follower to a candidate, and starts campaigning for leadership in
+  // the next term.
+  pub(in crate::raft) fn becomeCandidate(mut self) -> Result<GenericNode<Candidate>> {
+    info!("Election timed out in term {} | Becoming a candidate", self.currentTerm);
+
+    self.abortRequestsFromClient( )?;
+
+    let electionTimeout= self.randomElectionTimeout( );
+    let mut node= self.changeRole(Candidate::new(electionTimeout));
+    node.startNewTerm( )?;
+
+    Ok(node)
+  }
+
+  /*
+    Makes the follower follow a new leader / step into a new term.
+
+    The follower -
+    (a) Discovers the leader of the current term (in which case the cast vote is retained).
+    (b) Discovers a new term (in which case it'll step into the term as a leaderless follower).
+  */
+  pub(in crate::raft) fn becomeFollower(mut self,
+                                        currentTerm: Term,
+                                        leader: Option<NodeId>) -> Result<GenericNode<Follower>>
+  {
+    assert!(currentTerm >= self.currentTerm,
+            "Term transition attemp from {} to {}", self.currentTerm, currentTerm);
+
+    // Requests forwarded to the previous leader can't be tracked anymore.
+    self.abortRequestsFromClient( )?;
+
+    match leader {
+      // CASE (a) - The node discovered the leader of the current term.
+      Some(leader) => {
+        assert_eq!(currentTerm, self.currentTerm, "Can't follow leader in a different term");
+
+        info!("Following leader {} in term {}", leader, currentTerm);
+
+        let castVote= self.role.castVote;
+        let electionTimeout= self.randomElectionTimeout( );
+        let mut node= self.changeRole(Follower::new(Some(leader), castVote, electionTimeout));
+        node.forwardQueuedRequests( )?;
+
+        Ok(node)
+      },
+
+      // CASE (b) - The node discovered a new term.
+      None => {
+        assert_ne!(currentTerm, self.currentTerm, "Can't become leaderless follower in the current term");
+
+        info!("Discovered new term {} | Becoming a leaderless follower", currentTerm);
+
+        self.log.setCurrentTermAndCastVote(currentTerm, None)?;
+        self.currentTerm= currentTerm;
+
+        let electionTimeout= self.randomElectionTimeout( );
+        Ok(self.changeRole(Follower::new(None, None, electionTimeout)))
+      }
+    }
+  }
+}
+
+impl GenericNode<Follower> {
+  /*
+    Records the given node as the leader of the current term (if not done already) and resets the
+    election timer.
+
+    Another node claiming to be the leader of the same term violates Raft's election safety (there's
+    atmost one leader per term), so it fails.
+  */
+  fn acknowledgeLeader(mut self, leader: NodeId) -> Result<Self> {
+    match self.role.leader {
+      None => {
+        let currentTerm= self.currentTerm;
+        self= self.becomeFollower(currentTerm, Some(leader))?;
+      },
+
+      Some(currentLeader) if currentLeader != leader => {
+        error!("Received message from {}, but the leader of term {} is {}", leader, self.currentTerm, currentLeader);
+        return Err(Error::Internal(format!("Nodes {} and {} both claim to be the leader of term {}", currentLeader, leader, self.currentTerm)))
+      },
+
+      _ => { }
+    }
+
+    self.role.timeSinceLeaderSentHeartbeat= 0;
+    Ok(self)
+  }
+
+  /*
+    Decides whether to grant the vote requested by the given candidate. The vote is granted if -
+    (a) the node hasn't voted for some other candidate in the current term and
+    (b) the candidate's log is atleast as up-to-date as the node's log.
+  */
+  fn shouldVoteFor(&self, candidate: NodeId, candidateLastLogIndex: LogEntryIndex, candidateLastLogTerm: Term) -> bool {
+    if self.role.castVote.is_some_and(|castVote| castVote != candidate) {
+      return false}
+
+    self.isLogUpToDate(candidateLastLogIndex, candidateLastLogTerm)
+  }
+
+  // Forwards a request from a local client to the leader, and tracks it so that the leader's
+  // response gets relayed back.
+  fn forwardRequest(&mut self, leader: NodeId, id: Vec<u8>, request: Request) -> Result<( )> {
+    self.role.requestsFromClient.insert(id.clone( ));
+    self.send(MessageAddress::Node(leader), MessagePayload::ClientRequest { id, request })
+  }
+
+  // Forwards the requests queued while there was no known leader, to the newly known leader.
+  pub(in crate::raft) fn forwardQueuedRequests(&mut self) -> Result<( )> {
+    let Some(leader)= self.role.leader else {
+      return Ok(( ))};
+
+    for queued in std::mem::take(&mut self.queuedRequests) {
+      self.forwardRequest(leader, queued.id, queued.request)?;}
+
+    Ok(( ))
+  }
+
+  // Rejects the client requests, which were forwarded to the leader and are yet to be responded to.
+  fn abortRequestsFromClient(&mut self) -> Result<( )> {
+    for id in std::mem::take(&mut self.role.requestsFromClient) {
+      self.send(MessageAddress::Client, MessagePayload::ResponseToClient { id, response: Err(Error::Abort) })?;}
+
+    Ok(( ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    raft::{message::{MessageAddress, MessagePayload}, types::{LogEntryIndex, NodeId, Term}},
+    result::{Error, Result}
+  };
+  use super::{super::{tests::{message, TestCluster}, GenericNode, Node, ELECTION_TIMEOUT_RANGE}, Follower};
+
+  // Returns a cluster of 3 nodes, where node 1 has entries of terms 1 and 2 after the bootstrapped
+  // configuration (so its last entry is at index 3, of term 2).
+  fn newCluster( ) -> Result<TestCluster> {
+    let mut cluster= TestCluster::new(3)?;
+
+    let log= cluster.nodeMut(1).logMut( );
+    for term in [1, 2] {
+      log.append(term, None, None)?;}
+    log.flush( )?;
+
+    Ok(cluster)
+  }
+
+  fn follower(cluster: &TestCluster, id: u64) -> &GenericNode<Follower> {
+    match cluster.node(id) {
+      Node::Follower(node) => node,
+      _ => panic!("Node {} isn't a follower", id)
+    }
+  }
+
+  // Has node 1 process a request for its vote in the given term, and returns whether it granted the
+  // vote.
+  fn requestVote(cluster: &mut TestCluster, candidate: u64, term: Term, lastLogIndex: LogEntryIndex, lastLogTerm: Term) -> Result<bool> {
+    cluster.receive(message(candidate, 1, term, MessagePayload::RequestVote { lastLogIndex, lastLogTerm }))?;
+
+    match cluster.inflight.pop_back( ).map(|response| (response.to, response.payload)) {
+      Some((to, MessagePayload::RequestVoteResponse { voteGranted })) if to == MessageAddress::Node(NodeId(candidate)) => Ok(voteGranted),
+      response => panic!("Unexpected response {:?} to the request for vote", response)
+    }
+  }
+
+  #[test]
+  fn isLogUpToDate( ) -> Result<( )> {
+    let cluster= newCluster( )?;
+    let node= follower(&cluster, 1);
+
+    // Candidate's last entry, and whether its log is atleast as up-to-date as the node's.
+    let cases= [
+      ((3, 2), true),
+
+      // A longer log ending with the same term, or a log ending with a later term.
+      ((4, 2), true),
+      ((2, 3), true),
+
+      // A log ending with an earlier term (however long it is) is stale, as is a shorter log ending
+      // with the same term.
+      ((10, 1), false),
+      ((2, 2), false)
+    ];
+    for ((lastLogIndex, lastLogTerm), upToDate) in cases {
+      assert_eq!(node.isLogUpToDate(lastLogIndex, lastLogTerm), upToDate, "log ending at index {} of term {}", lastLogIndex, lastLogTerm);
+      assert_eq!(node.shouldVoteFor(NodeId(2), lastLogIndex, lastLogTerm), upToDate, "log ending at index {} of term {}", lastLogIndex, lastLogTerm);
+    }
+    Ok(( ))
+  }
+
+  #[test]
+  fn refusesCandidateWithStaleLog( ) -> Result<( )> {
+    let mut cluster= newCluster( )?;
+
+    // Stale by term, and then by index.
+    assert!(!requestVote(&mut cluster, 2, 3, 10, 1)?);
+    assert!(!requestVote(&mut cluster, 2, 3, 2, 2)?);
+
+    // The node steps into the candidate's term, without voting.
+    assert_eq!(cluster.node(1).currentTerm( ), 3);
+    assert_eq!(cluster.nodeMut(1).logMut( ).getCurrentTermAndCastVote( )?, (3, None));
+
+    assert!(requestVote(&mut cluster, 3, 3, 3, 2)?);
+    Ok(( ))
+  }
+
+  #[test]
+  fn votesOnceInTerm( ) -> Result<( )> {
+    let mut cluster= newCluster( )?;
+
+    assert!(requestVote(&mut cluster, 2, 3, 3, 2)?);
+    assert_eq!(cluster.nodeMut(1).logMut( ).getCurrentTermAndCastVote( )?, (3, Some(NodeId(2))));
+
+    // Having voted for node 2, the node refuses another candidate with an up-to-date log.
+    assert!(!follower(&cluster, 1).shouldVoteFor(NodeId(3), 3, 2));
+    assert!(!requestVote(&mut cluster, 3, 3, 4, 2)?);
+
+    // But grants the vote again to node 2, like when the response got lost and node 2 asks again.
+    assert!(follower(&cluster, 1).shouldVoteFor(NodeId(2), 3, 2));
+    assert!(requestVote(&mut cluster, 2, 3, 3, 2)?);
+
+    // The vote of a later term is free.
+    assert!(requestVote(&mut cluster, 3, 4, 3, 2)?);
+    assert_eq!(cluster.nodeMut(1).logMut( ).getCurrentTermAndCastVote( )?, (4, Some(NodeId(3))));
+    Ok(( ))
+  }
+
+  #[test]
+  fn keepsVoteAcrossRestart( ) -> Result<( )> {
+    let mut cluster= newCluster( )?;
+    assert!(requestVote(&mut cluster, 2, 3, 3, 2)?);
+
+    cluster.restart(1)?;
+    assert_eq!(cluster.node(1).currentTerm( ), 3);
+
+    assert!(!requestVote(&mut cluster, 3, 3, 3, 2)?);
+    assert!(requestVote(&mut cluster, 2, 3, 3, 2)?);
+    Ok(( ))
+  }
+
+  #[test]
+  fn doesNotGrantVoteIfStoringItFails( ) -> Result<( )> {
+    let mut cluster= newCluster( )?;
+
+    // The node steps into term 3, refusing a stale candidate.
+    assert!(!requestVote(&mut cluster, 2, 3, 2, 2)?);
+
+    // The vote for node 2 can't be stored, so the node fails without granting it.
+    cluster.engine(1).failFlushes(true);
+    let request= message(2, 1, 3, MessagePayload::RequestVote { lastLogIndex: 3, lastLogTerm: 2 });
+    assert!(matches!(cluster.receive(request), Err(Error::Internal(_))));
+    assert!(cluster.inflight.is_empty( ));
+
+    // It restarts in term 3 without a vote. So it can vote for another candidate, without having
+    // voted twice.
+    cluster.restart(1)?;
+    assert_eq!(cluster.nodeMut(1).logMut( ).getCurrentTermAndCastVote( )?, (3, None));
+    assert!(requestVote(&mut cluster, 3, 3, 3, 2)?);
+    Ok(( ))
+  }
+
+  #[test]
+  fn electsItselfInSingleNodeCluster( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(1)?;
+
+    for _ in 1..ELECTION_TIMEOUT_RANGE.start {
+      cluster.tick(1)?;}
+    assert!(matches!(cluster.node(1), Node::Follower(_)));
+
+    // Once its election timer runs out, the node wins the election by its own vote (without a
+    // pre-vote, or any other message).
+    let remainingTicks= (ELECTION_TIMEOUT_RANGE.end - ELECTION_TIMEOUT_RANGE.start) as u64;
+    assert!(cluster.tickUntil(1, remainingTicks, |cluster| matches!(cluster.node(1), Node::Leader(_)))?);
+
+    assert_eq!(cluster.node(1).currentTerm( ), 1);
+    assert!(cluster.inflight.is_empty( ));
+
+    // And commits the noop entry of its term right away.
+    let log= cluster.node(1).log( );
+    assert_eq!(log.getLastStoredEntryIndexAndTerm( ), (2, 1));
+    assert_eq!(log.getCommitIndexAndTerm( ), (2, 1));
+    Ok(( ))
+  }
 }
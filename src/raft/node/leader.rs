@@ -1,5 +1,14 @@
-use crate::result::Result;
-use super::{GenericNode, Role};
+use std::collections::{HashMap, HashSet, VecDeque};
+use tracing::{info, warn};
+use crate::{
+  raft::{
+    cluster_config::ClusterConfig,
+    message::{Message, MessageAddress, MessagePayload, Request, RequestId, Response},
+    state_machine_driver::StateMachineInstruction, types::{LogEntryIndex, NodeId, ReadSequence, Term, Ticks}
+  },
+  result::{Error, ErrorCode, Result}
+};
+use super::{follower::Follower, GenericNode, Node, Role, RoleName, ELECTION_TIMEOUT_RANGE};
 
 /*
   Once a leader has been elected, it begins servicing client requests. Each client request contains
@@ -12,13 +21,152 @@ use super::{GenericNode, Role};
   If followers crash or run slowly, or if network packets are lost, the leader retries AppendEntries
   RPCs indefinitely (even after it has responded to the client) until all followers eventually store
   all log entries.
+
+  The leader also changes the membership of the cluster, one node at a time (see addNode( ) and
+  removeNode( )). A new node votes (and counts towards the quorum) right away, before it has caught
+  up with the log. So, while it's catching up, the cluster tolerates one less failure.
+
+  A lagging peer (like one which has just joined, or has been unreachable for a while) is caught up
+  in successive AppendEntries, each carrying a bounded chunk of the entries it's missing (see
+  ReplicationLimits). They're pipelined : the next one is sent without waiting for the response to
+  the previous one, upto a window of them in flight per peer.
+
+  The leader holds a lease, as long as a quorum of the nodes has responded to it within the lease
+  timeout (see hasLeaseQuorum( )). A leader cut off from the quorum might have been deposed already,
+  without knowing about it. So once its lease runs out, it rejects the client requests and steps
+  down - rather than serving stale reads, or accepting writes which would never get committed.
+
+  The leader hands its leadership over to a peer on request (see transferLeadership( )), like before
+  it's shut down for maintenance. So the cluster doesn't wait out an election timeout.
 */
 #[derive(Default)]
-pub struct Leader { }
+pub struct Leader {
+  // Index of the next entry to be sent to each peer.
+  nextIndex: HashMap<NodeId, LogEntryIndex>,
+
+  // Index of the highest entry known to be replicated on each peer.
+  matchIndex: HashMap<NodeId, LogEntryIndex>,
+
+  // The AppendEntries (or snapshots) sent to each peer, which it's yet to respond to - as the indices
+  // of their first and last entries, in the order they were sent.
+  inflight: HashMap<NodeId, VecDeque<(LogEntryIndex, LogEntryIndex)>>,
+
+  // Peers whose logs the leader is yet to find the point of agreement with (like right after it's
+  // elected, or after a peer rejects the sent entries). Only a single AppendEntries is in flight to
+  // each of them.
+  probing: HashSet<NodeId>,
+
+  // Match index of each peer, as of the previous heartbeat.
+  matchIndexAtLastHeartbeat: HashMap<NodeId, LogEntryIndex>,
+
+  timeSinceLastHeartbeat: Ticks,
+
+  // Number of ticks since the node became the leader, and the tick at which each peer last
+  // responded to it (to a heartbeat or to an AppendEntries). NOTE : These are u64s, since Ticks is
+  // too narrow to count the whole term of a leader.
+  ticks: u64,
+  lastResponseTicks: HashMap<NodeId, u64>,
+
+  // Clients waiting for the entries (proposed on their behalf) to get committed and applied.
+  pendingRequests: HashMap<LogEntryIndex, (MessageAddress, RequestId)>,
+
+  // Index of the noop entry appended at the beginning of the leader's term. Reads must observe
+  // atleast this entry, since the commit index inherited from previous terms might be stale.
+  pub(in crate::raft) termStartIndex: LogEntryIndex,
+
+  // The latest read sequence number and the highest one acknowledged by each peer.
+  readSequence: ReadSequence,
+  acknowledgedReadSequence: HashMap<NodeId, ReadSequence>,
+
+  // Reads waiting for a quorum of the nodes to confirm the node's leadership.
+  pendingReads: VecDeque<PendingRead>,
+
+  // The leadership transfer in progress, if any.
+  transfer: Option<Transfer>
+}
+
+struct Transfer {
+  target: NodeId,
+
+  // The client which requested the transfer.
+  client: MessageAddress,
+  id: RequestId,
+
+  // Number of ticks left, before the transfer is aborted.
+  remainingTicks: Ticks,
+
+  // Whether the target has been told to start an election (see MessagePayload::TimeoutNow).
+  timeoutNowSent: bool,
+
+  // Requests proposing entries, which are held back while the transfer is in progress (along with
+  // the clients which sent them).
+  heldRequests: Vec<(MessageAddress, RequestId, Request)>
+}
+
+struct PendingRead {
+  sequence: ReadSequence,
+
+  id: RequestId,
+  client: MessageAddress,
+  command: Vec<u8>,
+
+  // The read must observe all the entries upto this index.
+  index: LogEntryIndex
+}
+
+// Limits on the AppendEntries sent to a peer, and on the proposals the leader has in flight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplicationLimits {
+  // Maximum number of entries in an AppendEntries, and their maximum total size (in bytes). An entry
+  // larger than maxBytes is still sent, on its own.
+  pub maxEntries: usize,
+  pub maxBytes: usize,
+
+  // Maximum number of AppendEntries in flight to a peer.
+  pub maxInflight: usize,
+
+  // Maximum number of proposals (client requests awaiting their entries to get applied, including
+  // the ones held back during a leadership transfer). Beyond it, new mutations are rejected with
+  // ServerBusy, instead of piling up in the log faster than it's replicated and compacted.
+  pub maxPendingProposals: usize
+}
+
+impl Default for ReplicationLimits {
+  fn default( ) -> Self {
+    Self { maxEntries: 1000, maxBytes: 1024 * 1024, maxInflight: 8, maxPendingProposals: 1000 }
+  }
+}
+
+// Number of ticks between consecutive heartbeats.
+// NOTE : Must be less than the election timeout, so that the followers don't start elections
+// while the leader is alive.
+pub const HEARTBEAT_INTERVAL: Ticks= 3;
+
+// Number of ticks within which a quorum must respond to the leader, for it to keep its lease.
+// NOTE : Must not exceed the (minimum) election timeout, since the followers which haven't heard
+// from the leader for that long might elect a new leader.
+const LEASE_TIMEOUT: Ticks= ELECTION_TIMEOUT_RANGE.start;
+
+// Number of ticks within which the target of a leadership transfer must catch up and get elected,
+// before the transfer is aborted.
+const TRANSFER_TIMEOUT: Ticks= ELECTION_TIMEOUT_RANGE.end;
 
 impl Leader {
-  pub fn new( ) -> Self {
-    Self { }
+  pub fn new(peers: &HashSet<NodeId>, lastLogIndex: LogEntryIndex) -> Self {
+    Self {
+      // Initially, the leader assumes that every peer's log matches its own.
+      nextIndex: peers.iter( ).map(|peer| (*peer, lastLogIndex + 1)).collect( ),
+      matchIndex: peers.iter( ).map(|peer| (*peer, 0)).collect( ),
+      probing: peers.clone( ),
+
+      acknowledgedReadSequence: peers.iter( ).map(|peer| (*peer, 0)).collect( ),
+
+      // The peers which voted for the node have just responded to it. The others get the same
+      // grace period.
+      lastResponseTicks: peers.iter( ).map(|peer| (*peer, 0)).collect( ),
+
+      ..Default::default( )
+    }
   }
 }
 
@@ -27,6 +175,795 @@ impl Role for Leader { }
 impl GenericNode<Leader> {
   // Broadcasts a heartbeat to all peers.
   pub fn broadcastHeartbeat(&mut self) -> Result<( )> {
-    unimplemented!( )
+    let (commitIndex, commitTerm)= self.log.getCommitIndexAndTerm( );
+    let readSequence= self.role.readSequence;
+
+    self.send(MessageAddress::Broadcast, MessagePayload::Heartbeat { commitIndex, commitTerm, readSequence })
+  }
+
+  // Appends a command (proposed on behalf of the client request with the given id, if any) to the
+  // log and replicates it to the peers. Returns the index of the corresponding log entry.
+  pub fn propose(&mut self, command: Option<Vec<u8>>, requestId: Option<RequestId>) -> Result<LogEntryIndex> {
+    let index= self.log.append(self.currentTerm, command, requestId)?;
+    self.replicate( )?;
+
+    Ok(index)
+  }
+
+  /*
+    Adds a node (listening for Raft messages on the given address) to the cluster, by appending a
+    configuration entry with the new membership. Returns the index of the entry.
+
+    The new configuration takes effect right away : the entries are replicated to the new node, and
+    it's counted in the quorum of the following entries (including the configuration entry itself).
+  */
+  pub fn addNode(&mut self, id: NodeId, address: String) -> Result<LogEntryIndex> {
+    let mut config= self.getConfigToChange( )?;
+
+    if config.nodes.insert(id, address).is_some( ) {
+      return Err(Error::Value(ErrorCode::DuplicateObject, format!("Node {} is a member of the cluster already", id)))}
+
+    info!("Adding node {} to the cluster", id);
+    self.proposeConfig(config)
+  }
+
+  /*
+    Removes a node from the cluster, by appending a configuration entry with the new membership.
+    Returns the index of the entry.
+
+    The new configuration takes effect right away : the leader stops replicating to the removed
+    node. The leader can remove itself as well. It then keeps serving (without counting itself in
+    the quorum) until the entry is committed, and steps down after that.
+
+    NOTE : The removed node doesn't get to know about its removal, and should be shut down.
+  */
+  pub fn removeNode(&mut self, id: NodeId) -> Result<LogEntryIndex> {
+    let mut config= self.getConfigToChange( )?;
+
+    if config.nodes.remove(&id).is_none( ) {
+      return Err(Error::Value(ErrorCode::UndefinedObject, format!("Node {} isn't a member of the cluster", id)))}
+
+    if config.nodes.is_empty( ) {
+      return Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, "Can't remove the only node of the cluster".to_string( )))}
+
+    info!("Removing node {} from the cluster", id);
+    self.proposeConfig(config)
+  }
+
+  /*
+    Transfers the leadership to the given node (or to the peer with the most entries replicated, if
+    none is given), on behalf of the client request with the given id.
+
+    The leader stops proposing entries : the requests which would, are held back until the transfer
+    ends. Once the target has caught up with the log, it's sent a TimeoutNow, making it start an
+    election right away. The leader steps down on discovering the target's term, answering the
+    client and handing the held requests of its local clients over to the new leader.
+
+    If the target doesn't get elected within TRANSFER_TIMEOUT ticks (like when it can't catch up),
+    the transfer is aborted with an error, and the leader serves the held requests.
+  */
+  pub fn transferLeadership(&mut self, client: MessageAddress, id: RequestId, target: Option<NodeId>) -> Result<( )> {
+    let respond= |response| self.send(client, MessagePayload::ResponseToClient { id: id.clone( ), response });
+
+    // Ties are broken by the lowest id, so that the pick is deterministic.
+    let mostUpToDatePeer= self.role.matchIndex.iter( )
+                                              .max_by_key(|(peer, matchIndex)| (**matchIndex, std::cmp::Reverse(**peer)))
+                                              .map(|(peer, _)| *peer);
+
+    let target= match target.or(mostUpToDatePeer) {
+      Some(target) if target == self.id => return respond(Ok(Response::TransferLeadership)),
+
+      Some(target) if self.peers( ).contains(&target) => target,
+
+      Some(target) =>
+        return respond(Err(Error::Value(ErrorCode::UndefinedObject, format!("Node {} isn't a member of the cluster", target)))),
+
+      None =>
+        return respond(Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, "There's no other node to transfer the leadership to".to_string( ))))
+    };
+
+    info!("Transferring the leadership to node {}", target);
+    self.role.transfer= Some(Transfer {
+      target,
+      client,
+      id,
+      remainingTicks: TRANSFER_TIMEOUT,
+      timeoutNowSent: false,
+      heldRequests: Vec::new( )
+    });
+
+    self.sendAppendEntries(target)?;
+    self.advanceTransfer( )
+  }
+
+  // Processes a message received by the leader.
+  pub fn processMessage(mut self, message: Message) -> Result<Node> {
+    message.validate( )?;
+
+    // The node discovered a new term. It steps down and enters into that term as a leaderless
+    // follower.
+    if message.currentTermOfSender > self.currentTerm {
+      return self.becomeFollower(message.currentTermOfSender)?.processMessage(message)}
+
+    // Messages from previous terms are stale and thus ignored.
+    // NOTE : Client requests and responses aren't bound to terms.
+    if (message.currentTermOfSender < self.currentTerm) && !message.isClientMessage( ) {
+      return Ok(self.into( ))}
+
+    if let (MessageAddress::Node(peer), MessagePayload::HeartbeatResponse { .. } | MessagePayload::AppendEntriesResponse { .. })=
+      (message.from, &message.payload)
+    {
+      self.role.lastResponseTicks.insert(peer, self.role.ticks);}
+
+    match (message.from, message.payload) {
+      (MessageAddress::Node(peer), MessagePayload::AppendEntriesResponse { success: true, lastLogIndex }) => {
+        // Responses can arrive out of order (or more than once), so the match index should never
+        // regress.
+        let matchIndex= self.role.matchIndex.entry(peer).or_default( );
+        *matchIndex= lastLogIndex.max(*matchIndex);
+        let matchIndex= *matchIndex;
+
+        // The AppendEntries upto the match index have been responded to.
+        let inflight= self.role.inflight.entry(peer).or_default( );
+        while inflight.front( ).is_some_and(|(_, lastIndex)| *lastIndex <= matchIndex) {
+          inflight.pop_front( );}
+
+        self.role.probing.remove(&peer);
+
+        let nextIndex= self.role.nextIndex.entry(peer).or_default( );
+        *nextIndex= (matchIndex + 1).max(*nextIndex);
+
+        self.commitReplicatedEntries( )?;
+
+        // The window has room for more entries.
+        self.sendAppendEntries(peer)?;
+
+        self.advanceTransfer( )?;
+      },
+
+      // The peer's log doesn't contain the entry preceding the sent entries. The AppendEntries in
+      // flight are given up on, and the peer is probed backwards (starting before the first of them,
+      // and skipping past the peer's last stored entry) one AppendEntries at a time.
+      (MessageAddress::Node(peer), MessagePayload::AppendEntriesResponse { success: false, lastLogIndex }) => {
+        let inflight= std::mem::take(self.role.inflight.entry(peer).or_default( ));
+
+        let nextIndex= self.role.nextIndex.entry(peer).or_insert(1);
+        let firstSentIndex= inflight.front( ).map_or(*nextIndex, |(firstIndex, _)| *firstIndex);
+        *nextIndex= (firstSentIndex - 1).min(lastLogIndex + 1).max(1);
+
+        self.role.probing.insert(peer);
+        self.sendAppendEntries(peer)?;
+      },
+
+      (MessageAddress::Node(peer), MessagePayload::HeartbeatResponse { readSequence }) => {
+        let acknowledgedReadSequence= self.role.acknowledgedReadSequence.entry(peer).or_default( );
+        *acknowledgedReadSequence= readSequence.max(*acknowledgedReadSequence);
+
+        self.executeConfirmedReads( )?;
+      },
+
+      (from @ MessageAddress::Node(_), MessagePayload::RequestVote { .. }) =>
+        self.send(from, MessagePayload::RequestVoteResponse { voteGranted: false })?,
+
+      // The leader is alive, so there's no need for an election.
+      (from @ MessageAddress::Node(_), MessagePayload::PreVote { .. }) =>
+        self.send(from, MessagePayload::PreVoteResponse { voteGranted: false })?,
+
+      // Votes may arrive after the node has won the election.
+      (_, MessagePayload::RequestVoteResponse { .. } | MessagePayload::PreVoteResponse { .. }) => { },
+
+      (MessageAddress::Node(node),
+       MessagePayload::Heartbeat { .. } | MessagePayload::AppendEntries { .. } | MessagePayload::InstallSnapshot { .. }) =>
+        return Err(Error::Internal(format!("Node {} claims to be the leader of term {} as well", node, self.currentTerm))),
+
+      (MessageAddress::Client, MessagePayload::ClientRequest { id, request: Request::Status }) => {
+        let leader= self.id;
+        self.respondWithStatus(id, RoleName::Leader, Some(leader))?;
+      },
+
+      (MessageAddress::Client, MessagePayload::ClientRequest { id, request: Request::LocalQuery { command, index } }) =>
+        self.queryLocally(id, command, index)?,
+
+      // Requests from clients (directly or forwarded by followers).
+      (from, MessagePayload::ClientRequest { id, request }) =>
+        self.serveClientRequest(from, id, request)?,
+
+      (from, payload) => warn!("Leader received unexpected message {:?} from {:?}", payload, from)
+    }
+
+    Ok(self.into( ))
+  }
+
+  // Moves the logical clock by a tick. Heartbeats are sent periodically, along with retrying
+  // replication to the lagging peers which haven't acknowledged any entries since the previous
+  // heartbeat (their AppendEntries in flight might have been lost).
+  pub fn tick(mut self) -> Result<Node> {
+    // The leader has been removed from the cluster, and the removal is committed.
+    let (configIndex, _)= self.log.getConfig( );
+    let (commitIndex, _)= self.log.getCommitIndexAndTerm( );
+    if !self.isMember( ) && (configIndex <= commitIndex) {
+      return Ok(self.stepDown("Removed from the cluster")?.into( ))}
+
+    self.role.ticks += 1;
+    if !self.hasLeaseQuorum( ) {
+      warn!("A quorum hasn't responded within {} ticks", LEASE_TIMEOUT);
+      return Ok(self.stepDown("Lease expired")?.into( ))
+    }
+
+    if let Some(transfer)= &mut self.role.transfer {
+      transfer.remainingTicks -= 1;
+      if transfer.remainingTicks == 0 {
+        self.abortTransfer( )?;}
+    }
+
+    self.role.timeSinceLastHeartbeat += 1;
+
+    if self.role.timeSinceLastHeartbeat >= HEARTBEAT_INTERVAL {
+      self.role.timeSinceLastHeartbeat= 0;
+      self.broadcastHeartbeat( )?;
+
+      let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+      let laggingPeers: Vec<NodeId>= self.role.matchIndex.iter( )
+                                                         .filter(|(_, matchIndex)| **matchIndex < lastLogIndex)
+                                                         .map(|(peer, _)| *peer)
+                                                         .collect( );
+      let matchIndexAtLastHeartbeat= std::mem::replace(&mut self.role.matchIndexAtLastHeartbeat, self.role.matchIndex.clone( ));
+      for peer in laggingPeers {
+        if matchIndexAtLastHeartbeat.get(&peer) == self.role.matchIndex.get(&peer) {
+          let inflight= self.role.inflight.entry(peer).or_default( );
+          if let Some((firstIndex, _))= inflight.front( ) {
+            self.role.nextIndex.insert(peer, *firstIndex);}
+          inflight.clear( );
+        }
+
+        self.sendAppendEntries(peer)?;
+      }
+    }
+
+    Ok(self.into( ))
+  }
+
+  // Transitions the leader to a leaderless follower, after it discovers a new term.
+  pub(in crate::raft) fn becomeFollower(mut self, currentTerm: Term) -> Result<GenericNode<Follower>> {
+    assert!(currentTerm > self.currentTerm,
+            "Leader can only step down into a higher term, not from {} to {}", self.currentTerm, currentTerm);
+
+    info!("Discovered new term {} | Stepping down as leader", currentTerm);
+    self.endTransfer( )?;
+    self.abortPendingRequests( )?;
+
+    self.log.setCurrentTermAndCastVote(currentTerm, None)?;
+    self.currentTerm= currentTerm;
+
+    let electionTimeout= self.randomElectionTimeout( );
+    Ok(self.changeRole(Follower::new(None, None, electionTimeout)))
+  }
+
+  // Transitions the leader to a leaderless follower in the current term, after its removal from
+  // the cluster has been committed or its lease has expired. A new leader is elected, once the
+  // election timers run out.
+  fn stepDown(mut self, reason: &str) -> Result<GenericNode<Follower>> {
+    info!("{} | Stepping down as leader of term {}", reason, self.currentTerm);
+    self.endTransfer( )?;
+    self.abortPendingRequests( )?;
+
+    // The leader had voted for itself in the current term.
+    let castVote= Some(self.id);
+    let electionTimeout= self.randomElectionTimeout( );
+    Ok(self.changeRole(Follower::new(None, castVote, electionTimeout)))
+  }
+}
+
+impl GenericNode<Leader> {
+  // Serves a request from a client (directly or forwarded by a follower), or queued during the
+  // election the node won.
+  pub(in crate::raft) fn serveClientRequest(&mut self, from: MessageAddress, id: RequestId, request: Request) -> Result<( )> {
+    match request {
+      // Without a lease, the node can't be sure that it's still the leader. The request is retried,
+      // once the node steps down (on its next tick) and a leader gets elected.
+      Request::Query(_) | Request::Mutate(_) | Request::AddNode { .. } | Request::RemoveNode(_) | Request::TransferLeadership(_)
+        if !self.hasLeaseQuorum( ) => self.send(from, MessagePayload::ResponseToClient { id, response: Err(Error::Abort) })?,
+
+      // Load shedding : the client is expected to back off and retry.
+      // NOTE : Membership changes and transfers aren't shed, since they're rare and needed to
+      // recover the cluster.
+      Request::Mutate(_) if self.pendingProposals( ) >= self.replicationLimits.maxPendingProposals => {
+        self.rejectedProposals += 1;
+
+        let error= Error::Value(ErrorCode::ServerBusy, format!(
+          "Leader {} has {} proposals in flight : retry later", self.id, self.pendingProposals( )
+        ));
+        self.send(from, MessagePayload::ResponseToClient { id, response: Err(error) })?;
+      },
+
+      // No entries are proposed while the leadership is being transferred, so that the target can
+      // catch up with the log.
+      request @ (Request::Mutate(_) | Request::AddNode { .. } | Request::RemoveNode(_) | Request::TransferLeadership(_))
+        if self.role.transfer.is_some( ) =>
+      {
+        if let Some(transfer)= &mut self.role.transfer {
+          transfer.heldRequests.push((from, id, request));}
+      },
+
+      Request::TransferLeadership(target) => self.transferLeadership(from, id, target)?,
+
+      // The client is tracked before proposing the entry, since in a single node cluster the entry
+      // gets committed right away.
+      // The request id is stored in the entry, so that a retried request doesn't get applied twice.
+      Request::Mutate(command) => {
+        let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+        self.role.pendingRequests.insert(lastLogIndex + 1, (from, id.clone( )));
+
+        self.propose(Some(command), Some(id))?;
+      },
+
+      // The client is answered once the configuration entry is committed, or right away if the
+      // change is rejected.
+      request @ (Request::AddNode { .. } | Request::RemoveNode(_)) => {
+        let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+        self.role.pendingRequests.insert(lastLogIndex + 1, (from, id.clone( )));
+
+        let result= match request {
+          Request::AddNode { id: node, address } => self.addNode(node, address),
+          Request::RemoveNode(node) => self.removeNode(node),
+          _ => unreachable!( )
+        };
+
+        match result {
+          Ok(_) => { },
+          Err(error @ Error::Internal(_)) => return Err(error),
+
+          Err(error) => {
+            self.role.pendingRequests.remove(&(lastLogIndex + 1));
+            self.send(from, MessagePayload::ResponseToClient { id, response: Err(error) })?;
+          }
+        }
+      },
+
+      /*
+        Read-only requests don't go through the log. But the leader can't serve them right away,
+        since it might have been deposed (without knowing about it yet) - serving the read would
+        then return stale data. So the read is assigned a sequence number, which is sent to the
+        peers along with a heartbeat. The read is served once a quorum of the nodes acknowledge the
+        heartbeat (confirming the node's leadership at the time of the read).
+      */
+      Request::Query(command) => {
+        self.role.readSequence += 1;
+
+        let (commitIndex, _)= self.log.getCommitIndexAndTerm( );
+        self.role.pendingReads.push_back(PendingRead {
+          sequence: self.role.readSequence,
+
+          id,
+          client: from,
+          command,
+
+          index: commitIndex.max(self.role.termStartIndex)
+        });
+
+        self.broadcastHeartbeat( )?;
+
+        // In a single node cluster, the read is confirmed right away.
+        self.executeConfirmedReads( )?;
+      },
+
+      // Status requests and local queries are only served for the local clients.
+      request @ (Request::Status | Request::LocalQuery { .. }) =>
+        warn!("Leader received unexpected client request {:?} from {:?}", request, from)
+    }
+
+    Ok(( ))
+  }
+
+  // Returns whether the leader holds its lease : whether a quorum of the nodes (including the leader
+  // itself, if it's a member) has responded to it within the last LEASE_TIMEOUT ticks.
+  pub fn hasLeaseQuorum(&self) -> bool {
+    let ticks= self.role.ticks;
+    let responded= self.role.lastResponseTicks.iter( )
+                                              .filter(|(_, lastResponseTick)| ticks - **lastResponseTick < LEASE_TIMEOUT as u64)
+                                              .map(|(peer, _)| peer)
+                                              .chain(Some(&self.id).filter(|_| self.isMember( )));
+
+    self.log.getConfig( ).1.isQuorum(responded)
+  }
+
+  // Returns the number of the proposals in flight (see ReplicationLimits::maxPendingProposals).
+  fn pendingProposals(&self) -> usize {
+    self.role.pendingRequests.len( ) + self.role.transfer.as_ref( ).map_or(0, |transfer| transfer.heldRequests.len( ))
+  }
+
+  // Replicates the newly appended entries to the peers, and commits them if they're replicated
+  // enough already (like in a single node cluster).
+  fn replicate(&mut self) -> Result<( )> {
+    for peer in self.peers( ) {
+      self.sendAppendEntries(peer)?;}
+
+    self.commitReplicatedEntries( )
+  }
+
+  /*
+    Returns the configuration in effect, if the membership can be changed. Only a single change
+    (of one node) is allowed at a time : a change is rejected, while the previous one is yet to be
+    committed.
+
+    The leader must also have committed an entry of its term, since a configuration entry of a
+    previous leader (that this one doesn't know of) might have been committed otherwise.
+  */
+  fn getConfigToChange(&self) -> Result<ClusterConfig> {
+    let (configIndex, config)= self.log.getConfig( );
+    let (commitIndex, _)= self.log.getCommitIndexAndTerm( );
+
+    if configIndex > commitIndex {
+      return Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, "Another membership change is in progress, which is yet to be committed".to_string( )))}
+
+    if self.role.termStartIndex > commitIndex {
+      return Err(Error::Abort)}
+
+    Ok(config.clone( ))
+  }
+
+  // Appends a configuration entry, and replicates it to the members of the new configuration.
+  // Returns the index of the entry.
+  fn proposeConfig(&mut self, config: ClusterConfig) -> Result<LogEntryIndex> {
+    let index= self.log.appendConfig(self.currentTerm, config)?;
+
+    // The removed peers aren't tracked anymore, and the added ones are tracked like the others
+    // were when the node became the leader.
+    let peers= self.peers( );
+    let role= &mut self.role;
+
+    role.nextIndex.retain(|peer, _| peers.contains(peer));
+    role.matchIndex.retain(|peer, _| peers.contains(peer));
+    role.inflight.retain(|peer, _| peers.contains(peer));
+    role.probing.retain(|peer| peers.contains(peer));
+    role.matchIndexAtLastHeartbeat.retain(|peer, _| peers.contains(peer));
+    role.acknowledgedReadSequence.retain(|peer, _| peers.contains(peer));
+    role.lastResponseTicks.retain(|peer, _| peers.contains(peer));
+
+    for peer in &peers {
+      if !role.nextIndex.contains_key(peer) {
+        role.probing.insert(*peer);}
+
+      role.nextIndex.entry(*peer).or_insert(index);
+      role.matchIndex.entry(*peer).or_insert(0);
+      role.acknowledgedReadSequence.entry(*peer).or_insert(0);
+      role.lastResponseTicks.entry(*peer).or_insert(role.ticks);
+    }
+
+    self.replicate( )?;
+
+    // Pending reads might be confirmed by the new quorum.
+    self.executeConfirmedReads( )?;
+
+    Ok(index)
+  }
+
+  // Sends a TimeoutNow to the target of the leadership transfer in progress (if any), once it has
+  // caught up with the log.
+  fn advanceTransfer(&mut self) -> Result<( )> {
+    let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+
+    let Some(transfer)= &mut self.role.transfer else {
+      return Ok(( ))};
+
+    if transfer.timeoutNowSent || (self.role.matchIndex.get(&transfer.target) < Some(&lastLogIndex)) {
+      return Ok(( ))}
+
+    transfer.timeoutNowSent= true;
+    let target= transfer.target;
+
+    info!("Node {} has caught up with the log | Asking it to start an election", target);
+    self.send(MessageAddress::Node(target), MessagePayload::TimeoutNow)
+  }
+
+  // Aborts the leadership transfer in progress, since the target didn't get elected in time. The
+  // leader resumes serving the requests, starting with the ones held back during the transfer.
+  fn abortTransfer(&mut self) -> Result<( )> {
+    let Some(transfer)= self.role.transfer.take( ) else {
+      return Ok(( ))};
+
+    let reason= match transfer.timeoutNowSent {
+      true => "it didn't get elected",
+      false => "it didn't catch up with the log"
+    };
+    warn!("Aborting the leadership transfer to node {}, since {}", transfer.target, reason);
+
+    let error= Error::Value(ErrorCode::ObjectNotInPrerequisiteState, format!(
+      "Leadership transfer to node {} timed out, since {} within {} ticks", transfer.target, reason, TRANSFER_TIMEOUT
+    ));
+    self.send(transfer.client, MessagePayload::ResponseToClient { id: transfer.id, response: Err(error) })?;
+
+    for (client, id, request) in transfer.heldRequests {
+      self.serveClientRequest(client, id, request)?;}
+
+    Ok(( ))
+  }
+
+  /*
+    Ends the leadership transfer in progress (if any), as the leader steps down. The transfer has
+    succeeded if the target was told to start an election (since the leader steps down on discovering
+    a new term, which the target has most likely started).
+
+    The held requests of the local clients are queued, to be forwarded to the new leader once it's
+    known. The ones forwarded by the followers are rejected, so that they're retried.
+  */
+  fn endTransfer(&mut self) -> Result<( )> {
+    let Some(transfer)= self.role.transfer.take( ) else {
+      return Ok(( ))};
+
+    let response= match transfer.timeoutNowSent {
+      true => Ok(Response::TransferLeadership),
+      false => Err(Error::Abort)
+    };
+    self.send(transfer.client, MessagePayload::ResponseToClient { id: transfer.id, response })?;
+
+    for (client, id, request) in transfer.heldRequests {
+      match client {
+        MessageAddress::Client => self.queueRequest(id, request),
+        client => self.send(client, MessagePayload::ResponseToClient { id, response: Err(Error::Abort) })?
+      }
+    }
+
+    Ok(( ))
+  }
+
+  // Rejects the client requests waiting on the leader, since the pending entries might never get
+  // committed and the pending reads can't be confirmed anymore.
+  fn abortPendingRequests(&mut self) -> Result<( )> {
+    for (_, (client, id)) in std::mem::take(&mut self.role.pendingRequests) {
+      self.send(client, MessagePayload::ResponseToClient { id, response: Err(Error::Abort) })?;}
+
+    for read in std::mem::take(&mut self.role.pendingReads) {
+      self.send(read.client, MessagePayload::ResponseToClient { id: read.id, response: Err(Error::Abort) })?;}
+
+    self.stateMachineInstructor.send(StateMachineInstruction::Abort)
+                               .map_err(|_| Error::Internal("State-machine driver has shut down".into( )))
+  }
+
+  // Sends the entries the peer is yet to be sent (starting from its next index) in successive
+  // AppendEntries, as long as the window of the ones in flight to the peer has room.
+  fn sendAppendEntries(&mut self, peer: NodeId) -> Result<( )> {
+    let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+    let window= match self.role.probing.contains(&peer) {
+      true => 1,
+      false => self.replicationLimits.maxInflight
+    };
+
+    loop {
+      let nextIndex= *self.role.nextIndex.get(&peer)
+                                          .ok_or_else(| | Error::Internal(format!("Unknown peer {}", peer)))?;
+
+      if (nextIndex > lastLogIndex) || (self.role.inflight.entry(peer).or_default( ).len( ) >= window) {
+        return Ok(( ))}
+
+      let lastSentIndex= self.sendEntriesFrom(peer, nextIndex)?;
+
+      self.role.inflight.entry(peer).or_default( ).push_back((nextIndex, lastSentIndex));
+      self.role.nextIndex.insert(peer, lastSentIndex + 1);
+    }
+  }
+
+  // Sends an AppendEntries, with the entries starting from the given index (within the replication
+  // limits), to the peer. If those entries have been compacted away, the snapshot is sent instead.
+  // Returns the index of the last entry sent.
+  fn sendEntriesFrom(&mut self, peer: NodeId, nextIndex: LogEntryIndex) -> Result<LogEntryIndex> {
+    let (snapshotIndex, _)= self.log.getSnapshotIndexAndTerm( );
+    if nextIndex <= snapshotIndex {
+      let snapshot= self.log.getSnapshot( )?
+                            .ok_or_else(| | Error::Internal(format!("Snapshot upto index {} not found", snapshotIndex)))?;
+
+      self.send(MessageAddress::Node(peer), MessagePayload::InstallSnapshot { snapshot })?;
+      return Ok(snapshotIndex)
+    }
+
+    let prevLogIndex= nextIndex - 1;
+    let prevLogTerm= match self.log.getTerm(prevLogIndex)? {
+      Some(term) => term,
+      None if prevLogIndex == 0 => 0,
+      None => return Err(Error::Internal(format!("Missing log entry at index {}", prevLogIndex)))
+    };
+
+    let ReplicationLimits { maxEntries, maxBytes, .. }= self.replicationLimits;
+
+    let mut entries= vec![ ];
+    let mut bytes= 0;
+    for entry in self.log.scan(nextIndex..).take(maxEntries) {
+      let entry= entry?;
+
+      bytes += bincode::serialized_size(&entry)? as usize;
+      if !entries.is_empty( ) && (bytes > maxBytes) {
+        break}
+
+      entries.push(entry);
+    }
+
+    let lastSentIndex= prevLogIndex + entries.len( ) as LogEntryIndex;
+    let (leaderCommit, _)= self.log.getCommitIndexAndTerm( );
+
+    self.send(MessageAddress::Node(peer), MessagePayload::AppendEntries { prevLogIndex, prevLogTerm, entries, leaderCommit })?;
+    Ok(lastSentIndex)
   }
-}
\ No newline at end of file
+
+  // Hands over the reads, whose sequence numbers have been acknowledged by a quorum of the nodes
+  // (including the leader itself, if it's a member), to the state-machine driver.
+  fn executeConfirmedReads(&mut self) -> Result<( )> {
+    let mut acknowledgedReadSequences: Vec<ReadSequence>= self.role.acknowledgedReadSequence.values( ).copied( ).collect( );
+    if self.isMember( ) {
+      acknowledgedReadSequences.push(self.role.readSequence);}
+    acknowledgedReadSequences.sort_unstable_by(|a, b| b.cmp(a));
+
+    let confirmedReadSequence= acknowledgedReadSequences[self.quorom( ) - 1];
+
+    while self.role.pendingReads.front( ).is_some_and(|read| read.sequence <= confirmedReadSequence) {
+      let read= self.role.pendingReads.pop_front( ).unwrap( );
+
+      self.stateMachineInstructor.send(StateMachineInstruction::Query {
+        id: read.id,
+        client: read.client,
+        command: read.command,
+        index: read.index
+      })
+      .map_err(|_| Error::Internal("State-machine driver has shut down".into( )))?;
+    }
+
+    Ok(( ))
+  }
+
+  /*
+    Commits the entries, which have been replicated to a quorum of the nodes (including the leader
+    itself, if it's a member).
+
+    NOTE : Only entries from the current term are committed by counting replicas. Entries from
+    previous terms get committed indirectly, when an entry from the current term gets committed.
+  */
+  fn commitReplicatedEntries(&mut self) -> Result<( )> {
+    let (lastLogIndex, _)= self.log.getLastStoredEntryIndexAndTerm( );
+
+    let mut replicatedIndices: Vec<LogEntryIndex>= self.role.matchIndex.values( ).copied( ).collect( );
+    if self.isMember( ) {
+      replicatedIndices.push(lastLogIndex);}
+    replicatedIndices.sort_unstable_by(|a, b| b.cmp(a));
+
+    // The highest index, which has been replicated to a quorum of the nodes.
+    let quorumIndex= replicatedIndices[self.quorom( ) - 1];
+
+    let (commitIndex, _)= self.log.getCommitIndexAndTerm( );
+    if quorumIndex <= commitIndex {
+      return Ok(( ))}
+
+    match self.log.get(quorumIndex)? {
+      Some(entry) if entry.term == self.currentTerm => {
+        let pendingRequests= &mut self.role.pendingRequests;
+        let clients= ((commitIndex + 1)..=quorumIndex).filter_map(|index| pendingRequests.remove(&index).map(|client| (index, client)))
+                                                      .collect( );
+        self.commit(quorumIndex, clients)
+      },
+
+      _ => Ok(( ))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    raft::{message::{Message, MessageAddress, MessagePayload, Request}, types::{LogEntryIndex, NodeId}},
+    result::Result
+  };
+  use super::{super::{tests::{message, TestCluster}, Node, ELECTION_TIMEOUT_RANGE}, HEARTBEAT_INTERVAL, LEASE_TIMEOUT};
+
+  fn commitIndex(cluster: &TestCluster, id: u64) -> LogEntryIndex {
+    cluster.node(id).log( ).getCommitIndexAndTerm( ).0
+  }
+
+  fn from(id: u64) -> impl Fn(&Message) -> bool {
+    move |message| message.from == MessageAddress::Node(NodeId(id))
+  }
+
+  #[test]
+  fn commitsEntryOnceQuorumStoresIt( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(3)?;
+    cluster.elect(1)?;
+    assert_eq!(commitIndex(&cluster, 1), 2);
+
+    let mutate= Message {
+      currentTermOfSender: 0,
+      from: MessageAddress::Client,
+      to: MessageAddress::Node(NodeId(1)),
+      payload: MessagePayload::ClientRequest { id: vec![1], request: Request::Mutate(b"command".to_vec( )) }
+    };
+    cluster.receive(mutate)?;
+    assert_eq!(cluster.node(1).log( ).getLastStoredEntryIndexAndTerm( ), (3, 1));
+
+    // The followers store the entry, but their acknowledgements are held back.
+    cluster.deliver(from(1))?;
+    assert_eq!(commitIndex(&cluster, 1), 2);
+
+    // Node 2 and the leader make a quorum.
+    cluster.deliver(from(2))?;
+    assert_eq!(commitIndex(&cluster, 1), 3);
+
+    // The followers learn of the commit along with the next heartbeat.
+    cluster.deliverAll( )?;
+    assert!(cluster.tickUntil(1, HEARTBEAT_INTERVAL as u64, |cluster| !cluster.inflight.is_empty( ))?);
+    cluster.deliverAll( )?;
+    assert_eq!((commitIndex(&cluster, 2), commitIndex(&cluster, 3)), (3, 3));
+    Ok(( ))
+  }
+
+  #[test]
+  fn doesNotCommitEntryOfPreviousTermByCounting( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(3)?;
+
+    // The leader of term 1 (node 3) replicated an entry to nodes 1 and 2, and failed before
+    // committing it. Nodes 1 and 2 restart in term 1.
+    for id in [1, 2] {
+      let log= cluster.nodeMut(id).logMut( );
+      log.setCurrentTermAndCastVote(1, Some(NodeId(3)))?;
+      log.append(1, Some(b"command".to_vec( )), None)?;
+      log.flush( )?;
+
+      cluster.restart(id)?;
+    }
+
+    // Node 1 gets elected in term 2 by node 2, and appends the noop entry of its term.
+    let toNode3= |message: &Message| message.to == MessageAddress::Node(NodeId(3));
+    assert!(cluster.tickUntil(1, ELECTION_TIMEOUT_RANGE.end as u64, |cluster| !cluster.inflight.is_empty( ))?);
+    cluster.deliver(|message| !toNode3(message) && matches!(message.payload,
+      MessagePayload::PreVote { .. } | MessagePayload::PreVoteResponse { .. } | MessagePayload::RequestVote { .. } | MessagePayload::RequestVoteResponse { .. }
+    ))?;
+    cluster.dropMessages(|_| true);
+
+    assert!(matches!(cluster.node(1), Node::Leader(_)));
+    assert_eq!(cluster.node(1).log( ).getLastStoredEntryIndexAndTerm( ), (3, 2));
+    let previousCommitIndex= commitIndex(&cluster, 1);
+    assert!(previousCommitIndex < 2);
+
+    // Once node 2 acknowledges the entry of term 1, it's stored by a quorum of the nodes. But it
+    // isn't committed, since a leader of a later term could still overwrite it.
+    cluster.receive(message(2, 1, 2, MessagePayload::AppendEntriesResponse { success: true, lastLogIndex: 2 }))?;
+    assert_eq!(commitIndex(&cluster, 1), previousCommitIndex);
+
+    // It's committed along with the noop entry, once node 2 acknowledges that.
+    cluster.receive(message(2, 1, 2, MessagePayload::AppendEntriesResponse { success: true, lastLogIndex: 3 }))?;
+    assert_eq!(cluster.node(1).log( ).getCommitIndexAndTerm( ), (3, 2));
+    Ok(( ))
+  }
+
+  // Ticks the leader (node 1), delivering its heartbeats to the followers. Only the responses of the
+  // given followers reach it, the rest are dropped.
+  fn tickLeader(cluster: &mut TestCluster, ticks: u64, respondingFollowers: &[u64]) -> Result<( )> {
+    for _ in 0..ticks {
+      cluster.tick(1)?;
+
+      cluster.deliver(|message| from(1)(message) || respondingFollowers.iter( ).any(|&id| from(id)(message)))?;
+      cluster.dropMessages(|_| true);
+    }
+    Ok(( ))
+  }
+
+  #[test]
+  fn stepsDownOnceLeaseExpires( ) -> Result<( )> {
+    let mut cluster= TestCluster::new(3)?;
+    cluster.elect(1)?;
+
+    // Node 2 and the leader make a quorum, so the leader keeps its lease without node 3. The last
+    // tick sends a heartbeat, which node 2 responds to.
+    tickLeader(&mut cluster, 5 * HEARTBEAT_INTERVAL as u64 * LEASE_TIMEOUT as u64, &[2])?;
+    assert!(matches!(cluster.node(1), Node::Leader(_)));
+
+    // Once the responses of a quorum are lost, the lease runs out LEASE_TIMEOUT ticks after the
+    // last response.
+    tickLeader(&mut cluster, LEASE_TIMEOUT as u64 - 1, &[ ])?;
+    assert!(matches!(cluster.node(1), Node::Leader(_)));
+
+    tickLeader(&mut cluster, 1, &[ ])?;
+    assert!(matches!(cluster.node(1), Node::Follower(_)));
+    assert_eq!(cluster.node(1).leader( ), None);
+
+    // The node steps down in the same term, keeping its vote for itself.
+    assert_eq!(cluster.node(1).currentTerm( ), 1);
+    assert_eq!(cluster.nodeMut(1).logMut( ).getCurrentTermAndCastVote( )?, (1, Some(NodeId(1))));
+    Ok(( ))
+  }
+}
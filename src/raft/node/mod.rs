@@ -1,21 +1,184 @@
-use rand::{thread_rng, Rng};
-use std::collections::HashSet;
-use tokio::sync::mpsc::UnboundedSender;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{collections::{HashMap, HashSet}, ops::Bound};
+use tokio::sync::{mpsc::UnboundedSender, oneshot::{self, error::TryRecvError}};
+use tracing::debug;
 use candidate::Candidate;
 use follower::Follower;
-use leader::Leader;
+use leader::{Leader, ReplicationLimits};
+use crate::{result::{Error, Result}, storage::{engine::StorageEngineStatus, sync::SyncStatus}};
 use super::{
-  log::Log, message::Message, state_machine_driver::StateMachineInstruction,
-  types::{NodeId, Term, Ticks}
+  cluster_config::ClusterConfig, log::{Log, LogMetrics}, message::{Message, MessageAddress, MessagePayload, Request, RequestId},
+  state_machine_driver::StateMachineInstruction, types::{LogEntryIndex, NodeId, Term, Ticks}
 };
 use std::ops::Range;
 
+#[allow(clippy::large_enum_variant)]
 pub enum Node {
   Candidate(GenericNode<Candidate>),
   Follower(GenericNode<Follower>),
   Leader(GenericNode<Leader>)
 }
 
+impl Node {
+  // Creates a new node, which starts as a leaderless follower. The replication limits apply once it
+  // becomes the leader. The election timeouts are randomized using the given source.
+  pub fn new(id: NodeId,
+             log: Log,
+             messageSender: UnboundedSender<Message>,
+             stateMachineDriverInstructionsSender: UnboundedSender<StateMachineInstruction>,
+             replicationLimits: ReplicationLimits,
+             random: RandomSource) -> Result<Self>
+  {
+    let node= GenericNode::newAsLeaderless(id, log, messageSender, stateMachineDriverInstructionsSender, replicationLimits, random)?;
+    Ok(node.into( ))
+  }
+
+  // Returns the node's id.
+  pub fn id(&self) -> NodeId {
+    match self {
+      Node::Candidate(node) => node.id,
+      Node::Follower(node) => node.id,
+      Node::Leader(node) => node.id
+    }
+  }
+
+  // Returns the node's current term.
+  pub fn currentTerm(&self) -> Term {
+    match self {
+      Node::Candidate(node) => node.currentTerm,
+      Node::Follower(node) => node.currentTerm,
+      Node::Leader(node) => node.currentTerm
+    }
+  }
+
+  // Returns the leader of the current term, if the node knows it.
+  pub fn leader(&self) -> Option<NodeId> {
+    match self {
+      Node::Candidate(_) => None,
+      Node::Follower(node) => node.role.leader,
+      Node::Leader(node) => Some(node.id)
+    }
+  }
+
+  // Returns the configuration of the cluster, which is in effect on the node.
+  pub fn config(&self) -> &ClusterConfig {
+    let log= match self {
+      Node::Candidate(node) => &node.log,
+      Node::Follower(node) => &node.log,
+      Node::Leader(node) => &node.log
+    };
+    log.getConfig( ).1
+  }
+
+  // Moves the node's logical clock by a tick, and returns the (possibly transitioned) node.
+  pub fn tick(self) -> Result<Self> {
+    match self {
+      Node::Candidate(mut node) => {
+        node.compactLog( )?;
+        node.log.flushIfDue( )?;
+        node.expireQueuedRequests( )?;
+        node.tick( )
+      },
+      Node::Follower(mut node) => {
+        node.compactLog( )?;
+        node.log.flushIfDue( )?;
+        node.expireQueuedRequests( )?;
+        node.tick( )
+      },
+      Node::Leader(mut node) => {
+        node.compactLog( )?;
+        node.log.flushIfDue( )?;
+        node.tick( )
+      }
+    }
+  }
+
+  // Processes a message received by the node, and returns the (possibly transitioned) node.
+  pub fn processMessage(self, message: Message) -> Result<Self> {
+    match self {
+      Node::Candidate(node) => node.processMessage(message),
+      Node::Follower(node) => node.processMessage(message),
+      Node::Leader(node) => node.processMessage(message)
+    }
+  }
+}
+
+// Status of a node, as reported to clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+  pub nodeId: NodeId,
+  pub role: RoleName,
+  pub currentTerm: Term,
+
+  // Leader of the current term, if the node knows it.
+  pub leader: Option<NodeId>,
+
+  pub peers: Vec<NodeId>,
+
+  pub lastLogIndex: LogEntryIndex,
+  pub lastLogTerm: Term,
+  pub commitIndex: LogEntryIndex,
+
+  // Index of the last entry covered by the log's snapshot.
+  pub snapshotIndex: LogEntryIndex,
+
+  // Filled in by the state-machine driver : the applied index, and the status reported by the state
+  // machine (serialized by it).
+  pub appliedIndex: LogEntryIndex,
+  pub stateMachine: Vec<u8>,
+
+  pub logMetrics: LogMetrics,
+
+  // Number of the mutations rejected by the node while it was the leader, since it was overloaded
+  // (see ReplicationLimits::maxPendingProposals). And the number of the requests of the local
+  // clients, which had to wait for room in the (bounded) request channel to the node - filled in
+  // by the client (see raft::server::Client).
+  pub rejectedProposals: u64,
+  pub deferredRequests: u64,
+
+  // Status of the storage engine storing the log.
+  pub logStorage: StorageEngineStatus,
+
+  // The sync policy of the log, along with the counters of its commits and flushes.
+  pub logSync: SyncStatus
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RoleName {
+  Candidate,
+  Follower,
+  Leader
+}
+
+impl std::fmt::Display for RoleName {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Candidate => write!(f, "candidate"),
+      Self::Follower => write!(f, "follower"),
+      Self::Leader => write!(f, "leader")
+    }
+  }
+}
+
+impl From<GenericNode<Candidate>> for Node {
+  fn from(node: GenericNode<Candidate>) -> Self {
+    Node::Candidate(node)
+  }
+}
+
+impl From<GenericNode<Follower>> for Node {
+  fn from(node: GenericNode<Follower>) -> Self {
+    Node::Follower(node)
+  }
+}
+
+impl From<GenericNode<Leader>> for Node {
+  fn from(node: GenericNode<Leader>) -> Self {
+    Node::Leader(node)
+  }
+}
+
 pub mod follower;
 pub mod candidate;
 pub mod leader;
@@ -25,15 +188,51 @@ pub struct GenericNode<R: Role= Follower> {
   currentTerm: Term,
 
   id: NodeId,
-  peers: HashSet<NodeId>,
   messageSender: UnboundedSender<Message>,
 
+  // NOTE : The members of the cluster are given by the latest configuration in the log.
   log: Log,
 
   // Sends instruction to the state-machine driver.
-  stateMachineInstructor: UnboundedSender<StateMachineInstruction>
+  stateMachineInstructor: UnboundedSender<StateMachineInstruction>,
+
+  // Receives the snapshot requested from the state-machine driver (if any), which the log is
+  // compacted with.
+  pendingSnapshot: Option<oneshot::Receiver<(LogEntryIndex, Vec<u8>)>>,
+
+  replicationLimits: ReplicationLimits,
+
+  // Number of the mutations rejected (with ServerBusy) while the node was the leader, since it
+  // started.
+  rejectedProposals: u64,
+
+  // Requests from the local clients, which arrived while there was no known leader (like during an
+  // election). They're served once a leader is known, or rejected if that takes too long (see
+  // queueRequest( )).
+  queuedRequests: Vec<QueuedRequest>,
+
+  // Randomizes the election timeouts.
+  random: RandomSource
 }
 
+/*
+  Source of the randomness used by a node. The servers use one seeded from the OS, while simulations
+  use a seeded one, so that they're reproducible (see simulation.rs).
+*/
+pub type RandomSource= Box<dyn RngCore + Send>;
+
+struct QueuedRequest {
+  id: RequestId,
+  request: Request,
+
+  // Number of ticks left, before the request is rejected.
+  remainingTicks: Ticks
+}
+
+// Number of ticks a client request stays queued, waiting for a leader to be known.
+// NOTE : Spans a few election timeouts, so that a split vote doesn't fail the request.
+const REQUEST_QUEUE_TIMEOUT: Ticks= 100;
+
 impl<R: Role> GenericNode<R> {
   // Changes the node's role.
   fn changeRole<NR: Role>(self, newRole: NR) -> GenericNode<NR> {
@@ -42,32 +241,205 @@ impl<R: Role> GenericNode<R> {
       currentTerm: self.currentTerm,
 
       id: self.id,
-      peers: self.peers,
       messageSender: self.messageSender,
 
       log: self.log,
-      stateMachineInstructor: self.stateMachineInstructor
+      stateMachineInstructor: self.stateMachineInstructor,
+      pendingSnapshot: self.pendingSnapshot,
+
+      replicationLimits: self.replicationLimits,
+      rejectedProposals: self.rejectedProposals,
+      queuedRequests: self.queuedRequests,
+
+      random: self.random
+    }
+  }
+
+  // Generates a random election timeout within range (10 - 20 ticks).
+  fn randomElectionTimeout(&mut self) -> Ticks {
+    self.random.gen_range(ELECTION_TIMEOUT_RANGE)
+  }
+
+  /*
+    Queues a request from a local client, which can't be served since there's no known leader. It's
+    served once a leader is known : it's forwarded to the leader, or served by the node itself if it
+    wins the election.
+
+    If no leader is known within REQUEST_QUEUE_TIMEOUT ticks, it's rejected with Error::Abort (so
+    that the client can retry it).
+  */
+  fn queueRequest(&mut self, id: RequestId, request: Request) {
+    debug!("Queueing client request {:?}, until a leader is known", id);
+    self.queuedRequests.push(QueuedRequest { id, request, remainingTicks: REQUEST_QUEUE_TIMEOUT });
+  }
+
+  // Moves the clocks of the queued requests by a tick, and rejects the ones which have timed out.
+  fn expireQueuedRequests(&mut self) -> Result<( )> {
+    let (expired, queued)= std::mem::take(&mut self.queuedRequests).into_iter( )
+                                                                     .partition(|queued| queued.remainingTicks <= 1);
+    self.queuedRequests= queued;
+
+    for queued in &mut self.queuedRequests {
+      queued.remainingTicks -= 1;}
+
+    for QueuedRequest { id, .. } in expired {
+      self.send(MessageAddress::Client, MessagePayload::ResponseToClient { id, response: Err(Error::Abort) })?;}
+
+    Ok(( ))
+  }
+
+  // Sends a message (stamped with the current term) from this node to the given address.
+  fn send(&self, to: MessageAddress, payload: MessagePayload) -> Result<( )> {
+    let message= Message {
+      currentTermOfSender: self.currentTerm,
+      from: MessageAddress::Node(self.id),
+      to,
+      payload
+    };
+    debug!("Sending message {:?}", message);
+
+    self.messageSender.send(message)
+                      .map_err(|error| Error::Internal(format!("Failed sending message : {}", error)))
+  }
+
+  /*
+    Commits the entries upto the given index, and instructs the state-machine driver to apply the
+    newly committed entries.
+
+    The clients waiting for the results of the newly committed entries (keyed by the entry index)
+    are handed over to the state-machine driver.
+  */
+  fn commit(&mut self,
+            index: LogEntryIndex,
+            mut clients: HashMap<LogEntryIndex, (MessageAddress, RequestId)>) -> Result<( )>
+  {
+    let (previousCommitIndex, _)= self.log.getCommitIndexAndTerm( );
+    let commitIndex= self.log.commit(index)?;
+
+    let newlyCommittedEntries= self.log.scan((Bound::Excluded(previousCommitIndex), Bound::Included(commitIndex)))
+                                       .collect::<Result<Vec<_>>>( )?;
+    for entry in newlyCommittedEntries {
+      let client= clients.remove(&entry.index);
+
+      self.stateMachineInstructor.send(StateMachineInstruction::Apply { entry, client })
+                                 .map_err(|_| Error::Internal("State-machine driver has shut down".into( )))?;
+    }
+
+    // Once enough entries have been committed since the last snapshot, a new snapshot is requested
+    // from the state-machine driver. It's taken after applying the entries sent above.
+    let (snapshotIndex, _)= self.log.getSnapshotIndexAndTerm( );
+    if self.pendingSnapshot.is_none( ) && (commitIndex - snapshotIndex >= SNAPSHOT_INTERVAL) {
+      let (sender, receiver)= oneshot::channel( );
+
+      self.stateMachineInstructor.send(StateMachineInstruction::Snapshot { sender })
+                                 .map_err(|_| Error::Internal("State-machine driver has shut down".into( )))?;
+      self.pendingSnapshot= Some(receiver);
+    }
+
+    Ok(( ))
+  }
+
+  // Compacts the log using the snapshot requested from the state-machine driver, once it's ready.
+  fn compactLog(&mut self) -> Result<( )> {
+    let Some(receiver)= &mut self.pendingSnapshot else {
+      return Ok(( ))};
+
+    match receiver.try_recv( ) {
+      Ok((index, state)) => {
+        self.pendingSnapshot= None;
+
+        debug!("Compacting the log upto index {}", index);
+        self.log.snapshot(index, state)
+      },
+
+      Err(TryRecvError::Empty) => Ok(( )),
+
+      Err(TryRecvError::Closed) => Err(Error::Internal("State-machine driver has shut down".into( )))
     }
   }
 
-  // Returns the cluster-size (number of nodes in the cluster).
-  fn clusterSize(&self) -> u8 {
-    let peerCount= self.peers.len( ) as u8;
-    peerCount + 1
+  /*
+    Responds to a status request from a client, with the node's status (taken right away, from the
+    event loop). The state-machine driver fills in its part of the status, and sends the response.
+
+    The role and the known leader are given by the role specific caller.
+  */
+  fn respondWithStatus(&mut self, id: RequestId, role: RoleName, leader: Option<NodeId>) -> Result<( )> {
+    let (lastLogIndex, lastLogTerm)= self.log.getLastStoredEntryIndexAndTerm( );
+    let (commitIndex, _)= self.log.getCommitIndexAndTerm( );
+    let (snapshotIndex, _)= self.log.getSnapshotIndexAndTerm( );
+
+    let mut peers: Vec<NodeId>= self.peers( ).into_iter( ).collect( );
+    peers.sort( );
+
+    let status= Status {
+      nodeId: self.id,
+      role,
+      currentTerm: self.currentTerm,
+      leader,
+      peers,
+
+      lastLogIndex,
+      lastLogTerm,
+      commitIndex,
+      snapshotIndex,
+
+      appliedIndex: 0,
+      stateMachine: Vec::new( ),
+
+      logMetrics: self.log.metrics( ).clone( ),
+
+      rejectedProposals: self.rejectedProposals,
+      deferredRequests: 0,
+      logStorage: self.log.status( )?,
+      logSync: self.log.syncStatus( )?
+    };
+
+    self.stateMachineInstructor.send(StateMachineInstruction::GetStatus { id, client: MessageAddress::Client, status: Box::new(status) })
+                               .map_err(|_| Error::Internal("State-machine driver has shut down".into( )))
+  }
+
+  // Executes a local query (see Request::LocalQuery) against the state machine, once it has applied
+  // the entries upto the given index.
+  fn queryLocally(&mut self, id: RequestId, command: Vec<u8>, index: LogEntryIndex) -> Result<( )> {
+    self.stateMachineInstructor.send(StateMachineInstruction::Query { id, client: MessageAddress::Client, command, index })
+                               .map_err(|_| Error::Internal("State-machine driver has shut down".into( )))
   }
 
-  // A quorum refers to the minimum number of group members in a decision-making process that must
-  // be present in order for the proceedings to be valid.
-  fn quorom(&self) -> u8 {
-    getQuorumForClusterSize(self.clusterSize( ))
+  /*
+    Returns whether a log with the given last entry is atleast as up-to-date as the node's log.
+
+    Raft determines which of two logs is more up-to-date by comparing the index and term of the last
+    entries in the logs. If the logs have last entries with different terms, then the log with the
+    later term is more up-to-date. If the logs end with the same term, then whichever log is longer
+    is more up-to-date.
+  */
+  fn isLogUpToDate(&self, otherLastLogIndex: LogEntryIndex, otherLastLogTerm: Term) -> bool {
+    let (lastLogIndex, lastLogTerm)= self.log.getLastStoredEntryIndexAndTerm( );
+    (otherLastLogTerm > lastLogTerm) ||
+      ((otherLastLogTerm == lastLogTerm) && (otherLastLogIndex >= lastLogIndex))
+  }
+
+  // Returns the ids of the cluster's members, except this node.
+  fn peers(&self) -> HashSet<NodeId> {
+    self.log.getConfig( ).1.getPeersOf(self.id)
+  }
+
+  // Returns whether the node is a member of the cluster. A node which isn't (like one yet to be
+  // added, or one which has been removed) never campaigns, and isn't counted in the quorum.
+  fn isMember(&self) -> bool {
+    self.log.getConfig( ).1.isMember(self.id)
+  }
+
+  fn quorom(&self) -> usize {
+    self.log.getConfig( ).1.quorum( )
   }
 }
 
 pub trait Role { }
 
-fn getQuorumForClusterSize(clusterSize: u8) -> u8 {
-  (clusterSize / 2) + 1
-}
+// Number of entries committed since the last snapshot, after which the log is compacted.
+const SNAPSHOT_INTERVAL: LogEntryIndex= 1000;
 
 /*
   Raft uses randomized election timeouts to ensure that split votes are rare and that they are
@@ -82,8 +454,216 @@ fn getQuorumForClusterSize(clusterSize: u8) -> u8 {
 */
 const ELECTION_TIMEOUT_RANGE: Range<Ticks> = 10..20;
 
-// Generates a random election timeout within range (10 - 20 ms).
-fn getRandomElectionTimeout( ) -> Ticks {
-  thread_rng( )
-    .gen_range(ELECTION_TIMEOUT_RANGE)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+  use std::{collections::{BTreeMap, VecDeque}, time::Duration};
+  use rand::{rngs::StdRng, SeedableRng};
+  use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+  use crate::{
+    raft::{
+      cluster_config::ClusterConfig, log::{Log, DEFAULT_CACHE_CAPACITY}, message::{Message, MessageAddress, MessagePayload},
+      state_machine_driver::StateMachineInstruction, types::{NodeId, Term}
+    },
+    result::{Error, Result},
+    storage::sync::SyncPolicy,
+    testing::sync::FaultyEngine
+  };
+  use super::{leader::ReplicationLimits, Node, ELECTION_TIMEOUT_RANGE};
+
+  /*
+    A cluster of nodes for testing the roles, whose messages are routed by the test itself : the
+    messages sent between the nodes stay in flight, until the test delivers (or drops) them. And the
+    clock of a node only moves when the test ticks it.
+
+    The logs are stored on faulty engines (see FaultyEngine), so that the test can restart a node
+    with only what its log had flushed.
+  */
+  pub(super) struct TestCluster {
+    nodes: BTreeMap<NodeId, TestNode>,
+
+    // Messages sent between the nodes, in the order they were sent. A broadcast is split into a
+    // message to each of the sender's peers.
+    pub(super) inflight: VecDeque<Message>,
+
+    // Messages sent by the nodes to their local clients.
+    pub(super) clientMessages: Vec<Message>
+  }
+
+  struct TestNode {
+    // NOTE : Only None while the node is processing a message or a tick, or once it has failed.
+    node: Option<Node>,
+    engine: FaultyEngine,
+
+    messageReceiver: UnboundedReceiver<Message>,
+
+    // Kept open (though the instructions are discarded), since the node fails once the
+    // state-machine driver is gone.
+    instructionReceiver: UnboundedReceiver<StateMachineInstruction>
+  }
+
+  impl TestCluster {
+    // Creates a cluster of the given number of nodes (with ids starting from 1), which start as
+    // leaderless followers in term 0.
+    pub(super) fn new(size: u64) -> Result<Self> {
+      let config= ClusterConfig::new((1..=size).map(|id| (NodeId(id), format!("node-{}", id))).collect( ));
+
+      let mut cluster= Self { nodes: BTreeMap::new( ), inflight: VecDeque::new( ), clientMessages: Vec::new( ) };
+      for &id in config.nodes.keys( ) {
+        let engine= FaultyEngine::new(id.0, Duration::ZERO, 0.0);
+
+        let mut log= Log::new(Box::new(engine.clone( )), DEFAULT_CACHE_CAPACITY, SyncPolicy::default( ))?;
+        log.bootstrap(config.clone( ))?;
+        log.flush( )?;
+
+        cluster.start(id, engine)?;
+      }
+      Ok(cluster)
+    }
+
+    pub(super) fn node(&self, id: u64) -> &Node {
+      self.nodes[&NodeId(id)].node.as_ref( ).expect("Node has failed")
+    }
+
+    pub(super) fn nodeMut(&mut self, id: u64) -> &mut Node {
+      self.nodes.get_mut(&NodeId(id)).and_then(|node| node.node.as_mut( )).expect("Node has failed")
+    }
+
+    pub(super) fn engine(&self, id: u64) -> &FaultyEngine {
+      &self.nodes[&NodeId(id)].engine
+    }
+
+    // Moves the clock of the node by a tick.
+    pub(super) fn tick(&mut self, id: u64) -> Result<( )> {
+      self.process(NodeId(id), Node::tick)
+    }
+
+    // Ticks the node until the condition holds, for atmost the given number of ticks. Returns
+    // whether the condition holds.
+    pub(super) fn tickUntil(&mut self, id: u64, maxTicks: u64, condition: impl Fn(&Self) -> bool) -> Result<bool> {
+      for _ in 0..maxTicks {
+        if condition(self) {
+          return Ok(true)}
+
+        self.tick(id)?;
+      }
+      Ok(condition(self))
+    }
+
+    // Has the node the message is addressed to process it. The message needn't have been sent by a
+    // node of the cluster.
+    pub(super) fn receive(&mut self, message: Message) -> Result<( )> {
+      let MessageAddress::Node(to)= message.to else {
+        return Err(Error::Internal(format!("Message {:?} isn't addressed to a node", message)))};
+
+      self.process(to, |node| node.processMessage(message))
+    }
+
+    // Delivers the messages in flight which match the filter (including the ones sent in response
+    // to them), until none are left. The rest stay in flight.
+    pub(super) fn deliver(&mut self, filter: impl Fn(&Message) -> bool) -> Result<( )> {
+      while let Some(position)= self.inflight.iter( ).position(&filter) {
+        let message= self.inflight.remove(position).expect("Message is missing");
+        self.receive(message)?;
+      }
+      Ok(( ))
+    }
+
+    pub(super) fn deliverAll(&mut self) -> Result<( )> {
+      self.deliver(|_| true)
+    }
+
+    // Drops the messages in flight which match the filter.
+    pub(super) fn dropMessages(&mut self, filter: impl Fn(&Message) -> bool) {
+      self.inflight.retain(|message| !filter(message));
+    }
+
+    // Ticks the node until it runs a pre-vote (or, in a single node cluster, wins the election), and
+    // then delivers all the messages - electing it as the leader of the next term.
+    pub(super) fn elect(&mut self, id: u64) -> Result<( )> {
+      let from= MessageAddress::Node(NodeId(id));
+      self.tickUntil(id, ELECTION_TIMEOUT_RANGE.end as u64, |cluster| {
+        cluster.inflight.iter( ).any(|message| message.from == from) || matches!(cluster.node(id), Node::Leader(_))
+      })?;
+      self.deliverAll( )?;
+
+      match self.node(id) {
+        Node::Leader(_) => Ok(( )),
+        _ => Err(Error::Internal(format!("Node {} didn't get elected", id)))
+      }
+    }
+
+    // Restarts the node (like after a crash, or once it has failed) with only what its log had
+    // flushed. The messages in flight from and to the node are lost.
+    pub(super) fn restart(&mut self, id: u64) -> Result<( )> {
+      let address= MessageAddress::Node(NodeId(id));
+      self.inflight.retain(|message| (message.from != address) && (message.to != address));
+
+      let engine= self.engine(id).crash( )?;
+      self.start(NodeId(id), engine)
+    }
+
+    fn start(&mut self, id: NodeId, engine: FaultyEngine) -> Result<( )> {
+      let log= Log::new(Box::new(engine.clone( )), DEFAULT_CACHE_CAPACITY, SyncPolicy::default( ))?;
+
+      let (messageSender, messageReceiver)= unbounded_channel( );
+      let (instructionSender, instructionReceiver)= unbounded_channel( );
+      let node= Node::new(id, log, messageSender, instructionSender, ReplicationLimits::default( ), Box::new(StdRng::seed_from_u64(id.0)))?;
+
+      self.nodes.insert(id, TestNode { node: Some(node), engine, messageReceiver, instructionReceiver });
+      Ok(( ))
+    }
+
+    // Feeds the node a message or a tick, and routes the messages it sent (even if it failed).
+    fn process(&mut self, id: NodeId, action: impl FnOnce(Node) -> Result<Node>) -> Result<( )> {
+      let testNode= self.nodes.get_mut(&id)
+                              .ok_or_else(| | Error::Internal(format!("Node {} isn't a part of the cluster", id)))?;
+      let node= testNode.node.take( )
+                        .ok_or_else(| | Error::Internal(format!("Node {} has failed", id)))?;
+
+      let mut peers= node.config( ).getPeersOf(id).into_iter( ).collect::<Vec<_>>( );
+      peers.sort( );
+
+      let result= action(node).map(|node| testNode.node= Some(node));
+
+      let mut messages= Vec::new( );
+      while let Ok(message)= testNode.messageReceiver.try_recv( ) {
+        messages.push(message);}
+      while testNode.instructionReceiver.try_recv( ).is_ok( ) { }
+
+      for message in messages {
+        match message.to {
+          MessageAddress::Broadcast =>
+            self.inflight.extend(peers.iter( ).map(|peer| Message { to: MessageAddress::Node(*peer), ..message.clone( ) })),
+
+          MessageAddress::Node(_) => self.inflight.push_back(message),
+
+          MessageAddress::Client => self.clientMessages.push(message)
+        }
+      }
+      result
+    }
+  }
+
+  // Returns a message from a node to another, sent in the given term.
+  pub(super) fn message(from: u64, to: u64, term: Term, payload: MessagePayload) -> Message {
+    Message { currentTermOfSender: term, from: MessageAddress::Node(NodeId(from)), to: MessageAddress::Node(NodeId(to)), payload }
+  }
+
+  impl Node {
+    pub(super) fn log(&self) -> &Log {
+      match self {
+        Node::Candidate(node) => &node.log,
+        Node::Follower(node) => &node.log,
+        Node::Leader(node) => &node.log
+      }
+    }
+
+    pub(super) fn logMut(&mut self) -> &mut Log {
+      match self {
+        Node::Candidate(node) => &mut node.log,
+        Node::Follower(node) => &mut node.log,
+        Node::Leader(node) => &mut node.log
+      }
+    }
+  }
+}
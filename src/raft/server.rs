@@ -0,0 +1,429 @@
+use std::{
+  collections::HashMap,
+  sync::{atomic::{AtomicU64, Ordering}, Arc},
+  thread,
+  time::Duration
+};
+use rand::{rngs::StdRng, thread_rng, RngCore, SeedableRng};
+use tokio::{
+  sync::{mpsc::{error::TrySendError, unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender}, oneshot, watch},
+  time::MissedTickBehavior
+};
+use tracing::{debug, error};
+use crate::result::{Error, ErrorCode, Result};
+use super::{
+  cluster_config::ClusterConfig,
+  log::Log,
+  message::{Message, MessageAddress, MessagePayload, Request, RequestId, Response},
+  node::{leader::ReplicationLimits, Node, Status},
+  state_machine_driver::{StateMachine, StateMachineDriver},
+  types::{LogEntryIndex, NodeId}
+};
+
+// Default duration of a tick of the node's logical clock.
+// NOTE : The election timeouts and the heartbeat interval are counted in ticks, so they scale with it.
+pub const DEFAULT_TICK_INTERVAL: Duration= Duration::from_millis(10);
+
+// Number of times a mutation is retried after being aborted (like during a leader change), and the
+// time waited before each retry (giving the cluster time to elect a new leader).
+const MUTATION_RETRIES: usize= 50;
+const MUTATION_RETRY_INTERVAL: Duration= Duration::from_millis(100);
+
+// A request (with its unique id) from a local client, along with the channel the response is sent
+// back on.
+pub type ClientRequest= (RequestId, Request, oneshot::Sender<Result<Response>>);
+
+// Capacity of the channel the local clients send their requests to the node through. Once it's
+// full, the clients wait for room in it (backpressure), instead of the requests piling up.
+// NOTE : The messages exchanged with the peers go through unbounded channels, since the node must
+// never block on them.
+pub const CLIENT_REQUEST_CHANNEL_CAPACITY: usize= 1024;
+
+/*
+  Runs a Raft node : drives its logical clock, feeds it the messages received from the peers and
+  the requests received from the local clients, and routes the messages it sends - to the peers
+  (through the transport) or back to the waiting clients.
+
+  The state machine is driven by a separate task, so that applying entries doesn't hold up the
+  node.
+
+  The configuration of the cluster in effect on the node is published (see watchConfig( )), so that
+  the transport can connect to the nodes added at runtime. So is the leader known to the node (see
+  watchLeader( )), so that the clients can be redirected to it. And so is the applied index of the
+  state machine (see watchAppliedIndex( )), so that the clients can wait for it to catch up with
+  their writes.
+*/
+pub struct Server {
+  node: Node,
+
+  // Messages sent by the node.
+  nodeMessageReceiver: UnboundedReceiver<Message>,
+
+  configSender: watch::Sender<ClusterConfig>,
+  leaderSender: watch::Sender<Option<NodeId>>,
+  appliedIndex: watch::Receiver<LogEntryIndex>,
+
+  stateMachineDriver: StateMachineDriver,
+  stateMachine: Box<dyn StateMachine>,
+
+  // Duration of a tick of the node's logical clock.
+  tickInterval: Duration
+}
+
+impl Server {
+  /*
+    Creates the server, catching up the state machine with the entries committed before a restart.
+    Fails, if the log and the state machine are inconsistent (see Log::checkIntegrity( )).
+
+    A new cluster is bootstrapped with the given configuration (see Log::bootstrap( )). None is
+    given for a node joining an existing cluster, which waits to be added by the leader. After a
+    restart, the configuration in the log is used (the given one is ignored).
+  */
+  pub fn new(id: NodeId,
+             config: Option<ClusterConfig>,
+             mut log: Log,
+             mut stateMachine: Box<dyn StateMachine>,
+             replicationLimits: ReplicationLimits,
+             tickInterval: Duration) -> Result<Self>
+  {
+    log.checkIntegrity(stateMachine.appliedIndex( ))?;
+
+    if let Some(config)= config {
+      log.bootstrap(config)?;}
+
+    let (nodeMessageSender, nodeMessageReceiver)= unbounded_channel( );
+    let (stateMachineInstructionsSender, stateMachineInstructionsReceiver)= unbounded_channel( );
+
+    let mut stateMachineDriver= StateMachineDriver::new(id, stateMachineInstructionsReceiver, nodeMessageSender.clone( ));
+    stateMachineDriver.applyCommittedEntries(stateMachine.as_mut( ), &mut log)?;
+
+    let random= Box::new(StdRng::from_entropy( ));
+    let node= Node::new(id, log, nodeMessageSender, stateMachineInstructionsSender, replicationLimits, random)?;
+    let (configSender, _)= watch::channel(node.config( ).clone( ));
+    let (leaderSender, _)= watch::channel(node.leader( ));
+    let appliedIndex= stateMachineDriver.watchAppliedIndex( );
+
+    Ok(Self { node, nodeMessageReceiver, configSender, leaderSender, appliedIndex, stateMachineDriver, stateMachine, tickInterval })
+  }
+
+  // Returns a receiver of the configuration of the cluster in effect on the node, which is updated
+  // whenever it changes.
+  pub fn watchConfig(&self) -> watch::Receiver<ClusterConfig> {
+    self.configSender.subscribe( )
+  }
+
+  // Returns a receiver of the leader known to the node (if any), which is updated whenever it
+  // changes.
+  pub fn watchLeader(&self) -> watch::Receiver<Option<NodeId>> {
+    self.leaderSender.subscribe( )
+  }
+
+  // Returns a receiver of the applied index of the node's state machine, which is updated whenever
+  // it advances.
+  pub fn watchAppliedIndex(&self) -> watch::Receiver<LogEntryIndex> {
+    self.appliedIndex.clone( )
+  }
+
+  /*
+    Runs the event loop, until the peer messages channel or the client requests channel closes.
+    Messages from the peers are received through inboundMessageReceiver, and the messages for them
+    are sent through outboundMessageSender (like to the transport).
+
+    Returns error, if the node or the state machine fails.
+  */
+  pub async fn serve(self,
+                     mut inboundMessageReceiver: UnboundedReceiver<Message>,
+                     outboundMessageSender: UnboundedSender<Message>,
+                     mut clientRequestReceiver: Receiver<ClientRequest>) -> Result<( )>
+  {
+    let Self { mut node, mut nodeMessageReceiver, configSender, leaderSender, stateMachineDriver, stateMachine, tickInterval, .. }= self;
+    let mut stateMachineDriverTask= tokio::spawn(stateMachineDriver.run(stateMachine));
+
+    // Clients waiting for the responses to their requests.
+    let mut pendingClientRequests: HashMap<RequestId, oneshot::Sender<Result<Response>>>= HashMap::new( );
+
+    // NOTE : The ticks missed while the node was held up aren't fired in a burst, since that would
+    // run out the election timeouts (and the leader's lease) all at once.
+    let mut ticker= tokio::time::interval(tickInterval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+      tokio::select! {
+        _ = ticker.tick( ) => node= node.tick( )?,
+
+        message= inboundMessageReceiver.recv( ) => match message {
+          Some(message) => node= node.processMessage(message)?,
+          None => break
+        },
+
+        clientRequest= clientRequestReceiver.recv( ) => match clientRequest {
+          Some((id, request, responseSender)) => {
+            pendingClientRequests.insert(id.clone( ), responseSender);
+
+            let message= Message {
+              // Client requests aren't bound to terms.
+              currentTermOfSender: 0,
+
+              from: MessageAddress::Client,
+              to: MessageAddress::Node(node.id( )),
+              payload: MessagePayload::ClientRequest { id, request }
+            };
+            node= node.processMessage(message)?;
+          },
+
+          None => break
+        },
+
+        Some(message)= nodeMessageReceiver.recv( ) => match (message.to, message.payload) {
+          (MessageAddress::Client, MessagePayload::ResponseToClient { id, response }) => match pendingClientRequests.remove(&id) {
+            // The client may have stopped waiting.
+            Some(responseSender) => { let _= responseSender.send(response); },
+            None => debug!("Dropping response to unknown client request {:?}", id)
+          },
+
+          (MessageAddress::Client, payload) => error!("Dropping unexpected message {:?} to client", payload),
+
+          (to, payload) => {
+            let message= Message { payload, to, ..message };
+            outboundMessageSender.send(message)
+                                 .map_err(|_| Error::Internal("Outbound message channel is closed".to_string( )))?;
+          }
+        },
+
+        result= &mut stateMachineDriverTask => return match result {
+          Ok(result) => result,
+          Err(error) => Err(Error::Internal(format!("State-machine driver panicked : {}", error)))
+        }
+      }
+
+      configSender.send_if_modified(|config| {
+        let changed= config != node.config( );
+        if changed {
+          config.clone_from(node.config( ));}
+        changed
+      });
+      leaderSender.send_if_modified(|leader| {
+        let changed= *leader != node.leader( );
+        *leader= node.leader( );
+        changed
+      });
+    }
+
+    stateMachineDriverTask.abort( );
+    Ok(( ))
+  }
+}
+
+/*
+  A handle for sending requests to the local Raft node from synchronous code (like the SQL engine),
+  which blocks until the response arrives.
+
+  NOTE : Since it blocks, it must not be used from within an async task (use spawn_blocking( )).
+
+  A mutation rejected by an overloaded leader (with ServerBusy) isn't retried by mutate( ), since
+  that's left to the client (after backing off). Only mutateUrgently( ) retries it.
+*/
+#[derive(Clone)]
+pub struct Client {
+  requestSender: Sender<ClientRequest>,
+
+  // The applied index of the local node's state machine (see Server::watchAppliedIndex( )).
+  appliedIndex: watch::Receiver<LogEntryIndex>,
+
+  // Number of the requests which found the request channel full, and waited for room in it.
+  deferredRequests: Arc<AtomicU64>
+}
+
+impl Client {
+  pub fn new(requestSender: Sender<ClientRequest>, appliedIndex: watch::Receiver<LogEntryIndex>) -> Self {
+    Self { requestSender, appliedIndex, deferredRequests: Arc::default( ) }
+  }
+
+  // Sends the request (with a new id) to the node and waits for the response.
+  pub fn request(&self, request: Request) -> Result<Response> {
+    self.requestWithId(newRequestId( ), request)
+  }
+
+  // Sends the request with the given id to the node and waits for the response.
+  fn requestWithId(&self, id: RequestId, request: Request) -> Result<Response> {
+    let (responseSender, responseReceiver)= oneshot::channel( );
+    let shutDown= | | Error::Internal("Raft server has shut down".to_string( ));
+
+    match self.requestSender.try_send((id, request, responseSender)) {
+      Ok(_) => { },
+      Err(TrySendError::Closed(_)) => return Err(shutDown( )),
+
+      Err(TrySendError::Full(request)) => {
+        self.deferredRequests.fetch_add(1, Ordering::Relaxed);
+        self.requestSender.blocking_send(request).map_err(|_| shutDown( ))?;
+      }
+    }
+
+    responseReceiver.blocking_recv( )
+                    .map_err(|_| Error::Internal("Raft server dropped the request".to_string( )))?
+  }
+
+  /*
+    Submits a state machine command through the log, and returns its result along with the index of
+    the entry it was applied at.
+
+    An aborted mutation (like when the leader steps down) might still get committed. So it's retried
+    with the same request id, which makes sure that the command is applied only once.
+  */
+  pub fn mutate(&self, command: Vec<u8>) -> Result<(Vec<u8>, LogEntryIndex)> {
+    self.mutateRetrying(command, false)
+  }
+
+  // Like mutate( ), but retries the mutation while the leader is overloaded as well. For the
+  // mutations which must get through, like the ones ending a transaction (which release what the
+  // transaction holds).
+  pub fn mutateUrgently(&self, command: Vec<u8>) -> Result<(Vec<u8>, LogEntryIndex)> {
+    self.mutateRetrying(command, true)
+  }
+
+  fn mutateRetrying(&self, command: Vec<u8>, retryBusy: bool) -> Result<(Vec<u8>, LogEntryIndex)> {
+    let id= newRequestId( );
+
+    let mut retries= 0;
+    loop {
+      match self.requestWithId(id.clone( ), Request::Mutate(command.clone( ))) {
+        Err(Error::Abort) if retries < MUTATION_RETRIES => {
+          debug!("Retrying aborted mutation {:?}", id);
+
+          retries += 1;
+          thread::sleep(MUTATION_RETRY_INTERVAL);
+        },
+
+        Err(Error::Value(ErrorCode::ServerBusy, _)) if retryBusy && retries < MUTATION_RETRIES => {
+          debug!("Retrying mutation {:?}, rejected by the overloaded leader", id);
+
+          retries += 1;
+          thread::sleep(MUTATION_RETRY_INTERVAL);
+        },
+
+        Ok(Response::Mutate { result, index }) => return Ok((result, index)),
+        Ok(response) => return Err(Error::Internal(format!("Unexpected response {:?} to a mutation", response))),
+        Err(error) => return Err(error)
+      }
+    }
+  }
+
+  // Adds a node (listening for Raft messages on the given address) to the cluster, once the
+  // change is committed.
+  pub fn addNode(&self, id: NodeId, address: String) -> Result<( )> {
+    self.changeMembership(Request::AddNode { id, address })
+  }
+
+  // Removes a node from the cluster, once the change is committed.
+  pub fn removeNode(&self, id: NodeId) -> Result<( )> {
+    self.changeMembership(Request::RemoveNode(id))
+  }
+
+  /*
+    Transfers the leadership to the given node (or to the most up-to-date peer, if none is given),
+    once the leader steps down for the target's term.
+
+    NOTE : A transfer forwarded by a follower gets aborted by it, when the new leader's term begins
+    (which may happen before the old leader's response arrives). So on abort, the local node is
+    watched for a while, to find out whether the transfer went through.
+  */
+  pub fn transferLeadership(&self, target: Option<NodeId>) -> Result<( )> {
+    let previous= self.status( )?;
+
+    match self.request(Request::TransferLeadership(target)) {
+      Ok(Response::TransferLeadership) => Ok(( )),
+      Ok(response) => Err(Error::Internal(format!("Unexpected response {:?} to a leadership transfer", response))),
+
+      Err(Error::Abort) => {
+        for _ in 0..MUTATION_RETRIES {
+          let status= self.status( )?;
+          if let Some(leader)= status.leader.filter(|_| status.currentTerm > previous.currentTerm) {
+            return match target.is_none_or(|target| target == leader) && Some(leader) != previous.leader {
+              true => Ok(( )),
+              false => Err(Error::Abort)
+            }
+          }
+          thread::sleep(MUTATION_RETRY_INTERVAL);
+        }
+        Err(Error::Abort)
+      },
+
+      Err(error) => Err(error)
+    }
+  }
+
+  // Returns the status of the local node.
+  pub fn status(&self) -> Result<Status> {
+    match self.request(Request::Status)? {
+      Response::Status(status) => Ok(Status { deferredRequests: self.deferredRequests.load(Ordering::Relaxed), ..*status }),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a status request", response)))
+    }
+  }
+
+  // Executes a read-only state machine command (after confirming the leadership of the leader),
+  // and returns its result.
+  pub fn query(&self, command: Vec<u8>) -> Result<Vec<u8>> {
+    match self.request(Request::Query(command))? {
+      Response::Query(result) => Ok(result),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a query", response)))
+    }
+  }
+
+  /*
+    Executes a read-only state machine command against the local node's state machine (see
+    Request::LocalQuery), and returns its (possibly stale) result.
+
+    The command observes the entries upto the given index (like the ones of the client's own writes).
+    If the state machine doesn't apply them within the timeout (like when the node is lagging behind
+    the leader), then it fails with the retryable ReplicaLagging error - rather than waiting on the
+    node indefinitely.
+  */
+  pub fn queryLocally(&self, command: Vec<u8>, index: LogEntryIndex, timeout: Duration) -> Result<Vec<u8>> {
+    self.awaitApplied(index, timeout)?;
+
+    match self.request(Request::LocalQuery { command, index })? {
+      Response::Query(result) => Ok(result),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a local query", response)))
+    }
+  }
+}
+
+impl Client {
+  // NOTE : A membership change isn't retried when it's aborted, since it may have been committed.
+  fn changeMembership(&self, request: Request) -> Result<( )> {
+    match self.request(request)? {
+      Response::Mutate { .. } => Ok(( )),
+      response => Err(Error::Internal(format!("Unexpected response {:?} to a membership change", response)))
+    }
+  }
+
+  // Waits for the local node's state machine to apply the entries upto the given index, for atmost
+  // the given duration.
+  // NOTE : It blocks on the runtime the client is used from (see spawn_blocking( )).
+  fn awaitApplied(&self, index: LogEntryIndex, timeout: Duration) -> Result<( )> {
+    let mut appliedIndex= self.appliedIndex.clone( );
+    if *appliedIndex.borrow( ) >= index {
+      return Ok(( ))}
+
+    let waited= tokio::runtime::Handle::current( ).block_on(async {
+      tokio::time::timeout(timeout, appliedIndex.wait_for(|appliedIndex| *appliedIndex >= index)).await
+    });
+
+    match waited {
+      Ok(Ok(_)) => Ok(( )),
+      Ok(Err(_)) => Err(Error::Internal("State-machine driver has shut down".to_string( ))),
+
+      Err(_) => Err(Error::Value(ErrorCode::ReplicaLagging, format!(
+        "The local replica has applied the log upto index {}, but not upto index {} within {} ms : retry later",
+        *self.appliedIndex.borrow( ), index, timeout.as_millis( )
+      )))
+    }
+  }
+}
+
+// Generates a random id for a client request.
+fn newRequestId( ) -> RequestId {
+  let mut id= vec![0; 16];
+  thread_rng( ).fill_bytes(&mut id);
+  id
+}
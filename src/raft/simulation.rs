@@ -0,0 +1,435 @@
+use std::{collections::{BTreeMap, HashMap}, ops::RangeInclusive};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use crate::{result::{Error, Result}, storage::{engine::memory::Memory, sync::SyncPolicy}};
+use super::{
+  cluster_config::ClusterConfig,
+  log::{Log, LogEntry, DEFAULT_CACHE_CAPACITY},
+  message::{Message, MessageAddress, MessagePayload, Request, RequestId, Response},
+  node::{leader::ReplicationLimits, Node},
+  state_machine_driver::{StateMachine, StateMachineDriver},
+  types::{LogEntryIndex, NodeId, Term}
+};
+
+/*
+  A deterministic simulation of a Raft cluster, for testing the nodes under adverse conditions (see
+  tests/simulate.rs).
+
+  The nodes run in a single thread, against a simulated clock : each step of the simulation moves
+  the logical clocks of all the nodes by a tick. The messages between the nodes go through a
+  simulated network, which delays each of them by a random number of ticks (so they may arrive out
+  of order), drops them with the given probability, and drops the ones between partitioned nodes.
+
+  All the randomness (including the election timeouts of the nodes) is drawn from a RNG seeded with
+  the given seed. So a run is reproduced exactly, by running it again with the same seed.
+
+  After every step, the safety properties of Raft are checked :
+  (a) Election safety - atmost one leader is elected in a term.
+  (b) State machine safety - the nodes apply the same entry at a given index.
+*/
+pub struct Simulation {
+  nodes: BTreeMap<NodeId, SimulatedNode>,
+
+  network: Network,
+  rng: StdRng,
+
+  // Number of ticks elapsed since the simulation started.
+  now: u64,
+
+  // Messages in flight, by the tick they're delivered at (and the order they were sent in).
+  inflightMessages: BTreeMap<(u64, u64), Message>,
+  sentMessages: u64,
+
+  // Group of each node, while the cluster is partitioned. Nodes in different groups can't reach
+  // each other.
+  partitions: HashMap<NodeId, usize>,
+
+  // Responses to the client requests, by their ids.
+  responses: HashMap<RequestId, Result<Response>>,
+  nextRequestId: u64,
+
+  // The leader elected in each term, and the entry applied at each index (by any of the nodes).
+  leaders: BTreeMap<Term, NodeId>,
+  appliedEntries: BTreeMap<LogEntryIndex, Option<Vec<u8>>>
+}
+
+// Conditions of the simulated network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+  // Probability of a message being dropped.
+  pub dropProbability: f64,
+
+  // Number of ticks a message takes to be delivered, picked randomly from the range.
+  pub delay: RangeInclusive<u64>
+}
+
+impl Default for Network {
+  // A reliable network, delivering every message in the next tick.
+  fn default( ) -> Self {
+    Self { dropProbability: 0.0, delay: 1..=1 }
+  }
+}
+
+struct SimulatedNode {
+  // NOTE : Only None while the node is processing a message or a tick (since that consumes it).
+  node: Option<Node>,
+
+  // Messages sent by the node (and by its state-machine driver).
+  messageReceiver: UnboundedReceiver<Message>,
+
+  driver: StateMachineDriver,
+  stateMachine: RecordingStateMachine,
+
+  // Whether the driver is held back (see Simulation::pauseApplying( )).
+  applyingPaused: bool,
+
+  // Index upto which the entries applied by the node have been checked.
+  checkedIndex: LogEntryIndex
+}
+
+impl Simulation {
+  // Creates a simulation of a new cluster of the given number of nodes (with ids starting from 1),
+  // each with the given replication limits.
+  pub fn new(nodeCount: u64, seed: u64, network: Network, replicationLimits: ReplicationLimits) -> Result<Self> {
+    let mut rng= StdRng::seed_from_u64(seed);
+
+    let config= ClusterConfig::new((1..=nodeCount).map(|id| (NodeId(id), format!("node-{}", id))).collect( ));
+
+    let mut nodes= BTreeMap::new( );
+    for &id in config.nodes.keys( ) {
+      let mut log= Log::new(Box::new(Memory::new( )), DEFAULT_CACHE_CAPACITY, SyncPolicy::default( ))?;
+      log.bootstrap(config.clone( ))?;
+
+      let (messageSender, messageReceiver)= unbounded_channel( );
+      let (instructionsSender, instructionsReceiver)= unbounded_channel( );
+
+      let driver= StateMachineDriver::new(id, instructionsReceiver, messageSender.clone( ));
+      let random= Box::new(StdRng::seed_from_u64(rng.gen( )));
+      let node= Node::new(id, log, messageSender, instructionsSender, replicationLimits, random)?;
+
+      nodes.insert(id, SimulatedNode {
+        node: Some(node),
+        messageReceiver,
+        driver,
+        stateMachine: RecordingStateMachine::default( ),
+        applyingPaused: false,
+        checkedIndex: 0
+      });
+    }
+
+    Ok(Self {
+      nodes,
+      network,
+      rng,
+      now: 0,
+      inflightMessages: BTreeMap::new( ),
+      sentMessages: 0,
+      partitions: HashMap::new( ),
+      responses: HashMap::new( ),
+      nextRequestId: 0,
+      leaders: BTreeMap::new( ),
+      appliedEntries: BTreeMap::new( )
+    })
+  }
+
+  pub fn setNetwork(&mut self, network: Network) {
+    self.network= network;
+  }
+
+  // Partitions the cluster into the given groups of nodes. The nodes left out of the groups are
+  // isolated, each in a group of its own. The messages in flight between the groups are lost.
+  pub fn partition(&mut self, groups: &[&[NodeId]]) {
+    self.partitions= self.nodes.keys( ).enumerate( ).map(|(i, &id)| (id, groups.len( ) + i)).collect( );
+
+    for (group, ids) in groups.iter( ).enumerate( ) {
+      for id in *ids {
+        self.partitions.insert(*id, group);}
+    }
+  }
+
+  // Heals the partitions, if any.
+  pub fn heal(&mut self) {
+    self.partitions.clear( );
+  }
+
+  // Holds back the state-machine driver of the node, like that of a node whose replica lags behind :
+  // the instructions sent to it (to apply the committed entries, or to execute queries) pile up,
+  // until applying is resumed.
+  pub fn pauseApplying(&mut self, nodeId: NodeId) {
+    if let Some(node)= self.nodes.get_mut(&nodeId) {
+      node.applyingPaused= true;}
+  }
+
+  // Lets the state-machine driver of the node process the instructions which have piled up.
+  pub fn resumeApplying(&mut self, nodeId: NodeId) -> Result<( )> {
+    if let Some(node)= self.nodes.get_mut(&nodeId) {
+      node.applyingPaused= false;}
+
+    self.process(nodeId, Ok)
+  }
+
+  // Sends a request (with a new id) from a client of the given node, and returns the id of the
+  // request. The response is collected once it arrives (see takeResponse( )).
+  pub fn request(&mut self, nodeId: NodeId, request: Request) -> Result<RequestId> {
+    self.nextRequestId += 1;
+    let id= self.nextRequestId.to_be_bytes( ).to_vec( );
+
+    self.requestWithId(nodeId, id.clone( ), request)?;
+    Ok(id)
+  }
+
+  // Sends a request with the given id (like a retry of an earlier request), from a client of the
+  // given node.
+  pub fn requestWithId(&mut self, nodeId: NodeId, id: RequestId, request: Request) -> Result<( )> {
+    let message= Message {
+      // Client requests aren't bound to terms.
+      currentTermOfSender: 0,
+
+      from: MessageAddress::Client,
+      to: MessageAddress::Node(nodeId),
+      payload: MessagePayload::ClientRequest { id, request }
+    };
+    self.process(nodeId, |node| node.processMessage(message))
+  }
+
+  // Returns the response to the client request, if it has arrived.
+  pub fn takeResponse(&mut self, id: &RequestId) -> Option<Result<Response>> {
+    self.responses.remove(id)
+  }
+
+  /*
+    Moves the simulation by a tick : delivers the messages due by then, and moves the logical clocks
+    of the nodes.
+
+    Returns error if a node fails, or if a safety property has been violated.
+  */
+  pub fn step(&mut self) -> Result<( )> {
+    self.now += 1;
+
+    while let Some(entry)= self.inflightMessages.first_entry( ) {
+      if entry.key( ).0 > self.now {
+        break}
+
+      let message= entry.remove( );
+      let (MessageAddress::Node(from), MessageAddress::Node(to))= (message.from, message.to) else {
+        return Err(Error::Internal(format!("Unexpected message {:?} in flight", message)))
+      };
+
+      if self.isReachable(from, to) {
+        self.process(to, |node| node.processMessage(message))?;}
+    }
+
+    let ids= self.nodes.keys( ).copied( ).collect::<Vec<_>>( );
+    for id in ids {
+      self.process(id, Node::tick)?;}
+
+    self.checkSafety( )
+  }
+
+  // Moves the simulation by the given number of ticks.
+  pub fn run(&mut self, ticks: u64) -> Result<( )> {
+    for _ in 0..ticks {
+      self.step( )?;}
+
+    Ok(( ))
+  }
+
+  // Moves the simulation until the condition holds, for atmost the given number of ticks. Returns
+  // whether the condition holds.
+  pub fn runUntil(&mut self, maxTicks: u64, condition: impl Fn(&Self) -> bool) -> Result<bool> {
+    for _ in 0..maxTicks {
+      if condition(self) {
+        return Ok(true)}
+
+      self.step( )?;
+    }
+    Ok(condition(self))
+  }
+
+  // Returns the number of ticks elapsed since the simulation started.
+  pub fn now(&self) -> u64 {
+    self.now
+  }
+
+  pub fn nodeIds(&self) -> Vec<NodeId> {
+    self.nodes.keys( ).copied( ).collect( )
+  }
+
+  // Returns the nodes which consider themselves leaders, along with their terms.
+  // NOTE : There can be more than one, like a leader cut off from the cluster which hasn't stepped
+  // down yet. They're leaders of different terms, though.
+  pub fn leaders(&self) -> Vec<(NodeId, Term)> {
+    self.nodes.iter( )
+              .filter_map(|(id, node)| match node.node.as_ref( )? {
+                Node::Leader(_) => Some((*id, node.currentTerm( ))),
+                _ => None
+              })
+              .collect( )
+  }
+
+  // Returns the leader of the latest term, which every other node reachable from it follows (so it's
+  // uncontested).
+  pub fn leader(&self) -> Option<NodeId> {
+    let (leader, _)= self.leaders( ).into_iter( ).max_by_key(|(_, term)| *term)?;
+
+    self.nodes.iter( )
+              .filter(|(id, _)| self.isReachable(leader, **id))
+              .all(|(_, node)| node.node.as_ref( ).and_then(Node::leader) == Some(leader))
+              .then_some(leader)
+  }
+
+  pub fn currentTerm(&self, nodeId: NodeId) -> Term {
+    self.nodes.get(&nodeId).map(SimulatedNode::currentTerm).unwrap_or_default( )
+  }
+
+  // Returns the commands applied by the state machine of the node, in the order they were applied.
+  pub fn appliedCommands(&self, nodeId: NodeId) -> Vec<Vec<u8>> {
+    self.nodes.get(&nodeId)
+              .map(|node| node.stateMachine.entries.values( ).flatten( ).cloned( ).collect( ))
+              .unwrap_or_default( )
+  }
+}
+
+impl Simulation {
+  fn isReachable(&self, from: NodeId, to: NodeId) -> bool {
+    self.partitions.get(&from) == self.partitions.get(&to)
+  }
+
+  /*
+    Feeds the node a message or a tick, lets its state-machine driver process the instructions sent
+    to it (unless applying is paused), and then routes the messages they sent.
+
+    NOTE : The messages are routed in the order of their destinations, since the order in which a node
+    sends messages to its different peers isn't deterministic (it iterates over hash maps). That
+    order decides the random numbers drawn for the messages.
+  */
+  fn process(&mut self, nodeId: NodeId, action: impl FnOnce(Node) -> Result<Node>) -> Result<( )> {
+    let node= self.nodes.get_mut(&nodeId)
+                        .ok_or_else(| | Error::Internal(format!("Node {} isn't a part of the simulation", nodeId)))?;
+
+    let processed= action(node.node.take( ).expect("Node is missing"))?;
+    node.node= Some(processed);
+
+    if !node.applyingPaused {
+      node.driver.processPendingInstructions(&mut node.stateMachine)?;}
+
+    let mut messages= Vec::new( );
+    while let Ok(message)= node.messageReceiver.try_recv( ) {
+      messages.push(message);}
+    messages.sort_by_key(|message| match message.to {
+      MessageAddress::Node(id) => Some(id),
+      _ => None
+    });
+
+    for message in messages {
+      self.route(nodeId, message);}
+
+    Ok(( ))
+  }
+
+  // Sends the message over the network, or hands it to the waiting client.
+  fn route(&mut self, nodeId: NodeId, message: Message) {
+    match message.to {
+      MessageAddress::Node(to) => self.send(message, to),
+
+      MessageAddress::Broadcast => {
+        // Only the members of the configuration in effect on the node are reached (like through the
+        // transport).
+        let peers= self.nodes[&nodeId].node.as_ref( ).map(|node| node.config( ).getPeersOf(nodeId)).unwrap_or_default( );
+        let mut peers= peers.into_iter( ).collect::<Vec<_>>( );
+        peers.sort( );
+
+        for peer in peers {
+          self.send(message.clone( ), peer);}
+      },
+
+      MessageAddress::Client => {
+        if let MessagePayload::ResponseToClient { id, response }= message.payload {
+          self.responses.insert(id, response);}
+      }
+    }
+  }
+
+  fn send(&mut self, message: Message, to: NodeId) {
+    if self.rng.gen_bool(self.network.dropProbability) {
+      return}
+
+    let delay= self.rng.gen_range(self.network.delay.clone( ));
+    self.sentMessages += 1;
+    self.inflightMessages.insert((self.now + delay, self.sentMessages), Message { to: MessageAddress::Node(to), ..message });
+  }
+
+  fn checkSafety(&mut self) -> Result<( )> {
+    for (&id, node) in &mut self.nodes {
+      if let Some(Node::Leader(_))= node.node {
+        let term= node.currentTerm( );
+        let leader= *self.leaders.entry(term).or_insert(id);
+
+        if leader != id {
+          return Err(Error::Internal(format!("Election safety violated : nodes {} and {} were both elected leaders in term {}", leader, id, term)))}
+      }
+
+      for (&index, command) in node.stateMachine.entries.range(node.checkedIndex + 1 ..) {
+        let applied= self.appliedEntries.entry(index).or_insert_with(| | command.clone( ));
+
+        if applied != command {
+          return Err(Error::Internal(format!(
+            "State machine safety violated : node {} applied {:?} at index {}, while another node applied {:?}", id, command, index, applied
+          )))}
+      }
+      node.checkedIndex= node.stateMachine.appliedIndex;
+    }
+    Ok(( ))
+  }
+}
+
+impl SimulatedNode {
+  fn currentTerm(&self) -> Term {
+    self.node.as_ref( ).map(Node::currentTerm).unwrap_or_default( )
+  }
+}
+
+/*
+  The state machine of the simulated nodes. It records the entries applied to it (so that the nodes
+  can be checked to apply the same ones), and returns the command of an entry as its result.
+*/
+#[derive(Default, Serialize, Deserialize)]
+struct RecordingStateMachine {
+  appliedIndex: LogEntryIndex,
+
+  // The command of each applied entry (None for the entries without a command).
+  entries: BTreeMap<LogEntryIndex, Option<Vec<u8>>>
+}
+
+impl StateMachine for RecordingStateMachine {
+  fn apply(&mut self, entry: LogEntry) -> Result<Vec<u8>> {
+    self.appliedIndex= entry.index;
+    self.entries.insert(entry.index, entry.command.clone( ));
+
+    Ok(entry.command.unwrap_or_default( ))
+  }
+
+  fn appliedIndex(&self) -> LogEntryIndex {
+    self.appliedIndex
+  }
+
+  // Returns the number of the entries applied.
+  fn query(&self, _command: Vec<u8>) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(&(self.entries.len( ) as u64))?)
+  }
+
+  fn snapshot(&self) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(self)?)
+  }
+
+  fn restore(&mut self, index: LogEntryIndex, state: Vec<u8>) -> Result<( )> {
+    *self= bincode::deserialize(&state)?;
+    self.appliedIndex= index;
+    Ok(( ))
+  }
+
+  fn status(&self) -> Result<Vec<u8>> {
+    Ok(Vec::new( ))
+  }
+}
@@ -1 +1,374 @@
-pub enum StateMachineInstruction { }
\ No newline at end of file
+use std::{collections::{BTreeMap, HashMap, VecDeque}, ops::Bound};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc::{UnboundedReceiver, UnboundedSender}, oneshot, watch};
+use tracing::{debug, error};
+use crate::result::{Error, ErrorCode, Result};
+use super::{
+  log::{Log, LogEntry, Snapshot}, message::{Message, MessageAddress, MessagePayload, RequestId, Response}, node::Status,
+  types::{LogEntryIndex, NodeId}
+};
+
+/*
+  Represents the replicated state machine. Committed log entries are applied to it, in log order.
+
+  Applying an entry must be deterministic : given the same sequence of entries, every replica must
+  end up in the same state and produce the same results. Errors returned while applying an entry
+  are considered to be deterministic (like a constraint violation), and are returned to the client
+  as the result of the command - except Error::Internal, which represents a non-deterministic
+  failure (like an IO error).
+*/
+pub trait StateMachine: Send {
+  // Applies a committed entry to the state machine and returns the result.
+  // NOTE : The state machine must also record the entry's index as its applied index (even for
+  // entries without a command).
+  fn apply(&mut self, entry: LogEntry) -> Result<Vec<u8>>;
+
+  // Returns the index of the last applied entry.
+  fn appliedIndex(&self) -> LogEntryIndex;
+
+  // Executes a read-only command against the state machine.
+  fn query(&self, command: Vec<u8>) -> Result<Vec<u8>>;
+
+  // Returns the serialized state of the state machine, as of the applied index. Used to compact the
+  // log.
+  fn snapshot(&self) -> Result<Vec<u8>>;
+
+  // Replaces the state of the state machine by the given serialized state (taken after applying the
+  // entries upto the given index), and records the index as the applied index.
+  fn restore(&mut self, index: LogEntryIndex, state: Vec<u8>) -> Result<( )>;
+
+  // Returns the serialized status of the state machine, which is reported along with the node's
+  // status.
+  fn status(&self) -> Result<Vec<u8>>;
+}
+
+// Represents an instruction sent by the Raft node to the state-machine driver.
+pub enum StateMachineInstruction {
+  // Apply a committed entry to the state machine. If the entry was proposed on behalf of a client,
+  // then the result is sent back to that client.
+  Apply {
+    entry: LogEntry,
+    client: Option<(MessageAddress, RequestId)>
+  },
+
+  // Execute a read-only command, once all the entries upto the given index have been applied.
+  Query {
+    id: RequestId,
+    client: MessageAddress,
+    command: Vec<u8>,
+    index: LogEntryIndex
+  },
+
+  // Fill in the state machine's part of the node's status, and send it to the client.
+  GetStatus {
+    id: RequestId,
+    client: MessageAddress,
+    status: Box<Status>
+  },
+
+  // Reject all the pending queries (like when the leader steps down).
+  Abort,
+
+  // Take a snapshot of the state machine, and send it (along with the applied index it's taken at)
+  // back to the Raft node - which compacts its log using it.
+  Snapshot {
+    sender: oneshot::Sender<(LogEntryIndex, Vec<u8>)>
+  },
+
+  // Restore the state machine from a snapshot (installed by the leader).
+  Restore {
+    snapshot: Snapshot
+  }
+}
+
+// Number of the most recently applied client requests, whose results are remembered for answering
+// retries of those requests.
+const APPLIED_REQUESTS_CAPACITY: usize= 10_000;
+
+/*
+  Results of the recently applied client requests, by their ids.
+
+  A client retries a request with the same id, when it doesn't know whether the request got
+  committed (like when the leader stepped down before responding). If the retried request has been
+  applied already, the remembered result is returned instead of applying it again.
+
+  Whether an entry gets applied must be the same on every replica. So the applied requests are part
+  of the replicated state : they're recorded in log order, included in the snapshots, and recovered
+  from the log on restart.
+*/
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct AppliedRequests {
+  results: HashMap<RequestId, Result<Vec<u8>>>,
+
+  // Ids of the applied requests, from the oldest to the latest.
+  ids: VecDeque<RequestId>
+}
+
+impl AppliedRequests {
+  fn get(&self, id: &RequestId) -> Option<&Result<Vec<u8>>> {
+    self.results.get(id)
+  }
+
+  // Records the result of an applied request, forgetting the oldest request if the capacity is
+  // exceeded.
+  fn insert(&mut self, id: RequestId, result: Result<Vec<u8>>) {
+    if self.results.insert(id.clone( ), result).is_some( ) {
+      return}
+    self.ids.push_back(id);
+
+    if self.ids.len( ) > APPLIED_REQUESTS_CAPACITY {
+      if let Some(oldestId)= self.ids.pop_front( ) {
+        self.results.remove(&oldestId);}
+    }
+  }
+}
+
+// The state of the state machine along with the applied requests, which the snapshots are taken of.
+#[derive(Serialize, Deserialize)]
+struct DriverSnapshot {
+  appliedRequests: AppliedRequests,
+  state: Vec<u8>
+}
+
+// Queries waiting for the state machine to catch up with the index they need to observe.
+struct PendingQuery {
+  id: RequestId,
+  client: MessageAddress,
+  command: Vec<u8>
+}
+
+/*
+  Drives the state machine : receives instructions from the Raft node, applies committed entries to
+  the state machine (in order) and sends the results back to the clients.
+
+  The applied index of the state machine is published (see watchAppliedIndex( )), so that the local
+  clients can wait for the replica to catch up with their writes.
+*/
+pub struct StateMachineDriver {
+  nodeId: NodeId,
+
+  instructionsReceiver: UnboundedReceiver<StateMachineInstruction>,
+
+  // Sends responses to the clients (through the Raft node's outbound message channel).
+  messageSender: UnboundedSender<Message>,
+
+  pendingQueries: BTreeMap<LogEntryIndex, Vec<PendingQuery>>,
+
+  appliedRequests: AppliedRequests,
+
+  appliedIndexSender: watch::Sender<LogEntryIndex>
+}
+
+impl StateMachineDriver {
+  pub fn new(nodeId: NodeId,
+             instructionsReceiver: UnboundedReceiver<StateMachineInstruction>,
+             messageSender: UnboundedSender<Message>) -> Self
+  {
+    Self {
+      nodeId,
+      instructionsReceiver,
+      messageSender,
+      pendingQueries: BTreeMap::new( ),
+      appliedRequests: AppliedRequests::default( ),
+      appliedIndexSender: watch::channel(0).0
+    }
+  }
+
+  // Returns a receiver of the applied index of the state machine, which is updated whenever entries
+  // get applied (or a snapshot gets restored).
+  pub fn watchAppliedIndex(&self) -> watch::Receiver<LogEntryIndex> {
+    self.appliedIndexSender.subscribe( )
+  }
+
+  // Applies the committed entries from the log, which the state machine hasn't applied yet. Used to
+  // catch up the state machine on startup, before the Raft node starts running. If the state machine
+  // is behind the log's snapshot, it's restored from the snapshot first.
+  //
+  // The applied requests are recovered from the snapshot and the entries applied after it. The
+  // results of those entries aren't known anymore though.
+  pub fn applyCommittedEntries(&mut self, stateMachine: &mut dyn StateMachine, log: &mut Log) -> Result<( )> {
+    let (snapshotIndex, _)= log.getSnapshotIndexAndTerm( );
+
+    if snapshotIndex > 0 {
+      let snapshot= log.getSnapshot( )?
+                       .ok_or_else(| | Error::Internal(format!("Snapshot upto index {} not found", snapshotIndex)))?;
+
+      match stateMachine.appliedIndex( ) < snapshotIndex {
+        true => self.restore(stateMachine, snapshot)?,
+        false => self.appliedRequests= bincode::deserialize::<DriverSnapshot>(&snapshot.state)?.appliedRequests
+      }
+    }
+
+    let appliedEntries= log.scan((Bound::Excluded(snapshotIndex), Bound::Included(stateMachine.appliedIndex( ))))
+                           .collect::<Result<Vec<_>>>( )?;
+    for entry in appliedEntries {
+      if let Some(id)= entry.requestId {
+        let result= Err(Error::Value(ErrorCode::StatementCompletionUnknown, format!("Request was applied already in entry {}, but its result is unknown", entry.index)));
+        self.appliedRequests.insert(id, result);
+      }
+    }
+
+    let unappliedEntries= log.scanUnapplied(stateMachine.appliedIndex( ))
+                             .collect::<Result<Vec<_>>>( )?;
+
+    for entry in unappliedEntries {
+      self.apply(stateMachine, entry, None)?;}
+
+    self.appliedIndexSender.send_replace(stateMachine.appliedIndex( ));
+    Ok(( ))
+  }
+
+  // Processes instructions until the Raft node shuts down. Returns error, if the state machine fails
+  // non-deterministically (in which case the driver halts, since the replica can't stay consistent).
+  pub async fn run(mut self, mut stateMachine: Box<dyn StateMachine>) -> Result<( )> {
+    while let Some(instruction)= self.instructionsReceiver.recv( ).await {
+      if let Err(error)= self.processInstruction(stateMachine.as_mut( ), instruction) {
+        error!("Halting the state-machine driver : {}", error);
+        return Err(error)
+      }
+    }
+
+    Ok(( ))
+  }
+
+  // Processes the instructions received so far, without waiting for more. Used to drive the state
+  // machine synchronously, along with the Raft node (like in the simulations).
+  pub fn processPendingInstructions(&mut self, stateMachine: &mut dyn StateMachine) -> Result<( )> {
+    while let Ok(instruction)= self.instructionsReceiver.try_recv( ) {
+      self.processInstruction(stateMachine, instruction)?;}
+
+    Ok(( ))
+  }
+
+  pub fn processInstruction(&mut self, stateMachine: &mut dyn StateMachine, instruction: StateMachineInstruction) -> Result<( )> {
+    match instruction {
+      StateMachineInstruction::Apply { entry, client } => self.apply(stateMachine, entry, client),
+
+      StateMachineInstruction::Query { id, client, command, index } => {
+        self.pendingQueries.entry(index).or_default( ).push(PendingQuery { id, client, command });
+        self.executeQueries(stateMachine)
+      },
+
+      StateMachineInstruction::GetStatus { id, client, mut status } => {
+        status.appliedIndex= stateMachine.appliedIndex( );
+        status.stateMachine= stateMachine.status( )?;
+
+        self.respond(client, id, Ok(Response::Status(status)))
+      },
+
+      StateMachineInstruction::Abort => {
+        for query in std::mem::take(&mut self.pendingQueries).into_values( ).flatten( ) {
+          self.respond(query.client, query.id, Err(Error::Abort))?;}
+
+        Ok(( ))
+      },
+
+      StateMachineInstruction::Snapshot { sender } => {
+        let state= bincode::serialize(&DriverSnapshot { appliedRequests: self.appliedRequests.clone( ), state: stateMachine.snapshot( )? })?;
+        let snapshot= (stateMachine.appliedIndex( ), state);
+
+        // The node may have stopped waiting (like when it's shutting down).
+        let _= sender.send(snapshot);
+        Ok(( ))
+      },
+
+      StateMachineInstruction::Restore { snapshot } => self.restore(stateMachine, snapshot)
+    }
+  }
+}
+
+impl StateMachineDriver {
+  fn apply(&mut self,
+           stateMachine: &mut dyn StateMachine,
+           entry: LogEntry,
+           client: Option<(MessageAddress, RequestId)>) -> Result<( )>
+  {
+    let appliedIndex= stateMachine.appliedIndex( );
+
+    // The entry has already been applied (like when replaying the log after a restart).
+    if entry.index <= appliedIndex {
+      debug!("Skipping already applied entry {}", entry.index);
+      return Ok(( ))
+    }
+
+    if entry.index != appliedIndex + 1 {
+      return Err(Error::Internal(format!(
+        "Can't apply entry {}, since the last applied entry is {}", entry.index, appliedIndex
+      )))}
+
+    // A retried request, which has been applied already, is answered with the remembered result.
+    // The entry is still applied as a noop, so that the applied index advances. Its index is
+    // returned, since it's after the one the request was applied at.
+    let (index, requestId)= (entry.index, entry.requestId.clone( ));
+    if let Some(result)= requestId.as_ref( ).and_then(|id| self.appliedRequests.get(id)).cloned( ) {
+      debug!("Entry {} is a retry of an applied request", entry.index);
+      stateMachine.apply(LogEntry { command: None, ..entry })?;
+
+      if let Some((client, id))= client {
+        self.respond(client, id, result.map(|result| Response::Mutate { result, index }))?;}
+
+      return self.executeQueries(stateMachine)
+    }
+
+    debug!("Applying entry {}", index);
+    let result= match stateMachine.apply(entry) {
+      Err(error @ Error::Internal(_)) => return Err(error),
+      result => result
+    };
+
+    if let Some(id)= requestId {
+      self.appliedRequests.insert(id, result.clone( ));}
+
+    if let Some((client, id))= client {
+      self.respond(client, id, result.map(|result| Response::Mutate { result, index }))?;}
+
+    self.executeQueries(stateMachine)
+  }
+
+  fn restore(&mut self, stateMachine: &mut dyn StateMachine, snapshot: Snapshot) -> Result<( )> {
+    let appliedIndex= stateMachine.appliedIndex( );
+
+    if snapshot.index <= appliedIndex {
+      return Err(Error::Internal(format!(
+        "Can't restore snapshot upto index {}, since the last applied entry is {}", snapshot.index, appliedIndex
+      )))}
+
+    debug!("Restoring snapshot upto index {}", snapshot.index);
+    let DriverSnapshot { appliedRequests, state }= bincode::deserialize(&snapshot.state)?;
+    stateMachine.restore(snapshot.index, state)?;
+    self.appliedRequests= appliedRequests;
+
+    self.executeQueries(stateMachine)
+  }
+
+  // Publishes the applied index, and executes the pending queries whose required entries have been
+  // applied.
+  fn executeQueries(&mut self, stateMachine: &mut dyn StateMachine) -> Result<( )> {
+    let appliedIndex= stateMachine.appliedIndex( );
+    self.appliedIndexSender.send_replace(appliedIndex);
+
+    let notReadyQueries= self.pendingQueries.split_off(&(appliedIndex + 1));
+    let readyQueries= std::mem::replace(&mut self.pendingQueries, notReadyQueries);
+
+    for query in readyQueries.into_values( ).flatten( ) {
+      let result= stateMachine.query(query.command).map(Response::Query);
+      self.respond(query.client, query.id, result)?;
+    }
+
+    Ok(( ))
+  }
+
+  fn respond(&self, client: MessageAddress, id: RequestId, response: Result<Response>) -> Result<( )> {
+    let message= Message {
+      // Responses to clients aren't bound to terms.
+      currentTermOfSender: 0,
+
+      from: MessageAddress::Node(self.nodeId),
+      to: client,
+      payload: MessagePayload::ResponseToClient { id, response }
+    };
+
+    self.messageSender.send(message)
+                      .map_err(|error| Error::Internal(format!("Failed sending response to client : {}", error)))
+  }
+}
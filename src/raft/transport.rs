@@ -0,0 +1,283 @@
+use std::{collections::{HashMap, HashSet}, time::Duration};
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+  net::{TcpListener, TcpStream},
+  sync::{mpsc::{error::TryRecvError, unbounded_channel, UnboundedReceiver, UnboundedSender}, watch}
+};
+use tracing::{debug, error, info, warn};
+use crate::result::{Error, ErrorCode, Result};
+use super::{cluster_config::ClusterConfig, message::{Message, MessageAddress}, types::NodeId};
+
+// Delays between consecutive attempts to (re)connect to a peer.
+const INITIAL_RECONNECT_DELAY: Duration= Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration= Duration::from_secs(5);
+
+// Maximum size of a (serialized) message, unless configured otherwise.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize= 16 * 1024 * 1024;
+
+/*
+  Moves Raft messages between the nodes of the cluster, over TCP.
+
+  Each message is sent as a frame : the length of the serialized message (as a big-endian u32),
+  followed by the message serialized using bincode. Messages larger than the maximum message size
+  aren't sent, and a peer sending a larger frame gets disconnected (before the frame is read in).
+
+  Raft tolerates message loss. So, connection failures are only logged, and messages which can't be
+  delivered are dropped.
+
+  The transport connects to the nodes in the given cluster configuration (like the one in the
+  node's config file), as well as to the nodes in the configurations in effect on the Raft node
+  (which change when nodes are added or removed at runtime). Broadcasted messages are sent only to
+  the members of the configuration in effect.
+*/
+pub struct Transport {
+  nodeId: NodeId,
+  clusterConfig: ClusterConfig,
+  maxMessageSize: usize
+}
+
+impl Transport {
+  pub fn new(nodeId: NodeId, clusterConfig: ClusterConfig, maxMessageSize: usize) -> Self {
+    Self { nodeId, clusterConfig, maxMessageSize }
+  }
+
+  /*
+    Listens for messages from the peers and feeds them into the node's inbound channel. Outbound
+    messages (received from the node) are routed to the destination peers.
+
+    The configurations in effect on the node are received through configReceiver.
+
+    Returns once the node stops sending outbound messages.
+  */
+  pub async fn run(self,
+                   inboundMessageSender: UnboundedSender<Message>,
+                   mut outboundMessageReceiver: UnboundedReceiver<Message>,
+                   mut configReceiver: watch::Receiver<ClusterConfig>) -> Result<( )>
+  {
+    let listener= TcpListener::bind(self.clusterConfig.getAddress(self.nodeId)?).await?;
+    info!("Listening for messages from peers on {}", listener.local_addr( )?);
+    let listenerTask= tokio::spawn(Self::receiveMessages(listener, inboundMessageSender, self.maxMessageSize));
+
+    let mut peers= Peers::new(self.nodeId, self.maxMessageSize);
+    peers.connect(&self.clusterConfig);
+
+    let config= configReceiver.borrow_and_update( ).clone( );
+    peers.connect(&config);
+    peers.members= config.getPeersOf(self.nodeId);
+
+    loop {
+      tokio::select! {
+        message= outboundMessageReceiver.recv( ) => match message {
+          Some(message) => peers.route(message),
+          None => break
+        },
+
+        // NOTE : Once the node stops publishing its configuration, this branch gets disabled.
+        Ok(( ))= configReceiver.changed( ) => {
+          let config= configReceiver.borrow_and_update( ).clone( );
+          peers.connect(&config);
+          peers.members= config.getPeersOf(self.nodeId);
+        }
+      }
+    }
+
+    listenerTask.abort( );
+    Ok(( ))
+  }
+}
+
+// The connections to the peers.
+struct Peers {
+  nodeId: NodeId,
+
+  // Each peer gets its own connection task (fed through the sender), so a slow or unreachable peer
+  // doesn't hold up the others.
+  senders: HashMap<NodeId, (String, UnboundedSender<Message>)>,
+
+  // The peers which are members of the configuration in effect, which the broadcasted messages
+  // are sent to.
+  members: HashSet<NodeId>,
+
+  maxMessageSize: usize
+}
+
+impl Peers {
+  fn new(nodeId: NodeId, maxMessageSize: usize) -> Self {
+    Self { nodeId, senders: HashMap::new( ), members: HashSet::new( ), maxMessageSize }
+  }
+
+  // Connects to the nodes of the given configuration, which it isn't connected to already (at the
+  // same address).
+  fn connect(&mut self, config: &ClusterConfig) {
+    for (peer, address) in config.nodes.iter( ).filter(|(peer, _)| **peer != self.nodeId) {
+      if self.senders.get(peer).is_some_and(|(connectedAddress, _)| connectedAddress == address) {
+        continue}
+
+      // Replacing the sender of a previous connection (to an old address) ends its task.
+      let (sender, receiver)= unbounded_channel( );
+      tokio::spawn(Transport::sendMessages(*peer, address.clone( ), receiver, self.maxMessageSize));
+      self.senders.insert(*peer, (address.clone( ), sender));
+    }
+  }
+
+  fn route(&self, message: Message) {
+    match message.to {
+      MessageAddress::Node(peer) => match self.senders.get(&peer) {
+        Some((_, sender)) => { let _= sender.send(message); },
+        None => warn!("Dropping message to unknown node {}", peer)
+      },
+
+      MessageAddress::Broadcast =>
+        for peer in &self.members {
+          if let Some((_, sender))= self.senders.get(peer) {
+            let _= sender.send(message.clone( ));}
+        },
+
+      MessageAddress::Client => debug!("Dropping message to client, since it can't be sent to a peer")
+    }
+  }
+}
+
+impl Transport {
+  // Accepts connections from the peers, and feeds the messages received over them into the
+  // node's inbound channel.
+  async fn receiveMessages(listener: TcpListener, inboundMessageSender: UnboundedSender<Message>, maxMessageSize: usize) {
+    loop {
+      let (stream, address)= match listener.accept( ).await {
+        Ok(connection) => connection,
+
+        Err(error) => {
+          error!("Failed accepting connection from peer : {}", error);
+          continue
+        }
+      };
+
+      debug!("Accepted connection from peer {}", address);
+      let inboundMessageSender= inboundMessageSender.clone( );
+
+      tokio::spawn(async move {
+        if let Err(error)= Self::receiveMessagesFromConnection(stream, inboundMessageSender, maxMessageSize).await {
+          warn!("Connection from peer {} closed : {}", address, error);}
+      });
+    }
+  }
+
+  async fn receiveMessagesFromConnection(stream: TcpStream,
+                                         inboundMessageSender: UnboundedSender<Message>,
+                                         maxMessageSize: usize) -> Result<( )>
+  {
+    let mut reader= BufReader::new(stream);
+
+    loop {
+      let message= match readMessage(&mut reader, maxMessageSize).await? {
+        Some(message) => message,
+
+        // The peer closed the connection.
+        None => return Ok(( ))
+      };
+
+      if let Err(error)= message.validate( ) {
+        warn!("Dropping message from peer : {}", error);
+        continue
+      }
+
+      if inboundMessageSender.send(message).is_err( ) {
+        return Err(Error::Internal("Node's inbound message channel is closed".to_string( )))}
+    }
+  }
+
+  // Maintains a persistent connection to the given peer and sends it the messages routed to it.
+  // Reconnects (with exponential backoff), whenever the connection fails. Returns once the sender
+  // of the messages is dropped.
+  async fn sendMessages(peer: NodeId, address: String, mut peerMessageReceiver: UnboundedReceiver<Message>, maxMessageSize: usize) {
+    let mut reconnectDelay= INITIAL_RECONNECT_DELAY;
+
+    loop {
+      // Messages queued while the peer was unreachable are stale by now. So they're dropped.
+      loop {
+        match peerMessageReceiver.try_recv( ) {
+          Ok(_) => { },
+          Err(TryRecvError::Empty) => break,
+          Err(TryRecvError::Disconnected) => return
+        }
+      }
+
+      let stream= match TcpStream::connect(&address).await {
+        Ok(stream) => stream,
+
+        Err(error) => {
+          debug!("Failed connecting to node {} at {} : {}", peer, address, error);
+
+          tokio::time::sleep(reconnectDelay).await;
+          reconnectDelay= (reconnectDelay * 2).min(MAX_RECONNECT_DELAY);
+          continue
+        }
+      };
+
+      info!("Connected to node {} at {}", peer, address);
+      reconnectDelay= INITIAL_RECONNECT_DELAY;
+
+      let mut writer= BufWriter::new(stream);
+      loop {
+        let message= match peerMessageReceiver.recv( ).await {
+          Some(message) => message,
+
+          // The transport has shut down.
+          None => return
+        };
+
+        // A message too large to be sent is dropped, keeping the connection.
+        let frame= match encodeMessage(&message, maxMessageSize) {
+          Ok(frame) => frame,
+
+          Err(error) => {
+            warn!("Dropping message to node {} : {}", peer, error);
+            continue
+          }
+        };
+
+        if let Err(error)= writeFrame(&mut writer, &frame).await {
+          warn!("Failed sending message to node {} : {}", peer, error);
+          break
+        }
+      }
+    }
+  }
+}
+
+// Reads a length-prefixed message. Returns None, if the stream ended before the next frame.
+// NOTE : A frame larger than the maximum message size fails the read, since the stream can't be
+// resynchronized without reading it in.
+async fn readMessage(reader: &mut BufReader<TcpStream>, maxMessageSize: usize) -> Result<Option<Message>> {
+  let length= match reader.read_u32( ).await {
+    Ok(length) => length as usize,
+    Err(error) if error.kind( ) == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+    Err(error) => return Err(error.into( ))
+  };
+
+  if length > maxMessageSize {
+    return Err(Error::Value(ErrorCode::ProtocolViolation, format!("Message of {} bytes exceeds the maximum message size of {} bytes", length, maxMessageSize)))}
+
+  let mut frame= vec![0; length];
+  reader.read_exact(&mut frame).await?;
+
+  Ok(Some(bincode::deserialize(&frame)?))
+}
+
+// Serializes the message, failing if it's larger than the maximum message size.
+fn encodeMessage(message: &Message, maxMessageSize: usize) -> Result<Vec<u8>> {
+  let frame= bincode::serialize(message)?;
+  if (frame.len( ) > maxMessageSize) || (u32::try_from(frame.len( )).is_err( )) {
+    return Err(Error::Value(ErrorCode::ProtocolViolation, format!("Message of {} bytes exceeds the maximum message size of {} bytes", frame.len( ), maxMessageSize)))}
+
+  Ok(frame)
+}
+
+// Writes the serialized message as a length-prefixed frame.
+async fn writeFrame(writer: &mut BufWriter<TcpStream>, frame: &[u8]) -> Result<( )> {
+  writer.write_u32(frame.len( ) as u32).await?;
+  writer.write_all(frame).await?;
+  writer.flush( ).await?;
+
+  Ok(( ))
+}
@@ -1,4 +1,15 @@
-pub type NodeId= u8;
+use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+
+// Uniquely identifies a node in the cluster.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct NodeId(pub u64);
+
+impl Display for NodeId {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
 
 /*
   Raft divides time into terms of arbitrary length. Terms are numbered with consecutive integers.
@@ -23,4 +34,9 @@ pub type Term= u64;
 // Represents a logical clock interval.
 pub type Ticks= u8;
 
-pub type LogEntryIndex= u64;
\ No newline at end of file
+pub type LogEntryIndex= u64;
+
+// Sequence number assigned by the leader to each read-only client request. A read is served only
+// after a quorum of nodes acknowledge a heartbeat carrying an equal or greater sequence number
+// (confirming that the node is still the leader).
+pub type ReadSequence= u64;
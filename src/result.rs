@@ -1,21 +1,265 @@
 use std::{fmt::Display, num::{ParseFloatError, ParseIntError}};
+use serde::{Deserialize, Serialize};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Error {
+  // The operation was aborted (like a client request received during a leader / term change) and
+  // should be retried.
+  Abort,
+
+  // An unexpected internal failure (like a corrupted log or an encoding failure).
+  Internal(String),
+
+  // Stored data failed its integrity check (like an encrypted value which fails to authenticate),
+  // so it has been tampered with or is being read with the wrong key.
+  Corruption(String),
+
   Parse(String),
-  Value(String)
+  // A parse error at a line and column (both starting from 1) of the input.
+  ParseAt { line: usize, column: usize, message: String },
+
+  // An invalid statement, value or operation, whose kind is given by its code.
+  Value(ErrorCode, String),
+
+  // The transaction conflicted with a concurrent transaction, and should be retried.
+  Serialization,
+
+  // A write was attempted in a read-only transaction.
+  ReadOnly,
+
+  // The request must be sent to the leader, whose SQL address is given (see ClientRouting::Redirect).
+  NotLeader { leaderAddress: String },
+
+  // The statement was cancelled (by the client, or since it exceeded the statement timeout of its
+  // session).
+  Cancelled(String)
+}
+
+impl Error {
+  // Returns the code of the error, which clients can tell the kinds of errors apart by.
+  pub fn code(&self) -> ErrorCode {
+    match self {
+      Error::Abort => ErrorCode::Aborted,
+      Error::Internal(_) => ErrorCode::Internal,
+      Error::Corruption(_) => ErrorCode::DataCorrupted,
+      Error::Parse(_) | Error::ParseAt { .. } => ErrorCode::SyntaxError,
+      Error::Value(code, _) => *code,
+      Error::Serialization => ErrorCode::SerializationFailure,
+      Error::ReadOnly => ErrorCode::ReadOnlyTransaction,
+      Error::NotLeader { .. } => ErrorCode::NotLeader,
+      Error::Cancelled(_) => ErrorCode::QueryCanceled
+    }
+  }
+
+  // Returns whether the failed operation may succeed if it's retried (in a new transaction), since
+  // it failed due to a concurrent operation, a leader change or an overloaded leader rather than due
+  // to the operation itself.
+  pub fn isRetryable(&self) -> bool {
+    self.code( ).isRetryable( )
+  }
+}
+
+/*
+  The kinds of errors, each identified by a stable code. The codes follow the SQLSTATE codes of the
+  SQL standard (and PostgreSQL) where there's one, so the first two characters are the class of the
+  error (like 23 for integrity constraint violations). The codes of the errors specific to this
+  database use the subclasses starting with D.
+
+  NOTE : Errors are sent over the wire as their variant's position (see bincode), so new codes must
+  be added at the end.
+*/
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorCode {
+  // The operation was aborted by a leader change, or no leader is known (like during an election).
+  Aborted,
+  // The node isn't the leader, the request must be sent to the leader.
+  NotLeader,
+  SerializationFailure,
+  // A request was applied already, but its result is unknown.
+  StatementCompletionUnknown,
+
+  ProtocolViolation,
+  FeatureNotSupported,
+  CardinalityViolation,
+
+  DataException,
+  NumericValueOutOfRange,
+  DatetimeFieldOverflow,
+  DivisionByZero,
+  InvalidParameterValue,
+  SubstringError,
+  InvalidEscapeSequence,
+  InvalidTextRepresentation,
+  InvalidBackup,
+
+  NotNullViolation,
+  ForeignKeyViolation,
+  UniqueViolation,
+
+  InvalidTransactionState,
+  ActiveTransaction,
+  NoActiveTransaction,
+  ReadOnlyTransaction,
+  UndefinedPreparedStatement,
+  DependentObjectsStillExist,
+
+  SyntaxError,
+  UndefinedTable,
+  UndefinedColumn,
+  UndefinedFunction,
+  UndefinedParameter,
+  UndefinedObject,
+  DuplicateTable,
+  DuplicateColumn,
+  DuplicateObject,
+  DuplicateAlias,
+  AmbiguousColumn,
+  DatatypeMismatch,
+  GroupingError,
+  InvalidColumnReference,
+  InvalidColumnDefinition,
+  InvalidTableDefinition,
+  InvalidObjectDefinition,
+  InvalidForeignKey,
+  ReservedName,
+  // A system column was written.
+  GeneratedAlways,
+
+  ObjectNotInPrerequisiteState,
+  QueryCanceled,
+
+  Internal,
+  DataCorrupted,
+
+  // The leader has too many proposals in flight, and sheds the new ones.
+  ServerBusy,
+  // A transaction has written more than its write limits allow.
+  ProgramLimitExceeded,
+  // The local replica didn't catch up with the writes of the session in time, to serve its read.
+  ReplicaLagging,
+  // The data directory is locked by another server process.
+  ObjectInUse
+}
+
+impl ErrorCode {
+  // Returns the SQLSTATE code.
+  pub fn sqlState(&self) -> &'static str {
+    match self {
+      ErrorCode::Aborted => "40D01",
+      ErrorCode::NotLeader => "08D01",
+      ErrorCode::SerializationFailure => "40001",
+      ErrorCode::StatementCompletionUnknown => "40003",
+
+      ErrorCode::ProtocolViolation => "08P01",
+      ErrorCode::FeatureNotSupported => "0A000",
+      ErrorCode::CardinalityViolation => "21000",
+
+      ErrorCode::DataException => "22000",
+      ErrorCode::NumericValueOutOfRange => "22003",
+      ErrorCode::DatetimeFieldOverflow => "22008",
+      ErrorCode::DivisionByZero => "22012",
+      ErrorCode::InvalidParameterValue => "22023",
+      ErrorCode::SubstringError => "22011",
+      ErrorCode::InvalidEscapeSequence => "22025",
+      ErrorCode::InvalidTextRepresentation => "22P02",
+      ErrorCode::InvalidBackup => "22D01",
+
+      ErrorCode::NotNullViolation => "23502",
+      ErrorCode::ForeignKeyViolation => "23503",
+      ErrorCode::UniqueViolation => "23505",
+
+      ErrorCode::InvalidTransactionState => "25000",
+      ErrorCode::ActiveTransaction => "25001",
+      ErrorCode::NoActiveTransaction => "25P01",
+      ErrorCode::ReadOnlyTransaction => "25006",
+      ErrorCode::UndefinedPreparedStatement => "26000",
+      ErrorCode::DependentObjectsStillExist => "2BP01",
+
+      ErrorCode::SyntaxError => "42601",
+      ErrorCode::UndefinedTable => "42P01",
+      ErrorCode::UndefinedColumn => "42703",
+      ErrorCode::UndefinedFunction => "42883",
+      ErrorCode::UndefinedParameter => "42P02",
+      ErrorCode::UndefinedObject => "42704",
+      ErrorCode::DuplicateTable => "42P07",
+      ErrorCode::DuplicateColumn => "42701",
+      ErrorCode::DuplicateObject => "42710",
+      ErrorCode::DuplicateAlias => "42712",
+      ErrorCode::AmbiguousColumn => "42702",
+      ErrorCode::DatatypeMismatch => "42804",
+      ErrorCode::GroupingError => "42803",
+      ErrorCode::InvalidColumnReference => "42P10",
+      ErrorCode::InvalidColumnDefinition => "42611",
+      ErrorCode::InvalidTableDefinition => "42P16",
+      ErrorCode::InvalidObjectDefinition => "42P17",
+      ErrorCode::InvalidForeignKey => "42830",
+      ErrorCode::ReservedName => "42939",
+      ErrorCode::GeneratedAlways => "428C9",
+
+      ErrorCode::ObjectNotInPrerequisiteState => "55000",
+      ErrorCode::QueryCanceled => "57014",
+
+      ErrorCode::Internal => "XX000",
+      ErrorCode::DataCorrupted => "XX001",
+
+      ErrorCode::ServerBusy => "53D01",
+      ErrorCode::ProgramLimitExceeded => "54000",
+      ErrorCode::ReplicaLagging => "40D02",
+      ErrorCode::ObjectInUse => "55006"
+    }
+  }
+
+  // Returns whether the operations failing with the code may succeed if they're retried (see
+  // Error::isRetryable).
+  // NOTE : StatementCompletionUnknown isn't retryable, since the request may have been applied.
+  pub fn isRetryable(&self) -> bool {
+    matches!(self, ErrorCode::Aborted | ErrorCode::NotLeader | ErrorCode::SerializationFailure | ErrorCode::ServerBusy | ErrorCode::ReplicaLagging)
+  }
+}
+
+impl Display for ErrorCode {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.sqlState( ))
+  }
 }
 
 impl Display for Error {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    unimplemented!( )
+    match self {
+      Error::Abort => write!(f, "Operation aborted"),
+
+      Error::Internal(message) => write!(f, "Internal error : {}", message),
+      Error::Corruption(message) => write!(f, "Data corruption : {}", message),
+
+      Error::Parse(message) => write!(f, "Parse error : {}", message),
+      Error::ParseAt { line, column, message } => write!(f, "Parse error at line {}, column {}: {}", line, column, message),
+      Error::Value(_, message) => write!(f, "Value error : {}", message),
+
+      Error::Serialization => write!(f, "Serialization failure, retry the transaction"),
+      Error::ReadOnly => write!(f, "Read-only transaction"),
+      Error::NotLeader { leaderAddress } => write!(f, "Not the leader, retry against the leader at {}", leaderAddress),
+      Error::Cancelled(message) => write!(f, "Cancelled : {}", message)
+    }
   }
 }
 
 impl std::error::Error for Error { }
 
+impl serde::ser::Error for Error {
+  fn custom<T: Display>(message: T) -> Self {
+    Error::Internal(message.to_string( ))
+  }
+}
+
+impl serde::de::Error for Error {
+  fn custom<T: Display>(message: T) -> Self {
+    Error::Internal(message.to_string( ))
+  }
+}
+
 impl From<ParseIntError> for Error {
   fn from(err: ParseIntError) -> Self {
     Error::Parse(err.to_string( ))
@@ -26,4 +270,16 @@ impl From<ParseFloatError> for Error {
   fn from(err: ParseFloatError) -> Self {
     Error::Parse(err.to_string( ))
   }
-}
\ No newline at end of file
+}
+
+impl From<std::io::Error> for Error {
+  fn from(err: std::io::Error) -> Self {
+    Error::Internal(err.to_string( ))
+  }
+}
+
+impl From<bincode::Error> for Error {
+  fn from(err: bincode::Error) -> Self {
+    Error::Internal(err.to_string( ))
+  }
+}
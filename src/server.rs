@@ -1,3 +1,895 @@
-pub struct Server { }
+use std::{
+  collections::{hash_map::Entry, BTreeMap, HashMap},
+  future::Future,
+  io::{Read, Write},
+  iter::Fuse,
+  mem,
+  net::TcpStream,
+  path::{Path, PathBuf},
+  sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex},
+  time::Duration
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::{net::TcpListener, sync::{mpsc::{channel, unbounded_channel}, watch}};
+use tracing::{debug, error, info, warn};
+use crate::{
+  raft::{
+    self, cluster_config::ClusterConfig, log::{Log, DEFAULT_CACHE_CAPACITY}, node::leader::ReplicationLimits, server::DEFAULT_TICK_INTERVAL,
+    transport::{Transport, DEFAULT_MAX_MESSAGE_SIZE}, types::NodeId
+  },
+  result::{Error, ErrorCode, Result},
+  sql::{
+    engine::{kv::KV, raft::{Raft, StateMachine, DEFAULT_READ_YOUR_WRITES_TIMEOUT}, BackupSummary, PlanCache, PreparedStatementHandle, Session, Status},
+    execution::{CancelHandle, Columns, ResultSet, WorkMemory, DEFAULT_WORK_MEMORY},
+    schema::{Catalog, Table},
+    types::{Row, Rows, Value}
+  },
+  storage::{engine::StorageEngine, mvcc::{Version, WriteLimits}, sync::SyncPolicy}
+};
 
-impl Server { }
\ No newline at end of file
+// A request sent by a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+  // Executes a SQL statement in the client's session.
+  Execute(String),
+
+  // Prepares a SQL statement in the client's session, for later executions.
+  Prepare(String),
+  // Executes a prepared statement, binding the given values to its parameters.
+  ExecutePrepared {
+    handle: PreparedStatementHandle,
+    parameters: Vec<Value>
+  },
+  // Removes a prepared statement.
+  Deallocate(PreparedStatementHandle),
+
+  // Sends a batch of rows (as CSV lines) of the COPY in progress, which is started by executing a
+  // COPY ... FROM STDIN statement.
+  CopyData(Vec<String>),
+  // Ends the COPY in progress.
+  CopyEnd,
+
+  // Returns the schema of a table.
+  GetTable(String),
+
+  // Returns the status of the node serving the client.
+  GetStatus,
+
+  // Backs up the database to a file at the given path, on the node serving the client (see
+  // engine::backup( )).
+  Backup(String),
+  // Restores the backup in the file at the given path, on the node serving the client (see
+  // engine::restore( )). The existing tables are dropped first if forced, or else the database must
+  // be empty.
+  Restore {
+    path: String,
+    force: bool
+  },
+
+  // Returns the cancel key of the connection, which another connection cancels its running
+  // statement with (see Cancel). The statement's connection is busy, so the cancellation must be
+  // sent out-of-band.
+  GetCancelKey,
+  // Cancels the statement running in the connection with the given cancel key (if any).
+  Cancel(u64),
+
+  // Adds a node (listening for Raft messages on the given address) to the cluster / removes a node
+  // from the cluster.
+  AddNode {
+    id: NodeId,
+    address: String
+  },
+  RemoveNode(NodeId),
+
+  // Transfers the leadership of the cluster to the given node (or to the most up-to-date one, if
+  // none is given).
+  TransferLeadership(Option<NodeId>)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+  Execute(StatementResult),
+  Prepared {
+    handle: PreparedStatementHandle,
+    parameterCount: u32
+  },
+  Deallocated,
+  CopyData,
+  Table(Table),
+  Status(Box<Status>),
+  BackedUp(BackupSummary),
+  Restored(BackupSummary),
+  CancelKey(u64),
+  Cancelled,
+  MembershipChanged,
+  LeadershipTransferred,
+
+  // The frames of a query result, which is streamed instead of sent as a StatementResult (see
+  // ResultFrames).
+  QueryHeader(Columns),
+  Rows(Vec<Row>),
+  QueryEnd { count: u64 }
+}
+
+/*
+  The result of executing a statement, as sent to the client. Unlike a ResultSet, the rows of a
+  query are materialized and the plan of an EXPLAIN is rendered.
+
+  NOTE : The rows of a query are streamed to the client (see ResultFrames), which materializes them
+  into a StatementResult::Query (unless it reads them as a stream, see Client::stream( )).
+*/
+#[derive(Debug, Serialize, Deserialize)]
+pub enum StatementResult {
+  Begin { version: Version, readonly: bool, stale: bool },
+  Commit { version: Version },
+  Rollback { version: Version },
+
+  CreateTable { name: String },
+  DropTable { name: String },
+  AlterTable { name: String },
+  CreateIndex { name: String },
+
+  Analyze { count: u64 },
+
+  Insert { count: u64 },
+  Update { count: u64 },
+  Delete { count: u64 },
+
+  CopyIn { table: String },
+  Copy { count: u64 },
+
+  Set { variable: String },
+
+  Query {
+    columns: Columns,
+    rows: Vec<Row>
+  },
+
+  Explain(String)
+}
+
+impl StatementResult {
+  /*
+    Returns the primary key of the last row of a query result, if its rows are ordered by the
+    primary key (see ResultColumn::key). The next page of the rows then starts after that key.
+
+    NOTE : There's none, if the result has no rows.
+  */
+  pub fn lastKey(&self) -> Option<&Value> {
+    let Self::Query { columns, rows }= self else {
+      return None
+    };
+
+    let keyColumn= columns.iter( ).position(|column| column.key)?;
+    rows.last( ).map(|row| &row[keyColumn])
+  }
+}
+
+impl TryFrom<ResultSet> for StatementResult {
+  type Error= Error;
+
+  fn try_from(resultSet: ResultSet) -> Result<Self> {
+    Ok(match resultSet {
+      ResultSet::Begin { version, readonly, stale } => Self::Begin { version, readonly, stale },
+      ResultSet::Commit { version } => Self::Commit { version },
+      ResultSet::Rollback { version } => Self::Rollback { version },
+
+      ResultSet::CreateTable { name } => Self::CreateTable { name },
+      ResultSet::DropTable { name } => Self::DropTable { name },
+      ResultSet::AlterTable { name } => Self::AlterTable { name },
+      ResultSet::CreateIndex { name } => Self::CreateIndex { name },
+
+      ResultSet::Analyze { count } => Self::Analyze { count },
+
+      ResultSet::Insert { count } => Self::Insert { count },
+      ResultSet::Update { count } => Self::Update { count },
+      ResultSet::Delete { count } => Self::Delete { count },
+
+      ResultSet::CopyIn { table } => Self::CopyIn { table },
+      ResultSet::Copy { count } => Self::Copy { count },
+
+      ResultSet::Set { variable } => Self::Set { variable },
+
+      ResultSet::Query { columns, rows } => Self::Query { columns, rows: rows.collect::<Result<_>>( )? },
+
+      ResultSet::Explain { plan, estimates } => Self::Explain(plan.annotated(estimates.as_ref( ), None).to_string( )),
+      ResultSet::ExplainAnalyze { plan, estimates, analysis } => Self::Explain(plan.annotated(estimates.as_ref( ), Some(&analysis)).to_string( ))
+    })
+  }
+}
+
+/*
+  Configuration of a node, read from a file of "key = value" lines (blank lines and lines starting
+  with # are ignored) :
+
+    id = 1
+    listenSQL = 127.0.0.1:9705
+
+    # Directory the node keeps its state in (defaults to data-node-<id> in the temporary directory
+    # of the system, see DataDir). A server process locks it, so it can't be shared.
+    dataDirectory = /var/lib/db/node-1
+
+    # Raft addresses of all the nodes in the cluster (including this node).
+    node.1 = 127.0.0.1:9605
+    node.2 = 127.0.0.1:9606
+
+    # Whether the node joins an existing cluster (defaults to false). It then waits to be added by
+    # the leader (see the !add-node command of the client), instead of bootstrapping a new cluster
+    # with the above nodes.
+    join = true
+
+    # Limits on the AppendEntries the node sends to a peer while it's the leader : the maximum number
+    # of entries, their maximum total size (in bytes), and the maximum number of AppendEntries in
+    # flight (see ReplicationLimits for the defaults).
+    maxAppendEntries = 1000
+    maxAppendBytes = 1048576
+    maxInflightAppends = 8
+
+    # Maximum number of proposals the node has in flight while it's the leader (defaults to 1000).
+    # Writes beyond it are rejected with ServerBusy, to be retried once the backlog drains.
+    maxPendingProposals = 1000
+
+    # Maximum size (in bytes) of a message exchanged with the peers. Larger messages are rejected
+    # (defaults to 16 MiB). Must exceed maxAppendBytes.
+    maxMessageSize = 16777216
+
+    # Number of the most recently stored entries of the Raft log, which are cached in memory
+    # (defaults to 1024). 0 disables the cache.
+    logCacheEntries = 1024
+
+    # When the commits to the SQL data and to the Raft log are flushed (see SyncPolicy) : always
+    # (the default, sharing the flushes between concurrent commits), interval(<milliseconds>) or
+    # never. Under the latter two, a crash loses the commits acknowledged since the last flush (as
+    # the status of the node discloses).
+    syncPolicy = interval(100)
+
+    # Duration (in milliseconds) of a tick of the Raft node's logical clock (defaults to 10). The
+    # election timeouts (10 - 20 ticks) and the heartbeat interval (3 ticks) scale with it.
+    tickInterval = 10
+
+    # Memory (in bytes) the executors of a statement may buffer rows in, before spilling them to disk
+    # (defaults to 64 MiB). It's the default of the sessions, which can change it by SET work_memory.
+    workMemory = 67108864
+
+    # Number of rows in a batch of a query result, which is streamed to the client (defaults to
+    # 1024, see ResultFrames).
+    rowBatchSize = 1024
+
+    # Directory the spilled rows are written to (defaults to tmp/spill in the data directory). The
+    # spill files left in it are removed when the node starts.
+    spillDirectory = /var/tmp/spill-node-1
+
+    # Limits on the uncommitted writes of a transaction : the number of distinct keys it writes, and
+    # the total size (in bytes) of the keys and values it writes (see WriteLimits for the defaults).
+    maxTransactionKeys = 1000000
+    maxTransactionBytes = 268435456
+
+    # How the statements of the clients connected to a follower are routed to the leader : forward
+    # (the default) or redirect (see ClientRouting). Redirection needs the SQL addresses of the
+    # other nodes.
+    clientRouting = redirect
+    sql.2 = 127.0.0.1:9706
+
+    # How long (in milliseconds) a read served by the node's replica, while there's no reachable
+    # leader, waits for the replica to apply the writes of its session (defaults to 1000, see
+    # engine::raft::Raft). The read fails with the retryable ReplicaLagging error after that.
+    readYourWritesTimeout = 1000
+
+  The nodes are only used to bootstrap the cluster : once a node has started, the membership is
+  changed by adding and removing nodes at runtime (and it's stored in the node's Raft log).
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+  pub id: NodeId,
+
+  // Address to listen for SQL clients on.
+  pub listenSQL: String,
+
+  pub dataDirectory: PathBuf,
+
+  pub clusterConfig: ClusterConfig,
+
+  pub join: bool,
+
+  pub replicationLimits: ReplicationLimits,
+  pub maxMessageSize: usize,
+  pub tickInterval: Duration,
+  pub logCacheEntries: usize,
+  pub syncPolicy: SyncPolicy,
+
+  pub workMemory: usize,
+  // None to spill into the data directory (see DataDir::spillDirectory( )).
+  pub spillDirectory: Option<PathBuf>,
+  pub rowBatchSize: usize,
+
+  pub writeLimits: WriteLimits,
+
+  pub clientRouting: ClientRouting,
+  pub readYourWritesTimeout: Duration
+}
+
+/*
+  How a follower handles the statements of its clients, which need the leader (to be replicated, or
+  to read the latest committed data) :
+
+  (a) Forward - The node forwards the requests to the leader (over the Raft transport), and relays
+      the responses back. The clients don't need to know which node is the leader.
+
+  (b) Redirect - The node rejects the statement with Error::NotLeader, carrying the SQL address of
+      the leader (given by the map, by the node ids). The client is expected to reconnect to the
+      leader and retry (like Client::execute( ) does). It saves the extra hop through the follower.
+
+  NOTE : A statement is only redirected outside a transaction, since the transaction is tied to the
+  session (and so to the connection). It's forwarded otherwise. It's forwarded as well, if there's
+  no known leader or its SQL address is unknown.
+
+  Either way, requests arriving while there's no known leader (like during an election) wait for one
+  to be elected, for a bounded time.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ClientRouting {
+  #[default]
+  Forward,
+  Redirect(BTreeMap<NodeId, String>)
+}
+
+impl Config {
+  pub fn parse(input: &str) -> Result<Self> {
+    let invalid= |message: String| Error::Value(ErrorCode::InvalidParameterValue, format!("Invalid config : {}", message));
+
+    let mut id= None;
+    let mut listenSQL= None;
+    let mut dataDirectory= None;
+    let mut nodes= BTreeMap::new( );
+    let mut join= false;
+    let mut replicationLimits= ReplicationLimits::default( );
+    let mut maxMessageSize= DEFAULT_MAX_MESSAGE_SIZE;
+    let mut tickInterval= DEFAULT_TICK_INTERVAL;
+    let mut logCacheEntries= DEFAULT_CACHE_CAPACITY;
+    let mut syncPolicy= SyncPolicy::default( );
+    let mut workMemory= DEFAULT_WORK_MEMORY;
+    let mut spillDirectory= None;
+    let mut rowBatchSize= DEFAULT_ROW_BATCH_SIZE;
+    let mut writeLimits= WriteLimits::default( );
+    let mut redirect= false;
+    let mut sqlAddresses= BTreeMap::new( );
+    let mut readYourWritesTimeout= DEFAULT_READ_YOUR_WRITES_TIMEOUT;
+
+    let parseLimit= |key: &str, value: &str| match value.parse::<usize>( ) {
+      Ok(limit) if limit > 0 => Ok(limit),
+      _ => Err(invalid(format!("invalid {} {}, expected a positive integer", key, value)))
+    };
+
+    for line in input.lines( ).map(str::trim).filter(|line| !line.is_empty( ) && !line.starts_with('#')) {
+      let Some((key, value))= line.split_once('=') else {
+        return Err(invalid(format!("expected key = value, got {}", line)))
+      };
+      let (key, value)= (key.trim( ), value.trim( ).to_string( ));
+
+      match key {
+        "id" => id= Some(NodeId(value.parse( ).map_err(|_| invalid(format!("invalid node id {}", value)))?)),
+        "listenSQL" => listenSQL= Some(value),
+        "dataDirectory" => dataDirectory= Some(PathBuf::from(value)),
+        "join" => join= value.parse( ).map_err(|_| invalid(format!("invalid join flag {}, expected true or false", value)))?,
+
+        "maxAppendEntries" => replicationLimits.maxEntries= parseLimit(key, &value)?,
+        "maxAppendBytes" => replicationLimits.maxBytes= parseLimit(key, &value)?,
+        "maxInflightAppends" => replicationLimits.maxInflight= parseLimit(key, &value)?,
+        "maxPendingProposals" => replicationLimits.maxPendingProposals= parseLimit(key, &value)?,
+        "maxMessageSize" => maxMessageSize= parseLimit(key, &value)?,
+        "tickInterval" => tickInterval= Duration::from_millis(parseLimit(key, &value)? as u64),
+        "logCacheEntries" => logCacheEntries= value.parse( ).map_err(|_| invalid(format!("invalid {} {}, expected a non-negative integer", key, value)))?,
+        "syncPolicy" => syncPolicy= value.parse( ).map_err(|_| invalid(format!("invalid {} {}, expected always, interval(<milliseconds>) or never", key, value)))?,
+
+        "workMemory" => workMemory= parseLimit(key, &value)?,
+        "spillDirectory" => spillDirectory= Some(PathBuf::from(value)),
+        "rowBatchSize" => rowBatchSize= parseLimit(key, &value)?,
+
+        "maxTransactionKeys" => writeLimits.maxKeys= parseLimit(key, &value)? as u64,
+        "maxTransactionBytes" => writeLimits.maxBytes= parseLimit(key, &value)? as u64,
+
+        "clientRouting" => redirect= match value.as_str( ) {
+          "forward" => false,
+          "redirect" => true,
+          _ => return Err(invalid(format!("invalid clientRouting {}, expected forward or redirect", value)))
+        },
+        "readYourWritesTimeout" => readYourWritesTimeout= Duration::from_millis(parseLimit(key, &value)? as u64),
+
+        key if key.starts_with("node.") => {
+          let nodeId= key["node.".len( )..].parse( )
+                                           .map_err(|_| invalid(format!("invalid node id in {}", key)))?;
+          nodes.insert(NodeId(nodeId), value);
+        },
+
+        key if key.starts_with("sql.") => {
+          let nodeId= key["sql.".len( )..].parse( )
+                                          .map_err(|_| invalid(format!("invalid node id in {}", key)))?;
+          sqlAddresses.insert(NodeId(nodeId), value);
+        },
+
+        key => return Err(invalid(format!("unknown key {}", key)))
+      }
+    }
+
+    let id= id.ok_or_else(| | invalid("id is missing".to_string( )))?;
+    if !nodes.contains_key(&id) {
+      return Err(invalid(format!("the Raft address of node {} is missing", id)))}
+
+    if replicationLimits.maxBytes >= maxMessageSize {
+      return Err(invalid(format!("maxAppendBytes {} must be less than maxMessageSize {}", replicationLimits.maxBytes, maxMessageSize)))}
+
+    let listenSQL= listenSQL.ok_or_else(| | invalid("listenSQL is missing".to_string( )))?;
+    let dataDirectory= dataDirectory.unwrap_or_else(| | std::env::temp_dir( ).join(format!("data-node-{}", id)));
+    sqlAddresses.entry(id).or_insert_with(| | listenSQL.clone( ));
+
+    let clientRouting= match redirect {
+      true => ClientRouting::Redirect(sqlAddresses),
+      false => ClientRouting::Forward
+    };
+
+    Ok(Self {
+      id,
+      listenSQL,
+      dataDirectory,
+      clusterConfig: ClusterConfig::new(nodes),
+      join,
+      replicationLimits,
+      maxMessageSize,
+      tickInterval,
+      logCacheEntries,
+      syncPolicy,
+      workMemory,
+      spillDirectory,
+      rowBatchSize,
+      writeLimits,
+      clientRouting,
+      readYourWritesTimeout
+    })
+  }
+}
+
+/*
+  The database server. It runs a Raft node (replicating the SQL data across the cluster) and serves
+  SQL clients.
+
+  A client sends requests and receives responses over a TCP connection, each as a frame : the length
+  of the serialized value (as a big-endian u32), followed by the value serialized using bincode.
+  Every connection gets its own session, so the transactions of different clients don't interfere.
+
+  A request gets a single response, except a statement resulting in the rows of a query : those are
+  streamed as multiple frames (see ResultFrames).
+*/
+pub struct Server<E: StorageEngine + 'static> {
+  id: NodeId,
+  clusterConfig: ClusterConfig,
+  maxMessageSize: usize,
+  clientRouting: ClientRouting,
+  readYourWritesTimeout: Duration,
+  workMemory: WorkMemory,
+  rowBatchSize: usize,
+
+  raftServer: raft::server::Server,
+
+  // The node's replica of the SQL data.
+  kv: KV<E>
+}
+
+impl<E: StorageEngine + 'static> Server<E> {
+  // A node joining an existing cluster (see Config) doesn't bootstrap the cluster with the given
+  // configuration. It's still used to reach the other nodes.
+  #[allow(clippy::too_many_arguments)]
+  pub fn new(id: NodeId,
+             clusterConfig: ClusterConfig,
+             join: bool,
+             replicationLimits: ReplicationLimits,
+             maxMessageSize: usize,
+             tickInterval: Duration,
+             clientRouting: ClientRouting,
+             readYourWritesTimeout: Duration,
+             workMemory: WorkMemory,
+             rowBatchSize: usize,
+             writeLimits: WriteLimits,
+             logCacheCapacity: usize,
+             syncPolicy: SyncPolicy,
+             logStorageEngine: Box<dyn StorageEngine>,
+             storageEngine: E) -> Result<Self>
+  {
+    let kv= KV::new(storageEngine, writeLimits, syncPolicy);
+
+    let raftServer= raft::server::Server::new(id,
+                                              (!join).then(| | clusterConfig.clone( )),
+                                              Log::new(logStorageEngine, logCacheCapacity, syncPolicy)?,
+                                              Box::new(StateMachine::new(kv.clone( ))?),
+                                              replicationLimits,
+                                              tickInterval)?;
+
+    Ok(Self { id, clusterConfig, maxMessageSize, clientRouting, readYourWritesTimeout, workMemory, rowBatchSize, raftServer, kv })
+  }
+
+  /*
+    Serves the clients connecting through the listener, until the shutdown future completes (or the
+    Raft node fails). The SQL data is flushed before returning.
+
+    On shutdown, a node which is the leader transfers its leadership to the most up-to-date peer
+    first. So the cluster doesn't wait out an election timeout, before electing a new leader.
+  */
+  pub async fn serve(self, listener: TcpListener, shutdown: impl Future<Output= ( )>) -> Result<( )> {
+    let (inboundMessageSender, inboundMessageReceiver)= unbounded_channel( );
+    let (outboundMessageSender, outboundMessageReceiver)= unbounded_channel( );
+    let (clientRequestSender, clientRequestReceiver)= channel(raft::server::CLIENT_REQUEST_CHANNEL_CAPACITY);
+
+    let redirector= match self.clientRouting {
+      ClientRouting::Redirect(sqlAddresses) => Some(Redirector { id: self.id, sqlAddresses: Arc::new(sqlAddresses), leader: self.raftServer.watchLeader( ) }),
+      ClientRouting::Forward => None
+    };
+
+    let leader= self.raftServer.watchLeader( );
+    let config= self.raftServer.watchConfig( );
+
+    let transport= Transport::new(self.id, self.clusterConfig, self.maxMessageSize);
+    let transportTask= tokio::spawn(transport.run(inboundMessageSender, outboundMessageReceiver, self.raftServer.watchConfig( )));
+
+    let raftClient= raft::server::Client::new(clientRequestSender, self.raftServer.watchAppliedIndex( ));
+    let mut raftServerTask= tokio::spawn(self.raftServer.serve(inboundMessageReceiver, outboundMessageSender, clientRequestReceiver));
+
+    let cancelHandles= CancelHandles::default( );
+    // The plans are cached for all the sessions together.
+    let planCache= PlanCache::default( );
+    let streamMetrics= StreamMetrics::default( );
+    info!("Listening for SQL clients on {}", listener.local_addr( )?);
+
+    tokio::pin!(shutdown);
+    let result= loop {
+      tokio::select! {
+        connection= listener.accept( ) => match connection {
+          Ok((stream, address)) => {
+            debug!("Accepted connection from client {}", address);
+
+            // Sessions block while waiting for the Raft node, so they're served on blocking threads.
+            let stream= stream.into_std( )?;
+            stream.set_nonblocking(false)?;
+
+            // Every session gets an engine of its own, which tracks the session's writes.
+            let session= Session::new(Raft::new(raftClient.clone( ), self.readYourWritesTimeout), planCache.clone( ), self.workMemory.clone( ));
+            let raftClient= raftClient.clone( );
+            let cancelHandles= cancelHandles.clone( );
+            let redirector= redirector.clone( );
+            let streaming= Streaming { batchSize: self.rowBatchSize, metrics: streamMetrics.clone( ) };
+            tokio::task::spawn_blocking(move || {
+              if let Err(error)= serveClient(stream, session, raftClient, cancelHandles, redirector, streaming) {
+                warn!("Connection from client {} closed : {}", address, error);}
+            });
+          },
+
+          Err(error) => error!("Failed accepting connection from client : {}", error)
+        },
+
+        result= &mut raftServerTask => break match result {
+          Ok(result) => result,
+          Err(error) => Err(Error::Internal(format!("Raft server panicked : {}", error)))
+        },
+
+        _ = &mut shutdown => {
+          info!("Shutting down");
+
+          let isLeader= *leader.borrow( ) == Some(self.id);
+          let hasPeers= !config.borrow( ).getPeersOf(self.id).is_empty( );
+          if isLeader && hasPeers {
+            let raftClient= raftClient.clone( );
+            match tokio::task::spawn_blocking(move || raftClient.transferLeadership(None)).await {
+              Ok(Ok(( ))) => info!("Transferred the leadership, before shutting down"),
+              Ok(Err(error)) => warn!("Failed transferring the leadership, before shutting down : {}", error),
+              Err(error) => warn!("Leadership transfer panicked : {}", error)
+            }
+          }
+
+          break Ok(( ))
+        }
+      }
+    };
+
+    raftServerTask.abort( );
+    transportTask.abort( );
+
+    self.kv.mvcc.flush( )?;
+    result
+  }
+}
+
+// The cancel handles of the sessions of the connected clients, by the cancel keys of their
+// connections.
+type CancelHandles= Arc<Mutex<HashMap<u64, CancelHandle>>>;
+
+// Finds the leader to redirect the clients to, in the redirect mode (see ClientRouting).
+#[derive(Clone)]
+struct Redirector {
+  id: NodeId,
+  sqlAddresses: Arc<BTreeMap<NodeId, String>>,
+  leader: watch::Receiver<Option<NodeId>>
+}
+
+impl Redirector {
+  // Returns the SQL address of the leader, unless it's this node or isn't known.
+  fn leaderAddress(&self) -> Option<String> {
+    match *self.leader.borrow( ) {
+      Some(leader) if leader != self.id => self.sqlAddresses.get(&leader).cloned( ),
+      _ => None
+    }
+  }
+}
+
+// Serves the requests of a client, until it disconnects.
+fn serveClient(stream: TcpStream,
+               session: Session<Raft>,
+               raftClient: raft::server::Client,
+               cancelHandles: CancelHandles,
+               redirector: Option<Redirector>,
+               streaming: Streaming) -> Result<( )>
+{
+  // The key is random, so that a client can't cancel the statements of other clients by guessing it.
+  let cancelKey= loop {
+    let key= rand::random::<u64>( );
+    if let Entry::Vacant(entry)= cancelHandles.lock( ).unwrap( ).entry(key) {
+      entry.insert(session.cancelHandle( ));
+      break key
+    }
+  };
+
+  let result= serveRequests(stream, session, raftClient, &cancelHandles, cancelKey, redirector, streaming);
+  cancelHandles.lock( ).unwrap( ).remove(&cancelKey);
+  result
+}
+
+fn serveRequests(mut stream: TcpStream,
+                 mut session: Session<Raft>,
+                 raftClient: raft::server::Client,
+                 cancelHandles: &CancelHandles,
+                 cancelKey: u64,
+                 redirector: Option<Redirector>,
+                 streaming: Streaming) -> Result<( )>
+{
+  while let Some(request)= readFrame::<Request>(&mut stream)? {
+    let response= match request {
+      Request::Execute(statement) => {
+        let leaderAddress= redirector.as_ref( )
+                                     .filter(|_| session.transaction( ).is_none( ))
+                                     .and_then(Redirector::leaderAddress);
+        match leaderAddress {
+          Some(leaderAddress) => Err(Error::NotLeader { leaderAddress }),
+          None => {
+            streaming.write(&mut stream, session.execute(&statement))?;
+            continue
+          }
+        }
+      },
+
+      Request::Prepare(statement) => session.prepare(&statement)
+                                            .map(|(handle, parameterCount)| Response::Prepared { handle, parameterCount }),
+
+      Request::ExecutePrepared { handle, parameters } => {
+        streaming.write(&mut stream, session.executePrepared(handle, &parameters))?;
+        continue
+      },
+
+      Request::Deallocate(handle) => session.deallocate(handle).map(|_| Response::Deallocated),
+
+      Request::CopyData(lines) => session.copyData(&lines).map(|_| Response::CopyData),
+      Request::CopyEnd => session.copyEnd( )
+                                 .and_then(StatementResult::try_from)
+                                 .map(Response::Execute),
+
+      Request::GetTable(table) => session.read(|txn| txn.mustGetTable(&table)).map(Response::Table),
+
+      Request::GetStatus => session.status( )
+                                   .map(|status| Response::Status(Box::new(Status { streaming: Some(streaming.metrics.status( )), ..status }))),
+
+      Request::Backup(path) => session.backup(Path::new(&path), |table, rows| info!("Backing up {} : {} rows of table {}", path, rows, table))
+                                      .map(Response::BackedUp),
+      Request::Restore { path, force } => session.restore(Path::new(&path), force, |table, rows| info!("Restoring {} : {} rows of table {}", path, rows, table))
+                                                 .map(Response::Restored),
+
+      Request::GetCancelKey => Ok(Response::CancelKey(cancelKey)),
+      Request::Cancel(key) => match cancelHandles.lock( ).unwrap( ).get(&key) {
+        Some(cancelHandle) => {
+          cancelHandle.cancel( );
+          Ok(Response::Cancelled)
+        },
+        None => Err(Error::Value(ErrorCode::UndefinedObject, format!("No connection has the cancel key {}", key)))
+      },
+
+      Request::AddNode { id, address } => raftClient.addNode(id, address).map(|_| Response::MembershipChanged),
+      Request::RemoveNode(id) => raftClient.removeNode(id).map(|_| Response::MembershipChanged),
+
+      Request::TransferLeadership(target) => raftClient.transferLeadership(target).map(|_| Response::LeadershipTransferred)
+    };
+
+    writeFrame(&mut stream, &response)?;
+  }
+
+  // Rolls back the COPY and the transaction left open by the client.
+  session.abortCopy( )?;
+  if session.transaction( ).is_some( ) {
+    session.execute("ROLLBACK")?;}
+
+  Ok(( ))
+}
+
+// Default number of rows in a batch of a streamed query result (see ResultFrames).
+pub const DEFAULT_ROW_BATCH_SIZE: usize= 1024;
+
+/*
+  The response frames to a statement. Unless the statement results in the rows of a query, that's a
+  single Response::Execute.
+
+  The rows of a query are streamed, instead of being materialized : a Response::QueryHeader (with the
+  columns) is followed by the rows in batches of upto the batch size (Response::Rows), and then by a
+  Response::QueryEnd with the number of rows. If reading the rows fails midway (like when the
+  statement is cancelled), the error is sent in place of the end - after the rows read before it.
+
+  The rows are pulled from the executors as the frames are written. So the server only holds a
+  batch of rows at a time : a client reading slowly holds back the execution (through the socket's
+  backpressure), instead of the rows piling up.
+
+  NOTE : The rows of a table are still read from the storage engine whole, by a scan (see
+  Transaction::scan). So just the rows computed by a query (like those of a join) are streamed
+  without being held.
+*/
+pub struct ResultFrames {
+  state: FramesState,
+  batchSize: usize,
+  metrics: StreamMetrics,
+
+  // Number of rows sent so far.
+  count: u64
+}
+
+enum FramesState {
+  Start(Box<ResultSet>),
+  Rows(Fuse<Rows>),
+  // Reading the rows failed, after a batch was read.
+  Failed(Error),
+  Ended
+}
+
+impl ResultFrames {
+  pub fn new(resultSet: ResultSet, batchSize: usize, metrics: StreamMetrics) -> Self {
+    Self { state: FramesState::Start(Box::new(resultSet)), batchSize, metrics, count: 0 }
+  }
+
+  // Counts a batch of rows, before it's sent.
+  fn sendBatch(&mut self, batch: Vec<Row>) -> Response {
+    self.count += batch.len( ) as u64;
+
+    self.metrics.batches.fetch_add(1, Ordering::Relaxed);
+    self.metrics.rows.fetch_add(batch.len( ) as u64, Ordering::Relaxed);
+    self.metrics.maxBatchSize.fetch_max(bincode::serialized_size(&batch).unwrap_or_default( ), Ordering::Relaxed);
+
+    Response::Rows(batch)
+  }
+}
+
+
+impl Iterator for ResultFrames {
+  type Item= Result<Response>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match mem::replace(&mut self.state, FramesState::Ended) {
+      FramesState::Start(resultSet) => match *resultSet {
+        ResultSet::Query { columns, rows } => {
+          self.state= FramesState::Rows(rows.fuse( ));
+          self.metrics.queries.fetch_add(1, Ordering::Relaxed);
+          Some(Ok(Response::QueryHeader(columns)))
+        },
+
+        resultSet => Some(StatementResult::try_from(resultSet).map(Response::Execute))
+      },
+
+      FramesState::Rows(mut rows) => {
+        let mut batch= Vec::new( );
+        while batch.len( ) < self.batchSize {
+          match rows.next( ) {
+            Some(Ok(row)) => batch.push(row),
+
+            Some(Err(error)) if batch.is_empty( ) => return Some(Err(error)),
+            Some(Err(error)) => {
+              self.state= FramesState::Failed(error);
+              return Some(Ok(self.sendBatch(batch)))
+            },
+
+            None if batch.is_empty( ) => return Some(Ok(Response::QueryEnd { count: self.count })),
+            None => break
+          }
+        }
+
+        self.state= FramesState::Rows(rows);
+        Some(Ok(self.sendBatch(batch)))
+      },
+
+      FramesState::Failed(error) => Some(Err(error)),
+      FramesState::Ended => None
+    }
+  }
+}
+
+// Counters of the query results streamed by a server, across all its connections.
+#[derive(Clone, Default)]
+pub struct StreamMetrics {
+  queries: Arc<AtomicU64>,
+  batches: Arc<AtomicU64>,
+  rows: Arc<AtomicU64>,
+  maxBatchSize: Arc<AtomicU64>
+}
+
+impl StreamMetrics {
+  pub fn status(&self) -> StreamStatus {
+    StreamStatus {
+      queries: self.queries.load(Ordering::Relaxed),
+      batches: self.batches.load(Ordering::Relaxed),
+      rows: self.rows.load(Ordering::Relaxed),
+      maxBatchSize: self.maxBatchSize.load(Ordering::Relaxed)
+    }
+  }
+}
+
+// Status of the streaming of query results, reported along with the status of the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StreamStatus {
+  // Number of query results streamed, and number of batches (and rows) sent in them.
+  pub queries: u64,
+  pub batches: u64,
+  pub rows: u64,
+
+  // Size (in bytes) of the largest batch sent. Since a batch is all the server holds of a result at
+  // a time, it bounds the memory taken by the streaming.
+  pub maxBatchSize: u64
+}
+
+// How the query results of a connection are streamed.
+struct Streaming {
+  batchSize: usize,
+  metrics: StreamMetrics
+}
+
+impl Streaming {
+  // Writes the response frames to the result of a statement (see ResultFrames), or the error it
+  // failed with.
+  fn write(&self, writer: &mut impl Write, result: Result<ResultSet>) -> Result<( )> {
+    match result {
+      Ok(resultSet) => {
+        for frame in ResultFrames::new(resultSet, self.batchSize, self.metrics.clone( )) {
+          writeFrame(writer, &frame)?;}
+        Ok(( ))
+      },
+
+      Err(error) => writeFrame(writer, &Err::<Response, _>(error))
+    }
+  }
+}
+
+// Reads a length-prefixed frame. Returns None, if the stream ended before the next frame.
+pub fn readFrame<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+  let mut length= [0; 4];
+  match reader.read_exact(&mut length) {
+    Ok(( )) => { },
+    Err(error) if error.kind( ) == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+    Err(error) => return Err(error.into( ))
+  }
+
+  let mut frame= vec![0; u32::from_be_bytes(length) as usize];
+  reader.read_exact(&mut frame)?;
+
+  Ok(Some(bincode::deserialize(&frame)?))
+}
+
+// Writes the value as a length-prefixed frame.
+pub fn writeFrame<T: Serialize>(writer: &mut impl Write, value: &T) -> Result<( )> {
+  let frame= bincode::serialize(value)?;
+  let length= u32::try_from(frame.len( ))
+                  .map_err(|_| Error::Internal(format!("Frame of {} bytes is too large to be sent", frame.len( ))))?;
+
+  writer.write_all(&length.to_be_bytes( ))?;
+  writer.write_all(&frame)?;
+  writer.flush( )?;
+
+  Ok(( ))
+}
@@ -0,0 +1,123 @@
+use crate::result::{Error, ErrorCode, Result};
+use super::{datetime, execution::Columns, schema::Table, types::{DataType, Row, Value}};
+
+/*
+  Parses a CSV line into its fields. Fields are separated by commas, and can be quoted with double
+  quotes (so that they can contain commas) - a doubled quote inside a quoted field being an escaped
+  quote.
+
+  An unquoted empty field is NULL (None), while a quoted empty field is an empty string.
+
+  NOTE : Quoted fields can't span multiple lines.
+*/
+pub fn parseLine(line: &str) -> Result<Vec<Option<String>>> {
+  let mut fields= Vec::new( );
+  let mut characters= line.chars( ).peekable( );
+
+  loop {
+    let mut field= String::new( );
+
+    let isQuoted= characters.next_if_eq(&'"').is_some( );
+    match isQuoted {
+      true => loop {
+        match characters.next( ) {
+          Some('"') => match characters.next_if_eq(&'"') {
+            Some(_) => field.push('"'),
+            None => break
+          },
+          Some(character) => field.push(character),
+          None => return Err(Error::Value(ErrorCode::InvalidTextRepresentation, format!("Unterminated quoted field in CSV line {}", line)))
+        }
+      },
+
+      false => {
+        while let Some(character)= characters.next_if(|character| *character != ',') {
+          field.push(character);}
+      }
+    }
+
+    fields.push(Some(field).filter(|field| isQuoted || !field.is_empty( )));
+
+    match characters.next( ) {
+      Some(',') => continue,
+      Some(_) => return Err(Error::Value(ErrorCode::InvalidTextRepresentation, format!("Unexpected characters after a quoted field in CSV line {}", line))),
+      None => return Ok(fields)
+    }
+  }
+}
+
+// Parses a CSV line into a row of the table. Every column must have a field, holding a value of the
+// column's data type.
+pub fn parseRow(line: &str, table: &Table) -> Result<Row> {
+  let fields= parseLine(line)?;
+
+  if fields.len( ) != table.columns.len( ) {
+    return Err(Error::Value(ErrorCode::DataException, format!(
+      "Table {} has {} columns, but got {} values", table.name, table.columns.len( ), fields.len( )
+    )))}
+
+  table.columns.iter( )
+               .zip(fields)
+               .map(|(column, field)| {
+                 let Some(field)= field else {
+                   return Ok(Value::Null)
+                 };
+
+                 let invalid= || Error::Value(ErrorCode::InvalidTextRepresentation, format!("Invalid {} value {} for column {}", column.dataType, field, column.name));
+                 Ok(match column.dataType {
+                   DataType::Boolean => match field.to_lowercase( ).as_str( ) {
+                     "true" => Value::Boolean(true),
+                     "false" => Value::Boolean(false),
+                     _ => return Err(invalid( ))
+                   },
+                   DataType::Integer => Value::Integer(field.trim( ).parse( ).map_err(|_| invalid( ))?),
+                   DataType::Float => Value::Float(field.trim( ).parse( ).map_err(|_| invalid( ))?),
+                   DataType::String => Value::String(field),
+                   DataType::Date => Value::Date(datetime::parseDate(&field).ok_or_else(invalid)?),
+                   DataType::Timestamp => Value::Timestamp(datetime::parseTimestamp(&field).ok_or_else(invalid)?)
+                 })
+               })
+               .collect( )
+}
+
+/*
+  Formats the fields as a CSV line (the reverse of parseLine). A field is quoted, if it's empty or
+  contains a comma, a quote or a line break - with the quotes inside it doubled. A None field (NULL)
+  is left empty and unquoted.
+
+  NOTE : A field with a line break spans multiple lines, so it can't be parsed back by parseLine.
+*/
+pub fn formatLine(fields: &[Option<String>]) -> String {
+  fields.iter( )
+        .map(|field| match field {
+          None => String::new( ),
+          Some(field) if field.is_empty( ) || field.contains([',', '"', '\n', '\r']) => format!("\"{}\"", field.replace('"', "\"\"")),
+          Some(field) => field.clone( )
+        })
+        .collect::<Vec<_>>( )
+        .join(",")
+}
+
+// Formats the rows of a query as CSV lines, preceded by a header line with the column labels
+// (unlabeled columns get ?).
+pub fn formatRows(columns: &Columns, rows: &[Row]) -> String {
+  std::iter::once(formatHeader(columns)).chain(rows.iter( ).map(formatRow)).collect::<Vec<_>>( ).join("\n")
+}
+
+// Formats the header line of formatRows( ), so that the rows can be formatted as they arrive.
+pub fn formatHeader(columns: &Columns) -> String {
+  let header: Vec<Option<String>>= columns.iter( )
+                                          .map(|column| Some(column.label.clone( ).unwrap_or_else(| | "?".to_string( ))))
+                                          .collect( );
+  formatLine(&header)
+}
+
+pub fn formatRow(row: &Row) -> String {
+  let fields: Vec<Option<String>>= row.iter( )
+                                      .map(|value| match value {
+                                        Value::Null => None,
+                                        value => Some(value.to_string( ))
+                                      })
+                                      .collect( );
+  formatLine(&fields)
+}
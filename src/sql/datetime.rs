@@ -0,0 +1,167 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/*
+  Conversions of the DATE and TIMESTAMP values, from and to their textual forms.
+
+  A date is stored as the number of days since the Unix epoch (1970-01-01), and a timestamp as the
+  number of microseconds since the Unix epoch (1970-01-01 00:00:00). Both use the proleptic Gregorian
+  calendar. Timestamps don't have a time zone (the ones returned by now( ) are in UTC).
+
+  The textual forms are 'YYYY-MM-DD' for dates, and 'YYYY-MM-DD HH:MM:SS[.ffffff]' for timestamps (a
+  'T' may separate the date and the time as well, like in ISO 8601). Years range from 0001 to 9999.
+*/
+
+pub const MICROSECONDS_PER_DAY: i64= 86_400_000_000;
+
+// The range of the supported dates, as the number of days since the Unix epoch.
+const MIN_DAYS: i64= daysFromCivil(1, 1, 1);
+const MAX_DAYS: i64= daysFromCivil(9999, 12, 31);
+
+// Parses a date of the form YYYY-MM-DD. Returns None, if it's malformed or out of range.
+pub fn parseDate(text: &str) -> Option<i64> {
+  let mut parts= text.trim( ).split('-');
+
+  let year= parseDigits(parts.next( )?, 4)?;
+  let month= parseDigits(parts.next( )?, 2)?;
+  let day= parseDigits(parts.next( )?, 2)?;
+  if parts.next( ).is_some( ) || year == 0 || !(1..=12).contains(&month) || day == 0 || day > daysInMonth(year, month) {
+    return None}
+
+  Some(daysFromCivil(year, month, day))
+}
+
+// Parses a timestamp of the form YYYY-MM-DD HH:MM:SS[.ffffff] (with upto 6 fractional digits). The
+// time may be left out, for midnight.
+pub fn parseTimestamp(text: &str) -> Option<i64> {
+  let text= text.trim( );
+  let (date, time)= match text.split_once([' ', 'T']) {
+    Some((date, time)) => (date, Some(time)),
+    None => (text, None)
+  };
+
+  let days= parseDate(date)?;
+  let Some(time)= time else {
+    return Some(days * MICROSECONDS_PER_DAY)
+  };
+
+  let (time, fraction)= match time.split_once('.') {
+    Some((time, fraction)) => (time, Some(fraction)),
+    None => (time, None)
+  };
+
+  let mut parts= time.split(':');
+  let hours= parseDigits(parts.next( )?, 2)?;
+  let minutes= parseDigits(parts.next( )?, 2)?;
+  let seconds= parseDigits(parts.next( )?, 2)?;
+  if parts.next( ).is_some( ) || hours > 23 || minutes > 59 || seconds > 59 {
+    return None}
+
+  let microseconds= match fraction {
+    Some(fraction) if (1..=6).contains(&fraction.len( )) =>
+      parseDigits(fraction, fraction.len( ))? * 10i64.pow(6 - fraction.len( ) as u32),
+    Some(_) => return None,
+    None => 0
+  };
+
+  Some(days * MICROSECONDS_PER_DAY + ((hours * 60 + minutes) * 60 + seconds) * 1_000_000 + microseconds)
+}
+
+// Formats a date as YYYY-MM-DD.
+pub fn formatDate(days: i64) -> String {
+  let (year, month, day)= civilFromDays(days);
+  format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Formats a timestamp as YYYY-MM-DD HH:MM:SS, followed by the fraction of the second (without the
+// trailing zeroes), if any.
+pub fn formatTimestamp(timestamp: i64) -> String {
+  let days= timestamp.div_euclid(MICROSECONDS_PER_DAY);
+  let microseconds= timestamp.rem_euclid(MICROSECONDS_PER_DAY);
+
+  let seconds= microseconds / 1_000_000;
+  let mut formatted= format!("{} {:02}:{:02}:{:02}", formatDate(days), seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+  let fraction= microseconds % 1_000_000;
+  if fraction != 0 {
+    formatted.push_str(format!(".{:06}", fraction).trim_end_matches('0'));
+  }
+  formatted
+}
+
+// Returns whether the date (as the number of days since the Unix epoch) is in the supported range.
+pub fn isValidDate(days: i64) -> bool {
+  (MIN_DAYS..=MAX_DAYS).contains(&days)
+}
+
+// Returns whether the timestamp (as the number of microseconds since the Unix epoch) is in the
+// supported range.
+pub fn isValidTimestamp(timestamp: i64) -> bool {
+  isValidDate(timestamp.div_euclid(MICROSECONDS_PER_DAY))
+}
+
+// Returns the current time (as per the local clock), as the number of microseconds since the Unix
+// epoch.
+pub fn currentTimestamp( ) -> i64 {
+  match SystemTime::now( ).duration_since(UNIX_EPOCH) {
+    Ok(elapsed) => elapsed.as_micros( ) as i64,
+    Err(error) => -(error.duration( ).as_micros( ) as i64)
+  }
+}
+
+// Parses a non-negative number made up of exactly the given number of digits.
+fn parseDigits(text: &str, digits: usize) -> Option<i64> {
+  if text.len( ) != digits || !text.bytes( ).all(|byte| byte.is_ascii_digit( )) {
+    return None}
+
+  text.parse( ).ok( )
+}
+
+fn isLeapYear(year: i64) -> bool {
+  (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn daysInMonth(year: i64, month: i64) -> i64 {
+  match month {
+    2 if isLeapYear(year) => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31
+  }
+}
+
+/*
+  Converts a date of the proleptic Gregorian calendar into the number of days since the Unix epoch.
+
+  The algorithm (by Howard Hinnant) shifts the start of the year to March, so that the leap day is
+  at the end of the year, and then counts the days in whole 400 year eras (of 146097 days each).
+*/
+const fn daysFromCivil(year: i64, month: i64, day: i64) -> i64 {
+  let year= if month <= 2 { year - 1 } else { year };
+  let era= year.div_euclid(400);
+  let yearOfEra= year - era * 400;
+
+  let shiftedMonth= if month > 2 { month - 3 } else { month + 9 };
+  let dayOfYear= (153 * shiftedMonth + 2) / 5 + day - 1;
+  let dayOfEra= yearOfEra * 365 + yearOfEra / 4 - yearOfEra / 100 + dayOfYear;
+
+  // 719468 is the number of days from 0000-03-01 to 1970-01-01.
+  era * 146097 + dayOfEra - 719468
+}
+
+// The inverse of daysFromCivil( ) : converts the number of days since the Unix epoch into a (year,
+// month, day) date.
+fn civilFromDays(days: i64) -> (i64, i64, i64) {
+  let days= days + 719468;
+  let era= days.div_euclid(146097);
+  let dayOfEra= days - era * 146097;
+
+  let yearOfEra= (dayOfEra - dayOfEra / 1460 + dayOfEra / 36524 - dayOfEra / 146096) / 365;
+  let dayOfYear= dayOfEra - (yearOfEra * 365 + yearOfEra / 4 - yearOfEra / 100);
+  let shiftedMonth= (5 * dayOfYear + 2) / 153;
+
+  let day= dayOfYear - (153 * shiftedMonth + 2) / 5 + 1;
+  let month= if shiftedMonth < 10 { shiftedMonth + 3 } else { shiftedMonth - 9 };
+  let year= yearOfEra + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  (year, month, day)
+}
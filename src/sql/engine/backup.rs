@@ -0,0 +1,348 @@
+use std::{
+  collections::{HashMap, HashSet},
+  io::{BufReader, BufWriter, ErrorKind, Read, Write},
+  mem
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{schema::{Catalog, Index, Table}, types::{Row, Rows, Value}},
+  storage::mvcc::Version
+};
+use super::{Engine, Transaction, COPY_BATCH_SIZE};
+
+/*
+  Backups of the SQL data : a consistent snapshot of all the tables, exported to a file, which can be
+  restored into an empty database.
+
+  The backup reads the tables in a single read-only transaction, so it sees the database as of the
+  version the transaction began at. It doesn't block the concurrent writers, since the versions it
+  reads are kept until it ends (see MVCC).
+
+  The format of a backup file (with the integers encoded big-endian) :
+
+    (a) A header : the magic bytes SQLBACKUP, the version of the format (u32) and the version of the
+        snapshot (u64).
+
+    (b) Records, each a tag byte followed by the length (u64) and the bincode encoding of its payload :
+
+          T - The schema of a table (see Table). It's followed by the rows of the table.
+          R - A row of the last table.
+          E - The end of the backup, with the number of tables and rows (u64, u64) in it. A file
+              without it has been truncated.
+
+  The tables are written in the order they were created in (by their schema versions). A table can
+  only reference the tables which existed when it was created, and a referenced table can't be
+  dropped : so the referenced tables come first. The rows of a table referencing itself are
+  reordered, so that every row comes after the row it references.
+
+  NOTE : The rows of a table referencing each other in a cycle (which only updates can create) can't
+  be ordered that way, so such a table can't be backed up.
+*/
+
+// Version of the backup format, written in the header. It's bumped whenever the format changes.
+pub const BACKUP_FORMAT_VERSION: u32= 2;
+
+const MAGIC: &[u8]= b"SQLBACKUP";
+
+const TABLE_RECORD: u8= b'T';
+const ROW_RECORD: u8= b'R';
+const END_RECORD: u8= b'E';
+
+// Number of rows between the progress reports of a table.
+const PROGRESS_INTERVAL: u64= 10_000;
+
+// What a backup holds (or a restore has loaded).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BackupSummary {
+  // Version of the snapshot.
+  pub version: Version,
+
+  pub tables: u64,
+  pub rows: u64
+}
+
+/*
+  Writes a backup of the database to the writer. The progress is reported (with the name of the table
+  and the number of its rows written so far) every PROGRESS_INTERVAL rows, and once a table is done.
+
+  NOTE : The writer isn't synced. If it's a file, the caller must sync it for the backup to be durable.
+*/
+pub fn backup<E: Engine>(engine: &E, writer: impl Write, mut progress: impl FnMut(&str, u64)) -> Result<BackupSummary> {
+  let txn= engine.beginReadOnly( )?;
+  let result= writeBackup(&txn, &mut BufWriter::new(writer), &mut progress);
+  txn.commit( )?;
+  result
+}
+
+fn writeBackup<T: Transaction>(txn: &T, writer: &mut impl Write, progress: &mut impl FnMut(&str, u64)) -> Result<BackupSummary> {
+  let mut summary= BackupSummary { version: txn.version( ), tables: 0, rows: 0 };
+
+  writer.write_all(MAGIC)?;
+  writer.write_all(&BACKUP_FORMAT_VERSION.to_be_bytes( ))?;
+  writer.write_all(&summary.version.to_be_bytes( ))?;
+
+  let mut tables= txn.listTables( )?;
+  tables.sort_by_key(|table| table.schemaVersion);
+
+  for table in tables {
+    writeRecord(writer, TABLE_RECORD, &table)?;
+    summary.tables += 1;
+
+    let mut rows= 0;
+    for row in ReferencedFirst::new(&table, txn.scan(&table.name)?) {
+      writeRecord(writer, ROW_RECORD, &row?)?;
+      rows += 1;
+
+      if rows % PROGRESS_INTERVAL == 0 {
+        progress(&table.name, rows);}
+    }
+    progress(&table.name, rows);
+    summary.rows += rows;
+  }
+
+  writeRecord(writer, END_RECORD, &(summary.tables, summary.rows))?;
+  writer.flush( )?;
+  Ok(summary)
+}
+
+fn writeRecord(writer: &mut impl Write, tag: u8, payload: &impl Serialize) -> Result<( )> {
+  let encoded= bincode::serialize(payload)?;
+  writer.write_all(&[tag])?;
+  writer.write_all(&(encoded.len( ) as u64).to_be_bytes( ))?;
+  writer.write_all(&encoded)?;
+  Ok(( ))
+}
+
+/*
+  Orders the rows of a table, so that a row comes after the rows of the table it references. The rows
+  are kept in their order otherwise.
+
+  A row referencing a row which hasn't been produced yet waits for it. So, the primary keys of the
+  rows produced so far are kept in memory, along with the waiting rows.
+*/
+struct ReferencedFirst<'a> {
+  table: &'a Table,
+  rows: Rows,
+
+  // The columns referencing the table itself. The rows pass through, if there are none.
+  columns: Vec<usize>,
+
+  produced: HashSet<Value>,
+  // The waiting rows, by the primary key they're waiting for.
+  waiting: HashMap<Value, Vec<Row>>,
+  // The rows which have stopped waiting, to be produced next.
+  ready: Vec<Row>
+}
+
+impl<'a> ReferencedFirst<'a> {
+  fn new(table: &'a Table, rows: Rows) -> Self {
+    let columns= (0..table.columns.len( )).filter(|&column| table.columns[column].references.as_ref( ) == Some(&table.name))
+                                          .collect( );
+    Self { table, rows, columns, produced: HashSet::new( ), waiting: HashMap::new( ), ready: Vec::new( ) }
+  }
+
+  // Returns the primary key, which the row has to wait for (if any).
+  fn missingReference(&self, row: &Row) -> Option<Value> {
+    let primaryKey= &row[self.table.primaryKey];
+    self.columns.iter( )
+                .map(|&column| &row[column])
+                .find(|value| !value.isNull( ) && *value != primaryKey && !self.produced.contains(*value))
+                .cloned( )
+  }
+}
+
+impl Iterator for ReferencedFirst<'_> {
+  type Item= Result<Row>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.columns.is_empty( ) {
+      return self.rows.next( )}
+
+    loop {
+      let row= match self.ready.pop( ) {
+        Some(row) => row,
+        None => match self.rows.next( ) {
+          Some(Ok(row)) => row,
+          Some(Err(error)) => return Some(Err(error)),
+
+          None if self.waiting.is_empty( ) => return None,
+          None => {
+            let primaryKeys= self.waiting.values( ).flatten( ).map(|row| row[self.table.primaryKey].to_string( )).collect::<Vec<_>>( );
+            self.waiting.clear( );
+            return Some(Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, format!(
+              "Can't back up table {}, since its rows {} reference each other in a cycle", self.table.name, primaryKeys.join(", ")
+            ))))
+          }
+        }
+      };
+
+      if let Some(missing)= self.missingReference(&row) {
+        self.waiting.entry(missing).or_default( ).push(row);
+        continue
+      }
+
+      let primaryKey= row[self.table.primaryKey].clone( );
+      if let Some(waiting)= self.waiting.remove(&primaryKey) {
+        self.ready.extend(waiting.into_iter( ).rev( ));}
+      self.produced.insert(primaryKey);
+
+      return Some(Ok(row))
+    }
+  }
+}
+
+/*
+  Restores a backup read from the reader, in a single transaction : so either the whole backup is
+  restored, or (if it fails midway) nothing is. The progress is reported like for backup( ).
+
+  The database must be empty, unless force is set : all the tables are dropped first then.
+
+  The tables are created without their expression indexes, which are built once the rows have been
+  loaded (see Catalog::createIndex). The rows are loaded in batches, like by a COPY (see
+  Transaction::createMany), so they're validated against the constraints of their tables.
+*/
+pub fn restore<E: Engine>(engine: &E, reader: impl Read, force: bool, mut progress: impl FnMut(&str, u64)) -> Result<BackupSummary> {
+  let mut txn= engine.begin( )?;
+
+  match readBackup(&mut txn, &mut BufReader::new(reader), force, &mut progress) {
+    Ok(summary) => {
+      txn.commit( )?;
+      Ok(summary)
+    },
+
+    Err(error) => {
+      txn.rollback( )?;
+      Err(error)
+    }
+  }
+}
+
+fn readBackup<T: Transaction>(txn: &mut T, reader: &mut impl Read, force: bool, progress: &mut impl FnMut(&str, u64)) -> Result<BackupSummary> {
+  let mut magic= [0; MAGIC.len( )];
+  reader.read_exact(&mut magic).map_err(truncated)?;
+  if magic != MAGIC {
+    return Err(Error::Value(ErrorCode::InvalidBackup, "Not a backup file".to_string( )))}
+
+  let formatVersion= u32::from_be_bytes(readArray(reader)?);
+  if formatVersion != BACKUP_FORMAT_VERSION {
+    return Err(Error::Value(ErrorCode::InvalidBackup, format!("Unsupported backup format version {}, expected {}", formatVersion, BACKUP_FORMAT_VERSION)))}
+
+  let mut summary= BackupSummary { version: u64::from_be_bytes(readArray(reader)?), tables: 0, rows: 0 };
+
+  // The referencing tables are dropped before the tables they reference.
+  let mut existingTables= txn.listTables( )?;
+  if !existingTables.is_empty( ) && !force {
+    return Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, format!("The database isn't empty (it has {} tables), restoring into it needs --force", existingTables.len( ))))}
+
+  existingTables.sort_by_key(|table| std::cmp::Reverse(table.schemaVersion));
+  for table in existingTables {
+    txn.dropTable(&table.name)?;}
+
+  let mut loading: Option<TableLoad>= None;
+  loop {
+    let tag= readArray::<1>(reader)?[0];
+    match tag {
+      TABLE_RECORD => {
+        if let Some(load)= loading.take( ) {
+          load.finish(txn, progress)?;}
+
+        let mut table: Table= readPayload(reader)?;
+        let indexes= mem::take(&mut table.indexes);
+        let name= table.name.clone( );
+        txn.createTable(table)?;
+
+        loading= Some(TableLoad { table: name, indexes, batch: Vec::new( ), rows: 0 });
+        summary.tables += 1;
+      },
+
+      ROW_RECORD => {
+        let load= loading.as_mut( ).ok_or_else(| | Error::Value(ErrorCode::InvalidBackup, "Invalid backup : a row precedes the first table".to_string( )))?;
+        load.batch.push(readPayload(reader)?);
+        summary.rows += 1;
+
+        if load.batch.len( ) == COPY_BATCH_SIZE {
+          load.flush(txn, progress)?;}
+      },
+
+      END_RECORD => {
+        if let Some(load)= loading.take( ) {
+          load.finish(txn, progress)?;}
+
+        let (tables, rows): (u64, u64)= readPayload(reader)?;
+        if (tables, rows) != (summary.tables, summary.rows) {
+          return Err(Error::Value(ErrorCode::InvalidBackup, format!(
+            "Invalid backup : it ends after {} tables and {} rows, but holds {} tables and {} rows", tables, rows, summary.tables, summary.rows
+          )))}
+
+        return Ok(summary)
+      },
+
+      tag => return Err(Error::Value(ErrorCode::InvalidBackup, format!("Invalid backup : unknown record {:?}", tag as char)))
+    }
+  }
+}
+
+// The table whose rows are being loaded.
+struct TableLoad {
+  table: String,
+  // The expression indexes, built once the rows have been loaded.
+  indexes: Vec<Index>,
+
+  batch: Vec<Row>,
+  // Number of rows loaded so far.
+  rows: u64
+}
+
+impl TableLoad {
+  fn flush<T: Transaction>(&mut self, txn: &mut T, progress: &mut impl FnMut(&str, u64)) -> Result<( )> {
+    let batch= mem::take(&mut self.batch);
+    let size= batch.len( ) as u64;
+
+    txn.createMany(&self.table, batch, self.rows + 1)?;
+    self.rows += size;
+
+    if self.rows % PROGRESS_INTERVAL < size {
+      progress(&self.table, self.rows);}
+    Ok(( ))
+  }
+
+  fn finish<T: Transaction>(mut self, txn: &mut T, progress: &mut impl FnMut(&str, u64)) -> Result<( )> {
+    if !self.batch.is_empty( ) {
+      self.flush(txn, progress)?;}
+
+    for index in self.indexes {
+      txn.createIndex(&self.table, index)?;}
+
+    progress(&self.table, self.rows);
+    Ok(( ))
+  }
+}
+
+fn readArray<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+  let mut array= [0; N];
+  reader.read_exact(&mut array).map_err(truncated)?;
+  Ok(array)
+}
+
+fn readPayload<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T> {
+  let length= u64::from_be_bytes(readArray(reader)?);
+
+  let mut encoded= Vec::new( );
+  reader.by_ref( ).take(length).read_to_end(&mut encoded)?;
+  if encoded.len( ) as u64 != length {
+    return Err(truncatedError( ))}
+
+  bincode::deserialize(&encoded).map_err(|error| Error::Value(ErrorCode::InvalidBackup, format!("Invalid backup : {}", error)))
+}
+
+fn truncated(error: std::io::Error) -> Error {
+  match error.kind( ) {
+    ErrorKind::UnexpectedEof => truncatedError( ),
+    _ => error.into( )
+  }
+}
+
+fn truncatedError( ) -> Error {
+  Error::Value(ErrorCode::InvalidBackup, "Invalid backup : the file is truncated".to_string( ))
+}
@@ -0,0 +1,848 @@
+use std::{collections::{HashMap, HashSet}, ops::Bound};
+use serde::{Deserialize, Serialize};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{
+    schema::{Catalog, Index, IndexId, ReferentialAction, SchemaVersion, Table, TableId, VERSION_COLUMN},
+    stats::{StatsCollector, TableStats},
+    types::{Row, Rows, Value}
+  },
+  storage::{engine::{Direction, KeyRange, StorageEngine, WriteBatch}, keycode, mvcc::{self, Version, WriteLimits, MVCC}, sync::SyncPolicy}
+};
+use super::{rowError, stats_cache::StatsCache, Engine, IndexRange, Status, Transaction as _};
+
+/*
+  A SQL engine, which stores the SQL data (schemas, rows and indexes) as key-value pairs in a local
+  MVCC store. The keys are encoded using the order-preserving key encoding, and the values are
+  serialized using bincode.
+
+  The schemas are stored by the table names, while the rows and the index entries are stored by the
+  ids of the tables (and of the indexed columns). So, renaming a table or a column only rewrites the
+  schemas.
+
+  A row has an entry of its own in every index of the table, keyed by its indexed value followed by
+  its primary key. So, the entries of the rows sharing a value are a contiguous range of keys, and a
+  write only touches the entries of the written row. The unique indexes map the values to the primary
+  keys instead (see Key::UniqueIndex) : a conflicting write then writes the same key, which the MVCC
+  store detects even across concurrent transactions.
+
+  The statistics of the tables are cached in memory (see StatsCache), by the transactions of the
+  engine.
+*/
+pub struct KV<E: StorageEngine> {
+  pub mvcc: MVCC<E>,
+  stats: StatsCache
+}
+
+impl<E: StorageEngine> Clone for KV<E> {
+  fn clone(&self) -> Self {
+    Self { mvcc: self.mvcc.clone( ), stats: self.stats.clone( ) }
+  }
+}
+
+impl<E: StorageEngine + 'static> KV<E> {
+  pub fn new(engine: E, writeLimits: WriteLimits, syncPolicy: SyncPolicy) -> Self {
+    Self { mvcc: MVCC::new(engine, writeLimits, syncPolicy), stats: StatsCache::default( ) }
+  }
+}
+
+impl<E: StorageEngine> KV<E> {
+  // Resumes a transaction, from its state.
+  pub fn resume(&self, state: mvcc::TransactionState) -> Result<Transaction<E>> {
+    Ok(Transaction::new(self.mvcc.resume(state)?, self.stats.clone( )))
+  }
+}
+
+impl<E: StorageEngine + 'static> Engine for KV<E> {
+  type Transaction= Transaction<E>;
+
+  fn begin(&self) -> Result<Self::Transaction> {
+    Ok(Transaction::new(self.mvcc.begin( )?, self.stats.clone( )))
+  }
+
+  fn beginReadOnly(&self) -> Result<Self::Transaction> {
+    Ok(Transaction::new(self.mvcc.beginReadOnly( )?, self.stats.clone( )))
+  }
+
+  fn beginAsOf(&self, version: Version) -> Result<Self::Transaction> {
+    Ok(Transaction::new(self.mvcc.beginAsOf(version)?, self.stats.clone( )))
+  }
+
+  fn status(&self) -> Result<Status> {
+    Ok(Status { raft: None, mvcc: self.mvcc.status( )?, planCache: None, streaming: None })
+  }
+}
+
+// Keys used by the SQL engine.
+#[derive(Debug, Serialize, Deserialize)]
+enum Key {
+  // A table schema, keyed by the table name.
+  Table(String),
+
+  // An index entry, keyed by the (table id, index id, indexed value, primary key of the row). The
+  // value is empty.
+  Index(TableId, IndexId, Value, Value),
+
+  // An entry of a unique index, keyed by the (table id, index id, indexed value). The value is the
+  // primary key of the row having that value.
+  // NOTE : NULLs don't conflict with each other, so the rows having NULL in a unique index get the
+  // entries of a non-unique index instead (see indexEntry( )).
+  UniqueIndex(TableId, IndexId, Value),
+
+  // A row, keyed by the (table id, primary key).
+  Row(TableId, Value),
+
+  // The version of the schema, bumped by every change of the schema. Since it's written by every
+  // DDL statement, concurrent DDL statements conflict (see MVCC).
+  SchemaVersion,
+
+  // The id of the last created table.
+  LastTableId,
+
+  // The statistics of a table (see TableStats), keyed by the table id.
+  TableStats(TableId)
+}
+
+impl Key {
+  fn encode(&self) -> Result<Vec<u8>> {
+    keycode::serialize(self)
+  }
+}
+
+// Prefixes of the SQL engine keys, used for prefix scans. Must match the variants of Key.
+#[derive(Debug, Serialize)]
+enum KeyPrefix {
+  Table,
+  Index(TableId, IndexId),
+  UniqueIndex(TableId, IndexId),
+  Row(TableId),
+  SchemaVersion
+}
+
+impl KeyPrefix {
+  fn encode(&self) -> Result<Vec<u8>> {
+    keycode::serialize(self)
+  }
+}
+
+// A SQL transaction, wrapping an MVCC transaction.
+pub struct Transaction<E: StorageEngine> {
+  txn: mvcc::Transaction<E>,
+  stats: StatsCache
+}
+
+impl<E: StorageEngine> Transaction<E> {
+  fn new(txn: mvcc::Transaction<E>, stats: StatsCache) -> Self {
+    Self { txn, stats }
+  }
+
+  // Returns the state of the transaction, which can be used to resume it.
+  pub fn state(&self) -> &mvcc::TransactionState {
+    self.txn.state( )
+  }
+
+  /*
+    Deletes the rows of the table and its index entries (along with its statistics), in a single
+    batch. Returns the number of rows deleted.
+
+    NOTE : The deletions are new versions of the keys, like any other write. So the rows are still
+    visible to the concurrent transactions, until their versions get garbage collected.
+  */
+  fn deleteTableData(&mut self, table: &Table) -> Result<u64> {
+    let mut writes: WriteBatch= self.txn.scanPrefix(&KeyPrefix::Row(table.id).encode( )?)?
+                                        .into_iter( )
+                                        .map(|(key, _)| (key, None))
+                                        .collect( );
+    let count= writes.len( ) as u64;
+
+    let indexes= table.columns.iter( )
+                              .filter(|column| column.index || column.unique)
+                              .map(|column| IndexId::Column(column.id))
+                              .chain(table.indexes.iter( ).map(|index| IndexId::Expression(index.name.clone( ))));
+
+    for index in indexes {
+      for prefix in [KeyPrefix::Index(table.id, index.clone( )), KeyPrefix::UniqueIndex(table.id, index)] {
+        writes.extend(self.txn.scanPrefix(&prefix.encode( )?)?
+                              .into_iter( )
+                              .map(|(key, _)| (key, None)));
+      }
+    }
+    writes.push((Key::TableStats(table.id).encode( )?, None));
+
+    self.txn.writeBatch(writes)?;
+    self.stats.remove(table.id)?;
+    Ok(count)
+  }
+
+  // Bumps the version of the schema, returning the new one.
+  fn bumpSchemaVersion(&mut self) -> Result<SchemaVersion> {
+    let schemaVersion= self.schemaVersion( )? + 1;
+    self.txn.set(&Key::SchemaVersion.encode( )?, bincode::serialize(&schemaVersion)?)?;
+
+    Ok(schemaVersion)
+  }
+
+  // Assigns an id to a new table.
+  fn nextTableId(&mut self) -> Result<TableId> {
+    let key= Key::LastTableId.encode( )?;
+    let tableId= match self.txn.get(&key)? {
+      Some(lastTableId) => bincode::deserialize::<TableId>(&lastTableId)? + 1,
+      None => 1
+    };
+    self.txn.set(&key, bincode::serialize(&tableId)?)?;
+
+    Ok(tableId)
+  }
+
+  // Stores the table's schema, by its name.
+  fn setTable(&mut self, table: &Table) -> Result<( )> {
+    self.txn.set(&Key::Table(table.name.clone( )).encode( )?, bincode::serialize(table)?)
+  }
+
+  fn readRow(&self, table: &Table, primaryKey: &Value) -> Result<Option<Row>> {
+    self.txn.get(&Key::Row(table.id, primaryKey.clone( )).encode( )?)?
+            .map(|row| bincode::deserialize(&row))
+            .transpose( )
+            .map_err(Error::from)
+  }
+
+  // Returns the primary keys of the rows having the given value in the index, scanning the range of
+  // their entries.
+  fn getIndexedPrimaryKeys(&self, table: &Table, index: &IndexId, value: &Value) -> Result<HashSet<Value>> {
+    if table.isUniqueIndex(index) && !value.isNull( ) {
+      return Ok(self.getUniqueIndexEntry(table, index, value)?.into_iter( ).collect( ))}
+
+    self.txn.scanPrefix(&indexValuePrefix(table, index, value)?)?
+            .into_iter( )
+            .map(|(key, _)| indexedPrimaryKey(&key))
+            .collect( )
+  }
+
+  // Returns the primary key of the row having the given (non-NULL) value in the unique index.
+  fn getUniqueIndexEntry(&self, table: &Table, index: &IndexId, value: &Value) -> Result<Option<Value>> {
+    self.txn.get(&Key::UniqueIndex(table.id, index.clone( ), value.clone( )).encode( )?)?
+            .map(|primaryKey| bincode::deserialize(&primaryKey))
+            .transpose( )
+            .map_err(Error::from)
+  }
+
+  fn setIndexEntry(&mut self, table: &Table, index: &IndexId, value: &Value, primaryKey: &Value) -> Result<( )> {
+    let (key, entry)= indexEntry(table, index, value, primaryKey)?;
+    self.txn.set(&key, entry)
+  }
+
+  // Removes the row's entry from the index. The entries of the other rows having the same value are
+  // left as they are.
+  fn deleteIndexEntry(&mut self, table: &Table, index: &IndexId, value: &Value, primaryKey: &Value) -> Result<( )> {
+    let (key, _)= indexEntry(table, index, value, primaryKey)?;
+    self.txn.delete(&key)
+  }
+
+  // Validates the row against the table's schema and constraints (except primary key uniqueness).
+  // The row being replaced (if any) is identified by its primary key. The rows written by the
+  // transaction, and the ones of the pending batch (if any) are taken into account, though the
+  // latter haven't been written yet.
+  fn validateRow(&self, table: &Table, row: &Row, replacing: Option<&Value>, batch: &PendingBatch) -> Result<( )> {
+    if row.len( ) != table.columns.len( ) {
+      return Err(Error::Value(ErrorCode::DataException, format!(
+        "Table {} has {} columns, but got a row with {} values", table.name, table.columns.len( ), row.len( )
+      )))}
+
+    let primaryKey= &row[table.primaryKey];
+
+    for (column, value) in table.columns.iter( ).zip(row) {
+      column.validateValue(value)?;
+
+      // The referenced row must exist. A row may reference itself.
+      if let Some(referencedTable)= &column.references {
+        let isSelfReference= referencedTable == &table.name && value == primaryKey;
+
+        let isInBatch= referencedTable == &table.name && batch.primaryKeys.contains(value);
+
+        if !value.isNull( ) && !isSelfReference && !isInBatch && self.read(referencedTable, value)?.is_none( ) {
+          return Err(Error::Value(ErrorCode::ForeignKeyViolation, format!(
+            "Referenced primary key {} doesn't exist in table {}", value, referencedTable
+          )))}
+      }
+
+      // NULLs don't conflict with each other.
+      if column.unique && !value.isNull( ) {
+        let conflictingPrimaryKey= self.lookupPendingUniqueIndex(batch, table, &IndexId::Column(column.id), value)?
+                                       .filter(|conflictingPrimaryKey| Some(conflictingPrimaryKey) != replacing);
+
+        if let Some(conflictingPrimaryKey)= conflictingPrimaryKey {
+          return Err(Error::Value(ErrorCode::UniqueViolation, format!(
+            "Unique value {} already exists for column {} in table {} (row {})",
+            value, column.name, table.name, conflictingPrimaryKey
+          )))}
+      }
+    }
+
+    Ok(( ))
+  }
+
+  // Returns the primary key of the row having the given (non-NULL) value in the unique index,
+  // including the rows of the batch.
+  fn lookupPendingUniqueIndex(&self, batch: &PendingBatch, table: &Table, index: &IndexId, value: &Value) -> Result<Option<Value>> {
+    match batch.uniqueEntries.get(&(index.clone( ), value.clone( ))) {
+      Some(primaryKey) => Ok(Some(primaryKey.clone( ))),
+      None => self.getUniqueIndexEntry(table, index, value)
+    }
+  }
+
+  // Returns the tables referencing the given table (including itself, if it's self-referencing),
+  // along with the indexes of their referencing columns.
+  fn getReferencingColumns(&self, table: &Table) -> Result<Vec<(Table, Vec<usize>)>> {
+    let mut referencingColumns= Vec::new( );
+
+    for (referencingTableName, columns) in self.getReferencesTo(&table.name)? {
+      let referencingTable= self.mustGetTable(&referencingTableName)?;
+      let columns= columns.iter( )
+                          .map(|column| referencingTable.getColumnIndex(column))
+                          .collect::<Result<Vec<_>>>( )?;
+      referencingColumns.push((referencingTable, columns));
+    }
+
+    let selfReferencingColumns= (0..table.columns.len( )).filter(|index| table.columns[*index].references.as_ref( ) == Some(&table.name))
+                                                         .collect::<Vec<_>>( );
+    if !selfReferencingColumns.is_empty( ) {
+      referencingColumns.push((table.clone( ), selfReferencingColumns));}
+
+    Ok(referencingColumns)
+  }
+
+  // Returns the rows of the referencing table, whose value in the given column is the given primary
+  // key.
+  fn getReferencingRows(&self, referencingTable: &Table, column: usize, primaryKey: &Value) -> Result<Vec<Row>> {
+    let mut rows= Vec::new( );
+    for row in self.scan(&referencingTable.name)? {
+      let row= row?;
+      if &row[column] == primaryKey {
+        rows.push(row);}
+    }
+
+    Ok(rows)
+  }
+
+  // Fails, if the row with the given primary key is referenced by a row of another table (or of the
+  // same table, other than itself).
+  fn ensureNotReferenced(&self, table: &Table, primaryKey: &Value) -> Result<( )> {
+    for (referencingTable, columns) in self.getReferencingColumns(table)? {
+      for column in columns {
+        for row in self.getReferencingRows(&referencingTable, column, primaryKey)? {
+          let referencingPrimaryKey= &row[referencingTable.primaryKey];
+          if referencingTable.name == table.name && referencingPrimaryKey == primaryKey {
+            continue}
+
+          return Err(referencedRowError(table, primaryKey, &referencingTable, referencingPrimaryKey))
+        }
+      }
+    }
+
+    Ok(( ))
+  }
+
+  /*
+    Applies the ON DELETE actions of the columns referencing the deleted row with the given primary
+    key : fails if a referencing row restricts the deletion, deletes the referencing rows (which may
+    cascade further), or sets their referencing column to NULL.
+
+    NOTE : The row must be deleted already. That way, a cascade reaching it again (through a cycle of
+    references) finds nothing to do, and a row referencing itself doesn't restrict its own deletion.
+  */
+  fn applyDeleteActions(&mut self, table: &Table, primaryKey: &Value) -> Result<( )> {
+    for (referencingTable, columns) in self.getReferencingColumns(table)? {
+      for column in columns {
+        for row in self.getReferencingRows(&referencingTable, column, primaryKey)? {
+          let referencingPrimaryKey= &row[referencingTable.primaryKey];
+
+          match referencingTable.columns[column].onDelete {
+            ReferentialAction::Restrict =>
+              return Err(referencedRowError(table, primaryKey, &referencingTable, referencingPrimaryKey)),
+
+            ReferentialAction::Cascade => self.delete(&referencingTable.name, referencingPrimaryKey)?,
+
+            // The row is read again, since a previous action may have changed (or deleted) it.
+            ReferentialAction::SetNull => {
+              if let Some(mut row)= self.readRow(&referencingTable, referencingPrimaryKey)? {
+                row[column]= Value::Null;
+                self.update(&referencingTable.name, referencingPrimaryKey, row)?;
+              }
+            }
+          }
+        }
+      }
+    }
+
+    Ok(( ))
+  }
+}
+
+/*
+  The rows of a batch being inserted (see createMany), along with the index entries they update.
+  They're written all at once, after every row has been validated - so they aren't visible to reads
+  until then.
+*/
+#[derive(Default)]
+struct PendingBatch {
+  rows: Vec<Row>,
+  primaryKeys: HashSet<Value>,
+
+  // The (encoded) index entries of the rows, and the primary keys of the rows by their (non-NULL)
+  // values in the unique indexes (by (index id, value)).
+  indexEntries: Vec<(Vec<u8>, Vec<u8>)>,
+  uniqueEntries: HashMap<(IndexId, Value), Value>
+}
+
+// Returns the range of the keys of the table's rows, whose primary keys are in the given range. An
+// unbounded side of the range is bounded by the rows of the table.
+fn rowRange(table: &Table, range: IndexRange) -> Result<KeyRange> {
+  let encode= |primaryKey: Value| Key::Row(table.id, primaryKey).encode( );
+
+  let (prefixStart, prefixEnd)= keycode::prefixRange(&KeyPrefix::Row(table.id).encode( )?);
+  let start= match range.0 {
+    Bound::Included(primaryKey) => Bound::Included(encode(primaryKey)?),
+    Bound::Excluded(primaryKey) => Bound::Excluded(encode(primaryKey)?),
+    Bound::Unbounded => prefixStart
+  };
+  let end= match range.1 {
+    Bound::Included(primaryKey) => Bound::Included(encode(primaryKey)?),
+    Bound::Excluded(primaryKey) => Bound::Excluded(encode(primaryKey)?),
+    Bound::Unbounded => prefixEnd
+  };
+
+  Ok((start, end))
+}
+
+// Returns the key of the row's entry in the index, along with the value stored under it.
+fn indexEntry(table: &Table, index: &IndexId, value: &Value, primaryKey: &Value) -> Result<(Vec<u8>, Vec<u8>)> {
+  match table.isUniqueIndex(index) && !value.isNull( ) {
+    true => Ok((Key::UniqueIndex(table.id, index.clone( ), value.clone( )).encode( )?, bincode::serialize(primaryKey)?)),
+    false => Ok((Key::Index(table.id, index.clone( ), value.clone( ), primaryKey.clone( )).encode( )?, vec![ ]))
+  }
+}
+
+// Returns the prefix of the keys of the index entries, having the given value. Since the fields of
+// a key are encoded one after the other, it's the encoding of the (table id, index id) prefix
+// followed by the encoding of the value.
+fn indexValuePrefix(table: &Table, index: &IndexId, value: &Value) -> Result<Vec<u8>> {
+  let mut prefix= KeyPrefix::Index(table.id, index.clone( )).encode( )?;
+  prefix.extend(keycode::serialize(value)?);
+  Ok(prefix)
+}
+
+// Returns the primary key of the row, an index entry belongs to.
+fn indexedPrimaryKey(key: &[u8]) -> Result<Value> {
+  match keycode::deserialize(key)? {
+    Key::Index(_, _, _, primaryKey) => Ok(primaryKey),
+    key => Err(Error::Internal(format!("Expected the key of an index entry, got {:?}", key)))
+  }
+}
+
+/*
+  Returns the ranges of the keys of the index entries, whose values are in the given range : of the
+  ones keyed by the (value, primary key), and of the ones of a unique index keyed by the value alone.
+  An unbounded side of a range is bounded by the entries of the index.
+*/
+fn indexRanges(table: &Table, index: &IndexId, range: IndexRange) -> Result<(KeyRange, KeyRange)> {
+  // The entries having a value are the keys prefixed by it (see indexValuePrefix( )).
+  let valueRange= |value: &Value| -> Result<KeyRange> { Ok(keycode::prefixRange(&indexValuePrefix(table, index, value)?)) };
+  let encodeUnique= |value: Value| Key::UniqueIndex(table.id, index.clone( ), value).encode( );
+
+  let (prefixStart, prefixEnd)= keycode::prefixRange(&KeyPrefix::Index(table.id, index.clone( )).encode( )?);
+  let (uniquePrefixStart, uniquePrefixEnd)= keycode::prefixRange(&KeyPrefix::UniqueIndex(table.id, index.clone( )).encode( )?);
+
+  let (start, uniqueStart)= match range.0 {
+    Bound::Included(value) => (valueRange(&value)?.0, Bound::Included(encodeUnique(value)?)),
+
+    // Starts right after the entries having the value. Their prefix starts with the tag of
+    // Key::Index, so it always has an end bound.
+    Bound::Excluded(value) => match valueRange(&value)?.1 {
+      Bound::Excluded(end) => (Bound::Included(end), Bound::Excluded(encodeUnique(value)?)),
+      bound => return Err(Error::Internal(format!("Expected the entries of index value {} to have an end bound, got {:?}", value, bound)))
+    },
+
+    Bound::Unbounded => (prefixStart, uniquePrefixStart)
+  };
+  let (end, uniqueEnd)= match range.1 {
+    Bound::Included(value) => (valueRange(&value)?.1, Bound::Included(encodeUnique(value)?)),
+    Bound::Excluded(value) => (Bound::Excluded(indexValuePrefix(table, index, &value)?), Bound::Excluded(encodeUnique(value)?)),
+    Bound::Unbounded => (prefixEnd, uniquePrefixEnd)
+  };
+
+  Ok(((start, end), (uniqueStart, uniqueEnd)))
+}
+
+// Deserializes a row, and suffixes it with the version it was written at.
+fn suffixVersion(row: &[u8], version: Version) -> Result<Row> {
+  let mut row: Row= bincode::deserialize(row)?;
+  row.push(Value::Integer(version as i64));
+  Ok(row)
+}
+
+fn referencedRowError(table: &Table, primaryKey: &Value, referencingTable: &Table, referencingPrimaryKey: &Value) -> Error {
+  Error::Value(ErrorCode::ForeignKeyViolation, format!(
+    "Primary key {} of table {} is referenced by row {} of table {}",
+    primaryKey, table.name, referencingPrimaryKey, referencingTable.name
+  ))
+}
+
+impl<E: StorageEngine> super::Transaction for Transaction<E> {
+  fn version(&self) -> Version {
+    self.txn.version( )
+  }
+
+  fn isReadOnly(&self) -> bool {
+    self.txn.isReadOnly( )
+  }
+
+  fn commit(self) -> Result<( )> {
+    self.txn.commit( )
+  }
+
+  fn rollback(self) -> Result<( )> {
+    self.txn.rollback( )
+  }
+
+  fn create(&mut self, table: &str, row: Row) -> Result<( )> {
+    let table= self.mustGetTable(table)?;
+    self.validateRow(&table, &row, None, &PendingBatch::default( ))?;
+
+    let primaryKey= &row[table.primaryKey];
+    if self.readRow(&table, primaryKey)?.is_some( ) {
+      return Err(Error::Value(ErrorCode::UniqueViolation, format!("Primary key {} already exists in table {}", primaryKey, table.name)))}
+
+    self.txn.set(&Key::Row(table.id, primaryKey.clone( )).encode( )?, bincode::serialize(&row)?)?;
+
+    for (index, value) in table.getIndexEntries(&row)? {
+      self.setIndexEntry(&table, &index, &value, primaryKey)?;}
+
+    self.stats.recordWrites(table.id, 1)
+  }
+
+  fn createMany(&mut self, table: &str, rows: Vec<Row>, firstRow: u64) -> Result<( )> {
+    let table= self.mustGetTable(table)?;
+    let mut batch= PendingBatch::default( );
+
+    for (number, row) in (firstRow..).zip(rows) {
+      self.validateRow(&table, &row, None, &batch).map_err(|error| rowError(number, error))?;
+
+      let primaryKey= row[table.primaryKey].clone( );
+      if batch.primaryKeys.contains(&primaryKey) || self.readRow(&table, &primaryKey)?.is_some( ) {
+        return Err(rowError(number, Error::Value(ErrorCode::UniqueViolation, format!("Primary key {} already exists in table {}", primaryKey, table.name))))}
+
+      for (index, value) in table.getIndexEntries(&row)? {
+        batch.indexEntries.push(indexEntry(&table, &index, &value, &primaryKey)?);
+
+        if table.isUniqueIndex(&index) && !value.isNull( ) {
+          batch.uniqueEntries.insert((index, value), primaryKey.clone( ));}
+      }
+
+      batch.primaryKeys.insert(primaryKey);
+      batch.rows.push(row);
+    }
+
+    let mut writes= WriteBatch::with_capacity(batch.rows.len( ) + batch.indexEntries.len( ));
+    for row in batch.rows {
+      writes.push((Key::Row(table.id, row[table.primaryKey].clone( )).encode( )?, Some(bincode::serialize(&row)?)));}
+    writes.extend(batch.indexEntries.into_iter( ).map(|(key, entry)| (key, Some(entry))));
+
+    self.txn.writeBatch(writes)?;
+    self.stats.recordWrites(table.id, batch.primaryKeys.len( ) as u64)
+  }
+
+  fn delete(&mut self, table: &str, primaryKey: &Value) -> Result<( )> {
+    let table= self.mustGetTable(table)?;
+
+    let row= match self.readRow(&table, primaryKey)? {
+      Some(row) => row,
+      None => return Ok(( ))
+    };
+
+    for (index, value) in table.getIndexEntries(&row)? {
+      self.deleteIndexEntry(&table, &index, &value, primaryKey)?;}
+
+    self.txn.delete(&Key::Row(table.id, primaryKey.clone( )).encode( )?)?;
+    self.stats.recordWrites(table.id, 1)?;
+
+    self.applyDeleteActions(&table, primaryKey)
+  }
+
+  fn truncate(&mut self, table: &str) -> Result<u64> {
+    let table= self.mustGetTable(table)?;
+
+    for (referencingTable, columns) in self.getReferencingColumns(&table)? {
+      if referencingTable.name == table.name {
+        continue}
+
+      for row in self.scan(&referencingTable.name)? {
+        let row= row?;
+        if let Some(column)= columns.iter( ).find(|column| row[**column] != Value::Null) {
+          return Err(referencedRowError(&table, &row[*column], &referencingTable, &row[referencingTable.primaryKey]))}
+      }
+    }
+
+    self.deleteTableData(&table)
+  }
+
+  fn read(&self, table: &str, primaryKey: &Value) -> Result<Option<Row>> {
+    self.readRow(&self.mustGetTable(table)?, primaryKey)
+  }
+
+  fn readVersioned(&self, table: &str, primaryKey: &Value) -> Result<Option<Row>> {
+    let table= self.mustGetTable(table)?;
+    self.txn.getVersioned(&Key::Row(table.id, primaryKey.clone( )).encode( )?)?
+            .map(|(version, row)| suffixVersion(&row, version))
+            .transpose( )
+  }
+
+  // The table is looked up once, for all the rows.
+  fn readManyVersioned(&self, table: &str, primaryKeys: &[Value]) -> Result<Vec<Option<Row>>> {
+    let table= self.mustGetTable(table)?;
+
+    primaryKeys.iter( )
+               .map(|primaryKey| {
+                 self.txn.getVersioned(&Key::Row(table.id, primaryKey.clone( )).encode( )?)?
+                         .map(|(version, row)| suffixVersion(&row, version))
+                         .transpose( )
+               })
+               .collect( )
+  }
+
+  fn scanDirected(&self, table: &str, range: IndexRange, direction: Direction, limit: Option<u64>) -> Result<Rows> {
+    let table= self.mustGetTable(table)?;
+
+    let limit= limit.map(|limit| usize::try_from(limit).unwrap_or(usize::MAX));
+    let rows= self.txn.scanVersioned(rowRange(&table, range)?, direction, limit)?
+                      .into_iter( )
+                      .map(|(_, _, row)| bincode::deserialize(&row).map_err(Error::from));
+
+    Ok(Box::new(rows))
+  }
+
+  fn scanVersioned(&self, table: &str, range: IndexRange, direction: Direction, limit: Option<u64>) -> Result<Rows> {
+    let table= self.mustGetTable(table)?;
+
+    let limit= limit.map(|limit| usize::try_from(limit).unwrap_or(usize::MAX));
+    let rows= self.txn.scanVersioned(rowRange(&table, range)?, direction, limit)?
+                      .into_iter( )
+                      .map(|(_, version, row)| suffixVersion(&row, version));
+
+    Ok(Box::new(rows))
+  }
+
+  fn update(&mut self, table: &str, primaryKey: &Value, row: Row) -> Result<( )> {
+    let table= self.mustGetTable(table)?;
+
+    // Changing a referenced primary key is always restricted, whatever the ON DELETE action is.
+    if &row[table.primaryKey] != primaryKey {
+      self.ensureNotReferenced(&table, primaryKey)?;
+      self.delete(&table.name, primaryKey)?;
+      return self.create(&table.name, row)
+    }
+
+    let oldRow= self.readRow(&table, primaryKey)?
+                    .ok_or_else(| | Error::Value(ErrorCode::DataException, format!("Primary key {} doesn't exist in table {}", primaryKey, table.name)))?;
+
+    self.validateRow(&table, &row, Some(primaryKey), &PendingBatch::default( ))?;
+
+    for ((index, oldValue), (_, newValue)) in table.getIndexEntries(&oldRow)?.into_iter( ).zip(table.getIndexEntries(&row)?) {
+      if oldValue == newValue {
+        continue}
+
+      self.deleteIndexEntry(&table, &index, &oldValue, primaryKey)?;
+      self.setIndexEntry(&table, &index, &newValue, primaryKey)?;
+    }
+
+    self.txn.set(&Key::Row(table.id, primaryKey.clone( )).encode( )?, bincode::serialize(&row)?)?;
+    self.stats.recordWrites(table.id, 1)
+  }
+
+  fn lookupIndex(&self, table: &str, column: &str, value: &Value) -> Result<HashSet<Value>> {
+    let table= self.mustGetTable(table)?;
+    self.getIndexedPrimaryKeys(&table, &table.getIndexId(column)?, value)
+  }
+
+  fn scanIndex(&self, table: &str, column: &str, range: IndexRange) -> Result<HashSet<Value>> {
+    let table= self.mustGetTable(table)?;
+    let index= table.getIndexId(column)?;
+    let (range, uniqueRange)= indexRanges(&table, &index, range)?;
+
+    let mut primaryKeys= self.txn.scan(range)?
+                                 .into_iter( )
+                                 .map(|(key, _)| indexedPrimaryKey(&key))
+                                 .collect::<Result<HashSet<_>>>( )?;
+
+    if table.isUniqueIndex(&index) {
+      for (_, primaryKey) in self.txn.scan(uniqueRange)? {
+        primaryKeys.insert(bincode::deserialize(&primaryKey)?);}
+    }
+
+    Ok(primaryKeys)
+  }
+}
+
+impl<E: StorageEngine> Catalog for Transaction<E> {
+  fn createTable(&mut self, mut table: Table) -> Result<( )> {
+    if self.getTable(&table.name)?.is_some( ) {
+      return Err(Error::Value(ErrorCode::DuplicateTable, format!("Table {} already exists", table.name)))}
+
+    table.validate(self)?;
+    table.id= self.nextTableId( )?;
+    table.schemaVersion= self.bumpSchemaVersion( )?;
+    self.setTable(&table)
+  }
+
+  /*
+    Drops the table, along with its rows and index entries.
+
+    NOTE : With the Raft engine, the whole drop is a single command, applied atomically (see
+    raft::StateMachine::apply( )). So a crash can't leave a replica with the schema dropped but some
+    of the data left behind : either none of it is stored, and the command gets applied again when
+    the log is replayed, or all of it is.
+  */
+  fn dropTable(&mut self, table: &str) -> Result<( )> {
+    let table= self.mustGetTable(table)?;
+
+    if let Some((referencingTable, columns))= self.getReferencesTo(&table.name)?.into_iter( ).next( ) {
+      return Err(Error::Value(ErrorCode::DependentObjectsStillExist, format!(
+        "Table {} is referenced by column {} of table {}", table.name, columns[0], referencingTable
+      )))}
+
+    self.deleteTableData(&table)?;
+
+    self.bumpSchemaVersion( )?;
+    self.txn.delete(&Key::Table(table.name).encode( )?)
+  }
+
+  fn createIndex(&mut self, table: &str, index: Index) -> Result<( )> {
+    let mut table= self.mustGetTable(table)?;
+
+    if table.hasColumnOrIndex(&index.name) {
+      return Err(Error::Value(ErrorCode::DuplicateObject, format!("Table {} already has a column or an index named {}", table.name, index.name)))}
+
+    // The existing rows are indexed in a single batch.
+    let expression= index.resolveExpression(&table)?;
+    let indexId= IndexId::Expression(index.name.clone( ));
+
+    let mut writes= WriteBatch::new( );
+    for row in self.scan(&table.name)? {
+      let row= row?;
+      let (key, entry)= indexEntry(&table, &indexId, &expression.evaluate(Some(&row))?, &row[table.primaryKey])?;
+      writes.push((key, Some(entry)));
+    }
+    self.txn.writeBatch(writes)?;
+
+    self.bumpSchemaVersion( )?;
+
+    table.indexes.push(index);
+    self.setTable(&table)
+  }
+
+  fn renameTable(&mut self, table: &str, newName: &str) -> Result<( )> {
+    let mut table= self.mustGetTable(table)?;
+
+    if self.getTable(newName)?.is_some( ) {
+      return Err(Error::Value(ErrorCode::DuplicateTable, format!("Table {} already exists", newName)))}
+    if newName.is_empty( ) {
+      return Err(Error::Value(ErrorCode::InvalidTableDefinition, "Table name can't be empty".to_string( )))}
+
+    // The rows are stored by the table's id, so only the schemas change : the table's (stored under
+    // the new name), and the ones of the tables referencing it.
+    for (referencingTableName, _) in self.getReferencesTo(&table.name)? {
+      let mut referencingTable= self.mustGetTable(&referencingTableName)?;
+      for column in &mut referencingTable.columns {
+        if column.references.as_ref( ) == Some(&table.name) {
+          column.references= Some(newName.to_string( ));}
+      }
+      self.setTable(&referencingTable)?;
+    }
+
+    for column in &mut table.columns {
+      if column.references.as_ref( ) == Some(&table.name) {
+        column.references= Some(newName.to_string( ));}
+    }
+
+    self.txn.delete(&Key::Table(table.name.clone( )).encode( )?)?;
+    table.name= newName.to_string( );
+
+    self.bumpSchemaVersion( )?;
+    self.setTable(&table)
+  }
+
+  /*
+    Renames a column. Its constraints (and its index entries) are tied to the column's id, and the
+    defaults can't reference columns. So, only the expression indexes need to be changed.
+  */
+  fn renameColumn(&mut self, table: &str, column: &str, newName: &str) -> Result<( )> {
+    let mut table= self.mustGetTable(table)?;
+    let columnIndex= table.getColumnIndex(column)?;
+
+    if newName == VERSION_COLUMN {
+      return Err(Error::Value(ErrorCode::ReservedName, format!("Column name {} is reserved for the system column", VERSION_COLUMN)))}
+    if newName.is_empty( ) {
+      return Err(Error::Value(ErrorCode::InvalidColumnDefinition, "Column name can't be empty".to_string( )))}
+
+    if newName != column && table.hasColumnOrIndex(newName) {
+      return Err(Error::Value(ErrorCode::DuplicateColumn, format!("Table {} already has a column or an index named {}", table.name, newName)))}
+
+    table.columns[columnIndex].name= newName.to_string( );
+    for index in &mut table.indexes {
+      index.renameColumn(column, newName)?;}
+
+    self.bumpSchemaVersion( )?;
+    self.setTable(&table)
+  }
+
+  fn getTable(&self, table: &str) -> Result<Option<Table>> {
+    self.txn.get(&Key::Table(table.to_string( )).encode( )?)?
+            .map(|table| bincode::deserialize(&table))
+            .transpose( )
+            .map_err(Error::from)
+  }
+
+  fn listTables(&self) -> Result<Vec<Table>> {
+    self.txn.scanPrefix(&KeyPrefix::Table.encode( )?)?
+            .into_iter( )
+            .map(|(_, table)| bincode::deserialize(&table).map_err(Error::from))
+            .collect( )
+  }
+
+  fn analyzeTable(&mut self, table: &str) -> Result<TableStats> {
+    let table= self.mustGetTable(table)?;
+
+    let mut collector= StatsCollector::new(&table)?;
+    for row in self.scan(&table.name)? {
+      collector.add(&row?)?;}
+    let stats= collector.finish( );
+
+    self.txn.set(&Key::TableStats(table.id).encode( )?, bincode::serialize(&stats)?)?;
+    self.bumpSchemaVersion( )?;
+
+    self.stats.put(table.id, stats.clone( ))?;
+    Ok(stats)
+  }
+
+  fn getTableStats(&self, table: &str) -> Result<Option<TableStats>> {
+    let table= self.mustGetTable(table)?;
+
+    self.stats.get(table.id, | | {
+      self.txn.get(&Key::TableStats(table.id).encode( )?)?
+              .map(|stats| bincode::deserialize(&stats))
+              .transpose( )
+              .map_err(Error::from)
+    })
+  }
+
+  fn schemaVersion(&self) -> Result<SchemaVersion> {
+    let key= Key::SchemaVersion.encode( )?;
+
+    if !self.txn.isReadOnly( ) {
+      self.txn.checkUnchanged(&key)?;}
+
+    match self.txn.get(&key)? {
+      Some(schemaVersion) => Ok(bincode::deserialize(&schemaVersion)?),
+      None => Ok(0)
+    }
+  }
+}
@@ -0,0 +1,266 @@
+use std::{collections::HashSet, ops::Bound};
+use serde::{Deserialize, Serialize};
+use crate::{
+  raft::node::Status as RaftStatus,
+  result::{Error, Result},
+  server::StreamStatus,
+  storage::{engine::{instrumented::StorageEngineMetrics, Direction}, mvcc::{MVCCStatus, Version}}
+};
+use super::{datetime, schema::Catalog, types::{Row, Rows, Value}};
+
+pub mod kv;
+pub mod raft;
+mod session;
+mod plan_cache;
+mod stats_cache;
+mod backup;
+mod variables;
+
+pub use session::{PreparedStatementHandle, Session};
+pub use backup::{backup, restore, BackupSummary, BACKUP_FORMAT_VERSION};
+pub use plan_cache::{PlanCache, PlanCacheStatus, PLAN_CACHE_CAPACITY};
+pub use variables::SessionVariables;
+
+// Maximum number of rows in a batch of a bulk load (see Transaction::createMany). For a replicated
+// engine, every batch is a single Raft command.
+pub const COPY_BATCH_SIZE: usize= 1000;
+
+// Maximum number of rows read in a batch (see Transaction::readManyVersioned). For a replicated
+// engine, every batch is a single Raft query.
+pub const READ_BATCH_SIZE: usize= 1000;
+
+// A SQL engine, which provides transactions over the SQL data.
+pub trait Engine: Clone {
+  // The transactions are sent along with the rows they're read by (see Session).
+  type Transaction: Transaction + Send + 'static;
+
+  // Begins a read-write transaction.
+  fn begin(&self) -> Result<Self::Transaction>;
+
+  // Begins a read-only transaction, which sees the latest committed data.
+  fn beginReadOnly(&self) -> Result<Self::Transaction>;
+
+  // Begins a read-only transaction, which sees the data as it was when the given version began.
+  fn beginAsOf(&self, version: Version) -> Result<Self::Transaction>;
+
+  // Returns the status of the engine. It's answered locally, without going through the Raft log.
+  fn status(&self) -> Result<Status>;
+
+  // Returns the current time (as the number of microseconds since the Unix epoch), which the calls
+  // to NOW( ) in a statement are bound to.
+  fn now(&self) -> Result<i64> {
+    Ok(datetime::currentTimestamp( ))
+  }
+
+  /*
+    Bulk loads the rows into the table, in batches of COPY_BATCH_SIZE rows. Returns the number of
+    rows loaded.
+
+    The rows are loaded in a transaction of their own : so either all of them are loaded, or (if a row
+    violates a constraint) none. The failing row is identified by its number (starting from 1).
+  */
+  fn copyInto(&self, table: &str, rows: impl IntoIterator<Item= Row>) -> Result<u64> {
+    let mut txn= self.begin( )?;
+    let mut count= 0;
+
+    let mut rows= rows.into_iter( ).peekable( );
+    while rows.peek( ).is_some( ) {
+      let batch= rows.by_ref( ).take(COPY_BATCH_SIZE).collect::<Vec<_>>( );
+      let size= batch.len( ) as u64;
+
+      if let Err(error)= txn.createMany(table, batch, count + 1) {
+        txn.rollback( )?;
+        return Err(error)
+      }
+      count += size;
+    }
+
+    txn.commit( )?;
+    Ok(count)
+  }
+}
+
+// Status of a SQL engine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+  // Status of the Raft node serving the engine, if the engine is replicated.
+  pub raft: Option<RaftStatus>,
+
+  // Status of the (local replica of the) SQL data.
+  pub mvcc: MVCCStatus,
+
+  // Status of the plan cache of the session (see Session::status), which the engine doesn't know of.
+  pub planCache: Option<PlanCacheStatus>,
+
+  // Status of the streaming of query results to the clients, reported by the server.
+  pub streaming: Option<StreamStatus>
+}
+
+impl Status {
+  // Returns the status as (name, value) rows, like they're shown to a user.
+  pub fn rows(&self) -> Vec<Row> {
+    let mut rows= Vec::new( );
+    let mut add= |name: &str, value: Value| rows.push(vec![Value::String(name.to_string( )), value]);
+    let integer= |value: u64| Value::Integer(value as i64);
+
+    if let Some(raft)= &self.raft {
+      add("node", integer(raft.nodeId.0));
+      add("role", Value::String(raft.role.to_string( )));
+      add("term", integer(raft.currentTerm));
+      add("leader", raft.leader.map_or(Value::Null, |leader| integer(leader.0)));
+      add("peers", Value::String(raft.peers.iter( ).map(ToString::to_string).collect::<Vec<_>>( ).join(", ")));
+      add("last_log_index", integer(raft.lastLogIndex));
+      add("last_log_term", integer(raft.lastLogTerm));
+      add("commit_index", integer(raft.commitIndex));
+      add("applied_index", integer(raft.appliedIndex));
+      add("snapshot_index", integer(raft.snapshotIndex));
+      add("log_storage", Value::String(raft.logStorage.name.clone( )));
+      add("log_keys", integer(raft.logStorage.keyCount));
+      add("log_size", integer(raft.logStorage.logicalSize));
+      add("log_entries_appended", integer(raft.logMetrics.entriesAppended));
+      add("log_conflicts", integer(raft.logMetrics.conflicts));
+      add("log_entries_removed", integer(raft.logMetrics.entriesRemoved));
+      add("log_cache_hits", integer(raft.logMetrics.cacheHits));
+      add("log_cache_misses", integer(raft.logMetrics.cacheMisses));
+      add("log_sync_policy", Value::String(raft.logSync.policy.to_string( )));
+      add("log_durability", Value::String(raft.logSync.policy.durability( )));
+      add("log_commits", integer(raft.logSync.commits));
+      add("log_fsyncs", integer(raft.logSync.fsyncs));
+      add("log_unflushed_commits", integer(raft.logSync.unflushedCommits));
+      add("rejected_proposals", integer(raft.rejectedProposals));
+      add("deferred_requests", integer(raft.deferredRequests));
+
+      for (name, value) in raft.logStorage.metrics.iter( ).flat_map(StorageEngineMetrics::counters) {
+        add(&format!("log_storage_{}", name), integer(value));}
+    }
+
+    add("versions", integer(self.mvcc.versions));
+    add("active_txns", integer(self.mvcc.activeTxns));
+    add("conflicts", integer(self.mvcc.conflicts));
+    add("gc_versions", integer(self.mvcc.gcVersions));
+    add("sync_policy", Value::String(self.mvcc.sync.policy.to_string( )));
+    add("durability", Value::String(self.mvcc.sync.policy.durability( )));
+    add("commits", integer(self.mvcc.sync.commits));
+    add("fsyncs", integer(self.mvcc.sync.fsyncs));
+    add("unflushed_commits", integer(self.mvcc.sync.unflushedCommits));
+    add("storage", Value::String(self.mvcc.storage.name.clone( )));
+    add("keys", integer(self.mvcc.storage.keyCount));
+    add("size", integer(self.mvcc.storage.logicalSize));
+    add("disk_size", integer(self.mvcc.storage.totalDiskSize));
+    add("garbage_disk_size", integer(self.mvcc.storage.garbageDiskSize));
+
+    for (name, value) in self.mvcc.storage.metrics.iter( ).flat_map(StorageEngineMetrics::counters) {
+      add(&format!("storage_{}", name), integer(value));}
+
+    if let Some(planCache)= &self.planCache {
+      add("plan_cache_hits", integer(planCache.hits));
+      add("plan_cache_misses", integer(planCache.misses));
+      add("plan_cache_entries", integer(planCache.entries));
+    }
+
+    if let Some(streaming)= &self.streaming {
+      add("streamed_queries", integer(streaming.queries));
+      add("streamed_batches", integer(streaming.batches));
+      add("streamed_rows", integer(streaming.rows));
+      add("streamed_max_batch_size", integer(streaming.maxBatchSize));
+    }
+
+    rows
+  }
+}
+
+/*
+  A SQL transaction. Along with the table schemas (through the Catalog), it provides access to the
+  rows of the tables.
+
+  Every mutation enforces the table's constraints (data types, NOT NULL, primary key and UNIQUE
+  uniqueness, and foreign keys) and keeps the secondary indexes up to date.
+*/
+pub trait Transaction: Catalog {
+  fn version(&self) -> Version;
+
+  fn isReadOnly(&self) -> bool;
+
+  // Returns whether the (read-only) transaction reads a replica's data as of whatever the replica
+  // has applied, which may be out of date.
+  fn isStale(&self) -> bool {
+    false
+  }
+
+  fn commit(self) -> Result<( )>;
+
+  fn rollback(self) -> Result<( )>;
+
+  // Inserts a new row into the table. Fails, if a row with the same primary key already exists.
+  fn create(&mut self, table: &str, row: Row) -> Result<( )>;
+
+  /*
+    Inserts the rows into the table, like create( ) does one by one, but with their writes batched.
+    It's meant for bulk loads.
+
+    Fails on the first row violating a constraint, without inserting any of the rows. The failing row
+    is identified by its number, the rows being numbered from firstRow.
+  */
+  fn createMany(&mut self, table: &str, rows: Vec<Row>, firstRow: u64) -> Result<( )>;
+
+  // Deletes the row with the given primary key. Fails, if the row is referenced by another row.
+  fn delete(&mut self, table: &str, primaryKey: &Value) -> Result<( )>;
+
+  /*
+    Deletes all the rows of the table (along with their index entries) at once, returning the number
+    of rows deleted. Fails, if any of the rows is referenced by a row of another table - whatever the
+    referential action of the referencing column is.
+
+    Unlike deleting the rows one by one, the rows aren't read and the index entries aren't updated
+    row by row : all the keys of the table are deleted in a single batch.
+  */
+  fn truncate(&mut self, table: &str) -> Result<u64>;
+
+  // Returns the row with the given primary key.
+  fn read(&self, table: &str, primaryKey: &Value) -> Result<Option<Row>>;
+
+  // Like read( ), but the row is suffixed with the version it was last written at (the value of its
+  // _version system column).
+  fn readVersioned(&self, table: &str, primaryKey: &Value) -> Result<Option<Row>>;
+
+  // Like readVersioned( ), but reads the rows with the given primary keys at once (None for the
+  // missing ones). The rows found through an index are read this way.
+  fn readManyVersioned(&self, table: &str, primaryKeys: &[Value]) -> Result<Vec<Option<Row>>> {
+    primaryKeys.iter( ).map(|primaryKey| self.readVersioned(table, primaryKey)).collect( )
+  }
+
+  // Returns all the rows of the table, ordered by their primary keys.
+  fn scan(&self, table: &str) -> Result<Rows> {
+    self.scanDirected(table, (Bound::Unbounded, Bound::Unbounded), Direction::Forward, None)
+  }
+
+  // Returns the rows of the table whose primary keys are in the given range, ordered by their primary
+  // keys in the given direction. Stops after the given number of rows.
+  fn scanDirected(&self, table: &str, range: IndexRange, direction: Direction, limit: Option<u64>) -> Result<Rows>;
+
+  // Like scanDirected( ), but every row is suffixed with the version it was last written at.
+  fn scanVersioned(&self, table: &str, range: IndexRange, direction: Direction, limit: Option<u64>) -> Result<Rows>;
+
+  // Replaces the row with the given primary key. If the primary key changes, then the row is
+  // deleted and the new row is inserted.
+  fn update(&mut self, table: &str, primaryKey: &Value, row: Row) -> Result<( )>;
+
+  // Returns the primary keys of the rows, whose value in the given indexed column is the given
+  // value.
+  fn lookupIndex(&self, table: &str, column: &str, value: &Value) -> Result<HashSet<Value>>;
+
+  // Returns the primary keys of the rows, whose value in the given indexed column is in the given
+  // range.
+  fn scanIndex(&self, table: &str, column: &str, range: IndexRange) -> Result<HashSet<Value>>;
+}
+
+// Identifies the failing row of a bulk load, in the error.
+fn rowError(number: u64, error: Error) -> Error {
+  match error {
+    Error::Value(code, message) => Error::Value(code, format!("Row {} : {}", number, message)),
+    error => error
+  }
+}
+
+// A range of values of an indexed column (or of the primary key).
+pub type IndexRange= (Bound<Value>, Bound<Value>);
@@ -0,0 +1,163 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  sync::{Arc, Mutex, MutexGuard}
+};
+use serde::{Deserialize, Serialize};
+use crate::{result::{Error, Result}, sql::{plan::StatementPlan, schema::SchemaVersion}};
+
+// Default number of plans kept by a plan cache.
+pub const PLAN_CACHE_CAPACITY: usize= 1000;
+
+/*
+  A bounded cache of statement plans, keyed by the (normalized) text of the statement and the schema
+  version the plan was built against. It lets a session skip the parsing and planning of a statement
+  it (or another session sharing the cache) has executed before.
+
+  A plan is only used, if the schema version it was built against is the one seen by the transaction
+  executing it. So, a DDL statement (which bumps the schema version) implicitly invalidates the plans
+  built before it : they miss the cache, and are replaced once they've been planned again. Only the
+  plan for the latest schema version is kept, for each statement text.
+
+  When the cache is full, the least recently used plan is evicted. The plans are immutable and shared
+  (using Arcs), so a plan can be executed by concurrent sessions.
+
+  NOTE : Statements with parameter placeholders aren't cached, since they're planned after their
+  parameters are bound (letting the planner use the values, like for key lookups). Neither are the
+  ones calling NOW( ), which is bound to the time when the statement runs.
+*/
+#[derive(Clone)]
+pub struct PlanCache {
+  inner: Arc<Mutex<Inner>>
+}
+
+struct Inner {
+  capacity: usize,
+
+  entries: HashMap<String, Entry>,
+  // The statement texts by the tick they were last used at, to find the least recently used one.
+  lastUses: BTreeMap<u64, String>,
+  ticks: u64,
+
+  hits: u64,
+  misses: u64
+}
+
+struct Entry {
+  plan: Arc<StatementPlan>,
+  // Whether the statement only reads data (see Statement::isReadOnly).
+  readOnly: bool,
+  lastUse: u64
+}
+
+// Status of a plan cache, reported along with the status of the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlanCacheStatus {
+  // Number of statements executed using a cached plan, and number of statements planned and added
+  // to the cache, since the cache was created.
+  pub hits: u64,
+  pub misses: u64,
+
+  // Number of plans in the cache.
+  pub entries: u64
+}
+
+impl PlanCache {
+  pub fn new(capacity: usize) -> Self {
+    let inner= Inner {
+      capacity,
+      entries: HashMap::new( ),
+      lastUses: BTreeMap::new( ),
+      ticks: 0,
+      hits: 0,
+      misses: 0
+    };
+    Self { inner: Arc::new(Mutex::new(inner)) }
+  }
+
+  // Returns whether the cached statement (planned against any schema version) only reads data. None
+  // if the statement isn't cached.
+  pub fn isReadOnly(&self, text: &str) -> Result<Option<bool>> {
+    Ok(self.lock( )?.entries.get(text).map(|entry| entry.readOnly))
+  }
+
+  // Returns the plan of the statement, if it's cached and was built against the given schema version.
+  pub fn get(&self, text: &str, schemaVersion: SchemaVersion) -> Result<Option<Arc<StatementPlan>>> {
+    let mut inner= self.lock( )?;
+    let Inner { entries, lastUses, ticks, hits, .. }= &mut *inner;
+
+    let Some(entry)= entries.get_mut(text).filter(|entry| entry.plan.schemaVersion == schemaVersion) else {
+      return Ok(None)
+    };
+
+    *ticks += 1;
+    lastUses.remove(&entry.lastUse);
+    lastUses.insert(*ticks, text.to_string( ));
+    entry.lastUse= *ticks;
+
+    *hits += 1;
+    Ok(Some(entry.plan.clone( )))
+  }
+
+  // Adds the plan of a statement, which has just been planned since it missed the cache. It replaces
+  // the plan for an older schema version, if any.
+  pub fn insert(&self, text: String, readOnly: bool, plan: Arc<StatementPlan>) -> Result<( )> {
+    let mut inner= self.lock( )?;
+    inner.misses += 1;
+    inner.ticks += 1;
+    let lastUse= inner.ticks;
+
+    if let Some(replaced)= inner.entries.insert(text.clone( ), Entry { plan, readOnly, lastUse }) {
+      inner.lastUses.remove(&replaced.lastUse);}
+    inner.lastUses.insert(lastUse, text);
+
+    while inner.entries.len( ) > inner.capacity {
+      let Some((_, evicted))= inner.lastUses.pop_first( ) else { break };
+      inner.entries.remove(&evicted);
+    }
+    Ok(( ))
+  }
+
+  pub fn status(&self) -> Result<PlanCacheStatus> {
+    let inner= self.lock( )?;
+    Ok(PlanCacheStatus { hits: inner.hits, misses: inner.misses, entries: inner.entries.len( ) as u64 })
+  }
+
+  fn lock(&self) -> Result<MutexGuard<'_, Inner>> {
+    self.inner.lock( )
+              .map_err(|error| Error::Internal(format!("Plan cache lock is poisoned : {}", error)))
+  }
+}
+
+impl Default for PlanCache {
+  fn default( ) -> Self {
+    Self::new(PLAN_CACHE_CAPACITY)
+  }
+}
+
+/*
+  Normalizes the text of a statement, to be used as its key in the plan cache : runs of whitespaces
+  are collapsed into a single space, and the leading / trailing whitespaces and the terminating
+  semicolon are removed. The whitespaces inside string literals and quoted identifiers are kept as
+  they are.
+*/
+pub fn normalize(text: &str) -> String {
+  let mut normalized= String::with_capacity(text.len( ));
+  let mut quote= None;
+
+  for character in text.trim( ).trim_end_matches(';').trim_end( ).chars( ) {
+    match (quote, character) {
+      (Some(opening), _) if character == opening => quote= None,
+      (Some(_), _) => { },
+
+      (None, '\'' | '"') => quote= Some(character),
+      (None, _) if character.is_whitespace( ) => {
+        if !normalized.ends_with(' ') {
+          normalized.push(' ');}
+        continue
+      },
+      (None, _) => { }
+    }
+    normalized.push(character);
+  }
+  normalized
+}
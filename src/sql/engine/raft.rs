@@ -0,0 +1,563 @@
+use std::{collections::HashSet, sync::{atomic::{AtomicU64, Ordering}, Arc}, time::Duration};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use crate::{
+  raft::{self, log::LogEntry, server::Client, types::LogEntryIndex},
+  result::{Error, ErrorCode, Result},
+  sql::{datetime, schema::{Catalog, Index, SchemaVersion, Table}, stats::TableStats, types::{Row, Rows, Value}},
+  storage::{engine::{Direction, StorageEngine}, mvcc::{TransactionState, Version}}
+};
+use super::{kv::KV, Engine, IndexRange, Status, Transaction as _};
+
+/*
+  A SQL engine, replicated using Raft. Every replica stores the SQL data in a local KV engine (the
+  Raft state machine).
+
+  Mutations (including beginning and ending transactions) are submitted to the Raft log, and get
+  applied to every replica in log order. Reads are served by the leader's replica, once a quorum
+  of the nodes confirm its leadership (so they never return stale data).
+
+  If a read-only transaction can't be begun through the log, since there's no (reachable) leader,
+  then it's begun on the local replica instead : a stale transaction, which reads the replica's data
+  as of whatever the replica has applied. Its reads are served by the local replica as well.
+
+  Still, the session reads its own writes (read-your-writes) : the engine tracks the highest index of
+  the log entries its mutations were applied at, and the local replica serves its reads only once it
+  has applied upto that index. If it doesn't catch up within the timeout, the read fails with the
+  retryable ReplicaLagging error (rather than returning data missing the session's writes).
+
+  NOTE : The clones of an engine share the tracked index. So a new engine is created for every
+  session (see Raft::new( )), and the session's transactions use its clones.
+*/
+#[derive(Clone)]
+pub struct Raft {
+  client: Client,
+
+  // Highest index of the log entries, which the mutations of the session were applied at.
+  writtenIndex: Arc<AtomicU64>,
+
+  // How long a read served by the local replica waits for it to apply the session's writes.
+  readYourWritesTimeout: Duration
+}
+
+// Default of how long a read served by the local replica waits for it to apply the session's writes.
+pub const DEFAULT_READ_YOUR_WRITES_TIMEOUT: Duration= Duration::from_secs(1);
+
+impl Raft {
+  // Creates the engine of a new session.
+  pub fn new(client: Client, readYourWritesTimeout: Duration) -> Self {
+    Self { client, writtenIndex: Arc::default( ), readYourWritesTimeout }
+  }
+
+  fn mutate<T: DeserializeOwned>(&self, mutation: Mutation) -> Result<T> {
+    let (result, index)= self.client.mutate(bincode::serialize(&mutation)?)?;
+    self.writtenIndex.fetch_max(index, Ordering::Relaxed);
+
+    Ok(bincode::deserialize(&result)?)
+  }
+
+  // Like mutate( ), but isn't shed by an overloaded leader (see Client::mutateUrgently( )).
+  fn mutateUrgently<T: DeserializeOwned>(&self, mutation: Mutation) -> Result<T> {
+    let (result, index)= self.client.mutateUrgently(bincode::serialize(&mutation)?)?;
+    self.writtenIndex.fetch_max(index, Ordering::Relaxed);
+
+    Ok(bincode::deserialize(&result)?)
+  }
+
+  fn query<T: DeserializeOwned>(&self, query: Query) -> Result<T> {
+    Ok(bincode::deserialize(&self.client.query(bincode::serialize(&query)?)?)?)
+  }
+
+  // The query observes the session's writes (see Raft).
+  fn queryLocally<T: DeserializeOwned>(&self, query: Query) -> Result<T> {
+    let index= self.writtenIndex.load(Ordering::Relaxed);
+    Ok(bincode::deserialize(&self.client.queryLocally(bincode::serialize(&query)?, index, self.readYourWritesTimeout)?)?)
+  }
+}
+
+impl Engine for Raft {
+  type Transaction= Transaction;
+
+  fn begin(&self) -> Result<Self::Transaction> {
+    Transaction::begin(self.clone( ), false, None)
+  }
+
+  fn beginReadOnly(&self) -> Result<Self::Transaction> {
+    Transaction::beginReadOnly(self.clone( ), None)
+  }
+
+  fn beginAsOf(&self, version: Version) -> Result<Self::Transaction> {
+    Transaction::beginReadOnly(self.clone( ), Some(version))
+  }
+
+  // The status of the local node (and its replica of the SQL data) is returned, even if it's a
+  // follower.
+  fn status(&self) -> Result<Status> {
+    let raft= self.client.status( )?;
+    let mvcc= bincode::deserialize(&raft.stateMachine)?;
+
+    Ok(Status { raft: Some(raft), mvcc, planCache: None, streaming: None })
+  }
+
+  /*
+    The time is read from the leader's clock, so that the statements get consistent times no matter
+    which node serves them. The rows a statement writes are replicated with the time embedded in
+    them, so every replica applies the same value.
+  */
+  fn now(&self) -> Result<i64> {
+    self.query(Query::Now)
+  }
+}
+
+// State machine commands, which are submitted through the Raft log.
+#[derive(Serialize, Deserialize)]
+enum Mutation {
+  // Read-only transactions are begun through the log as well, since they get registered in the
+  // MVCC store (to hold back garbage collection).
+  Begin { readOnly: bool, asOf: Option<Version> },
+  Commit(TransactionState),
+  Rollback(TransactionState),
+
+  Create { txn: TransactionState, table: String, row: Row },
+  // A batch of a bulk load. Its rows are numbered from firstRow.
+  CreateMany { txn: TransactionState, table: String, rows: Vec<Row>, firstRow: u64 },
+  Delete { txn: TransactionState, table: String, primaryKey: Value },
+  Truncate { txn: TransactionState, table: String },
+  Update { txn: TransactionState, table: String, primaryKey: Value, row: Row },
+
+  CreateTable { txn: TransactionState, schema: Table },
+  DropTable { txn: TransactionState, table: String },
+  CreateIndex { txn: TransactionState, table: String, index: Index },
+  RenameTable { txn: TransactionState, table: String, newName: String },
+  RenameColumn { txn: TransactionState, table: String, column: String, newName: String },
+  AnalyzeTable { txn: TransactionState, table: String }
+}
+
+// Read-only state machine commands, which don't go through the Raft log.
+#[derive(Serialize, Deserialize)]
+enum Query {
+  // Returns the state of a stale transaction, which isn't registered in the MVCC store (since the
+  // local replica can't be written outside the log).
+  BeginStale { asOf: Option<Version> },
+
+  // A versioned read / scan suffixes the rows with their versions.
+  Read { txn: TransactionState, table: String, primaryKey: Value, versioned: bool },
+  ReadMany { txn: TransactionState, table: String, primaryKeys: Vec<Value> },
+  Scan { txn: TransactionState, table: String, range: IndexRange, direction: Direction, limit: Option<u64>, versioned: bool },
+  LookupIndex { txn: TransactionState, table: String, column: String, value: Value },
+  ScanIndex { txn: TransactionState, table: String, column: String, range: IndexRange },
+
+  GetTable { txn: TransactionState, table: String },
+  ListTables { txn: TransactionState },
+  SchemaVersion { txn: TransactionState },
+  GetTableStats { txn: TransactionState, table: String },
+
+  // Returns the current time, as per the leader's clock (see Raft::now( )).
+  Now
+}
+
+// A transaction of the Raft SQL engine. Its state is sent along with each command, so that the
+// state machine can resume it.
+pub struct Transaction {
+  engine: Raft,
+  state: TransactionState,
+
+  // Whether it's a stale transaction, served by the local replica.
+  stale: bool
+}
+
+impl Transaction {
+  fn begin(engine: Raft, readOnly: bool, asOf: Option<Version>) -> Result<Self> {
+    let state= engine.mutate(Mutation::Begin { readOnly, asOf })?;
+    Ok(Self { engine, state, stale: false })
+  }
+
+  // Falls back to a stale transaction, if the transaction can't be begun through the log (the
+  // mutation is aborted even after its retries).
+  fn beginReadOnly(engine: Raft, asOf: Option<Version>) -> Result<Self> {
+    match Self::begin(engine.clone( ), true, asOf) {
+      Err(Error::Abort) => {
+        let state= engine.queryLocally(Query::BeginStale { asOf })?;
+        Ok(Self { engine, state, stale: true })
+      },
+
+      result => result
+    }
+  }
+
+  // The reads of a stale transaction are served by the local replica.
+  fn query<T: DeserializeOwned>(&self, query: Query) -> Result<T> {
+    match self.stale {
+      true => self.engine.queryLocally(query),
+      false => self.engine.query(query)
+    }
+  }
+}
+
+impl super::Transaction for Transaction {
+  fn version(&self) -> Version {
+    self.state.version
+  }
+
+  fn isReadOnly(&self) -> bool {
+    self.state.readOnly
+  }
+
+  fn isStale(&self) -> bool {
+    self.stale
+  }
+
+  // A stale transaction isn't registered anywhere, so there's nothing to end.
+  // NOTE : Ending a transaction isn't shed by an overloaded leader, since that would leave the
+  // transaction's writes (and the conflicts with them) behind.
+  fn commit(self) -> Result<( )> {
+    match self.stale {
+      true => Ok(( )),
+      false => self.engine.mutateUrgently(Mutation::Commit(self.state))
+    }
+  }
+
+  fn rollback(self) -> Result<( )> {
+    match self.stale {
+      true => Ok(( )),
+      false => self.engine.mutateUrgently(Mutation::Rollback(self.state))
+    }
+  }
+
+  fn create(&mut self, table: &str, row: Row) -> Result<( )> {
+    self.engine.mutate(Mutation::Create { txn: self.state.clone( ), table: table.to_string( ), row })
+  }
+
+  // The whole batch is submitted as a single command.
+  fn createMany(&mut self, table: &str, rows: Vec<Row>, firstRow: u64) -> Result<( )> {
+    self.engine.mutate(Mutation::CreateMany { txn: self.state.clone( ), table: table.to_string( ), rows, firstRow })
+  }
+
+  fn delete(&mut self, table: &str, primaryKey: &Value) -> Result<( )> {
+    self.engine.mutate(Mutation::Delete { txn: self.state.clone( ), table: table.to_string( ), primaryKey: primaryKey.clone( ) })
+  }
+
+  fn truncate(&mut self, table: &str) -> Result<u64> {
+    self.engine.mutate(Mutation::Truncate { txn: self.state.clone( ), table: table.to_string( ) })
+  }
+
+  fn read(&self, table: &str, primaryKey: &Value) -> Result<Option<Row>> {
+    self.query(Query::Read { txn: self.state.clone( ), table: table.to_string( ), primaryKey: primaryKey.clone( ), versioned: false })
+  }
+
+  fn readVersioned(&self, table: &str, primaryKey: &Value) -> Result<Option<Row>> {
+    self.query(Query::Read { txn: self.state.clone( ), table: table.to_string( ), primaryKey: primaryKey.clone( ), versioned: true })
+  }
+
+  // The rows are read with a single query.
+  fn readManyVersioned(&self, table: &str, primaryKeys: &[Value]) -> Result<Vec<Option<Row>>> {
+    self.query(Query::ReadMany { txn: self.state.clone( ), table: table.to_string( ), primaryKeys: primaryKeys.to_vec( ) })
+  }
+
+  fn scanDirected(&self, table: &str, range: IndexRange, direction: Direction, limit: Option<u64>) -> Result<Rows> {
+    let rows: Vec<Row>= self.query(Query::Scan { txn: self.state.clone( ), table: table.to_string( ), range, direction, limit, versioned: false })?;
+    Ok(Box::new(rows.into_iter( ).map(Ok)))
+  }
+
+  fn scanVersioned(&self, table: &str, range: IndexRange, direction: Direction, limit: Option<u64>) -> Result<Rows> {
+    let rows: Vec<Row>= self.query(Query::Scan { txn: self.state.clone( ), table: table.to_string( ), range, direction, limit, versioned: true })?;
+    Ok(Box::new(rows.into_iter( ).map(Ok)))
+  }
+
+  fn update(&mut self, table: &str, primaryKey: &Value, row: Row) -> Result<( )> {
+    self.engine.mutate(Mutation::Update {
+      txn: self.state.clone( ),
+      table: table.to_string( ),
+      primaryKey: primaryKey.clone( ),
+      row
+    })
+  }
+
+  fn lookupIndex(&self, table: &str, column: &str, value: &Value) -> Result<HashSet<Value>> {
+    self.query(Query::LookupIndex {
+      txn: self.state.clone( ),
+      table: table.to_string( ),
+      column: column.to_string( ),
+      value: value.clone( )
+    })
+  }
+
+  fn scanIndex(&self, table: &str, column: &str, range: IndexRange) -> Result<HashSet<Value>> {
+    self.query(Query::ScanIndex {
+      txn: self.state.clone( ),
+      table: table.to_string( ),
+      column: column.to_string( ),
+      range
+    })
+  }
+}
+
+impl Catalog for Transaction {
+  fn createTable(&mut self, table: Table) -> Result<( )> {
+    self.engine.mutate(Mutation::CreateTable { txn: self.state.clone( ), schema: table })
+  }
+
+  fn dropTable(&mut self, table: &str) -> Result<( )> {
+    self.engine.mutate(Mutation::DropTable { txn: self.state.clone( ), table: table.to_string( ) })
+  }
+
+  fn createIndex(&mut self, table: &str, index: Index) -> Result<( )> {
+    self.engine.mutate(Mutation::CreateIndex { txn: self.state.clone( ), table: table.to_string( ), index })
+  }
+
+  fn renameTable(&mut self, table: &str, newName: &str) -> Result<( )> {
+    self.engine.mutate(Mutation::RenameTable { txn: self.state.clone( ), table: table.to_string( ), newName: newName.to_string( ) })
+  }
+
+  fn renameColumn(&mut self, table: &str, column: &str, newName: &str) -> Result<( )> {
+    self.engine.mutate(Mutation::RenameColumn {
+      txn: self.state.clone( ), table: table.to_string( ), column: column.to_string( ), newName: newName.to_string( )
+    })
+  }
+
+  fn getTable(&self, table: &str) -> Result<Option<Table>> {
+    self.query(Query::GetTable { txn: self.state.clone( ), table: table.to_string( ) })
+  }
+
+  fn listTables(&self) -> Result<Vec<Table>> {
+    self.query(Query::ListTables { txn: self.state.clone( ) })
+  }
+
+  fn schemaVersion(&self) -> Result<SchemaVersion> {
+    self.query(Query::SchemaVersion { txn: self.state.clone( ) })
+  }
+
+  // Every replica collects the statistics from its own data, applying the command.
+  fn analyzeTable(&mut self, table: &str) -> Result<TableStats> {
+    self.engine.mutate(Mutation::AnalyzeTable { txn: self.state.clone( ), table: table.to_string( ) })
+  }
+
+  fn getTableStats(&self, table: &str) -> Result<Option<TableStats>> {
+    self.query(Query::GetTableStats { txn: self.state.clone( ), table: table.to_string( ) })
+  }
+}
+
+// Key of the applied index, stored as an unversioned key in the MVCC store.
+const APPLIED_INDEX_KEY: &[u8]= b"appliedIndex";
+
+/*
+  The Raft state machine, which applies the mutations to the local KV engine.
+
+  The applied index is stored in the same MVCC store, and is written atomically with the writes of
+  the entry (as a single write batch of the storage engine, see MVCC::atomically). So after a crash,
+  the stored applied index always matches the stored data : entries upto it are skipped when the
+  log is replayed after a restart, and the ones after it are applied exactly once.
+*/
+pub struct StateMachine<E: StorageEngine> {
+  engine: KV<E>,
+  appliedIndex: LogEntryIndex
+}
+
+impl<E: StorageEngine + 'static> StateMachine<E> {
+  pub fn new(engine: KV<E>) -> Result<Self> {
+    let appliedIndex= match engine.mvcc.getUnversioned(APPLIED_INDEX_KEY)? {
+      Some(appliedIndex) => bincode::deserialize(&appliedIndex)?,
+      None => 0
+    };
+
+    Ok(Self { engine, appliedIndex })
+  }
+
+  // Applies a mutation, returning the serialized result.
+  fn mutate(&self, mutation: Mutation) -> Result<Vec<u8>> {
+    match mutation {
+      Mutation::Begin { readOnly, asOf } => {
+        let txn= match (readOnly, asOf) {
+          (true, Some(version)) => self.engine.beginAsOf(version)?,
+          (true, None) => self.engine.beginReadOnly( )?,
+          (false, None) => self.engine.begin( )?,
+
+          (false, Some(_)) => return Err(Error::Value(ErrorCode::InvalidTransactionState, "Can't start a read-write transaction as of a version".to_string( )))
+        };
+        serialize(txn.state( ))
+      },
+
+      Mutation::Commit(txn) => serialize(&self.engine.resume(txn)?.commit( )?),
+      Mutation::Rollback(txn) => serialize(&self.engine.resume(txn)?.rollback( )?),
+
+      Mutation::Create { txn, table, row } => serialize(&self.engine.resume(txn)?.create(&table, row)?),
+      Mutation::CreateMany { txn, table, rows, firstRow } =>
+        serialize(&self.engine.resume(txn)?.createMany(&table, rows, firstRow)?),
+      Mutation::Delete { txn, table, primaryKey } => serialize(&self.engine.resume(txn)?.delete(&table, &primaryKey)?),
+      Mutation::Truncate { txn, table } => serialize(&self.engine.resume(txn)?.truncate(&table)?),
+      Mutation::Update { txn, table, primaryKey, row } =>
+        serialize(&self.engine.resume(txn)?.update(&table, &primaryKey, row)?),
+
+      Mutation::CreateTable { txn, schema } => serialize(&self.engine.resume(txn)?.createTable(schema)?),
+      Mutation::DropTable { txn, table } => serialize(&self.engine.resume(txn)?.dropTable(&table)?),
+      Mutation::CreateIndex { txn, table, index } => serialize(&self.engine.resume(txn)?.createIndex(&table, index)?),
+      Mutation::RenameTable { txn, table, newName } => serialize(&self.engine.resume(txn)?.renameTable(&table, &newName)?),
+      Mutation::RenameColumn { txn, table, column, newName } =>
+        serialize(&self.engine.resume(txn)?.renameColumn(&table, &column, &newName)?),
+      Mutation::AnalyzeTable { txn, table } => serialize(&self.engine.resume(txn)?.analyzeTable(&table)?)
+    }
+  }
+}
+
+impl<E: StorageEngine + 'static> raft::state_machine_driver::StateMachine for StateMachine<E> {
+  // Deterministic errors (like constraint violations) are returned to the client, but the entry is
+  // still considered applied.
+  // An internal error aborts the entry, so that none of its writes (or the applied index) are
+  // stored.
+  fn apply(&mut self, entry: LogEntry) -> Result<Vec<u8>> {
+    let result= self.engine.mvcc.atomically(| | {
+      let result= match entry.command {
+        Some(command) => bincode::deserialize(&command).map_err(Error::from)
+                                                       .and_then(|mutation| self.mutate(mutation)),
+        None => Ok(Vec::new( ))
+      };
+
+      if let Err(error @ Error::Internal(_))= result {
+        return Err(error)}
+
+      self.engine.mvcc.setUnversioned(APPLIED_INDEX_KEY, bincode::serialize(&entry.index)?)?;
+      Ok(result)
+    })?;
+    self.appliedIndex= entry.index;
+
+    result
+  }
+
+  fn appliedIndex(&self) -> LogEntryIndex {
+    self.appliedIndex
+  }
+
+  fn status(&self) -> Result<Vec<u8>> {
+    serialize(&self.engine.mvcc.status( )?)
+  }
+
+  fn snapshot(&self) -> Result<Vec<u8>> {
+    serialize(&self.engine.mvcc.export( )?)
+  }
+
+  // The snapshot contains the applied index as well, which is overwritten with the index the
+  // snapshot was taken at (in case the applied index wasn't flushed yet when it was taken).
+  fn restore(&mut self, index: LogEntryIndex, state: Vec<u8>) -> Result<( )> {
+    let pairs= bincode::deserialize(&state)?;
+
+    self.engine.mvcc.atomically(| | {
+      self.engine.mvcc.import(pairs)?;
+      self.engine.mvcc.setUnversioned(APPLIED_INDEX_KEY, bincode::serialize(&index)?)
+    })?;
+    self.appliedIndex= index;
+
+    Ok(( ))
+  }
+
+  fn query(&self, command: Vec<u8>) -> Result<Vec<u8>> {
+    match bincode::deserialize(&command)? {
+      Query::BeginStale { asOf } => serialize(&self.engine.mvcc.readOnlyState(asOf)?),
+      Query::Now => serialize(&datetime::currentTimestamp( )),
+
+      Query::Read { txn, table, primaryKey, versioned: false } => serialize(&self.engine.resume(txn)?.read(&table, &primaryKey)?),
+      Query::Read { txn, table, primaryKey, versioned: true } =>
+        serialize(&self.engine.resume(txn)?.readVersioned(&table, &primaryKey)?),
+      Query::ReadMany { txn, table, primaryKeys } =>
+        serialize(&self.engine.resume(txn)?.readManyVersioned(&table, &primaryKeys)?),
+
+      Query::Scan { txn, table, range, direction, limit, versioned: false } =>
+        serialize(&self.engine.resume(txn)?.scanDirected(&table, range, direction, limit)?.collect::<Result<Vec<_>>>( )?),
+      Query::Scan { txn, table, range, direction, limit, versioned: true } =>
+        serialize(&self.engine.resume(txn)?.scanVersioned(&table, range, direction, limit)?.collect::<Result<Vec<_>>>( )?),
+      Query::LookupIndex { txn, table, column, value } =>
+        serialize(&self.engine.resume(txn)?.lookupIndex(&table, &column, &value)?),
+      Query::ScanIndex { txn, table, column, range } =>
+        serialize(&self.engine.resume(txn)?.scanIndex(&table, &column, range)?),
+
+      Query::GetTable { txn, table } => serialize(&self.engine.resume(txn)?.getTable(&table)?),
+      Query::ListTables { txn } => serialize(&self.engine.resume(txn)?.listTables( )?),
+      Query::SchemaVersion { txn } => serialize(&self.engine.resume(txn)?.schemaVersion( )?),
+      Query::GetTableStats { txn, table } => serialize(&self.engine.resume(txn)?.getTableStats(&table)?)
+    }
+  }
+}
+
+fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+  Ok(bincode::serialize(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::BTreeMap;
+  use tokio::{runtime::Runtime, sync::mpsc::{channel, unbounded_channel}};
+  use crate::{
+    raft::{cluster_config::ClusterConfig, log::{Log, DEFAULT_CACHE_CAPACITY}, node::leader::ReplicationLimits, server::{Server, CLIENT_REQUEST_CHANNEL_CAPACITY}, types::NodeId},
+    sql::{engine::{PlanCache, Session}, execution::{ResultSet, WorkMemory, DEFAULT_WORK_MEMORY}},
+    storage::{engine::memory::Memory, mvcc::WriteLimits, sync::SyncPolicy}
+  };
+  use super::*;
+
+  // The node ticks often, so that the requests it queues for a leader time out quickly.
+  const TICK_INTERVAL: Duration= Duration::from_millis(1);
+
+  fn newSession<E: Engine>(engine: E) -> Result<Session<E>> {
+    let spillDirectory= std::env::temp_dir( ).join(format!("raft-engine-test-spill-{}", std::process::id( )));
+    Ok(Session::new(engine, PlanCache::default( ), WorkMemory::new(DEFAULT_WORK_MEMORY, spillDirectory)?))
+  }
+
+  /*
+    Starts a node of a 3 node cluster, whose peers never respond (the node is run without a
+    transport). So no leader gets elected, and the node's requests through the log are aborted. The
+    node's replica of the SQL data is the given engine.
+
+    The node is stopped once the runtime is dropped.
+  */
+  fn startCutOffNode(runtime: &Runtime, kv: KV<Memory>) -> Result<Raft> {
+    let nodes= (1..=3).map(|id| (NodeId(id), "127.0.0.1:0".to_string( ))).collect::<BTreeMap<_, _>>( );
+    let server= Server::new(NodeId(1),
+                            Some(ClusterConfig::new(nodes)),
+                            Log::new(Box::new(Memory::new( )), DEFAULT_CACHE_CAPACITY, SyncPolicy::default( ))?,
+                            Box::new(StateMachine::new(kv)?),
+                            ReplicationLimits::default( ),
+                            TICK_INTERVAL)?;
+
+    let (inboundMessageSender, inboundMessageReceiver)= unbounded_channel( );
+    let (outboundMessageSender, mut outboundMessageReceiver)= unbounded_channel( );
+    let (clientRequestSender, clientRequestReceiver)= channel(CLIENT_REQUEST_CHANNEL_CAPACITY);
+
+    let appliedIndex= server.watchAppliedIndex( );
+    runtime.spawn(async move {
+      let _inboundMessageSender= inboundMessageSender;
+      tokio::spawn(async move { while outboundMessageReceiver.recv( ).await.is_some( ) { } });
+
+      server.serve(inboundMessageReceiver, outboundMessageSender, clientRequestReceiver).await
+    });
+
+    Ok(Raft::new(Client::new(clientRequestSender, appliedIndex), DEFAULT_READ_YOUR_WRITES_TIMEOUT))
+  }
+
+  #[test]
+  fn readsFromLocalReplicaWhenLeaderIsUnreachable( ) -> Result<( )> {
+    // The replica has applied a table with a row, written by the transaction of the given version.
+    let kv= KV::new(Memory::new( ), WriteLimits::default( ), SyncPolicy::default( ));
+    let mut replica= newSession(kv.clone( ))?;
+
+    replica.execute("CREATE TABLE users (id INT PRIMARY KEY, name STRING)")?;
+    let ResultSet::Begin { version, .. }= replica.execute("BEGIN")? else {
+      panic!("Expected a BEGIN acknowledgement")};
+    replica.execute("INSERT INTO users VALUES (1, 'ann')")?;
+    replica.execute("COMMIT")?;
+
+    let runtime= Runtime::new( )?;
+    let mut session= newSession(startCutOffNode(&runtime, kv)?)?;
+
+    // The transaction can't be begun through the log, so it's begun on the local replica : pinned to
+    // the version after the last one the replica has applied.
+    let resultSet= session.execute("BEGIN READ ONLY")?;
+    assert!(matches!(resultSet, ResultSet::Begin { version: pinned, readonly: true, stale: true } if pinned == version + 1));
+    assert_eq!(resultSet.to_string( ), format!("Began stale read-only transaction at version {}", version + 1));
+
+    let (_, rows)= session.execute("SELECT * FROM users")?.intoQuery( )?;
+    assert_eq!(rows.collect::<Result<Vec<_>>>( )?, vec![vec![Value::Integer(1), Value::String("ann".to_string( ))]]);
+
+    assert_eq!(session.execute("INSERT INTO users VALUES (2, 'bob')").err( ), Some(Error::ReadOnly));
+    session.execute("COMMIT")?;
+
+    runtime.shutdown_background( );
+    Ok(( ))
+  }
+}
@@ -0,0 +1,731 @@
+use std::{
+  collections::HashMap,
+  fs::{self, File},
+  path::Path,
+  sync::{atomic::{AtomicBool, Ordering}, Arc}
+};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{
+    csv,
+    execution::{CancelHandle, Cancellation, ResultColumn, ResultSet, WorkMemory},
+    parser::{ast::Statement, Parser},
+    plan::{PlanOptions, StatementPlan},
+    schema::{Catalog, Table},
+    types::{DataType, Row, Rows, Value, ValueType}
+  }
+};
+use super::{backup::{self, BackupSummary}, plan_cache::{self, PlanCache}, rowError, Engine, SessionVariables, Status, Transaction};
+
+/*
+  A client session, which executes SQL statements one at a time.
+
+  BEGIN, COMMIT and ROLLBACK manage an explicit transaction, which the following statements run in.
+  Outside an explicit transaction, every statement runs in its own (auto-committed) transaction.
+
+  Statements are atomic : if a write statement fails, the explicit transaction it ran in is rolled
+  back. If the failure is retryable (see Error::isRetryable), like a write conflicting with a
+  concurrent transaction, then the transaction should be retried from its BEGIN.
+
+  BEGIN READ ONLY AS OF SYSTEM TIME <version> starts a time-travel transaction, which sees the
+  database as it was when the given version began (that's the version acknowledged by a BEGIN). Any
+  write statement in a read-only transaction fails with Error::ReadOnly.
+
+  Statements can be prepared once (parsed ahead of time), and executed many times with different
+  values bound to their parameter placeholders (? or $1, $2, ...).
+
+  The plans of the executed statements are cached (see PlanCache), so that executing the same
+  statement text again skips its parsing and planning. The cache can be shared by the sessions.
+
+  COPY <table> FROM STDIN bulk loads rows into a table : once it's executed, the rows are sent as
+  batches of CSV lines (see copyData( )) until the COPY is ended (see copyEnd( )). No other statement
+  can be executed in between. Like any other write statement, the COPY is atomic.
+
+  The running statement can be cancelled from another thread (see cancelHandle( )). It's also
+  cancelled once it runs longer than the statement timeout, set by SET statement_timeout = <ms> (0
+  disables it). A cancelled statement fails with Error::Cancelled, and is rolled back like any other
+  failed statement.
+
+  The rows buffered by the executors of a statement (to sort or group them) are spilled to disk once
+  they exceed the work memory of the session, set by SET work_memory = <bytes> (see WorkMemory).
+
+  SET lenient_aliases = TRUE lets the WHERE clause of a query reference the aliases of its
+  selections (see PlanOptions::lenientAliases).
+
+  The session variables (see SessionVariables) are read when a statement starts, so setting them
+  only affects the following statements.
+*/
+pub struct Session<E: Engine + 'static> {
+  engine: E,
+
+  // The explicit transaction, if one is open.
+  txn: Option<E::Transaction>,
+
+  planCache: PlanCache,
+
+  // The statements prepared in the session, by their handles.
+  preparedStatements: HashMap<PreparedStatementHandle, Statement>,
+  nextHandle: PreparedStatementHandle,
+
+  // The COPY in progress, if any.
+  copy: Option<CopyIn<E::Transaction>>,
+
+  // Set to cancel the running statement. It's reset when the next statement starts.
+  cancelled: Arc<AtomicBool>,
+
+  // The spill directory of the statements, whose budget is set from the variables by every
+  // statement.
+  workMemory: WorkMemory,
+
+  variables: SessionVariables
+}
+
+// A COPY, waiting for its rows.
+struct CopyIn<T: Transaction> {
+  table: Table,
+
+  // The transaction the rows are loaded in, unless they're loaded in the explicit transaction.
+  txn: Option<T>,
+
+  // Number of rows loaded so far.
+  count: u64
+}
+
+// Identifies a prepared statement, in the session it was prepared in.
+pub type PreparedStatementHandle= u64;
+
+impl<E: Engine + 'static> Session<E> {
+  pub fn new(engine: E, planCache: PlanCache, workMemory: WorkMemory) -> Self {
+    Self {
+      engine,
+      txn: None,
+      planCache,
+      preparedStatements: HashMap::new( ),
+      nextHandle: 1,
+      copy: None,
+      cancelled: Arc::new(AtomicBool::new(false)),
+      variables: SessionVariables::new(workMemory.budget),
+      workMemory
+    }
+  }
+
+  // Returns a handle, which cancels the statement running in the session (if any).
+  pub fn cancelHandle(&self) -> CancelHandle {
+    CancelHandle(self.cancelled.clone( ))
+  }
+
+  // Returns the explicit transaction, if one is open.
+  pub fn transaction(&self) -> Option<&E::Transaction> {
+    self.txn.as_ref( )
+  }
+
+  /*
+    Parses and executes a single SQL statement, which mustn't have any parameter placeholders. If
+    the statement is in the plan cache, it's executed using the cached plan instead.
+
+    NOTE : The plan cache only holds plans built with the default plan options, since it's shared
+    with sessions which may not have set the same options.
+  */
+  pub fn execute(&mut self, query: &str) -> Result<ResultSet> {
+    let text= plan_cache::normalize(query);
+    let usesPlanCache= self.variables.planOptions( ) == PlanOptions::default( );
+
+    if self.copy.is_none( ) && usesPlanCache {
+      if let Some(readOnly)= self.planCache.isReadOnly(&text)? {
+        let cancellation= self.startStatement( );
+        let planCache= self.planCache.clone( );
+
+        return self.executePlan(readOnly, &cancellation, |txn| {
+          if let Some(plan)= planCache.get(&text, txn.schemaVersion( )?)? {
+            return Ok(plan)}
+
+          // The cached plan was built against an older schema.
+          let statement= Parser::new(query).parse( )?.bindParameters(&[ ])?;
+          let plan= Arc::new(StatementPlan::build(statement, txn, PlanOptions::default( ))?);
+          planCache.insert(text, readOnly, plan.clone( ))?;
+          Ok(plan)
+        })
+      }
+    }
+
+    let statement= Parser::new(query).parse( )?.bindParameters(&[ ])?;
+    self.executeStatement(statement, usesPlanCache.then_some(text))
+  }
+
+  // Parses a SQL statement, and stores it for later executions. Returns the handle of the prepared
+  // statement, along with the number of parameters it takes.
+  pub fn prepare(&mut self, query: &str) -> Result<(PreparedStatementHandle, u32)> {
+    let statement= Parser::new(query).parse( )?;
+    let parameterCount= statement.parameterCount( );
+
+    let handle= self.nextHandle;
+    self.nextHandle += 1;
+    self.preparedStatements.insert(handle, statement);
+
+    Ok((handle, parameterCount))
+  }
+
+  // Executes a prepared statement, binding the given values to its parameters (the first value
+  // binding $1). The values are type checked when the bound statement is planned and executed, just
+  // like literals.
+  pub fn executePrepared(&mut self, handle: PreparedStatementHandle, parameters: &[Value]) -> Result<ResultSet> {
+    let statement= self.preparedStatements.get(&handle)
+                                          .ok_or_else(| | Error::Value(ErrorCode::UndefinedPreparedStatement, format!("Prepared statement {} doesn't exist", handle)))?
+                                          .clone( )
+                                          .bindParameters(parameters)?;
+    self.executeStatement(statement, None)
+  }
+
+  // Removes a prepared statement.
+  pub fn deallocate(&mut self, handle: PreparedStatementHandle) -> Result<( )> {
+    self.preparedStatements.remove(&handle)
+                           .map(|_| ( ))
+                           .ok_or_else(| | Error::Value(ErrorCode::UndefinedPreparedStatement, format!("Prepared statement {} doesn't exist", handle)))
+  }
+
+  // Resets the cancellation of the session for a new statement, since a cancellation requested
+  // before the statement started doesn't apply to it. The statement runs with the work memory and
+  // the timeout set when it starts.
+  fn startStatement(&mut self) -> Cancellation {
+    self.cancelled.store(false, Ordering::Relaxed);
+    self.workMemory.budget= self.variables.workMemory( );
+    Cancellation::new(self.cancelled.clone( ), self.variables.statementTimeout( ))
+  }
+
+  // Executes a statement. If the statement text (the key in the plan cache) is given, the plan is
+  // added to the cache.
+  fn executeStatement(&mut self, statement: Statement, text: Option<String>) -> Result<ResultSet> {
+    let cancellation= self.startStatement( );
+
+    let text= text.filter(|_| !statement.callsNow( ));
+    // NOW( ) is bound once per statement (rather than evaluated per row), so that the rows written
+    // by a replicated engine carry the time, and every replica applies the same values.
+    let statement= statement.bindNow(| | self.engine.now( ))?;
+
+    match statement {
+      _ if self.copy.is_some( ) => Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, "A COPY is in progress, it must be ended first".to_string( ))),
+
+      Statement::Begin { .. } if self.txn.is_some( ) => Err(Error::Value(ErrorCode::ActiveTransaction, "Already in a transaction".to_string( ))),
+
+      Statement::Begin { readonly: false, asOfVersion: Some(_) } =>
+        Err(Error::Value(ErrorCode::InvalidTransactionState, "Can't start a read-write transaction as of a version".to_string( ))),
+
+      Statement::Begin { readonly, asOfVersion } => {
+        let txn= match asOfVersion {
+          Some(version) => self.engine.beginAsOf(version)?,
+          None if readonly => self.engine.beginReadOnly( )?,
+          None => self.engine.begin( )?
+        };
+
+        let resultSet= ResultSet::Begin { version: txn.version( ), readonly: txn.isReadOnly( ), stale: txn.isStale( ) };
+        self.txn= Some(txn);
+        Ok(resultSet)
+      },
+
+      // The status isn't transactional.
+      Statement::Status => Ok(ResultSet::Query {
+        columns: vec![
+          ResultColumn::new(Some("name".to_string( )), ValueType::new(DataType::String, false)),
+          ResultColumn::new(Some("value".to_string( )), ValueType::unknown( ))
+        ],
+        rows: Box::new(self.status( )?.rows( ).into_iter( ).map(Ok))
+      }),
+
+      // Neither are the session variables.
+      Statement::Set { variable, value } => {
+        self.variables.set(&variable, value.evaluate(None)?)?;
+        Ok(ResultSet::Set { variable })
+      },
+
+      Statement::ShowVariable(variable) => {
+        let value= self.variables.get(&variable)?.clone( );
+        Ok(ResultSet::Query {
+          columns: vec![ResultColumn::new(Some(variable), ValueType::new(value.dataType( ).unwrap_or(DataType::String), false))],
+          rows: Box::new(std::iter::once(Ok(vec![value])))
+        })
+      },
+
+      Statement::ShowVariables => {
+        let rows= self.variables.list( )
+                                .map(|(name, value, default)| Ok(vec![Value::String(name.to_string( )), value.clone( ), default.clone( )]))
+                                .collect::<Vec<_>>( );
+        Ok(ResultSet::Query {
+          columns: vec![
+            ResultColumn::new(Some("name".to_string( )), ValueType::new(DataType::String, false)),
+            ResultColumn::new(Some("value".to_string( )), ValueType::unknown( )),
+            ResultColumn::new(Some("default".to_string( )), ValueType::unknown( ))
+          ],
+          rows: Box::new(rows.into_iter( ))
+        })
+      },
+
+      Statement::Commit | Statement::Rollback if self.txn.is_none( ) =>
+        Err(Error::Value(ErrorCode::NoActiveTransaction, "Not in a transaction".to_string( ))),
+
+      Statement::Commit => {
+        let txn= self.txn.take( ).unwrap( );
+        let version= txn.version( );
+        txn.commit( )?;
+        Ok(ResultSet::Commit { version })
+      },
+
+      Statement::Rollback => {
+        let txn= self.txn.take( ).unwrap( );
+        let version= txn.version( );
+        txn.rollback( )?;
+        Ok(ResultSet::Rollback { version })
+      },
+
+      Statement::Copy { table } => {
+        self.checkWritable(false)?;
+        let schema= self.read(|txn| txn.mustGetTable(&table))?;
+
+        // Outside an explicit transaction, the rows are loaded in a transaction of their own.
+        let txn= match &self.txn {
+          Some(_) => None,
+          None => Some(self.engine.begin( )?)
+        };
+
+        self.copy= Some(CopyIn { table: schema, txn, count: 0 });
+        Ok(ResultSet::CopyIn { table })
+      },
+
+      // The writes of an analyzed statement are rolled back, so that the analysis doesn't mutate any
+      // data. Since only the whole transaction can be rolled back, it must run in its own.
+      Statement::Explain { statement, analyze: true } if !statement.isReadOnly( ) => {
+        if self.txn.is_some( ) {
+          return Err(Error::Value(ErrorCode::ActiveTransaction, "Can't EXPLAIN ANALYZE a write statement in an explicit transaction".to_string( )))}
+
+        let mut txn= self.engine.begin( )?;
+        let resultSet= StatementPlan::build(Statement::Explain { statement, analyze: true }, &txn, self.variables.planOptions( )).and_then(|plan| plan.execute(&mut txn, &cancellation, &self.workMemory));
+        txn.rollback( )?;
+        resultSet
+      },
+
+      statement => {
+        let readOnly= statement.isReadOnly( );
+        let planCache= self.planCache.clone( );
+        let planOptions= self.variables.planOptions( );
+
+        self.executePlan(readOnly, &cancellation, |txn| {
+          let plan= Arc::new(StatementPlan::build(statement, txn, planOptions)?);
+          if let Some(text)= text {
+            planCache.insert(text, readOnly, plan.clone( ))?;}
+          Ok(plan)
+        })
+      }
+    }
+  }
+
+  // Fails a write statement in a read-only transaction up front (before it's planned), rather than
+  // on whichever check (like a constraint) it'd hit first.
+  fn checkWritable(&self, readOnly: bool) -> Result<( )> {
+    match &self.txn {
+      Some(txn) if !readOnly && txn.isReadOnly( ) => Err(Error::ReadOnly),
+      _ => Ok(( ))
+    }
+  }
+
+  // Plans (using the given function) and executes a statement, in the explicit transaction or else
+  // in an auto-committed one.
+  fn executePlan(&mut self,
+                 readOnly: bool,
+                 cancellation: &Cancellation,
+                 plan: impl FnOnce(&mut E::Transaction) -> Result<Arc<StatementPlan>>) -> Result<ResultSet>
+  {
+    self.checkWritable(readOnly)?;
+
+    match &mut self.txn {
+      Some(txn) => {
+        let plan= plan(txn)?;
+
+        match Arc::unwrap_or_clone(plan).execute(txn, cancellation, &self.workMemory) {
+          // A write statement failing midway (like on a constraint violation) may have written
+          // some of its rows. Since a statement can't be undone on its own, the whole transaction
+          // is rolled back - so that the statement doesn't partially apply.
+          Err(error) if !readOnly && !txn.isReadOnly( ) => {
+            self.txn.take( ).unwrap( ).rollback( )?;
+            Err(error)
+          },
+
+          resultSet => resultSet
+        }
+      },
+
+      None => {
+        // Queries don't need a read-write transaction.
+        let mut txn= match readOnly {
+          true => self.engine.beginReadOnly( )?,
+          false => self.engine.begin( )?
+        };
+
+        match plan(&mut txn).and_then(|plan| Arc::unwrap_or_clone(plan).execute(&mut txn, cancellation, &self.workMemory)) {
+          // The rows of a query are read lazily (so that they can be streamed to the client), so the
+          // transaction ends once they've all been read.
+          Ok(ResultSet::Query { columns, rows }) =>
+            Ok(ResultSet::Query { columns, rows: Box::new(AutoCommittedRows { rows, txn: Some(txn) }) }),
+
+          Ok(resultSet) => {
+            txn.commit( )?;
+            Ok(resultSet)
+          },
+
+          Err(error) => {
+            txn.rollback( )?;
+            Err(error)
+          }
+        }
+      }
+    }
+  }
+
+  /*
+    Loads a batch of rows (as CSV lines, see csv::parseLine) into the table of the COPY in progress.
+    The rows are numbered across the batches (starting from 1), to identify a failing row.
+
+    If a row fails to load, the COPY is aborted.
+  */
+  pub fn copyData(&mut self, lines: &[String]) -> Result<( )> {
+    let Some(copy)= &mut self.copy else {
+      return Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, "No COPY is in progress".to_string( )))
+    };
+
+    let firstRow= copy.count + 1;
+    let result= (firstRow..).zip(lines)
+                            .map(|(number, line)| csv::parseRow(line, &copy.table).map_err(|error| rowError(number, error)))
+                            .collect::<Result<Vec<_>>>( )
+                            .and_then(|rows| {
+                              let txn= copy.txn.as_mut( )
+                                               .or(self.txn.as_mut( ))
+                                               .ok_or_else(| | Error::Internal("COPY has no transaction".to_string( )))?;
+                              txn.createMany(&copy.table.name, rows, firstRow)
+                            });
+
+    match result {
+      Ok(( )) => {
+        copy.count += lines.len( ) as u64;
+        Ok(( ))
+      },
+
+      Err(error) => {
+        self.abortCopy( )?;
+        Err(error)
+      }
+    }
+  }
+
+  // Ends the COPY in progress, committing its rows (unless they're loaded in the explicit
+  // transaction).
+  pub fn copyEnd(&mut self) -> Result<ResultSet> {
+    let copy= self.copy.take( ).ok_or_else(| | Error::Value(ErrorCode::ObjectNotInPrerequisiteState, "No COPY is in progress".to_string( )))?;
+
+    if let Some(txn)= copy.txn {
+      txn.commit( )?;}
+
+    Ok(ResultSet::Copy { count: copy.count })
+  }
+
+  /*
+    Aborts the COPY in progress (if any), rolling back the rows loaded by it.
+
+    Since a statement can't be undone on its own, the explicit transaction (if the COPY runs in one)
+    is rolled back as a whole - just like when any other write statement fails.
+  */
+  pub fn abortCopy(&mut self) -> Result<( )> {
+    let Some(copy)= self.copy.take( ) else {
+      return Ok(( ))
+    };
+
+    match copy.txn.or_else(| | self.txn.take( )) {
+      Some(txn) => txn.rollback( ),
+      None => Ok(( ))
+    }
+  }
+
+  // Returns the status of the engine, along with the status of the plan cache.
+  pub fn status(&self) -> Result<Status> {
+    let status= self.engine.status( )?;
+    Ok(Status { planCache: Some(self.planCache.status( )?), ..status })
+  }
+
+  // Runs a read-only operation (like a catalog lookup) in the explicit transaction, or else in an
+  // auto-committed read-only transaction.
+  pub fn read<T>(&mut self, operation: impl FnOnce(&mut E::Transaction) -> Result<T>) -> Result<T> {
+    if let Some(txn)= &mut self.txn {
+      return operation(txn)}
+
+    let mut txn= self.engine.beginReadOnly( )?;
+    let result= operation(&mut txn);
+    txn.commit( )?;
+    result
+  }
+
+  /*
+    Backs up the database to the file at the path (see engine::backup( )), reporting the progress.
+
+    The backup is written to a .partial file next to it, which replaces the file once it's complete
+    and synced : so a failed backup doesn't leave a partial file behind.
+  */
+  pub fn backup(&mut self, path: &Path, progress: impl FnMut(&str, u64)) -> Result<BackupSummary> {
+    self.checkIdle("back up")?;
+
+    let mut partialPath= path.as_os_str( ).to_owned( );
+    partialPath.push(".partial");
+
+    let result= File::create(&partialPath).map_err(Error::from).and_then(|file| {
+      let summary= backup::backup(&self.engine, &file, progress)?;
+      file.sync_all( )?;
+      Ok(summary)
+    });
+
+    match result {
+      Ok(summary) => {
+        fs::rename(&partialPath, path)?;
+        Ok(summary)
+      },
+
+      Err(error) => {
+        let _= fs::remove_file(&partialPath);
+        Err(error)
+      }
+    }
+  }
+
+  // Restores the backup in the file at the path (see engine::restore( )), reporting the progress.
+  pub fn restore(&mut self, path: &Path, force: bool, progress: impl FnMut(&str, u64)) -> Result<BackupSummary> {
+    self.checkIdle("restore")?;
+    backup::restore(&self.engine, File::open(path)?, force, progress)
+  }
+
+  // Fails, if an explicit transaction or a COPY is in progress.
+  fn checkIdle(&self, operation: &str) -> Result<( )> {
+    if self.copy.is_some( ) {
+      return Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, "A COPY is in progress, it must be ended first".to_string( )))}
+
+    if self.txn.is_some( ) {
+      return Err(Error::Value(ErrorCode::ActiveTransaction, format!("Can't {} in a transaction", operation)))}
+
+    Ok(( ))
+  }
+}
+
+/*
+  The rows of a query, read in the auto-committed transaction they're owned along with. The
+  transaction is committed once all the rows have been read, or rolled back if reading them fails
+  (or they're dropped before that).
+*/
+struct AutoCommittedRows<T: Transaction> {
+  rows: Rows,
+
+  // The transaction, until it's ended.
+  txn: Option<T>
+}
+
+impl<T: Transaction> Iterator for AutoCommittedRows<T> {
+  type Item= Result<Row>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let txn= self.txn.take( )?;
+
+    match self.rows.next( ) {
+      Some(Ok(row)) => {
+        self.txn= Some(txn);
+        Some(Ok(row))
+      },
+
+      Some(Err(error)) => Some(txn.rollback( ).and(Err(error))),
+      None => txn.commit( ).err( ).map(Err)
+    }
+  }
+}
+
+impl<T: Transaction> Drop for AutoCommittedRows<T> {
+  fn drop(&mut self) {
+    if let Some(txn)= self.txn.take( ) {
+      let _= txn.rollback( );}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{sql::{engine::kv::KV, execution::DEFAULT_WORK_MEMORY, types::Row}, storage::{engine::memory::Memory, mvcc::WriteLimits, sync::SyncPolicy}};
+  use super::*;
+
+  fn memoryEngine( ) -> KV<Memory> {
+    KV::new(Memory::new( ), WriteLimits::default( ), SyncPolicy::default( ))
+  }
+
+  // The sessions spill rows to a directory of the process's own (though the tests don't read enough
+  // rows to spill any).
+  fn newSession<E: Engine>(engine: E) -> Result<Session<E>> {
+    let spillDirectory= std::env::temp_dir( ).join(format!("session-test-spill-{}", std::process::id( )));
+    Ok(Session::new(engine, PlanCache::default( ), WorkMemory::new(DEFAULT_WORK_MEMORY, spillDirectory)?))
+  }
+
+  // Returns an engine having a table of accounts, with balances of 100 and 50.
+  fn newEngine( ) -> Result<KV<Memory>> {
+    let engine= memoryEngine( );
+    let mut session= newSession(engine.clone( ))?;
+
+    session.execute("CREATE TABLE accounts (id INT PRIMARY KEY, balance INT)")?;
+    session.execute("INSERT INTO accounts VALUES (1, 100), (2, 50)")?;
+    Ok(engine)
+  }
+
+  fn query<E: Engine>(session: &mut Session<E>, query: &str) -> Result<Vec<Row>> {
+    let (_, rows)= session.execute(query)?.intoQuery( )?;
+    rows.collect( )
+  }
+
+  fn balances(a: i64, b: i64) -> Vec<Row> {
+    vec![vec![Value::Integer(1), Value::Integer(a)], vec![Value::Integer(2), Value::Integer(b)]]
+  }
+
+  #[test]
+  fn secondWriterRetriesAfterConflict( ) -> Result<( )> {
+    let engine= newEngine( )?;
+    let (mut a, mut b)= (newSession(engine.clone( ))?, newSession(engine)?);
+
+    a.execute("BEGIN")?;
+    b.execute("BEGIN")?;
+    a.execute("UPDATE accounts SET balance = balance + 5 WHERE id = 2")?;
+
+    // The first writer wins, even though it hasn't committed yet. The second one fails, which rolls
+    // its transaction back.
+    let error= b.execute("UPDATE accounts SET balance = balance * 2 WHERE id = 2").err( );
+    assert_eq!(error, Some(Error::Serialization));
+    assert!(error.is_some_and(|error| error.isRetryable( )));
+    assert!(b.transaction( ).is_none( ));
+
+    a.execute("COMMIT")?;
+
+    // Retried, the transaction sees the committed write.
+    b.execute("BEGIN")?;
+    b.execute("UPDATE accounts SET balance = balance * 2 WHERE id = 2")?;
+    b.execute("COMMIT")?;
+
+    assert_eq!(query(&mut a, "SELECT * FROM accounts")?, balances(100, 110));
+    Ok(( ))
+  }
+
+  #[test]
+  fn permitsWriteSkew( ) -> Result<( )> {
+    let engine= newEngine( )?;
+    let (mut a, mut b)= (newSession(engine.clone( ))?, newSession(engine)?);
+
+    // Each transaction withdraws 100 from a different account, having checked that the total
+    // balance covers it.
+    for (session, id) in [(&mut a, 1), (&mut b, 2)] {
+      session.execute("BEGIN")?;
+      assert_eq!(query(session, "SELECT SUM(balance) FROM accounts")?, vec![vec![Value::Integer(150)]]);
+      session.execute(&format!("UPDATE accounts SET balance = balance - 100 WHERE id = {}", id))?;
+    }
+
+    // Since they wrote different rows, both commit - though the total goes negative.
+    a.execute("COMMIT")?;
+    b.execute("COMMIT")?;
+
+    assert_eq!(query(&mut a, "SELECT * FROM accounts")?, balances(0, -50));
+    assert_eq!(query(&mut a, "SELECT SUM(balance) FROM accounts")?, vec![vec![Value::Integer(-50)]]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn updatesRowMovedIntoLookedUpValueOnce( ) -> Result<( )> {
+    let mut session= newSession(memoryEngine( ))?;
+    session.execute("CREATE TABLE tasks (id INT PRIMARY KEY, priority INT INDEX)")?;
+    session.execute("INSERT INTO tasks VALUES (1, 1), (2, 1), (3, 2), (4, 3)")?;
+
+    // The rows are looked up through the index, both when updated and when deleted.
+    assert_eq!(session.execute("EXPLAIN UPDATE tasks SET priority = priority + 1 WHERE priority = 1 OR priority = 2")?.to_string( ),
+               "Update: tasks (#1=#1 + 1)\n└─ IndexLookup: tasks column priority (1, 2)");
+    assert_eq!(session.execute("EXPLAIN DELETE FROM tasks WHERE priority = 3")?.to_string( ),
+               "Delete: tasks\n└─ IndexLookup: tasks column priority (3)");
+
+    // The rows of priority 1 move into priority 2, which is looked up too. But they're still only
+    // incremented once.
+    assert_eq!(session.execute("UPDATE tasks SET priority = priority + 1 WHERE priority = 1 OR priority = 2")?.to_string( ),
+               "Updated 3 rows");
+
+    let priorities= [(1, 2), (2, 2), (3, 3), (4, 3)].map(|(id, priority)| vec![Value::Integer(id), Value::Integer(priority)]);
+    assert_eq!(query(&mut session, "SELECT * FROM tasks")?, priorities);
+
+    assert_eq!(session.execute("DELETE FROM tasks WHERE priority = 3")?.to_string( ), "Deleted 2 rows");
+    assert_eq!(query(&mut session, "SELECT id FROM tasks WHERE priority = 2")?, vec![vec![Value::Integer(1)], vec![Value::Integer(2)]]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn readsAsOfEachVersion( ) -> Result<( )> {
+    let mut session= newSession(memoryEngine( ))?;
+    session.execute("CREATE TABLE versions (id INT PRIMARY KEY)")?;
+
+    // Each row is inserted in a transaction of its own.
+    let mut versions= vec![ ];
+    for id in 1..=5 {
+      let ResultSet::Begin { version, .. }= session.execute("BEGIN")? else {
+        panic!("Expected a BEGIN acknowledgement")};
+      session.execute(&format!("INSERT INTO versions VALUES ({})", id))?;
+      session.execute("COMMIT")?;
+      versions.push(version);
+    }
+
+    // A transaction as of a version sees the rows committed before that version.
+    for (inserted, version) in versions.iter( ).enumerate( ) {
+      assert_eq!(session.execute(&format!("BEGIN READ ONLY AS OF SYSTEM TIME {}", version))?.to_string( ),
+                 format!("Began read-only transaction at version {}", version));
+
+      let ids= (1..=inserted as i64).map(|id| vec![Value::Integer(id)]).collect::<Vec<_>>( );
+      assert_eq!(query(&mut session, "SELECT * FROM versions")?, ids);
+
+      assert_eq!(session.execute("INSERT INTO versions VALUES (6)").err( ), Some(Error::ReadOnly));
+      session.execute("COMMIT")?;
+    }
+
+    // The version after the last one hasn't begun yet.
+    let nextVersion= versions[4] + 1;
+    assert_eq!(session.execute(&format!("BEGIN READ ONLY AS OF SYSTEM TIME {}", nextVersion)).err( ),
+               Some(Error::Value(ErrorCode::InvalidParameterValue, format!("Version {} doesn't exist yet", nextVersion))));
+    assert_eq!(query(&mut session, "SELECT COUNT(*) FROM versions")?, vec![vec![Value::Integer(5)]]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn failsWritesUpFrontInReadOnlyTransaction( ) -> Result<( )> {
+    let mut session= newSession(newEngine( )?)?;
+
+    // The plan of the write is cached, so that it's executed without being planned again.
+    session.execute("UPDATE accounts SET balance = balance + 1 WHERE id = 1")?;
+
+    session.execute("BEGIN READ ONLY")?;
+    assert_eq!(session.execute("UPDATE accounts SET balance = balance + 1 WHERE id = 1").err( ), Some(Error::ReadOnly));
+    assert_eq!(session.execute("COPY accounts FROM STDIN").err( ), Some(Error::ReadOnly));
+
+    // The transaction is still open, and no COPY is in progress.
+    assert_eq!(query(&mut session, "SELECT * FROM accounts")?, balances(101, 50));
+    session.execute("COMMIT")?;
+    Ok(( ))
+  }
+
+  #[test]
+  fn failsEveryWriteInReadOnlyTransaction( ) -> Result<( )> {
+    let mut session= newSession(newEngine( )?)?;
+    let writes= [
+      "INSERT INTO accounts VALUES (3, 0)",
+      "UPDATE accounts SET balance = 0",
+      "DELETE FROM accounts WHERE id = 1",
+      "CREATE TABLE audits (id INT PRIMARY KEY)",
+      "DROP TABLE accounts"
+    ];
+
+    session.execute("BEGIN READ ONLY")?;
+    for write in writes {
+      assert_eq!(session.execute(write).err( ), Some(Error::ReadOnly), "{}", write);}
+    session.execute("COMMIT")?;
+
+    // None of the writes were applied.
+    assert_eq!(query(&mut session, "SELECT * FROM accounts")?, balances(100, 50));
+    assert_eq!(query(&mut session, "SHOW TABLES")?, vec![vec![Value::String("accounts".to_string( ))]]);
+    Ok(( ))
+  }
+}
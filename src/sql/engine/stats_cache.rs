@@ -0,0 +1,93 @@
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex, MutexGuard}
+};
+use crate::{result::{Error, Result}, sql::{schema::TableId, stats::TableStats}};
+
+// Fraction of a table's rows (as of when it was analyzed), which can be written since, before its
+// statistics are stale. Small tables get a minimum number of writes.
+const STALENESS_FRACTION: f64= 0.2;
+const STALENESS_MIN_ROWS: u64= 100;
+
+/*
+  Caches the statistics of the tables (see TableStats) in memory, so that planning a statement
+  doesn't read them from the store every time. Along with them, it counts the rows written to each
+  table (inserted, updated or deleted) since it was analyzed. Once they're more than a fraction of the
+  rows it was analyzed with (see STALENESS_FRACTION), its statistics are stale : they aren't returned,
+  until the table is analyzed again. So the planner falls back to its heuristics, rather than
+  costing the plans with row counts far off the actual ones.
+
+  It's shared by the transactions of the engine (through its clones).
+
+  NOTE : The cache isn't transactional. The statistics collected by an ANALYZE are cached right away
+  (even if its transaction gets rolled back), and the written rows are counted even if their
+  transactions don't commit. That's fine, since the statistics are estimates anyway. The counts
+  start from zero when the node restarts.
+
+  NOTE : The plans cached by the sessions (see PlanCache) are only replanned once the schema version
+  changes (which ANALYZE bumps). So a cached plan keeps using the statistics it was built with, even
+  once they're stale.
+*/
+#[derive(Clone, Default)]
+pub struct StatsCache {
+  inner: Arc<Mutex<HashMap<TableId, Entry>>>
+}
+
+#[derive(Default)]
+struct Entry {
+  // The statistics read from the store (None, if the table hasn't been analyzed), unless they haven't
+  // been read yet.
+  stats: Option<Option<TableStats>>,
+
+  // Number of rows written to the table, since it was analyzed.
+  writtenRows: u64
+}
+
+impl StatsCache {
+  // Returns the statistics of the table, unless they're stale. They're read (using the given
+  // function) if they aren't cached.
+  pub fn get(&self, table: TableId, read: impl FnOnce( ) -> Result<Option<TableStats>>) -> Result<Option<TableStats>> {
+    if self.lock( )?.get(&table).is_none_or(|entry| entry.stats.is_none( )) {
+      let stats= read( )?;
+
+      let mut inner= self.lock( )?;
+      let entry= inner.entry(table).or_default( );
+      if entry.stats.is_none( ) {
+        entry.stats= Some(stats);}
+    }
+
+    let inner= self.lock( )?;
+    Ok(inner.get(&table).and_then(|entry| match &entry.stats {
+      Some(Some(stats)) if !isStale(stats, entry.writtenRows) => Some(stats.clone( )),
+      _ => None
+    }))
+  }
+
+  // Caches the statistics of a table which has just been analyzed.
+  pub fn put(&self, table: TableId, stats: TableStats) -> Result<( )> {
+    self.lock( )?.insert(table, Entry { stats: Some(Some(stats)), writtenRows: 0 });
+    Ok(( ))
+  }
+
+  // Counts the rows written to the table.
+  pub fn recordWrites(&self, table: TableId, rows: u64) -> Result<( )> {
+    self.lock( )?.entry(table).or_default( ).writtenRows += rows;
+    Ok(( ))
+  }
+
+  // Forgets the statistics of the table (like once it's dropped or truncated), so that they're read
+  // again when they're needed.
+  pub fn remove(&self, table: TableId) -> Result<( )> {
+    self.lock( )?.remove(&table);
+    Ok(( ))
+  }
+
+  fn lock(&self) -> Result<MutexGuard<'_, HashMap<TableId, Entry>>> {
+    self.inner.lock( )
+              .map_err(|error| Error::Internal(format!("Stats cache lock is poisoned : {}", error)))
+  }
+}
+
+fn isStale(stats: &TableStats, writtenRows: u64) -> bool {
+  writtenRows as f64 > STALENESS_FRACTION * stats.rowCount.max(STALENESS_MIN_ROWS) as f64
+}
@@ -0,0 +1,110 @@
+use std::{collections::BTreeMap, time::Duration};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{plan::PlanOptions, types::{DataType, Value}}
+};
+
+/*
+  The variables of a session, set by SET <variable> = <value> and read by SHOW <variable> (or all of
+  them by SHOW ALL).
+
+  Every variable has a type and a default, and its values are validated when they're set. The values
+  are kept by the session, so they only apply to its connection : a new connection starts with the
+  defaults.
+*/
+pub struct SessionVariables {
+  defaults: BTreeMap<&'static str, Value>,
+  values: BTreeMap<&'static str, Value>
+}
+
+// Definition of a session variable.
+struct Variable {
+  name: &'static str,
+  dataType: DataType,
+
+  // Whether a value (of the variable's type) is valid, and the description of the valid values.
+  isValid: fn(&Value) -> bool,
+  validValues: &'static str
+}
+
+// The session variables, sorted by their names.
+const VARIABLES: &[Variable]= &[
+  // Whether the WHERE clause of a query can reference the aliases of its selections (see
+  // PlanOptions::lenientAliases).
+  Variable { name: "lenient_aliases", dataType: DataType::Boolean, isValid: |_| true, validValues: "a boolean" },
+
+  // Milliseconds a statement can run for, before it's cancelled. 0 disables the timeout.
+  Variable {
+    name: "statement_timeout",
+    dataType: DataType::Integer,
+    isValid: |value| matches!(value, Value::Integer(millis) if *millis >= 0),
+    validValues: "a non-negative integer (of milliseconds)"
+  },
+
+  // Bytes of rows the executors of a statement can buffer, before spilling them to disk (see
+  // WorkMemory).
+  Variable {
+    name: "work_memory",
+    dataType: DataType::Integer,
+    isValid: |value| matches!(value, Value::Integer(bytes) if *bytes > 0),
+    validValues: "a positive integer (of bytes)"
+  }
+];
+
+impl SessionVariables {
+  // The default of work_memory is the work memory budget the server is configured with.
+  pub fn new(workMemory: usize) -> Self {
+    let defaults= BTreeMap::from([
+      ("lenient_aliases", Value::Boolean(false)),
+      ("statement_timeout", Value::Integer(0)),
+      ("work_memory", Value::Integer(workMemory as i64))
+    ]);
+    Self { values: defaults.clone( ), defaults }
+  }
+
+  pub fn get(&self, variable: &str) -> Result<&Value> {
+    let variable= Self::mustGetVariable(variable)?;
+    Ok(&self.values[variable.name])
+  }
+
+  pub fn set(&mut self, variable: &str, value: Value) -> Result<( )> {
+    let variable= Self::mustGetVariable(variable)?;
+
+    if value.dataType( ) != Some(variable.dataType) || !(variable.isValid)(&value) {
+      return Err(Error::Value(ErrorCode::InvalidParameterValue, format!("{} must be {}, got {}", variable.name, variable.validValues, value)))}
+
+    self.values.insert(variable.name, value);
+    Ok(( ))
+  }
+
+  // Returns the name, the value and the default of every variable, sorted by their names.
+  pub fn list(&self) -> impl Iterator<Item = (&'static str, &Value, &Value)> {
+    VARIABLES.iter( ).map(|variable| (variable.name, &self.values[variable.name], &self.defaults[variable.name]))
+  }
+
+  pub fn statementTimeout(&self) -> Option<Duration> {
+    match self.values["statement_timeout"] {
+      Value::Integer(millis) if millis > 0 => Some(Duration::from_millis(millis as u64)),
+      _ => None
+    }
+  }
+
+  pub fn workMemory(&self) -> usize {
+    match self.values["work_memory"] {
+      Value::Integer(bytes) => bytes as usize,
+      // It's validated to be an integer when it's set.
+      _ => unreachable!( )
+    }
+  }
+
+  pub fn planOptions(&self) -> PlanOptions {
+    PlanOptions { lenientAliases: self.values["lenient_aliases"] == Value::Boolean(true) }
+  }
+
+  fn mustGetVariable(name: &str) -> Result<&'static Variable> {
+    VARIABLES.iter( ).find(|variable| variable.name == name).ok_or_else(| | {
+      let names= VARIABLES.iter( ).map(|variable| variable.name).collect::<Vec<_>>( ).join(", ");
+      Error::Value(ErrorCode::UndefinedObject, format!("Unknown session variable {}, expected one of : {}", name, names))
+    })
+  }
+}
@@ -0,0 +1,218 @@
+use std::{
+  collections::{hash_map::DefaultHasher, BTreeMap, HashSet},
+  hash::{Hash, Hasher},
+  mem::size_of
+};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{engine::Transaction, expression::add, plan::Aggregate, types::{Row, Rows, Value, ValueType}}
+};
+use super::{spill::{merge, rowSize, valueSize, SpillWriter, WorkMemory}, Executor, ResultColumn, ResultSet};
+
+// Number of partitions the rows are spilled to, once the groups exceed the work memory.
+const SPILL_PARTITIONS: usize= 16;
+
+// Maximum number of times the rows of a group can be spilled (each time to a partition of the
+// partition they were read from). Beyond it, the groups are aggregated in memory regardless of the
+// work memory.
+const MAX_SPILL_LEVELS: u32= 4;
+
+/*
+  Groups the source rows by their leading groupSize columns, and aggregates the remaining columns.
+  The groups are produced ordered by their keys.
+
+  Without any grouping columns, all the rows form a single group - which exists even when there are
+  no rows (so that SELECT COUNT(*) of an empty table returns 0).
+
+  A DISTINCT aggregate keeps the values it has aggregated in every group, to skip their duplicates.
+
+  Once the groups exceed the work memory, no more groups are added : the rows of the groups which
+  aren't in memory are spilled to partitions instead (by the hash of their group keys), while the
+  groups in memory keep being aggregated. Every partition is then aggregated on its own (spilling
+  further, if its groups exceed the work memory too), once the groups in memory have been spilled
+  too. The groups are spilled in order, and since a group lives in a single partition, the spilled
+  groups are merged by their keys.
+*/
+pub struct Aggregation<T: Transaction> {
+  source: Box<dyn Executor<T>>,
+  groupSize: usize,
+  aggregates: Vec<(Aggregate, bool)>,
+  workMemory: WorkMemory
+}
+
+impl<T: Transaction> Aggregation<T> {
+  pub fn new(source: Box<dyn Executor<T>>, groupSize: usize, aggregates: Vec<(Aggregate, bool)>, workMemory: WorkMemory) -> Box<Self> {
+    Box::new(Self { source, groupSize, aggregates, workMemory })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Aggregation<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (mut columns, rows)= self.source.execute(txn)?.intoQuery( )?;
+
+    // The group key columns are followed by the aggregate values, which are typed by their arguments.
+    let arguments= columns.split_off(self.groupSize);
+    columns.extend(self.aggregates.iter( ).zip(arguments).map(|((aggregate, _), argument)| {
+      ResultColumn::new(None, aggregate.resultType(argument.valueType).unwrap_or_else(ValueType::unknown))
+    }));
+
+    Ok(ResultSet::Query {
+      columns,
+      rows: aggregate(rows, self.groupSize, &self.aggregates, &self.workMemory, 0)?
+    })
+  }
+}
+
+// Aggregates the rows, returning the groups ordered by their keys. The rows have been spilled the
+// given number of times.
+fn aggregate(rows: Rows, groupSize: usize, aggregates: &[(Aggregate, bool)], workMemory: &WorkMemory, level: u32) -> Result<Rows> {
+  // Every accumulator comes with the set of the values aggregated so far, if it's DISTINCT.
+  let newAccumulators= | | aggregates.iter( )
+                                     .map(|(aggregate, distinct)| (Accumulator::new(*aggregate), distinct.then(HashSet::new)))
+                                     .collect::<Vec<_>>( );
+  let accumulatorsSize= aggregates.len( ) * size_of::<(Accumulator, Option<HashSet<Value>>)>( );
+
+  let mut groups= BTreeMap::new( );
+  let mut size= 0;
+  let mut partitions: Vec<Option<SpillWriter>>= (0..SPILL_PARTITIONS).map(|_| None).collect( );
+
+  for row in rows {
+    let mut row= row?;
+    let arguments= row.split_off(groupSize);
+
+    if !groups.contains_key(&row) {
+      if size > workMemory.budget && level < MAX_SPILL_LEVELS {
+        let partition= &mut partitions[partitionOf(&row, level)];
+        if partition.is_none( ) {
+          *partition= Some(SpillWriter::new(workMemory)?);}
+
+        row.extend(arguments);
+        partition.as_mut( ).unwrap( ).write(&row)?;
+        continue
+      }
+      size += rowSize(&row) + accumulatorsSize;
+    }
+
+    let accumulators= groups.entry(row).or_insert_with(newAccumulators);
+    for ((accumulator, seen), argument) in accumulators.iter_mut( ).zip(arguments) {
+      if let Some(seen)= seen {
+        let argumentSize= valueSize(&argument);
+        if !seen.insert(argument.clone( )) {
+          continue}
+        size += argumentSize;
+      }
+      accumulator.add(argument)?;
+    }
+  }
+
+  if groups.is_empty( ) && groupSize == 0 {
+    groups.insert(Vec::new( ), newAccumulators( ));}
+
+  let rows= groups.into_iter( )
+                  .map(|(mut key, accumulators)| {
+                    for (accumulator, _) in accumulators {
+                      key.push(accumulator.finish( ));}
+                    key
+                  })
+                  .collect::<Vec<_>>( );
+  let rows= rows.into_iter( ).map(Ok);
+
+  if partitions.iter( ).all(Option::is_none) {
+    return Ok(Box::new(rows))}
+
+  // The groups in memory are spilled as well, so that the partitions can be aggregated within the
+  // work memory.
+  let mut runs= vec![workMemory.spill(rows)?.read( )?];
+  for partition in partitions.into_iter( ).flatten( ) {
+    let groups= aggregate(partition.finish( )?.read( )?, groupSize, aggregates, workMemory, level + 1)?;
+    runs.push(workMemory.spill(groups)?.read( )?);
+  }
+
+  merge(runs, move |lhs, rhs| lhs[..groupSize].cmp(&rhs[..groupSize]))
+}
+
+// Computes an aggregate incrementally. Following SQL, NULL values are skipped.
+enum Accumulator {
+  Count(i64),
+  Sum(Value),
+  Min(Value),
+  Max(Value),
+  Average { sum: Value, count: i64 }
+}
+
+impl Accumulator {
+  fn new(aggregate: Aggregate) -> Self {
+    match aggregate {
+      Aggregate::Count => Self::Count(0),
+      Aggregate::Sum => Self::Sum(Value::Null),
+      Aggregate::Min => Self::Min(Value::Null),
+      Aggregate::Max => Self::Max(Value::Null),
+      Aggregate::Average => Self::Average { sum: Value::Null, count: 0 }
+    }
+  }
+
+  fn add(&mut self, value: Value) -> Result<( )> {
+    if value.isNull( ) {
+      return Ok(( ))}
+
+    match self {
+      Self::Count(count) => *count += 1,
+
+      Self::Sum(sum) => *sum= addToSum(sum, value)?,
+
+      Self::Average { sum, count } => {
+        *sum= addToSum(sum, value)?;
+        *count += 1;
+      },
+
+      Self::Min(min) => {
+        if min.isNull( ) || compare(min, &value)?.is_gt( ) {
+          *min= value;}
+      },
+
+      Self::Max(max) => {
+        if max.isNull( ) || compare(max, &value)?.is_lt( ) {
+          *max= value;}
+      }
+    }
+
+    Ok(( ))
+  }
+
+  fn finish(self) -> Value {
+    match self {
+      Self::Count(count) => Value::Integer(count),
+      Self::Sum(sum) | Self::Min(sum) | Self::Max(sum) => sum,
+
+      Self::Average { count: 0, .. } => Value::Null,
+      Self::Average { sum: Value::Integer(sum), count } => Value::Float(sum as f64 / count as f64),
+      Self::Average { sum: Value::Float(sum), count } => Value::Float(sum / count as f64),
+      Self::Average { .. } => Value::Null
+    }
+  }
+}
+
+// Adds a (non-NULL) value to a running sum, which is NULL until the first value.
+fn addToSum(sum: &Value, value: Value) -> Result<Value> {
+  match (sum, value) {
+    (_, value @ (Value::Boolean(_) | Value::String(_))) => Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't sum {}", value))),
+    (Value::Null, value) => Ok(value),
+    (sum, value) => add(sum.clone( ), value)
+  }
+}
+
+// Returns the partition the rows of a group are spilled to. The level salts the hash, so that the
+// rows of a partition are spread across the partitions they're spilled to in turn.
+fn partitionOf(key: &Row, level: u32) -> usize {
+  let mut hasher= DefaultHasher::new( );
+  (level, key).hash(&mut hasher);
+  hasher.finish( ) as usize % SPILL_PARTITIONS
+}
+
+// Compares two (non-NULL) values for MIN / MAX, which must be of the same data type.
+fn compare(lhs: &Value, rhs: &Value) -> Result<std::cmp::Ordering> {
+  if lhs.dataType( ) != rhs.dataType( ) {
+    return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't compare {} and {} of different data types", lhs, rhs)))}
+
+  Ok(lhs.cmp(rhs))
+}
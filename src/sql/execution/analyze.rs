@@ -0,0 +1,152 @@
+use std::{
+  sync::{atomic::{AtomicU64, Ordering}, Arc},
+  time::{Duration, Instant}
+};
+use crate::{
+  result::Result,
+  sql::{engine::Transaction, plan::{Estimates, Plan}, types::{Row, Rows}}
+};
+use super::{cancellation::Cancellable, evaluateSubqueries, Cancellation, Executor, ResultSet, WorkMemory};
+
+// Statistics measured while executing a plan node, and the statistics of its children (in the order
+// they're rendered).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Analysis {
+  // Number of rows produced by the node (or affected, for a mutation).
+  pub rows: u64,
+
+  // Wall-clock time spent in the node, including the time spent in its children.
+  pub time: Duration,
+
+  // Number of spill files written by the node, once its rows exceeded the work memory.
+  pub spills: u64,
+
+  pub children: Vec<Analysis>
+}
+
+// Statistics of a node, which are updated while its rows are being pulled.
+#[derive(Default)]
+struct Stats {
+  rows: AtomicU64,
+  nanos: AtomicU64
+}
+
+impl Stats {
+  fn addTime(&self, time: Duration) {
+    self.nanos.fetch_add(time.as_nanos( ) as u64, Ordering::Relaxed);
+  }
+}
+
+// The statistics of a plan node and its children, which mirror the plan.
+struct StatsTree {
+  stats: Arc<Stats>,
+  workMemory: WorkMemory,
+  children: Vec<StatsTree>
+}
+
+impl StatsTree {
+  fn collect(&self) -> Analysis {
+    Analysis {
+      rows: self.stats.rows.load(Ordering::Relaxed),
+      time: Duration::from_nanos(self.stats.nanos.load(Ordering::Relaxed)),
+      spills: self.workMemory.spills( ),
+      children: self.children.iter( ).map(StatsTree::collect).collect( )
+    }
+  }
+}
+
+// Builds the executor tree for the plan, with every executor instrumented (and checking the
+// cancellation).
+fn instrument<T: Transaction + 'static>(plan: Plan, cancellation: &Cancellation, workMemory: &WorkMemory) -> (Box<dyn Executor<T>>, StatsTree) {
+  let mut children= Vec::new( );
+  let nodeWorkMemory= workMemory.forExecutor( );
+  let executor= <dyn Executor<T>>::buildNode(plan, cancellation, &nodeWorkMemory, &mut |child| {
+    let (executor, tree)= instrument(child, cancellation, workMemory);
+    children.push(tree);
+    executor
+  });
+  let executor= Box::new(Cancellable { executor, cancellation: cancellation.clone( ) });
+
+  let stats= Arc::new(Stats::default( ));
+  (Box::new(Instrumented { executor, stats: stats.clone( ) }), StatsTree { stats, workMemory: nodeWorkMemory, children })
+}
+
+// Wraps an executor, counting the rows it produces and measuring the time spent in it.
+struct Instrumented<T: Transaction> {
+  executor: Box<dyn Executor<T>>,
+  stats: Arc<Stats>
+}
+
+impl<T: Transaction> Executor<T> for Instrumented<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let start= Instant::now( );
+    let resultSet= self.executor.execute(txn);
+    self.stats.addTime(start.elapsed( ));
+
+    match resultSet? {
+      ResultSet::Query { columns, rows } =>
+        Ok(ResultSet::Query { columns, rows: Box::new(InstrumentedRows { rows, stats: self.stats }) }),
+
+      resultSet @ (ResultSet::Insert { count } | ResultSet::Update { count } | ResultSet::Delete { count }) => {
+        self.stats.rows.store(count, Ordering::Relaxed);
+        Ok(resultSet)
+      },
+
+      resultSet => Ok(resultSet)
+    }
+  }
+}
+
+struct InstrumentedRows {
+  rows: Rows,
+  stats: Arc<Stats>
+}
+
+impl Iterator for InstrumentedRows {
+  type Item= Result<Row>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let start= Instant::now( );
+    let row= self.rows.next( );
+    self.stats.addTime(start.elapsed( ));
+
+    if let Some(Ok(_))= row {
+      self.stats.rows.fetch_add(1, Ordering::Relaxed);}
+
+    row
+  }
+}
+
+/*
+  Executes the plan (pulling all the rows of a query), and returns it along with the statistics
+  measured for every node.
+
+  NOTE : The writes of the plan aren't undone. The caller is responsible for rolling back the
+  transaction.
+*/
+pub struct ExplainAnalyze {
+  plan: Plan,
+  estimates: Option<Estimates>,
+  cancellation: Cancellation,
+  workMemory: WorkMemory
+}
+
+impl ExplainAnalyze {
+  pub fn new(plan: Plan, estimates: Option<Estimates>, cancellation: Cancellation, workMemory: WorkMemory) -> Box<Self> {
+    Box::new(Self { plan, estimates, cancellation, workMemory })
+  }
+}
+
+impl<T: Transaction + 'static> Executor<T> for ExplainAnalyze {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let plan= evaluateSubqueries(self.plan.clone( ), txn, &self.cancellation, &self.workMemory)?;
+    let (executor, statsTree)= instrument::<T>(plan, &self.cancellation, &self.workMemory);
+
+    if let ResultSet::Query { rows, .. }= executor.execute(txn)? {
+      for row in rows {
+        row?;}
+    }
+
+    Ok(ResultSet::ExplainAnalyze { plan: self.plan, estimates: self.estimates, analysis: statsTree.collect( ) })
+  }
+}
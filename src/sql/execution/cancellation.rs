@@ -0,0 +1,103 @@
+use std::{
+  sync::{atomic::{AtomicBool, Ordering}, Arc},
+  time::{Duration, Instant}
+};
+use crate::{
+  result::{Error, Result},
+  sql::{engine::Transaction, plan::Plan, types::{Row, Rows}}
+};
+use super::{Executor, ResultSet, WorkMemory};
+
+/*
+  Lets a running statement be cancelled : either through the flag shared with a CancelHandle, or
+  once its deadline (derived from the statement timeout) has passed.
+
+  The cancellation is cooperative - every executor checks it whenever a row is pulled from it (see
+  Cancellable), so that even a long running join stops promptly.
+*/
+#[derive(Clone, Default)]
+pub struct Cancellation {
+  cancelled: Arc<AtomicBool>,
+
+  // The deadline, along with the timeout it was derived from.
+  deadline: Option<(Instant, Duration)>
+}
+
+impl Cancellation {
+  // The timeout (if any) counts from now.
+  pub fn new(cancelled: Arc<AtomicBool>, timeout: Option<Duration>) -> Self {
+    Self { cancelled, deadline: timeout.map(|timeout| (Instant::now( ) + timeout, timeout)) }
+  }
+
+  // Fails with Error::Cancelled, if the statement has been cancelled or has timed out.
+  pub fn check(&self) -> Result<( )> {
+    if self.cancelled.load(Ordering::Relaxed) {
+      return Err(Error::Cancelled("Statement cancelled by the client".to_string( )))}
+
+    match self.deadline {
+      Some((deadline, timeout)) if Instant::now( ) >= deadline =>
+        Err(Error::Cancelled(format!("Statement exceeded the statement_timeout of {} ms", timeout.as_millis( )))),
+      _ => Ok(( ))
+    }
+  }
+}
+
+// Cancels the statement running in a session (if any), from another thread.
+#[derive(Clone)]
+pub struct CancelHandle(pub(crate) Arc<AtomicBool>);
+
+impl CancelHandle {
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::Relaxed);
+  }
+}
+
+// Builds the executor tree for the plan, with every executor checking the cancellation.
+pub(super) fn build<T: Transaction + 'static>(plan: Plan, cancellation: &Cancellation, workMemory: &WorkMemory) -> Box<dyn Executor<T>> {
+  let executor= <dyn Executor<T>>::buildNode(plan, cancellation, workMemory, &mut |child| build(child, cancellation, workMemory));
+  Box::new(Cancellable { executor, cancellation: cancellation.clone( ) })
+}
+
+// Wraps an executor, checking the cancellation before executing it and before pulling every row
+// from it.
+pub(super) struct Cancellable<T: Transaction> {
+  pub(super) executor: Box<dyn Executor<T>>,
+  pub(super) cancellation: Cancellation
+}
+
+impl<T: Transaction> Executor<T> for Cancellable<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    self.cancellation.check( )?;
+
+    match self.executor.execute(txn)? {
+      ResultSet::Query { columns, rows } =>
+        Ok(ResultSet::Query { columns, rows: Box::new(CancellableRows { rows, cancellation: self.cancellation, done: false }) }),
+
+      resultSet => Ok(resultSet)
+    }
+  }
+}
+
+struct CancellableRows {
+  rows: Rows,
+  cancellation: Cancellation,
+
+  // Whether the cancellation error has been returned, ending the rows.
+  done: bool
+}
+
+impl Iterator for CancellableRows {
+  type Item= Result<Row>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None}
+
+    if let Err(error)= self.cancellation.check( ) {
+      self.done= true;
+      return Some(Err(error))
+    }
+
+    self.rows.next( )
+  }
+}
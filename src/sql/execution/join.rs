@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use crate::{
+  result::{Error, Result},
+  sql::{engine::Transaction, numeric, parser::ast::Expression, types::{Row, Value}}
+};
+use super::{query::evaluatePredicate, Columns, Executor, ResultSet};
+
+/*
+  Joins every row of the left source with every row of the right source, satisfying the predicate.
+  The right rows are buffered, since they're iterated over for every left row.
+
+  For an outer join, a left row without any matching right row is emitted once, padded with NULLs
+  for the right columns.
+*/
+pub struct NestedLoopJoin<T: Transaction> {
+  left: Box<dyn Executor<T>>,
+  right: Box<dyn Executor<T>>,
+  predicate: Option<Expression>,
+  outer: bool
+}
+
+impl<T: Transaction> NestedLoopJoin<T> {
+  pub fn new(left: Box<dyn Executor<T>>, right: Box<dyn Executor<T>>, predicate: Option<Expression>, outer: bool) -> Box<Self> {
+    Box::new(Self { left, right, predicate, outer })
+  }
+}
+
+impl<T: Transaction> Executor<T> for NestedLoopJoin<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (leftColumns, leftRows)= self.left.execute(txn)?.intoQuery( )?;
+    let (rightColumns, rightRows)= self.right.execute(txn)?.intoQuery( )?;
+
+    let rightSize= rightColumns.len( );
+    let columns= joinColumns(leftColumns, rightColumns, self.outer);
+
+    let rightRows= rightRows.collect::<Result<Vec<_>>>( )?;
+    let (predicate, outer)= (self.predicate, self.outer);
+
+    let rows= leftRows.map(move |leftRow| {
+                        let leftRow= leftRow?;
+
+                        let mut joinedRows= Vec::new( );
+                        for rightRow in &rightRows {
+                          let mut row= leftRow.clone( );
+                          row.extend(rightRow.iter( ).cloned( ));
+
+                          let satisfied= match &predicate {
+                            Some(predicate) => evaluatePredicate(predicate, &row)?,
+                            None => true
+                          };
+                          if satisfied {
+                            joinedRows.push(row);}
+                        }
+
+                        if outer && joinedRows.is_empty( ) {
+                          let mut row= leftRow;
+                          row.extend(std::iter::repeat_n(Value::Null, rightSize));
+                          joinedRows.push(row);
+                        }
+
+                        Ok(joinedRows)
+                      })
+                      .flat_map(|joinedRows: Result<Vec<Row>>| match joinedRows {
+                        Ok(joinedRows) => joinedRows.into_iter( ).map(Ok).collect::<Vec<_>>( ),
+                        Err(error) => vec![Err(error)]
+                      });
+
+    Ok(ResultSet::Query { columns, rows: Box::new(rows) })
+  }
+}
+
+/*
+  Joins the rows of the left and right sources, whose values in the given columns are equal. A hash
+  table is built over the right rows (keyed by their join column values), and is then probed with
+  every left row.
+
+  Following SQL, NULL (and NaN) keys never match. For an outer join, a left row without any matching
+  right row is emitted once, padded with NULLs. (Right joins are planned as left joins with the
+  sides swapped.)
+*/
+pub struct HashJoin<T: Transaction> {
+  left: Box<dyn Executor<T>>,
+  leftColumn: usize,
+  right: Box<dyn Executor<T>>,
+  rightColumn: usize,
+  outer: bool,
+  buildLeft: bool
+}
+
+impl<T: Transaction> HashJoin<T> {
+  pub fn new(left: Box<dyn Executor<T>>,
+             leftColumn: usize,
+             right: Box<dyn Executor<T>>,
+             rightColumn: usize,
+             outer: bool,
+             buildLeft: bool) -> Box<Self>
+  {
+    Box::new(Self { left, leftColumn, right, rightColumn, outer, buildLeft })
+  }
+}
+
+impl<T: Transaction> Executor<T> for HashJoin<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (leftColumns, leftRows)= self.left.execute(txn)?.intoQuery( )?;
+    let (rightColumns, rightRows)= self.right.execute(txn)?.intoQuery( )?;
+
+    let rightSize= rightColumns.len( );
+    let columns= joinColumns(leftColumns, rightColumns, self.outer);
+
+    // The hash table is built over the rows of one side, and probed with the rows of the other.
+    let (buildRows, buildColumn, probeRows, probeColumn)= match self.buildLeft {
+      true => (leftRows, self.leftColumn, rightRows, self.rightColumn),
+      false => (rightRows, self.rightColumn, leftRows, self.leftColumn)
+    };
+
+    let mut table: HashMap<Value, Vec<Row>>= HashMap::new( );
+    for row in buildRows {
+      let row= row?;
+
+      let key= row.get(buildColumn)
+                  .cloned( )
+                  .ok_or_else(| | Error::Internal(format!("Join column #{} doesn't exist", buildColumn)))?;
+
+      if let Some(key)= joinKey(key) {
+        table.entry(key).or_default( ).push(row);}
+    }
+
+    let (outer, buildLeft)= (self.outer, self.buildLeft);
+
+    let rows= probeRows.map(move |probeRow| {
+                        let probeRow= probeRow?;
+
+                        let key= probeRow.get(probeColumn)
+                                         .cloned( )
+                                         .ok_or_else(| | Error::Internal(format!("Join column #{} doesn't exist", probeColumn)))?;
+
+                        // The joined rows are the left row, followed by the right row.
+                        let mut joinedRows= Vec::new( );
+                        if let Some(buildRows)= joinKey(key).and_then(|key| table.get(&key)) {
+                          for buildRow in buildRows {
+                            let (left, right)= match buildLeft {
+                              true => (buildRow, &probeRow),
+                              false => (&probeRow, buildRow)
+                            };
+
+                            let mut row= left.clone( );
+                            row.extend(right.iter( ).cloned( ));
+                            joinedRows.push(row);
+                          }
+                        }
+
+                        if outer && joinedRows.is_empty( ) {
+                          let mut row= probeRow;
+                          row.extend(std::iter::repeat_n(Value::Null, rightSize));
+                          joinedRows.push(row);
+                        }
+
+                        Ok(joinedRows)
+                      })
+                      .flat_map(|joinedRows: Result<Vec<Row>>| match joinedRows {
+                        Ok(joinedRows) => joinedRows.into_iter( ).map(Ok).collect::<Vec<_>>( ),
+                        Err(error) => vec![Err(error)]
+                      });
+
+    Ok(ResultSet::Query { columns, rows: Box::new(rows) })
+  }
+}
+
+// Returns the columns of the joined rows. For an outer join, the right columns may be NULL padded.
+fn joinColumns(mut leftColumns: Columns, rightColumns: Columns, outer: bool) -> Columns {
+  leftColumns.extend(rightColumns.into_iter( ).map(|mut column| {
+    column.valueType.nullable |= outer;
+    column
+  }));
+  leftColumns
+}
+
+// Returns the hash table key for a join column value. Since SQL compares integers and floats
+// numerically, integral floats are keyed as integers. NULL and NaN don't equal anything, and hence
+// have no key.
+fn joinKey(value: Value) -> Option<Value> {
+  match value {
+    Value::Null => None,
+    Value::Float(value) if value.is_nan( ) => None,
+    Value::Float(value) => Some(numeric::integralFloat(value).map_or(Value::Float(value), Value::Integer)),
+    value => Some(value)
+  }
+}
@@ -0,0 +1,221 @@
+use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+use crate::{result::{Error, Result}, storage::mvcc::Version};
+use super::{engine::Transaction, plan::{Estimates, Plan}, types::{Rows, ValueType}};
+
+mod source;
+mod join;
+mod aggregation;
+mod query;
+mod mutation;
+mod schema;
+mod analyze;
+mod subquery;
+mod cancellation;
+mod spill;
+
+pub use analyze::Analysis;
+pub use cancellation::{CancelHandle, Cancellation};
+pub use spill::{WorkMemory, DEFAULT_WORK_MEMORY};
+pub use subquery::evaluateSubqueries;
+
+/*
+  Executes a plan node against a transaction. Every plan node has its own executor, which executes
+  the executors of its source nodes (if any) and processes their results.
+
+  Query executors produce their rows lazily - the rows are pulled by the consumer of the result set.
+*/
+pub trait Executor<T: Transaction> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet>;
+}
+
+impl<T: Transaction + 'static> dyn Executor<T> {
+  // Builds the executor (tree) for the plan, which stops once the statement is cancelled. The
+  // executors buffering rows spill them beyond the work memory.
+  pub fn build(plan: Plan, cancellation: &Cancellation, workMemory: &WorkMemory) -> Box<dyn Executor<T>> {
+    cancellation::build(plan, cancellation, workMemory)
+  }
+
+  // Builds the executor for the plan node, building the executors of its children using the given
+  // function (in the order the children are rendered).
+  fn buildNode(plan: Plan,
+               cancellation: &Cancellation,
+               workMemory: &WorkMemory,
+               buildChild: &mut dyn FnMut(Plan) -> Box<dyn Executor<T>>) -> Box<dyn Executor<T>>
+  {
+    match plan {
+      Plan::Scan { table, alias: _, filter, range, direction, limit } => source::Scan::new(table, filter, range, direction, limit),
+      Plan::KeyLookup { table, alias: _, keys } => source::KeyLookup::new(table, keys),
+      Plan::IndexLookup { table, alias: _, column, values } => source::IndexLookup::new(table, column, values),
+      Plan::IndexRangeScan { table, alias: _, column, range } => source::IndexRangeScan::new(table, column, range),
+      Plan::Values { rows } => source::Values::new(rows),
+      Plan::Nothing => source::Nothing::new( ),
+      Plan::Empty { size } => source::Empty::new(size),
+
+      Plan::NestedLoopJoin { left, leftSize: _, right, predicate, outer, using: _ } => {
+        let left= buildChild(*left);
+        join::NestedLoopJoin::new(left, buildChild(*right), predicate, outer)
+      },
+
+      Plan::HashJoin { left, leftColumn, right, rightColumn, outer, using: _, buildLeft } => {
+        let left= buildChild(*left);
+        join::HashJoin::new(left, leftColumn, buildChild(*right), rightColumn, outer, buildLeft)
+      },
+
+      Plan::Filter { source, predicate } => query::Filter::new(buildChild(*source), predicate),
+      Plan::Projection { source, expressions } => query::Projection::new(buildChild(*source), expressions),
+      Plan::Aggregation { source, groupSize, aggregates } =>
+        aggregation::Aggregation::new(buildChild(*source), groupSize, aggregates, workMemory.clone( )),
+      Plan::Order { source, orders } => query::Order::new(buildChild(*source), orders, workMemory.clone( )),
+      Plan::Distinct { source, sorted } => query::Distinct::new(buildChild(*source), sorted),
+      Plan::Limit { source, limit } => query::Limit::new(buildChild(*source), limit),
+      Plan::Offset { source, offset } => query::Offset::new(buildChild(*source), offset),
+
+      Plan::Insert { table, columns, source, onConflict } => mutation::Insert::new(table, columns, buildChild(*source), onConflict),
+      Plan::Update { table, source, expressions } => mutation::Update::new(table, buildChild(*source), expressions),
+      Plan::Delete { table, source } => mutation::Delete::new(table, buildChild(*source)),
+      Plan::Truncate { table } => mutation::Truncate::new(table),
+
+      Plan::CreateTable { schema, ifNotExists } => schema::CreateTable::new(schema, ifNotExists),
+      Plan::CreateIndex { table, index } => schema::CreateIndex::new(table, index),
+      Plan::DropTable { table, ifExists } => schema::DropTable::new(table, ifExists),
+      Plan::AlterTable { table, action } => schema::AlterTable::new(table, action),
+      Plan::ShowTables => schema::ShowTables::new( ),
+      Plan::DescribeTable { schema } => schema::DescribeTable::new(schema),
+      Plan::Analyze { tables } => schema::Analyze::new(tables),
+
+      Plan::Explain { plan, analyze: false, estimates } => Box::new(Explain { plan: *plan, estimates }),
+      Plan::Explain { plan, analyze: true, estimates } =>
+        analyze::ExplainAnalyze::new(*plan, estimates, cancellation.clone( ), workMemory.clone( ))
+    }
+  }
+}
+
+// A column of a query result, with the type of its values. Columns computed from expressions may be
+// unlabeled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultColumn {
+  pub label: Option<String>,
+  pub valueType: ValueType,
+
+  /*
+    Whether the column holds the primary key of the table, which the rows are ordered by. The next
+    page of the rows can then be fetched by filtering on the primary key being past the key of the
+    last row (like id > 1000, with a LIMIT), instead of skipping the rows using an OFFSET. Such a
+    filter is turned into a scan of just the rows in that range (keyset pagination).
+  */
+  pub key: bool
+}
+
+impl ResultColumn {
+  pub fn new(label: Option<String>, valueType: ValueType) -> Self {
+    Self { label, valueType, key: false }
+  }
+}
+
+pub type Columns= Vec<ResultColumn>;
+
+// The result of executing a statement.
+pub enum ResultSet {
+  // Acknowledgements of transaction control statements, with the version of the transaction. A
+  // stale transaction reads a replica's data, which may be out of date (see Transaction::isStale).
+  Begin { version: Version, readonly: bool, stale: bool },
+  Commit { version: Version },
+  Rollback { version: Version },
+
+  CreateTable { name: String },
+  DropTable { name: String },
+  AlterTable { name: String },
+  CreateIndex { name: String },
+
+  // Number of tables analyzed.
+  Analyze { count: u64 },
+
+  // Number of rows affected by a DML statement.
+  Insert { count: u64 },
+  Update { count: u64 },
+  Delete { count: u64 },
+
+  // A COPY has started, and is waiting for its rows.
+  CopyIn { table: String },
+  // Number of rows loaded by a COPY.
+  Copy { count: u64 },
+
+  // A session variable has been set.
+  Set { variable: String },
+
+  Query {
+    columns: Columns,
+    rows: Rows
+  },
+
+  // The plan, along with the estimates of the planner (if any).
+  Explain {
+    plan: Plan,
+    estimates: Option<Estimates>
+  },
+
+  // The plan, along with the estimates of the planner (if any) and the statistics of its execution.
+  ExplainAnalyze {
+    plan: Plan,
+    estimates: Option<Estimates>,
+    analysis: Analysis
+  }
+}
+
+impl ResultSet {
+  // Returns the columns and rows of a query result.
+  pub fn intoQuery(self) -> Result<(Columns, Rows)> {
+    match self {
+      Self::Query { columns, rows } => Ok((columns, rows)),
+      resultSet => Err(Error::Internal(format!("Expected a query result, got {}", resultSet)))
+    }
+  }
+}
+
+impl Display for ResultSet {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Begin { version, stale: true, .. } => write!(f, "Began stale read-only transaction at version {}", version),
+      Self::Begin { version, readonly: true, .. } => write!(f, "Began read-only transaction at version {}", version),
+      Self::Begin { version, readonly: false, .. } => write!(f, "Began transaction {}", version),
+      Self::Commit { version } => write!(f, "Committed transaction {}", version),
+      Self::Rollback { version } => write!(f, "Rolled back transaction {}", version),
+
+      Self::CreateTable { name } => write!(f, "Created table {}", name),
+      Self::DropTable { name } => write!(f, "Dropped table {}", name),
+      Self::AlterTable { name } => write!(f, "Altered table {}", name),
+      Self::CreateIndex { name } => write!(f, "Created index {}", name),
+      Self::Analyze { count } => write!(f, "Analyzed {} tables", count),
+
+      Self::Insert { count } => write!(f, "Inserted {} rows", count),
+      Self::Update { count } => write!(f, "Updated {} rows", count),
+      Self::Delete { count } => write!(f, "Deleted {} rows", count),
+
+      Self::CopyIn { table } => write!(f, "Copying rows into table {}", table),
+      Self::Copy { count } => write!(f, "Copied {} rows", count),
+
+      Self::Set { variable } => write!(f, "Set {}", variable),
+
+      Self::Query { columns, .. } => write!(f, "Query with columns {}", columns.iter( )
+                                                                           .map(|column| column.label.as_deref( ).unwrap_or("?"))
+                                                                           .collect::<Vec<_>>( )
+                                                                           .join(", ")),
+
+      Self::Explain { plan, estimates } => write!(f, "{}", plan.annotated(estimates.as_ref( ), None)),
+      Self::ExplainAnalyze { plan, estimates, analysis } => write!(f, "{}", plan.annotated(estimates.as_ref( ), Some(analysis)))
+    }
+  }
+}
+
+// Returns the plan, instead of executing it.
+struct Explain {
+  plan: Plan,
+  estimates: Option<Estimates>
+}
+
+impl<T: Transaction> Executor<T> for Explain {
+  fn execute(self: Box<Self>, _: &mut T) -> Result<ResultSet> {
+    Ok(ResultSet::Explain { plan: self.plan, estimates: self.estimates })
+  }
+}
@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{engine::Transaction, parser::ast::Expression, plan::OnConflict, schema::Table, types::{Row, Value}}
+};
+use super::{Executor, ResultSet};
+
+// Inserts the rows produced by the source into a table. The rows conflicting with existing rows are
+// skipped or update the existing rows, if there's an ON CONFLICT clause.
+pub struct Insert<T: Transaction> {
+  table: String,
+  columns: Vec<String>,
+  source: Box<dyn Executor<T>>,
+  onConflict: Option<OnConflict>
+}
+
+impl<T: Transaction> Insert<T> {
+  pub fn new(table: String, columns: Vec<String>, source: Box<dyn Executor<T>>, onConflict: Option<OnConflict>) -> Box<Self> {
+    Box::new(Self { table, columns, source, onConflict })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Insert<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let table= txn.mustGetTable(&self.table)?;
+
+    // The rows are collected before being inserted, since the source may read from the same table.
+    let (sourceColumns, rows)= self.source.execute(txn)?.intoQuery( )?;
+    checkValueCount(&table, &self.columns, sourceColumns.len( ))?;
+    let rows= rows.collect::<Result<Vec<_>>>( )?;
+
+    // The defaults are parsed once, and evaluated for every row.
+    let defaults= table.columns.iter( )
+                               .map(|column| column.defaultExpression( ))
+                               .collect::<Result<Vec<_>>>( )?;
+
+    let Some(onConflict)= &self.onConflict else {
+      let mut count= 0;
+      for row in rows {
+        txn.create(&table.name, buildRow(&table, &self.columns, &defaults, row)?)?;
+        count += 1;
+      }
+      return Ok(ResultSet::Insert { count })
+    };
+
+    /*
+      The count includes the updated rows, but not the skipped ones.
+
+      Like in PostgreSQL, a DO UPDATE can't affect a row which the statement has already inserted or
+      updated : the result would depend on the order of the rows. So the primary keys of those rows
+      are tracked.
+    */
+    let mut count= 0;
+    let mut affected= HashSet::new( );
+    for row in rows {
+      let row= buildRow(&table, &self.columns, &defaults, row)?;
+
+      let Some(existing)= findConflict(txn, &table, onConflict.column, &row[onConflict.column])? else {
+        affected.insert(row[table.primaryKey].clone( ));
+        txn.create(&table.name, row)?;
+        count += 1;
+        continue
+      };
+
+      let Some(updates)= &onConflict.updates else {
+        continue
+      };
+
+      let primaryKey= existing[table.primaryKey].clone( );
+      if affected.contains(&primaryKey) {
+        return Err(Error::Value(ErrorCode::CardinalityViolation, format!(
+          "ON CONFLICT DO UPDATE can't affect the row with primary key {} a second time : the inserted rows have duplicate values in column {}",
+          primaryKey, table.columns[onConflict.column].name
+        )))}
+
+      // The expressions are evaluated against the existing row followed by the excluded one, like
+      // the planner resolved them.
+      let mut newRow= existing[..table.columns.len( )].to_vec( );
+      let context= existing.into_iter( ).chain(row).collect::<Row>( );
+      for (index, expression) in updates {
+        newRow[*index]= expression.evaluate(Some(&context))?;}
+
+      affected.insert(newRow[table.primaryKey].clone( ));
+      txn.update(&table.name, &primaryKey, newRow)?;
+      count += 1;
+    }
+
+    Ok(ResultSet::Insert { count })
+  }
+}
+
+// Returns the existing row (suffixed with its version), whose value in the given column (the primary
+// key or a unique column) is the given value. NULLs don't conflict.
+fn findConflict<T: Transaction>(txn: &T, table: &Table, column: usize, value: &Value) -> Result<Option<Row>> {
+  if column == table.primaryKey {
+    return txn.readVersioned(&table.name, value)}
+
+  if value.isNull( ) {
+    return Ok(None)}
+
+  match txn.lookupIndex(&table.name, &table.columns[column].name, value)?.into_iter( ).next( ) {
+    Some(primaryKey) => txn.readVersioned(&table.name, &primaryKey),
+    None => Ok(None)
+  }
+}
+
+// Checks the number of values in each row to be inserted, against the number of target columns.
+// Without explicit columns, the values are assigned to the leading columns of the table.
+fn checkValueCount(table: &Table, columns: &[String], count: usize) -> Result<( )> {
+  let columnCount= match columns.is_empty( ) {
+    true => table.columns.len( ),
+    false => columns.len( )
+  };
+  if count > columnCount {
+    return Err(Error::Value(ErrorCode::SyntaxError, format!("Expected at most {} values, got {}", columnCount, count)))}
+  if !columns.is_empty( ) && count < columnCount {
+    return Err(Error::Value(ErrorCode::SyntaxError, format!("Expected {} values, got {}", columnCount, count)))}
+
+  Ok(( ))
+}
+
+// Builds a full table row from the values of the target columns. Columns without a value take their
+// default values (evaluating the given default expressions of the columns).
+fn buildRow(table: &Table, columns: &[String], defaults: &[Option<Expression>], values: Vec<Value>) -> Result<Row> {
+  let mut givenValues= match columns.is_empty( ) {
+    true => values.into_iter( ).enumerate( ).collect::<HashMap<_, _>>( ),
+    false => columns.iter( )
+                    .map(|column| table.getColumnIndex(column))
+                    .zip(values)
+                    .map(|(index, value)| Ok((index?, value)))
+                    .collect::<Result<HashMap<_, _>>>( )?
+  };
+
+  table.columns.iter( )
+               .enumerate( )
+               .map(|(index, column)| match (givenValues.remove(&index), &defaults[index]) {
+                 (Some(value), _) => Ok(value),
+                 (None, Some(default)) => default.evaluate(None),
+                 (None, None) => Err(Error::Value(ErrorCode::NotNullViolation, format!("No value given for column {}, which has no default", column.name)))
+               })
+               .collect( )
+}
+
+// Updates the rows produced by the source.
+pub struct Update<T: Transaction> {
+  table: String,
+  source: Box<dyn Executor<T>>,
+  expressions: Vec<(usize, Expression)>
+}
+
+impl<T: Transaction> Update<T> {
+  pub fn new(table: String, source: Box<dyn Executor<T>>, expressions: Vec<(usize, Expression)>) -> Box<Self> {
+    Box::new(Self { table, source, expressions })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Update<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let table= txn.mustGetTable(&self.table)?;
+
+    /*
+      The rows are collected before being updated, since the source reads from the transaction. This
+      also avoids the Halloween problem : when the source is an index lookup (or range scan) on a
+      column being updated, an updated row may match the source again, but it's still updated only
+      once.
+    */
+    let (_, rows)= self.source.execute(txn)?.intoQuery( )?;
+    let rows= rows.collect::<Result<Vec<_>>>( )?;
+
+    let mut count= 0;
+    for row in rows {
+      let primaryKey= row[table.primaryKey].clone( );
+
+      // The expressions are evaluated against the old row. The new row leaves out the _version
+      // system column.
+      let mut newRow= row[..table.columns.len( )].to_vec( );
+      for (index, expression) in &self.expressions {
+        newRow[*index]= expression.evaluate(Some(&row))?;}
+
+      txn.update(&table.name, &primaryKey, newRow)?;
+      count += 1;
+    }
+
+    Ok(ResultSet::Update { count })
+  }
+}
+
+// Deletes the rows produced by the source.
+pub struct Delete<T: Transaction> {
+  table: String,
+  source: Box<dyn Executor<T>>
+}
+
+impl<T: Transaction> Delete<T> {
+  pub fn new(table: String, source: Box<dyn Executor<T>>) -> Box<Self> {
+    Box::new(Self { table, source })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Delete<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let table= txn.mustGetTable(&self.table)?;
+
+    // The primary keys are collected before any row is deleted, for the same reason as in Update.
+    let (_, rows)= self.source.execute(txn)?.intoQuery( )?;
+    let primaryKeys= rows.map(|row| Ok(row?[table.primaryKey].clone( )))
+                         .collect::<Result<Vec<_>>>( )?;
+
+    let mut count= 0;
+    for primaryKey in primaryKeys {
+      txn.delete(&table.name, &primaryKey)?;
+      count += 1;
+    }
+
+    Ok(ResultSet::Delete { count })
+  }
+}
+
+// Deletes all the rows of a table at once. The result is the same as deleting them one by one.
+pub struct Truncate {
+  table: String
+}
+
+impl Truncate {
+  pub fn new(table: String) -> Box<Self> {
+    Box::new(Self { table })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Truncate {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    Ok(ResultSet::Delete { count: txn.truncate(&self.table)? })
+  }
+}
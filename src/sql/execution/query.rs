@@ -0,0 +1,287 @@
+use std::{cmp::Ordering, collections::HashSet, sync::Arc};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{engine::Transaction, parser::ast::{self, Expression}, types::{Row, Value, ValueType}}
+};
+use super::{spill::{merge, rowSize, SpillFile, WorkMemory}, Executor, ResultColumn, ResultSet, Rows};
+
+// Evaluates a predicate against a row. Following three-valued logic, a NULL result doesn't satisfy
+// the predicate.
+pub fn evaluatePredicate(predicate: &Expression, row: &Row) -> Result<bool> {
+  match predicate.evaluate(Some(row))? {
+    Value::Boolean(satisfied) => Ok(satisfied),
+    Value::Null => Ok(false),
+    value => Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Predicate {} returned {}, expected a boolean", predicate, value)))
+  }
+}
+
+// Filters the rows satisfying the predicate.
+pub struct Filter<T: Transaction> {
+  source: Box<dyn Executor<T>>,
+  predicate: Expression
+}
+
+impl<T: Transaction> Filter<T> {
+  pub fn new(source: Box<dyn Executor<T>>, predicate: Expression) -> Box<Self> {
+    Box::new(Self { source, predicate })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Filter<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (columns, rows)= self.source.execute(txn)?.intoQuery( )?;
+    let predicate= self.predicate.compileLikePatterns( )?;
+
+    Ok(ResultSet::Query {
+      columns,
+      rows: Box::new(rows.filter_map(move |row| row.and_then(|row| {
+        Ok(evaluatePredicate(&predicate, &row)?.then_some(row))
+      }).transpose( )))
+    })
+  }
+}
+
+// Evaluates the (labeled) expressions against every row.
+pub struct Projection<T: Transaction> {
+  source: Box<dyn Executor<T>>,
+  expressions: Vec<(Expression, Option<String>)>
+}
+
+impl<T: Transaction> Projection<T> {
+  pub fn new(source: Box<dyn Executor<T>>, expressions: Vec<(Expression, Option<String>)>) -> Box<Self> {
+    Box::new(Self { source, expressions })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Projection<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (sourceColumns, rows)= self.source.execute(txn)?.intoQuery( )?;
+
+    let resolveType= |expression: &Expression| match expression {
+      Expression::Column(index) => sourceColumns.get(*index)
+                                                .map(|column| column.valueType)
+                                                .ok_or_else(| | Error::Internal(format!("Column #{} doesn't exist", index))),
+      _ => Ok(ValueType::unknown( ))
+    };
+
+    // Unlabeled columns passed through as is, retain the labels of the source columns. The type of
+    // an expression is unknown, if it couldn't be inferred (the planner has already reported any
+    // type errors, except for those involving the values of subqueries).
+    let (expressions, columns): (Vec<_>, Vec<_>)= self.expressions.into_iter( )
+                                                                  .map(|(expression, label)| {
+                                                                    let label= match (label, &expression) {
+                                                                      (Some(label), _) => Some(label),
+                                                                      (None, Expression::Column(index)) => sourceColumns.get(*index).and_then(|column| column.label.clone( )),
+                                                                      (None, _) => None
+                                                                    };
+                                                                    let valueType= expression.inferType(&resolveType).unwrap_or_else(|_| ValueType::unknown( ));
+                                                                    (expression, ResultColumn::new(label, valueType))
+                                                                  })
+                                                                  .unzip( );
+
+    Ok(ResultSet::Query {
+      columns,
+      rows: Box::new(rows.map(move |row| {
+        let row= row?;
+        expressions.iter( )
+                   .map(|expression| expression.evaluate(Some(&row)))
+                   .collect( )
+      }))
+    })
+  }
+}
+
+/*
+  Sorts the rows (stably). NULLs come first, irrespective of the order direction.
+
+  The rows are sorted in runs fitting the work memory. Unless all the rows fit in a single run, the
+  runs are spilled to disk, and then merged. If there are more runs than can be merged at once, they
+  are merged in multiple passes.
+*/
+pub struct Order<T: Transaction> {
+  source: Box<dyn Executor<T>>,
+  orders: Vec<(Expression, ast::Order)>,
+  workMemory: WorkMemory
+}
+
+// Maximum number of runs merged at once, each of which keeps a spill file open.
+const MAX_MERGED_RUNS: usize= 64;
+
+impl<T: Transaction> Order<T> {
+  pub fn new(source: Box<dyn Executor<T>>, orders: Vec<(Expression, ast::Order)>, workMemory: WorkMemory) -> Box<Self> {
+    Box::new(Self { source, orders, workMemory })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Order<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (columns, rows)= self.source.execute(txn)?.intoQuery( )?;
+    let orders= self.orders.iter( ).map(|(_, order)| *order).collect::<Vec<_>>( );
+
+    // The sort keys are evaluated upfront, since evaluation can fail. A spilled row is prefixed by
+    // its sort keys.
+    let mut spilledRuns= Vec::new( );
+    let mut run= Vec::new( );
+    let mut runSize= 0;
+    for row in rows {
+      let row= row?;
+      let keys= self.orders.iter( )
+                           .map(|(expression, _)| expression.evaluate(Some(&row)))
+                           .collect::<Result<Vec<_>>>( )?;
+
+      runSize += rowSize(&keys) + rowSize(&row);
+      run.push((keys, row));
+
+      if runSize > self.workMemory.budget {
+        run.sort_by(|(lhs, _), (rhs, _)| compareSortKeys(lhs, rhs, &orders));
+        spilledRuns.push(self.workMemory.spill(run.drain(..).map(|(mut keys, row)| {
+          keys.extend(row);
+          Ok(keys)
+        }))?);
+        runSize= 0;
+      }
+    }
+    run.sort_by(|(lhs, _), (rhs, _)| compareSortKeys(lhs, rhs, &orders));
+
+    if spilledRuns.is_empty( ) {
+      return Ok(ResultSet::Query {
+        columns,
+        rows: Box::new(run.into_iter( ).map(|(_, row)| Ok(row)))
+      })
+    }
+
+    let keyCount= orders.len( );
+    let orders= Arc::new(orders);
+    let compare= move |lhs: &Row, rhs: &Row| compareSortKeys(&lhs[..keyCount], &rhs[..keyCount], &orders);
+
+    // Earlier runs hold earlier rows, so merging consecutive runs keeps the sort stable.
+    while spilledRuns.len( ) >= MAX_MERGED_RUNS {
+      let mut mergedRuns= Vec::new( );
+      let mut runs= spilledRuns.into_iter( );
+      loop {
+        let chunk= runs.by_ref( ).take(MAX_MERGED_RUNS).map(SpillFile::read).collect::<Result<Vec<_>>>( )?;
+        if chunk.is_empty( ) {
+          break}
+
+        mergedRuns.push(self.workMemory.spill(merge(chunk, compare.clone( ))?)?);
+      }
+      spilledRuns= mergedRuns;
+    }
+
+    // The last run is merged straight from memory.
+    let mut runs= spilledRuns.into_iter( ).map(SpillFile::read).collect::<Result<Vec<_>>>( )?;
+    runs.push(Box::new(run.into_iter( ).map(|(mut keys, row)| {
+      keys.extend(row);
+      Ok(keys)
+    })));
+
+    Ok(ResultSet::Query {
+      columns,
+      rows: Box::new(merge(runs, compare)?.map(move |row| row.map(|mut row| row.split_off(keyCount))))
+    })
+  }
+}
+
+// Compares the sort keys of two rows. NULLs come first. The other values are sorted by their total
+// order (see Value) : so NaN comes after +inf, and -0.0 ties with 0.0.
+fn compareSortKeys(lhsKeys: &[Value], rhsKeys: &[Value], orders: &[ast::Order]) -> Ordering {
+  for ((lhs, rhs), order) in lhsKeys.iter( ).zip(rhsKeys).zip(orders) {
+    let ordering= match (lhs, rhs) {
+      (Value::Null, Value::Null) => Ordering::Equal,
+      (Value::Null, _) => Ordering::Less,
+      (_, Value::Null) => Ordering::Greater,
+
+      (lhs, rhs) => match order {
+        ast::Order::Ascending => lhs.cmp(rhs),
+        ast::Order::Descending => rhs.cmp(lhs)
+      }
+    };
+
+    if ordering != Ordering::Equal {
+      return ordering}
+  }
+  Ordering::Equal
+}
+
+// Removes the duplicate rows (see Plan::Distinct). The rows are streamed : unless they're sorted,
+// the distinct rows seen so far are kept in memory.
+pub struct Distinct<T: Transaction> {
+  source: Box<dyn Executor<T>>,
+  sorted: bool
+}
+
+impl<T: Transaction> Distinct<T> {
+  pub fn new(source: Box<dyn Executor<T>>, sorted: bool) -> Box<Self> {
+    Box::new(Self { source, sorted })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Distinct<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (columns, rows)= self.source.execute(txn)?.intoQuery( )?;
+
+    let rows: Rows= match self.sorted {
+      true => {
+        let mut previous= None;
+        Box::new(rows.filter(move |row| match row {
+          Ok(row) if previous.as_ref( ) == Some(row) => false,
+          Ok(row) => {
+            previous= Some(row.clone( ));
+            true
+          },
+          Err(_) => true
+        }))
+      },
+
+      false => {
+        let mut seen= HashSet::new( );
+        Box::new(rows.filter(move |row| match row {
+          Ok(row) => seen.insert(row.clone( )),
+          Err(_) => true
+        }))
+      }
+    };
+
+    Ok(ResultSet::Query { columns, rows })
+  }
+}
+
+// Returns only the given number of rows.
+pub struct Limit<T: Transaction> {
+  source: Box<dyn Executor<T>>,
+  limit: u64
+}
+
+impl<T: Transaction> Limit<T> {
+  pub fn new(source: Box<dyn Executor<T>>, limit: u64) -> Box<Self> {
+    Box::new(Self { source, limit })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Limit<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (columns, rows)= self.source.execute(txn)?.intoQuery( )?;
+
+    Ok(ResultSet::Query { columns, rows: Box::new(rows.take(self.limit as usize)) })
+  }
+}
+
+// Skips the given number of rows.
+pub struct Offset<T: Transaction> {
+  source: Box<dyn Executor<T>>,
+  offset: u64
+}
+
+impl<T: Transaction> Offset<T> {
+  pub fn new(source: Box<dyn Executor<T>>, offset: u64) -> Box<Self> {
+    Box::new(Self { source, offset })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Offset<T> {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let (columns, rows)= self.source.execute(txn)?.intoQuery( )?;
+
+    Ok(ResultSet::Query { columns, rows: Box::new(rows.skip(self.offset as usize)) })
+  }
+}
@@ -0,0 +1,193 @@
+use crate::{
+  result::Result,
+  sql::{engine::Transaction, parser::ast::AlterTableAction, schema::{Index, Table}, types::{DataType, Value, ValueType}}
+};
+use super::{Executor, ResultColumn, ResultSet};
+
+pub struct CreateTable {
+  schema: Table,
+  ifNotExists: bool
+}
+
+impl CreateTable {
+  pub fn new(schema: Table, ifNotExists: bool) -> Box<Self> {
+    Box::new(Self { schema, ifNotExists })
+  }
+}
+
+impl<T: Transaction> Executor<T> for CreateTable {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let name= self.schema.name.clone( );
+
+    // An existing table is left as it is, even if its definition differs.
+    if !(self.ifNotExists && txn.getTable(&name)?.is_some( )) {
+      txn.createTable(self.schema)?;}
+
+    Ok(ResultSet::CreateTable { name })
+  }
+}
+
+pub struct DropTable {
+  table: String,
+  ifExists: bool
+}
+
+impl DropTable {
+  pub fn new(table: String, ifExists: bool) -> Box<Self> {
+    Box::new(Self { table, ifExists })
+  }
+}
+
+impl<T: Transaction> Executor<T> for DropTable {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    if !(self.ifExists && txn.getTable(&self.table)?.is_none( )) {
+      txn.dropTable(&self.table)?;}
+
+    Ok(ResultSet::DropTable { name: self.table })
+  }
+}
+
+pub struct AlterTable {
+  table: String,
+  action: AlterTableAction
+}
+
+impl AlterTable {
+  pub fn new(table: String, action: AlterTableAction) -> Box<Self> {
+    Box::new(Self { table, action })
+  }
+}
+
+impl<T: Transaction> Executor<T> for AlterTable {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    match self.action {
+      AlterTableAction::RenameTo(newName) => {
+        txn.renameTable(&self.table, &newName)?;
+        Ok(ResultSet::AlterTable { name: newName })
+      },
+      AlterTableAction::RenameColumn { column, newName } => {
+        txn.renameColumn(&self.table, &column, &newName)?;
+        Ok(ResultSet::AlterTable { name: self.table })
+      }
+    }
+  }
+}
+
+pub struct CreateIndex {
+  table: String,
+  index: Index
+}
+
+impl CreateIndex {
+  pub fn new(table: String, index: Index) -> Box<Self> {
+    Box::new(Self { table, index })
+  }
+}
+
+impl<T: Transaction> Executor<T> for CreateIndex {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let name= self.index.name.clone( );
+    txn.createIndex(&self.table, self.index)?;
+
+    Ok(ResultSet::CreateIndex { name })
+  }
+}
+
+pub struct ShowTables;
+
+impl ShowTables {
+  pub fn new( ) -> Box<Self> {
+    Box::new(Self)
+  }
+}
+
+impl<T: Transaction> Executor<T> for ShowTables {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    // The tables are listed ordered by their names.
+    let rows= txn.listTables( )?
+                 .into_iter( )
+                 .map(|table| Ok(vec![Value::String(table.name)]))
+                 .collect::<Vec<_>>( );
+
+    Ok(ResultSet::Query {
+      columns: vec![ResultColumn::new(Some("table".to_string( )), ValueType::new(DataType::String, false))],
+      rows: Box::new(rows.into_iter( ))
+    })
+  }
+}
+
+// Collects the statistics of the tables, one after the other.
+pub struct Analyze {
+  tables: Vec<String>
+}
+
+impl Analyze {
+  pub fn new(tables: Vec<String>) -> Box<Self> {
+    Box::new(Self { tables })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Analyze {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    for table in &self.tables {
+      txn.analyzeTable(table)?;}
+
+    Ok(ResultSet::Analyze { count: self.tables.len( ) as u64 })
+  }
+}
+
+/*
+  Returns a row per column of the table : its name, type, nullability, default value (rendered as a
+  SQL literal, or NULL if it has none), whether it's the primary key, unique or indexed, the table
+  it references and the action taken when a referenced row is deleted.
+*/
+pub struct DescribeTable {
+  schema: Table
+}
+
+impl DescribeTable {
+  pub fn new(schema: Table) -> Box<Self> {
+    Box::new(Self { schema })
+  }
+}
+
+impl<T: Transaction> Executor<T> for DescribeTable {
+  fn execute(self: Box<Self>, _: &mut T) -> Result<ResultSet> {
+    let columns= [
+      ("name", DataType::String, false),
+      ("type", DataType::String, false),
+      ("nullable", DataType::Boolean, false),
+      ("default", DataType::String, true),
+      ("primary_key", DataType::Boolean, false),
+      ("unique", DataType::Boolean, false),
+      ("index", DataType::Boolean, false),
+      ("references", DataType::String, true),
+      ("on_delete", DataType::String, true)
+    ];
+
+    let rows= self.schema.columns.iter( )
+                                 .enumerate( )
+                                 .map(|(index, column)| Ok(vec![
+                                   Value::String(column.name.clone( )),
+                                   Value::String(column.dataType.to_string( )),
+                                   Value::Boolean(column.nullable),
+                                   column.renderDefault( ).map_or(Value::Null, Value::String),
+                                   Value::Boolean(index == self.schema.primaryKey),
+                                   Value::Boolean(column.unique),
+                                   Value::Boolean(column.index),
+                                   column.references.clone( ).map_or(Value::Null, Value::String),
+                                   match column.references {
+                                     Some(_) => Value::String(column.onDelete.to_string( )),
+                                     None => Value::Null
+                                   }
+                                 ]))
+                                 .collect::<Vec<_>>( );
+
+    Ok(ResultSet::Query {
+      columns: columns.into_iter( )
+                      .map(|(label, dataType, nullable)| ResultColumn::new(Some(label.to_string( )), ValueType::new(dataType, nullable)))
+                      .collect( ),
+      rows: Box::new(rows.into_iter( ))
+    })
+  }
+}
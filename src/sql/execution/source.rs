@@ -0,0 +1,227 @@
+use crate::{
+  result::{Error, Result},
+  sql::{
+    engine::{IndexRange, Transaction, READ_BATCH_SIZE},
+    parser::ast::Expression,
+    schema::VERSION_COLUMN,
+    types::{DataType, Row, Rows, Value, ValueType}
+  },
+  storage::engine::Direction
+};
+use super::{query::evaluatePredicate, Columns, Executor, ResultColumn, ResultSet};
+
+// Returns the columns of the table, followed by its _version system column. The rows of a table
+// source are suffixed with their versions accordingly.
+fn tableColumns<T: Transaction>(txn: &T, table: &str) -> Result<Columns> {
+  Ok(txn.mustGetTable(table)?
+        .columns
+        .into_iter( )
+        .map(|column| ResultColumn::new(Some(column.name), ValueType::new(column.dataType, column.nullable)))
+        .chain([ResultColumn::new(Some(VERSION_COLUMN.to_string( )), ValueType::new(DataType::Integer, false))])
+        .collect( ))
+}
+
+// Scans the rows of a table whose primary keys are in the given range, in the given direction,
+// optionally filtering them (or stopping after the given number of rows).
+pub struct Scan {
+  table: String,
+  filter: Option<Expression>,
+  range: IndexRange,
+  direction: Direction,
+  limit: Option<u64>
+}
+
+impl Scan {
+  pub fn new(table: String, filter: Option<Expression>, range: IndexRange, direction: Direction, limit: Option<u64>) -> Box<Self> {
+    Box::new(Self { table, filter, range, direction, limit })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Scan {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let columns= tableColumns(txn, &self.table)?;
+    let rows= txn.scanVersioned(&self.table, self.range, self.direction, self.limit)?;
+
+    Ok(ResultSet::Query {
+      columns,
+      rows: match self.filter.map(Expression::compileLikePatterns).transpose( )? {
+        Some(filter) => Box::new(rows.filter_map(move |row| row.and_then(|row| {
+          Ok(evaluatePredicate(&filter, &row)?.then_some(row))
+        }).transpose( ))),
+
+        None => rows
+      }
+    })
+  }
+}
+
+// Looks up the rows of a table by their primary keys.
+pub struct KeyLookup {
+  table: String,
+  keys: Vec<Value>
+}
+
+impl KeyLookup {
+  pub fn new(table: String, keys: Vec<Value>) -> Box<Self> {
+    Box::new(Self { table, keys })
+  }
+}
+
+impl<T: Transaction> Executor<T> for KeyLookup {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let columns= tableColumns(txn, &self.table)?;
+
+    // The rows are read eagerly, since they're few.
+    let rows= self.keys.iter( )
+                       .filter_map(|key| txn.readVersioned(&self.table, key).transpose( ))
+                       .collect::<Result<Vec<_>>>( )?;
+
+    Ok(ResultSet::Query { columns, rows: Box::new(rows.into_iter( ).map(Ok)) })
+  }
+}
+
+// Looks up the rows of a table, whose value in an indexed column is one of the given values.
+pub struct IndexLookup {
+  table: String,
+  column: String,
+  values: Vec<Value>
+}
+
+impl IndexLookup {
+  pub fn new(table: String, column: String, values: Vec<Value>) -> Box<Self> {
+    Box::new(Self { table, column, values })
+  }
+}
+
+impl<T: Transaction> Executor<T> for IndexLookup {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let columns= tableColumns(txn, &self.table)?;
+
+    let mut keys= Vec::new( );
+    for value in &self.values {
+      keys.extend(txn.lookupIndex(&self.table, &self.column, value)?);}
+
+    Ok(ResultSet::Query { columns, rows: readIndexedRows(txn, &self.table, keys)? })
+  }
+}
+
+// Scans the rows of a table, whose value in an indexed column is in the given range.
+pub struct IndexRangeScan {
+  table: String,
+  column: String,
+  range: IndexRange
+}
+
+impl IndexRangeScan {
+  pub fn new(table: String, column: String, range: IndexRange) -> Box<Self> {
+    Box::new(Self { table, column, range })
+  }
+}
+
+impl<T: Transaction> Executor<T> for IndexRangeScan {
+  fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+    let columns= tableColumns(txn, &self.table)?;
+    let keys= txn.scanIndex(&self.table, &self.column, self.range)?;
+
+    Ok(ResultSet::Query { columns, rows: readIndexedRows(txn, &self.table, keys.into_iter( ).collect( ))? })
+  }
+}
+
+// Reads the rows with the given primary keys, found through an index. They're read in batches of
+// READ_BATCH_SIZE rows, rather than one by one.
+fn readIndexedRows<T: Transaction>(txn: &T, table: &str, mut keys: Vec<Value>) -> Result<Rows> {
+  // Rows are returned in primary key order, like a scan.
+  keys.sort( );
+  keys.dedup( );
+
+  let mut rows= Vec::with_capacity(keys.len( ));
+  for batch in keys.chunks(READ_BATCH_SIZE) {
+    for (key, row) in batch.iter( ).zip(txn.readManyVersioned(table, batch)?) {
+      rows.push(row.ok_or_else(| | Error::Internal(format!("Indexed row {} of table {} doesn't exist", key, table)))?);}
+  }
+
+  Ok(Box::new(rows.into_iter( ).map(Ok)))
+}
+
+// Produces a row of the values of the expressions, for every list of expressions - the source of an
+// INSERT ... VALUES.
+pub struct Values {
+  rows: Vec<Vec<Expression>>
+}
+
+impl Values {
+  pub fn new(rows: Vec<Vec<Expression>>) -> Box<Self> {
+    Box::new(Self { rows })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Values {
+  fn execute(self: Box<Self>, _: &mut T) -> Result<ResultSet> {
+    // The type of a column unifies the types of its values, if they're compatible. Otherwise, the
+    // row inserting the incompatible value fails.
+    let columns= match self.rows.split_first( ) {
+      Some((first, rest)) => {
+        let inferType= |expression: &Expression| expression.inferType(&|_| Ok(ValueType::unknown( ))).ok( );
+
+        let mut types= first.iter( ).map(inferType).collect::<Vec<_>>( );
+        for row in rest {
+          for (valueType, expression) in types.iter_mut( ).zip(row) {
+            *valueType= valueType.zip(inferType(expression)).and_then(|(lhs, rhs)| lhs.unify(&rhs));}
+        }
+
+        types.into_iter( )
+             .map(|valueType| ResultColumn::new(None, valueType.unwrap_or_else(ValueType::unknown)))
+             .collect( )
+      },
+
+      None => Vec::new( )
+    };
+
+    Ok(ResultSet::Query {
+      columns,
+      rows: Box::new(self.rows.into_iter( ).map(|expressions| {
+        expressions.iter( )
+                   .map(|expression| expression.evaluate(None))
+                   .collect( )
+      }))
+    })
+  }
+}
+
+// Produces a single empty row - the source of a SELECT without a FROM clause.
+pub struct Nothing;
+
+impl Nothing {
+  pub fn new( ) -> Box<Self> {
+    Box::new(Self)
+  }
+}
+
+impl<T: Transaction> Executor<T> for Nothing {
+  fn execute(self: Box<Self>, _: &mut T) -> Result<ResultSet> {
+    Ok(ResultSet::Query {
+      columns: Vec::new( ),
+      rows: Box::new(std::iter::once(Ok(Row::new( ))))
+    })
+  }
+}
+
+// Produces no rows - the source of a query, whose filter is always false.
+pub struct Empty {
+  size: usize
+}
+
+impl Empty {
+  pub fn new(size: usize) -> Box<Self> {
+    Box::new(Self { size })
+  }
+}
+
+impl<T: Transaction> Executor<T> for Empty {
+  fn execute(self: Box<Self>, _: &mut T) -> Result<ResultSet> {
+    Ok(ResultSet::Query {
+      columns: vec![ResultColumn::new(None, ValueType::unknown( )); self.size],
+      rows: Box::new(std::iter::empty( ))
+    })
+  }
+}
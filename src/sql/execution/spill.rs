@@ -0,0 +1,258 @@
+use std::{
+  cmp::Ordering as CmpOrdering,
+  collections::BinaryHeap,
+  fs::{self, File},
+  io::{BufReader, BufWriter, ErrorKind, Read, Write},
+  mem::size_of,
+  path::PathBuf,
+  sync::{atomic::{AtomicU64, Ordering}, Arc}
+};
+use tracing::warn;
+use crate::{
+  result::Result,
+  sql::types::{Row, Rows, Value}
+};
+
+// Default memory budget (in bytes) of the executors of a statement.
+pub const DEFAULT_WORK_MEMORY: usize= 64 * 1024 * 1024;
+
+// Extension of the spill files, so that the startup sweep only removes those.
+const SPILL_FILE_EXTENSION: &str= "spill";
+
+/*
+  The memory the executors of a statement may buffer rows in, and the directory they spill the rows
+  to beyond it. Only the executors which buffer their whole input spill : Order (which sorts runs of
+  rows fitting the budget, and merges them) and Aggregation (which partitions the rows of the groups
+  not fitting the budget).
+
+  The budget is set per session, by SET work_memory = <bytes>.
+
+  Spill files are removed once they've been read, or when the statement fails or is cancelled (as
+  they're dropped along with its executors). Files left behind by a crash are removed when the
+  directory is opened again, at startup (see WorkMemory::new).
+
+  NOTE : The sizes of the rows are estimated (see rowSize), so the budget is approximate.
+*/
+#[derive(Clone)]
+pub struct WorkMemory {
+  pub budget: usize,
+  directory: Arc<SpillDirectory>,
+
+  // Number of spill files written, which EXPLAIN ANALYZE reports for every node.
+  spills: Arc<AtomicU64>
+}
+
+impl WorkMemory {
+  // Creates the spill directory (if it doesn't exist), removing the spill files left in it.
+  pub fn new(budget: usize, directory: impl Into<PathBuf>) -> Result<Self> {
+    let directory= directory.into( );
+    fs::create_dir_all(&directory)?;
+
+    for entry in fs::read_dir(&directory)? {
+      let path= entry?.path( );
+      if path.extension( ).is_some_and(|extension| extension == SPILL_FILE_EXTENSION) {
+        fs::remove_file(&path)?;}
+    }
+
+    Ok(Self {
+      budget,
+      directory: Arc::new(SpillDirectory { path: directory, nextFile: AtomicU64::new(0) }),
+      spills: Arc::new(AtomicU64::new(0))
+    })
+  }
+
+  // Returns the work memory for a single executor, counting its spills apart.
+  pub(super) fn forExecutor(&self) -> Self {
+    Self { spills: Arc::new(AtomicU64::new(0)), ..self.clone( ) }
+  }
+
+  pub(super) fn spills(&self) -> u64 {
+    self.spills.load(Ordering::Relaxed)
+  }
+
+  // Writes the rows to a new spill file.
+  pub(super) fn spill(&self, rows: impl IntoIterator<Item= Result<Row>>) -> Result<SpillFile> {
+    let mut writer= SpillWriter::new(self)?;
+    for row in rows {
+      writer.write(&row?)?;}
+    writer.finish( )
+  }
+}
+
+struct SpillDirectory {
+  path: PathBuf,
+  nextFile: AtomicU64
+}
+
+// A file of spilled rows, which is removed once it's dropped.
+pub(super) struct SpillFile {
+  path: PathBuf
+}
+
+impl SpillFile {
+  // Reads the rows back, in the order they were written. The file is removed once they've been read.
+  pub(super) fn read(self) -> Result<Rows> {
+    let reader= BufReader::new(File::open(&self.path)?);
+    Ok(Box::new(SpilledRows { reader, _file: self, done: false }))
+  }
+}
+
+impl Drop for SpillFile {
+  fn drop(&mut self) {
+    if let Err(error)= fs::remove_file(&self.path) {
+      if error.kind( ) != ErrorKind::NotFound {
+        warn!("Failed removing spill file {} : {}", self.path.display( ), error);}
+    }
+  }
+}
+
+// Writes rows to a spill file, each prefixed by the length of its encoding.
+pub(super) struct SpillWriter {
+  writer: BufWriter<File>,
+  file: SpillFile
+}
+
+impl SpillWriter {
+  pub(super) fn new(workMemory: &WorkMemory) -> Result<Self> {
+    let directory= &workMemory.directory;
+    let name= format!("{}-{}.{}", std::process::id( ), directory.nextFile.fetch_add(1, Ordering::Relaxed), SPILL_FILE_EXTENSION);
+
+    // The file is owned before it's created, so that it's removed even if the creation fails midway.
+    let file= SpillFile { path: directory.path.join(name) };
+    let writer= BufWriter::new(File::create(&file.path)?);
+
+    workMemory.spills.fetch_add(1, Ordering::Relaxed);
+    Ok(Self { writer, file })
+  }
+
+  pub(super) fn write(&mut self, row: &Row) -> Result<( )> {
+    let encoded= bincode::serialize(row)?;
+    self.writer.write_all(&(encoded.len( ) as u64).to_be_bytes( ))?;
+    self.writer.write_all(&encoded)?;
+    Ok(( ))
+  }
+
+  pub(super) fn finish(mut self) -> Result<SpillFile> {
+    self.writer.flush( )?;
+    Ok(self.file)
+  }
+}
+
+struct SpilledRows {
+  reader: BufReader<File>,
+  _file: SpillFile,
+  done: bool
+}
+
+impl SpilledRows {
+  fn readRow(&mut self) -> Result<Option<Row>> {
+    let mut length= [0; 8];
+    match self.reader.read_exact(&mut length) {
+      Err(error) if error.kind( ) == ErrorKind::UnexpectedEof => return Ok(None),
+      result => result?
+    }
+
+    let mut encoded= vec![0; u64::from_be_bytes(length) as usize];
+    self.reader.read_exact(&mut encoded)?;
+    Ok(Some(bincode::deserialize(&encoded)?))
+  }
+}
+
+impl Iterator for SpilledRows {
+  type Item= Result<Row>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None}
+
+    let row= self.readRow( ).transpose( );
+    self.done= !matches!(row, Some(Ok(_)));
+    row
+  }
+}
+
+/*
+  Merges sorted runs of rows into a single sorted stream (a k-way merge), using the given ordering.
+  Rows which are equal by the ordering come from the runs in their order, so the merge is stable if
+  the runs were cut from the input in order.
+*/
+pub(super) fn merge(mut runs: Vec<Rows>, compare: impl Fn(&Row, &Row) -> CmpOrdering + Send + Sync + 'static) -> Result<Rows> {
+  let compare: RowOrdering= Arc::new(compare);
+
+  let mut heads= BinaryHeap::new( );
+  for (run, rows) in runs.iter_mut( ).enumerate( ) {
+    if let Some(row)= rows.next( ).transpose( )? {
+      heads.push(Head { row, run, compare: compare.clone( ) });}
+  }
+
+  Ok(Box::new(Merge { runs, heads, failed: false }))
+}
+
+type RowOrdering= Arc<dyn Fn(&Row, &Row) -> CmpOrdering + Send + Sync>;
+
+struct Merge {
+  runs: Vec<Rows>,
+  // The next row of every run which hasn't ended.
+  heads: BinaryHeap<Head>,
+  failed: bool
+}
+
+impl Iterator for Merge {
+  type Item= Result<Row>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.failed {
+      return None}
+
+    let Head { row, run, compare }= self.heads.pop( )?;
+    match self.runs[run].next( ) {
+      Some(Ok(next)) => self.heads.push(Head { row: next, run, compare }),
+      Some(Err(error)) => {
+        self.failed= true;
+        return Some(Err(error))
+      },
+      None => { }
+    }
+    Some(Ok(row))
+  }
+}
+
+// The next row of a run being merged. BinaryHeap pops the greatest element, so the ordering is
+// reversed : the smallest row (from the earliest run, on ties) is the greatest head.
+struct Head {
+  row: Row,
+  run: usize,
+  compare: RowOrdering
+}
+
+impl Ord for Head {
+  fn cmp(&self, other: &Self) -> CmpOrdering {
+    (self.compare)(&self.row, &other.row).then(self.run.cmp(&other.run)).reverse( )
+  }
+}
+
+impl PartialOrd for Head {
+  fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl PartialEq for Head {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == CmpOrdering::Equal
+  }
+}
+
+impl Eq for Head { }
+
+// Estimates the memory taken by a row (including the values it owns on the heap).
+pub(super) fn rowSize(row: &Row) -> usize {
+  size_of::<Row>( ) + row.iter( ).map(valueSize).sum::<usize>( )
+}
+
+pub(super) fn valueSize(value: &Value) -> usize {
+  size_of::<Value>( ) + match value {
+    Value::String(string) => string.len( ),
+    _ => 0
+  }
+}
@@ -0,0 +1,74 @@
+use std::{collections::HashSet, sync::Arc};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{
+    engine::Transaction,
+    parser::ast::{Expression, Literal, Operation, Statement},
+    plan::{Plan, PlanOptions, StatementPlan},
+    types::Value
+  }
+};
+use super::{Cancellation, WorkMemory};
+
+/*
+  Evaluates the subqueries in the expressions of the plan, replacing them by their results : a
+  scalar subquery by its value, and the subquery of an IN by the set of its values. Since subqueries
+  are uncorrelated, each one is evaluated just once - rather than for every row.
+
+  The plan of an EXPLAIN is left as is, since it isn't executed.
+*/
+pub fn evaluateSubqueries<T: Transaction + 'static>(plan: Plan, txn: &mut T, cancellation: &Cancellation, workMemory: &WorkMemory) -> Result<Plan> {
+  if let Plan::Explain { .. }= plan {
+    return Ok(plan)}
+
+  plan.transformExpressions(&mut |expression| evaluateExpression(expression, txn, cancellation, workMemory))
+}
+
+// Replaces the subqueries in the expression (top-down, since the subquery of an IN is evaluated
+// differently).
+fn evaluateExpression<T: Transaction + 'static>(expression: Expression, txn: &mut T, cancellation: &Cancellation, workMemory: &WorkMemory) -> Result<Expression> {
+  Ok(match expression {
+    Expression::Operation(Operation::In(lhs, rhs)) => {
+      let rhs= match *rhs {
+        Expression::Subquery(statement) => Expression::ValueSet(Arc::new(evaluateValues(*statement, txn, cancellation, workMemory)?.collect::<Result<_>>( )?)),
+        rhs => evaluateExpression(rhs, txn, cancellation, workMemory)?
+      };
+
+      Operation::In(Box::new(evaluateExpression(*lhs, txn, cancellation, workMemory)?), Box::new(rhs)).into( )
+    },
+
+    Expression::Subquery(statement) => {
+      let mut values= evaluateValues(*statement, txn, cancellation, workMemory)?;
+
+      let value= values.next( ).transpose( )?.unwrap_or(Value::Null);
+      if values.next( ).transpose( )?.is_some( ) {
+        return Err(Error::Value(ErrorCode::CardinalityViolation, "Scalar subquery returned more than one row".to_string( )))}
+
+      Literal::from(value).into( )
+    },
+
+    Expression::Operation(operation) => Expression::Operation(operation.mapOperands(&mut |operand| evaluateExpression(operand, txn, cancellation, workMemory))?),
+
+    Expression::FunctionCall(name, arguments) => Expression::FunctionCall(
+      name,
+      arguments.into_iter( )
+               .map(|argument| evaluateExpression(argument, txn, cancellation, workMemory))
+               .collect::<Result<_>>( )?
+    ),
+
+    Expression::Distinct(expression) => Expression::Distinct(Box::new(evaluateExpression(*expression, txn, cancellation, workMemory)?)),
+
+    expression => expression
+  })
+}
+
+// Executes the subquery, returning the values of its (single) column.
+fn evaluateValues<T: Transaction + 'static>(statement: Statement, txn: &mut T, cancellation: &Cancellation, workMemory: &WorkMemory) -> Result<impl Iterator<Item= Result<Value>>> {
+  let (_, rows)= StatementPlan::build(statement, txn, PlanOptions::default( ))?.execute(txn, cancellation, workMemory)?.intoQuery( )?;
+
+  Ok(rows.map(|row| {
+    row?.into_iter( )
+        .next( )
+        .ok_or_else(| | Error::Internal("Subquery returned a row without any columns".to_string( )))
+  }))
+}
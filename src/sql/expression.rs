@@ -0,0 +1,1044 @@
+use std::{cmp::Ordering, collections::HashSet, fmt::Display, ops::Bound, sync::Arc};
+use crate::result::{Error, ErrorCode, Result};
+use super::{
+  datetime,
+  engine::IndexRange,
+  functions::getFunction,
+  numeric,
+  parser::ast::{Expression, Literal, Operation},
+  plan::Aggregate,
+  types::{DataType, Row, Value, ValueType}
+};
+
+// Resolves a (table name, column name) field reference to the index of the column in the row.
+pub type FieldResolver<'a>= &'a dyn Fn(Option<&str>, &str) -> Result<usize>;
+
+// Resolves the type of a field reference (Expression::Field) or a resolved one (Expression::Column).
+pub type TypeResolver<'a>= &'a dyn Fn(&Expression) -> Result<ValueType>;
+
+impl Expression {
+  // Evaluates an expression, which doesn't reference any fields (or whose fields have been resolved
+  // already).
+  pub fn evaluate(&self, row: Option<&Row>) -> Result<Value> {
+    self.evaluateWith(row, &|table, column| Err(Error::Value(ErrorCode::UndefinedColumn, format!(
+      "Can't resolve field {}", qualifiedFieldName(table, column)
+    ))))
+  }
+
+  // Evaluates the expression against the given row, resolving field references using the given
+  // resolver.
+  pub fn evaluateWith(&self, row: Option<&Row>, resolveField: FieldResolver) -> Result<Value> {
+    Ok(match self {
+      Self::Literal(literal) => literal.into( ),
+
+      Self::Field(table, column) => {
+        let index= resolveField(table.as_deref( ), column)?;
+
+        match row.and_then(|row| row.get(index)) {
+          Some(value) => value.clone( ),
+
+          None => return Err(Error::Value(ErrorCode::UndefinedColumn, format!(
+            "Field {} can't be evaluated without a row", qualifiedFieldName(table.as_deref( ), column)
+          )))
+        }
+      },
+
+      Self::FunctionCall(name, arguments) => {
+        let function= getFunction(name).ok_or_else(| | Error::Value(ErrorCode::UndefinedFunction, format!("Unknown function {}", name)))?;
+
+        let arguments= arguments.iter( )
+                                .map(|argument| argument.evaluateWith(row, resolveField))
+                                .collect::<Result<Vec<_>>>( )?;
+        function.call(arguments)?
+      },
+
+      Self::Parameter(number) => return Err(Error::Value(ErrorCode::UndefinedParameter, format!("Parameter ${} isn't bound", number))),
+
+      Self::Distinct(_) => return Err(distinctOutsideAggregate(self)),
+      Self::QualifiedWildcard(_) => return Err(wildcardOutsideSelections(self)),
+
+      // Subqueries are evaluated (against the transaction) before the plan is executed. So, one left
+      // here is in a clause which doesn't support them (like LIMIT).
+      Self::Subquery(statement) => return Err(Error::Value(ErrorCode::FeatureNotSupported, format!("Subquery ({}) can't be used here", statement))),
+      Self::ValueSet(_) => return Err(Error::Value(ErrorCode::SyntaxError, "A set of values can only be used with IN".to_string( ))),
+      Self::LikePattern(pattern) => return Err(Error::Value(ErrorCode::SyntaxError, format!("Pattern {} can only be used with LIKE", pattern))),
+
+      Self::Column(index) => match row.and_then(|row| row.get(*index)) {
+        Some(value) => value.clone( ),
+        None => return Err(Error::Value(ErrorCode::UndefinedColumn, format!("Column #{} can't be evaluated without a row", index)))
+      },
+
+      Self::Operation(operation) => {
+        let evaluate= |expression: &Expression| expression.evaluateWith(row, resolveField);
+
+        match operation {
+          Operation::And(lhs, rhs) => and(evaluate(lhs)?, evaluate(rhs)?)?,
+          Operation::Or(lhs, rhs) => or(evaluate(lhs)?, evaluate(rhs)?)?,
+          Operation::Not(operand) => match evaluate(operand)? {
+            Value::Boolean(value) => Value::Boolean(!value),
+            Value::Null => Value::Null,
+            value => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't negate {}", value)))
+          },
+
+          Operation::Equal(lhs, rhs) =>
+            compare(evaluate(lhs)?, evaluate(rhs)?, |ordering| ordering == Ordering::Equal)?,
+          Operation::NotEqual(lhs, rhs) =>
+            match compare(evaluate(lhs)?, evaluate(rhs)?, |ordering| ordering == Ordering::Equal)? {
+              Value::Boolean(equal) => Value::Boolean(!equal),
+              value => value
+            },
+          Operation::GreaterThan(lhs, rhs) =>
+            compare(evaluate(lhs)?, evaluate(rhs)?, |ordering| ordering == Ordering::Greater)?,
+          Operation::GreaterThanOrEqual(lhs, rhs) =>
+            compare(evaluate(lhs)?, evaluate(rhs)?, |ordering| ordering != Ordering::Less)?,
+          Operation::LessThan(lhs, rhs) =>
+            compare(evaluate(lhs)?, evaluate(rhs)?, |ordering| ordering == Ordering::Less)?,
+          Operation::LessThanOrEqual(lhs, rhs) =>
+            compare(evaluate(lhs)?, evaluate(rhs)?, |ordering| ordering != Ordering::Greater)?,
+          Operation::IsNull(operand) => Value::Boolean(evaluate(operand)?.isNull( )),
+
+          Operation::Add(lhs, rhs) => add(evaluate(lhs)?, evaluate(rhs)?)?,
+          Operation::Subtract(lhs, rhs) => subtract(evaluate(lhs)?, evaluate(rhs)?)?,
+          Operation::Multiply(lhs, rhs) => numeric::multiply(evaluate(lhs)?, evaluate(rhs)?)?,
+          Operation::Divide(lhs, rhs) => numeric::divide(evaluate(lhs)?, evaluate(rhs)?)?,
+          Operation::Modulo(lhs, rhs) => numeric::remainder(evaluate(lhs)?, evaluate(rhs)?)?,
+          Operation::Exponentiate(lhs, rhs) => numeric::exponentiate(evaluate(lhs)?, evaluate(rhs)?)?,
+          Operation::Factorial(operand) => numeric::factorial(evaluate(operand)?)?,
+          Operation::Negate(operand) => numeric::negate(evaluate(operand)?)?,
+          Operation::Assert(operand) => match evaluate(operand)? {
+            value @ (Value::Integer(_) | Value::Float(_) | Value::Null) => value,
+            value => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't take the positive of {}", value)))
+          },
+
+          Operation::Concatenate(lhs, rhs) => concatenate(evaluate(lhs)?, evaluate(rhs)?)?,
+
+          Operation::Like(lhs, rhs, escape) => match (evaluate(lhs)?, rhs.as_ref( )) {
+            (Value::String(value), Expression::LikePattern(pattern)) => Value::Boolean(pattern.matches(&value)),
+            (Value::Null, Expression::LikePattern(_)) => Value::Null,
+            (value, Expression::LikePattern(pattern)) =>
+              return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't match {} LIKE {}", value, pattern))),
+
+            (value, rhs) => like(value, evaluate(rhs)?, escape.as_deref( ).map(evaluate).transpose( )?)?
+          },
+
+          Operation::In(lhs, rhs) => match rhs.as_ref( ) {
+            Expression::ValueSet(values) => isIn(evaluate(lhs)?, values),
+            rhs => return Err(Error::Value(ErrorCode::SyntaxError, format!("Expected a subquery after IN, found {}", rhs)))
+          },
+
+          // Only the result of the matching branch is evaluated. A NULL condition doesn't match.
+          Operation::Case { operand, branches, otherwise } => {
+            let operand= operand.as_deref( ).map(evaluate).transpose( )?;
+
+            let mut matchingBranch= None;
+            for (condition, result) in branches {
+              let matches= match &operand {
+                Some(operand) => compare(operand.clone( ), evaluate(condition)?, |ordering| ordering == Ordering::Equal)?,
+                None => evaluate(condition)?
+              };
+
+              match matches {
+                Value::Boolean(true) => {
+                  matchingBranch= Some(result);
+                  break
+                },
+                Value::Boolean(false) | Value::Null => { },
+                value => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("CASE condition {} must be a boolean, got {}", condition, value)))
+              }
+            }
+
+            match matchingBranch.or(otherwise.as_deref( )) {
+              Some(result) => evaluate(result)?,
+              None => Value::Null
+            }
+          }
+        }
+      }
+    })
+  }
+}
+
+impl Expression {
+  /*
+    Infers the type of the expression, resolving the types of the fields using the given resolver.
+    Returns error, if an operation is applied to operands of incompatible types (like 'abc' + 1 or
+    TRUE * 2) - so that such errors are reported when the statement is planned, rather than when a
+    row is evaluated.
+
+    The only implicit coercion is of integers into floats, in arithmetic and comparisons. The result
+    is nullable, if any of the operands is.
+  */
+  pub fn inferType(&self, resolveType: TypeResolver) -> Result<ValueType> {
+    let infer= |expression: &Expression| expression.inferType(resolveType);
+    let describe= |expression: &Expression, valueType: ValueType| format!("{} ({})", expression, valueType);
+
+    Ok(match self {
+      Self::Literal(literal) => match Value::from(literal).dataType( ) {
+        Some(dataType) => ValueType::new(dataType, false),
+        None => ValueType::unknown( )
+      },
+
+      Self::Field(..) | Self::Column(_) => resolveType(self)?,
+
+      // The types of parameters and subqueries aren't known, until they're bound / evaluated.
+      Self::Parameter(_) | Self::Subquery(_) | Self::ValueSet(_) => ValueType::unknown( ),
+
+      Self::LikePattern(_) => ValueType::new(DataType::String, false),
+
+      Self::Distinct(_) => return Err(distinctOutsideAggregate(self)),
+      Self::QualifiedWildcard(_) => return Err(wildcardOutsideSelections(self)),
+
+      Self::FunctionCall(name, arguments) => match Aggregate::fromName(name) {
+        Some(aggregate) => {
+          let [argument]= arguments.as_slice( ) else {
+            return Err(Error::Value(ErrorCode::UndefinedFunction, format!("Aggregate function {} takes exactly one argument", name)))
+          };
+          let argument= match argument {
+            Self::Distinct(argument) => argument,
+            argument => argument
+          };
+
+          let argumentType= infer(argument)?;
+          aggregate.resultType(argumentType)
+                   .ok_or_else(| | Error::Value(ErrorCode::DatatypeMismatch, format!("Can't compute {} of {}", aggregate, describe(argument, argumentType))))?
+        },
+
+        None => {
+          let function= getFunction(name).ok_or_else(| | Error::Value(ErrorCode::UndefinedFunction, format!("Unknown function {}", name)))?;
+
+          let argumentTypes= arguments.iter( )
+                                      .map(|argument| Ok((argument, infer(argument)?)))
+                                      .collect::<Result<Vec<_>>>( )?;
+          function.inferType(&argumentTypes)?
+        }
+      },
+
+      Self::Operation(operation) => {
+        let operandTypes= operation.operands( )
+                                   .into_iter( )
+                                   .map(|operand| Ok((operand, infer(operand)?)))
+                                   .collect::<Result<Vec<_>>>( )?;
+        let nullable= operandTypes.iter( ).any(|(_, operandType)| operandType.nullable);
+
+        // Checks the types of the operands. If they're invalid, the error message is formatted from
+        // the descriptions of the operands.
+        let check= |isValid: &dyn Fn(&ValueType) -> bool, message: &dyn Fn(&[String]) -> String| -> Result<( )> {
+          if operandTypes.iter( ).all(|(_, operandType)| isValid(operandType)) {
+            return Ok(( ))}
+
+          let operands= operandTypes.iter( )
+                                    .map(|(operand, operandType)| describe(operand, *operandType))
+                                    .collect::<Vec<_>>( );
+          Err(Error::Value(ErrorCode::DatatypeMismatch, message(&operands)))
+        };
+
+        let isBoolean= |valueType: &ValueType| valueType.isCompatibleWith(DataType::Boolean);
+        let isString= |valueType: &ValueType| valueType.isCompatibleWith(DataType::String);
+        let isNumeric= |valueType: &ValueType| valueType.isNumeric( );
+
+        // Integers are coerced into floats. Otherwise, the result is of the operands' type.
+        let numericType= | | {
+          let dataTypes= operandTypes.iter( ).map(|(_, operandType)| operandType.dataType);
+          let dataType= dataTypes.clone( ).any(|dataType| dataType == Some(DataType::Float))
+                                 .then_some(DataType::Float)
+                                 .or(dataTypes.reduce(|lhs, rhs| lhs.and(rhs)).flatten( ));
+          ValueType { dataType, nullable }
+        };
+        let boolean= ValueType { dataType: Some(DataType::Boolean), nullable };
+
+        match operation {
+          Operation::And(..) => {
+            check(&isBoolean, &|operands| format!("Can't evaluate {} AND {}", operands[0], operands[1]))?;
+            boolean
+          },
+          Operation::Or(..) => {
+            check(&isBoolean, &|operands| format!("Can't evaluate {} OR {}", operands[0], operands[1]))?;
+            boolean
+          },
+          Operation::Not(_) => {
+            check(&isBoolean, &|operands| format!("Can't negate {}", operands[0]))?;
+            boolean
+          },
+
+          Operation::Equal(..)
+          | Operation::NotEqual(..)
+          | Operation::GreaterThan(..)
+          | Operation::GreaterThanOrEqual(..)
+          | Operation::LessThan(..)
+          | Operation::LessThanOrEqual(..) => {
+            let (lhs, lhsType)= operandTypes[0];
+            let (rhs, rhsType)= operandTypes[1];
+
+            if lhsType.unify(&rhsType).is_none( ) {
+              return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't compare {} and {}", describe(lhs, lhsType), describe(rhs, rhsType))))}
+            boolean
+          },
+
+          Operation::IsNull(_) => ValueType::new(DataType::Boolean, false),
+
+          // An integer can be added to (or subtracted from) a date / timestamp, offsetting it. And
+          // subtracting a date / timestamp from another results in an integer.
+          Operation::Add(..) => match (operandTypes[0].1.dataType, operandTypes[1].1.dataType) {
+            (Some(dataType @ (DataType::Date | DataType::Timestamp)), None | Some(DataType::Integer))
+            | (None | Some(DataType::Integer), Some(dataType @ (DataType::Date | DataType::Timestamp))) =>
+              ValueType { dataType: Some(dataType), nullable },
+
+            _ => {
+              check(&isNumeric, &|operands| format!("Can't add {} and {}", operands[0], operands[1]))?;
+              numericType( )
+            }
+          },
+          Operation::Subtract(..) => match (operandTypes[0].1.dataType, operandTypes[1].1.dataType) {
+            (Some(dataType @ (DataType::Date | DataType::Timestamp)), Some(DataType::Integer)) =>
+              ValueType { dataType: Some(dataType), nullable },
+            (Some(lhs @ (DataType::Date | DataType::Timestamp)), Some(rhs)) if lhs == rhs =>
+              ValueType { dataType: Some(DataType::Integer), nullable },
+            (Some(DataType::Date | DataType::Timestamp), None) | (None, Some(DataType::Date | DataType::Timestamp)) =>
+              ValueType { dataType: None, nullable },
+
+            _ => {
+              check(&isNumeric, &|operands| format!("Can't subtract {} from {}", operands[1], operands[0]))?;
+              numericType( )
+            }
+          },
+          Operation::Multiply(..) => {
+            check(&isNumeric, &|operands| format!("Can't multiply {} and {}", operands[0], operands[1]))?;
+            numericType( )
+          },
+          Operation::Divide(..) => {
+            check(&isNumeric, &|operands| format!("Can't divide {} by {}", operands[0], operands[1]))?;
+            numericType( )
+          },
+          Operation::Modulo(..) => {
+            check(&isNumeric, &|operands| format!("Can't find the remainder of {} divided by {}", operands[0], operands[1]))?;
+            numericType( )
+          },
+
+          // An integer raised to a negative (or huge) integer power results in a float. So the data
+          // type of the result is known, only if either operand is a float.
+          Operation::Exponentiate(..) => {
+            check(&isNumeric, &|operands| format!("Can't raise {} to the power of {}", operands[0], operands[1]))?;
+            match numericType( ) {
+              resultType @ ValueType { dataType: Some(DataType::Float), .. } => resultType,
+              _ => ValueType { dataType: None, nullable }
+            }
+          },
+
+          Operation::Factorial(_) => {
+            let isInteger= |valueType: &ValueType| valueType.isCompatibleWith(DataType::Integer);
+            check(&isInteger, &|operands| format!("Can't take the factorial of {}", operands[0]))?;
+            ValueType { dataType: Some(DataType::Integer), nullable }
+          },
+          Operation::Negate(_) => {
+            check(&isNumeric, &|operands| format!("Can't negate {}", operands[0]))?;
+            numericType( )
+          },
+          Operation::Assert(_) => {
+            check(&isNumeric, &|operands| format!("Can't take the positive of {}", operands[0]))?;
+            numericType( )
+          },
+
+          // Other values aren't converted to strings, so they can't be concatenated.
+          Operation::Concatenate(..) => {
+            check(&isString, &|operands| format!("Can't concatenate {} and {}", operands[0], operands[1]))?;
+            ValueType { dataType: Some(DataType::String), nullable }
+          },
+
+          Operation::Like(..) => {
+            check(&isString, &|operands| format!("Can't match {} LIKE {}", operands[0], operands[1..].join(" ESCAPE ")))?;
+            boolean
+          },
+
+          // The values of the subquery may be NULL, making the result NULL.
+          Operation::In(..) => ValueType { dataType: Some(DataType::Boolean), nullable: true },
+
+          // The conditions must be booleans (or values comparable with the operand), and the results
+          // must be of compatible types. Without an ELSE, the result is NULL when no branch matches.
+          Operation::Case { operand, branches, otherwise } => {
+            for (condition, _) in branches {
+              let conditionType= infer(condition)?;
+
+              match operand {
+                Some(operand) => {
+                  let operandType= infer(operand)?;
+                  if operandType.unify(&conditionType).is_none( ) {
+                    return Err(Error::Value(ErrorCode::DatatypeMismatch, format!(
+                      "Can't compare {} and {}", describe(operand, operandType), describe(condition, conditionType)
+                    )))}
+                },
+
+                None if !isBoolean(&conditionType) =>
+                  return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("CASE condition {} must be a boolean", describe(condition, conditionType)))),
+
+                None => { }
+              }
+            }
+
+            let mut results= branches.iter( ).map(|(_, result)| result).chain(otherwise.as_deref( ));
+            let first= results.next( ).ok_or_else(| | Error::Value(ErrorCode::SyntaxError, "CASE expects atleast one WHEN branch".to_string( )))?;
+
+            let mut resultType= infer(first)?;
+            for result in results {
+              let valueType= infer(result)?;
+              resultType= resultType.unify(&valueType)
+                                    .ok_or_else(| | Error::Value(ErrorCode::DatatypeMismatch, format!(
+                                      "CASE results must be of compatible types, got {} after results of type {}", describe(result, valueType), resultType
+                                    )))?;
+            }
+
+            ValueType { nullable: resultType.nullable || otherwise.is_none( ), ..resultType }
+          }
+        }
+      }
+    })
+  }
+}
+
+impl Expression {
+  // Transforms the expression bottom-up : first the operands (recursively), and then the expression
+  // itself. The expressions of subqueries aren't transformed, since they're in a different scope.
+  pub fn transform(self, transformer: &mut dyn FnMut(Expression) -> Result<Expression>) -> Result<Expression> {
+    let expression= match self {
+      Self::Operation(operation) => Self::Operation(operation.mapOperands(&mut |operand| operand.transform(transformer))?),
+
+      Self::FunctionCall(name, arguments) => Self::FunctionCall(
+        name,
+        arguments.into_iter( )
+                 .map(|argument| argument.transform(transformer))
+                 .collect::<Result<_>>( )?
+      ),
+
+      Self::Distinct(expression) => Self::Distinct(Box::new(expression.transform(transformer)?)),
+
+      expression => expression
+    };
+
+    transformer(expression)
+  }
+
+  // Returns whether the expression or any of its subexpressions satisfies the predicate.
+  pub fn contains(&self, predicate: &dyn Fn(&Expression) -> bool) -> bool {
+    if predicate(self) {
+      return true}
+
+    match self {
+      Self::Operation(operation) => operation.operands( ).into_iter( ).any(|operand| operand.contains(predicate)),
+      Self::FunctionCall(_, arguments) => arguments.iter( ).any(|argument| argument.contains(predicate)),
+      Self::Distinct(expression) => expression.contains(predicate),
+
+      _ => false
+    }
+  }
+}
+
+impl Expression {
+  // Combines the expressions using AND. Returns None, if there are no expressions.
+  pub fn and(expressions: Vec<Expression>) -> Option<Expression> {
+    expressions.into_iter( )
+               .reduce(|lhs, rhs| Operation::And(Box::new(lhs), Box::new(rhs)).into( ))
+  }
+
+  // Converts the expression into conjunctive normal form : an AND of ORs. NOTs are pushed down to
+  // the leaves (using De Morgan's laws), and ORs are distributed over ANDs. These rewrites hold
+  // under three-valued logic too.
+  pub fn intoCNF(self) -> Self {
+    match self.intoNNF( ) {
+      Self::Operation(Operation::And(lhs, rhs)) => Operation::And(Box::new(lhs.intoCNF( )), Box::new(rhs.intoCNF( ))).into( ),
+
+      Self::Operation(Operation::Or(lhs, rhs)) => match (lhs.intoCNF( ), rhs.intoCNF( )) {
+        (Self::Operation(Operation::And(l, r)), rhs) => Operation::And(
+          Box::new(Self::Operation(Operation::Or(l, Box::new(rhs.clone( )))).intoCNF( )),
+          Box::new(Self::Operation(Operation::Or(r, Box::new(rhs))).intoCNF( ))
+        ).into( ),
+
+        (lhs, Self::Operation(Operation::And(l, r))) => Operation::And(
+          Box::new(Self::Operation(Operation::Or(Box::new(lhs.clone( )), l)).intoCNF( )),
+          Box::new(Self::Operation(Operation::Or(Box::new(lhs), r)).intoCNF( ))
+        ).into( ),
+
+        (lhs, rhs) => Operation::Or(Box::new(lhs), Box::new(rhs)).into( )
+      },
+
+      expression => expression
+    }
+  }
+
+  // Converts the expression into its conjunctive normal form, split into the ANDed expressions.
+  pub fn intoCNFList(self) -> Vec<Self> {
+    match self.intoCNF( ) {
+      Self::Operation(Operation::And(lhs, rhs)) => {
+        let mut expressions= lhs.intoCNFList( );
+        expressions.extend(rhs.intoCNFList( ));
+        expressions
+      },
+
+      expression => vec![expression]
+    }
+  }
+
+  // Converts the expression into negation normal form, where NOTs are only applied to the leaves.
+  fn intoNNF(self) -> Self {
+    let not= |expression: Box<Expression>| Self::Operation(Operation::Not(expression)).intoNNF( );
+
+    match self {
+      Self::Operation(Operation::Not(operand)) => match *operand {
+        Self::Operation(Operation::And(lhs, rhs)) => Operation::Or(Box::new(not(lhs)), Box::new(not(rhs))).into( ),
+        Self::Operation(Operation::Or(lhs, rhs)) => Operation::And(Box::new(not(lhs)), Box::new(not(rhs))).into( ),
+        Self::Operation(Operation::Not(operand)) => operand.intoNNF( ),
+
+        operand => Operation::Not(Box::new(operand)).into( )
+      },
+
+      Self::Operation(Operation::And(lhs, rhs)) => Operation::And(Box::new(lhs.intoNNF( )), Box::new(rhs.intoNNF( ))).into( ),
+      Self::Operation(Operation::Or(lhs, rhs)) => Operation::Or(Box::new(lhs.intoNNF( )), Box::new(rhs.intoNNF( ))).into( ),
+
+      expression => expression
+    }
+  }
+
+  /*
+    Checks whether the expression is a lookup of the given indexed expression (like a column) : an
+    equality comparison of the same expression with a constant (in either order), or an OR of such
+    comparisons. If so, returns the constants. A comparison with NULL never matches, and hence
+    contributes no constants.
+  */
+  pub fn asLookup(&self, indexed: &Expression) -> Option<Vec<Value>> {
+    match self {
+      Self::Operation(Operation::Equal(lhs, rhs)) => match (lhs.as_ref( ), rhs.as_ref( )) {
+        (expression, Self::Literal(literal))
+        | (Self::Literal(literal), expression) if expression == indexed => match Value::from(literal) {
+          Value::Null => Some(vec![ ]),
+          value => Some(vec![value])
+        },
+
+        _ => None
+      },
+
+      Self::Operation(Operation::Or(lhs, rhs)) => {
+        let mut values= lhs.asLookup(indexed)?;
+        values.extend(rhs.asLookup(indexed)?);
+        Some(values)
+      },
+
+      _ => None
+    }
+  }
+
+  /*
+    Checks whether the expression is a comparison of the given column with a non NULL constant (like
+    col > 5, or 5 <= col). If so, returns the range of the column's values satisfying it.
+  */
+  pub fn asRange(&self, column: usize) -> Option<IndexRange> {
+    let Self::Operation(operation)= self else {
+      return None
+    };
+
+    // A constant on the left side flips the comparison.
+    let (value, isLower, isInclusive)= match operation {
+      Operation::GreaterThan(lhs, rhs)
+      | Operation::GreaterThanOrEqual(lhs, rhs)
+      | Operation::LessThan(lhs, rhs)
+      | Operation::LessThanOrEqual(lhs, rhs) => {
+        let isGreater= matches!(operation, Operation::GreaterThan(..) | Operation::GreaterThanOrEqual(..));
+        let isInclusive= matches!(operation, Operation::GreaterThanOrEqual(..) | Operation::LessThanOrEqual(..));
+
+        match (lhs.as_ref( ), rhs.as_ref( )) {
+          (Self::Column(index), Self::Literal(literal)) if *index == column => (Value::from(literal), isGreater, isInclusive),
+          (Self::Literal(literal), Self::Column(index)) if *index == column => (Value::from(literal), !isGreater, isInclusive),
+          _ => return None
+        }
+      },
+
+      _ => return None
+    };
+
+    if matches!(value, Value::Null) {
+      return None}
+
+    let bound= match isInclusive {
+      true => Bound::Included(value),
+      false => Bound::Excluded(value)
+    };
+
+    Some(match isLower {
+      true => (bound, Bound::Unbounded),
+      false => (Bound::Unbounded, bound)
+    })
+  }
+
+  /*
+    Checks whether the expression is a LIKE match of the given column against a constant pattern,
+    which starts with literal characters. If so, returns that prefix (which every matching value
+    starts with), along with whether the pattern matches every value starting with it (like
+    'abc%').
+  */
+  pub fn asPrefixMatch(&self, column: usize) -> Option<(String, bool)> {
+    match self {
+      Self::Operation(Operation::Like(lhs, rhs, escape)) if matches!(lhs.as_ref( ), Self::Column(index) if *index == column) =>
+        constantLikePattern(rhs, escape.as_deref( ))
+          .map(|pattern| pattern.prefix( ))
+          .filter(|(prefix, _)| !prefix.is_empty( )),
+
+      _ => None
+    }
+  }
+
+  // Compiles the constant patterns of the LIKE operations in the expression, so that they're
+  // compiled once (and not for every row the expression is evaluated against). Patterns which fail
+  // to compile are left as they are, so that the error surfaces when they're evaluated.
+  pub fn compileLikePatterns(self) -> Result<Expression> {
+    self.transform(&mut |expression| Ok(match expression {
+      Self::Operation(Operation::Like(lhs, rhs, escape)) => match constantLikePattern(&rhs, escape.as_deref( )) {
+        Some(pattern) => Operation::Like(lhs, Box::new(Self::LikePattern(Arc::new(pattern))), None).into( ),
+        None => Operation::Like(lhs, rhs, escape).into( )
+      },
+
+      expression => expression
+    }))
+  }
+
+  // Returns whether the expression references any column satisfying the predicate.
+  pub fn referencesColumn(&self, predicate: &dyn Fn(usize) -> bool) -> bool {
+    self.contains(&|expression| matches!(expression, Self::Column(index) if predicate(*index)))
+  }
+
+  // Returns whether the expression calls a volatile function (like RANDOM), and so may evaluate to a
+  // different value every time.
+  pub fn isVolatile(&self) -> bool {
+    self.contains(&|expression| matches!(
+      expression, Self::FunctionCall(name, _) if getFunction(name).is_some_and(|function| function.volatile)
+    ))
+  }
+}
+
+impl Operation {
+  pub fn operands(&self) -> Vec<&Expression> {
+    match self {
+      Self::And(lhs, rhs)
+      | Self::Or(lhs, rhs)
+      | Self::Equal(lhs, rhs)
+      | Self::GreaterThan(lhs, rhs)
+      | Self::GreaterThanOrEqual(lhs, rhs)
+      | Self::LessThan(lhs, rhs)
+      | Self::LessThanOrEqual(lhs, rhs)
+      | Self::NotEqual(lhs, rhs)
+      | Self::Add(lhs, rhs)
+      | Self::Divide(lhs, rhs)
+      | Self::Exponentiate(lhs, rhs)
+      | Self::Modulo(lhs, rhs)
+      | Self::Multiply(lhs, rhs)
+      | Self::Subtract(lhs, rhs)
+      | Self::Concatenate(lhs, rhs)
+      | Self::Like(lhs, rhs, None)
+      | Self::In(lhs, rhs) => vec![lhs, rhs],
+
+      Self::Like(lhs, rhs, Some(escape)) => vec![lhs, rhs, escape],
+
+      Self::Case { operand, branches, otherwise } =>
+        operand.as_deref( ).into_iter( )
+               .chain(branches.iter( ).flat_map(|(condition, result)| [condition, result]))
+               .chain(otherwise.as_deref( ))
+               .collect( ),
+
+      Self::Not(operand)
+      | Self::IsNull(operand)
+      | Self::Assert(operand)
+      | Self::Factorial(operand)
+      | Self::Negate(operand) => vec![operand]
+    }
+  }
+
+  // Rebuilds the operation, with its operands mapped using the given mapper.
+  pub fn mapOperands(self, mapper: &mut dyn FnMut(Expression) -> Result<Expression>) -> Result<Operation> {
+    let mut map= |operand: Box<Expression>| mapper(*operand).map(Box::new);
+
+    Ok(match self {
+      Self::And(lhs, rhs) => Self::And(map(lhs)?, map(rhs)?),
+      Self::Or(lhs, rhs) => Self::Or(map(lhs)?, map(rhs)?),
+      Self::Not(operand) => Self::Not(map(operand)?),
+
+      Self::Equal(lhs, rhs) => Self::Equal(map(lhs)?, map(rhs)?),
+      Self::GreaterThan(lhs, rhs) => Self::GreaterThan(map(lhs)?, map(rhs)?),
+      Self::GreaterThanOrEqual(lhs, rhs) => Self::GreaterThanOrEqual(map(lhs)?, map(rhs)?),
+      Self::IsNull(operand) => Self::IsNull(map(operand)?),
+      Self::LessThan(lhs, rhs) => Self::LessThan(map(lhs)?, map(rhs)?),
+      Self::LessThanOrEqual(lhs, rhs) => Self::LessThanOrEqual(map(lhs)?, map(rhs)?),
+      Self::NotEqual(lhs, rhs) => Self::NotEqual(map(lhs)?, map(rhs)?),
+
+      Self::Add(lhs, rhs) => Self::Add(map(lhs)?, map(rhs)?),
+      Self::Assert(operand) => Self::Assert(map(operand)?),
+      Self::Divide(lhs, rhs) => Self::Divide(map(lhs)?, map(rhs)?),
+      Self::Exponentiate(lhs, rhs) => Self::Exponentiate(map(lhs)?, map(rhs)?),
+      Self::Factorial(operand) => Self::Factorial(map(operand)?),
+      Self::Modulo(lhs, rhs) => Self::Modulo(map(lhs)?, map(rhs)?),
+      Self::Multiply(lhs, rhs) => Self::Multiply(map(lhs)?, map(rhs)?),
+      Self::Negate(operand) => Self::Negate(map(operand)?),
+      Self::Subtract(lhs, rhs) => Self::Subtract(map(lhs)?, map(rhs)?),
+
+      Self::Concatenate(lhs, rhs) => Self::Concatenate(map(lhs)?, map(rhs)?),
+
+      Self::Like(lhs, rhs, escape) => Self::Like(map(lhs)?, map(rhs)?, escape.map(map).transpose( )?),
+      Self::In(lhs, rhs) => Self::In(map(lhs)?, map(rhs)?),
+
+      Self::Case { operand, branches, otherwise } => Self::Case {
+        operand: operand.map(&mut map).transpose( )?,
+        branches: branches.into_iter( )
+                          .map(|(condition, result)| Ok((*map(Box::new(condition))?, *map(Box::new(result))?)))
+                          .collect::<Result<_>>( )?,
+        otherwise: otherwise.map(&mut map).transpose( )?
+      }
+    })
+  }
+}
+
+impl From<&Literal> for Value {
+  fn from(literal: &Literal) -> Self {
+    match literal {
+      Literal::Null => Value::Null,
+      Literal::Boolean(value) => Value::Boolean(*value),
+      Literal::Integer(value) => Value::Integer(*value),
+      Literal::Float(value) => Value::Float(*value),
+      Literal::String(value) => Value::String(value.clone( )),
+      Literal::Date(value) => Value::Date(*value),
+      Literal::Timestamp(value) => Value::Timestamp(*value)
+    }
+  }
+}
+
+impl From<Value> for Literal {
+  fn from(value: Value) -> Self {
+    match value {
+      Value::Null => Literal::Null,
+      Value::Boolean(value) => Literal::Boolean(value),
+      Value::Integer(value) => Literal::Integer(value),
+      Value::Float(value) => Literal::Float(value),
+      Value::String(value) => Literal::String(value),
+      Value::Date(value) => Literal::Date(value),
+      Value::Timestamp(value) => Literal::Timestamp(value)
+    }
+  }
+}
+
+fn qualifiedFieldName(table: Option<&str>, column: &str) -> String {
+  match table {
+    Some(table) => format!("{}.{}", table, column),
+    None => column.to_string( )
+  }
+}
+
+// Implements three-valued logic : FALSE AND NULL is FALSE, while TRUE AND NULL is NULL.
+fn and(lhs: Value, rhs: Value) -> Result<Value> {
+  Ok(match (lhs, rhs) {
+    (Value::Boolean(false), Value::Boolean(_) | Value::Null)
+    | (Value::Boolean(_) | Value::Null, Value::Boolean(false)) => Value::Boolean(false),
+
+    (Value::Boolean(true), Value::Boolean(true)) => Value::Boolean(true),
+
+    (Value::Boolean(true) | Value::Null, Value::Null)
+    | (Value::Null, Value::Boolean(true)) => Value::Null,
+
+    (lhs, rhs) => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't evaluate {} AND {}", lhs, rhs)))
+  })
+}
+
+// Implements three-valued logic : TRUE OR NULL is TRUE, while FALSE OR NULL is NULL.
+fn or(lhs: Value, rhs: Value) -> Result<Value> {
+  Ok(match (lhs, rhs) {
+    (Value::Boolean(true), Value::Boolean(_) | Value::Null)
+    | (Value::Boolean(_) | Value::Null, Value::Boolean(true)) => Value::Boolean(true),
+
+    (Value::Boolean(false), Value::Boolean(false)) => Value::Boolean(false),
+
+    (Value::Boolean(false) | Value::Null, Value::Null)
+    | (Value::Null, Value::Boolean(false)) => Value::Null,
+
+    (lhs, rhs) => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't evaluate {} OR {}", lhs, rhs)))
+  })
+}
+
+/*
+  Checks whether the value is in the set, comparing integers and floats numerically (like =). The
+  result is NULL, if the value isn't found but could equal a NULL in the set - or if the value is
+  NULL itself (and the set isn't empty).
+*/
+fn isIn(value: Value, values: &HashSet<Value>) -> Value {
+  let found= match &value {
+    Value::Null => false,
+    Value::Float(float) if float.is_nan( ) => false,
+
+    Value::Integer(integer) =>
+      values.contains(&value) || numeric::exactFloat(*integer).is_some_and(|float| values.contains(&Value::Float(float))),
+    Value::Float(float) =>
+      values.contains(&value) || numeric::integralFloat(*float).is_some_and(|integer| values.contains(&Value::Integer(integer))),
+
+    _ => values.contains(&value)
+  };
+
+  match found {
+    true => Value::Boolean(true),
+    false if values.is_empty( ) => Value::Boolean(false),
+    false if value.isNull( ) || values.contains(&Value::Null) => Value::Null,
+    false => Value::Boolean(false)
+  }
+}
+
+/*
+  Compares two values of compatible types, and checks the ordering using the given predicate.
+
+  Comparing with NULL results in NULL. Integers and floats are compared numerically, and exactly (see
+  numeric::compareIntegerWithFloat( )). Comparisons with NaN follow IEEE 754 : they're all false (and
+  so NaN != NaN is true).
+*/
+fn compare(lhs: Value, rhs: Value, predicate: impl Fn(Ordering) -> bool) -> Result<Value> {
+  let ordering= match (&lhs, &rhs) {
+    (Value::Null, _) | (_, Value::Null) => return Ok(Value::Null),
+
+    (Value::Boolean(lhs), Value::Boolean(rhs)) => Some(lhs.cmp(rhs)),
+    (Value::Integer(lhs), Value::Integer(rhs)) => Some(lhs.cmp(rhs)),
+    (Value::Integer(lhs), Value::Float(rhs)) => numeric::compareIntegerWithFloat(*lhs, *rhs),
+    (Value::Float(lhs), Value::Integer(rhs)) => numeric::compareIntegerWithFloat(*rhs, *lhs).map(Ordering::reverse),
+    (Value::Float(lhs), Value::Float(rhs)) => lhs.partial_cmp(rhs),
+    (Value::String(lhs), Value::String(rhs)) => Some(lhs.cmp(rhs)),
+    (Value::Date(lhs), Value::Date(rhs)) => Some(lhs.cmp(rhs)),
+    (Value::Timestamp(lhs), Value::Timestamp(rhs)) => Some(lhs.cmp(rhs)),
+
+    _ => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't compare {} and {}", lhs, rhs)))
+  };
+
+  Ok(Value::Boolean(ordering.is_some_and(predicate)))
+}
+
+/*
+  Adds two numeric values (see the numeric module). Also used to compute the SUM and AVG aggregates.
+
+  Adding an integer to a date offsets it by that many days, and adding one to a timestamp offsets it
+  by that many microseconds.
+*/
+pub fn add(lhs: Value, rhs: Value) -> Result<Value> {
+  Ok(match (lhs, rhs) {
+    (Value::Date(date), Value::Integer(days)) | (Value::Integer(days), Value::Date(date)) =>
+      offsetDate(date.checked_add(days))?,
+    (Value::Timestamp(timestamp), Value::Integer(microseconds)) | (Value::Integer(microseconds), Value::Timestamp(timestamp)) =>
+      offsetTimestamp(timestamp.checked_add(microseconds))?,
+    (Value::Null, Value::Date(_) | Value::Timestamp(_)) | (Value::Date(_) | Value::Timestamp(_), Value::Null) => Value::Null,
+
+    (lhs, rhs) => numeric::add(lhs, rhs)?
+  })
+}
+
+// Concatenates two strings. NULL concatenated with a string (or NULL) is NULL.
+fn concatenate(lhs: Value, rhs: Value) -> Result<Value> {
+  match (lhs, rhs) {
+    (Value::String(mut lhs), Value::String(rhs)) => {
+      lhs.push_str(&rhs);
+      Ok(Value::String(lhs))
+    },
+    (Value::Null, Value::String(_) | Value::Null) | (Value::String(_), Value::Null) => Ok(Value::Null),
+
+    (lhs, rhs) => Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't concatenate {} and {}", lhs, rhs)))
+  }
+}
+
+/*
+  Subtracts the second value from the first one.
+
+  Subtracting a date from another results in the number of days between them, and subtracting a
+  timestamp from another results in the number of microseconds between them. Subtracting an integer
+  from a date / timestamp offsets it backwards (see add( )).
+*/
+fn subtract(lhs: Value, rhs: Value) -> Result<Value> {
+  Ok(match (lhs, rhs) {
+    (Value::Date(lhs), Value::Date(rhs)) | (Value::Timestamp(lhs), Value::Timestamp(rhs)) =>
+      numeric::subtract(Value::Integer(lhs), Value::Integer(rhs))?,
+    (Value::Date(date), Value::Integer(days)) => offsetDate(date.checked_sub(days))?,
+    (Value::Timestamp(timestamp), Value::Integer(microseconds)) => offsetTimestamp(timestamp.checked_sub(microseconds))?,
+    (Value::Null, Value::Date(_) | Value::Timestamp(_)) | (Value::Date(_) | Value::Timestamp(_), Value::Null) => Value::Null,
+
+    (lhs, rhs) => numeric::subtract(lhs, rhs)?
+  })
+}
+
+// Checks that an offset date is still in the supported range.
+fn offsetDate(date: Option<i64>) -> Result<Value> {
+  match date {
+    Some(date) if datetime::isValidDate(date) => Ok(Value::Date(date)),
+    _ => Err(Error::Value(ErrorCode::DatetimeFieldOverflow, "Date out of range".to_string( )))
+  }
+}
+
+// Checks that an offset timestamp is still in the supported range.
+fn offsetTimestamp(timestamp: Option<i64>) -> Result<Value> {
+  match timestamp {
+    Some(timestamp) if datetime::isValidTimestamp(timestamp) => Ok(Value::Timestamp(timestamp)),
+    _ => Err(Error::Value(ErrorCode::DatetimeFieldOverflow, "Timestamp out of range".to_string( )))
+  }
+}
+
+fn like(value: Value, pattern: Value, escape: Option<Value>) -> Result<Value> {
+  Ok(match (value, pattern, escape) {
+    (Value::Null, _, _) | (_, Value::Null, _) | (_, _, Some(Value::Null)) => Value::Null,
+
+    (Value::String(value), Value::String(pattern), escape) => {
+      let escape= escape.map(escapeCharacter).transpose( )?;
+      Value::Boolean(LikePattern::compile(&pattern, escape)?.matches(&value))
+    },
+
+    (value, pattern, _) => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't match {} LIKE {}", value, pattern)))
+  })
+}
+
+fn escapeCharacter(escape: Value) -> Result<char> {
+  let Value::String(escape)= escape else {
+    return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("The escape of a LIKE pattern must be a string, found {}", escape)))
+  };
+
+  let mut characters= escape.chars( );
+  match (characters.next( ), characters.next( )) {
+    (Some(character), None) => Ok(character),
+    _ => Err(Error::Value(ErrorCode::InvalidEscapeSequence, format!("The escape of a LIKE pattern must be a single character, found '{}'", escape)))
+  }
+}
+
+// Compiles the pattern of a LIKE operation, if it's a constant (and so is the escape character).
+fn constantLikePattern(pattern: &Expression, escape: Option<&Expression>) -> Option<LikePattern> {
+  let Expression::Literal(Literal::String(pattern))= pattern else {
+    return None
+  };
+
+  let escape= match escape {
+    Some(Expression::Literal(escape)) => Some(escapeCharacter(escape.into( )).ok( )?),
+    Some(_) => return None,
+    None => None
+  };
+
+  LikePattern::compile(pattern, escape).ok( )
+}
+
+/*
+  A compiled LIKE pattern, where % matches any sequence of characters (including an empty one), and
+  _ matches a single character. Matching is case-sensitive.
+
+  An escape character makes the character following it (like % or _) match literally.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct LikePattern {
+  pattern: String,
+  escape: Option<char>,
+
+  elements: Vec<LikePatternElement>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LikePatternElement {
+  Character(char),
+  AnyCharacter,
+  AnySequence
+}
+
+impl LikePattern {
+  pub fn compile(pattern: &str, escape: Option<char>) -> Result<Self> {
+    let mut elements= Vec::new( );
+
+    let mut characters= pattern.chars( );
+    while let Some(character)= characters.next( ) {
+      let element= match character {
+        character if Some(character) == escape => match characters.next( ) {
+          Some(character) => LikePatternElement::Character(character),
+          None => return Err(Error::Value(ErrorCode::InvalidEscapeSequence, format!("LIKE pattern '{}' ends with the escape character", pattern)))
+        },
+
+        '%' => LikePatternElement::AnySequence,
+        '_' => LikePatternElement::AnyCharacter,
+        character => LikePatternElement::Character(character)
+      };
+
+      // Consecutive %s match the same as a single one.
+      if !(element == LikePatternElement::AnySequence && elements.last( ) == Some(&LikePatternElement::AnySequence)) {
+        elements.push(element);}
+    }
+
+    Ok(Self { pattern: pattern.to_string( ), escape, elements })
+  }
+
+  /*
+    Checks whether the value matches the pattern.
+
+    The pattern is matched greedily, remembering the last % seen. When a character doesn't match,
+    the match backtracks to that %, making it consume one more character of the value. Backtracking
+    to an earlier % is never needed, since the later one can consume whatever the earlier one would
+    have.
+  */
+  pub fn matches(&self, value: &str) -> bool {
+    let value: Vec<char>= value.chars( ).collect( );
+
+    let (mut i, mut j)= (0, 0);
+
+    // Position in the pattern after the last %, and the position in the value it's matched up to.
+    let mut backtrack: Option<(usize, usize)>= None;
+
+    while i < value.len( ) {
+      match self.elements.get(j) {
+        Some(LikePatternElement::AnySequence) => {
+          j += 1;
+          backtrack= Some((j, i));
+        },
+
+        Some(LikePatternElement::AnyCharacter) => {
+          i += 1;
+          j += 1;
+        },
+
+        Some(LikePatternElement::Character(character)) if *character == value[i] => {
+          i += 1;
+          j += 1;
+        },
+
+        _ => match &mut backtrack {
+          Some((patternPosition, valuePosition)) => {
+            *valuePosition += 1;
+            (i, j)= (*valuePosition, *patternPosition);
+          },
+
+          None => return false
+        }
+      }
+    }
+
+    self.elements[j..].iter( ).all(|element| *element == LikePatternElement::AnySequence)
+  }
+
+  // Returns the literal characters the pattern starts with, along with whether they're followed
+  // only by a %.
+  pub fn prefix(&self) -> (String, bool) {
+    let mut prefix= String::new( );
+
+    for (position, element) in self.elements.iter( ).enumerate( ) {
+      match element {
+        LikePatternElement::Character(character) => prefix.push(*character),
+        element => return (prefix, *element == LikePatternElement::AnySequence && position == self.elements.len( ) - 1)
+      }
+    }
+
+    (prefix, false)
+  }
+}
+
+// Renders the pattern as SQL (with its ESCAPE clause, if any).
+impl Display for LikePattern {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", Literal::String(self.pattern.clone( )))?;
+
+    match self.escape {
+      Some(escape) => write!(f, " ESCAPE {}", Literal::String(escape.to_string( ))),
+      None => Ok(( ))
+    }
+  }
+}
+
+fn distinctOutsideAggregate(expression: &Expression) -> Error {
+  Error::Value(ErrorCode::GroupingError, format!("{} can only be the argument of an aggregate function", expression))
+}
+
+fn wildcardOutsideSelections(expression: &Expression) -> Error {
+  Error::Value(ErrorCode::SyntaxError, format!("{} can only be used as a selection", expression))
+}
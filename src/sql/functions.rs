@@ -0,0 +1,392 @@
+use crate::result::{Error, ErrorCode, Result};
+use super::{datetime, parser::ast::Expression, types::{DataType, Value, ValueType}};
+
+// A scalar function, which computes a value from the values of its arguments.
+pub struct Function {
+  pub name: &'static str,
+
+  // The minimum and maximum (None for variadic functions) number of arguments.
+  pub minArguments: usize,
+  pub maxArguments: Option<usize>,
+
+  // A volatile function (like RANDOM) may return a different value on every call, even with the same
+  // arguments. So, its calls are never folded into constants.
+  pub volatile: bool,
+
+  evaluate: fn(&'static str, Vec<Value>) -> Result<Value>,
+
+  // Infers the type of the result from the arguments (along with their types), checking that the
+  // arguments are of the expected types.
+  inferType: fn(&'static str, TypedArguments) -> Result<ValueType>
+}
+
+// The arguments of a function call, along with their inferred types.
+pub type TypedArguments<'a>= &'a [(&'a Expression, ValueType)];
+
+// The registry of scalar functions.
+const FUNCTIONS: &[Function]= &[
+  Function { name: "upper", minArguments: 1, maxArguments: Some(1), volatile: false, evaluate: upper, inferType: stringType },
+  Function { name: "lower", minArguments: 1, maxArguments: Some(1), volatile: false, evaluate: lower, inferType: stringType },
+  Function { name: "length", minArguments: 1, maxArguments: Some(1), volatile: false, evaluate: length, inferType: lengthType },
+  Function { name: "substr", minArguments: 2, maxArguments: Some(3), volatile: false, evaluate: substr, inferType: substrType },
+  Function { name: "trim", minArguments: 1, maxArguments: Some(2), volatile: false, evaluate: trim, inferType: stringsType },
+  Function { name: "ltrim", minArguments: 1, maxArguments: Some(2), volatile: false, evaluate: ltrim, inferType: stringsType },
+  Function { name: "rtrim", minArguments: 1, maxArguments: Some(2), volatile: false, evaluate: rtrim, inferType: stringsType },
+  Function { name: "replace", minArguments: 3, maxArguments: Some(3), volatile: false, evaluate: replace, inferType: stringsType },
+  Function { name: "position", minArguments: 2, maxArguments: Some(2), volatile: false, evaluate: position, inferType: positionType },
+  Function { name: "abs", minArguments: 1, maxArguments: Some(1), volatile: false, evaluate: abs, inferType: numericType },
+  Function { name: "round", minArguments: 1, maxArguments: Some(2), volatile: false, evaluate: round, inferType: numericType },
+  Function { name: "coalesce", minArguments: 1, maxArguments: None, volatile: false, evaluate: coalesce, inferType: coalesceType },
+  Function { name: "random", minArguments: 0, maxArguments: Some(0), volatile: true, evaluate: random, inferType: randomType },
+  Function { name: "now", minArguments: 0, maxArguments: Some(0), volatile: true, evaluate: now, inferType: nowType }
+];
+
+// Returns the scalar function with the given name.
+// NOTE : The parser lowercases unquoted names, so upper(x) and UPPER(x) both call upper, while
+// "UPPER"(x) calls no function.
+pub fn getFunction(name: &str) -> Option<&'static Function> {
+  FUNCTIONS.iter( ).find(|function| function.name == name)
+}
+
+impl Function {
+  // Checks whether the function can be called with the given number of arguments.
+  pub fn checkArity(&self, count: usize) -> Result<( )> {
+    let isValid= count >= self.minArguments && self.maxArguments.is_none_or(|maxArguments| count <= maxArguments);
+    if isValid {
+      return Ok(( ))}
+
+    let expected= match self.maxArguments {
+      Some(maxArguments) if maxArguments == self.minArguments => format!("{}", maxArguments),
+      Some(maxArguments) => format!("{} to {}", self.minArguments, maxArguments),
+      None => format!("at least {}", self.minArguments)
+    };
+    Err(Error::Value(ErrorCode::UndefinedFunction, format!("Function {} takes {} arguments, got {}", self.name, expected, count)))
+  }
+
+  pub fn call(&self, arguments: Vec<Value>) -> Result<Value> {
+    self.checkArity(arguments.len( ))?;
+    (self.evaluate)(self.name, arguments)
+  }
+
+  // Infers the type of the result of calling the function with the given arguments (along with
+  // their types).
+  pub fn inferType(&self, arguments: TypedArguments) -> Result<ValueType> {
+    self.checkArity(arguments.len( ))?;
+    (self.inferType)(self.name, arguments)
+  }
+}
+
+fn invalidArgument(function: &str, expected: &str, value: &Value) -> Error {
+  Error::Value(ErrorCode::DatatypeMismatch, format!("Function {} expects {}, got {}", function, expected, value))
+}
+
+fn upper(name: &'static str, mut arguments: Vec<Value>) -> Result<Value> {
+  match arguments.remove(0) {
+    Value::String(value) => Ok(Value::String(value.to_uppercase( ))),
+    Value::Null => Ok(Value::Null),
+    value => Err(invalidArgument(name, "a string", &value))
+  }
+}
+
+fn lower(name: &'static str, mut arguments: Vec<Value>) -> Result<Value> {
+  match arguments.remove(0) {
+    Value::String(value) => Ok(Value::String(value.to_lowercase( ))),
+    Value::Null => Ok(Value::Null),
+    value => Err(invalidArgument(name, "a string", &value))
+  }
+}
+
+// Returns the number of characters (not bytes) in a string.
+fn length(name: &'static str, mut arguments: Vec<Value>) -> Result<Value> {
+  match arguments.remove(0) {
+    Value::String(value) => Ok(Value::Integer(value.chars( ).count( ) as i64)),
+    Value::Null => Ok(Value::Null),
+    value => Err(invalidArgument(name, "a string", &value))
+  }
+}
+
+/*
+  Returns the characters (not bytes) of a string from the given position (counting from 1), upto the
+  given number of them - or upto the end, without a length.
+
+  The positions before the first character still count towards the length, and the ones past the
+  last character are ignored : SUBSTR('abc', 0, 2) is 'a', and SUBSTR('abc', 5) is ''. A negative
+  length is an error.
+*/
+fn substr(name: &'static str, arguments: Vec<Value>) -> Result<Value> {
+  let string= match &arguments[0] {
+    Value::String(string) => string,
+    Value::Null => return Ok(Value::Null),
+    value => return Err(invalidArgument(name, "a string", value))
+  };
+  let start= match &arguments[1] {
+    Value::Integer(start) => *start,
+    Value::Null => return Ok(Value::Null),
+    value => return Err(invalidArgument(name, "an integer position", value))
+  };
+  let length= match arguments.get(2) {
+    None => None,
+    Some(Value::Integer(length)) if *length < 0 =>
+      return Err(Error::Value(ErrorCode::SubstringError, format!("Function {} expects a non-negative length, got {}", name, length))),
+    Some(Value::Integer(length)) => Some(*length),
+    Some(Value::Null) => return Ok(Value::Null),
+    Some(value) => return Err(invalidArgument(name, "an integer length", value))
+  };
+
+  let first= start.max(1);
+  let skipped= usize::try_from(first - 1).unwrap_or(usize::MAX);
+  let taken= match length {
+    Some(length) => usize::try_from(start.saturating_add(length).saturating_sub(first)).unwrap_or(0),
+    None => usize::MAX
+  };
+
+  Ok(Value::String(string.chars( ).skip(skipped).take(taken).collect( )))
+}
+
+// Removes the characters in the given set (spaces, by default) from both ends of a string.
+fn trim(name: &'static str, arguments: Vec<Value>) -> Result<Value> {
+  trimEnds(name, arguments, true, true)
+}
+
+// Removes the characters in the given set (spaces, by default) from the start of a string.
+fn ltrim(name: &'static str, arguments: Vec<Value>) -> Result<Value> {
+  trimEnds(name, arguments, true, false)
+}
+
+// Removes the characters in the given set (spaces, by default) from the end of a string.
+fn rtrim(name: &'static str, arguments: Vec<Value>) -> Result<Value> {
+  trimEnds(name, arguments, false, true)
+}
+
+fn trimEnds(name: &'static str, arguments: Vec<Value>, start: bool, end: bool) -> Result<Value> {
+  let string= match &arguments[0] {
+    Value::String(string) => string,
+    Value::Null => return Ok(Value::Null),
+    value => return Err(invalidArgument(name, "a string", value))
+  };
+  let characters: Vec<char>= match arguments.get(1) {
+    None => vec![' '],
+    Some(Value::String(characters)) => characters.chars( ).collect( ),
+    Some(Value::Null) => return Ok(Value::Null),
+    Some(value) => return Err(invalidArgument(name, "a string of the characters to trim", value))
+  };
+
+  let mut trimmed= string.as_str( );
+  if start {
+    trimmed= trimmed.trim_start_matches(characters.as_slice( ));}
+  if end {
+    trimmed= trimmed.trim_end_matches(characters.as_slice( ));}
+
+  Ok(Value::String(trimmed.to_string( )))
+}
+
+// Replaces every occurrence of a substring in a string with another string. An empty substring
+// occurs nowhere (but the result is still NULL, if any of the arguments is).
+fn replace(name: &'static str, arguments: Vec<Value>) -> Result<Value> {
+  match (&arguments[0], &arguments[1], &arguments[2]) {
+    (Value::String(string), Value::String(from), Value::String(_)) if from.is_empty( ) => Ok(Value::String(string.clone( ))),
+    (Value::String(string), Value::String(from), Value::String(to)) => Ok(Value::String(string.replace(from.as_str( ), to))),
+
+    _ => match arguments.iter( ).find(|argument| !matches!(argument, Value::String(_) | Value::Null)) {
+      Some(value) => Err(invalidArgument(name, "strings", value)),
+      None => Ok(Value::Null)
+    }
+  }
+}
+
+/*
+  Returns the position (counting characters from 1) of the first occurrence of a substring in a
+  string, or 0 if it doesn't occur. An empty substring occurs at 1.
+
+  Also called as POSITION(substring IN string), which the parser turns into POSITION(substring,
+  string).
+*/
+fn position(name: &'static str, arguments: Vec<Value>) -> Result<Value> {
+  match (&arguments[0], &arguments[1]) {
+    (Value::String(substring), Value::String(string)) =>
+      Ok(Value::Integer(string.find(substring.as_str( )).map_or(0, |index| string[..index].chars( ).count( ) as i64 + 1))),
+
+    _ => match arguments.iter( ).find(|argument| !matches!(argument, Value::String(_) | Value::Null)) {
+      Some(value) => Err(invalidArgument(name, "strings", value)),
+      None => Ok(Value::Null)
+    }
+  }
+}
+
+fn abs(name: &'static str, mut arguments: Vec<Value>) -> Result<Value> {
+  match arguments.remove(0) {
+    Value::Integer(value) => value.checked_abs( )
+                                  .map(Value::Integer)
+                                  .ok_or_else(| | Error::Value(ErrorCode::NumericValueOutOfRange, "Integer overflow".to_string( ))),
+    Value::Float(value) => Ok(Value::Float(value.abs( ))),
+    Value::Null => Ok(Value::Null),
+    value => Err(invalidArgument(name, "a number", &value))
+  }
+}
+
+/*
+  Rounds a number to the given number of decimal places (0 by default), rounding halfway cases away
+  from zero. A negative precision rounds to the left of the decimal point : ROUND(1234, -2) is 1200.
+
+  Integers stay integers, and floats stay floats.
+*/
+fn round(name: &'static str, mut arguments: Vec<Value>) -> Result<Value> {
+  let precision= match arguments.get(1) {
+    None => 0,
+    Some(Value::Integer(precision)) => *precision,
+    Some(Value::Null) => return Ok(Value::Null),
+    Some(value) => return Err(invalidArgument(name, "an integer precision", value))
+  };
+
+  match arguments.remove(0) {
+    Value::Integer(value) if precision >= 0 => Ok(Value::Integer(value)),
+    Value::Integer(value) => {
+      // Rounding to more places than an i64 has, always results in 0.
+      let Some(factor)= u32::try_from(-precision).ok( ).and_then(|exponent| 10i64.checked_pow(exponent)) else {
+        return Ok(Value::Integer(0))
+      };
+
+      let remainder= value % factor;
+      let truncated= value - remainder;
+
+      match remainder.unsigned_abs( ) * 2 >= factor as u64 {
+        true => truncated.checked_add(value.signum( ) * factor)
+                         .map(Value::Integer)
+                         .ok_or_else(| | Error::Value(ErrorCode::NumericValueOutOfRange, "Integer overflow".to_string( ))),
+        false => Ok(Value::Integer(truncated))
+      }
+    },
+
+    Value::Float(value) if !value.is_finite( ) => Ok(Value::Float(value)),
+    Value::Float(value) => {
+      let factor= 10f64.powi(precision.clamp(i32::MIN as i64, i32::MAX as i64) as i32);
+
+      match factor.is_finite( ) && factor != 0.0 {
+        true if (value * factor).is_finite( ) => Ok(Value::Float((value * factor).round( ) / factor)),
+
+        // The precision is beyond what a float can represent.
+        _ if precision > 0 => Ok(Value::Float(value)),
+        _ => Ok(Value::Float(0.0))
+      }
+    },
+
+    Value::Null => Ok(Value::Null),
+    value => Err(invalidArgument(name, "a number", &value))
+  }
+}
+
+// Returns the first non-NULL argument, or NULL if all of them are NULL.
+fn coalesce(_: &'static str, arguments: Vec<Value>) -> Result<Value> {
+  Ok(arguments.into_iter( )
+              .find(|argument| !argument.isNull( ))
+              .unwrap_or(Value::Null))
+}
+
+// Returns a random float, uniformly distributed in [0, 1).
+fn random(_: &'static str, _: Vec<Value>) -> Result<Value> {
+  Ok(Value::Float(rand::random::<f64>( )))
+}
+
+/*
+  Returns the current time, as per the local clock.
+
+  NOTE : The calls to NOW( ) in a statement are bound to the time before it's planned (see
+  Statement::bindNow), so this is only evaluated for a column default - once for every inserted row,
+  by the node executing the INSERT.
+*/
+fn now(_: &'static str, _: Vec<Value>) -> Result<Value> {
+  Ok(Value::Timestamp(datetime::currentTimestamp( )))
+}
+
+// Checks whether an argument is of the expected type, when inferring the type of a function call.
+fn checkArgument(function: &str, expected: &str, (argument, valueType): (&Expression, ValueType), isValid: bool) -> Result<( )> {
+  if isValid {
+    return Ok(( ))}
+
+  Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Function {} expects {}, got {} ({})", function, expected, argument, valueType)))
+}
+
+// A string function (like UPPER) results in a string.
+fn stringType(name: &'static str, arguments: TypedArguments) -> Result<ValueType> {
+  let (_, valueType)= arguments[0];
+  checkArgument(name, "a string", arguments[0], valueType.isCompatibleWith(DataType::String))?;
+
+  Ok(ValueType { dataType: Some(DataType::String), ..valueType })
+}
+
+// A string function of string arguments only (like REPLACE) results in a string, which is NULL if any
+// argument is.
+fn stringsType(name: &'static str, arguments: TypedArguments) -> Result<ValueType> {
+  let mut nullable= false;
+  for &(argument, valueType) in arguments {
+    checkArgument(name, "a string", (argument, valueType), valueType.isCompatibleWith(DataType::String))?;
+    nullable |= valueType.nullable;
+  }
+
+  Ok(ValueType::new(DataType::String, nullable))
+}
+
+// The position and the length of SUBSTR must be integers.
+fn substrType(name: &'static str, arguments: TypedArguments) -> Result<ValueType> {
+  let (_, valueType)= arguments[0];
+  checkArgument(name, "a string", arguments[0], valueType.isCompatibleWith(DataType::String))?;
+
+  let mut nullable= valueType.nullable;
+  for (&(argument, argumentType), expected) in arguments[1..].iter( ).zip(["an integer position", "an integer length"]) {
+    checkArgument(name, expected, (argument, argumentType), argumentType.isCompatibleWith(DataType::Integer))?;
+    nullable |= argumentType.nullable;
+  }
+
+  Ok(ValueType::new(DataType::String, nullable))
+}
+
+fn positionType(name: &'static str, arguments: TypedArguments) -> Result<ValueType> {
+  let ValueType { nullable, .. }= stringsType(name, arguments)?;
+  Ok(ValueType::new(DataType::Integer, nullable))
+}
+
+fn lengthType(name: &'static str, arguments: TypedArguments) -> Result<ValueType> {
+  let (_, valueType)= arguments[0];
+  checkArgument(name, "a string", arguments[0], valueType.isCompatibleWith(DataType::String))?;
+
+  Ok(ValueType { dataType: Some(DataType::Integer), ..valueType })
+}
+
+// A numeric function (like ABS) results in a number of the same type as its first argument. The
+// other argument (the precision of ROUND) must be an integer.
+fn numericType(name: &'static str, arguments: TypedArguments) -> Result<ValueType> {
+  let (_, valueType)= arguments[0];
+  checkArgument(name, "a number", arguments[0], valueType.isNumeric( ))?;
+
+  let mut nullable= valueType.nullable;
+  for &(precision, precisionType) in &arguments[1..] {
+    checkArgument(name, "an integer precision", (precision, precisionType), precisionType.isCompatibleWith(DataType::Integer))?;
+    nullable |= precisionType.nullable;
+  }
+
+  Ok(ValueType { nullable, ..valueType })
+}
+
+// The arguments of COALESCE must be of compatible types. The result is NULL, only if all of them are.
+fn coalesceType(name: &'static str, arguments: TypedArguments) -> Result<ValueType> {
+  let (_, mut resultType)= arguments[0];
+
+  for &(argument, valueType) in &arguments[1..] {
+    resultType= resultType.unify(&valueType)
+                          .map(|unified| ValueType { nullable: resultType.nullable && valueType.nullable, ..unified })
+                          .ok_or_else(| | Error::Value(ErrorCode::DatatypeMismatch, format!(
+                            "Function {} expects arguments of compatible types, got {} ({}) after arguments of type {}",
+                            name, argument, valueType, resultType
+                          )))?;
+  }
+
+  Ok(resultType)
+}
+
+fn randomType(_: &'static str, _: TypedArguments) -> Result<ValueType> {
+  Ok(ValueType::new(DataType::Float, false))
+}
+
+fn nowType(_: &'static str, _: TypedArguments) -> Result<ValueType> {
+  Ok(ValueType::new(DataType::Timestamp, false))
+}
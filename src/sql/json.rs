@@ -0,0 +1,91 @@
+use super::{execution::Columns, types::{Row, Value}};
+
+// The largest magnitude upto which every integer is exactly representable as a JSON number (as most
+// JSON parsers read numbers into doubles).
+const MAX_SAFE_INTEGER: i64= (1 << 53) - 1;
+
+/*
+  Formats the rows of a query as a JSON array of objects, each mapping the column labels (unlabeled
+  columns get ?) to the values of a row :
+
+    [
+      {"id": 1, "name": "Alice", "active": true},
+      {"id": 2, "name": null, "active": false}
+    ]
+
+  NULL is null, booleans are booleans and numbers are numbers - except for the values JSON numbers
+  can't hold : integers beyond +-(2^53 - 1) (which would lose precision) are strings of their
+  digits, and NaN and the infinities are the strings "NaN", "Infinity" and "-Infinity".
+
+  NOTE : Columns with the same label (like the id columns of two joined tables) result in duplicate
+  keys, which most JSON parsers resolve to the last one.
+*/
+pub fn formatRows(columns: &Columns, rows: &[Row]) -> String {
+  if rows.is_empty( ) {
+    return "[]".to_string( )}
+
+  let keys= formatKeys(columns);
+  let objects= rows.iter( ).map(|row| formatObject(&keys, row));
+
+  format!("[\n{}\n]", objects.collect::<Vec<_>>( ).join(",\n"))
+}
+
+// Formats the column labels as the keys of the objects (see formatObject( )).
+pub fn formatKeys(columns: &Columns) -> Vec<String> {
+  columns.iter( )
+         .map(|column| formatString(column.label.as_deref( ).unwrap_or("?")))
+         .collect( )
+}
+
+// Formats a row as an (indented) object of the array formatRows( ) formats, so that the rows can be
+// formatted as they arrive.
+pub fn formatObject(keys: &[String], row: &Row) -> String {
+  let members= keys.iter( )
+                   .zip(row)
+                   .map(|(key, value)| format!("{}: {}", key, formatValue(value)))
+                   .collect::<Vec<_>>( );
+  format!("  {{{}}}", members.join(", "))
+}
+
+fn formatValue(value: &Value) -> String {
+  match value {
+    Value::Null => "null".to_string( ),
+    Value::Boolean(value) => value.to_string( ),
+
+    Value::Integer(value) if value.unsigned_abs( ) > MAX_SAFE_INTEGER as u64 => format!("\"{}\"", value),
+    Value::Integer(value) => value.to_string( ),
+
+    Value::Float(value) if value.is_nan( ) => "\"NaN\"".to_string( ),
+    Value::Float(value) if value.is_infinite( ) => match value.is_sign_positive( ) {
+      true => "\"Infinity\"".to_string( ),
+      false => "\"-Infinity\"".to_string( )
+    },
+    Value::Float(value) => value.to_string( ),
+
+    Value::String(value) => formatString(value),
+
+    // Dates and timestamps are formatted as strings, since JSON has no type for them.
+    value @ (Value::Date(_) | Value::Timestamp(_)) => formatString(&value.to_string( ))
+  }
+}
+
+// Formats a JSON string, escaping the quotes, backslashes and control characters.
+fn formatString(value: &str) -> String {
+  let mut string= String::with_capacity(value.len( ) + 2);
+  string.push('"');
+
+  for character in value.chars( ) {
+    match character {
+      '"' => string.push_str("\\\""),
+      '\\' => string.push_str("\\\\"),
+      '\n' => string.push_str("\\n"),
+      '\r' => string.push_str("\\r"),
+      '\t' => string.push_str("\\t"),
+      character if (character as u32) < 0x20 => string.push_str(&format!("\\u{:04x}", character as u32)),
+      character => string.push(character)
+    }
+  }
+
+  string.push('"');
+  string
+}
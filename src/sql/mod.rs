@@ -1 +1,13 @@
-mod parser;
\ No newline at end of file
+pub mod parser;
+pub mod types;
+pub mod datetime;
+pub mod numeric;
+pub mod expression;
+pub mod functions;
+pub mod schema;
+pub mod stats;
+pub mod engine;
+pub mod plan;
+pub mod execution;
+pub mod csv;
+pub mod json;
@@ -0,0 +1,221 @@
+use std::cmp::Ordering;
+use crate::result::{Error, ErrorCode, Result};
+use super::types::{compareFloats, Value};
+
+/*
+  The semantics of the numeric values : INTEGERs (i64) and FLOATs (f64). They're shared by the
+  arithmetic and comparison operators, the aggregates, the hash joins and the numeric literals.
+
+    Integer arithmetic is checked. A result out of the range of i64 is an error naming the operation
+    (like for 9223372036854775807 + 1), rather than wrapping around.
+
+    Integer division truncates towards zero, and the remainder takes the sign of the dividend (like
+    Rust's / and %) : -7 / 2 is -3 and -7 % 2 is -1, while 7 % -2 is 1. So, (a / b) * b + a % b is
+    always a. Dividing (or taking the remainder) by zero is an error, for floats as well.
+
+    An integer raised to a non-negative integer power (which fits in a u32) is an integer. Otherwise
+    (for a negative or a huge exponent) it's a float.
+
+    The factorial is defined for the integers from 0 to 20 (21! doesn't fit in i64).
+
+    Mixing an integer with a float (in arithmetic) promotes the integer to the nearest float. But
+    comparing an integer with a float is exact : 9007199254740993 (2^53 + 1) is greater than
+    9007199254740992.0, even though it converts to that float.
+
+  NULL operands result in NULL.
+*/
+
+// 2^63, exactly. The floats in [-2^63, 2^63) have their integral parts in the range of i64.
+const I64_BOUND: f64= 9_223_372_036_854_775_808.0;
+
+// The binary arithmetic operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+  Add,
+  Subtract,
+  Multiply,
+  Divide,
+  Remainder,
+  Exponentiate
+}
+
+impl Operator {
+  fn symbol(self) -> &'static str {
+    match self {
+      Self::Add => "+",
+      Self::Subtract => "-",
+      Self::Multiply => "*",
+      Self::Divide => "/",
+      Self::Remainder => "%",
+      Self::Exponentiate => "^"
+    }
+  }
+
+  // The error for operands which aren't numeric.
+  fn datatypeMismatch(self, lhs: &Value, rhs: &Value) -> Error {
+    let message= match self {
+      Self::Add => format!("Can't add {} and {}", lhs, rhs),
+      Self::Subtract => format!("Can't subtract {} from {}", rhs, lhs),
+      Self::Multiply => format!("Can't multiply {} and {}", lhs, rhs),
+      Self::Divide => format!("Can't divide {} by {}", lhs, rhs),
+      Self::Remainder => format!("Can't find the remainder of {} divided by {}", lhs, rhs),
+      Self::Exponentiate => format!("Can't raise {} to the power of {}", lhs, rhs)
+    };
+    Error::Value(ErrorCode::DatatypeMismatch, message)
+  }
+}
+
+pub fn add(lhs: Value, rhs: Value) -> Result<Value> {
+  apply(Operator::Add, lhs, rhs)
+}
+
+pub fn subtract(lhs: Value, rhs: Value) -> Result<Value> {
+  apply(Operator::Subtract, lhs, rhs)
+}
+
+pub fn multiply(lhs: Value, rhs: Value) -> Result<Value> {
+  apply(Operator::Multiply, lhs, rhs)
+}
+
+pub fn divide(lhs: Value, rhs: Value) -> Result<Value> {
+  apply(Operator::Divide, lhs, rhs)
+}
+
+pub fn remainder(lhs: Value, rhs: Value) -> Result<Value> {
+  apply(Operator::Remainder, lhs, rhs)
+}
+
+pub fn exponentiate(lhs: Value, rhs: Value) -> Result<Value> {
+  apply(Operator::Exponentiate, lhs, rhs)
+}
+
+pub fn negate(operand: Value) -> Result<Value> {
+  Ok(match operand {
+    Value::Integer(value) =>
+      Value::Integer(value.checked_neg( ).ok_or_else(|| integerOverflow(format!("-({})", value)))?),
+    Value::Float(value) => Value::Float(-value),
+    Value::Null => Value::Null,
+
+    value => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't negate {}", value)))
+  })
+}
+
+// NOTE : The factorial of a negative number isn't defined (like for -5!, which is parsed as (-5)!).
+pub fn factorial(operand: Value) -> Result<Value> {
+  Ok(match operand {
+    Value::Integer(value) if value < 0 =>
+      return Err(Error::Value(ErrorCode::InvalidParameterValue, format!("Can't take the factorial of negative number {}", value))),
+    Value::Integer(value) if value > 20 =>
+      return Err(Error::Value(ErrorCode::NumericValueOutOfRange, format!("Factorial of {} is out of range (the largest one is of 20)", value))),
+
+    Value::Integer(value) => Value::Integer((1..=value).product( )),
+    Value::Null => Value::Null,
+
+    value => return Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Can't take the factorial of {}", value)))
+  })
+}
+
+/*
+  Compares an integer with a float exactly. Returns None, if the float is NaN.
+
+  Converting the integer to a float would round it (above 2^53). So instead, the float is checked
+  against the range of i64 first. And within that range, its integral part converts to an i64
+  exactly : the integer is compared with it, and then (if they're equal) with its fraction.
+*/
+pub fn compareIntegerWithFloat(integer: i64, float: f64) -> Option<Ordering> {
+  if float.is_nan( ) {
+    return None}
+
+  if float >= I64_BOUND {
+    return Some(Ordering::Less)}
+  if float < -I64_BOUND {
+    return Some(Ordering::Greater)}
+
+  let integral= float.trunc( );
+  Some(integer.cmp(&(integral as i64)).then_with(|| compareFloats(0.0, float - integral)))
+}
+
+// Returns the integer equal to the float, if any.
+pub fn integralFloat(float: f64) -> Option<i64> {
+  (float.fract( ) == 0.0 && (-I64_BOUND..I64_BOUND).contains(&float)).then_some(float as i64)
+}
+
+// Returns the float equal to the integer, if any (the integers above 2^53 aren't all representable).
+pub fn exactFloat(integer: i64) -> Option<f64> {
+  let float= integer as f64;
+  (compareIntegerWithFloat(integer, float) == Some(Ordering::Equal)).then_some(float)
+}
+
+/*
+  Parses a numeric literal : it's a float if it has a fraction or an exponent, and an integer
+  otherwise.
+
+  NOTE : The lexer has validated the number already (checking that it's in range), and converted a
+  hexadecimal integer to decimal. An integer literal beyond the range of i64 is an error, rather
+  than becoming a float. So, -9223372036854775808 can't be written as a literal (since it's parsed
+  as -(9223372036854775808)).
+*/
+pub fn parseNumber(number: &str) -> Result<Value> {
+  Ok(match number.contains(['.', 'e', 'E']) {
+    true => Value::Float(number.parse( )?),
+    false => Value::Integer(number.parse( )?)
+  })
+}
+
+fn apply(operator: Operator, lhs: Value, rhs: Value) -> Result<Value> {
+  Ok(match (lhs, rhs) {
+    (Value::Integer(lhs), Value::Integer(rhs)) => applyToIntegers(operator, lhs, rhs)?,
+    (Value::Integer(lhs), Value::Float(rhs)) => Value::Float(applyToFloats(operator, lhs as f64, rhs)?),
+    (Value::Float(lhs), Value::Integer(rhs)) => Value::Float(applyToFloats(operator, lhs, rhs as f64)?),
+    (Value::Float(lhs), Value::Float(rhs)) => Value::Float(applyToFloats(operator, lhs, rhs)?),
+
+    (Value::Null, Value::Integer(_) | Value::Float(_) | Value::Null)
+    | (Value::Integer(_) | Value::Float(_), Value::Null) => Value::Null,
+
+    (lhs, rhs) => return Err(operator.datatypeMismatch(&lhs, &rhs))
+  })
+}
+
+fn applyToIntegers(operator: Operator, lhs: i64, rhs: i64) -> Result<Value> {
+  let result= match operator {
+    Operator::Add => lhs.checked_add(rhs),
+    Operator::Subtract => lhs.checked_sub(rhs),
+    Operator::Multiply => lhs.checked_mul(rhs),
+
+    Operator::Divide | Operator::Remainder if rhs == 0 => return Err(divisionByZero( )),
+    Operator::Divide => lhs.checked_div(rhs),
+    // The remainder is never out of range. But checked_rem( ) returns None for i64::MIN % -1 (whose
+    // remainder is 0), since the quotient is.
+    Operator::Remainder => Some(lhs.wrapping_rem(rhs)),
+
+    Operator::Exponentiate => match u32::try_from(rhs) {
+      Ok(exponent) => lhs.checked_pow(exponent),
+      Err(_) => return Ok(Value::Float((lhs as f64).powf(rhs as f64)))
+    }
+  };
+
+  result.map(Value::Integer)
+        .ok_or_else(|| integerOverflow(format!("{} {} {}", lhs, operator.symbol( ), rhs)))
+}
+
+fn applyToFloats(operator: Operator, lhs: f64, rhs: f64) -> Result<f64> {
+  Ok(match operator {
+    Operator::Add => lhs + rhs,
+    Operator::Subtract => lhs - rhs,
+    Operator::Multiply => lhs * rhs,
+
+    Operator::Divide | Operator::Remainder if rhs == 0.0 => return Err(divisionByZero( )),
+    Operator::Divide => lhs / rhs,
+    Operator::Remainder => lhs % rhs,
+
+    Operator::Exponentiate => lhs.powf(rhs)
+  })
+}
+
+fn integerOverflow(operation: String) -> Error {
+  Error::Value(ErrorCode::NumericValueOutOfRange, format!("Integer overflow in {}", operation))
+}
+
+fn divisionByZero( ) -> Error {
+  Error::Value(ErrorCode::DivisionByZero, "Division by zero".to_string( ))
+}
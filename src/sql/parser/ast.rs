@@ -1,24 +1,72 @@
-use std::{collections::BTreeMap, default};
+use std::{collections::{BTreeMap, HashSet}, default, fmt::Display, sync::Arc};
+use crate::{result::{Error, ErrorCode, Result}, sql::{datetime, expression::LikePattern, schema::ReferentialAction, types::Value}};
+use super::{operators::Associativity, token::Keyword};
 
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum Statement {
   Begin {
     readonly: bool,
     asOfVersion: Option<u64>
   },
 
+  // With IF NOT EXISTS, creating a table which already exists does nothing. And with IF EXISTS,
+  // dropping a table which doesn't exist does nothing.
   CreateTable {
     name: String,
-    columns: Vec<Column>
+    columns: Vec<Column>,
+    ifNotExists: bool
   },
-  DropTable(String),
+  DropTable {
+    name: String,
+    ifExists: bool
+  },
+
+  // Creates an index on the values of an expression computed from the columns of a table (which can
+  // be just a column).
+  // NOTE : Its expression isn't transformed by transformExpressions( ), so that parameters in it are
+  // left to be rejected when the statement is planned.
+  CreateIndex {
+    name: String,
+    table: String,
+    expression: Expression
+  },
+
+  AlterTable {
+    name: String,
+    action: AlterTableAction
+  },
+
+  // Lists the names of the tables.
+  ShowTables,
+
+  // Describes the columns of a table.
+  DescribeTable(String),
+
+  // Returns the status of the engine (and of the Raft node serving it).
+  Status,
+
+  // Sets a variable of the session (like statement_timeout) to the value of a constant expression.
+  Set {
+    variable: String,
+    value: Expression
+  },
+
+  // Returns the value of a variable of the session, or (for SHOW ALL) the values and the defaults of
+  // all of them.
+  ShowVariable(String),
+  ShowVariables,
 
   Insert {
     table: String,
     columns: Option<Vec<String>>,
-    values: Vec<Vec<Expression>>
+    source: InsertSource,
+    onConflict: Option<OnConflict>
   },
   Select {
-    selections: Vec<(Expression, Option<AliasColumnName>)>,
+    // Whether duplicate rows are removed from the result (SELECT DISTINCT).
+    distinct: bool,
+    selections: Selections,
     from: Vec<SearchField>,
     r#where: Option<Expression>,
     groupBy: Vec<Expression>,
@@ -37,14 +85,37 @@ pub enum Statement {
     table: String,
     r#where: Option<Expression>
   },
+  // Deletes all the rows of a table at once.
+  Truncate(String),
+
+  // Collects the statistics of a table (or of every table, if none is given) for the planner.
+  Analyze(Option<String>),
+
+  // Bulk loads rows into a table. The rows are sent separately (as CSV lines), after the statement
+  // is executed.
+  Copy {
+    table: String
+  },
 
   Commit,
   Rollback,
 
-  Explain(Box<Statement>)
+  // With ANALYZE, the statement is executed as well, and the plan is annotated with the measured
+  // execution statistics.
+  Explain {
+    statement: Box<Statement>,
+    analyze: bool
+  }
 }
 
-#[derive(Default)]
+// A change of a table's schema, made by ALTER TABLE.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterTableAction {
+  RenameTo(String),
+  RenameColumn { column: String, newName: String }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Column {
   pub name: String,
   pub dataType: DataType,
@@ -57,28 +128,56 @@ pub struct Column {
   pub primaryKey: bool,
   pub index: bool,
 
-  pub references: Option<String>
+  pub references: Option<String>,
+  pub onDelete: ReferentialAction
 }
 
-#[derive(Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum DataType {
   Boolean,
   Integer,
   Float,
   String,
+  Date,
+  Timestamp,
 
   #[default]
   Phantom
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
   Field(Option<String>, String),
   Literal(Literal),
   FunctionCall(String, Vec<Expression>),
   Operation(Operation),
 
-  // Only used during the planning stage - to break off expression subtrees.
-  Column,
+  // A SELECT statement nested in the expression. Used as a scalar value (it must return a single
+  // column and at most one row), or as the set of values on the right side of IN.
+  Subquery(Box<Statement>),
+
+  // Only used during the execution stage - the values of an IN subquery, which are computed once and
+  // probed for every row.
+  ValueSet(Arc<HashSet<Value>>),
+
+  // A parameter placeholder of a prepared statement, holding its number (starting from 1). It's
+  // substituted with the bound value, before the statement is planned.
+  Parameter(u32),
+
+  // The argument of an aggregate function, which only aggregates its distinct values (like in
+  // COUNT(DISTINCT x)). It can't be used anywhere else.
+  Distinct(Box<Expression>),
+
+  // All the columns of the named table (or alias), like in SELECT t.*. It can only be used as a
+  // selection, and is expanded into the columns during planning.
+  QualifiedWildcard(String),
+
+  // Only used during the planning stage - a field resolved to the index of the column in the row.
+  Column(usize),
+
+  // Only used during the execution stage - a LIKE pattern, which is compiled once and matched against
+  // every row.
+  LikePattern(Arc<LikePattern>),
 }
 
 impl From<Literal> for Expression {
@@ -93,14 +192,37 @@ impl From<Operation> for Expression {
   }
 }
 
+#[derive(Debug, Clone)]
 pub enum Literal {
   Null,
   Boolean(bool),
   Integer(i64),
   Float(f64),
   String(String),
+
+  // Typed literals (like DATE '2024-01-15'), stored like the corresponding values.
+  Date(i64),
+  Timestamp(i64)
 }
 
+// NaN literals are equal to each other, so that equality of ASTs is deterministic.
+impl PartialEq for Literal {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::Null, Self::Null) => true,
+      (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs == rhs,
+      (Self::Integer(lhs), Self::Integer(rhs)) => lhs == rhs,
+      (Self::Float(lhs), Self::Float(rhs)) => lhs == rhs || (lhs.is_nan( ) && rhs.is_nan( )),
+      (Self::String(lhs), Self::String(rhs)) => lhs == rhs,
+      (Self::Date(lhs), Self::Date(rhs)) => lhs == rhs,
+      (Self::Timestamp(lhs), Self::Timestamp(rhs)) => lhs == rhs,
+
+      _ => false
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Operation {
   // Done by logical operators.
   And(Box<Expression>, Box<Expression>),
@@ -127,10 +249,24 @@ pub enum Operation {
   Negate(Box<Expression>),
   Subtract(Box<Expression>, Box<Expression>),
 
-  // Done by string operators.
-  Like(Box<Expression>, Box<Expression>),
+  // Done by string operators. The optional operand of LIKE is the escape character of the pattern.
+  Concatenate(Box<Expression>, Box<Expression>),
+  Like(Box<Expression>, Box<Expression>, Option<Box<Expression>>),
+
+  // Checks whether the value is one of the values of a subquery.
+  In(Box<Expression>, Box<Expression>),
+
+  // Results in the result of the first branch whose condition holds, or else in the otherwise
+  // result (NULL, if there's none). With an operand (CASE <operand> WHEN ...), the condition of a
+  // branch is a value, which the operand is compared with for equality.
+  Case {
+    operand: Option<Box<Expression>>,
+    branches: Vec<(Expression, Expression)>,
+    otherwise: Option<Box<Expression>>
+  },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Order {
   Ascending,
   Descending,
@@ -138,6 +274,32 @@ pub enum Order {
 
 pub type AliasColumnName= String;
 
+// The selected expressions of a SELECT statement, along with their optional labels.
+pub type Selections= Vec<(Expression, Option<AliasColumnName>)>;
+
+// The rows inserted by an INSERT statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InsertSource {
+  Values(Vec<Vec<Expression>>),
+  Select(Box<Statement>)
+}
+
+/*
+  The ON CONFLICT (<column>) clause of an INSERT statement : a row whose value in the column (the
+  primary key or a unique column) already exists in the table isn't inserted. Instead, it's skipped
+  (DO NOTHING), or the existing row is updated (DO UPDATE SET ...).
+
+  The updates can reference the columns of the existing row, and those of the row which wasn't
+  inserted - qualified by excluded.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnConflict {
+  pub column: String,
+  // None for DO NOTHING.
+  pub updates: Option<BTreeMap<String, Expression>>
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum SearchField {
   Table {
     name: String,
@@ -147,13 +309,624 @@ pub enum SearchField {
     left: Box<SearchField>,
     right: Box<SearchField>,
     r#type: JoinType,
-    predicate: Option<Expression>
+    constraint: JoinConstraint
   }
 }
 
+// How the rows of the two sides of a join are matched.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinConstraint {
+  // A CROSS JOIN matches every pair of rows.
+  None,
+
+  On(Expression),
+
+  // The rows whose values in the named columns (present on both sides) are equal. The columns are
+  // emitted only once, by a SELECT *.
+  Using(Vec<String>),
+
+  // Like USING the columns whose names are shared by both sides.
+  Natural
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum JoinType {
   Cross,
   Inner,
   Left,
   Right,
-}
\ No newline at end of file
+}
+
+// Renders the expression as SQL.
+impl Display for Expression {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Field(Some(table), column) => write!(f, "{}.{}", Identifier(table), Identifier(column)),
+      Self::Field(None, column) => write!(f, "{}", Identifier(column)),
+
+      Self::Literal(literal) => write!(f, "{}", literal),
+
+      Self::FunctionCall(name, arguments) => {
+        write!(f, "{}(", Identifier(name))?;
+        for (index, argument) in arguments.iter( ).enumerate( ) {
+          if index > 0 {
+            write!(f, ", ")?;}
+          write!(f, "{}", argument)?;
+        }
+        write!(f, ")")
+      },
+
+      Self::Operation(operation) => write!(f, "{}", operation),
+
+      Self::Subquery(statement) => write!(f, "({})", statement),
+
+      Self::ValueSet(values) => {
+        let mut values= values.iter( ).collect::<Vec<_>>( );
+        values.sort( );
+
+        write!(f, "(")?;
+        writeList(f, values.into_iter( ).map(|value| Literal::from(value.clone( ))))?;
+        write!(f, ")")
+      },
+
+      Self::Parameter(number) => write!(f, "${}", number),
+
+      Self::Distinct(expression) => write!(f, "DISTINCT {}", expression),
+
+      Self::QualifiedWildcard(table) => write!(f, "{}.*", Identifier(table)),
+
+      Self::Column(index) => write!(f, "#{}", index),
+
+      Self::LikePattern(pattern) => write!(f, "{}", pattern)
+    }
+  }
+}
+
+impl Display for Literal {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Null => write!(f, "NULL"),
+      Self::Boolean(true) => write!(f, "TRUE"),
+      Self::Boolean(false) => write!(f, "FALSE"),
+      Self::Integer(value) => write!(f, "{}", value),
+
+      Self::Float(value) if value.is_nan( ) => write!(f, "NAN"),
+      Self::Float(value) if value.is_infinite( ) && value.is_sign_positive( ) => write!(f, "INFINITY"),
+      Self::Float(value) if value.is_infinite( ) => write!(f, "-INFINITY"),
+      Self::Float(value) => write!(f, "{:?}", value),
+
+      Self::String(value) => write!(f, "'{}'", value.replace('\'', "''")),
+
+      Self::Date(value) => write!(f, "DATE '{}'", datetime::formatDate(*value)),
+      Self::Timestamp(value) => write!(f, "TIMESTAMP '{}'", datetime::formatTimestamp(*value))
+    }
+  }
+}
+
+impl Statement {
+  // Returns whether the statement only reads data (so it can run in a read-only transaction).
+  // NOTE : EXPLAIN ANALYZE executes the explained statement, which may write.
+  pub fn isReadOnly(&self) -> bool {
+    matches!(self, Self::Select { .. } | Self::Explain { .. } | Self::ShowTables | Self::DescribeTable(_))
+  }
+
+  // Transforms every expression in the statement bottom up (including the ones in subqueries, which
+  // Expression::transform doesn't go into).
+  pub fn transformExpressions(self, transformer: &mut dyn FnMut(Expression) -> Result<Expression>) -> Result<Statement> {
+    let mut transform= |expression: Expression| expression.transform(&mut |expression| match expression {
+      Expression::Subquery(statement) => {
+        let statement= statement.transformExpressions(transformer)?;
+        transformer(Expression::Subquery(Box::new(statement)))
+      },
+      expression => transformer(expression)
+    });
+
+    Ok(match self {
+      Self::CreateTable { name, columns, ifNotExists } => Self::CreateTable {
+        name,
+        columns: columns.into_iter( )
+                        .map(|column| Ok(Column { default: column.default.map(&mut transform).transpose( )?, ..column }))
+                        .collect::<Result<_>>( )?,
+        ifNotExists
+      },
+
+      Self::Insert { table, columns, source, onConflict } => {
+        let onConflict= match onConflict {
+          Some(OnConflict { column, updates: Some(updates) }) => Some(OnConflict {
+            column,
+            updates: Some(updates.into_iter( )
+                                 .map(|(column, expression)| Ok((column, transform(expression)?)))
+                                 .collect::<Result<_>>( )?)
+          }),
+          onConflict => onConflict
+        };
+
+        Self::Insert {
+          table,
+          columns,
+          source: match source {
+            InsertSource::Values(rows) => InsertSource::Values(
+              rows.into_iter( )
+                  .map(|row| row.into_iter( ).map(&mut transform).collect::<Result<_>>( ))
+                  .collect::<Result<_>>( )?
+            ),
+            InsertSource::Select(select) => InsertSource::Select(Box::new(select.transformExpressions(transformer)?))
+          },
+          onConflict
+        }
+      },
+
+      Self::Select { distinct, selections, from, r#where, groupBy, having, order, limit, offset } => Self::Select {
+        distinct,
+        selections: selections.into_iter( )
+                              .map(|(expression, alias)| Ok((transform(expression)?, alias)))
+                              .collect::<Result<_>>( )?,
+        from: from.into_iter( )
+                  .map(|searchField| searchField.transformExpressions(&mut transform))
+                  .collect::<Result<_>>( )?,
+        r#where: r#where.map(&mut transform).transpose( )?,
+        groupBy: groupBy.into_iter( ).map(&mut transform).collect::<Result<_>>( )?,
+        having: having.map(&mut transform).transpose( )?,
+        order: order.into_iter( )
+                    .map(|(expression, order)| Ok((transform(expression)?, order)))
+                    .collect::<Result<_>>( )?,
+        limit: limit.map(&mut transform).transpose( )?,
+        offset: offset.map(&mut transform).transpose( )?
+      },
+
+      Self::Update { table, updates, r#where } => Self::Update {
+        table,
+        updates: updates.into_iter( )
+                        .map(|(column, expression)| Ok((column, transform(expression)?)))
+                        .collect::<Result<_>>( )?,
+        r#where: r#where.map(&mut transform).transpose( )?
+      },
+
+      Self::Delete { table, r#where } => Self::Delete { table, r#where: r#where.map(&mut transform).transpose( )? },
+
+      Self::Explain { statement, analyze } => Self::Explain { statement: Box::new(statement.transformExpressions(transformer)?), analyze },
+
+      Self::Set { variable, value } => Self::Set { variable, value: transform(value)? },
+
+      statement => statement
+    })
+  }
+
+  // Returns the number of parameters the statement takes : the highest parameter number in it.
+  pub fn parameterCount(&self) -> u32 {
+    let mut count= 0;
+    let _= self.clone( ).transformExpressions(&mut |expression| {
+      if let Expression::Parameter(number)= expression {
+        count= count.max(number);}
+      Ok(expression)
+    });
+    count
+  }
+
+  // Substitutes the parameter placeholders with the given values (the first value binding $1).
+  pub fn bindParameters(self, parameters: &[Value]) -> Result<Statement> {
+    let count= self.parameterCount( ) as usize;
+    if parameters.len( ) != count {
+      return Err(Error::Value(ErrorCode::UndefinedParameter, format!("Expected {} parameters, got {}", count, parameters.len( ))))}
+
+    if count == 0 {
+      return Ok(self)}
+
+    self.transformExpressions(&mut |expression| match expression {
+      Expression::Parameter(number) => Ok(Literal::from(parameters[number as usize - 1].clone( )).into( )),
+      expression => Ok(expression)
+    })
+  }
+
+  /*
+    Substitutes the calls to NOW( ) with the current time, which is fetched (only if the statement
+    calls NOW( )) using the given function. So, all the calls in a statement return the same time.
+
+    NOTE : The defaults of CREATE TABLE are left as they are, since they're evaluated for every row
+    inserted later.
+  */
+  pub fn bindNow(self, now: impl FnOnce( ) -> Result<i64>) -> Result<Statement> {
+    if !self.callsNow( ) {
+      return Ok(self)}
+
+    let now= Literal::Timestamp(now( )?);
+    self.transformExpressions(&mut |expression| match isNow(&expression) {
+      true => Ok(now.clone( ).into( )),
+      false => Ok(expression)
+    })
+  }
+
+  // Returns whether the statement calls NOW( ), outside the defaults of CREATE TABLE (see bindNow).
+  pub fn callsNow(&self) -> bool {
+    if matches!(self, Self::CreateTable { .. }) {
+      return false}
+
+    let mut callsNow= false;
+    let _= self.clone( ).transformExpressions(&mut |expression| {
+      callsNow |= isNow(&expression);
+      Ok(expression)
+    });
+    callsNow
+  }
+}
+
+fn isNow(expression: &Expression) -> bool {
+  matches!(expression, Expression::FunctionCall(name, arguments) if name == "now" && arguments.is_empty( ))
+}
+
+impl SearchField {
+  fn transformExpressions(self, transform: &mut dyn FnMut(Expression) -> Result<Expression>) -> Result<SearchField> {
+    Ok(match self {
+      Self::Join { left, right, r#type, constraint } => Self::Join {
+        left: Box::new(left.transformExpressions(transform)?),
+        right: Box::new(right.transformExpressions(transform)?),
+        r#type,
+        constraint: match constraint {
+          JoinConstraint::On(predicate) => JoinConstraint::On(transform(predicate)?),
+          constraint => constraint
+        }
+      },
+
+      searchField => searchField
+    })
+  }
+}
+
+impl Operation {
+  fn isPrefix(&self) -> bool {
+    matches!(self, Self::Not(_) | Self::Assert(_) | Self::Negate(_))
+  }
+}
+
+/*
+  Renders the operation as SQL, parenthesizing an operand only when the parser would otherwise bind
+  it differently : when its operator binds looser than this operator - or as tight, unless the
+  associativity of this operator groups it on the operand's side.
+
+  A prefix operation on the left is parenthesized even when it binds as tight, since its operand
+  would otherwise swallow this operator (NOT a = b is NOT (a = b)).
+*/
+impl Display for Operation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let (precedance, associativity)= self.precedance( );
+
+    let operand= |expression: &Expression, isLeft: bool| {
+      let Expression::Operation(operation)= expression else {
+        return expression.to_string( )
+      };
+
+      let (operandPrecedance, _)= operation.precedance( );
+      let bindsTighter= match isLeft {
+        true => operandPrecedance > precedance
+                  || (operandPrecedance == precedance && associativity == Associativity::Left && !operation.isPrefix( )),
+
+        false => operandPrecedance > precedance
+                   || (operandPrecedance == precedance && associativity == Associativity::Right)
+      };
+
+      match bindsTighter {
+        true => expression.to_string( ),
+        false => format!("({})", expression)
+      }
+    };
+    let lhs= |expression: &Expression| operand(expression, true);
+    let rhs= |expression: &Expression| operand(expression, false);
+
+    match self {
+      Self::And(l, r) => write!(f, "{} AND {}", lhs(l), rhs(r)),
+      Self::Or(l, r) => write!(f, "{} OR {}", lhs(l), rhs(r)),
+      Self::Not(expression) => write!(f, "NOT {}", rhs(expression)),
+
+      Self::Equal(l, r) => write!(f, "{} = {}", lhs(l), rhs(r)),
+      Self::GreaterThan(l, r) => write!(f, "{} > {}", lhs(l), rhs(r)),
+      Self::GreaterThanOrEqual(l, r) => write!(f, "{} >= {}", lhs(l), rhs(r)),
+      Self::IsNull(expression) => write!(f, "{} IS NULL", lhs(expression)),
+      Self::LessThan(l, r) => write!(f, "{} < {}", lhs(l), rhs(r)),
+      Self::LessThanOrEqual(l, r) => write!(f, "{} <= {}", lhs(l), rhs(r)),
+      Self::NotEqual(l, r) => write!(f, "{} != {}", lhs(l), rhs(r)),
+
+      Self::Add(l, r) => write!(f, "{} + {}", lhs(l), rhs(r)),
+      Self::Assert(expression) => write!(f, "+{}", rhs(expression)),
+      Self::Divide(l, r) => write!(f, "{} / {}", lhs(l), rhs(r)),
+      Self::Exponentiate(l, r) => write!(f, "{} ^ {}", lhs(l), rhs(r)),
+      Self::Factorial(expression) => write!(f, "{}!", lhs(expression)),
+      Self::Modulo(l, r) => write!(f, "{} % {}", lhs(l), rhs(r)),
+      Self::Multiply(l, r) => write!(f, "{} * {}", lhs(l), rhs(r)),
+
+      // Two minus signs in a row would start a comment.
+      Self::Negate(expression) => match rhs(expression) {
+        operand if operand.starts_with('-') => write!(f, "-({})", operand),
+        operand => write!(f, "-{}", operand)
+      },
+
+      Self::Subtract(l, r) => write!(f, "{} - {}", lhs(l), rhs(r)),
+
+      Self::Concatenate(l, r) => write!(f, "{} || {}", lhs(l), rhs(r)),
+
+      Self::Like(l, r, None) => write!(f, "{} LIKE {}", lhs(l), rhs(r)),
+      Self::Like(l, r, Some(escape)) => write!(f, "{} LIKE {} ESCAPE {}", lhs(l), rhs(r), rhs(escape)),
+
+      Self::In(l, r) => write!(f, "{} IN {}", lhs(l), rhs(r)),
+
+      Self::Case { operand, branches, otherwise } => {
+        write!(f, "CASE")?;
+        if let Some(operand)= operand {
+          write!(f, " {}", operand)?;}
+
+        for (condition, result) in branches {
+          write!(f, " WHEN {} THEN {}", condition, result)?;}
+
+        if let Some(otherwise)= otherwise {
+          write!(f, " ELSE {}", otherwise)?;}
+        write!(f, " END")
+      }
+    }
+  }
+}
+
+/*
+  Renders an identifier (like a table / column / index name, or an alias) as SQL, which lexes back
+  into the same identifier.
+
+  An unquoted identifier is case-insensitive (the lexer lowercases it), while a quoted one is kept as
+  it is. So, the identifier is quoted, unless it's a valid unquoted identifier which is already
+  lowercase and isn't a keyword. Like, "order", "Name" and "first name" are quoted, but name isn't.
+*/
+pub struct Identifier<'a>(pub &'a str);
+
+impl Identifier<'_> {
+  fn needsQuotes(&self) -> bool {
+    let mut characters= self.0.chars( );
+
+    let isValidUnquoted= characters.next( ).is_some_and(|character| character.is_alphabetic( ) || character == '_') &&
+                         characters.all(|character| character.is_alphanumeric( ) || character == '_');
+
+    !isValidUnquoted || (self.0.to_lowercase( ) != self.0) || Keyword::from_str(self.0).is_some( )
+  }
+}
+
+impl Display for Identifier<'_> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.needsQuotes( ) {
+      true => write!(f, "\"{}\"", self.0.replace('"', "\"\"")),
+      false => f.write_str(self.0)
+    }
+  }
+}
+
+// Writes the items separated by commas.
+fn writeList<T: Display>(f: &mut std::fmt::Formatter<'_>, items: impl IntoIterator<Item = T>) -> std::fmt::Result {
+  for (index, item) in items.into_iter( ).enumerate( ) {
+    if index > 0 {
+      write!(f, ", ")?;}
+    write!(f, "{}", item)?;
+  }
+  Ok(( ))
+}
+
+// Renders the statement as SQL, which parses back into the same statement.
+impl Display for Statement {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Begin { readonly, asOfVersion } => {
+        write!(f, "BEGIN")?;
+        if *readonly {
+          write!(f, " READ ONLY")?;}
+        if let Some(version)= asOfVersion {
+          write!(f, " AS OF SYSTEM TIME {}", version)?;}
+        Ok(( ))
+      },
+
+      Self::CreateTable { name, columns, ifNotExists } => {
+        write!(f, "CREATE TABLE ")?;
+        if *ifNotExists {
+          write!(f, "IF NOT EXISTS ")?;}
+        write!(f, "{} (", Identifier(name))?;
+        writeList(f, columns)?;
+        write!(f, ")")
+      },
+      Self::DropTable { name, ifExists } => match ifExists {
+        true => write!(f, "DROP TABLE IF EXISTS {}", Identifier(name)),
+        false => write!(f, "DROP TABLE {}", Identifier(name))
+      },
+      Self::CreateIndex { name, table, expression } =>
+        write!(f, "CREATE INDEX {} ON {} ({})", Identifier(name), Identifier(table), expression),
+
+      Self::AlterTable { name, action: AlterTableAction::RenameTo(newName) } =>
+        write!(f, "ALTER TABLE {} RENAME TO {}", Identifier(name), Identifier(newName)),
+      Self::AlterTable { name, action: AlterTableAction::RenameColumn { column, newName } } =>
+        write!(f, "ALTER TABLE {} RENAME COLUMN {} TO {}", Identifier(name), Identifier(column), Identifier(newName)),
+
+      Self::ShowTables => write!(f, "SHOW TABLES"),
+      Self::DescribeTable(name) => write!(f, "DESCRIBE {}", Identifier(name)),
+      Self::Status => write!(f, "STATUS"),
+      Self::Set { variable, value } => write!(f, "SET {} = {}", Identifier(variable), value),
+      Self::ShowVariable(variable) => write!(f, "SHOW {}", Identifier(variable)),
+      Self::ShowVariables => write!(f, "SHOW ALL"),
+
+      Self::Copy { table } => write!(f, "COPY {} FROM STDIN", Identifier(table)),
+
+      Self::Insert { table, columns, source, onConflict } => {
+        write!(f, "INSERT INTO {}", Identifier(table))?;
+        if let Some(columns)= columns {
+          write!(f, " (")?;
+          writeList(f, columns.iter( ).map(|column| Identifier(column)))?;
+          write!(f, ")")?;
+        }
+
+        write!(f, " {}", source)?;
+
+        if let Some(OnConflict { column, updates })= onConflict {
+          write!(f, " ON CONFLICT ({}) DO ", Identifier(column))?;
+          match updates {
+            Some(updates) => {
+              write!(f, "UPDATE SET ")?;
+              writeList(f, updates.iter( ).map(|(column, expression)| format!("{} = {}", Identifier(column), expression)))?;
+            },
+            None => write!(f, "NOTHING")?
+          }
+        }
+        Ok(( ))
+      },
+
+      Self::Select { distinct, selections, from, r#where, groupBy, having, order, limit, offset } => {
+        write!(f, "SELECT ")?;
+        if *distinct {
+          write!(f, "DISTINCT ")?;}
+        match selections.is_empty( ) {
+          true => write!(f, "*")?,
+          false => writeList(f, selections.iter( ).map(|(expression, label)| match label {
+            Some(label) => format!("{} AS {}", expression, Identifier(label)),
+            None => expression.to_string( )
+          }))?
+        }
+
+        if !from.is_empty( ) {
+          write!(f, " FROM ")?;
+          writeList(f, from)?;
+        }
+        if let Some(predicate)= r#where {
+          write!(f, " WHERE {}", predicate)?;}
+        if !groupBy.is_empty( ) {
+          write!(f, " GROUP BY ")?;
+          writeList(f, groupBy)?;
+        }
+        if let Some(predicate)= having {
+          write!(f, " HAVING {}", predicate)?;}
+        if !order.is_empty( ) {
+          write!(f, " ORDER BY ")?;
+          writeList(f, order.iter( ).map(|(expression, order)| format!("{} {}", expression, order)))?;
+        }
+        if let Some(limit)= limit {
+          write!(f, " LIMIT {}", limit)?;}
+        if let Some(offset)= offset {
+          write!(f, " OFFSET {}", offset)?;}
+        Ok(( ))
+      },
+
+      Self::Update { table, updates, r#where } => {
+        write!(f, "UPDATE {} SET ", Identifier(table))?;
+        writeList(f, updates.iter( ).map(|(column, expression)| format!("{} = {}", Identifier(column), expression)))?;
+        if let Some(predicate)= r#where {
+          write!(f, " WHERE {}", predicate)?;}
+        Ok(( ))
+      },
+
+      Self::Delete { table, r#where } => {
+        write!(f, "DELETE FROM {}", Identifier(table))?;
+        if let Some(predicate)= r#where {
+          write!(f, " WHERE {}", predicate)?;}
+        Ok(( ))
+      },
+      Self::Truncate(table) => write!(f, "TRUNCATE TABLE {}", Identifier(table)),
+
+      Self::Analyze(Some(table)) => write!(f, "ANALYZE {}", Identifier(table)),
+      Self::Analyze(None) => write!(f, "ANALYZE"),
+
+      Self::Commit => write!(f, "COMMIT"),
+      Self::Rollback => write!(f, "ROLLBACK"),
+
+      Self::Explain { statement, analyze: false } => write!(f, "EXPLAIN {}", statement),
+      Self::Explain { statement, analyze: true } => write!(f, "EXPLAIN ANALYZE {}", statement)
+    }
+  }
+}
+
+impl Display for Column {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} {}", Identifier(&self.name), self.dataType)?;
+
+    if self.primaryKey {
+      write!(f, " PRIMARY KEY")?;}
+    match self.nullable {
+      Some(true) => write!(f, " NULL")?,
+      Some(false) => write!(f, " NOT NULL")?,
+      None => { }
+    }
+    if self.unique {
+      write!(f, " UNIQUE")?;}
+    if self.index {
+      write!(f, " INDEX")?;}
+    if let Some(default)= &self.default {
+      write!(f, " DEFAULT {}", default)?;}
+    if let Some(table)= &self.references {
+      write!(f, " REFERENCES {}", Identifier(table))?;}
+    if self.onDelete != ReferentialAction::Restrict {
+      write!(f, " ON DELETE {}", self.onDelete)?;}
+    Ok(( ))
+  }
+}
+
+impl Display for DataType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Self::Boolean => "BOOLEAN",
+      Self::Integer => "INTEGER",
+      Self::Float => "FLOAT",
+      Self::String => "STRING",
+      Self::Date => "DATE",
+      Self::Timestamp => "TIMESTAMP",
+      Self::Phantom => "PHANTOM"
+    })
+  }
+}
+
+impl Display for InsertSource {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Values(values) => {
+        write!(f, "VALUES ")?;
+        for (index, row) in values.iter( ).enumerate( ) {
+          if index > 0 {
+            write!(f, ", ")?;}
+          write!(f, "(")?;
+          writeList(f, row)?;
+          write!(f, ")")?;
+        }
+        Ok(( ))
+      },
+
+      Self::Select(statement) => write!(f, "{}", statement)
+    }
+  }
+}
+
+impl Display for SearchField {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Table { name, alias: Some(alias) } => write!(f, "{} AS {}", Identifier(name), Identifier(alias)),
+      Self::Table { name, alias: None } => write!(f, "{}", Identifier(name)),
+
+      Self::Join { left, right, r#type, constraint } => match constraint {
+        JoinConstraint::None => write!(f, "{} {} {}", left, r#type, right),
+        JoinConstraint::On(predicate) => write!(f, "{} {} {} ON {}", left, r#type, right, predicate),
+        JoinConstraint::Using(columns) => {
+          write!(f, "{} {} {} USING (", left, r#type, right)?;
+          writeList(f, columns.iter( ).map(|column| Identifier(column)))?;
+          write!(f, ")")
+        },
+        JoinConstraint::Natural => write!(f, "{} NATURAL {} {}", left, r#type, right)
+      }
+    }
+  }
+}
+
+impl Display for JoinType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Self::Cross => "CROSS JOIN",
+      Self::Inner => "INNER JOIN",
+      Self::Left => "LEFT JOIN",
+      Self::Right => "RIGHT JOIN"
+    })
+  }
+}
+
+impl Display for Order {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Self::Ascending => "ASC",
+      Self::Descending => "DESC"
+    })
+  }
+}
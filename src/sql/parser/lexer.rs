@@ -1,22 +1,57 @@
-use std::{fmt::Display, iter::Peekable, str::Chars};
+use std::{fmt::Display, str::Chars};
 use crate::result::{Error, Result};
 use super::token::{Keyword, Token};
 
+// A position in the input. Lines and columns start from 1, and columns are counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+  pub line: usize,
+  pub column: usize
+}
+
+impl Position {
+  // Returns a parse error at this position.
+  pub fn error(&self, message: impl Into<String>) -> Error {
+    Error::ParseAt { line: self.line, column: self.column, message: message.into( ) }
+  }
+}
+
+impl Display for Position {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "line {}, column {}", self.line, self.column)
+  }
+}
+
+// The part of the input a token was lexed from : from the position of its first character, upto
+// (but excluding) the position after its last character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+  pub start: Position,
+  pub end: Position
+}
+
 pub struct Lexer<'a> {
-  input: Peekable<Chars<'a>>,
+  input: Chars<'a>,
+
+  // Position of the next character of the input.
+  position: Position
 }
 
 impl<'a> Iterator for Lexer<'a> {
-  type Item = Result<Token>;
+  type Item = Result<(Token, Span)>;
 
   fn next(&mut self) -> Option<Self::Item> {
+    if let Err(error)= self.ignoreWhitespacesAndComments( ) {
+      return Some(Err(error))}
+
+    let start= self.position;
     match self.scan( ) {
       Err(error) => Some(Err(error)),
 
-      Ok(Some(token)) => Some(Ok(token)),
+      Ok(Some(token)) => Some(Ok((token, Span { start, end: self.position }))),
       Ok(None) => {
-        self.input.peek( )
-                  .map(|character| Err(Error::Parse(format!("Unexpected character {}", character))))
+        self.peek( )
+            .map(|character| Err(start.error(format!("Unexpected character {}", character))))
       }
     }
   }
@@ -24,105 +59,239 @@ impl<'a> Iterator for Lexer<'a> {
 
 impl<'a> Lexer<'a> {
   pub fn new(input: &'a str) -> Self {
-    return Self {
-      input: input.chars( ).peekable( )
+    Self {
+      input: input.chars( ),
+      position: Position { line: 1, column: 1 }
     }
   }
 
-  // Scans the input for the next token (Ignores leading whitespaces).
-  fn scan(&mut self) -> Result<Option<Token>> {
-    self.ignoreLeadingWhitespaces( );
+  // Returns the position of the next character of the input.
+  pub fn position(&self) -> Position {
+    self.position
+  }
 
-    match self.input.peek( ) {
+  // Scans the input for the next token.
+  fn scan(&mut self) -> Result<Option<Token>> {
+    match self.peek( ) {
       None => Ok(None),
 
-      Some(character) if character.is_ascii_digit( ) => Ok(self.scanNumber( )),
-      Some(character) if character.is_alphabetic( ) => Ok(self.scanIdentifier( )),
+      Some(character) if character.is_ascii_digit( ) => self.scanNumber( ).map(Some),
+      // NOTE : An identifier can start with an underscore (like the _version system column).
+      Some(character) if character.is_alphabetic( ) || character == '_' => Ok(self.scanIdentifier( )),
 
       // NOTE : Single quotes delimit a string constant (literal) / a date-time constant. And double quotes
       // delimit identifiers (e.g. table / column / index names).
       Some('\'') => self.scanStringLiteral( ),
       Some('"') => self.scanQuotedIdentifier( ),
 
+      Some('$') => self.scanParameter( ).map(Some),
+
       Some(_) => Ok(self.scanSymbol( ))
     }
   }
 
-  fn ignoreLeadingWhitespaces(&mut self) {
-    self.nextWhile(|character| character.is_whitespace( ));
+  /*
+    Skips whitespaces and comments, which can be line comments (from -- upto the end of the line) or
+    block comments (enclosed by slash-asterisk and asterisk-slash, possibly spanning multiple lines).
+    Block comments don't nest.
+  */
+  fn ignoreWhitespacesAndComments(&mut self) -> Result<( )> {
+    loop {
+      self.nextWhile(|character| character.is_whitespace( ));
+
+      match (self.peek( ), self.peekSecond( )) {
+        (Some('-'), Some('-')) => {
+          self.nextWhile(|character| character != '\n');},
+
+        (Some('/'), Some('*')) => {
+          let start= self.position;
+          self.advance( );
+          self.advance( );
+
+          loop {
+            match self.advance( ) {
+              Some('*') if self.nextIf(|character| character == '/').is_some( ) => break,
+              Some(_) => continue,
+              None => return Err(start.error("Unterminated block comment"))
+            }
+          }
+        },
+
+        _ => return Ok(( ))
+      }
+    }
   }
 
-  fn scanNumber(&mut self) -> Option<Token> {
-    let mut number: String= self.nextWhile(|character| character.is_ascii_digit( ))?;
+  /*
+    Scans a numeric literal, which can be -
+    (a) a decimal integer (e.g. - 42)
+    (b) a hexadecimal integer (e.g. - 0x1F)
+    (c) a decimal number with a fractional part and / or an exponent (e.g. - 3.27, 1.8e-3 or 2E10).
+    The digits can be separated by underscores for readability (e.g. - 1_000_000), which are
+    stripped.
+
+    Integers must fit in an i64, and hexadecimal ones are converted to decimal. So the token of an
+    integer only has digits, while that of a float has a . or an exponent.
+  */
+  fn scanNumber(&mut self) -> Result<Token> {
+    let start= self.position;
+
+    if (self.peek( ) == Some('0')) && matches!(self.peekSecond( ), Some('x' | 'X')) {
+      self.advance( );
+      self.advance( );
+
+      let digits= self.scanDigits(|character| character.is_ascii_hexdigit( ))?
+                      .ok_or_else(| | start.error("Expected hexadecimal digits after 0x"))?;
+      self.expectEndOfNumber( )?;
+
+      return i64::from_str_radix(&digits, 16)
+               .map(|value| Token::Number(value.to_string( )))
+               .map_err(|_| start.error(format!("Integer literal 0x{} is out of range", digits)))
+    }
+
+    let mut number= self.scanDigits(|character| character.is_ascii_digit( ))?.unwrap_or_default( );
+    let mut isFloat= false;
 
     // Handling Decimal numbers (e.g. - 3.27)
     if let Some(decimal)= self.nextIf(|character| character == '.') {
       number.push(decimal);
+      isFloat= true;
 
-      self.nextWhile(|character| character.is_ascii_digit( ))
-          .map(|postDecimalDigits| number.push_str(&postDecimalDigits));
+      if let Some(postDecimalDigits)= self.scanDigits(|character| character.is_ascii_digit( ))? {
+        number.push_str(&postDecimalDigits);}
     }
 
     // Handling Exponential notation (e.g. - 1.8e-3 which represents 1.8 * (10 ^ -3)).
     if let Some(e)= self.nextIf(|character| character == 'e' || character == 'E') {
       number.push(e);
+      isFloat= true;
 
-      if let Some('+') | Some('-')= self.input.peek( ) {
-        number.push(self.input.next( ).unwrap( ));}
+      if let Some(sign)= self.nextIf(|character| character == '+' || character == '-') {
+        number.push(sign);}
 
-      self.nextWhile(|character| character.is_ascii_digit( ))
-          .map(|postSignDigits| number.push_str(&postSignDigits));
+      let exponentDigits= self.scanDigits(|character| character.is_ascii_digit( ))?
+                              .ok_or_else(| | self.position.error(format!("Expected digits in the exponent of {}", number)))?;
+      number.push_str(&exponentDigits);
     }
 
-    Some(Token::Number(number))
+    self.expectEndOfNumber( )?;
+
+    match isFloat {
+      true if !number.parse::<f64>( ).is_ok_and(f64::is_finite) =>
+        Err(start.error(format!("Float literal {} is out of range", number))),
+
+      false if number.parse::<i64>( ).is_err( ) =>
+        Err(start.error(format!("Integer literal {} is out of range", number))),
+
+      _ => Ok(Token::Number(number))
+    }
+  }
+
+  // Grabs the next contiguous digits (matching the predicate), which can be separated by single
+  // underscores. The underscores are stripped.
+  fn scanDigits<P>(&mut self, isDigit: P) -> Result<Option<String>>
+    where P: Fn(char) -> bool
+  {
+    let mut digits= String::new( );
+
+    loop {
+      if let Some(moreDigits)= self.nextWhile(&isDigit) {
+        digits.push_str(&moreDigits);}
+
+      if self.peek( ) != Some('_') {
+        return Ok(Some(digits).filter(|digits| !digits.is_empty( )))}
+
+      if digits.is_empty( ) || !self.peekSecond( ).is_some_and(&isDigit) {
+        return Err(self.position.error("Underscores in a number must be between digits"))}
+
+      self.advance( );
+    }
   }
 
+  // A number can't be immediately followed by a letter, digit, underscore or period (like in 10ab
+  // or 1.2.3).
+  fn expectEndOfNumber(&self) -> Result<( )> {
+    match self.peek( ) {
+      Some(character) if character.is_alphanumeric( ) || character == '_' || character == '.' =>
+        Err(self.position.error(format!("Unexpected character {} in number", character))),
+
+      _ => Ok(( ))
+    }
+  }
+
+  // Scans a keyword or an unquoted identifier. Unquoted identifiers are case-insensitive, so they're
+  // lowercased (see ast::Identifier for how an identifier is rendered back as SQL).
   fn scanIdentifier(&mut self) -> Option<Token> {
-    let mut identifierName= self.nextIf(|character| character.is_alphabetic( ))?.to_string( );
+    let mut identifierName= self.nextIf(|character| character.is_alphabetic( ) || character == '_')?.to_string( );
 
-    self.nextWhile(|character| character.is_alphabetic( ) || character == '_')
-        .map(|remainingCharacters| identifierName.push_str(&remainingCharacters));
+    if let Some(remainingCharacters)= self.nextWhile(|character| character.is_alphanumeric( ) || character == '_') {
+      identifierName.push_str(&remainingCharacters);}
 
     Keyword::from_str(&identifierName)
-              .map(|keyword| Token::Keyword(keyword))
+              .map(Token::Keyword)
               .or_else(| | Some(Token::Identifier(identifierName.to_lowercase( ))))
   }
 
+  // Scans an identifier enclosed in double quotes, preserving its case. A double quote inside it is
+  // escaped by doubling it (e.g. "say ""hi""").
   fn scanQuotedIdentifier(&mut self) -> Result<Option<Token>> {
+    let start= self.position;
     if self.nextIf(|character| character == '"').is_none( ) {
       return Ok(None)}
 
     let mut identifierName= String::new( );
 
     loop {
-      match self.input.next( ) {
-        Some(character) => identifierName.push(character),
+      match self.advance( ) {
+        Some('"') if self.nextIf(|character| character == '"').is_some( ) => identifierName.push('"'),
         Some('"') => break,
-        None => return Err(Error::Parse("Unexpected end of quoted identifier".to_string( ))),
+        Some(character) => identifierName.push(character),
+        None => return Err(start.error("Unexpected end of quoted identifier")),
       }
     }
 
     Ok(Some(Token::Identifier(identifierName)))
   }
 
+  // Scans a string literal enclosed in single quotes. A single quote inside it is escaped by doubling
+  // it (e.g. 'it''s').
   fn scanStringLiteral(&mut self) -> Result<Option<Token>> {
+    let start= self.position;
     if self.nextIf(|character| character == '\'').is_none( ) {
       return Ok(None)}
 
     let mut value= String::new( );
 
     loop {
-      match self.input.next( ) {
-        Some(character) => value.push(character),
+      match self.advance( ) {
+        Some('\'') if self.nextIf(|character| character == '\'').is_some( ) => value.push('\''),
         Some('\'') => break,
-        None => return Err(Error::Parse("Unexpected end of string literal".to_string( ))),
+        Some(character) => value.push(character),
+        None => return Err(start.error("Unexpected end of string literal")),
       }
     }
 
-    Ok(Some(Token::Identifier(value)))
+    Ok(Some(Token::String(value)))
+  }
+
+  // Scans a numbered parameter placeholder (like $1).
+  fn scanParameter(&mut self) -> Result<Token> {
+    let start= self.position;
+    self.advance( );
+
+    self.nextWhile(|character| character.is_ascii_digit( ))
+        .map(Token::Parameter)
+        .ok_or_else(| | start.error("Expected the number of the parameter after $"))
   }
 
   fn scanSymbol(&mut self) -> Option<Token> {
+    // A | is only a symbol as part of ||.
+    if self.peek( ) == Some('|') && self.peekSecond( ) == Some('|') {
+      self.advance( );
+      self.advance( );
+      return Some(Token::Concatenate)
+    }
+
     self.nextIfToken(|character| match character {
       '.' => Some(Token::Period),
 
@@ -170,21 +339,45 @@ impl<'a> Lexer<'a> {
 }
 
 impl<'a> Lexer<'a> {
+  // Peeks the next character.
+  fn peek(&self) -> Option<char> {
+    self.input.clone( ).next( )
+  }
+
+  // Peeks the character after the next one.
+  fn peekSecond(&self) -> Option<char> {
+    self.input.clone( ).nth(1)
+  }
+
+  // Grabs the next character, updating the position.
+  fn advance(&mut self) -> Option<char> {
+    let character= self.input.next( )?;
+
+    if character == '\n' {
+      self.position.line += 1;
+      self.position.column= 1;
+    }
+    else {
+      self.position.column += 1;}
+
+    Some(character)
+  }
+
   // Grabs the next character if it matches the predicate.
   fn nextIf<P>(&mut self, predicate: P) -> Option<char>
     where P: Fn(char) -> bool
   {
-    self.input.peek( )
-              .filter(|&character| predicate(*character))?;
-    self.input.next( )
+    self.peek( )
+        .filter(|&character| predicate(character))?;
+    self.advance( )
   }
 
   // Grabs the next single-character token if the predicate function returns one.
   fn nextIfToken<P>(&mut self, parseCharacterToToken: P) -> Option<Token>
     where P: Fn(char) -> Option<Token>
   {
-    let token = self.input.peek( ).and_then(|&character| parseCharacterToToken(character))?;
-    self.input.next( );
+    let token = self.peek( ).and_then(parseCharacterToToken)?;
+    self.advance( );
     Some(token)
   }
 
@@ -200,3 +393,56 @@ impl<'a> Lexer<'a> {
     Some(string).filter(|value| !value.is_empty( ))
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Lexes the input, dropping the spans of the tokens.
+  fn tokens(input: &str) -> Result<Vec<Token>> {
+    Lexer::new(input).map(|token| token.map(|(token, _)| token)).collect( )
+  }
+
+  fn identifier(name: &str) -> Token {
+    Token::Identifier(name.to_string( ))
+  }
+
+  #[test]
+  fn unescapesDoubledQuoteInStringLiteral( ) -> Result<( )> {
+    assert_eq!(tokens("'it''s'")?, vec![Token::String("it's".to_string( ))]);
+    assert_eq!(tokens("''''")?, vec![Token::String("'".to_string( ))]);
+
+    // Adjacent literals, separated by whitespace, aren't merged.
+    assert_eq!(tokens("'it' 's'")?, vec![Token::String("it".to_string( )), Token::String("s".to_string( ))]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn preservesCaseOfQuotedIdentifier( ) -> Result<( )> {
+    assert_eq!(tokens("\"MixedCase\" MixedCase")?, vec![identifier("MixedCase"), identifier("mixedcase")]);
+
+    // A quoted keyword is an identifier.
+    assert_eq!(tokens("\"select\" select")?, vec![identifier("select"), Token::Keyword(Keyword::SELECT)]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn unescapesDoubledQuoteInQuotedIdentifier( ) -> Result<( )> {
+    assert_eq!(tokens("\"say \"\"hi\"\"\"")?, vec![identifier("say \"hi\"")]);
+    assert_eq!(tokens("\"\"\"\"")?, vec![identifier("\"")]);
+    Ok(( ))
+  }
+
+  #[test]
+  fn rejectsUnterminatedStringLiteralOrQuotedIdentifier( ) {
+    let unterminated= [
+      ("SELECT 'it''s", 8, "Unexpected end of string literal"),
+      ("SELECT 'abc''", 8, "Unexpected end of string literal"),
+      ("SELECT \"MixedCase", 8, "Unexpected end of quoted identifier"),
+      ("SELECT \"say \"\"hi\"\"", 8, "Unexpected end of quoted identifier")
+    ];
+
+    for (input, column, message) in unterminated {
+      assert_eq!(tokens(input), Err(Error::ParseAt { line: 1, column, message: message.to_string( ) }), "{}", input);}
+  }
+}
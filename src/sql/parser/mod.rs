@@ -1,23 +1,66 @@
 use std::{collections::BTreeMap, iter::Peekable};
-use crate::{result::{Error, Result}, sql::parser::{ast::DataType, operators::PrefixOperator}};
+use crate::{result::{Error, ErrorCode, Result}, sql::{datetime, numeric, parser::{ast::DataType, operators::PrefixOperator}, schema::ReferentialAction}};
 use self::{
-  ast::{AliasColumnName, Column, Expression, JoinType, Literal, Order, SearchField, Statement},
-  lexer::Lexer,
+  ast::{AliasColumnName, AlterTableAction, Column, Expression, InsertSource, JoinConstraint, JoinType, Literal, OnConflict, Operation, Order, SearchField, Selections, Statement},
+  lexer::{Lexer, Position},
   operators::{InfixOperator, Operator, PostfixOperator, Precedance}, token::{Keyword, Token}
 };
 
 mod token;
 mod lexer;
-mod ast;
+pub mod ast;
 mod operators;
 
+/*
+  Maximum depth of an expression (counting the subqueries in it as well). The parser, and later the
+  planner and the executor, recurse into the operands of an expression, so a deeper one (like one
+  with thousands of nested parentheses) could overflow the stack.
+*/
+pub const MAX_EXPRESSION_DEPTH: usize= 64;
+
 pub struct Parser<'a> {
-  lexer: Peekable<Lexer<'a>>
+  lexer: Peekable<Lexer<'a>>,
+
+  // Position of the last token grabbed or peeked (or of the end of the input, once it's reached).
+  // Parse errors are reported at this position.
+  position: Position,
+
+  // Position after the last token grabbed.
+  end: Position,
+
+  // Number of the ? parameter placeholders parsed so far. They're numbered in the order they appear.
+  positionalParameters: u32,
+  // Whether numbered parameter placeholders (like $1) have been parsed. They can't be mixed with ?
+  // placeholders.
+  numberedParameters: bool,
+
+  // Depth of the expression being parsed (see MAX_EXPRESSION_DEPTH).
+  depth: usize
 }
 
 impl<'a> Parser<'a> {
   // Parses the input into an Abstract Syntax Tree (AST).
   pub fn parse(&mut self) -> Result<Statement> {
+    self.parseAll( ).map_err(|error| match error {
+      Error::Parse(message) => self.position.error(message),
+      error => error
+    })
+  }
+
+  // Parses the input into a single expression (like the stored default of a column).
+  pub fn parseStandaloneExpression(&mut self) -> Result<Expression> {
+    let expression= self.parseExpression(0).and_then(|expression| {
+      self.nextExpectedToken(None)?;
+      Ok(expression)
+    });
+
+    expression.map_err(|error| match error {
+      Error::Parse(message) => self.position.error(message),
+      error => error
+    })
+  }
+
+  fn parseAll(&mut self) -> Result<Statement> {
     let statement= self.parseStatement( )?;
     self.nextTokenIfIts(Token::Semicolon);
 
@@ -29,6 +72,7 @@ impl<'a> Parser<'a> {
   fn parseStatement(&mut self) -> Result<Statement> {
     match self.peekNextToken( )? {
       Some(Token::Keyword(Keyword::CREATE | Keyword::DROP)) => self.parseCreateOrDropStatement( ),
+      Some(Token::Keyword(Keyword::ALTER)) => self.parseAlterTableStatement( ),
 
       Some(Token::Keyword(Keyword::BEGIN | Keyword::COMMIT | Keyword::ROLLBACK)) =>
         self.parseTransactionStatement( ),
@@ -37,9 +81,16 @@ impl<'a> Parser<'a> {
       Some(Token::Keyword(Keyword::SELECT)) => self.parseSelectStatement( ),
       Some(Token::Keyword(Keyword::UPDATE)) => self.parseUpdateStatement( ),
       Some(Token::Keyword(Keyword::DELETE)) => self.parseDeleteStatement( ),
+      Some(Token::Keyword(Keyword::TRUNCATE)) => self.parseTruncateStatement( ),
+      Some(Token::Keyword(Keyword::COPY)) => self.parseCopyStatement( ),
+      Some(Token::Keyword(Keyword::ANALYZE)) => self.parseAnalyzeStatement( ),
 
       Some(Token::Keyword(Keyword::EXPLAIN)) => self.parseExplainStatement( ),
 
+      Some(Token::Keyword(Keyword::SHOW | Keyword::DESCRIBE | Keyword::STATUS)) => self.parseIntrospectionStatement( ),
+
+      Some(Token::Keyword(Keyword::SET)) => self.parseSetStatement( ),
+
       Some(token) => Err(Error::Parse(format!("Unexpected token {}", token))),
       None =>  Err(Error::Parse("Unexpected end of input".into( ))),
     }
@@ -49,7 +100,8 @@ impl<'a> Parser<'a> {
     match self.nextToken( )? {
       Token::Keyword(Keyword::CREATE) => match self.nextToken( )? {
         Token::Keyword(Keyword::TABLE) => self.parseCreateTableStatement( ),
-        token => Err(Error::Parse(format!("Expected TABLE keyword, got {}", token)))
+        Token::Keyword(Keyword::INDEX) => self.parseCreateIndexStatement( ),
+        token => Err(Error::Parse(format!("Expected TABLE / INDEX keyword, got {}", token)))
       },
 
       Token::Keyword(Keyword::DROP) => match self.nextToken( )? {
@@ -62,6 +114,7 @@ impl<'a> Parser<'a> {
   }
 
   fn parseCreateTableStatement(&mut self) -> Result<Statement> {
+    let ifNotExists= self.parseIfExists(true)?;
     let tableName= self.nextIdentifier( )?;
 
     self.nextExpectedToken(Some(Token::OpenParenthesis))?;
@@ -76,12 +129,65 @@ impl<'a> Parser<'a> {
 
     self.nextExpectedToken(Some(Token::CloseParenthesis))?;
 
-    Ok(Statement::CreateTable { name: tableName, columns })
+    Ok(Statement::CreateTable { name: tableName, columns, ifNotExists })
+  }
+
+  // Parses CREATE INDEX name ON table (expression). An expression other than a column is usually
+  // parenthesized again, like ((LOWER(email))).
+  fn parseCreateIndexStatement(&mut self) -> Result<Statement> {
+    let name= self.nextIdentifier( )?;
+
+    self.nextExpectedToken(Some(Keyword::ON.into( )))?;
+    let table= self.nextIdentifier( )?;
+
+    self.nextExpectedToken(Some(Token::OpenParenthesis))?;
+    let expression= self.parseExpression(0)?;
+    self.nextExpectedToken(Some(Token::CloseParenthesis))?;
+
+    Ok(Statement::CreateIndex { name, table, expression })
   }
 
   fn parseDropTableStatement(&mut self) -> Result<Statement> {
+    let ifExists= self.parseIfExists(false)?;
     let tableName= self.nextIdentifier( )?;
-    Ok(Statement::DropTable(tableName))
+    Ok(Statement::DropTable { name: tableName, ifExists })
+  }
+
+  // Parses ALTER TABLE <table> RENAME TO <name>, and ALTER TABLE <table> RENAME COLUMN <column> TO
+  // <name>.
+  fn parseAlterTableStatement(&mut self) -> Result<Statement> {
+    self.nextExpectedToken(Some(Keyword::ALTER.into( )))?;
+    self.nextExpectedToken(Some(Keyword::TABLE.into( )))?;
+    let name= self.nextIdentifier( )?;
+
+    self.nextExpectedToken(Some(Keyword::RENAME.into( )))?;
+    let action= match self.nextTokenIfIts(Keyword::COLUMN.into( )) {
+      Some(_) => {
+        let column= self.nextIdentifier( )?;
+        self.nextExpectedToken(Some(Keyword::TO.into( )))?;
+        AlterTableAction::RenameColumn { column, newName: self.nextIdentifier( )? }
+      },
+
+      None => {
+        self.nextExpectedToken(Some(Keyword::TO.into( )))?;
+        AlterTableAction::RenameTo(self.nextIdentifier( )?)
+      }
+    };
+
+    Ok(Statement::AlterTable { name, action })
+  }
+
+  // Parses an optional IF EXISTS (or IF NOT EXISTS, if negated) modifier, returning whether it's
+  // given.
+  fn parseIfExists(&mut self, negated: bool) -> Result<bool> {
+    if self.nextTokenIfIts(Keyword::IF.into( )).is_none( ) {
+      return Ok(false)}
+
+    if negated {
+      self.nextExpectedToken(Some(Keyword::NOT.into( )))?;}
+    self.nextExpectedToken(Some(Keyword::EXISTS.into( )))?;
+
+    Ok(true)
   }
 
   fn parseColumnSpec(&mut self) -> Result<Column> {
@@ -102,6 +208,9 @@ impl<'a> Parser<'a> {
         Token::Keyword(Keyword::TEXT) => DataType::String,
         Token::Keyword(Keyword::VARCHAR) => DataType::String,
 
+        Token::Keyword(Keyword::DATE) => DataType::Date,
+        Token::Keyword(Keyword::TIMESTAMP) => DataType::Timestamp,
+
         token => return Err(Error::Parse(format!("Unexpected token {}", token)))
       },
 
@@ -121,7 +230,7 @@ impl<'a> Parser<'a> {
 
         Keyword::NULL => {
           if let Some(false)= column.nullable {
-            return Err(Error::Value(
+            return Err(Error::Value(ErrorCode::InvalidColumnDefinition,
               format!("Column {} can't be both not-nullable and nullable", column.name))
             )
           }
@@ -131,7 +240,7 @@ impl<'a> Parser<'a> {
         Keyword::NOT => {
           self.nextExpectedToken(Some(Keyword::NULL.into( )))?;
           if let Some(true)= column.nullable {
-            return Err(Error::Value(
+            return Err(Error::Value(ErrorCode::InvalidColumnDefinition,
               format!("Column {} can't be both nullable and not-nullable", column.name)))
           }
           column.nullable= Some(false)
@@ -139,7 +248,24 @@ impl<'a> Parser<'a> {
 
         Keyword::DEFAULT => column.default= Some(self.parseExpression(0)?),
 
-        Keyword::REFERENCES => column.references= Some(self.nextIdentifier( )?),
+        Keyword::REFERENCES => {
+          column.references= Some(self.nextIdentifier( )?);
+
+          if self.nextTokenIfIts(Keyword::ON.into( )).is_some( ) {
+            self.nextExpectedToken(Some(Keyword::DELETE.into( )))?;
+
+            column.onDelete= match self.nextToken( )? {
+              Token::Keyword(Keyword::RESTRICT) => ReferentialAction::Restrict,
+              Token::Keyword(Keyword::CASCADE) => ReferentialAction::Cascade,
+              Token::Keyword(Keyword::SET) => {
+                self.nextExpectedToken(Some(Keyword::NULL.into( )))?;
+                ReferentialAction::SetNull
+              },
+
+              token => return Err(Error::Parse(format!("Expected RESTRICT, CASCADE or SET NULL, found {}", token)))
+            };
+          }
+        },
 
         keyword => return Err(Error::Parse(format!("Unexpected keyword {}", keyword))),
       }
@@ -205,6 +331,38 @@ impl<'a> Parser<'a> {
       }
       else { None };
 
+    let source= match self.peekNextToken( )? {
+      Some(Token::Keyword(Keyword::SELECT)) => InsertSource::Select(Box::new(self.parseSelectStatement( )?)),
+      _ => InsertSource::Values(self.parseValuesClause( )?)
+    };
+
+    Ok(Statement::Insert { table, columns, source, onConflict: self.parseOnConflictClause( )? })
+  }
+
+  // Parses the optional ON CONFLICT (<column>) DO NOTHING / DO UPDATE SET ... clause of an INSERT.
+  fn parseOnConflictClause(&mut self) -> Result<Option<OnConflict>> {
+    if self.nextTokenIfIts(Keyword::ON.into( )).is_none( ) {
+      return Ok(None)}
+
+    self.nextExpectedToken(Some(Keyword::CONFLICT.into( )))?;
+    self.nextExpectedToken(Some(Token::OpenParenthesis))?;
+    let column= self.nextIdentifier( )?;
+    self.nextExpectedToken(Some(Token::CloseParenthesis))?;
+
+    self.nextExpectedToken(Some(Keyword::DO.into( )))?;
+    let updates= match self.nextToken( )? {
+      Token::Keyword(Keyword::NOTHING) => None,
+      Token::Keyword(Keyword::UPDATE) => {
+        self.nextExpectedToken(Some(Keyword::SET.into( )))?;
+        Some(self.parseAssignments( )?)
+      },
+      token => return Err(Error::Parse(format!("Expected NOTHING / UPDATE keyword, got {}", token)))
+    };
+
+    Ok(Some(OnConflict { column, updates }))
+  }
+
+  fn parseValuesClause(&mut self) -> Result<Vec<Vec<Expression>>> {
     self.nextExpectedToken(Some(Keyword::VALUES.into( )))?;
 
     let mut values= vec![ ];
@@ -226,12 +384,15 @@ impl<'a> Parser<'a> {
       }
     }
 
-    Ok(Statement::Insert { table, columns, values })
+    Ok(values)
   }
 
   fn parseSelectStatement(&mut self) -> Result<Statement> {
+    let (distinct, selections)= self.parseSelectClause( )?;
+
     Ok(Statement::Select {
-      selections: self.parseSelectClause( )?,
+      distinct,
+      selections,
       from:       self.parseFromClause( )?,
       r#where:    self.parseWhereClause( )?,
       groupBy:    self.parseGroupByClause( )?,
@@ -251,6 +412,13 @@ impl<'a> Parser<'a> {
     let table= self.nextIdentifier( )?;
 
     self.nextExpectedToken(Some(Keyword::SET.into( )))?;
+    let updates= self.parseAssignments( )?;
+
+    Ok(Statement::Update { table, updates, r#where: self.parseWhereClause( )? })
+  }
+
+  // Parses the <column> = <expression>, ... list of an UPDATE SET.
+  fn parseAssignments(&mut self) -> Result<BTreeMap<String, Expression>> {
     let mut updates= BTreeMap::new( );
     loop {
       let column= self.nextIdentifier( )?;
@@ -258,15 +426,14 @@ impl<'a> Parser<'a> {
       let value= self.parseExpression(0)?;
 
       if updates.contains_key(&column) {
-        return Err(Error::Value(format!("Duplicate values for column {}", &column)))}
+        return Err(Error::Value(ErrorCode::DuplicateColumn, format!("Duplicate values for column {}", &column)))}
       updates.insert(column, value);
 
       if self.nextTokenIfIts(Token::Comma).is_none( ) {
         break
       }
     }
-
-    Ok(Statement::Update { table, updates, r#where: self.parseWhereClause( )? })
+    Ok(updates)
   }
 
   fn parseDeleteStatement(&mut self) -> Result<Statement> {
@@ -276,24 +443,108 @@ impl<'a> Parser<'a> {
     Ok(Statement::Delete { table: self.nextIdentifier( )?, r#where: self.parseWhereClause( )? })
   }
 
+  // Parses TRUNCATE [TABLE] <table>.
+  fn parseTruncateStatement(&mut self) -> Result<Statement> {
+    self.nextExpectedToken(Some(Keyword::TRUNCATE.into( )))?;
+    self.nextTokenIfIts(Keyword::TABLE.into( ));
+
+    Ok(Statement::Truncate(self.nextIdentifier( )?))
+  }
+
+  // Parses COPY <table> FROM STDIN.
+  fn parseCopyStatement(&mut self) -> Result<Statement> {
+    self.nextExpectedToken(Some(Keyword::COPY.into( )))?;
+    let table= self.nextIdentifier( )?;
+
+    self.nextExpectedToken(Some(Keyword::FROM.into( )))?;
+    self.nextExpectedToken(Some(Keyword::STDIN.into( )))?;
+
+    Ok(Statement::Copy { table })
+  }
+
+  // Parses ANALYZE [<table>].
+  fn parseAnalyzeStatement(&mut self) -> Result<Statement> {
+    self.nextExpectedToken(Some(Keyword::ANALYZE.into( )))?;
+    self.parseAnalyzedTable( )
+  }
+
+  // Parses the table of an ANALYZE statement (if any), following the ANALYZE keyword.
+  fn parseAnalyzedTable(&mut self) -> Result<Statement> {
+    let table= match self.nextTokenIf(|token| matches!(token, Token::Identifier(_))) {
+      Some(Token::Identifier(table)) => Some(table),
+      _ => None
+    };
+    Ok(Statement::Analyze(table))
+  }
+
   fn parseExplainStatement(&mut self) -> Result<Statement> {
     self.nextExpectedToken(Some(Keyword::EXPLAIN.into( )))?;
+
+    let analyze= self.nextTokenIfIts(Keyword::ANALYZE.into( )).is_some( );
+
+    // EXPLAIN ANALYZE [<table>] explains an ANALYZE statement, rather than analyzing the execution of
+    // a statement (that's EXPLAIN ANALYZE ANALYZE [<table>]).
+    if analyze && matches!(self.peekNextToken( )?, None | Some(Token::Semicolon | Token::Identifier(_))) {
+      return Ok(Statement::Explain { statement: Box::new(self.parseAnalyzedTable( )?), analyze: false })}
     if let Some(Token::Keyword(Keyword::EXPLAIN)) = self.peekNextToken( )? {
       return Err(Error::Parse("Cannot nest EXPLAIN statements".into( )))}
 
-    Ok(Statement::Explain(Box::new(self.parseStatement( )?)))
+    Ok(Statement::Explain { statement: Box::new(self.parseStatement( )?), analyze })
+  }
+
+  // Parses SET <variable> = <expression>.
+  fn parseSetStatement(&mut self) -> Result<Statement> {
+    self.nextExpectedToken(Some(Keyword::SET.into( )))?;
+
+    let variable= self.nextIdentifier( )?;
+    self.nextExpectedToken(Some(Token::Equal))?;
+
+    Ok(Statement::Set { variable, value: self.parseExpression(0)? })
+  }
+
+  // Parses SHOW TABLES, SHOW TABLE <name> / DESCRIBE <name>, SHOW STATUS / STATUS, and SHOW
+  // <variable> / SHOW ALL.
+  fn parseIntrospectionStatement(&mut self) -> Result<Statement> {
+    match self.nextToken( )? {
+      Token::Keyword(Keyword::SHOW) => match self.nextToken( )? {
+        Token::Keyword(Keyword::TABLES) => Ok(Statement::ShowTables),
+        Token::Keyword(Keyword::TABLE) => Ok(Statement::DescribeTable(self.nextIdentifier( )?)),
+        Token::Keyword(Keyword::STATUS) => Ok(Statement::Status),
+        Token::Keyword(Keyword::ALL) => Ok(Statement::ShowVariables),
+        Token::Identifier(variable) => Ok(Statement::ShowVariable(variable)),
+        token => Err(Error::Parse(format!("Expected TABLES / TABLE / STATUS / ALL keyword or a variable, got {}", token)))
+      },
+
+      Token::Keyword(Keyword::DESCRIBE) => Ok(Statement::DescribeTable(self.nextIdentifier( )?)),
+      Token::Keyword(Keyword::STATUS) => Ok(Statement::Status),
+
+      token => Err(Error::Parse(format!("Expected SHOW / DESCRIBE / STATUS keyword, got {}", token)))
+    }
   }
 
-  fn parseSelectClause(&mut self) -> Result<Vec<(Expression, Option<AliasColumnName>)>> {
+  /*
+    Parses SELECT [DISTINCT] followed by the selections, returning whether DISTINCT is given.
+
+    A bare * (selecting all the columns) must be the only selection, while the columns of a table
+    (table.*) can be selected along with other selections.
+  */
+  fn parseSelectClause(&mut self) -> Result<(bool, Selections)> {
     self.nextExpectedToken(Some(Keyword::SELECT.into( )))?;
+    let distinct= self.nextTokenIfIts(Keyword::DISTINCT.into( )).is_some( );
 
     let mut selections= vec![ ];
     loop {
-      if self.nextTokenIfIts(Token::Asterisk).is_some( ) && selections.is_empty( ) {
-        return Ok(selections)}
+      if self.nextTokenIfIts(Token::Asterisk).is_some( ) {
+        if !selections.is_empty( ) || self.peekNextToken( )? == Some(Token::Comma) {
+          return Err(Error::Parse("* can't be selected along with other selections, select table.* instead".to_string( )))}
+
+        return Ok((distinct, selections))
+      }
 
       let expression= self.parseExpression(0)?;
       let label= match self.peekNextToken( )? {
+        // The selected columns of a table keep their names.
+        _ if matches!(expression, Expression::QualifiedWildcard(_)) => None,
 
         Some(Token::Keyword(Keyword::AS)) => {
           let _= self.nextToken( )?;
@@ -309,30 +560,30 @@ impl<'a> Parser<'a> {
         break
       }
     }
-    Ok(selections)
+    Ok((distinct, selections))
   }
 
   fn parseFromClause(&mut self) -> Result<Vec<SearchField>> {
-    self.nextExpectedToken(Some(Keyword::FROM.into( )))?;
-
     let mut searchFields= vec![ ];
+
+    // The FROM clause is optional (like in SELECT 1 + 2).
+    if self.nextTokenIfIts(Keyword::FROM.into( )).is_none( ) {
+      return Ok(searchFields)}
+
     loop {
       let mut searchField= self.parseFromTableClause( )?;
 
-      while let Some(joinType)= self.parseJoinClause( )? {
+      while let Some((joinType, natural))= self.parseJoinClause( )? {
         searchField= SearchField::Join {
           left: Box::new(searchField),
           right: Box::new(self.parseFromTableClause( )?),
 
-          predicate: match &joinType {
-            JoinType::Cross => None,
-
-            _ => {
-              self.nextExpectedToken(Some(Keyword::ON.into( )))?;
-              Some(self.parseExpression(0)?)
-            }
+          constraint: match (&joinType, natural) {
+            (JoinType::Cross, _) => JoinConstraint::None,
+            (_, true) => JoinConstraint::Natural,
+            _ => self.parseJoinConstraint( )?
           },
- 
+
           r#type: joinType
         };
       }
@@ -361,24 +612,61 @@ impl<'a> Parser<'a> {
     Ok(SearchField::Table { name: tablename, alias })
   }
 
-  fn parseJoinClause(&mut self) -> Result<Option<JoinType>> {
+  // Parses the type of a join, returning it along with whether it's a NATURAL join.
+  fn parseJoinClause(&mut self) -> Result<Option<(JoinType, bool)>> {
+    let natural= self.nextTokenIfIts(Keyword::NATURAL.into( )).is_some( );
+
     // Only writing JOIN indiciates an INNER JOIN.
     if self.nextTokenIfIts(Keyword::JOIN.into( )).is_some( ) {
-      return Ok(Some(JoinType::Inner))}
+      return Ok(Some((JoinType::Inner, natural)))}
 
+    // NOTE : A cross join has nothing to match the rows on, so it can't be NATURAL.
     let joinType= match self.peekNextToken( )? {
       Some(Token::Keyword(keyword)) => match keyword {
-        Keyword::CROSS => JoinType::Cross,
+        Keyword::CROSS if !natural => JoinType::Cross,
         Keyword::INNER => JoinType::Inner,
         Keyword::LEFT => JoinType::Left,
         Keyword::RIGHT => JoinType::Right,
 
-        _ => return Ok(None)
+        _ => return self.parseNaturalJoinEnd(natural)
       },
-      _ => return Ok(None),
+      _ => return self.parseNaturalJoinEnd(natural)
     };
+    let _= self.nextToken( )?;
+
+    if matches!(joinType, JoinType::Left | JoinType::Right) {
+      self.nextTokenIfIts(Keyword::OUTER.into( ));}
+
     self.nextExpectedToken(Some(Keyword::JOIN.into( )))?;
-    Ok(Some(joinType))
+    Ok(Some((joinType, natural)))
+  }
+
+  // Returns that there's no join, unless the NATURAL keyword was given (which must be followed by
+  // a join).
+  fn parseNaturalJoinEnd(&mut self, natural: bool) -> Result<Option<(JoinType, bool)>> {
+    if natural {
+      self.nextExpectedToken(Some(Keyword::JOIN.into( )))?;}
+    Ok(None)
+  }
+
+  // Parses the ON <predicate> or USING (<column>, ...) clause of a join.
+  fn parseJoinConstraint(&mut self) -> Result<JoinConstraint> {
+    if self.nextTokenIfIts(Keyword::USING.into( )).is_none( ) {
+      self.nextExpectedToken(Some(Keyword::ON.into( )))?;
+      return Ok(JoinConstraint::On(self.parseExpression(0)?))
+    }
+
+    self.nextExpectedToken(Some(Token::OpenParenthesis))?;
+    let mut columns= vec![ ];
+    loop {
+      columns.push(self.nextIdentifier( )?);
+      match self.nextToken( )? {
+        Token::CloseParenthesis => break,
+        Token::Comma => continue,
+        token => return Err(Error::Parse(format!("Unexpected token {}", token))),
+      }
+    }
+    Ok(JoinConstraint::Using(columns))
   }
 
   fn parseWhereClause(&mut self) -> Result<Option<Expression>> {
@@ -390,7 +678,7 @@ impl<'a> Parser<'a> {
   fn parseGroupByClause(&mut self) -> Result<Vec<Expression>> {
     let mut groupingParameters= vec![ ];
 
-    if self.nextTokenIfIts(Keyword::WHERE.into( )).is_none( ) {
+    if self.nextTokenIfIts(Keyword::GROUP.into( )).is_none( ) {
       return Ok(groupingParameters)}
 
     self.nextExpectedToken(Some(Keyword::BY.into( )))?;
@@ -418,12 +706,14 @@ impl<'a> Parser<'a> {
 
     let mut orderingRules= vec![ ];
     loop {
-      orderingRules.push((
-        self.parseExpression(0)?,
-        self.nextTokenIfIts(Keyword::DESC.into( ))
-          .map(|_| Order::Descending)
-          .unwrap_or(Order::Ascending)
-      ));
+      let expression= self.parseExpression(0)?;
+
+      let order= match self.nextTokenIf(|token| matches!(token, Token::Keyword(Keyword::ASC | Keyword::DESC))) {
+        Some(Token::Keyword(Keyword::DESC)) => Order::Descending,
+        _ => Order::Ascending
+      };
+
+      orderingRules.push((expression, order));
 
       if self.nextTokenIfIts(Token::Comma).is_none( ) {
         break
@@ -435,23 +725,68 @@ impl<'a> Parser<'a> {
   // Parses an expression containing atleast one operand operated on by any number of operands.
   // An example expression : -5 * 2! + 3.
   // NOTE : It uses the Precedance Climbing Algorithm.
-  // FIX: Case -5! - since factorials of negative numbers cannot be calculated.
+  // NOTE : Prefix operators bind tighter than postfix ones. So -5! is parsed as (-5)!, which fails
+  // during evaluation - since factorials of negative numbers cannot be calculated.
   fn parseExpression(&mut self, minOperatorPrecedance: Precedance) -> Result<Expression> {
+    self.depth += 1;
+    let expression= self.parseNestedExpression(minOperatorPrecedance);
+    self.depth -= 1;
+    expression
+  }
+
+  fn parseNestedExpression(&mut self, minOperatorPrecedance: Precedance) -> Result<Expression> {
+    self.checkDepth(0)?;
+
     let mut lhs=
       if let Some(prefixOperator)= self.nextIfOperator::<PrefixOperator>(minOperatorPrecedance)? {
-        self.parseExpression(minOperatorPrecedance + prefixOperator.associativity( ) as Precedance)?}
+        let operand= self.parseExpression(prefixOperator.precedance( ) + prefixOperator.associativity( ) as Precedance)?;
+        prefixOperator.operate(operand)
+      }
       else {
         self.parseExpressionOperand( )?};
 
-    while let Some(postfixOperator)= self.nextIfOperator::<PostfixOperator>(minOperatorPrecedance)? {
-      lhs= postfixOperator.operate(lhs);}
+    // The operand of an infix operator, only binds operators with a higher precedance (or the same
+    // precedance, if the operator is right associative).
+    // NOTE : Every operator applied here nests the expression parsed so far one level deeper.
+    let mut nesting= 0;
+    loop {
+      if let Some(postfixOperator)= self.nextIfOperator::<PostfixOperator>(minOperatorPrecedance)? {
+        lhs= postfixOperator.operate(lhs);}
+
+      else if let Some(infixOperator)= self.nextIfOperator::<InfixOperator>(minOperatorPrecedance)? {
+        let rhs= self.parseExpression(infixOperator.precedance( ) + infixOperator.associativity( ) as Precedance)?;
 
-    while let Some(infixOperator)= self.nextIfOperator::<InfixOperator>(minOperatorPrecedance)? {
-      lhs= infixOperator.operate(lhs, self.parseExpression(minOperatorPrecedance + infixOperator.associativity( ) as Precedance)?);}
+        if matches!(infixOperator, InfixOperator::In { .. }) && !matches!(rhs, Expression::Subquery(_)) {
+          return Err(Error::Parse(format!("Expected a subquery after IN, found {}", rhs)))}
+
+        lhs= match infixOperator {
+          // The pattern of LIKE can be followed by an ESCAPE clause, with the escape character.
+          InfixOperator::Like if self.nextTokenIfIts(Keyword::ESCAPE.into( )).is_some( ) => {
+            let escape= self.parseExpression(infixOperator.precedance( ) + 1)?;
+            Operation::Like(Box::new(lhs), Box::new(rhs), Some(Box::new(escape))).into( )
+          },
+
+          infixOperator => infixOperator.operate(lhs, rhs)
+        };
+      }
+
+      else {
+        break}
+
+      nesting += 1;
+      self.checkDepth(nesting)?;
+    }
 
     Ok(lhs)
   }
 
+  fn checkDepth(&self, nesting: usize) -> Result<( )> {
+    if self.depth + nesting > MAX_EXPRESSION_DEPTH {
+      return Err(Error::Parse(format!("Expression is nested too deeply, the maximum depth is {}", MAX_EXPRESSION_DEPTH)))}
+
+    Ok(( ))
+  }
+
   fn parseExpressionOperand(&mut self) -> Result<Expression> {
     Ok(match self.nextToken( )? {
 
@@ -459,15 +794,30 @@ impl<'a> Parser<'a> {
         if self.nextTokenIfIts(Token::OpenParenthesis).is_some( ) {
           let mut arguments= vec![ ];
 
-          while self.nextTokenIfIts(Token::CloseParenthesis).is_some( ) {
+          while self.nextTokenIfIts(Token::CloseParenthesis).is_none( ) {
             if !arguments.is_empty( ) {
               self.nextExpectedToken(Some(Token::Comma))?;}
 
+            // Handling POSITION(substring IN string), which is parsed like POSITION(substring, string).
+            // NOTE : The substring binds only operators with a higher precedance than IN's.
+            if (identifier == "position") && arguments.is_empty( ) {
+              let substring= self.parseExpression(InfixOperator::In { not: false }.precedance( ) + 1)?;
+              arguments.push(substring);
+
+              if self.nextTokenIfIts(Keyword::IN.into( )).is_some( ) {
+                arguments.push(self.parseExpression(0)?);}
+              continue
+            }
+
             arguments.push(
               // Handling COUNT(*).
-              if (identifier == "COUNT") && self.nextExpectedToken(Some(Token::Asterisk))?.is_some( ) {
+              if (identifier == "count") && self.nextTokenIfIts(Token::Asterisk).is_some( ) {
                 Literal::Boolean(true).into( )}
 
+              // Handling the DISTINCT argument of an aggregate function (like COUNT(DISTINCT x)).
+              else if arguments.is_empty( ) && self.nextTokenIfIts(Keyword::DISTINCT.into( )).is_some( ) {
+                Expression::Distinct(Box::new(self.parseExpression(0)?))}
+
               else { self.parseExpression(0)? }
             );
           }
@@ -475,10 +825,14 @@ impl<'a> Parser<'a> {
           Expression::FunctionCall(identifier, arguments)
         }
         else {
-          let mut field= self.nextIdentifier( )?;
+          let mut field= identifier;
 
           let mut relation= None;
           if self.nextTokenIfIts(Token::Period).is_some( ) {
+            // Handling table.* (only valid as a selection).
+            if self.nextTokenIfIts(Token::Asterisk).is_some( ) {
+              return Ok(Expression::QualifiedWildcard(field))}
+
             relation= Some(field);
             field= self.nextIdentifier( )?;
           }
@@ -487,20 +841,37 @@ impl<'a> Parser<'a> {
         }
       },
 
-      Token::Number(value) =>
-        if value.chars( ).all(|character| character.is_ascii_digit( )) {
-          Literal::Integer(value.parse( )?).into( )}
-        else {
-          Literal::Float(value.parse( )?).into( )},
+      Token::Number(value) => Literal::from(numeric::parseNumber(&value)?).into( ),
+
+      Token::OpenParenthesis if matches!(self.peekNextToken( ), Ok(Some(Token::Keyword(Keyword::SELECT)))) => {
+        let subquery= self.parseSelectStatement( )?;
+        self.nextExpectedToken(Some(Token::CloseParenthesis))?;
+        Expression::Subquery(Box::new(subquery))
+      },
 
       Token::OpenParenthesis => {
         let expression= self.parseExpression(0)?;
-        self.nextExpectedToken(Some(Token::CloseParenthesis.into( )))?;
+        self.nextExpectedToken(Some(Token::CloseParenthesis))?;
         expression
       },
 
       Token::String(value) => Literal::String(value).into( ),
 
+      Token::Question if self.numberedParameters => return Err(mixedParameters( )),
+      Token::Question => {
+        self.positionalParameters += 1;
+        Expression::Parameter(self.positionalParameters)
+      },
+
+      Token::Parameter(_) if self.positionalParameters > 0 => return Err(mixedParameters( )),
+      Token::Parameter(number) => {
+        self.numberedParameters= true;
+        match number.parse( ) {
+          Ok(number) if number > 0 => Expression::Parameter(number),
+          _ => return Err(Error::Parse(format!("Invalid parameter ${}, parameters are numbered from 1", number)))
+        }
+      },
+
       Token::Keyword(Keyword::FALSE) => Literal::Boolean(false).into( ),
       Token::Keyword(Keyword::TRUE) => Literal::Boolean(true).into( ),
 
@@ -509,27 +880,87 @@ impl<'a> Parser<'a> {
 
       Token::Keyword(Keyword::NULL) => Literal::Null.into( ),
 
+      // Typed literals, like DATE '2024-01-15' and TIMESTAMP '2024-01-15 10:30:00'.
+      Token::Keyword(Keyword::DATE) => {
+        let text= self.nextString( )?;
+        let days= datetime::parseDate(&text)
+                    .ok_or_else(| | Error::Parse(format!("Invalid date '{}', expected YYYY-MM-DD", text)))?;
+        Literal::Date(days).into( )
+      },
+      Token::Keyword(Keyword::TIMESTAMP) => {
+        let text= self.nextString( )?;
+        let timestamp= datetime::parseTimestamp(&text)
+                         .ok_or_else(| | Error::Parse(format!("Invalid timestamp '{}', expected YYYY-MM-DD HH:MM:SS[.ffffff]", text)))?;
+        Literal::Timestamp(timestamp).into( )
+      },
+
+      Token::Keyword(Keyword::CASE) => self.parseCase( )?,
+
       token => return Err(Error::Parse(format!("Expected expression operand, found {}", token))),
     })
   }
+
+  // Parses the rest of a CASE expression (after CASE) :
+  // CASE [<operand>] WHEN <condition> THEN <result> [WHEN ...] [ELSE <result>] END.
+  fn parseCase(&mut self) -> Result<Expression> {
+    let operand= match self.peekNextToken( )? {
+      Some(Token::Keyword(Keyword::WHEN)) => None,
+      _ => Some(Box::new(self.parseExpression(0)?))
+    };
+
+    let mut branches= vec![ ];
+    while self.nextTokenIfIts(Keyword::WHEN.into( )).is_some( ) {
+      let condition= self.parseExpression(0)?;
+      self.nextExpectedToken(Some(Keyword::THEN.into( )))?;
+
+      branches.push((condition, self.parseExpression(0)?));
+    }
+    if branches.is_empty( ) {
+      return Err(Error::Parse("CASE expects atleast one WHEN branch".to_string( )))}
+
+    let otherwise= self.nextTokenIfIts(Keyword::ELSE.into( ))
+                       .map(|_| self.parseExpression(0))
+                       .transpose( )?;
+    self.nextExpectedToken(Some(Keyword::END.into( )))?;
+
+    Ok(Operation::Case { operand, branches, otherwise: otherwise.map(Box::new) }.into( ))
+  }
 }
 
 impl<'a> Parser<'a> {
   pub fn new(input: &'a str) -> Self {
-    return Parser {
-      lexer: Lexer::new(input).peekable( )
+    let lexer= Lexer::new(input);
+    Parser {
+      position: lexer.position( ),
+      end: lexer.position( ),
+      lexer: lexer.peekable( ),
+
+      positionalParameters: 0,
+      numberedParameters: false,
+
+      depth: 0
     }
   }
 
   // Gets the next lexed token and returns it. Returns error, if not found.
   fn nextToken(&mut self) -> Result<Token> {
-    self.lexer.next( )
-      .unwrap_or_else(| | Err(Error::Parse("Unexpected end of tokens".into( ))))
+    let Some((token, span))= self.lexer.next( ).transpose( )? else {
+      self.position= self.end;
+      return Err(Error::Parse("Unexpected end of tokens".into( )))
+    };
+    self.position= span.start;
+    self.end= span.end;
+    Ok(token)
   }
 
   // Peeks for the next lexed token and returns it.
   fn peekNextToken(&mut self) -> Result<Option<Token>> {
-    self.lexer.peek( ).cloned( ).transpose( )
+    let Some((token, span))= self.lexer.peek( ).cloned( ).transpose( )? else {
+      self.position= self.end;
+      return Ok(None)
+    };
+    self.position= span.start;
+    Ok(Some(token))
   }
 
   // If the next lexed token matches the given expected token, then grabs and returns it. Otherwise,
@@ -556,6 +987,14 @@ impl<'a> Parser<'a> {
     }
   }
 
+  // Gets the next lexed string token and returns its value. Returns error, if not found.
+  fn nextString(&mut self) -> Result<String> {
+    match self.nextToken( )? {
+      Token::String(value) => Ok(value),
+      token => Err(Error::Parse(format!("Expected string, got {}", token)))
+    }
+  }
+
   // Grabs and returns the next token, if it satisfies the given predicate function.
   fn nextTokenIf<F: Fn(&Token) -> bool>(&mut self, predicate: F) -> Option<Token> {
     self.peekNextToken( ).unwrap_or(None)
@@ -588,4 +1027,8 @@ impl<'a> Parser<'a> {
 
     Ok(None)
   }
-}
\ No newline at end of file
+}
+
+fn mixedParameters( ) -> Error {
+  Error::Parse("Can't mix ? and numbered ($n) parameter placeholders".into( ))
+}
@@ -4,6 +4,7 @@ use super::{ast::{Expression, Operation}, token::{Keyword, Token}, Parser};
 pub type Precedance= u8;
 
 // Represents whether the operator for the given operand is on the left / right.
+#[derive(Clone, Copy, PartialEq)]
 pub enum Associativity {
   Left = 1,
   Right = 0
@@ -49,8 +50,12 @@ impl Operator for PrefixOperator {
     Associativity::Right
   }
 
+  // NOT binds looser than the comparison operators (so NOT a = b is NOT (a = b)).
   fn precedance(&self) -> Precedance {
-    9
+    match self {
+      Self::Not => 3,
+      Self::Minus | Self::Plus => 9
+    }
   }
 }
 
@@ -72,6 +77,7 @@ pub enum InfixOperator {
   Multiply,
   Subtract,
   Exponentiate,
+  Concatenate,
 
   Equal,
   NotEqual,
@@ -82,7 +88,13 @@ pub enum InfixOperator {
 
   And,
   Or,
-  Like
+  Like,
+
+  // Checks whether the value is one of the values of a subquery. NOT IN is parsed from NOT, since
+  // the operator is picked by its first token.
+  In {
+    not: bool
+  }
 }
 
 impl Operator for InfixOperator {
@@ -94,6 +106,7 @@ impl Operator for InfixOperator {
       Token::Percent => Self::Modulo,
       Token::Plus => Self::Add,
       Token::Slash => Self::Divide,
+      Token::Concatenate => Self::Concatenate,
 
       Token::Equal => Self::Equal,
       Token::NotEqual => Self::NotEqual,
@@ -107,18 +120,24 @@ impl Operator for InfixOperator {
       Token::Keyword(Keyword::OR) => Self::Or,
       Token::Keyword(Keyword::LIKE) => Self::Like,
 
+      Token::Keyword(Keyword::IN) => Self::In { not: false },
+      Token::Keyword(Keyword::NOT) => Self::In { not: true },
+
       _ => return None
     })
   }
 
-  fn augment(self, _parser: &mut Parser) -> Result<Self> {
+  fn augment(self, parser: &mut Parser) -> Result<Self> {
+    if let Self::In { not: true }= self {
+      parser.nextExpectedToken(Some(Keyword::IN.into( )))?;}
+
     Ok(self)
   }
 
   fn associativity(&self) -> Associativity {
     match self {
-      Self::Exponentiate => Associativity::Left,
-      _ => Associativity::Right
+      Self::Exponentiate => Associativity::Right,
+      _ => Associativity::Left
     }
   }
 
@@ -127,14 +146,14 @@ impl Operator for InfixOperator {
       Self::Or => 1,
       Self::And => 2,
 
-      Self::Equal | Self::NotEqual | Self::Like => 3,
+      Self::Equal | Self::NotEqual | Self::Like | Self::In { .. } => 3,
 
       Self::GreaterThan
       | Self::GreaterThanOrEqual
       | Self::LessThan
       | Self::LessThanOrEqual => 4,
 
-      Self::Add | Self::Subtract => 5,
+      Self::Add | Self::Subtract | Self::Concatenate => 5,
       Self::Multiply | Self::Divide | Self::Modulo => 6,
       Self::Exponentiate => 7
     }
@@ -153,6 +172,7 @@ impl InfixOperator {
       Self::Divide => Operation::Divide(lhs, rhs),
       Self::Modulo => Operation::Modulo(lhs, rhs),
       Self::Exponentiate => Operation::Exponentiate(lhs, rhs),
+      Self::Concatenate => Operation::Concatenate(lhs, rhs),
 
       Self::Equal => Operation::Equal(lhs, rhs),
       Self::NotEqual => Operation::NotEqual(lhs, rhs),
@@ -163,7 +183,10 @@ impl InfixOperator {
 
       Self::And => Operation::And(lhs, rhs),
       Self::Or => Operation::Or(lhs, rhs),
-      Self::Like => Operation::Like(lhs, rhs)
+      Self::Like => Operation::Like(lhs, rhs, None),
+
+      Self::In { not: false } => Operation::In(lhs, rhs),
+      Self::In { not: true } => Operation::Not(Box::new(Operation::In(lhs, rhs).into( )))
     }.into( )
   }
 }
@@ -222,4 +245,44 @@ impl PostfixOperator {
       Self::Factorial => Operation::Factorial(lhs)
     }.into( )
   }
-}
\ No newline at end of file
+}
+impl Operation {
+  // Returns the precedance and associativity of the operator, which the operation is parsed from.
+  pub fn precedance(&self) -> (Precedance, Associativity) {
+    let prefix= |operator: PrefixOperator| (operator.precedance( ), operator.associativity( ));
+    let infix= |operator: InfixOperator| (operator.precedance( ), operator.associativity( ));
+    let postfix= |operator: PostfixOperator| (operator.precedance( ), operator.associativity( ));
+
+    match self {
+      Self::And(..) => infix(InfixOperator::And),
+      Self::Not(_) => prefix(PrefixOperator::Not),
+      Self::Or(..) => infix(InfixOperator::Or),
+
+      Self::Equal(..) => infix(InfixOperator::Equal),
+      Self::GreaterThan(..) => infix(InfixOperator::GreaterThan),
+      Self::GreaterThanOrEqual(..) => infix(InfixOperator::GreaterThanOrEqual),
+      Self::IsNull(_) => postfix(PostfixOperator::IsNull { not: false }),
+      Self::LessThan(..) => infix(InfixOperator::LessThan),
+      Self::LessThanOrEqual(..) => infix(InfixOperator::LessThanOrEqual),
+      Self::NotEqual(..) => infix(InfixOperator::NotEqual),
+
+      Self::Add(..) => infix(InfixOperator::Add),
+      Self::Assert(_) => prefix(PrefixOperator::Plus),
+      Self::Divide(..) => infix(InfixOperator::Divide),
+      Self::Exponentiate(..) => infix(InfixOperator::Exponentiate),
+      Self::Factorial(_) => postfix(PostfixOperator::Factorial),
+      Self::Modulo(..) => infix(InfixOperator::Modulo),
+      Self::Multiply(..) => infix(InfixOperator::Multiply),
+      Self::Negate(_) => prefix(PrefixOperator::Minus),
+      Self::Subtract(..) => infix(InfixOperator::Subtract),
+
+      Self::Concatenate(..) => infix(InfixOperator::Concatenate),
+
+      Self::Like(..) => infix(InfixOperator::Like),
+      Self::In(..) => infix(InfixOperator::In { not: false }),
+
+      // CASE is delimited by its keywords, so it never needs to be parenthesized.
+      Self::Case { .. } => (Precedance::MAX, Associativity::Left)
+    }
+  }
+}
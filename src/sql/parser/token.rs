@@ -1,10 +1,13 @@
 use std::fmt::Display;
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
   Number(String),
   String(String),
 
+  // A numbered parameter placeholder (like $1), holding its number.
+  Parameter(String),
+
   Identifier(String), // Represents name of a database object (like table, column, index etc.)
   Period,
 
@@ -24,6 +27,7 @@ pub enum Token {
   Slash,
   Caret,
   Percent,
+  Concatenate,
 
   Exclamation,
   Comma,
@@ -40,6 +44,8 @@ impl Display for Token {
       Token::Number(n) => n,
       Token::String(s) => s,
 
+      Token::Parameter(n) => return write!(f, "${}", n),
+
       Token::Identifier(s) => s,
       Token::Period => ".",
 
@@ -59,6 +65,7 @@ impl Display for Token {
       Token::Slash => "/",
       Token::Caret => "^",
       Token::Percent => "%",
+      Token::Concatenate => "||",
 
       Token::Exclamation => "!",
       Token::Comma => ",",
@@ -73,12 +80,47 @@ impl Display for Token {
 
 impl From<Keyword> for Token {
   fn from(keyword: Keyword) -> Self {
-    Token::Identifier(keyword.to_str( ).to_string( ))
+    Token::Keyword(keyword)
   }
 }
 
-#[derive(Clone, PartialEq, Eq)]
-pub enum Keyword {
+/*
+  Generates the Keyword enum from the list of keywords, along with the conversions between a keyword
+  and its string form. The list is the single source of truth for what's reserved : an identifier
+  scanned by the lexer is a keyword, iff it's (case-insensitively) in the list.
+*/
+macro_rules! keywords {
+  ($($keyword: ident),* $(,)?) => {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Keyword {
+      $($keyword),*
+    }
+
+    impl Keyword {
+      // All the keywords, in the order they're listed.
+      pub const ALL: &'static [Keyword]= &[$(Keyword::$keyword),*];
+
+      // Returns the keyword matching the identifier, ignoring the case.
+      pub fn from_str(identifier: &str) -> Option<Self> {
+        Some(match identifier.to_uppercase( ).as_ref( ) {
+          $(stringify!($keyword) => Self::$keyword,)*
+          _ => return None
+        })
+      }
+
+      pub fn to_str(&self) -> &'static str {
+        match self {
+          $(Self::$keyword => stringify!($keyword)),*
+        }
+      }
+    }
+  };
+}
+
+keywords! {
+  ALL,
+  ALTER,
+  ANALYZE,
   AND,
   AS,
   ASC,
@@ -86,21 +128,36 @@ pub enum Keyword {
   BOOL,
   BOOLEAN,
   BY,
+  CASCADE,
+  CASE,
   CHAR,
+  COLUMN,
   COMMIT,
+  CONFLICT,
+  COPY,
   CREATE,
   CROSS,
+  DATE,
   DEFAULT,
   DELETE,
   DESC,
+  DESCRIBE,
+  DISTINCT,
+  DO,
   DOUBLE,
   DROP,
+  ELSE,
+  END,
+  ESCAPE,
+  EXISTS,
   EXPLAIN,
   FALSE,
   FLOAT,
   FROM,
   GROUP,
   HAVING,
+  IF,
+  IN,
   INDEX,
   INFINITY,
   INNER,
@@ -115,7 +172,9 @@ pub enum Keyword {
   LIKE,
   LIMIT,
   NAN,
+  NATURAL,
   NOT,
+  NOTHING,
   NULL,
   OF,
   OFFSET,
@@ -127,168 +186,37 @@ pub enum Keyword {
   PRIMARY,
   READ,
   REFERENCES,
+  RENAME,
+  RESTRICT,
   RIGHT,
   ROLLBACK,
   SELECT,
   SET,
+  SHOW,
+  STATUS,
+  STDIN,
   STRING,
   SYSTEM,
   TABLE,
+  TABLES,
   TEXT,
+  THEN,
   TIME,
+  TIMESTAMP,
+  TO,
   TRANSACTION,
   TRUE,
+  TRUNCATE,
   UNIQUE,
   UPDATE,
+  USING,
   VALUES,
   VARCHAR,
+  WHEN,
   WHERE,
   WRITE
 }
 
-impl Keyword {
-  pub fn from_str(identifier: &str) -> Option<Self> {
-    Some(match identifier.to_uppercase( ).as_ref( ) {
-      "AS" => Self::AS,
-      "ASC" => Self::ASC,
-      "AND" => Self::AND,
-      "BEGIN" => Self::BEGIN,
-      "BOOL" => Self::BOOL,
-      "BOOLEAN" => Self::BOOLEAN,
-      "BY" => Self::BY,
-      "CHAR" => Self::CHAR,
-      "COMMIT" => Self::COMMIT,
-      "CREATE" => Self::CREATE,
-      "CROSS" => Self::CROSS,
-      "DEFAULT" => Self::DEFAULT,
-      "DELETE" => Self::DELETE,
-      "DESC" => Self::DESC,
-      "DOUBLE" => Self::DOUBLE,
-      "DROP" => Self::DROP,
-      "EXPLAIN" => Self::EXPLAIN,
-      "FALSE" => Self::FALSE,
-      "FLOAT" => Self::FLOAT,
-      "FROM" => Self::FROM,
-      "GROUP" => Self::GROUP,
-      "HAVING" => Self::HAVING,
-      "INDEX" => Self::INDEX,
-      "INFINITY" => Self::INFINITY,
-      "INNER" => Self::INNER,
-      "INSERT" => Self::INSERT,
-      "INT" => Self::INT,
-      "INTEGER" => Self::INTEGER,
-      "INTO" => Self::INTO,
-      "IS" => Self::IS,
-      "JOIN" => Self::JOIN,
-      "KEY" => Self::KEY,
-      "LEFT" => Self::LEFT,
-      "LIKE" => Self::LIKE,
-      "LIMIT" => Self::LIMIT,
-      "NAN" => Self::NAN,
-      "NOT" => Self::NOT,
-      "NULL" => Self::NULL,
-      "OF" => Self::OF,
-      "OFFSET" => Self::OFFSET,
-      "ON" => Self::ON,
-      "ONLY" => Self::ONLY,
-      "OR" => Self::OR,
-      "ORDER" => Self::ORDER,
-      "OUTER" => Self::OUTER,
-      "PRIMARY" => Self::PRIMARY,
-      "READ" => Self::READ,
-      "REFERENCES" => Self::REFERENCES,
-      "RIGHT" => Self::RIGHT,
-      "ROLLBACK" => Self::ROLLBACK,
-      "SELECT" => Self::SELECT,
-      "SET" => Self::SET,
-      "STRING" => Self::STRING,
-      "SYSTEM" => Self::SYSTEM,
-      "TABLE" => Self::TABLE,
-      "TEXT" => Self::TEXT,
-      "TIME" => Self::TIME,
-      "TRANSACTION" => Self::TRANSACTION,
-      "TRUE" => Self::TRUE,
-      "UNIQUE" => Self::UNIQUE,
-      "UPDATE" => Self::UPDATE,
-      "VALUES" => Self::VALUES,
-      "VARCHAR" => Self::VARCHAR,
-      "WHERE" => Self::WHERE,
-      "WRITE" => Self::WRITE,
-      _ => return None,
-    })
-  }
-
-  pub fn to_str(&self) -> &str {
-    match self {
-      Self::AS => "AS",
-      Self::ASC => "ASC",
-      Self::AND => "AND",
-      Self::BEGIN => "BEGIN",
-      Self::BOOL => "BOOL",
-      Self::BOOLEAN => "BOOLEAN",
-      Self::BY => "BY",
-      Self::CHAR => "CHAR",
-      Self::COMMIT => "COMMIT",
-      Self::CREATE => "CREATE",
-      Self::CROSS => "CROSS",
-      Self::DEFAULT => "DEFAULT",
-      Self::DELETE => "DELETE",
-      Self::DESC => "DESC",
-      Self::DOUBLE => "DOUBLE",
-      Self::DROP => "DROP",
-      Self::EXPLAIN => "EXPLAIN",
-      Self::FALSE => "FALSE",
-      Self::FLOAT => "FLOAT",
-      Self::FROM => "FROM",
-      Self::GROUP => "GROUP",
-      Self::HAVING => "HAVING",
-      Self::INDEX => "INDEX",
-      Self::INFINITY => "INFINITY",
-      Self::INNER => "INNER",
-      Self::INSERT => "INSERT",
-      Self::INT => "INT",
-      Self::INTEGER => "INTEGER",
-      Self::INTO => "INTO",
-      Self::IS => "IS",
-      Self::JOIN => "JOIN",
-      Self::KEY => "KEY",
-      Self::LEFT => "LEFT",
-      Self::LIKE => "LIKE",
-      Self::LIMIT => "LIMIT",
-      Self::NAN => "NAN",
-      Self::NOT => "NOT",
-      Self::NULL => "NULL",
-      Self::OF => "OF",
-      Self::OFFSET => "OFFSET",
-      Self::ON => "ON",
-      Self::ONLY => "ONLY",
-      Self::OUTER => "OUTER",
-      Self::OR => "OR",
-      Self::ORDER => "ORDER",
-      Self::PRIMARY => "PRIMARY",
-      Self::READ => "READ",
-      Self::REFERENCES => "REFERENCES",
-      Self::RIGHT => "RIGHT",
-      Self::ROLLBACK => "ROLLBACK",
-      Self::SELECT => "SELECT",
-      Self::SET => "SET",
-      Self::STRING => "STRING",
-      Self::SYSTEM => "SYSTEM",
-      Self::TABLE => "TABLE",
-      Self::TEXT => "TEXT",
-      Self::TIME => "TIME",
-      Self::TRANSACTION => "TRANSACTION",
-      Self::TRUE => "TRUE",
-      Self::UNIQUE => "UNIQUE",
-      Self::UPDATE => "UPDATE",
-      Self::VALUES => "VALUES",
-      Self::VARCHAR => "VARCHAR",
-      Self::WHERE => "WHERE",
-      Self::WRITE => "WRITE",
-    }
-  }
-}
-
 impl Display for Keyword {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.write_str(self.to_str( ))
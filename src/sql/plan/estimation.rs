@@ -0,0 +1,347 @@
+use std::{collections::HashMap, ops::Bound};
+use crate::{
+  result::Result,
+  sql::{
+    engine::IndexRange,
+    parser::ast::{Expression, Literal, Operation},
+    schema::{Catalog, Table},
+    stats::{ColumnStats, TableStats},
+    types::Value
+  }
+};
+use super::Plan;
+
+// Selectivities assumed for the predicates, which the statistics don't tell about : equality
+// comparisons (and IS NULL), and any other predicate.
+const DEFAULT_EQUALITY_SELECTIVITY: f64= 0.1;
+const DEFAULT_SELECTIVITY: f64= 1.0 / 3.0;
+
+/*
+  The statistics of the tables read by a plan (see TableStats), which the optimizer estimates the
+  number of rows produced by the plan nodes from. They're loaded once, before optimizing the plan.
+
+  The estimates assume that the values of a column are distributed uniformly between its min and
+  max, and that the values of different columns are independent. Nothing is estimated for a node
+  reading a table without (fresh) statistics, or for a node above it.
+*/
+pub struct Statistics {
+  tables: HashMap<String, (Table, TableStats)>
+}
+
+// The estimated number of rows produced by a node, along with the statistics of the values of their
+// columns (when they're values of a table column, or of an expression index).
+struct Estimate<'a> {
+  rows: f64,
+  columns: Vec<Option<&'a ColumnStats>>
+}
+
+/*
+  The estimated number of rows produced by every node of a plan, mirroring the plan tree. Shown by
+  EXPLAIN, next to the nodes.
+*/
+#[derive(Debug, Clone)]
+pub struct Estimates {
+  pub rows: Option<f64>,
+  pub children: Vec<Estimates>
+}
+
+impl Statistics {
+  // Loads the statistics of the tables read by the plan, from the catalog.
+  pub fn load<C: Catalog + ?Sized>(plan: &Plan, catalog: &C) -> Result<Self> {
+    let mut tables= HashMap::new( );
+
+    let mut nodes= vec![plan];
+    while let Some(node)= nodes.pop( ) {
+      if let Plan::Scan { table, .. } | Plan::KeyLookup { table, .. } | Plan::IndexLookup { table, .. } | Plan::IndexRangeScan { table, .. }= node {
+        if !tables.contains_key(table) {
+          if let (Some(schema), Some(stats))= (catalog.getTable(table)?, catalog.getTableStats(table)?) {
+            tables.insert(table.clone( ), (schema, stats));}
+        }
+      }
+      nodes.extend(node.children( ));
+    }
+
+    Ok(Self { tables })
+  }
+
+  pub fn isEmpty(&self) -> bool {
+    self.tables.is_empty( )
+  }
+
+  // Returns the statistics of the index (or of the indexed column) with the given name, along with
+  // the number of rows of its table.
+  pub fn index(&self, table: &str, index: &str) -> Option<(u64, &ColumnStats)> {
+    let (schema, stats)= self.tables.get(table)?;
+    Some((stats.rowCount, stats.index(schema, index)?))
+  }
+
+  // Returns the estimated number of rows produced by the node.
+  pub fn rows(&self, node: &Plan) -> Option<f64> {
+    self.estimate(node).map(|estimate| estimate.rows)
+  }
+
+  // Returns the estimated number of rows produced by every node of the plan.
+  pub fn estimates(&self, plan: &Plan) -> Estimates {
+    Estimates {
+      rows: self.rows(plan),
+      children: plan.children( ).into_iter( ).map(|child| self.estimates(child)).collect( )
+    }
+  }
+
+  fn estimate(&self, node: &Plan) -> Option<Estimate<'_>> {
+    Some(match node {
+      Plan::Scan { table, filter, range, limit, .. } => {
+        let (schema, stats)= self.tables.get(table)?;
+        let columns= tableColumns(schema, stats);
+
+        let mut rows= stats.rowCount as f64 * rangeSelectivity(columns[schema.primaryKey], range);
+        if let Some(filter)= filter {
+          rows *= selectivity(filter, &columns);}
+        if let Some(limit)= limit {
+          rows= rows.min(*limit as f64);}
+
+        Estimate { rows, columns }
+      },
+
+      Plan::KeyLookup { table, keys, .. } => {
+        let (schema, stats)= self.tables.get(table)?;
+        Estimate { rows: (keys.len( ) as f64).min(stats.rowCount as f64), columns: tableColumns(schema, stats) }
+      },
+
+      Plan::IndexLookup { table, column, values, .. } => {
+        let (schema, stats)= self.tables.get(table)?;
+        let index= stats.index(schema, column);
+
+        let rows= stats.rowCount as f64 * values.len( ) as f64 * index.map_or(DEFAULT_EQUALITY_SELECTIVITY, ColumnStats::equalitySelectivity);
+        Estimate { rows: rows.min(stats.rowCount as f64), columns: tableColumns(schema, stats) }
+      },
+
+      Plan::IndexRangeScan { table, column, range, .. } => {
+        let (schema, stats)= self.tables.get(table)?;
+        let rows= stats.rowCount as f64 * stats.index(schema, column).map_or(DEFAULT_SELECTIVITY, |index| rangeSelectivity(Some(index), range));
+        Estimate { rows, columns: tableColumns(schema, stats) }
+      },
+
+      Plan::NestedLoopJoin { left, right, predicate, outer, .. } => {
+        let (left, right)= (self.estimate(left)?, self.estimate(right)?);
+
+        let mut columns= left.columns;
+        columns.extend(right.columns);
+
+        let mut rows= left.rows * right.rows * predicate.as_ref( ).map_or(1.0, |predicate| selectivity(predicate, &columns));
+        if *outer {
+          rows= rows.max(left.rows);}
+
+        Estimate { rows, columns }
+      },
+
+      // Every distinct value of the join column (of the side having more of them) is assumed to
+      // match rows on the other side. A side without statistics of its join column is assumed to
+      // have distinct values.
+      Plan::HashJoin { left, leftColumn, right, rightColumn, outer, .. } => {
+        let (left, right)= (self.estimate(left)?, self.estimate(right)?);
+
+        let distinctCount= |estimate: &Estimate, column: usize| {
+          estimate.columns.get(column).copied( ).flatten( ).map_or(estimate.rows, |stats| stats.distinctCount as f64)
+        };
+        let distinctCount= distinctCount(&left, *leftColumn).max(distinctCount(&right, *rightColumn)).max(1.0);
+
+        let mut rows= left.rows * right.rows / distinctCount;
+        if *outer {
+          rows= rows.max(left.rows);}
+
+        let mut columns= left.columns;
+        columns.extend(right.columns);
+
+        Estimate { rows, columns }
+      },
+
+      Plan::Filter { source, predicate } => {
+        let source= self.estimate(source)?;
+        Estimate { rows: source.rows * selectivity(predicate, &source.columns), columns: source.columns }
+      },
+
+      Plan::Projection { source, expressions } => {
+        let source= self.estimate(source)?;
+        let columns= expressions.iter( )
+                                .map(|(expression, _)| match expression {
+                                  Expression::Column(index) => source.columns.get(*index).copied( ).flatten( ),
+                                  _ => None
+                                })
+                                .collect( );
+
+        Estimate { rows: source.rows, columns }
+      },
+
+      // There's a group per distinct combination of the group key values (bounded by the number of
+      // rows). Without grouping, there's a single group.
+      Plan::Aggregation { source, groupSize, aggregates } => {
+        let source= self.estimate(source)?;
+
+        let rows= match groupSize {
+          0 => 1.0,
+          groupSize => source.columns[..*groupSize].iter( )
+                                                   .map(|stats| stats.map(|stats| stats.distinctCount.max(1) as f64))
+                                                   .product::<Option<f64>>( )
+                                                   .map_or(source.rows, |groups| groups.min(source.rows))
+        };
+
+        let mut columns= source.columns[..*groupSize].to_vec( );
+        columns.extend(std::iter::repeat_n(None, aggregates.len( )));
+
+        Estimate { rows, columns }
+      },
+
+      Plan::Order { source, .. } | Plan::Distinct { source, .. } => self.estimate(source)?,
+
+      Plan::Limit { source, limit } => {
+        let source= self.estimate(source)?;
+        Estimate { rows: source.rows.min(*limit as f64), columns: source.columns }
+      },
+
+      Plan::Offset { source, offset } => {
+        let source= self.estimate(source)?;
+        Estimate { rows: (source.rows - *offset as f64).max(0.0), columns: source.columns }
+      },
+
+      Plan::Values { rows } => Estimate { rows: rows.len( ) as f64, columns: vec![None; rows.first( ).map_or(0, Vec::len)] },
+      Plan::Nothing => Estimate { rows: 1.0, columns: vec![ ] },
+      Plan::Empty { size } => Estimate { rows: 0.0, columns: vec![None; *size] },
+
+      Plan::Insert { .. }
+      | Plan::Update { .. }
+      | Plan::Delete { .. }
+      | Plan::Truncate { .. }
+      | Plan::CreateTable { .. }
+      | Plan::DropTable { .. }
+      | Plan::AlterTable { .. }
+      | Plan::CreateIndex { .. }
+      | Plan::ShowTables
+      | Plan::Analyze { .. }
+      | Plan::DescribeTable { .. }
+      | Plan::Explain { .. } => return None
+    })
+  }
+}
+
+// Returns the statistics of the columns of the rows read from the table : its columns, followed by
+// its _version system column.
+fn tableColumns<'a>(schema: &Table, stats: &'a TableStats) -> Vec<Option<&'a ColumnStats>> {
+  (0..schema.columns.len( )).map(|index| stats.column(schema, index))
+                            .chain([None])
+                            .collect( )
+}
+
+// Returns the estimated fraction of the rows satisfying the predicate, given the statistics of the
+// columns of the rows.
+fn selectivity(predicate: &Expression, columns: &[Option<&ColumnStats>]) -> f64 {
+  let column= |expression: &Expression| match expression {
+    Expression::Column(index) => columns.get(*index).copied( ).flatten( ),
+    _ => None
+  };
+
+  let Expression::Operation(operation)= predicate else {
+    return match predicate {
+      Expression::Literal(Literal::Boolean(true)) => 1.0,
+      Expression::Literal(Literal::Boolean(false) | Literal::Null) => 0.0,
+      _ => DEFAULT_SELECTIVITY
+    }
+  };
+
+  match operation {
+    Operation::And(lhs, rhs) => selectivity(lhs, columns) * selectivity(rhs, columns),
+    Operation::Or(lhs, rhs) => {
+      let (lhs, rhs)= (selectivity(lhs, columns), selectivity(rhs, columns));
+      lhs + rhs - lhs * rhs
+    },
+    Operation::Not(operand) => 1.0 - selectivity(operand, columns),
+
+    Operation::Equal(lhs, rhs) | Operation::NotEqual(lhs, rhs) => {
+      let equality= match (lhs.as_ref( ), rhs.as_ref( )) {
+        (_, Expression::Literal(Literal::Null)) | (Expression::Literal(Literal::Null), _) => 0.0,
+
+        (expression, Expression::Literal(_)) | (Expression::Literal(_), expression) =>
+          column(expression).map_or(DEFAULT_EQUALITY_SELECTIVITY, ColumnStats::equalitySelectivity),
+
+        // A comparison of two columns matches a value of the one having more distinct values.
+        (lhs, rhs) => match (column(lhs), column(rhs)) {
+          (None, None) => DEFAULT_EQUALITY_SELECTIVITY,
+          (lhs, rhs) => lhs.into_iter( ).chain(rhs)
+                           .map(ColumnStats::equalitySelectivity)
+                           .fold(1.0, f64::min)
+        }
+      };
+
+      match operation {
+        Operation::NotEqual(lhs, rhs) => {
+          let nullFraction= column(lhs).or(column(rhs)).map_or(0.0, |stats| stats.nullFraction);
+          (1.0 - nullFraction - equality).max(0.0)
+        },
+        _ => equality
+      }
+    },
+
+    Operation::GreaterThan(lhs, rhs)
+    | Operation::GreaterThanOrEqual(lhs, rhs)
+    | Operation::LessThan(lhs, rhs)
+    | Operation::LessThanOrEqual(lhs, rhs) => {
+      let range= [lhs, rhs].into_iter( ).find_map(|operand| match operand.as_ref( ) {
+        Expression::Column(index) => predicate.asRange(*index).map(|range| (*index, range)),
+        _ => None
+      });
+
+      match range {
+        Some((index, range)) => rangeSelectivity(columns.get(index).copied( ).flatten( ), &range),
+        None => DEFAULT_SELECTIVITY
+      }
+    },
+
+    Operation::IsNull(operand) => column(operand).map_or(DEFAULT_EQUALITY_SELECTIVITY, |stats| stats.nullFraction),
+
+    _ => DEFAULT_SELECTIVITY
+  }
+}
+
+// Returns the estimated fraction of the rows, whose value in the column is in the range. Only the
+// ranges of numeric (and date / time) values are estimated, as the fraction of the column's values
+// between its min and max they span.
+fn rangeSelectivity(stats: Option<&ColumnStats>, range: &IndexRange) -> f64 {
+  if *range == (Bound::Unbounded, Bound::Unbounded) {
+    return 1.0}
+
+  let Some(stats)= stats else {
+    return DEFAULT_SELECTIVITY
+  };
+  let (Some(min), Some(max))= (stats.min.as_ref( ).and_then(numeric), stats.max.as_ref( ).and_then(numeric)) else {
+    return match (&stats.min, &stats.max) {
+      // Every value is NULL, so none is in the range.
+      (None, None) => 0.0,
+      _ => DEFAULT_SELECTIVITY
+    }
+  };
+
+  let bound= |bound: &Bound<Value>, unbounded: f64| match bound {
+    Bound::Included(value) | Bound::Excluded(value) => numeric(value),
+    Bound::Unbounded => Some(unbounded)
+  };
+  let (Some(start), Some(end))= (bound(&range.0, min), bound(&range.1, max)) else {
+    return DEFAULT_SELECTIVITY
+  };
+
+  let fraction= match max > min {
+    true => ((end.min(max) - start.max(min)) / (max - min)).clamp(0.0, 1.0),
+    false => match start <= min && min <= end {
+      true => 1.0,
+      false => 0.0
+    }
+  };
+  fraction * (1.0 - stats.nullFraction)
+}
+
+fn numeric(value: &Value) -> Option<f64> {
+  match value {
+    Value::Integer(value) | Value::Date(value) | Value::Timestamp(value) => Some(*value as f64),
+    Value::Float(value) if value.is_finite( ) => Some(*value),
+    _ => None
+  }
+}
@@ -0,0 +1,794 @@
+use std::{fmt::Display, ops::Bound};
+use crate::{result::{Error, Result}, storage::engine::Direction};
+use super::{
+  engine::{IndexRange, Transaction},
+  execution::{evaluateSubqueries, Analysis, Cancellation, Executor, ResultSet, WorkMemory},
+  parser::ast::{AlterTableAction, Expression, Literal, Order, Statement},
+  schema::{Catalog, Index, SchemaVersion, Table},
+  types::{DataType, Value, ValueType}
+};
+
+mod planner;
+mod optimizer;
+mod estimation;
+
+pub use estimation::Estimates;
+pub use planner::Planner;
+
+/*
+  A query plan : a tree of nodes, which the executor runs to produce the result of a statement.
+
+  Expressions in the plan have their field references resolved to the indexes of the columns in the
+  rows flowing into the node (Expression::Column).
+*/
+/*
+  A plan for a statement, along with the version of the schema it was planned against.
+
+  A plan holds the schema it was built with (the resolved columns, the indexes picked, ...). So if
+  the schema has been changed since, by a concurrent transaction, the plan is stale : it's not
+  executed then, and a retryable Error::Serialization is returned instead.
+*/
+#[derive(Debug, Clone)]
+pub struct StatementPlan {
+  pub plan: Plan,
+  pub schemaVersion: SchemaVersion
+}
+
+impl StatementPlan {
+  pub fn build<C: Catalog + ?Sized>(statement: Statement, catalog: &C, options: PlanOptions) -> Result<Self> {
+    let schemaVersion= catalog.schemaVersion( )?;
+    Ok(Self { plan: Plan::build(statement, catalog, options)?, schemaVersion })
+  }
+
+  // Executes the plan (see Plan::execute), if the schema hasn't been changed since it was built.
+  pub fn execute<T: Transaction + 'static>(self, txn: &mut T, cancellation: &Cancellation, workMemory: &WorkMemory) -> Result<ResultSet> {
+    if txn.schemaVersion( )? != self.schemaVersion {
+      return Err(Error::Serialization)}
+
+    self.plan.execute(txn, cancellation, workMemory)
+  }
+}
+
+// Options of the planner, set by the session planning the statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlanOptions {
+  /*
+    Whether the WHERE clause of a query can reference the aliases of its selections (which SQL
+    doesn't allow, since WHERE is evaluated before the selections). A reference to an alias (which
+    doesn't name a column) is substituted by the aliased expression then.
+
+    NOTE : Subqueries are always planned without it, since they're planned again (with the default
+    options) when they're evaluated.
+  */
+  pub lenientAliases: bool
+}
+
+#[derive(Debug, Clone)]
+pub enum Plan {
+  // Scans the rows of a table whose primary keys are in the given range, in primary key order (in
+  // the given direction), optionally filtering them. Stops after the given number of rows, which can
+  // only be set if there's no filter.
+  Scan {
+    table: String,
+    alias: Option<String>,
+    filter: Option<Expression>,
+    range: IndexRange,
+    direction: Direction,
+    limit: Option<u64>
+  },
+
+  // Looks up the rows of a table by their primary keys.
+  KeyLookup {
+    table: String,
+    alias: Option<String>,
+    keys: Vec<Value>
+  },
+
+  // Looks up the rows of a table, whose value in the given indexed column is one of the given values.
+  // For an expression index, the column is the index's name.
+  IndexLookup {
+    table: String,
+    alias: Option<String>,
+    column: String,
+    values: Vec<Value>
+  },
+
+  // Scans the rows of a table, whose value in the given indexed column is in the given range.
+  IndexRangeScan {
+    table: String,
+    alias: Option<String>,
+    column: String,
+    range: IndexRange
+  },
+
+  // Joins every row of the left source with every row of the right source, satisfying the
+  // predicate. For an outer join, left rows without any matching right row are padded with NULLs.
+  // The columns of a USING (or NATURAL) join, which the predicate was derived from, are kept to be
+  // shown by EXPLAIN.
+  NestedLoopJoin {
+    left: Box<Plan>,
+    leftSize: usize,
+    right: Box<Plan>,
+    predicate: Option<Expression>,
+    outer: bool,
+    using: Vec<String>
+  },
+
+  // Joins the rows of the left and right sources, whose values in the given columns are equal, by
+  // building a hash table of the right rows (or of the left rows, if buildLeft is set) and probing it
+  // with the rows of the other side. For an outer join, left rows without any matching right row are
+  // padded with NULLs (buildLeft can't be set then).
+  HashJoin {
+    left: Box<Plan>,
+    leftColumn: usize,
+    right: Box<Plan>,
+    rightColumn: usize,
+    outer: bool,
+    using: Vec<String>,
+    buildLeft: bool
+  },
+
+  // Filters the rows satisfying the predicate.
+  Filter {
+    source: Box<Plan>,
+    predicate: Expression
+  },
+
+  // Evaluates the expressions against every row, producing a row of their values. The expressions
+  // can be labeled.
+  Projection {
+    source: Box<Plan>,
+    expressions: Vec<(Expression, Option<String>)>
+  },
+
+  // Groups the rows by their leading groupSize columns (the group key), and computes the aggregates
+  // over the remaining columns (one per aggregate) of every group. Produces a row per group : the
+  // group key followed by the aggregate values. An aggregate may only aggregate the distinct values
+  // of its column (like COUNT(DISTINCT x)).
+  Aggregation {
+    source: Box<Plan>,
+    groupSize: usize,
+    aggregates: Vec<(Aggregate, bool)>
+  },
+
+  // Removes the duplicate rows, NULLs being equal to each other. The first of the duplicates is kept,
+  // so the order of the rows is preserved. When the rows are sorted (by all their columns), only
+  // adjacent rows are compared.
+  Distinct {
+    source: Box<Plan>,
+    sorted: bool
+  },
+
+  // Sorts the rows.
+  Order {
+    source: Box<Plan>,
+    orders: Vec<(Expression, Order)>
+  },
+
+  // Returns only the given number of rows.
+  Limit {
+    source: Box<Plan>,
+    limit: u64
+  },
+
+  // Skips the given number of rows.
+  Offset {
+    source: Box<Plan>,
+    offset: u64
+  },
+
+  // Inserts the rows produced by the source into a table. The values of a row are assigned to the
+  // given columns, and the remaining columns take their default values. No columns means the
+  // leading columns of the table.
+  Insert {
+    table: String,
+    columns: Vec<String>,
+    source: Box<Plan>,
+    onConflict: Option<OnConflict>
+  },
+
+  // Updates the rows produced by the source, by setting the columns (identified by their indexes)
+  // to the values of the expressions.
+  Update {
+    table: String,
+    source: Box<Plan>,
+    expressions: Vec<(usize, Expression)>
+  },
+
+  // Deletes the rows produced by the source.
+  Delete {
+    table: String,
+    source: Box<Plan>
+  },
+
+  // Deletes all the rows of a table at once (see Transaction::truncate).
+  Truncate {
+    table: String
+  },
+
+  // Unless the flag is set, creating a table which exists (or dropping one which doesn't) fails.
+  CreateTable {
+    schema: Table,
+    ifNotExists: bool
+  },
+
+  DropTable {
+    table: String,
+    ifExists: bool
+  },
+
+  // Renames a table or one of its columns. The rows and the index entries are stored by ids, so they
+  // aren't moved (see KV).
+  AlterTable {
+    table: String,
+    action: AlterTableAction
+  },
+
+  CreateIndex {
+    table: String,
+    index: Index
+  },
+
+  // Returns the names of the tables, in sorted order.
+  ShowTables,
+
+  // Collects the statistics of the tables (see Catalog::analyzeTable).
+  Analyze {
+    tables: Vec<String>
+  },
+
+  // Returns a row per column of the table, describing it.
+  DescribeTable {
+    schema: Table
+  },
+
+  // Returns a row of the values of the expressions, for every list of expressions (like the source
+  // of INSERT ... VALUES).
+  Values {
+    rows: Vec<Vec<Expression>>
+  },
+
+  // Returns a single empty row (like the source of SELECT 1 + 2).
+  Nothing,
+
+  // Returns no rows. The rows would have had the given number of columns.
+  Empty {
+    size: usize
+  },
+
+  // Returns the rendering of the plan, instead of executing it. With analyze, the plan is executed
+  // as well, measuring the statistics of every node. The estimated number of rows produced by every
+  // node is shown too, if the tables have statistics (see TableStats).
+  Explain {
+    plan: Box<Plan>,
+    analyze: bool,
+    estimates: Option<Estimates>
+  }
+}
+
+/*
+  What an Insert does with a row, whose value in the conflict column (the primary key or a unique
+  column, identified by its index) already exists : the row is skipped, or the existing row is
+  updated instead, by setting the columns (identified by their indexes) to the values of the
+  expressions.
+
+  The expressions are evaluated against the existing row (suffixed with its version), followed by
+  the row which wasn't inserted.
+*/
+#[derive(Debug, Clone)]
+pub struct OnConflict {
+  pub column: usize,
+  // None for DO NOTHING.
+  pub updates: Option<Vec<(usize, Expression)>>
+}
+
+// An aggregate function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregate {
+  Count,
+  Sum,
+  Min,
+  Max,
+  Average
+}
+
+impl Aggregate {
+  // Returns the aggregate function with the given name, if any.
+  pub fn fromName(name: &str) -> Option<Self> {
+    match name {
+      "count" => Some(Self::Count),
+      "sum" => Some(Self::Sum),
+      "min" => Some(Self::Min),
+      "max" => Some(Self::Max),
+      "avg" => Some(Self::Average),
+      _ => None
+    }
+  }
+}
+
+impl Aggregate {
+  // Returns the type of the aggregate's result, given the type of its argument. Returns None, if the
+  // aggregate can't be computed over values of the argument's type.
+  pub fn resultType(&self, argument: ValueType) -> Option<ValueType> {
+    match self {
+      Self::Count => Some(ValueType::new(DataType::Integer, false)),
+
+      // The result is NULL, when there are no (non-NULL) values to aggregate.
+      Self::Sum if argument.isNumeric( ) => Some(ValueType { nullable: true, ..argument }),
+      Self::Average if argument.isNumeric( ) => Some(ValueType::new(DataType::Float, true)),
+      Self::Sum | Self::Average => None,
+
+      Self::Min | Self::Max => Some(ValueType { nullable: true, ..argument })
+    }
+  }
+}
+
+impl Display for Aggregate {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Count => write!(f, "count"),
+      Self::Sum => write!(f, "sum"),
+      Self::Min => write!(f, "min"),
+      Self::Max => write!(f, "max"),
+      Self::Average => write!(f, "avg")
+    }
+  }
+}
+
+impl Plan {
+  // Builds a plan for the statement. Tables are looked up in the catalog.
+  pub fn build<C: Catalog + ?Sized>(statement: Statement, catalog: &C, options: PlanOptions) -> Result<Self> {
+    let plan= Planner::new(catalog, options).build(statement)?;
+    optimizer::optimize(plan, catalog)
+  }
+
+  // Transforms the plan bottom-up : first the children (recursively), and then the node itself.
+  pub fn transform(self, transformer: &mut dyn FnMut(Plan) -> Result<Plan>) -> Result<Plan> {
+    let mut transformChild= |child: Box<Plan>| -> Result<Box<Plan>> {
+      Ok(Box::new(child.transform(transformer)?))
+    };
+
+    let node= match self {
+      Self::NestedLoopJoin { left, leftSize, right, predicate, outer, using } => Self::NestedLoopJoin {
+        left: transformChild(left)?,
+        leftSize,
+        right: transformChild(right)?,
+        predicate,
+        outer,
+        using
+      },
+
+      Self::HashJoin { left, leftColumn, right, rightColumn, outer, using, buildLeft } => Self::HashJoin {
+        left: transformChild(left)?,
+        leftColumn,
+        right: transformChild(right)?,
+        rightColumn,
+        outer,
+        using,
+        buildLeft
+      },
+
+      Self::Filter { source, predicate } => Self::Filter { source: transformChild(source)?, predicate },
+      Self::Projection { source, expressions } => Self::Projection { source: transformChild(source)?, expressions },
+      Self::Aggregation { source, groupSize, aggregates } =>
+        Self::Aggregation { source: transformChild(source)?, groupSize, aggregates },
+      Self::Order { source, orders } => Self::Order { source: transformChild(source)?, orders },
+      Self::Distinct { source, sorted } => Self::Distinct { source: transformChild(source)?, sorted },
+      Self::Limit { source, limit } => Self::Limit { source: transformChild(source)?, limit },
+      Self::Offset { source, offset } => Self::Offset { source: transformChild(source)?, offset },
+
+      Self::Insert { table, columns, source, onConflict } => Self::Insert { table, columns, source: transformChild(source)?, onConflict },
+      Self::Update { table, source, expressions } => Self::Update { table, source: transformChild(source)?, expressions },
+      Self::Delete { table, source } => Self::Delete { table, source: transformChild(source)? },
+
+      Self::Explain { plan, analyze, estimates } => Self::Explain { plan: transformChild(plan)?, analyze, estimates },
+
+      node @ (Self::Scan { .. }
+              | Self::KeyLookup { .. }
+              | Self::IndexLookup { .. }
+              | Self::IndexRangeScan { .. }
+              | Self::Values { .. }
+              | Self::Nothing
+              | Self::Empty { .. }
+              | Self::Truncate { .. }
+              | Self::CreateTable { .. }
+              | Self::DropTable { .. }
+              | Self::AlterTable { .. }
+              | Self::CreateIndex { .. }
+              | Self::ShowTables
+              | Self::Analyze { .. }
+              | Self::DescribeTable { .. }) => node
+    };
+
+    transformer(node)
+  }
+
+  // Transforms every (top level) expression in the plan, using the given transformer.
+  pub fn transformExpressions(self, transformer: &mut dyn FnMut(Expression) -> Result<Expression>) -> Result<Plan> {
+    self.transform(&mut |node| Ok(match node {
+      Self::Scan { table, alias, filter, range, direction, limit } =>
+        Self::Scan { table, alias, filter: filter.map(&mut *transformer).transpose( )?, range, direction, limit },
+
+      Self::NestedLoopJoin { left, leftSize, right, predicate, outer, using } =>
+        Self::NestedLoopJoin { left, leftSize, right, predicate: predicate.map(&mut *transformer).transpose( )?, outer, using },
+
+      Self::Filter { source, predicate } => Self::Filter { source, predicate: transformer(predicate)? },
+
+      Self::Projection { source, expressions } => Self::Projection {
+        source,
+        expressions: expressions.into_iter( )
+                                .map(|(expression, label)| Ok((transformer(expression)?, label)))
+                                .collect::<Result<_>>( )?
+      },
+
+      Self::Order { source, orders } => Self::Order {
+        source,
+        orders: orders.into_iter( )
+                      .map(|(expression, order)| Ok((transformer(expression)?, order)))
+                      .collect::<Result<_>>( )?
+      },
+
+      Self::Update { table, source, expressions } => Self::Update {
+        table,
+        source,
+        expressions: expressions.into_iter( )
+                                .map(|(column, expression)| Ok((column, transformer(expression)?)))
+                                .collect::<Result<_>>( )?
+      },
+
+      Self::Insert { table, columns, source, onConflict: Some(OnConflict { column, updates: Some(updates) }) } => Self::Insert {
+        table,
+        columns,
+        source,
+        onConflict: Some(OnConflict {
+          column,
+          updates: Some(updates.into_iter( )
+                               .map(|(column, expression)| Ok((column, transformer(expression)?)))
+                               .collect::<Result<_>>( )?)
+        })
+      },
+
+      Self::Values { rows } => Self::Values {
+        rows: rows.into_iter( )
+                  .map(|row| row.into_iter( ).map(&mut *transformer).collect::<Result<_>>( ))
+                  .collect::<Result<_>>( )?
+      },
+
+      node => node
+    }))
+  }
+
+  // Executes the plan against the transaction, until it's cancelled. The subqueries in its
+  // expressions are evaluated upfront.
+  // The columns of a query result holding the primary key its rows are ordered by, are marked (see
+  // ResultColumn::key).
+  pub fn execute<T: Transaction + 'static>(self, txn: &mut T, cancellation: &Cancellation, workMemory: &WorkMemory) -> Result<ResultSet> {
+    let keyColumns= self.keyColumns(txn)?;
+
+    let plan= evaluateSubqueries(self, txn, cancellation, workMemory)?;
+    Ok(match <dyn Executor<T>>::build(plan, cancellation, workMemory).execute(txn)? {
+      ResultSet::Query { mut columns, rows } => {
+        for column in keyColumns {
+          columns[column].key= true;}
+
+        ResultSet::Query { columns, rows }
+      },
+
+      resultSet => resultSet
+    })
+  }
+}
+
+impl Plan {
+  // Returns the columns of the rows produced by the node, holding the primary key of a table which
+  // the rows are ordered by (ascending or descending). There are none, if the rows aren't ordered by
+  // a primary key (or if it isn't among the columns).
+  fn keyColumns<C: Catalog + ?Sized>(&self, catalog: &C) -> Result<Vec<usize>> {
+    Ok(match self {
+      // The rows looked up through an index are read in primary key order too.
+      Self::Scan { table, .. }
+      | Self::KeyLookup { table, .. }
+      | Self::IndexLookup { table, .. }
+      | Self::IndexRangeScan { table, .. } => vec![catalog.mustGetTable(table)?.primaryKey],
+
+      Self::Filter { source, .. }
+      | Self::Limit { source, .. }
+      | Self::Offset { source, .. }
+      | Self::Distinct { source, .. } => source.keyColumns(catalog)?,
+
+      Self::Projection { source, expressions } => {
+        let keyColumns= source.keyColumns(catalog)?;
+
+        (0..expressions.len( )).filter(|index| matches!(&expressions[*index].0, Expression::Column(column) if keyColumns.contains(column)))
+                               .collect( )
+      },
+
+      _ => vec![ ]
+    })
+  }
+
+  // Returns the child nodes, in the order they're rendered.
+  fn children(&self) -> Vec<&Plan> {
+    match self {
+      Self::NestedLoopJoin { left, right, .. } | Self::HashJoin { left, right, .. } => vec![left, right],
+
+      Self::Filter { source, .. }
+      | Self::Projection { source, .. }
+      | Self::Aggregation { source, .. }
+      | Self::Order { source, .. }
+      | Self::Distinct { source, .. }
+      | Self::Limit { source, .. }
+      | Self::Offset { source, .. }
+      | Self::Insert { source, .. }
+      | Self::Update { source, .. }
+      | Self::Delete { source, .. }
+      | Self::Explain { plan: source, .. } => vec![source],
+
+      Self::Scan { .. }
+      | Self::KeyLookup { .. }
+      | Self::IndexLookup { .. }
+      | Self::IndexRangeScan { .. }
+      | Self::Truncate { .. }
+      | Self::CreateTable { .. }
+      | Self::DropTable { .. }
+      | Self::AlterTable { .. }
+      | Self::CreateIndex { .. }
+      | Self::ShowTables
+      | Self::Analyze { .. }
+      | Self::DescribeTable { .. }
+      | Self::Values { .. }
+      | Self::Nothing
+      | Self::Empty { .. } => vec![ ]
+    }
+  }
+
+  /*
+    Renders the node and its children as a tree, indenting each level using the given prefix.
+
+    With the estimates of the planner and / or the statistics of an analyzed execution (both
+    mirroring the tree), each node is annotated with the estimated and the measured figures.
+  */
+  fn render(&self,
+            f: &mut std::fmt::Formatter<'_>,
+            prefix: &str,
+            isRoot: bool,
+            estimates: Option<&Estimates>,
+            analysis: Option<&Analysis>) -> std::fmt::Result
+  {
+    if !isRoot {
+      write!(f, "\n{}└─ ", prefix)?;}
+
+    self.renderNode(f)?;
+
+    let mut annotations= Vec::new( );
+
+    if let Some(rows)= estimates.and_then(|estimates| estimates.rows) {
+      annotations.push(format!("estimated rows={}", rows.round( )));}
+
+    if let Some(analysis)= analysis {
+      annotations.push(format!("rows={}", analysis.rows));
+
+      // Each row of the left source, iterates over (or probes) the right source once. When the hash
+      // table is built over the left rows, the right rows probe it instead.
+      let probes= match self {
+        Self::HashJoin { buildLeft: true, .. } => analysis.children.get(1),
+        Self::NestedLoopJoin { .. } | Self::HashJoin { .. } => analysis.children.first( ),
+        _ => None
+      };
+      if let Some(probes)= probes {
+        annotations.push(format!("iterations={}", probes.rows));}
+
+      if analysis.spills > 0 {
+        annotations.push(format!("spills={}", analysis.spills));}
+
+      annotations.push(format!("time={:.3}ms", analysis.time.as_secs_f64( ) * 1000.0));
+    }
+
+    if !annotations.is_empty( ) {
+      write!(f, " ({})", annotations.join(" "))?;}
+
+    let childPrefix= match isRoot {
+      true => prefix.to_string( ),
+      false => format!("{}   ", prefix)
+    };
+
+    for (index, child) in self.children( ).into_iter( ).enumerate( ) {
+      child.render(f, &childPrefix, false,
+                   estimates.and_then(|estimates| estimates.children.get(index)),
+                   analysis.and_then(|analysis| analysis.children.get(index)))?;
+    }
+
+    Ok(( ))
+  }
+
+  // Renders the node, without its children.
+  fn renderNode(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let formatExpressions= |expressions: &mut dyn Iterator<Item= String>| expressions.collect::<Vec<_>>( ).join(", ");
+    let formatTable= |table: &str, alias: &Option<String>| match alias {
+      Some(alias) => format!("{} as {}", table, alias),
+      None => table.to_string( )
+    };
+
+    match self {
+      Self::Scan { table, alias, filter, range, direction, limit } => {
+        write!(f, "Scan: {}", formatTable(table, alias))?;
+        if *range != (Bound::Unbounded, Bound::Unbounded) {
+          write!(f, " key {}", formatRange(range))?;}
+        if *direction == Direction::Reverse {
+          write!(f, " reverse")?;}
+        if let Some(limit)= limit {
+          write!(f, " limit {}", limit)?;}
+        if let Some(filter)= filter {
+          write!(f, " ({})", filter)?;}
+        Ok(( ))
+      },
+
+      Self::KeyLookup { table, alias, keys } =>
+        write!(f, "KeyLookup: {} ({})", formatTable(table, alias),
+               formatExpressions(&mut keys.iter( ).map(formatValue))),
+
+      Self::IndexLookup { table, alias, column, values } =>
+        write!(f, "IndexLookup: {} column {} ({})", formatTable(table, alias), column,
+               formatExpressions(&mut values.iter( ).map(formatValue))),
+
+      Self::IndexRangeScan { table, alias, column, range } =>
+        write!(f, "IndexRangeScan: {} column {} {}", formatTable(table, alias), column, formatRange(range)),
+
+      Self::NestedLoopJoin { predicate, outer, using, .. } => {
+        write!(f, "NestedLoopJoin: {}{}", if *outer { "outer" } else { "inner" }, formatUsing(using))?;
+        if let Some(predicate)= predicate {
+          write!(f, " on {}", predicate)?;}
+        Ok(( ))
+      },
+
+      Self::HashJoin { leftColumn, rightColumn, outer, using, buildLeft, .. } => {
+        write!(f, "HashJoin: {}{} on left #{} = right #{}", if *outer { "outer" } else { "inner" }, formatUsing(using), leftColumn, rightColumn)?;
+        if *buildLeft {
+          write!(f, ", build left")?;}
+        Ok(( ))
+      },
+
+      Self::Filter { predicate, .. } => write!(f, "Filter: {}", predicate),
+
+      Self::Projection { expressions, .. } =>
+        write!(f, "Projection: {}", formatExpressions(&mut expressions.iter( ).map(|(expression, label)| match label {
+          Some(label) => format!("{} as {}", expression, label),
+          None => expression.to_string( )
+        }))),
+
+      Self::Aggregation { groupSize, aggregates, .. } => {
+        write!(f, "Aggregation: {}", formatExpressions(&mut aggregates.iter( ).enumerate( ).map(|(index, (aggregate, distinct))| {
+          match distinct {
+            true => format!("{}(DISTINCT #{})", aggregate, groupSize + index),
+            false => format!("{}(#{})", aggregate, groupSize + index)
+          }
+        })))?;
+        if *groupSize > 0 {
+          write!(f, " group by {}", formatExpressions(&mut (0..*groupSize).map(|index| format!("#{}", index))))?;}
+        Ok(( ))
+      },
+
+      Self::Order { orders, .. } =>
+        write!(f, "Order: {}", formatExpressions(&mut orders.iter( ).map(|(expression, order)| match order {
+          Order::Ascending => format!("{} asc", expression),
+          Order::Descending => format!("{} desc", expression)
+        }))),
+
+      Self::Distinct { sorted: true, .. } => write!(f, "Distinct: sorted"),
+      Self::Distinct { sorted: false, .. } => write!(f, "Distinct"),
+
+      Self::Limit { limit, .. } => write!(f, "Limit: {}", limit),
+
+      Self::Offset { offset, .. } => write!(f, "Offset: {}", offset),
+
+      Self::Insert { table, columns, onConflict, .. } => {
+        write!(f, "Insert: {}", table)?;
+        if !columns.is_empty( ) {
+          write!(f, " ({})", columns.join(", "))?;}
+
+        match onConflict {
+          Some(OnConflict { column, updates: Some(updates) }) =>
+            write!(f, ", on conflict #{} do update ({})", column, formatExpressions(&mut updates.iter( ).map(|(index, expression)| {
+              format!("#{}={}", index, expression)
+            }))),
+          Some(OnConflict { column, updates: None }) => write!(f, ", on conflict #{} do nothing", column),
+          None => Ok(( ))
+        }
+      },
+
+      Self::Update { table, expressions, .. } =>
+        write!(f, "Update: {} ({})", table, formatExpressions(&mut expressions.iter( ).map(|(index, expression)| {
+          format!("#{}={}", index, expression)
+        }))),
+
+      Self::Delete { table, .. } => write!(f, "Delete: {}", table),
+
+      Self::Truncate { table } => write!(f, "Truncate: {}", table),
+
+      Self::CreateTable { schema, ifNotExists: true } => write!(f, "CreateTable: {} (if not exists)", schema.name),
+      Self::CreateTable { schema, ifNotExists: false } => write!(f, "CreateTable: {}", schema.name),
+      Self::CreateIndex { table, index } => write!(f, "CreateIndex: {} on {} ({})", index.name, table, index.expression),
+
+      Self::DropTable { table, ifExists: true } => write!(f, "DropTable: {} (if exists)", table),
+      Self::DropTable { table, ifExists: false } => write!(f, "DropTable: {}", table),
+
+      Self::AlterTable { table, action: AlterTableAction::RenameTo(newName) } => write!(f, "AlterTable: {} rename to {}", table, newName),
+      Self::AlterTable { table, action: AlterTableAction::RenameColumn { column, newName } } =>
+        write!(f, "AlterTable: {} rename column {} to {}", table, column, newName),
+
+      Self::ShowTables => write!(f, "ShowTables"),
+
+      Self::Analyze { tables } => write!(f, "Analyze: {}", tables.join(", ")),
+
+      Self::DescribeTable { schema } => write!(f, "DescribeTable: {}", schema.name),
+
+      Self::Values { rows } =>
+        write!(f, "Values: {}", formatExpressions(&mut rows.iter( ).map(|row| {
+          format!("({})", formatExpressions(&mut row.iter( ).map(|expression| expression.to_string( ))))
+        }))),
+
+      Self::Nothing => write!(f, "Nothing"),
+
+      Self::Empty { .. } => write!(f, "Empty"),
+
+      Self::Explain { analyze: false, .. } => write!(f, "Explain"),
+      Self::Explain { analyze: true, .. } => write!(f, "ExplainAnalyze")
+    }
+  }
+
+  // Returns a rendering of the plan, annotated with the estimates of the planner and / or the
+  // statistics of its analyzed execution.
+  pub fn annotated<'a>(&'a self, estimates: Option<&'a Estimates>, analysis: Option<&'a Analysis>) -> impl Display + 'a {
+    AnnotatedPlan { plan: self, estimates, analysis }
+  }
+}
+
+struct AnnotatedPlan<'a> {
+  plan: &'a Plan,
+  estimates: Option<&'a Estimates>,
+  analysis: Option<&'a Analysis>
+}
+
+impl Display for AnnotatedPlan<'_> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    self.plan.render(f, "", true, self.estimates, self.analysis)
+  }
+}
+
+// Formats a range in interval notation, like ['abc', 'abd').
+fn formatRange(range: &IndexRange) -> String {
+  let start= match &range.0 {
+    Bound::Included(value) => format!("[{}", formatValue(value)),
+    Bound::Excluded(value) => format!("({}", formatValue(value)),
+    Bound::Unbounded => "(-inf".to_string( )
+  };
+  let end= match &range.1 {
+    Bound::Included(value) => format!("{}]", formatValue(value)),
+    Bound::Excluded(value) => format!("{})", formatValue(value)),
+    Bound::Unbounded => "+inf)".to_string( )
+  };
+
+  format!("{}, {}", start, end)
+}
+
+// Formats the columns of a USING join, like " using (id, name)". Nothing, for other joins.
+fn formatUsing(columns: &[String]) -> String {
+  match columns.is_empty( ) {
+    true => String::new( ),
+    false => format!(" using ({})", columns.join(", "))
+  }
+}
+
+fn formatValue(value: &Value) -> String {
+  match value {
+    Value::String(value) => format!("'{}'", value.replace('\'', "''")),
+    value @ (Value::Date(_) | Value::Timestamp(_)) => Literal::from(value.clone( )).to_string( ),
+    value => value.to_string( )
+  }
+}
+
+impl Display for Plan {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    self.render(f, "", true, None, None)
+  }
+}
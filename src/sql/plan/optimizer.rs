@@ -0,0 +1,656 @@
+use std::ops::Bound;
+use crate::{
+  result::Result,
+  sql::{
+    engine::IndexRange,
+    functions::getFunction,
+    parser::ast::{Expression, Literal, Operation, Order},
+    schema::Catalog,
+    types::{DataType, Value}
+  },
+  storage::engine::Direction
+};
+use super::{estimation::Statistics, OnConflict, Plan};
+
+// A secondary index is looked up only if the lookup is estimated to match at most this fraction of
+// the table's rows. Beyond it, scanning the table is cheaper than fetching the rows one by one.
+const INDEX_SELECTIVITY_THRESHOLD: f64= 0.1;
+
+// An optimization pass, which rewrites a plan into an equivalent one that's cheaper to execute.
+trait Optimizer {
+  fn optimize(&self, plan: Plan) -> Result<Plan>;
+}
+
+/*
+  Runs the optimization passes over the plan. The passes picking between alternatives (whether to
+  look up an index, which side of a hash join to build the hash table over) go by the statistics of
+  the tables, when there are any (see Statistics). Otherwise, they go by heuristics.
+
+  The estimates of the optimized plan are kept in an EXPLAIN, to be shown.
+*/
+pub fn optimize<C: Catalog + ?Sized>(plan: Plan, catalog: &C) -> Result<Plan> {
+  let statistics= Statistics::load(&plan, catalog)?;
+
+  let plan= ConstantFolding { catalog }.optimize(plan)?;
+  let plan= FilterPushdown.optimize(plan)?;
+  let plan= IndexLookup { catalog, statistics: &statistics }.optimize(plan)?;
+  let plan= OrderedScan { catalog }.optimize(plan)?;
+  let plan= HashJoin { statistics: &statistics }.optimize(plan)?;
+
+  Ok(match plan {
+    Plan::Explain { plan, analyze, estimates: _ } if !statistics.isEmpty( ) => {
+      let estimates= Some(statistics.estimates(&plan));
+      Plan::Explain { plan, analyze, estimates }
+    },
+    plan => plan
+  })
+}
+
+/*
+  Folds the constant parts of the expressions into literals, and simplifies boolean identities (like
+  x AND TRUE into x). Filters which are always true are removed, and filters which are never true
+  are replaced by an Empty node.
+
+  Constant parts are evaluated using the executor's evaluation code. The ones that fail to evaluate
+  are left as is - so that the error is raised only if (and when) they're actually evaluated.
+*/
+struct ConstantFolding<'a, C: Catalog + ?Sized> {
+  catalog: &'a C
+}
+
+impl<'a, C: Catalog + ?Sized> Optimizer for ConstantFolding<'a, C> {
+  fn optimize(&self, plan: Plan) -> Result<Plan> {
+    plan.transform(&mut |node| Ok(match node {
+      Plan::Filter { source, predicate } => match fold(predicate)? {
+        Expression::Literal(Literal::Boolean(true)) => *source,
+        Expression::Literal(Literal::Boolean(false) | Literal::Null) => Plan::Empty { size: self.size(&source)? },
+        predicate => Plan::Filter { source, predicate }
+      },
+
+      Plan::Scan { table, alias, filter, range, direction, limit } => match filter.map(fold).transpose( )? {
+        Some(Expression::Literal(Literal::Boolean(true))) => Plan::Scan { table, alias, filter: None, range, direction, limit },
+        Some(Expression::Literal(Literal::Boolean(false) | Literal::Null)) =>
+          Plan::Empty { size: self.size(&Plan::Scan { table, alias, filter: None, range, direction, limit })? },
+        filter => Plan::Scan { table, alias, filter, range, direction, limit }
+      },
+
+      Plan::NestedLoopJoin { left, leftSize, right, predicate, outer, using } => match predicate.map(fold).transpose( )? {
+        Some(Expression::Literal(Literal::Boolean(true))) => Plan::NestedLoopJoin { left, leftSize, right, predicate: None, outer, using },
+
+        // The rows of an outer join's left side are emitted irrespective of the join predicate.
+        Some(Expression::Literal(Literal::Boolean(false) | Literal::Null)) if !outer =>
+          Plan::Empty { size: leftSize + self.size(&right)? },
+
+        predicate => Plan::NestedLoopJoin { left, leftSize, right, predicate, outer, using }
+      },
+
+      Plan::Projection { source, expressions } => Plan::Projection {
+        source,
+        expressions: expressions.into_iter( )
+                                .map(|(expression, label)| Ok((fold(expression)?, label)))
+                                .collect::<Result<_>>( )?
+      },
+
+      Plan::Order { source, orders } => Plan::Order {
+        source,
+        orders: orders.into_iter( )
+                      .map(|(expression, order)| Ok((fold(expression)?, order)))
+                      .collect::<Result<_>>( )?
+      },
+
+      Plan::Update { table, source, expressions } => Plan::Update {
+        table,
+        source,
+        expressions: expressions.into_iter( )
+                                .map(|(column, expression)| Ok((column, fold(expression)?)))
+                                .collect::<Result<_>>( )?
+      },
+
+      Plan::Insert { table, columns, source, onConflict: Some(OnConflict { column, updates: Some(updates) }) } => Plan::Insert {
+        table,
+        columns,
+        source,
+        onConflict: Some(OnConflict {
+          column,
+          updates: Some(updates.into_iter( )
+                               .map(|(column, expression)| Ok((column, fold(expression)?)))
+                               .collect::<Result<_>>( )?)
+        })
+      },
+
+      node => node
+    }))
+  }
+}
+
+impl<'a, C: Catalog + ?Sized> ConstantFolding<'a, C> {
+  // Returns the number of columns of the rows produced by the node.
+  fn size(&self, node: &Plan) -> Result<usize> {
+    Ok(match node {
+      Plan::Scan { table, .. }
+      | Plan::KeyLookup { table, .. }
+      | Plan::IndexLookup { table, .. }
+      // The table's columns, followed by its _version system column.
+      | Plan::IndexRangeScan { table, .. } =>
+        self.catalog.mustGetTable(table)?.columns.len( ) + 1,
+
+      Plan::NestedLoopJoin { left, right, .. } | Plan::HashJoin { left, right, .. } => self.size(left)? + self.size(right)?,
+
+      Plan::Filter { source, .. }
+      | Plan::Order { source, .. }
+      | Plan::Distinct { source, .. }
+      | Plan::Limit { source, .. }
+      | Plan::Offset { source, .. } => self.size(source)?,
+
+      Plan::Projection { expressions, .. } => expressions.len( ),
+      Plan::Aggregation { groupSize, aggregates, .. } => groupSize + aggregates.len( ),
+      Plan::Empty { size } => *size,
+      Plan::Values { rows } => rows.first( ).map_or(0, Vec::len),
+      Plan::ShowTables => 1,
+      Plan::DescribeTable { .. } => 9, // See execution::schema::DescribeTable.
+
+      Plan::Nothing
+      | Plan::Insert { .. }
+      | Plan::Update { .. }
+      | Plan::Delete { .. }
+      | Plan::Truncate { .. }
+      | Plan::CreateTable { .. }
+      | Plan::DropTable { .. }
+      | Plan::AlterTable { .. }
+      | Plan::CreateIndex { .. }
+      | Plan::Analyze { .. }
+      | Plan::Explain { .. } => 0
+    })
+  }
+}
+
+// Folds the constant parts of the expression, and simplifies boolean identities.
+fn fold(expression: Expression) -> Result<Expression> {
+  let isTrue= |expression: &Expression| matches!(expression, Expression::Literal(Literal::Boolean(true)));
+  let isFalse= |expression: &Expression| matches!(expression, Expression::Literal(Literal::Boolean(false)));
+
+  expression.transform(&mut |expression| {
+    // The operands have been folded already. So, an operation (or a scalar function call) is
+    // constant if all its operands are literals. Volatile function calls are never constant.
+    let isConstant= match &expression {
+      Expression::Operation(operation) => operation.operands( ).iter( ).all(|operand| matches!(operand, Expression::Literal(_))),
+
+      Expression::FunctionCall(name, arguments) =>
+        getFunction(name).is_some_and(|function| !function.volatile) && arguments.iter( ).all(|argument| matches!(argument, Expression::Literal(_))),
+
+      _ => false
+    };
+
+    if isConstant {
+      return Ok(match expression.evaluate(None) {
+        Ok(value) => Expression::Literal(value.into( )),
+        Err(_) => expression
+      })
+    }
+
+    Ok(match expression {
+      Expression::Operation(Operation::And(lhs, rhs)) => match (*lhs, *rhs) {
+        (lhs, rhs) if isTrue(&lhs) => rhs,
+        (lhs, rhs) if isTrue(&rhs) => lhs,
+        (lhs, rhs) if isFalse(&lhs) || isFalse(&rhs) => Expression::Literal(Literal::Boolean(false)),
+        (lhs, rhs) => Operation::And(Box::new(lhs), Box::new(rhs)).into( )
+      },
+
+      Expression::Operation(Operation::Or(lhs, rhs)) => match (*lhs, *rhs) {
+        (lhs, rhs) if isFalse(&lhs) => rhs,
+        (lhs, rhs) if isFalse(&rhs) => lhs,
+        (lhs, rhs) if isTrue(&lhs) || isTrue(&rhs) => Expression::Literal(Literal::Boolean(true)),
+        (lhs, rhs) => Operation::Or(Box::new(lhs), Box::new(rhs)).into( )
+      },
+
+      Expression::Operation(Operation::Not(operand)) => match *operand {
+        Expression::Operation(Operation::Not(operand)) => *operand,
+        operand => Operation::Not(Box::new(operand)).into( )
+      },
+
+      expression => expression
+    })
+  })
+}
+
+/*
+  Pushes the filter predicates down the plan tree, as close to the table scans as possible : so
+  that rows are filtered out as early as possible (and so that the IndexLookup pass gets to see them).
+
+  Predicates are split into their conjunctive normal form, and each ANDed part is pushed as far as
+  the columns it references allow.
+*/
+struct FilterPushdown;
+
+impl Optimizer for FilterPushdown {
+  fn optimize(&self, plan: Plan) -> Result<Plan> {
+    plan.transform(&mut |node| match node {
+      Plan::Filter { source, predicate } => pushFilter(*source, predicate),
+      node @ Plan::NestedLoopJoin { .. } => pushJoinPredicate(node),
+      node => Ok(node)
+    })
+  }
+}
+
+// Applies the predicate to the rows produced by the node, pushing it as far down as possible.
+fn pushFilter(node: Plan, predicate: Expression) -> Result<Plan> {
+  Ok(match node {
+    // A bounded scan stops before filtering, so the predicate can't be pushed into it.
+    Plan::Scan { table, alias, filter, range, direction, limit: None } => Plan::Scan {
+      table,
+      alias,
+      filter: Expression::and(filter.into_iter( ).chain([predicate]).collect( )),
+      range,
+      direction,
+      limit: None
+    },
+
+    // For an inner join, filtering the joined rows is the same as adding to the join predicate.
+    Plan::NestedLoopJoin { left, leftSize, right, predicate: joinPredicate, outer: false, using } =>
+      pushJoinPredicate(Plan::NestedLoopJoin {
+        left,
+        leftSize,
+        right,
+        predicate: Expression::and(joinPredicate.into_iter( ).chain([predicate]).collect( )),
+        outer: false,
+        using
+      })?,
+
+    // For an outer join, only the parts referencing just the left side can be pushed down (into the
+    // left side). The others may be evaluated against the NULL padded rows.
+    Plan::NestedLoopJoin { left, leftSize, right, predicate: joinPredicate, outer: true, using } => {
+      let (leftPredicates, remaining): (Vec<_>, Vec<_>)= predicate.intoCNFList( )
+                                                                  .into_iter( )
+                                                                  .partition(|expression| !expression.referencesColumn(&|index| index >= leftSize));
+
+      let left= match Expression::and(leftPredicates) {
+        Some(predicate) => pushFilter(*left, predicate)?,
+        None => *left
+      };
+
+      let node= Plan::NestedLoopJoin { left: Box::new(left), leftSize, right, predicate: joinPredicate, outer: true, using };
+      match Expression::and(remaining) {
+        Some(predicate) => Plan::Filter { source: Box::new(node), predicate },
+        None => node
+      }
+    },
+
+    // A projection which only reorders the columns (like the one of a right join) can be seen
+    // through, by remapping the columns.
+    Plan::Projection { source, expressions }
+      if expressions.iter( ).all(|(expression, _)| matches!(expression, Expression::Column(_))) =>
+    {
+      let predicate= predicate.transform(&mut |expression| Ok(match expression {
+        Expression::Column(index) => expressions[index].0.clone( ),
+        expression => expression
+      }))?;
+
+      Plan::Projection { source: Box::new(pushFilter(*source, predicate)?), expressions }
+    },
+
+    Plan::Filter { source, predicate: existing } => Plan::Filter {
+      source,
+      predicate: Operation::And(Box::new(existing), Box::new(predicate)).into( )
+    },
+
+    node => Plan::Filter { source: Box::new(node), predicate }
+  })
+}
+
+// Pushes the parts of a join predicate referencing just one side of the join, into that side.
+fn pushJoinPredicate(node: Plan) -> Result<Plan> {
+  let Plan::NestedLoopJoin { left, leftSize, right, predicate: Some(predicate), outer, using }= node else {
+    return Ok(node)
+  };
+
+  let (mut leftPredicates, mut rightPredicates, mut remaining)= (Vec::new( ), Vec::new( ), Vec::new( ));
+
+  for expression in predicate.intoCNFList( ) {
+    let referencesLeft= expression.referencesColumn(&|index| index < leftSize);
+    let referencesRight= expression.referencesColumn(&|index| index >= leftSize);
+
+    match (referencesLeft, referencesRight) {
+      // Every left row of an outer join is emitted, irrespective of the join predicate.
+      (true, false) if !outer => leftPredicates.push(expression),
+
+      // The columns of the right side are shifted, to be relative to the right rows.
+      (false, true) => rightPredicates.push(expression.transform(&mut |expression| Ok(match expression {
+        Expression::Column(index) => Expression::Column(index - leftSize),
+        expression => expression
+      }))?),
+
+      _ => remaining.push(expression)
+    }
+  }
+
+  let left= match Expression::and(leftPredicates) {
+    Some(predicate) => pushFilter(*left, predicate)?,
+    None => *left
+  };
+
+  let right= match Expression::and(rightPredicates) {
+    Some(predicate) => pushFilter(*right, predicate)?,
+    None => *right
+  };
+
+  Ok(Plan::NestedLoopJoin {
+    left: Box::new(left),
+    leftSize,
+    right: Box::new(right),
+    predicate: Expression::and(remaining),
+    outer,
+    using
+  })
+}
+
+/*
+  Replaces a filtered table scan by a primary key lookup or a secondary index lookup, when a part of
+  the filter is an equality comparison of the primary key / an indexed column with constants. The
+  rest of the filter is applied on the looked up rows. An expression index is looked up likewise,
+  when the filter compares the same expression (structurally) with constants.
+
+  Primary key lookups are preferred, since they avoid going through the index. A secondary index
+  isn't looked up, if the statistics of the table estimate that the lookup matches too many of its
+  rows (see INDEX_SELECTIVITY_THRESHOLD) : like for a column with a few distinct values.
+
+  Otherwise, a LIKE match of an indexed column against a pattern starting with literal characters
+  (like col LIKE 'abc%') is turned into a scan of the index over the range of values starting with
+  them (['abc', 'abd')). The LIKE is still applied on the scanned rows, unless the pattern is just
+  the prefix followed by a %.
+*/
+struct IndexLookup<'a, C: Catalog + ?Sized> {
+  catalog: &'a C,
+  statistics: &'a Statistics
+}
+
+impl<'a, C: Catalog + ?Sized> Optimizer for IndexLookup<'a, C> {
+  fn optimize(&self, plan: Plan) -> Result<Plan> {
+    plan.transform(&mut |node| match node {
+      Plan::Scan { table, alias, filter: Some(filter), range, direction, limit: None } => {
+        let schema= self.catalog.mustGetTable(&table)?;
+        let mut expressions= filter.intoCNFList( );
+
+        // The (indexed expression, data type of its values, index name) triples of the primary key
+        // (which needs no index), the indexed columns and the expression indexes.
+        let mut candidates= vec![(Expression::Column(schema.primaryKey), schema.getPrimaryKeyColumn( ).dataType, None)];
+        for (index, column) in schema.columns.iter( ).enumerate( ) {
+          if column.index || column.unique {
+            candidates.push((Expression::Column(index), column.dataType, Some(column.name.clone( ))));}
+        }
+        for index in &schema.indexes {
+          if let Some(dataType)= index.valueType(&schema)?.dataType {
+            candidates.push((index.resolveExpression(&schema)?, dataType, Some(index.name.clone( ))));}
+        }
+
+        for (indexed, dataType, index) in candidates {
+          // The constants must be of the column's data type, since the lookups match values exactly
+          // (while the comparison would coerce integers and floats).
+          let lookup= expressions.iter( )
+                                 .enumerate( )
+                                 .find_map(|(position, expression)| {
+                                   expression.asLookup(&indexed)
+                                             .filter(|values| values.iter( ).all(|value| value.dataType( ) == Some(dataType)))
+                                             .map(|values| (position, values))
+                                 });
+
+          if let Some((position, mut values))= lookup {
+            values.sort( );
+            values.dedup( );
+
+            let isSelective= index.as_ref( )
+                                  .and_then(|index| self.statistics.index(&table, index))
+                                  .is_none_or(|(_, stats)| values.len( ) as f64 * stats.equalitySelectivity( ) <= INDEX_SELECTIVITY_THRESHOLD);
+            if !isSelective {
+              continue}
+
+            expressions.remove(position);
+
+            let node= match index {
+              None => Plan::KeyLookup { table, alias, keys: values },
+              Some(index) => Plan::IndexLookup { table, alias, column: index, values }
+            };
+
+            return Ok(match Expression::and(expressions) {
+              Some(predicate) => Plan::Filter { source: Box::new(node), predicate },
+              None => node
+            })
+          }
+        }
+
+        // Comparisons of the primary key with constants (of its data type) bound the range of the
+        // scan, so that only the rows in that range are read. Unlike an index lookup, the scan keeps
+        // the primary key order - which lets a LIMIT bound it too (like for keyset pagination).
+        let primaryKeyType= schema.columns[schema.primaryKey].dataType;
+        let mut keyRange= range.clone( );
+
+        expressions.retain(|expression| {
+          let bound= expression.asRange(schema.primaryKey)
+                               .filter(|bound| rangeValues(bound).all(|value| value.dataType( ) == Some(primaryKeyType)));
+
+          match bound {
+            Some(bound) => {
+              keyRange= intersectRanges(std::mem::replace(&mut keyRange, (Bound::Unbounded, Bound::Unbounded)), bound);
+              false
+            },
+            None => true
+          }
+        });
+
+        if keyRange != range {
+          return Ok(Plan::Scan { table, alias, filter: Expression::and(expressions), range: keyRange, direction, limit: None })}
+
+        let indexedStringColumns= (0..schema.columns.len( )).filter(|index| {
+          let column= &schema.columns[*index];
+          *index != schema.primaryKey && (column.index || column.unique) && column.dataType == DataType::String
+        });
+
+        for column in indexedStringColumns {
+          let prefixMatch= expressions.iter( )
+                                      .enumerate( )
+                                      .find_map(|(position, expression)| expression.asPrefixMatch(column).map(|prefixMatch| (position, prefixMatch)));
+
+          if let Some((position, (prefix, isPrefixOnly)))= prefixMatch {
+            if isPrefixOnly {
+              expressions.remove(position);}
+
+            let range= (
+              Bound::Included(Value::String(prefix.clone( ))),
+              prefixEnd(&prefix).map_or(Bound::Unbounded, |end| Bound::Excluded(Value::String(end)))
+            );
+            let node= Plan::IndexRangeScan { table, alias, column: schema.columns[column].name.clone( ), range };
+
+            return Ok(match Expression::and(expressions) {
+              Some(predicate) => Plan::Filter { source: Box::new(node), predicate },
+              None => node
+            })
+          }
+        }
+
+        Ok(Plan::Scan { table, alias, filter: Expression::and(expressions), range, direction, limit: None })
+      },
+
+      node => Ok(node)
+    })
+  }
+}
+
+// Returns the values bounding the range.
+fn rangeValues(range: &IndexRange) -> impl Iterator<Item= &Value> {
+  [&range.0, &range.1].into_iter( ).filter_map(|bound| match bound {
+    Bound::Included(value) | Bound::Excluded(value) => Some(value),
+    Bound::Unbounded => None
+  })
+}
+
+// Returns the range of the values lying in both the ranges : bounded by the greater of the start
+// bounds, and the lesser of the end bounds. An excluded bound is tighter than an included one, on
+// the same value.
+fn intersectRanges(lhs: IndexRange, rhs: IndexRange) -> IndexRange {
+  let tighter= |lhs: Bound<Value>, rhs: Bound<Value>, isStart: bool| match (&lhs, &rhs) {
+    (Bound::Unbounded, _) => rhs,
+    (_, Bound::Unbounded) => lhs,
+
+    (Bound::Included(lhsValue) | Bound::Excluded(lhsValue), Bound::Included(rhsValue) | Bound::Excluded(rhsValue)) =>
+      match lhsValue.cmp(rhsValue) {
+        std::cmp::Ordering::Equal => match lhs {
+          Bound::Excluded(_) => lhs,
+          _ => rhs
+        },
+        ordering => match (ordering == std::cmp::Ordering::Greater) == isStart {
+          true => lhs,
+          false => rhs
+        }
+      }
+  };
+
+  (tighter(lhs.0, rhs.0, true), tighter(lhs.1, rhs.1, false))
+}
+
+// Returns the smallest string which is greater than every string starting with the prefix : the
+// prefix with its last character incremented. There's none, if every character is the maximum one.
+fn prefixEnd(prefix: &str) -> Option<String> {
+  let mut characters: Vec<char>= prefix.chars( ).collect( );
+
+  while let Some(character)= characters.pop( ) {
+    // Surrogates aren't valid characters, so they're skipped.
+    let next= match character {
+      '\u{D7FF}' => Some('\u{E000}'),
+      character => char::from_u32(character as u32 + 1)
+    };
+
+    if let Some(next)= next {
+      characters.push(next);
+      return Some(characters.into_iter( ).collect( ))
+    }
+  }
+
+  None
+}
+
+/*
+  Since a table's rows are stored in primary key order, sorting a scan by the primary key is replaced
+  by scanning in that order : an Order node right above a scan is removed, if its leading sort key is
+  the primary key (which, being unique, decides the order on its own). A descending order makes it a
+  reverse scan. Orders in mixed directions are left as is.
+
+  Then a Limit (along with an Offset, if any) right above an unfiltered scan bounds the scan, so that
+  it stops after the rows which are needed.
+*/
+struct OrderedScan<'a, C: Catalog + ?Sized> {
+  catalog: &'a C
+}
+
+impl<'a, C: Catalog + ?Sized> Optimizer for OrderedScan<'a, C> {
+  fn optimize(&self, plan: Plan) -> Result<Plan> {
+    plan.transform(&mut |node| Ok(match node {
+      Plan::Order { source, orders } => match *source {
+        Plan::Scan { table, alias, filter, range, direction: Direction::Forward, limit: None } => {
+          let primaryKey= self.catalog.mustGetTable(&table)?.primaryKey;
+
+          let direction= match orders.first( ) {
+            Some((Expression::Column(column), order)) if *column == primaryKey && orders.iter( ).all(|(_, other)| other == order) =>
+              Some(match order {
+                Order::Ascending => Direction::Forward,
+                Order::Descending => Direction::Reverse
+              }),
+
+            _ => None
+          };
+
+          match direction {
+            Some(direction) => Plan::Scan { table, alias, filter, range, direction, limit: None },
+            None => Plan::Order { source: Box::new(Plan::Scan { table, alias, filter, range, direction: Direction::Forward, limit: None }), orders }
+          }
+        },
+
+        source => Plan::Order { source: Box::new(source), orders }
+      },
+
+      Plan::Limit { source, limit } => {
+        let source= match *source {
+          Plan::Scan { table, alias, filter: None, range, direction, limit: None } =>
+            Plan::Scan { table, alias, filter: None, range, direction, limit: Some(limit) },
+
+          Plan::Offset { source, offset } => match *source {
+            Plan::Scan { table, alias, filter: None, range, direction, limit: None } => Plan::Offset {
+              source: Box::new(Plan::Scan { table, alias, filter: None, range, direction, limit: Some(limit.saturating_add(offset)) }),
+              offset
+            },
+
+            source => Plan::Offset { source: Box::new(source), offset }
+          },
+
+          source => source
+        };
+
+        Plan::Limit { source: Box::new(source), limit }
+      },
+
+      node => node
+    }))
+  }
+}
+
+/*
+  Replaces nested loop joins on an equality of a left column and a right column (equi-joins) by hash
+  joins. For an inner join, the rest of the join predicate is applied as a filter on the joined rows.
+  That's not possible for an outer join, so its predicate must be just the equality.
+
+  The hash table is built over the right rows, unless the statistics estimate that there are fewer
+  left rows (of an inner join) : the smaller the hash table, the less memory it takes.
+*/
+struct HashJoin<'a> {
+  statistics: &'a Statistics
+}
+
+impl Optimizer for HashJoin<'_> {
+  fn optimize(&self, plan: Plan) -> Result<Plan> {
+    plan.transform(&mut |node| Ok(match node {
+      Plan::NestedLoopJoin { left, leftSize, right, predicate: Some(predicate), outer, using } => {
+        let mut expressions= predicate.intoCNFList( );
+
+        let equiJoin= expressions.iter( )
+                                 .enumerate( )
+                                 .find_map(|(position, expression)| {
+                                   asEquiJoin(expression, leftSize).map(|(leftColumn, rightColumn)| (position, leftColumn, rightColumn))
+                                 });
+
+        match equiJoin {
+          Some((position, leftColumn, rightColumn)) if !outer || expressions.len( ) == 1 => {
+            expressions.remove(position);
+
+            let buildLeft= match (self.statistics.rows(&left), self.statistics.rows(&right)) {
+              (Some(leftRows), Some(rightRows)) => !outer && leftRows < rightRows,
+              _ => false
+            };
+
+            let node= Plan::HashJoin { left, leftColumn, right, rightColumn: rightColumn - leftSize, outer, using, buildLeft };
+            match Expression::and(expressions) {
+              Some(predicate) => Plan::Filter { source: Box::new(node), predicate },
+              None => node
+            }
+          },
+
+          _ => Plan::NestedLoopJoin { left, leftSize, right, predicate: Expression::and(expressions), outer, using }
+        }
+      },
+
+      node => node
+    }))
+  }
+}
+
+// Checks whether the expression is an equality of a left column and a right column (in either
+// order). If so, returns the (left, right) columns.
+fn asEquiJoin(expression: &Expression, leftSize: usize) -> Option<(usize, usize)> {
+  let Expression::Operation(Operation::Equal(lhs, rhs))= expression else {
+    return None
+  };
+
+  match (lhs.as_ref( ), rhs.as_ref( )) {
+    (Expression::Column(left), Expression::Column(right))
+    | (Expression::Column(right), Expression::Column(left)) if *left < leftSize && *right >= leftSize => Some((*left, *right)),
+
+    _ => None
+  }
+}
@@ -0,0 +1,1086 @@
+use std::{collections::{HashMap, HashSet}, ops::Bound};
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{
+    functions::getFunction,
+    parser::ast::{self, AliasColumnName, Expression, InsertSource, JoinConstraint, JoinType, Literal, Operation, Order, SearchField, Statement},
+    schema::{Catalog, Index, Table, VERSION_COLUMN},
+    types::{DataType, Value, ValueType}
+  },
+  storage::engine::Direction
+};
+use super::{Aggregate, OnConflict, Plan, PlanOptions};
+
+// The name, under which the ON CONFLICT DO UPDATE of an INSERT references the row which wasn't
+// inserted.
+const EXCLUDED_TABLE: &str= "excluded";
+
+// Lowers statements into plans, looking up the tables in the catalog.
+pub struct Planner<'a, C: Catalog + ?Sized> {
+  catalog: &'a C,
+  options: PlanOptions,
+
+  // When planning a subquery, the scope of the query it's nested in.
+  outer: Option<&'a Scope>
+}
+
+impl<'a, C: Catalog + ?Sized> Planner<'a, C> {
+  pub fn new(catalog: &'a C, options: PlanOptions) -> Self {
+    Self { catalog, options, outer: None }
+  }
+
+  pub fn build(&self, statement: Statement) -> Result<Plan> {
+    match statement {
+      Statement::Begin { .. } | Statement::Commit | Statement::Rollback =>
+        Err(Error::Internal("Transaction control statements can't be planned".to_string( ))),
+
+      Statement::Status => Err(Error::Value(ErrorCode::FeatureNotSupported, "STATUS can't be planned".to_string( ))),
+      Statement::Copy { .. } => Err(Error::Value(ErrorCode::FeatureNotSupported, "COPY can't be planned".to_string( ))),
+      Statement::Set { .. } => Err(Error::Value(ErrorCode::FeatureNotSupported, "SET can't be planned".to_string( ))),
+      Statement::ShowVariable(_) | Statement::ShowVariables => Err(Error::Value(ErrorCode::FeatureNotSupported, "SHOW can't be planned".to_string( ))),
+
+      Statement::Explain { statement, analyze } => match *statement {
+        Statement::Explain { .. } => Err(Error::Value(ErrorCode::FeatureNotSupported, "EXPLAIN can't be nested".to_string( ))),
+        statement => Ok(Plan::Explain { plan: Box::new(self.build(statement)?), analyze, estimates: None })
+      },
+
+      Statement::CreateTable { name, columns, ifNotExists } => Ok(Plan::CreateTable { schema: Table::new(name, columns)?, ifNotExists }),
+
+      Statement::DropTable { name, ifExists } => Ok(Plan::DropTable { table: name, ifExists }),
+
+      Statement::AlterTable { name, action } => {
+        self.catalog.mustGetTable(&name)?;
+        Ok(Plan::AlterTable { table: name, action })
+      },
+
+      Statement::CreateIndex { name, table, expression } => {
+        let index= Index::new(&self.catalog.mustGetTable(&table)?, name, expression)?;
+        Ok(Plan::CreateIndex { table, index })
+      },
+
+      Statement::ShowTables => Ok(Plan::ShowTables),
+
+      Statement::DescribeTable(table) => Ok(Plan::DescribeTable { schema: self.catalog.mustGetTable(&table)? }),
+
+      Statement::Insert { table, columns, source, onConflict } => self.buildInsert(table, columns, source, onConflict),
+
+      Statement::Update { table, updates, r#where } => {
+        let schema= self.catalog.mustGetTable(&table)?;
+
+        let mut scope= self.newScope( );
+        scope.addTable(&table, &schema)?;
+
+        for expression in updates.values( ).chain(&r#where) {
+          self.checkSubqueries(expression, &scope)?;}
+
+        for (column, expression) in &updates {
+          checkWritable(column)?;
+          checkNoAggregates("UPDATE SET", expression)?;
+          let dataType= schema.columns[schema.getColumnIndex(column)?].dataType;
+          checkAssignment(column, dataType, expression, scope.checkType(expression)?)?;
+        }
+        if let Some(expression)= &r#where {
+          checkNoAggregates("WHERE", expression)?;
+          scope.checkPredicate("WHERE clause", expression)?;
+        }
+
+        let expressions= updates.into_iter( )
+                                .map(|(column, expression)| {
+                                  Ok((schema.getColumnIndex(&column)?, scope.resolveExpression(expression)?))
+                                })
+                                .collect::<Result<_>>( )?;
+
+        // Like for a query, the optimizer turns the filtered scan into a key / index lookup where
+        // possible.
+        let source= self.buildFilter(Plan::Scan { table: table.clone( ), alias: None, filter: None, range: (Bound::Unbounded, Bound::Unbounded), direction: Direction::Forward, limit: None }, r#where, &scope)?;
+
+        Ok(Plan::Update { table, source: Box::new(source), expressions })
+      },
+
+      Statement::Delete { table, r#where } => {
+        let schema= self.catalog.mustGetTable(&table)?;
+
+        let mut scope= self.newScope( );
+        scope.addTable(&table, &schema)?;
+
+        if let Some(expression)= &r#where {
+          self.checkSubqueries(expression, &scope)?;
+          checkNoAggregates("WHERE", expression)?;
+          scope.checkPredicate("WHERE clause", expression)?;
+        }
+
+        let source= self.buildFilter(Plan::Scan { table: table.clone( ), alias: None, filter: None, range: (Bound::Unbounded, Bound::Unbounded), direction: Direction::Forward, limit: None }, r#where, &scope)?;
+
+        Ok(Plan::Delete { table, source: Box::new(source) })
+      },
+
+      Statement::Truncate(table) => {
+        self.catalog.mustGetTable(&table)?;
+        Ok(Plan::Truncate { table })
+      },
+
+      // Without a table, every table is analyzed.
+      Statement::Analyze(table) => {
+        let tables= match table {
+          Some(table) => vec![self.catalog.mustGetTable(&table)?.name],
+          None => self.catalog.listTables( )?.into_iter( ).map(|table| table.name).collect( )
+        };
+        Ok(Plan::Analyze { tables })
+      },
+
+      Statement::Select { distinct, selections, from, r#where, groupBy, having, order, limit, offset } =>
+        self.buildSelect(distinct, selections, from, r#where, groupBy, having, order, limit, offset)
+    }
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn buildSelect(&self,
+                 distinct: bool,
+                 selections: Vec<(Expression, Option<AliasColumnName>)>,
+                 from: Vec<SearchField>,
+                 r#where: Option<Expression>,
+                 groupBy: Vec<Expression>,
+                 having: Option<Expression>,
+                 order: Vec<(Expression, Order)>,
+                 limit: Option<Expression>,
+                 offset: Option<Expression>) -> Result<Plan>
+  {
+    // Multiple items in the FROM clause are cross joined.
+    let mut scope= self.newScope( );
+    let mut node= Plan::Nothing;
+
+    for (index, searchField) in from.into_iter( ).enumerate( ) {
+      let (right, rightScope)= self.buildSearchField(searchField)?;
+
+      node= match index {
+        0 => right,
+        _ => Plan::NestedLoopJoin {
+          left: Box::new(node),
+          leftSize: scope.columns.len( ),
+          right: Box::new(right),
+          predicate: None,
+          outer: false,
+          using: Vec::new( )
+        }
+      };
+      scope.merge(rightScope)?;
+    }
+
+    // SELECT * projects all the columns, and t.* the columns of table t (except the system columns).
+    // Selected fields are labeled with their column names, unless aliased.
+    let mut expanded= match selections.is_empty( ) {
+      true => scope.wildcardColumns(None)?,
+      false => Vec::new( )
+    };
+    // The explicit aliases of the (expanded) selections.
+    let mut aliases= vec![None; expanded.len( )];
+
+    for (expression, alias) in selections {
+      match expression {
+        Expression::QualifiedWildcard(table) => {
+          expanded.extend(scope.wildcardColumns(Some(&table))?);
+          aliases.resize(expanded.len( ), None);
+        },
+
+        expression => {
+          let label= match (&alias, &expression) {
+            (Some(alias), _) => Some(alias.clone( )),
+            (None, Expression::Field(_, name)) => Some(name.clone( )),
+            _ => None
+          };
+          expanded.push((expression, label));
+          aliases.push(alias);
+        }
+      }
+    }
+    let selections= expanded;
+
+    // ORDER BY and GROUP BY can refer to the selections by their aliases or positions, and HAVING by
+    // their aliases.
+    let references= SelectionReferences { selections: &selections, aliases: &aliases };
+    let order= order.into_iter( )
+                    .map(|(expression, order)| Ok((references.resolve("ORDER BY", expression)?, order)))
+                    .collect::<Result<Vec<_>>>( )?;
+    let groupBy= groupBy.into_iter( )
+                        .map(|expression| references.resolve("GROUP BY", expression))
+                        .collect::<Result<Vec<_>>>( )?;
+    let having= having.map(|having| references.resolveAliases("HAVING", having)).transpose( )?;
+    let r#where= r#where.map(|expression| references.resolveWhereAliases(expression, &scope, self.options.lenientAliases)).transpose( )?;
+
+    let expressions= selections.iter( )
+                               .map(|(expression, _)| expression)
+                               .chain(&r#where)
+                               .chain(&groupBy)
+                               .chain(&having)
+                               .chain(order.iter( ).map(|(expression, _)| expression));
+    for expression in expressions {
+      self.checkSubqueries(expression, &scope)?;}
+
+    // Type errors are reported upfront, rather than when the first offending row is evaluated.
+    let expressions= selections.iter( )
+                               .map(|(expression, _)| expression)
+                               .chain(&groupBy)
+                               .chain(order.iter( ).map(|(expression, _)| expression));
+    for expression in expressions {
+      scope.checkType(expression)?;}
+
+    if let Some(expression)= &r#where {
+      checkNoAggregates("WHERE", expression)?;
+      scope.checkPredicate("WHERE clause", expression)?;
+    }
+    if let Some(expression)= &having {
+      scope.checkPredicate("HAVING clause", expression)?;}
+
+    node= self.buildFilter(node, r#where, &scope)?;
+
+    // With DISTINCT, the rows are sorted after being projected (see below). So the ORDER BY
+    // expressions must be among the selections.
+    let orderLabels= order.iter( ).map(|(expression, _)| expression.to_string( )).collect::<Vec<_>>( );
+
+    let isAggregated= !groupBy.is_empty( ) || having.is_some( ) ||
+                      selections.iter( ).any(|(expression, _)| containsAggregate(expression)) ||
+                      order.iter( ).any(|(expression, _)| containsAggregate(expression));
+
+    let (selections, having, order)= match isAggregated {
+      // The selections, HAVING and ORDER BY are evaluated against the aggregated rows.
+      true => {
+        let mut aggregation= AggregationBuilder::new(&scope, groupBy)?;
+
+        let selections= selections.into_iter( )
+                                  .map(|(expression, label)| Ok((aggregation.build(expression)?, label)))
+                                  .collect::<Result<Vec<_>>>( )?;
+        let having= having.map(|having| aggregation.build(having)).transpose( )?;
+        let order= order.into_iter( )
+                        .map(|(expression, order)| Ok((aggregation.build(expression)?, order)))
+                        .collect::<Result<Vec<_>>>( )?;
+
+        node= aggregation.plan(node);
+        (selections, having, order)
+      },
+
+      false => (
+        selections.into_iter( )
+                  .map(|(expression, label)| Ok((scope.resolveExpression(expression)?, label)))
+                  .collect::<Result<Vec<_>>>( )?,
+        None,
+        order.into_iter( )
+             .map(|(expression, order)| Ok((scope.resolveExpression(expression)?, order)))
+             .collect::<Result<Vec<_>>>( )?
+      )
+    };
+
+    if let Some(having)= having {
+      node= Plan::Filter { source: Box::new(node), predicate: having };}
+
+    if distinct {
+      return self.buildDistinct(node, selections, order, orderLabels, limit, offset)}
+
+    if !order.is_empty( ) {
+      node= Plan::Order { source: Box::new(node), orders: order };}
+
+    if let Some(offset)= offset {
+      node= Plan::Offset { source: Box::new(node), offset: evaluateCount("OFFSET", offset)? };}
+
+    if let Some(limit)= limit {
+      node= Plan::Limit { source: Box::new(node), limit: evaluateCount("LIMIT", limit)? };}
+
+    Ok(Plan::Projection { source: Box::new(node), expressions: selections })
+  }
+
+  /*
+    Plans the rest of a SELECT DISTINCT statement : the rows are projected and deduplicated, and then
+    the ORDER BY, OFFSET and LIMIT apply to the distinct rows. The ORDER BY expressions are evaluated
+    against the projected rows, so they must be among the selections.
+
+    When the rows are sorted by all the selections, duplicate rows end up next to each other - so the
+    deduplication only needs to compare a row with the previous one.
+  */
+  fn buildDistinct(&self,
+                   source: Plan,
+                   selections: Vec<(Expression, Option<AliasColumnName>)>,
+                   order: Vec<(Expression, Order)>,
+                   orderLabels: Vec<String>,
+                   limit: Option<Expression>,
+                   offset: Option<Expression>) -> Result<Plan>
+  {
+    let orders= order.into_iter( )
+                     .zip(orderLabels)
+                     .map(|((expression, order), label)| {
+                       let index= selections.iter( )
+                                            .position(|(selection, _)| *selection == expression)
+                                            .ok_or_else(| | Error::Value(ErrorCode::InvalidColumnReference, format!("ORDER BY expression {} must be among the selections of SELECT DISTINCT", label)))?;
+                       Ok((Expression::Column(index), order))
+                     })
+                     .collect::<Result<Vec<_>>>( )?;
+
+    let sorted= !orders.is_empty( ) &&
+                (0..selections.len( )).all(|index| orders.iter( ).any(|(expression, _)| *expression == Expression::Column(index)));
+
+    let mut node= Plan::Projection { source: Box::new(source), expressions: selections };
+    if !orders.is_empty( ) {
+      node= Plan::Order { source: Box::new(node), orders };}
+
+    node= Plan::Distinct { source: Box::new(node), sorted };
+
+    if let Some(offset)= offset {
+      node= Plan::Offset { source: Box::new(node), offset: evaluateCount("OFFSET", offset)? };}
+
+    if let Some(limit)= limit {
+      node= Plan::Limit { source: Box::new(node), limit: evaluateCount("LIMIT", limit)? };}
+
+    Ok(node)
+  }
+
+  fn buildInsert(&self, table: String, columns: Option<Vec<String>>, source: InsertSource, onConflict: Option<ast::OnConflict>) -> Result<Plan> {
+    let schema= self.catalog.mustGetTable(&table)?;
+
+    let columns= columns.unwrap_or_default( );
+
+    let mut seen= HashSet::new( );
+    for column in &columns {
+      checkWritable(column)?;
+      schema.getColumnIndex(column)?;
+
+      if !seen.insert(column) {
+        return Err(Error::Value(ErrorCode::DuplicateColumn, format!("Column {} is given multiple times", column)))}
+    }
+
+    let source= match source {
+      InsertSource::Values(values) => {
+        // Rows of differing lengths are rejected upfront, rather than failing after inserting some
+        // of the rows.
+        if let Some((index, row))= values.iter( ).enumerate( ).find(|(_, row)| row.len( ) != values[0].len( )) {
+          return Err(Error::Value(ErrorCode::SyntaxError, format!("All VALUES rows must have the same number of values, but row {} has {} values while row 1 has {}",
+                                          index + 1, row.len( ), values[0].len( ))))}
+
+        // The values can't reference any fields.
+        let scope= self.newScope( );
+
+        for expression in values.iter( ).flatten( ) {
+          self.checkSubqueries(expression, &scope)?;}
+
+        // The values are inserted into the given columns, or else into all the columns in order.
+        let targets= match columns.is_empty( ) {
+          true => schema.columns.iter( ).collect::<Vec<_>>( ),
+          false => columns.iter( ).map(|column| &schema.columns[schema.getColumnIndex(column).unwrap( )]).collect( )
+        };
+        for row in &values {
+          for (target, expression) in targets.iter( ).zip(row) {
+            checkNoAggregates("VALUES", expression)?;
+            checkAssignment(&target.name, target.dataType, expression, scope.checkType(expression)?)?;
+          }
+        }
+
+        let rows= values.into_iter( )
+                        .map(|row| {
+                          row.into_iter( )
+                             .map(|expression| scope.resolveExpression(expression))
+                             .collect::<Result<Vec<_>>>( )
+                        })
+                        .collect::<Result<_>>( )?;
+
+        Plan::Values { rows }
+      },
+
+      InsertSource::Select(statement) => self.build(*statement)?
+    };
+
+    let onConflict= match onConflict {
+      Some(onConflict) => Some(self.buildOnConflict(&table, &schema, onConflict)?),
+      None => None
+    };
+
+    Ok(Plan::Insert { table, columns, source: Box::new(source), onConflict })
+  }
+
+  // Builds the ON CONFLICT clause of an INSERT. The conflict column must be the primary key or a
+  // unique column, so that a row conflicts with at most one existing row.
+  fn buildOnConflict(&self, table: &str, schema: &Table, onConflict: ast::OnConflict) -> Result<OnConflict> {
+    let column= schema.getColumnIndex(&onConflict.column)?;
+    if column != schema.primaryKey && !schema.columns[column].unique {
+      return Err(Error::Value(ErrorCode::InvalidColumnReference, format!("ON CONFLICT column {} is neither the primary key nor a unique column of table {}", onConflict.column, table)))}
+
+    let Some(updates)= onConflict.updates else {
+      return Ok(OnConflict { column, updates: None })
+    };
+
+    // The updates see the existing row, and (qualified by excluded) the row which wasn't inserted.
+    let mut scope= self.newScope( );
+    scope.addTable(table, schema)?;
+    scope.addQualifiedTable(EXCLUDED_TABLE, schema)?;
+
+    for expression in updates.values( ) {
+      self.checkSubqueries(expression, &scope)?;}
+
+    for (column, expression) in &updates {
+      checkWritable(column)?;
+      checkNoAggregates("ON CONFLICT DO UPDATE", expression)?;
+      let dataType= schema.columns[schema.getColumnIndex(column)?].dataType;
+      checkAssignment(column, dataType, expression, scope.checkType(expression)?)?;
+    }
+
+    let updates= updates.into_iter( )
+                        .map(|(column, expression)| Ok((schema.getColumnIndex(&column)?, scope.resolveExpression(expression)?)))
+                        .collect::<Result<_>>( )?;
+
+    Ok(OnConflict { column, updates: Some(updates) })
+  }
+
+  // Builds the plan for an item of the FROM clause, returning it along with the scope of the rows
+  // it produces.
+  fn buildSearchField(&self, searchField: SearchField) -> Result<(Plan, Scope)> {
+    match searchField {
+      SearchField::Table { name, alias } => {
+        let schema= self.catalog.mustGetTable(&name)?;
+
+        let mut scope= self.newScope( );
+        scope.addTable(alias.as_deref( ).unwrap_or(&name), &schema)?;
+
+        Ok((Plan::Scan { table: name, alias, filter: None, range: (Bound::Unbounded, Bound::Unbounded), direction: Direction::Forward, limit: None }, scope))
+      },
+
+      SearchField::Join { left, right, r#type, constraint } => {
+        // A right join is executed as a left join with the sides swapped. The columns are reordered
+        // back afterwards.
+        // The sides are described (as SQL) in the errors.
+        let mut sides= [("left", left.to_string( )), ("right", right.to_string( ))];
+        let (left, right)= match r#type {
+          JoinType::Right => {
+            sides.swap(0, 1);
+            (right, left)
+          },
+          _ => (left, right)
+        };
+
+        let (leftNode, leftScope)= self.buildSearchField(*left)?;
+        let (rightNode, mut rightScope)= self.buildSearchField(*right)?;
+
+        // The columns of a USING (or NATURAL) join, along with their indexes on both sides.
+        let usingColumns= match &constraint {
+          JoinConstraint::Using(names) => names.clone( ),
+          JoinConstraint::Natural => leftScope.commonColumns(&rightScope, &sides)?,
+          _ => Vec::new( )
+        };
+        let mut using= Vec::new( );
+        for name in &usingColumns {
+          if using.iter( ).any(|(existing, _, _)| existing == name) {
+            return Err(Error::Value(ErrorCode::DuplicateColumn, format!("Column {} is listed multiple times in USING", name)))}
+
+          using.push((name.clone( ), leftScope.resolveJoinColumn(name, &sides[0])?, rightScope.resolveJoinColumn(name, &sides[1])?));
+        }
+
+        // The columns of the right side are NULL, for the left rows which don't match any right row.
+        if matches!(r#type, JoinType::Left | JoinType::Right) {
+          rightScope.makeNullable( );}
+
+        let leftSize= leftScope.columns.len( );
+        let rightSize= rightScope.columns.len( );
+
+        let mut scope= leftScope.clone( );
+        scope.merge(rightScope.clone( ))?;
+
+        // A USING join matches the rows whose values are equal in each of its columns.
+        let predicate= match constraint {
+          JoinConstraint::On(predicate) => Some(predicate),
+
+          _ => Expression::and(using.iter( ).map(|(_, leftIndex, rightIndex)| {
+            let (leftTable, name, ..)= &leftScope.columns[*leftIndex];
+            let (rightTable, ..)= &rightScope.columns[*rightIndex];
+
+            let field= |table: &Option<String>| Box::new(Expression::Field(table.clone( ), name.clone( ).unwrap_or_default( )));
+            Operation::Equal(field(leftTable), field(rightTable)).into( )
+          }).collect( ))
+        };
+
+        if let Some(predicate)= &predicate {
+          self.checkSubqueries(predicate, &scope)?;
+          checkNoAggregates("JOIN predicate", predicate)?;
+          scope.checkPredicate("JOIN predicate", predicate)?;
+        }
+
+        let node= Plan::NestedLoopJoin {
+          left: Box::new(leftNode),
+          leftSize,
+          right: Box::new(rightNode),
+          predicate: predicate.map(|predicate| scope.resolveExpression(predicate)).transpose( )?,
+          outer: matches!(r#type, JoinType::Left | JoinType::Right),
+          using: usingColumns
+        };
+
+        // Each join column is kept from the left side, which is the side whose rows are all emitted
+        // by an outer join (so its values aren't NULL padded).
+        match r#type {
+          JoinType::Right => {
+            let expressions= (leftSize..leftSize + rightSize).chain(0..leftSize)
+                                                             .map(|index| (Expression::Column(index), None))
+                                                             .collect( );
+
+            let mut scope= rightScope;
+            scope.merge(leftScope)?;
+            scope.coalesce(&using.iter( ).map(|(_, leftIndex, rightIndex)| (rightSize + leftIndex, *rightIndex)).collect::<Vec<_>>( ));
+
+            Ok((Plan::Projection { source: Box::new(node), expressions }, scope))
+          },
+
+          _ => {
+            scope.coalesce(&using.iter( ).map(|(_, leftIndex, rightIndex)| (*leftIndex, leftSize + rightIndex)).collect::<Vec<_>>( ));
+            Ok((node, scope))
+          }
+        }
+      }
+    }
+  }
+
+  /*
+    Checks that the subqueries in the expression can be planned, and that they return a single
+    column. They can't reference the columns of the given (outer) scope.
+
+    NOTE : The subqueries are planned again, when they're evaluated at the start of the execution.
+  */
+  fn checkSubqueries(&self, expression: &Expression, scope: &Scope) -> Result<( )> {
+    match expression {
+      Expression::Subquery(statement) => {
+        let plan= Planner { catalog: self.catalog, options: PlanOptions::default( ), outer: Some(scope) }.build((**statement).clone( ))?;
+
+        match plan {
+          Plan::Projection { expressions, .. } if expressions.len( ) != 1 =>
+            Err(Error::Value(ErrorCode::SyntaxError, format!("Subquery must return exactly one column, but returns {} : ({})", expressions.len( ), statement))),
+          _ => Ok(( ))
+        }
+      },
+
+      Expression::Operation(operation) => operation.operands( ).into_iter( ).try_for_each(|operand| self.checkSubqueries(operand, scope)),
+      Expression::FunctionCall(_, arguments) => arguments.iter( ).try_for_each(|argument| self.checkSubqueries(argument, scope)),
+      Expression::Distinct(expression) => self.checkSubqueries(expression, scope),
+
+      _ => Ok(( ))
+    }
+  }
+
+  // Returns an empty scope, which sees the outer scope (if any) when resolving correlated fields.
+  fn newScope(&self) -> Scope {
+    Scope { outer: self.outer.map(|outer| Box::new(outer.clone( ))), ..Scope::default( ) }
+  }
+
+  fn buildFilter(&self, source: Plan, predicate: Option<Expression>, scope: &Scope) -> Result<Plan> {
+    Ok(match predicate {
+      Some(predicate) => Plan::Filter { source: Box::new(source), predicate: scope.resolveExpression(predicate)? },
+      None => source
+    })
+  }
+}
+
+/*
+  Resolves the references to the selections of a SELECT statement, from its ORDER BY, GROUP BY and
+  HAVING clauses :
+
+    A bare name matching the alias of a selection refers to that selection. The alias shadows a
+    column of the same name, but it mustn't be given to multiple selections.
+
+    In ORDER BY and GROUP BY, an integer constant refers to the selection at that position (starting
+    from 1). Other constants aren't allowed there.
+
+  Any other expression is left as it is, to be resolved against the columns of the FROM clause.
+
+  The WHERE clause can't see the aliases, since it's evaluated before the selections. Unless that's
+  relaxed (see PlanOptions::lenientAliases), a reference to an alias from WHERE is reported as such.
+*/
+struct SelectionReferences<'a> {
+  selections: &'a [(Expression, Option<AliasColumnName>)],
+  aliases: &'a [Option<AliasColumnName>]
+}
+
+impl SelectionReferences<'_> {
+  // Resolves an ORDER BY / GROUP BY expression, if it refers to a selection.
+  fn resolve(&self, clause: &str, expression: Expression) -> Result<Expression> {
+    match expression {
+      Expression::Field(None, name) => Ok(self.findAlias(clause, &name)?.unwrap_or(Expression::Field(None, name))),
+
+      Expression::Literal(Literal::Integer(position)) => match usize::try_from(position) {
+        Ok(position) if (1..=self.selections.len( )).contains(&position) => Ok(self.selections[position - 1].0.clone( )),
+        _ => Err(Error::Value(ErrorCode::InvalidColumnReference, format!("{} position {} is out of range, there are {} selections", clause, position, self.selections.len( ))))
+      },
+      Expression::Literal(literal) =>
+        Err(Error::Value(ErrorCode::InvalidColumnReference, format!("{} expects an expression or the position of a selection, got {}", clause, literal))),
+
+      expression => Ok(expression)
+    }
+  }
+
+  // Substitutes the aliases of the selections referenced anywhere in the expression (outside
+  // subqueries).
+  fn resolveAliases(&self, clause: &str, expression: Expression) -> Result<Expression> {
+    expression.transform(&mut |expression| match expression {
+      Expression::Field(None, name) => Ok(self.findAlias(clause, &name)?.unwrap_or(Expression::Field(None, name))),
+      expression => Ok(expression)
+    })
+  }
+
+  /*
+    Resolves the references to the aliases of the selections, from the WHERE clause. Names which
+    resolve to columns (or to the outer query) are left as they are.
+
+    With lenient aliases, a reference is substituted by the aliased expression (unless it contains
+    an aggregate function, which can't be evaluated before grouping). Otherwise, it fails with an
+    error pointing at the alias.
+  */
+  fn resolveWhereAliases(&self, expression: Expression, scope: &Scope, lenient: bool) -> Result<Expression> {
+    expression.transform(&mut |expression| match expression {
+      Expression::Field(None, name) if !scope.isVisible(None, &name) && !scope.ambiguous.contains(&name) => match self.findAlias("WHERE", &name)? {
+        Some(selection) if lenient && containsAggregate(&selection) =>
+          Err(Error::Value(ErrorCode::GroupingError, format!("Alias {} refers to an aggregate, which can't be used in WHERE : use HAVING instead", name))),
+
+        Some(selection) if lenient => Ok(selection),
+
+        Some(_) => Err(Error::Value(ErrorCode::UndefinedColumn, format!(
+          "Unknown column {} : aliases of the selections can't be referenced in WHERE, repeat the expression or use a subquery",
+          name
+        ))),
+
+        None => Ok(Expression::Field(None, name))
+      },
+
+      expression => Ok(expression)
+    })
+  }
+
+  // Returns the expression of the selection with the given alias, if any.
+  fn findAlias(&self, clause: &str, name: &str) -> Result<Option<Expression>> {
+    let mut matching= self.aliases.iter( )
+                                  .zip(self.selections)
+                                  .filter(|(alias, _)| alias.as_deref( ) == Some(name))
+                                  .map(|(_, (expression, _))| expression);
+
+    match (matching.next( ), matching.next( )) {
+      (Some(_), Some(_)) => Err(Error::Value(ErrorCode::AmbiguousColumn, format!("{} reference {} is ambiguous, multiple selections are aliased so", clause, name))),
+      (expression, _) => Ok(expression.cloned( ))
+    }
+  }
+}
+
+// Returns whether the expression contains an aggregate function call.
+fn containsAggregate(expression: &Expression) -> bool {
+  expression.contains(&|expression| {
+    matches!(expression, Expression::FunctionCall(name, _) if Aggregate::fromName(name).is_some( ))
+  })
+}
+
+/*
+  Rejects the aggregate function calls in a clause which is evaluated before the aggregation (or
+  without one), upfront - rather than failing to evaluate them as scalar functions later.
+
+  NOTE : The aggregates in a subquery belong to the subquery, so they're allowed.
+*/
+fn checkNoAggregates(clause: &str, expression: &Expression) -> Result<( )> {
+  if !containsAggregate(expression) {
+    return Ok(( ))}
+
+  let hint= match clause {
+    "WHERE" => " (use HAVING to filter on aggregates)",
+    _ => ""
+  };
+  Err(Error::Value(ErrorCode::GroupingError, format!("Aggregate functions can't be used in {} : {}{}", clause, expression, hint)))
+}
+
+/*
+  Plans the aggregation of a SELECT statement.
+
+  The source rows are first projected into the GROUP BY expressions followed by the arguments of the
+  aggregate function calls. The Aggregation node then produces a row per group : the group key
+  followed by the aggregate values. Expressions evaluated after the aggregation (selections, HAVING
+  and ORDER BY) are rewritten to reference those columns.
+*/
+struct AggregationBuilder<'a> {
+  // Scope of the source rows.
+  scope: &'a Scope,
+
+  // The GROUP BY expressions, resolved against the source rows.
+  groups: Vec<Expression>,
+
+  // The aggregate function calls along with their arguments (resolved against the source rows), and
+  // whether they only aggregate the distinct values.
+  aggregates: Vec<(Aggregate, Expression, bool)>
+}
+
+impl<'a> AggregationBuilder<'a> {
+  fn new(scope: &'a Scope, groupBy: Vec<Expression>) -> Result<Self> {
+    let groups= groupBy.into_iter( )
+                       .map(|expression| {
+                         checkNoAggregates("GROUP BY", &expression)?;
+                         scope.resolveExpression(expression)
+                       })
+                       .collect::<Result<_>>( )?;
+
+    Ok(Self { scope, groups, aggregates: Vec::new( ) })
+  }
+
+  // Rewrites an expression to be evaluated against the aggregated rows.
+  fn build(&mut self, expression: Expression) -> Result<Expression> {
+    if let Expression::FunctionCall(name, arguments)= &expression {
+      if let Some(aggregate)= Aggregate::fromName(name) {
+        let [argument]= arguments.as_slice( ) else {
+          return Err(Error::Value(ErrorCode::UndefinedFunction, format!("Aggregate function {} takes exactly one argument", name)))
+        };
+
+        if containsAggregate(argument) {
+          return Err(Error::Value(ErrorCode::GroupingError, format!("Aggregate functions can't be nested : {}", expression)))}
+
+        let (argument, distinct)= match argument {
+          Expression::Distinct(argument) => (self.scope.resolveExpression(*argument.clone( ))?, true),
+          argument => (self.scope.resolveExpression(argument.clone( ))?, false)
+        };
+
+        let index= match self.aggregates.iter( ).position(|existing| existing == &(aggregate, argument.clone( ), distinct)) {
+          Some(index) => index,
+          None => {
+            self.aggregates.push((aggregate, argument, distinct));
+            self.aggregates.len( ) - 1
+          }
+        };
+        return Ok(Expression::Column(self.groups.len( ) + index))
+      }
+    }
+
+    // An expression matching a GROUP BY expression, evaluates to the group key.
+    if !containsAggregate(&expression) {
+      let resolved= self.scope.resolveExpression(expression.clone( ))?;
+
+      if let Some(index)= self.groups.iter( ).position(|group| group == &resolved) {
+        return Ok(Expression::Column(index))}
+    }
+
+    match expression {
+      Expression::Field(table, name) => Err(Error::Value(ErrorCode::GroupingError, format!(
+        "Column {} must appear in GROUP BY or be used in an aggregate function",
+        table.map(|table| format!("{}.{}", table, name)).unwrap_or(name)
+      ))),
+
+      Expression::Operation(operation) => Ok(Expression::Operation(operation.mapOperands(&mut |operand| self.build(operand))?)),
+
+      Expression::FunctionCall(name, arguments) => Ok(Expression::FunctionCall(
+        name,
+        arguments.into_iter( )
+                 .map(|argument| self.build(argument))
+                 .collect::<Result<_>>( )?
+      )),
+
+      expression => Ok(expression)
+    }
+  }
+
+  // Builds the aggregation on top of the source node.
+  fn plan(self, source: Plan) -> Plan {
+    let groupSize= self.groups.len( );
+
+    let (aggregates, arguments): (Vec<_>, Vec<_>)= self.aggregates.into_iter( )
+                                                                  .map(|(aggregate, argument, distinct)| ((aggregate, distinct), argument))
+                                                                  .unzip( );
+
+    let expressions= self.groups.into_iter( )
+                                .chain(arguments)
+                                .map(|expression| (expression, None))
+                                .collect( );
+
+    Plan::Aggregation {
+      source: Box::new(Plan::Projection { source: Box::new(source), expressions }),
+      groupSize,
+      aggregates
+    }
+  }
+}
+
+// Checks that the value of the expression can be assigned to a column of the given data type.
+fn checkAssignment(column: &str, dataType: DataType, expression: &Expression, valueType: ValueType) -> Result<( )> {
+  match valueType.isCompatibleWith(dataType) {
+    true => Ok(( )),
+    false => Err(Error::Value(ErrorCode::DatatypeMismatch, format!("Column {} is of type {}, but {} is of type {}", column, dataType, expression, valueType)))
+  }
+}
+
+// Fails, if the column of an INSERT / UPDATE is the _version system column.
+fn checkWritable(column: &str) -> Result<( )> {
+  match column == VERSION_COLUMN {
+    true => Err(Error::Value(ErrorCode::GeneratedAlways, format!("Column {} is a system column, and can't be written", column))),
+    false => Ok(( ))
+  }
+}
+
+// Evaluates a LIMIT / OFFSET expression, which must be a constant non-negative integer.
+fn evaluateCount(clause: &str, expression: Expression) -> Result<u64> {
+  match expression.evaluate(None)? {
+    Value::Integer(count) if count >= 0 => Ok(count as u64),
+    value => Err(Error::Value(ErrorCode::InvalidParameterValue, format!("{} must be a non-negative integer, got {}", clause, value)))
+  }
+}
+
+/*
+  The names visible to expressions at a point of the plan : the columns of the rows flowing through
+  that point, along with the tables they belong to.
+
+  Columns can be referenced by their qualified (table.column) name, or by their unqualified name -
+  when that's unambiguous.
+*/
+#[derive(Debug, Clone, Default)]
+struct Scope {
+  // Names (or aliases) of the tables in the scope.
+  tables: HashSet<String>,
+
+  // The (table, column) names of the columns along with their types and whether they're system
+  // columns (like _version), by their indexes in the row.
+  columns: Vec<(Option<String>, Option<String>, ValueType, bool)>,
+
+  qualified: HashMap<(String, String), usize>,
+  unqualified: HashMap<String, usize>,
+
+  // Unqualified names shared by multiple columns.
+  ambiguous: HashSet<String>,
+
+  // The columns emitted by SELECT *, if they differ from all the (non-system) columns in order : a
+  // USING join emits each of its join columns once, ahead of the other columns.
+  starColumns: Option<Vec<usize>>,
+
+  // Columns which can only be referenced by their qualified names : the join columns of a USING
+  // join dropped in favour of the other side's (see coalesce( )).
+  hiddenColumns: HashSet<usize>,
+
+  // The scope of the query a subquery is nested in. Its columns can't be referenced, since
+  // correlated subqueries aren't supported. It's only used to report such references.
+  outer: Option<Box<Scope>>
+}
+
+impl Scope {
+  // Adds the columns of a table, under the given name.
+  fn addTable(&mut self, name: &str, table: &Table) -> Result<( )> {
+    if !self.tables.insert(name.to_string( )) {
+      return Err(Error::Value(ErrorCode::DuplicateAlias, format!("Table {} is referenced multiple times", name)))}
+
+    for column in &table.columns {
+      self.addColumn(Some(name.to_string( )), Some(column.name.clone( )), ValueType::new(column.dataType, column.nullable), false);}
+
+    // The rows of a table source are suffixed with their versions.
+    self.addColumn(Some(name.to_string( )), Some(VERSION_COLUMN.to_string( )), ValueType::new(DataType::Integer, false), true);
+
+    Ok(( ))
+  }
+
+  // Adds the (non-system) columns of a table under the given name, which can only be referenced by
+  // their qualified names.
+  fn addQualifiedTable(&mut self, name: &str, table: &Table) -> Result<( )> {
+    if !self.tables.insert(name.to_string( )) {
+      return Err(Error::Value(ErrorCode::DuplicateAlias, format!("Table {} is referenced multiple times", name)))}
+
+    for column in &table.columns {
+      self.qualified.insert((name.to_string( ), column.name.clone( )), self.columns.len( ));
+      self.hiddenColumns.insert(self.columns.len( ));
+      self.columns.push((Some(name.to_string( )), Some(column.name.clone( )), ValueType::new(column.dataType, column.nullable), false));
+    }
+
+    Ok(( ))
+  }
+
+  fn addColumn(&mut self, table: Option<String>, name: Option<String>, valueType: ValueType, isSystem: bool) {
+    let index= self.columns.len( );
+
+    if let Some(name)= &name {
+      if let Some(table)= &table {
+        self.qualified.insert((table.clone( ), name.clone( )), index);}
+
+      if !self.ambiguous.contains(name) && self.unqualified.insert(name.clone( ), index).is_some( ) {
+        self.unqualified.remove(name);
+        self.ambiguous.insert(name.clone( ));
+      }
+    }
+
+    self.columns.push((table, name, valueType, isSystem));
+  }
+
+  // Appends the columns of another scope.
+  fn merge(&mut self, scope: Scope) -> Result<( )> {
+    for table in &scope.tables {
+      if !self.tables.insert(table.clone( )) {
+        return Err(Error::Value(ErrorCode::DuplicateAlias, format!("Table {} is referenced multiple times", table)))}
+    }
+
+    let offset= self.columns.len( );
+    if self.starColumns.is_some( ) || scope.starColumns.is_some( ) {
+      self.starColumns= Some(self.starColumns( ).into_iter( ).chain(scope.starColumns( ).into_iter( ).map(|index| offset + index)).collect( ));}
+
+    for (index, (table, name, valueType, isSystem)) in scope.columns.into_iter( ).enumerate( ) {
+      match scope.hiddenColumns.contains(&index) {
+        true => {
+          if let (Some(table), Some(name))= (&table, &name) {
+            self.qualified.insert((table.clone( ), name.clone( )), offset + index);}
+
+          self.columns.push((table, name, valueType, isSystem));
+          self.hiddenColumns.insert(offset + index);
+        },
+
+        false => self.addColumn(table, name, valueType, isSystem)
+      }
+    }
+
+    Ok(( ))
+  }
+
+  /*
+    Merges the join columns of a USING join, given as the (kept, dropped) pairs of their indexes. The
+    unqualified name of a join column references the kept column, and SELECT * emits just the kept
+    column (ahead of the other columns). The dropped column is still referenced by its qualified
+    name.
+  */
+  fn coalesce(&mut self, columns: &[(usize, usize)]) {
+    for &(kept, dropped) in columns {
+      let name= self.columns[kept].1.clone( ).unwrap_or_default( );
+
+      self.ambiguous.remove(&name);
+      self.unqualified.insert(name, kept);
+      self.hiddenColumns.insert(dropped);
+    }
+
+    if columns.is_empty( ) {
+      return}
+
+    let others= self.starColumns( ).into_iter( )
+                    .filter(|index| !columns.iter( ).any(|(kept, dropped)| index == kept || index == dropped))
+                    .collect::<Vec<_>>( );
+    self.starColumns= Some(columns.iter( ).map(|(kept, _)| *kept).chain(others).collect( ));
+  }
+
+  // Returns the indexes of the columns emitted by SELECT *.
+  fn starColumns(&self) -> Vec<usize> {
+    match &self.starColumns {
+      Some(columns) => columns.clone( ),
+      None => (0..self.columns.len( )).filter(|index| !self.columns[*index].3).collect( )
+    }
+  }
+
+  // Resolves a column of a USING join, on the given side of the join.
+  fn resolveJoinColumn(&self, name: &str, (side, sql): &(&str, String)) -> Result<usize> {
+    match self.resolveLocal(None, name) {
+      Err(Error::Value(ErrorCode::UndefinedColumn, _)) =>
+        Err(Error::Value(ErrorCode::UndefinedColumn, format!("Column {} of USING doesn't exist on the {} side of the join ({})", name, side, sql))),
+      resolved => resolved
+    }
+  }
+
+  // Returns the names of the columns shared by this scope and the other one (in the order of this
+  // scope), which a NATURAL join joins on. Fails if there are none.
+  fn commonColumns(&self, other: &Scope, sides: &[(&str, String); 2]) -> Result<Vec<String>> {
+    let mut names= Vec::new( );
+    for name in self.starColumns( ).into_iter( ).filter_map(|index| self.columns[index].1.as_ref( )) {
+      if !names.contains(name) && (other.unqualified.contains_key(name) || other.ambiguous.contains(name)) {
+        names.push(name.clone( ));}
+    }
+
+    let [(_, left), (_, right)]= match sides[0].0 {
+      "left" => [&sides[0], &sides[1]],
+      _ => [&sides[1], &sides[0]]
+    };
+
+    match names.is_empty( ) {
+      true => Err(Error::Value(ErrorCode::UndefinedColumn, format!("NATURAL JOIN of {} and {} has no columns in common", left, right))),
+      false => Ok(names)
+    }
+  }
+
+  // Returns the fields of the (non-system) columns of the given table, or of all the tables (see
+  // starColumns( )), along with their names.
+  fn wildcardColumns(&self, table: Option<&str>) -> Result<Vec<(Expression, Option<String>)>> {
+    let field= |(table, name, ..): &(Option<String>, Option<String>, ValueType, bool)| (Expression::Field(table.clone( ), name.clone( ).unwrap_or_default( )), name.clone( ));
+
+    let Some(table)= table else {
+      return Ok(self.starColumns( ).into_iter( ).map(|index| field(&self.columns[index])).collect( ))
+    };
+
+    if !self.tables.contains(table) {
+      return Err(Error::Value(ErrorCode::UndefinedTable, format!("Unknown table {} referenced by {}.*", table, table)))}
+
+    Ok(self.columns.iter( )
+                   .filter(|(columnTable, _, _, isSystem)| !isSystem && columnTable.as_deref( ) == Some(table))
+                   .map(field)
+                   .collect( ))
+  }
+
+  // Marks all the columns as nullable.
+  fn makeNullable(&mut self) {
+    for (_, _, valueType, _) in &mut self.columns {
+      valueType.nullable= true;}
+  }
+
+  // Resolves a field to the index of its column.
+  fn resolve(&self, table: Option<&str>, name: &str) -> Result<usize> {
+    let resolved= self.resolveLocal(table, name);
+
+    match &self.outer {
+      Some(outer) if resolved.is_err( ) && !self.ambiguous.contains(name) && outer.isVisible(table, name) =>
+        Err(Error::Value(ErrorCode::FeatureNotSupported, format!(
+          "Correlated subqueries are not supported : column {} references the outer query",
+          table.map(|table| format!("{}.{}", table, name)).unwrap_or(name.to_string( ))
+        ))),
+
+      _ => resolved
+    }
+  }
+
+  // Returns whether the field resolves in the scope, or in any of the outer scopes.
+  fn isVisible(&self, table: Option<&str>, name: &str) -> bool {
+    self.resolveLocal(table, name).is_ok( ) || self.outer.as_ref( ).is_some_and(|outer| outer.isVisible(table, name))
+  }
+
+  fn resolveLocal(&self, table: Option<&str>, name: &str) -> Result<usize> {
+    match table {
+      Some(table) => {
+        if !self.tables.contains(table) {
+          return Err(Error::Value(ErrorCode::UndefinedTable, format!("Unknown table {} referenced by column {}.{}", table, table, name)))}
+
+        self.qualified.get(&(table.to_string( ), name.to_string( )))
+                      .copied( )
+                      .ok_or_else(| | Error::Value(ErrorCode::UndefinedColumn, format!("Unknown column {}.{}", table, name)))
+      },
+
+      None => {
+        if self.ambiguous.contains(name) {
+          return Err(Error::Value(ErrorCode::AmbiguousColumn, format!("Ambiguous column {}", name)))}
+
+        self.unqualified.get(name)
+                        .copied( )
+                        .ok_or_else(| | Error::Value(ErrorCode::UndefinedColumn, format!("Unknown column {}", name)))
+      }
+    }
+  }
+
+  // Infers the type of the expression, reporting any operands of incompatible types.
+  fn checkType(&self, expression: &Expression) -> Result<ValueType> {
+    expression.inferType(&|expression| match expression {
+      Expression::Field(table, name) => Ok(self.columns[self.resolve(table.as_deref( ), name)?].2),
+      Expression::Column(index) => Ok(self.columns[*index].2),
+      _ => Ok(ValueType::unknown( ))
+    })
+  }
+
+  // Checks that the predicate of a clause (like WHERE) is a boolean expression.
+  fn checkPredicate(&self, clause: &str, expression: &Expression) -> Result<( )> {
+    let valueType= self.checkType(expression)?;
+
+    match valueType.isCompatibleWith(DataType::Boolean) {
+      true => Ok(( )),
+      false => Err(Error::Value(ErrorCode::DatatypeMismatch, format!("{} must be a BOOLEAN, but {} is of type {}", clause, expression, valueType)))
+    }
+  }
+
+  // Replaces the fields in the expression, by the indexes of their columns. Calls to scalar functions
+  // are validated too.
+  fn resolveExpression(&self, expression: Expression) -> Result<Expression> {
+    expression.transform(&mut |expression| match expression {
+      Expression::Field(table, name) => Ok(Expression::Column(self.resolve(table.as_deref( ), &name)?)),
+
+      Expression::FunctionCall(name, arguments) if Aggregate::fromName(&name).is_none( ) => {
+        getFunction(&name).ok_or_else(| | Error::Value(ErrorCode::UndefinedFunction, format!("Unknown function {}", name)))?
+                          .checkArity(arguments.len( ))?;
+        Ok(Expression::FunctionCall(name, arguments))
+      },
+
+      expression => Ok(expression)
+    })
+  }
+}
@@ -0,0 +1,651 @@
+use std::{collections::HashSet, fmt::Display};
+use serde::{Deserialize, Serialize};
+use crate::result::{Error, ErrorCode, Result};
+use super::{
+  parser::{ast::{self, Identifier}, Parser},
+  plan::Aggregate,
+  stats::TableStats,
+  types::{DataType, Row, Value, ValueType}
+};
+
+// Stores the table schemas.
+pub trait Catalog {
+  // Creates a new table. Fails, if a table with the same name already exists or the schema is
+  // invalid.
+  fn createTable(&mut self, table: Table) -> Result<( )>;
+
+  // Drops a table. Fails, if the table doesn't exist or is referenced by another table.
+  fn dropTable(&mut self, table: &str) -> Result<( )>;
+
+  // Creates an expression index on a table, indexing its existing rows. Fails, if the table already
+  // has a column or an index with the same name.
+  fn createIndex(&mut self, table: &str, index: Index) -> Result<( )>;
+
+  // Renames a table. The columns referencing it (in other tables, or in itself) are changed to
+  // reference the new name. Fails, if a table with the new name already exists.
+  fn renameTable(&mut self, table: &str, newName: &str) -> Result<( )>;
+
+  // Renames a column of a table. The expression indexes referencing it are changed to reference the
+  // new name. Fails, if the table already has a column or an index with the new name.
+  fn renameColumn(&mut self, table: &str, column: &str, newName: &str) -> Result<( )>;
+
+  fn getTable(&self, table: &str) -> Result<Option<Table>>;
+
+  // Returns all the tables, ordered by their names.
+  fn listTables(&self) -> Result<Vec<Table>>;
+
+  /*
+    Collects the statistics of the table from its rows (see TableStats), replacing the ones collected
+    before. Since the plans depend on them, it bumps the schema version (so that the plans built
+    before get replanned).
+  */
+  fn analyzeTable(&mut self, table: &str) -> Result<TableStats>;
+
+  // Returns the statistics of the table. There are none, if the table hasn't been analyzed (or if
+  // it has changed a lot since, see StatsCache).
+  fn getTableStats(&self, table: &str) -> Result<Option<TableStats>>;
+
+  /*
+    Returns the version of the schema, which every CREATE / DROP / ALTER TABLE (and CREATE INDEX)
+    bumps.
+
+    A read-write transaction fails with Error::Serialization, if a concurrent transaction has changed
+    the schema : since the schema it sees is then stale, its writes could go to a dropped table.
+  */
+  fn schemaVersion(&self) -> Result<SchemaVersion>;
+
+  // Returns the table, failing if it doesn't exist.
+  fn mustGetTable(&self, table: &str) -> Result<Table> {
+    self.getTable(table)?
+        .ok_or_else(| | Error::Value(ErrorCode::UndefinedTable, format!("Table {} doesn't exist", table)))
+  }
+
+  // Returns the (table name, column names) pairs of the tables referencing the given table,
+  // excluding the table itself.
+  fn getReferencesTo(&self, table: &str) -> Result<Vec<(String, Vec<String>)>> {
+    Ok(self.listTables( )?
+           .into_iter( )
+           .filter(|referencingTable| referencingTable.name != table)
+           .map(|referencingTable| {
+             let columns= referencingTable.columns.iter( )
+                                                  .filter(|column| column.references.as_deref( ) == Some(table))
+                                                  .map(|column| column.name.clone( ))
+                                                  .collect::<Vec<_>>( );
+             (referencingTable.name, columns)
+           })
+           .filter(|(_, columns)| !columns.is_empty( ))
+           .collect( ))
+  }
+}
+
+/*
+  Name of the system column, which every table has (after its own columns) : the version at which a
+  row was last written. It can be queried like any other column, though SELECT * doesn't include
+  it, and it can't be written.
+*/
+pub const VERSION_COLUMN: &str= "_version";
+
+pub type SchemaVersion= u64;
+
+/*
+  Ids of the tables and their columns, which the rows and the index entries are stored by (see
+  engine::kv). Unlike the names, they never change, so renaming a table or a column doesn't move
+  any data.
+*/
+pub type TableId= u64;
+pub type ColumnId= u64;
+
+// Represents a table schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+  // Assigned by the catalog, when the table is created.
+  pub id: TableId,
+
+  pub name: String,
+
+  // Index of the primary key column.
+  pub primaryKey: usize,
+
+  pub columns: Vec<Column>,
+
+  // Expression indexes on the table (see Catalog::createIndex).
+  pub indexes: Vec<Index>,
+
+  // Version of the schema, when the table was created. Assigned by the catalog.
+  pub schemaVersion: SchemaVersion
+}
+
+// Represents a column schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+  // The position of the column, when the table was created.
+  pub id: ColumnId,
+
+  pub name: String,
+  pub dataType: DataType,
+
+  pub nullable: bool,
+  pub default: Option<ColumnDefault>,
+
+  pub unique: bool,
+  pub index: bool,
+
+  // Name of the table, whose primary key this column references.
+  pub references: Option<String>,
+
+  // What happens to the rows referencing a row (using this column), when it's deleted.
+  pub onDelete: ReferentialAction
+}
+
+/*
+  Represents an index on the values of an expression computed from each row (like LOWER(email)).
+  Its entries are keyed by the index name, the way the ones of an indexed column are keyed by the
+  column's id (see IndexId).
+
+  The expression must be deterministic, so that a row's entry is computed the same way every time
+  the row is written.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+  pub name: String,
+
+  // The expression, stored as SQL (referencing the columns by their unqualified names).
+  pub expression: String
+}
+
+// Identifies the entries of an index of a table : the ones of an indexed (or unique) column by the
+// column's id, and the ones of an expression index by the index name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IndexId {
+  Column(ColumnId),
+  Expression(String)
+}
+
+// The default value of a column, taken by the inserted rows which don't give a value for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ColumnDefault {
+  // A constant, evaluated once when the table is created.
+  Value(Value),
+
+  // A non-constant expression (calling a volatile function, like RANDOM), stored as SQL. It's
+  // evaluated for every inserted row.
+  Expression(String)
+}
+
+// The action taken on the rows referencing a deleted row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ReferentialAction {
+  // Fails the deletion.
+  #[default]
+  Restrict,
+
+  // Deletes the referencing rows as well (which may cascade further).
+  Cascade,
+
+  // Sets the referencing column to NULL.
+  SetNull
+}
+
+impl Table {
+  // Builds a table schema from the column specifications of a CREATE TABLE statement.
+  pub fn new(name: String, columnSpecs: Vec<ast::Column>) -> Result<Self> {
+    let primaryKeys= columnSpecs.iter( )
+                                .enumerate( )
+                                .filter(|(_, columnSpec)| columnSpec.primaryKey)
+                                .map(|(index, _)| index)
+                                .collect::<Vec<_>>( );
+
+    let primaryKey= match primaryKeys[..] {
+      [primaryKey] => primaryKey,
+      [ ] => return Err(Error::Value(ErrorCode::InvalidTableDefinition, format!("Table {} has no primary key", name))),
+      _ => return Err(Error::Value(ErrorCode::InvalidTableDefinition, format!("Table {} has multiple primary keys", name)))
+    };
+
+    let columns= columnSpecs.into_iter( )
+                            .enumerate( )
+                            .map(|(id, columnSpec)| Column::new(id as ColumnId, columnSpec))
+                            .collect::<Result<Vec<_>>>( )?;
+
+    Ok(Self { id: 0, name, primaryKey, columns, indexes: vec![ ], schemaVersion: 0 })
+  }
+
+  // Returns the index of the column with the given name.
+  pub fn getColumnIndex(&self, name: &str) -> Result<usize> {
+    self.columns.iter( )
+                .position(|column| column.name == name)
+                .ok_or_else(| | Error::Value(ErrorCode::UndefinedColumn, format!("Column {} doesn't exist in table {}", name, self.name)))
+  }
+
+  pub fn getColumn(&self, name: &str) -> Result<&Column> {
+    Ok(&self.columns[self.getColumnIndex(name)?])
+  }
+
+  pub fn getPrimaryKeyColumn(&self) -> &Column {
+    &self.columns[self.primaryKey]
+  }
+
+  // Returns the id of the index with the given name : the one of the column with that name, or else
+  // the one of the expression index.
+  pub fn getIndexId(&self, name: &str) -> Result<IndexId> {
+    if let Some(column)= self.columns.iter( ).find(|column| column.name == name) {
+      return Ok(IndexId::Column(column.id))}
+
+    match self.indexes.iter( ).any(|index| index.name == name) {
+      true => Ok(IndexId::Expression(name.to_string( ))),
+      false => Err(Error::Value(ErrorCode::UndefinedObject, format!("Index {} doesn't exist on table {}", name, self.name)))
+    }
+  }
+
+  // Returns whether the table has a column or an index with the given name (they share a namespace).
+  pub fn hasColumnOrIndex(&self, name: &str) -> bool {
+    self.columns.iter( ).map(|column| &column.name).chain(self.indexes.iter( ).map(|index| &index.name)).any(|existing| existing == name)
+  }
+
+  // Returns the (index id, value) pairs of the row's index entries : the values of the indexed (or
+  // unique) columns, followed by the values of the expression indexes.
+  pub fn getIndexEntries(&self, row: &Row) -> Result<Vec<(IndexId, Value)>> {
+    let mut entries= self.columns.iter( )
+                                 .zip(row)
+                                 .filter(|(column, _)| column.index || column.unique)
+                                 .map(|(column, value)| (IndexId::Column(column.id), value.clone( )))
+                                 .collect::<Vec<_>>( );
+
+    for index in &self.indexes {
+      entries.push((IndexId::Expression(index.name.clone( )), index.resolveExpression(self)?.evaluate(Some(row))?));}
+
+    Ok(entries)
+  }
+
+  // Returns whether the index is the one of a unique column (expression indexes aren't unique).
+  pub fn isUniqueIndex(&self, index: &IndexId) -> bool {
+    match index {
+      IndexId::Column(id) => self.columns.iter( ).any(|column| column.id == *id && column.unique),
+      IndexId::Expression(_) => false
+    }
+  }
+
+  // Validates the table schema. Tables referenced by the columns are looked up in the catalog.
+  pub fn validate(&self, catalog: &dyn Catalog) -> Result<( )> {
+    if self.name.is_empty( ) {
+      return Err(Error::Value(ErrorCode::InvalidTableDefinition, "Table name can't be empty".to_string( )))}
+
+    if self.columns.is_empty( ) {
+      return Err(Error::Value(ErrorCode::InvalidTableDefinition, format!("Table {} has no columns", self.name)))}
+
+    let primaryKey= self.columns.get(self.primaryKey)
+                                .ok_or_else(| | Error::Value(ErrorCode::InvalidTableDefinition, format!("Table {} has an invalid primary key", self.name)))?;
+    if primaryKey.nullable {
+      return Err(Error::Value(ErrorCode::InvalidTableDefinition, format!("Primary key {} of table {} can't be nullable", primaryKey.name, self.name)))}
+
+    let mut columnNames= HashSet::new( );
+    for column in &self.columns {
+      if !columnNames.insert(&column.name) {
+        return Err(Error::Value(ErrorCode::DuplicateColumn, format!("Table {} has multiple columns named {}", self.name, column.name)))}
+
+      if column.name == VERSION_COLUMN {
+        return Err(Error::Value(ErrorCode::ReservedName, format!("Column name {} is reserved for the system column", VERSION_COLUMN)))}
+
+      column.validate(self, catalog)?;
+    }
+
+    Ok(( ))
+  }
+}
+
+impl Index {
+  /*
+    Builds an index of the table on the given expression. It can't contain parameters, subqueries,
+    aggregate or volatile function calls, and its fields must be columns of the table.
+  */
+  pub fn new(table: &Table, name: String, expression: ast::Expression) -> Result<Self> {
+    let isInvalid= |expression: &ast::Expression| match expression {
+      ast::Expression::Subquery(_) | ast::Expression::Parameter(_) | ast::Expression::Distinct(_) => true,
+      ast::Expression::FunctionCall(name, _) => Aggregate::fromName(name).is_some( ),
+      _ => false
+    };
+    if expression.contains(&isInvalid) || expression.isVolatile( ) {
+      return Err(Error::Value(ErrorCode::InvalidObjectDefinition, format!("Expression {} of index {} must be deterministic", expression, name)))}
+
+    let expression= expression.transform(&mut |expression| match expression {
+      ast::Expression::Field(Some(qualifier), column) if qualifier != table.name =>
+        Err(Error::Value(ErrorCode::InvalidObjectDefinition, format!("Index {} of table {} can't reference field {}.{}", name, table.name, qualifier, column))),
+
+      ast::Expression::Field(_, column) => {
+        table.getColumnIndex(&column)?;
+        Ok(ast::Expression::Field(None, column))
+      },
+
+      expression => Ok(expression)
+    })?;
+
+    let index= Self { name, expression: expression.to_string( ) };
+    index.valueType(table)?;
+
+    Ok(index)
+  }
+
+  // Returns the expression, with its fields resolved to the columns of the table's rows.
+  pub fn resolveExpression(&self, table: &Table) -> Result<ast::Expression> {
+    Parser::new(&self.expression).parseStandaloneExpression( )?
+                                 .transform(&mut |expression| match expression {
+                                   ast::Expression::Field(_, column) => Ok(ast::Expression::Column(table.getColumnIndex(&column)?)),
+                                   expression => Ok(expression)
+                                 })
+  }
+
+  // Changes the expression to reference the renamed column by its new name.
+  pub fn renameColumn(&mut self, column: &str, newName: &str) -> Result<( )> {
+    let expression= Parser::new(&self.expression).parseStandaloneExpression( )?
+                                                 .transform(&mut |expression| match expression {
+                                                   ast::Expression::Field(None, name) if name == column => Ok(ast::Expression::Field(None, newName.to_string( ))),
+                                                   expression => Ok(expression)
+                                                 })?;
+    self.expression= expression.to_string( );
+
+    Ok(( ))
+  }
+
+  // Returns the type of the indexed values.
+  pub fn valueType(&self, table: &Table) -> Result<ValueType> {
+    let expression= Parser::new(&self.expression).parseStandaloneExpression( )?;
+    expression.inferType(&|field| match field {
+      ast::Expression::Field(_, column) => {
+        let column= table.getColumn(column)?;
+        Ok(ValueType::new(column.dataType, column.nullable))
+      },
+      field => Err(Error::Internal(format!("Unexpected field {} in index {}", field, self.name)))
+    })
+  }
+}
+
+impl Column {
+  fn new(id: ColumnId, columnSpec: ast::Column) -> Result<Self> {
+    let dataType= match columnSpec.dataType {
+      ast::DataType::Boolean => DataType::Boolean,
+      ast::DataType::Integer => DataType::Integer,
+      ast::DataType::Float => DataType::Float,
+      ast::DataType::String => DataType::String,
+      ast::DataType::Date => DataType::Date,
+      ast::DataType::Timestamp => DataType::Timestamp,
+
+      ast::DataType::Phantom => return Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!("Column {} has no data type", columnSpec.name)))
+    };
+
+    // Columns are nullable by default, except the primary key.
+    let nullable= columnSpec.nullable.unwrap_or(!columnSpec.primaryKey);
+
+    let default= match &columnSpec.default {
+      Some(expression) => Some(Self::buildDefault(&columnSpec.name, dataType, expression)?),
+
+      // A nullable column defaults to NULL.
+      None if nullable => Some(ColumnDefault::Value(Value::Null)),
+      None => None
+    };
+
+    Ok(Self {
+      id,
+      name: columnSpec.name,
+      dataType,
+
+      nullable,
+      default,
+
+      // The primary key is unique and indexed anyway.
+      unique: columnSpec.unique && !columnSpec.primaryKey,
+      index: columnSpec.index && !columnSpec.primaryKey,
+
+      references: columnSpec.references,
+      onDelete: columnSpec.onDelete
+    })
+  }
+
+  /*
+    Checks the default expression of a column : it must be evaluable without a row (so it can't
+    reference any columns), and of the column's data type.
+
+    A constant expression is evaluated right away, while a volatile one is kept to be evaluated for
+    every inserted row.
+  */
+  fn buildDefault(column: &str, dataType: DataType, expression: &ast::Expression) -> Result<ColumnDefault> {
+    let isInvalid= |expression: &ast::Expression| match expression {
+      ast::Expression::Subquery(_) | ast::Expression::Parameter(_) => true,
+      ast::Expression::FunctionCall(name, _) => Aggregate::fromName(name).is_some( ),
+      _ => false
+    };
+    if expression.contains(&isInvalid) {
+      return Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!("Default value {} of column {} must be a scalar expression", expression, column)))}
+
+    let valueType= expression.inferType(&|field| Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!(
+      "Default value of column {} can't reference column {}", column, field
+    ))))?;
+    if !valueType.isCompatibleWith(dataType) {
+      return Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!(
+        "Default value {} of column {} is of type {}, but the column is of type {}", expression, column, valueType, dataType
+      )))}
+
+    Ok(match expression.isVolatile( ) {
+      true => ColumnDefault::Expression(expression.to_string( )),
+      false => ColumnDefault::Value(expression.evaluate(None)?)
+    })
+  }
+
+  fn validate(&self, table: &Table, catalog: &dyn Catalog) -> Result<( )> {
+    let isPrimaryKey= table.getPrimaryKeyColumn( ).name == self.name;
+
+    if isPrimaryKey && (self.unique || self.index) {
+      return Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!("Primary key {} can't be marked unique or indexed", self.name)))}
+
+    match &self.default {
+      Some(ColumnDefault::Value(Value::Null)) if !self.nullable =>
+        return Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!("Column {} can't default to NULL, since it's not nullable", self.name))),
+
+      Some(ColumnDefault::Value(default)) if !default.isNull( ) && default.dataType( ) != Some(self.dataType) =>
+        return Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!(
+          "Default value {} of column {} doesn't match its data type {}", default, self.name, self.dataType
+        ))),
+
+      _ => { }
+    }
+
+    match (&self.references, self.onDelete) {
+      (None, ReferentialAction::Restrict) => { },
+      (None, onDelete) =>
+        return Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!("Column {} can't have an ON DELETE {} action, since it doesn't reference a table", self.name, onDelete))),
+
+      (Some(_), ReferentialAction::SetNull) if !self.nullable =>
+        return Err(Error::Value(ErrorCode::InvalidColumnDefinition, format!("Column {} can't be SET NULL on delete, since it's not nullable", self.name))),
+
+      _ => { }
+    }
+
+    if let Some(referencedTableName)= &self.references {
+      // A table can reference itself.
+      let referencedTable= match referencedTableName == &table.name {
+        true => table.clone( ),
+
+        false => catalog.getTable(referencedTableName)?
+                        .ok_or_else(| | Error::Value(ErrorCode::UndefinedTable, format!(
+                          "Column {} references table {}, which doesn't exist", self.name, referencedTableName
+                        )))?
+      };
+
+      let referencedPrimaryKey= referencedTable.getPrimaryKeyColumn( );
+      if referencedPrimaryKey.dataType != self.dataType {
+        return Err(Error::Value(ErrorCode::InvalidForeignKey, format!(
+          "Column {} of type {} can't reference primary key {}.{} of type {}",
+          self.name, self.dataType, referencedTable.name, referencedPrimaryKey.name, referencedPrimaryKey.dataType
+        )))}
+    }
+
+    Ok(( ))
+  }
+
+  // Renders the default value (if any) as a SQL literal, or as the SQL of a non-constant default.
+  pub fn renderDefault(&self) -> Option<String> {
+    self.default.as_ref( ).map(|default| match default {
+      ColumnDefault::Value(Value::String(default)) => format!("'{}'", default.replace('\'', "''")),
+      ColumnDefault::Value(default @ (Value::Date(_) | Value::Timestamp(_))) => ast::Literal::from(default.clone( )).to_string( ),
+      ColumnDefault::Value(default) => default.to_string( ),
+      ColumnDefault::Expression(expression) => expression.clone( )
+    })
+  }
+
+  // Returns the default of the column (if any) as an expression, to be evaluated for every inserted
+  // row which doesn't give a value for the column.
+  pub fn defaultExpression(&self) -> Result<Option<ast::Expression>> {
+    self.default.as_ref( )
+                .map(|default| match default {
+                  ColumnDefault::Value(value) => Ok(ast::Expression::Literal(value.clone( ).into( ))),
+                  ColumnDefault::Expression(expression) => Parser::new(expression).parseStandaloneExpression( )
+                })
+                .transpose( )
+  }
+
+  // Validates a value to be stored in the column.
+  pub fn validateValue(&self, value: &Value) -> Result<( )> {
+    match value.dataType( ) {
+      None if self.nullable => Ok(( )),
+      None => Err(Error::Value(ErrorCode::NotNullViolation, format!("Column {} can't be NULL", self.name))),
+
+      Some(dataType) if dataType == self.dataType => Ok(( )),
+      Some(dataType) => Err(Error::Value(ErrorCode::DatatypeMismatch, format!(
+        "Column {} is of type {}, but got {} of type {}", self.name, self.dataType, value, dataType
+      )))
+    }
+  }
+}
+
+impl Display for Table {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "CREATE TABLE {} (", Identifier(&self.name))?;
+
+    for (index, column) in self.columns.iter( ).enumerate( ) {
+      write!(f, "  {} {}", Identifier(&column.name), column.dataType)?;
+
+      if index == self.primaryKey {
+        write!(f, " PRIMARY KEY")?;}
+      else if !column.nullable {
+        write!(f, " NOT NULL")?;}
+
+      if let Some(default)= column.renderDefault( ).filter(|_| column.default != Some(ColumnDefault::Value(Value::Null))) {
+        write!(f, " DEFAULT {}", default)?;}
+
+      if column.unique {
+        write!(f, " UNIQUE")?;}
+
+      if column.index {
+        write!(f, " INDEX")?;}
+
+      if let Some(references)= &column.references {
+        write!(f, " REFERENCES {}", Identifier(references))?;}
+
+      if column.onDelete != ReferentialAction::Restrict {
+        write!(f, " ON DELETE {}", column.onDelete)?;}
+
+      if index + 1 < self.columns.len( ) {
+        write!(f, ",")?;}
+      writeln!(f)?;
+    }
+
+    write!(f, ")")
+  }
+}
+
+impl Display for ReferentialAction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Self::Restrict => "RESTRICT",
+      Self::Cascade => "CASCADE",
+      Self::SetNull => "SET NULL"
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{sql::engine::{kv::KV, Engine}, storage::{engine::memory::Memory, mvcc::WriteLimits, sync::SyncPolicy}};
+  use super::*;
+
+  // Builds the schema of the table created by the given CREATE TABLE statement.
+  fn parseTable(sql: &str) -> Result<Table> {
+    match Parser::new(sql).parse( )? {
+      ast::Statement::CreateTable { name, columns, .. } => Table::new(name, columns),
+      statement => panic!("Expected a CREATE TABLE statement, got {:?}", statement)
+    }
+  }
+
+  // Returns a catalog having a table of users, with an integer primary key.
+  fn newCatalog( ) -> Result<impl Catalog> {
+    let mut catalog= KV::new(Memory::new( ), WriteLimits::default( ), SyncPolicy::default( )).begin( )?;
+    catalog.createTable(parseTable("CREATE TABLE users (id INT PRIMARY KEY, name STRING)")?)?;
+    Ok(catalog)
+  }
+
+  fn assertInvalid<T: std::fmt::Debug>(result: Result<T>, code: ErrorCode, message: &str) {
+    assert_eq!(result.unwrap_err( ), Error::Value(code, message.to_string( )));
+  }
+
+  #[test]
+  fn rejectsMissingOrMultiplePrimaryKeys( ) {
+    assertInvalid(parseTable("CREATE TABLE t (id INT, name STRING)"), ErrorCode::InvalidTableDefinition, "Table t has no primary key");
+    assertInvalid(parseTable("CREATE TABLE t (id INT PRIMARY KEY, name STRING PRIMARY KEY)"),
+                  ErrorCode::InvalidTableDefinition, "Table t has multiple primary keys");
+  }
+
+  #[test]
+  fn rejectsNullablePrimaryKey( ) -> Result<( )> {
+    let catalog= newCatalog( )?;
+
+    let table= parseTable("CREATE TABLE t (id INT PRIMARY KEY NULL, name STRING)")?;
+    assertInvalid(table.validate(&catalog), ErrorCode::InvalidTableDefinition, "Primary key id of table t can't be nullable");
+    Ok(( ))
+  }
+
+  #[test]
+  fn rejectsDefaultOfWrongType( ) -> Result<( )> {
+    let catalog= newCatalog( )?;
+
+    // The default is type checked when the table is built from the statement, and again when the
+    // schema is validated.
+    assertInvalid(parseTable("CREATE TABLE t (id INT PRIMARY KEY, count INT DEFAULT 'none')"),
+                  ErrorCode::InvalidColumnDefinition, "Default value 'none' of column count is of type STRING, but the column is of type INTEGER");
+
+    let mut table= parseTable("CREATE TABLE t (id INT PRIMARY KEY, count INT)")?;
+    table.columns[1].default= Some(ColumnDefault::Value(Value::String("none".to_string( ))));
+    assertInvalid(table.validate(&catalog), ErrorCode::InvalidColumnDefinition, "Default value none of column count doesn't match its data type INTEGER");
+    Ok(( ))
+  }
+
+  #[test]
+  fn rejectsReferenceToMissingTable( ) -> Result<( )> {
+    let catalog= newCatalog( )?;
+
+    let table= parseTable("CREATE TABLE posts (id INT PRIMARY KEY, author INT REFERENCES authors)")?;
+    assertInvalid(table.validate(&catalog), ErrorCode::UndefinedTable, "Column author references table authors, which doesn't exist");
+    Ok(( ))
+  }
+
+  #[test]
+  fn rejectsReferenceToPrimaryKeyOfOtherType( ) -> Result<( )> {
+    let catalog= newCatalog( )?;
+
+    let table= parseTable("CREATE TABLE posts (id INT PRIMARY KEY, author STRING REFERENCES users)")?;
+    assertInvalid(table.validate(&catalog), ErrorCode::InvalidForeignKey, "Column author of type STRING can't reference primary key users.id of type INTEGER");
+
+    parseTable("CREATE TABLE posts (id INT PRIMARY KEY, author INT REFERENCES users)")?.validate(&catalog)
+  }
+
+  #[test]
+  fn rejectsDroppingReferencedTable( ) -> Result<( )> {
+    let mut catalog= newCatalog( )?;
+    catalog.createTable(parseTable("CREATE TABLE posts (id INT PRIMARY KEY, author INT REFERENCES users)")?)?;
+
+    assertInvalid(catalog.dropTable("users"), ErrorCode::DependentObjectsStillExist, "Table users is referenced by column author of table posts");
+
+    // Once the referencing table is dropped, so can be the referenced one.
+    catalog.dropTable("posts")?;
+    catalog.dropTable("users")?;
+    assert_eq!(catalog.getTable("users")?, None);
+    Ok(( ))
+  }
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::result::Result;
+use super::{
+  parser::ast::Expression,
+  schema::{ColumnId, IndexId, Table},
+  types::{Row, Value}
+};
+
+/*
+  Statistics of the rows of a table, collected by ANALYZE (see Catalog::analyzeTable). The planner
+  estimates how many rows the plan nodes produce from them : so that it looks up an index only when
+  the lookup is selective enough, and builds the hash table of a hash join over the smaller side.
+
+  They aren't kept up to date as the table is written, but replaced by the next ANALYZE. Once the
+  table has changed a lot since, they're ignored (see StatsCache) : the planner then falls back to
+  its heuristics, like it does for a table which was never analyzed.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableStats {
+  pub rowCount: u64,
+
+  // Statistics of the values of the columns (by their ids), and of the expression indexes (by their
+  // names).
+  pub columns: BTreeMap<ColumnId, ColumnStats>,
+  pub indexes: BTreeMap<String, ColumnStats>
+}
+
+// Statistics of the values of a column (or of an expression index).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStats {
+  // Estimated number of distinct values, NULL excluded (see DistinctCounter).
+  pub distinctCount: u64,
+
+  // Fraction of the rows, having NULL.
+  pub nullFraction: f64,
+
+  // The smallest and the greatest values, NULL excluded. None, if every value is NULL.
+  pub min: Option<Value>,
+  pub max: Option<Value>
+}
+
+impl TableStats {
+  // Returns the statistics of the column with the given index.
+  pub fn column(&self, table: &Table, column: usize) -> Option<&ColumnStats> {
+    self.columns.get(&table.columns.get(column)?.id)
+  }
+
+  // Returns the statistics of the index with the given name (see Table::getIndexId). There are none
+  // for an expression index created after the table was analyzed.
+  pub fn index(&self, table: &Table, name: &str) -> Option<&ColumnStats> {
+    match table.getIndexId(name).ok( )? {
+      IndexId::Column(column) => self.columns.get(&column),
+      IndexId::Expression(index) => self.indexes.get(&index)
+    }
+  }
+}
+
+impl ColumnStats {
+  // Returns the estimated fraction of the rows, having the given value (assuming that the non-NULL
+  // values are distributed uniformly).
+  pub fn equalitySelectivity(&self) -> f64 {
+    match self.distinctCount {
+      0 => 0.0,
+      distinctCount => (1.0 - self.nullFraction) / distinctCount as f64
+    }
+  }
+}
+
+// Collects the statistics of a table, from its rows.
+pub struct StatsCollector {
+  rowCount: u64,
+
+  columns: Vec<(ColumnId, ValuesCollector)>,
+  // The expression indexes, with their expressions resolved to the columns of the rows.
+  indexes: Vec<(String, Expression, ValuesCollector)>
+}
+
+impl StatsCollector {
+  pub fn new(table: &Table) -> Result<Self> {
+    let indexes= table.indexes.iter( )
+                              .map(|index| Ok((index.name.clone( ), index.resolveExpression(table)?, ValuesCollector::default( ))))
+                              .collect::<Result<_>>( )?;
+
+    Ok(Self {
+      rowCount: 0,
+      columns: table.columns.iter( ).map(|column| (column.id, ValuesCollector::default( ))).collect( ),
+      indexes
+    })
+  }
+
+  pub fn add(&mut self, row: &Row) -> Result<( )> {
+    self.rowCount += 1;
+
+    for ((_, collector), value) in self.columns.iter_mut( ).zip(row) {
+      collector.add(value)?;}
+    for (_, expression, collector) in &mut self.indexes {
+      collector.add(&expression.evaluate(Some(row))?)?;}
+
+    Ok(( ))
+  }
+
+  pub fn finish(self) -> TableStats {
+    let rowCount= self.rowCount;
+
+    TableStats {
+      rowCount,
+      columns: self.columns.into_iter( ).map(|(column, collector)| (column, collector.finish(rowCount))).collect( ),
+      indexes: self.indexes.into_iter( ).map(|(index, _, collector)| (index, collector.finish(rowCount))).collect( )
+    }
+  }
+}
+
+#[derive(Default)]
+struct ValuesCollector {
+  distinctCounter: DistinctCounter,
+  nulls: u64,
+  min: Option<Value>,
+  max: Option<Value>
+}
+
+impl ValuesCollector {
+  fn add(&mut self, value: &Value) -> Result<( )> {
+    if value.isNull( ) {
+      self.nulls += 1;
+      return Ok(( ))
+    }
+
+    self.distinctCounter.add(value)?;
+
+    if self.min.as_ref( ).is_none_or(|min| value < min) {
+      self.min= Some(value.clone( ));}
+    if self.max.as_ref( ).is_none_or(|max| value > max) {
+      self.max= Some(value.clone( ));}
+
+    Ok(( ))
+  }
+
+  fn finish(self, rowCount: u64) -> ColumnStats {
+    ColumnStats {
+      distinctCount: self.distinctCounter.estimate( ),
+      nullFraction: match rowCount {
+        0 => 0.0,
+        rowCount => self.nulls as f64 / rowCount as f64
+      },
+      min: self.min,
+      max: self.max
+    }
+  }
+}
+
+// Number of bits of a value's hash, picking its register in a DistinctCounter.
+const REGISTER_BITS: u32= 10;
+
+/*
+  Estimates the number of distinct values it's given, using a fixed amount of memory however many
+  there are (a HyperLogLog, with 2^REGISTER_BITS registers). The values are hashed : the leading bits
+  of a hash pick a register, which keeps the longest run of leading zeros seen in the remaining bits.
+  The more distinct values, the longer the runs. The error of the estimate is around 3%, and the
+  estimate is (nearly) exact for a few distinct values.
+
+  The values are hashed by their bincode encoding (using FNV-1a, followed by a mixing step), rather
+  than by the Hasher of the standard library : so that the estimate is the same on every replica,
+  whichever build of the server it runs.
+*/
+struct DistinctCounter {
+  registers: Vec<u8>
+}
+
+impl Default for DistinctCounter {
+  fn default( ) -> Self {
+    Self { registers: vec![0; 1 << REGISTER_BITS] }
+  }
+}
+
+impl DistinctCounter {
+  fn add(&mut self, value: &Value) -> Result<( )> {
+    let hash= hash(&bincode::serialize(value)?);
+
+    let register= (hash >> (64 - REGISTER_BITS)) as usize;
+    let rank= ((hash << REGISTER_BITS).leading_zeros( ) + 1).min(64 - REGISTER_BITS + 1) as u8;
+
+    self.registers[register]= self.registers[register].max(rank);
+    Ok(( ))
+  }
+
+  // While many registers are still empty, the estimate is derived from the number of empty ones
+  // instead (linear counting), which is more accurate for a few distinct values.
+  fn estimate(&self) -> u64 {
+    let registers= self.registers.len( ) as f64;
+    let alpha= 0.7213 / (1.0 + 1.079 / registers);
+
+    let sum= self.registers.iter( ).map(|rank| 2f64.powi(-(*rank as i32))).sum::<f64>( );
+    let estimate= alpha * registers * registers / sum;
+
+    let emptyRegisters= self.registers.iter( ).filter(|rank| **rank == 0).count( );
+    let estimate= match estimate <= 2.5 * registers && emptyRegisters > 0 {
+      true => registers * (registers / emptyRegisters as f64).ln( ),
+      false => estimate
+    };
+
+    estimate.round( ) as u64
+  }
+}
+
+fn hash(bytes: &[u8]) -> u64 {
+  let mut hash= 0xcbf29ce484222325u64;
+  for byte in bytes {
+    hash= (hash ^ *byte as u64).wrapping_mul(0x100000001b3);}
+
+  // FNV-1a spreads the differences of short inputs poorly over the leading bits, so the hash is
+  // mixed further (like the finalizer of SplitMix64 does).
+  hash= (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  hash= (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+  hash ^ (hash >> 31)
+}
@@ -0,0 +1,262 @@
+use std::{cmp::Ordering, fmt::Display, hash::{Hash, Hasher}};
+use serde::{Deserialize, Serialize};
+use crate::result::Result;
+use super::datetime;
+
+// Represents the data type of a column / value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DataType {
+  Boolean,
+  Integer,
+  Float,
+  String,
+  Date,
+  Timestamp
+}
+
+impl Display for DataType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Boolean => write!(f, "BOOLEAN"),
+      Self::Integer => write!(f, "INTEGER"),
+      Self::Float => write!(f, "FLOAT"),
+      Self::String => write!(f, "STRING"),
+      Self::Date => write!(f, "DATE"),
+      Self::Timestamp => write!(f, "TIMESTAMP")
+    }
+  }
+}
+
+/*
+  The type of the values of an expression (or of a column), inferred when a statement is planned.
+
+  The data type is unknown for an expression which is always NULL (like the NULL literal). It's
+  compatible with every data type.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueType {
+  pub dataType: Option<DataType>,
+  pub nullable: bool
+}
+
+impl ValueType {
+  pub fn new(dataType: DataType, nullable: bool) -> Self {
+    Self { dataType: Some(dataType), nullable }
+  }
+
+  // The type of the NULL literal.
+  pub fn unknown( ) -> Self {
+    Self { dataType: None, nullable: true }
+  }
+
+  // Returns whether the values can be of the given data type.
+  pub fn isCompatibleWith(&self, dataType: DataType) -> bool {
+    self.dataType.is_none_or(|ownDataType| ownDataType == dataType)
+  }
+
+  // Returns whether the values can be used in arithmetic.
+  pub fn isNumeric(&self) -> bool {
+    matches!(self.dataType, None | Some(DataType::Integer | DataType::Float))
+  }
+
+  /*
+    Returns the type, which the values of both types can be combined into (like for comparing them),
+    if any. The only implicit coercion is of integers into floats.
+  */
+  pub fn unify(&self, other: &Self) -> Option<Self> {
+    let dataType= match (self.dataType, other.dataType) {
+      (None, dataType) | (dataType, None) => dataType,
+
+      (Some(DataType::Integer), Some(DataType::Float))
+      | (Some(DataType::Float), Some(DataType::Integer)) => Some(DataType::Float),
+
+      (Some(lhs), Some(rhs)) if lhs == rhs => Some(lhs),
+      _ => return None
+    };
+
+    Some(Self { dataType, nullable: self.nullable || other.nullable })
+  }
+}
+
+impl Display for ValueType {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.dataType {
+      Some(dataType) => write!(f, "{}", dataType),
+      None => write!(f, "NULL")
+    }
+  }
+}
+
+/*
+  Represents a SQL value.
+
+  NOTE : The ordering and equality defined here are used for sorting, grouping and as keys (and
+  hence must be total). They differ from the SQL comparison operators, which are implemented during
+  expression evaluation - where NULL compares as NULL, and integers and floats are compared
+  numerically.
+
+  Values are ordered first by their type (NULL < booleans < integers < floats < strings < dates <
+  timestamps), and then by the value itself. Floats are ordered numerically, with -0.0 equal to 0.0,
+  and NaN being equal to itself and greater than every other float.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Value {
+  Null,
+  Boolean(bool),
+  Integer(i64),
+  Float(f64),
+  String(String),
+
+  // The number of days since the Unix epoch (see the datetime module).
+  Date(i64),
+  // The number of microseconds since the Unix epoch.
+  Timestamp(i64)
+}
+
+// Represents a row of values.
+pub type Row= Vec<Value>;
+
+impl Value {
+  // Returns the rank of the value's type, used to order values of different types.
+  fn typeRank(&self) -> u8 {
+    match self {
+      Self::Null => 0,
+      Self::Boolean(_) => 1,
+      Self::Integer(_) => 2,
+      Self::Float(_) => 3,
+      Self::String(_) => 4,
+      Self::Date(_) => 5,
+      Self::Timestamp(_) => 6
+    }
+  }
+
+  // Returns the data type of the value. NULL doesn't have a data type.
+  pub fn dataType(&self) -> Option<DataType> {
+    match self {
+      Self::Null => None,
+      Self::Boolean(_) => Some(DataType::Boolean),
+      Self::Integer(_) => Some(DataType::Integer),
+      Self::Float(_) => Some(DataType::Float),
+      Self::String(_) => Some(DataType::String),
+      Self::Date(_) => Some(DataType::Date),
+      Self::Timestamp(_) => Some(DataType::Timestamp)
+    }
+  }
+
+  pub fn isNull(&self) -> bool {
+    matches!(self, Self::Null)
+  }
+}
+
+// Compares floats totally : NaN is equal to itself and greater than every other float.
+pub fn compareFloats(lhs: f64, rhs: f64) -> Ordering {
+  match (lhs.is_nan( ), rhs.is_nan( )) {
+    (true, true) => Ordering::Equal,
+    (true, false) => Ordering::Greater,
+    (false, true) => Ordering::Less,
+    (false, false) => lhs.partial_cmp(&rhs).unwrap_or(Ordering::Equal)
+  }
+}
+
+impl PartialEq for Value {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Value { }
+
+impl PartialOrd for Value {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Value {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (Self::Null, Self::Null) => Ordering::Equal,
+      (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs.cmp(rhs),
+      (Self::Integer(lhs), Self::Integer(rhs)) => lhs.cmp(rhs),
+      (Self::Float(lhs), Self::Float(rhs)) => compareFloats(*lhs, *rhs),
+      (Self::String(lhs), Self::String(rhs)) => lhs.cmp(rhs),
+      (Self::Date(lhs), Self::Date(rhs)) => lhs.cmp(rhs),
+      (Self::Timestamp(lhs), Self::Timestamp(rhs)) => lhs.cmp(rhs),
+
+      (lhs, rhs) => lhs.typeRank( ).cmp(&rhs.typeRank( ))
+    }
+  }
+}
+
+impl Hash for Value {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.typeRank( ).hash(state);
+
+    match self {
+      Self::Null => { },
+      Self::Boolean(value) => value.hash(state),
+      Self::Integer(value) => value.hash(state),
+
+      // Equal floats must hash the same : so -0.0 is hashed as 0.0, and all the NaNs are hashed
+      // the same.
+      Self::Float(value) =>
+        if value.is_nan( ) {
+          f64::NAN.to_bits( ).hash(state)}
+        else if *value == 0.0 {
+          0.0f64.to_bits( ).hash(state)}
+        else {
+          value.to_bits( ).hash(state)},
+
+      Self::String(value) => value.hash(state),
+      Self::Date(value) | Self::Timestamp(value) => value.hash(state)
+    }
+  }
+}
+
+impl Display for Value {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Null => write!(f, "NULL"),
+      Self::Boolean(true) => write!(f, "TRUE"),
+      Self::Boolean(false) => write!(f, "FALSE"),
+      Self::Integer(value) => write!(f, "{}", value),
+      Self::Float(value) => write!(f, "{}", value),
+      Self::String(value) => write!(f, "{}", value),
+      Self::Date(value) => write!(f, "{}", datetime::formatDate(*value)),
+      Self::Timestamp(value) => write!(f, "{}", datetime::formatTimestamp(*value))
+    }
+  }
+}
+
+impl From<bool> for Value {
+  fn from(value: bool) -> Self {
+    Self::Boolean(value)
+  }
+}
+
+impl From<i64> for Value {
+  fn from(value: i64) -> Self {
+    Self::Integer(value)
+  }
+}
+
+impl From<f64> for Value {
+  fn from(value: f64) -> Self {
+    Self::Float(value)
+  }
+}
+
+impl From<String> for Value {
+  fn from(value: String) -> Self {
+    Self::String(value)
+  }
+}
+
+impl From<&str> for Value {
+  fn from(value: &str) -> Self {
+    Self::String(value.to_string( ))
+  }
+}
+
+// An iterator over rows.
+pub type Rows= Box<dyn Iterator<Item= Result<Row>> + Send>;
@@ -0,0 +1,197 @@
+use std::{
+  fs::{self, File, OpenOptions, TryLockError},
+  io::{Read, Write},
+  path::{Path, PathBuf}
+};
+use tracing::info;
+use crate::result::{Error, ErrorCode, Result};
+
+// Version of the layout (and of the formats of the files) of the data directory. It's bumped on an
+// incompatible change, so that an older server doesn't misread a directory written by a newer one.
+pub const FORMAT_VERSION: u32= 2;
+
+const VERSION_FILE: &str= "VERSION";
+const LOCK_FILE: &str= "LOCK";
+
+const RAFT_DIRECTORY: &str= "raft";
+const DATA_DIRECTORY: &str= "data";
+const SNAPSHOTS_DIRECTORY: &str= "snapshots";
+const TMP_DIRECTORY: &str= "tmp";
+
+/*
+  The directory a node keeps its state in. Its layout is :
+
+    VERSION    : the format version the directory was initialized with (see FORMAT_VERSION).
+    LOCK       : locked (by an advisory lock) by the server process using the directory. It holds the
+                 id of that process.
+    raft/      : the Raft log.
+    data/      : the SQL data (the MVCC storage).
+    snapshots/ : the snapshots of the state machine.
+    tmp/       : temporary files (like the spilled rows of the executors). They only live as long as
+                 the process which wrote them, so the ones left by a crashed run are removed when the
+                 directory is opened.
+
+  The rest of the code gets the paths through the accessors, rather than joining them by itself.
+
+  The directory is locked for as long as the DataDir lives, so that two server processes can't use
+  the same directory (corrupting each other's files). The lock is released by the OS when the
+  process exits, even if it crashes.
+*/
+pub struct DataDir {
+  path: PathBuf,
+
+  // Holds the lock on the directory.
+  _lock: File
+}
+
+impl DataDir {
+  /*
+    Opens the directory, creating and initializing it if it doesn't exist (or is empty).
+
+    Fails, if another process has the directory locked, or if the directory was written in a format
+    this server doesn't support (like by a newer version of it).
+  */
+  pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+    let path= path.into( );
+    fs::create_dir_all(&path)?;
+
+    let lock= Self::lock(&path)?;
+
+    let versionPath= path.join(VERSION_FILE);
+    match versionPath.exists( ) {
+      true => Self::checkVersion(&path, &fs::read_to_string(&versionPath)?)?,
+      false => Self::initialize(&path)?
+    }
+
+    for directory in [RAFT_DIRECTORY, DATA_DIRECTORY, SNAPSHOTS_DIRECTORY, TMP_DIRECTORY] {
+      fs::create_dir_all(path.join(directory))?;}
+
+    let dataDir= Self { path, _lock: lock };
+    dataDir.sweepTmpDirectory( )?;
+
+    Ok(dataDir)
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn raftDirectory(&self) -> PathBuf {
+    self.path.join(RAFT_DIRECTORY)
+  }
+
+  pub fn dataDirectory(&self) -> PathBuf {
+    self.path.join(DATA_DIRECTORY)
+  }
+
+  pub fn snapshotsDirectory(&self) -> PathBuf {
+    self.path.join(SNAPSHOTS_DIRECTORY)
+  }
+
+  pub fn tmpDirectory(&self) -> PathBuf {
+    self.path.join(TMP_DIRECTORY)
+  }
+
+  // Directory the executors spill rows to (see WorkMemory).
+  pub fn spillDirectory(&self) -> PathBuf {
+    self.tmpDirectory( ).join("spill")
+  }
+
+  // Takes the lock on the directory, recording the id of this process in the lock file.
+  fn lock(path: &Path) -> Result<File> {
+    let lockPath= path.join(LOCK_FILE);
+    let mut lock= OpenOptions::new( ).read(true).write(true).create(true).truncate(false).open(&lockPath)?;
+
+    match lock.try_lock( ) {
+      Ok(( )) => { },
+
+      Err(TryLockError::WouldBlock) => {
+        let mut holder= String::new( );
+        lock.read_to_string(&mut holder)?;
+
+        return Err(Error::Value(ErrorCode::ObjectInUse, format!(
+          "Data directory {} is locked by another server process (with id {}). Stop it, or point this server at another data directory",
+          path.display( ), holder.trim( )
+        )))
+      },
+
+      Err(TryLockError::Error(error)) => return Err(error.into( ))
+    }
+
+    lock.set_len(0)?;
+    lock.write_all(std::process::id( ).to_string( ).as_bytes( ))?;
+    lock.sync_all( )?;
+
+    Ok(lock)
+  }
+
+  fn checkVersion(path: &Path, version: &str) -> Result<( )> {
+    let version= version.trim( ).parse::<u32>( ).map_err(|_| Error::Corruption(format!(
+      "The {} file of data directory {} holds {:?}, rather than a format version", VERSION_FILE, path.display( ), version.trim( )
+    )))?;
+
+    match version {
+      FORMAT_VERSION => Ok(( )),
+
+      version if version > FORMAT_VERSION => Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, format!(
+        "Data directory {} has format version {}, which is newer than the version {} this server supports. Upgrade the server to open it",
+        path.display( ), version, FORMAT_VERSION
+      ))),
+      version => Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, format!(
+        "Data directory {} has format version {}, which this server (of format version {}) can't migrate from",
+        path.display( ), version, FORMAT_VERSION
+      )))
+    }
+  }
+
+  /*
+    Writes the VERSION file into a new directory. It's written to a temporary file and then renamed,
+    so that a crash leaves either no VERSION file or a complete one.
+
+    NOTE : A directory without a VERSION file must be empty (apart from the lock and the temporary
+    files), so that a server pointed at the wrong directory doesn't take it over.
+  */
+  fn initialize(path: &Path) -> Result<( )> {
+    for entry in fs::read_dir(path)? {
+      let name= entry?.file_name( );
+      if name != LOCK_FILE && name != TMP_DIRECTORY {
+        return Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, format!(
+          "Data directory {} isn't empty, but has no {} file (it has {}). Point the server at an empty (or a new) directory",
+          path.display( ), VERSION_FILE, name.to_string_lossy( )
+        )))}
+    }
+
+    let tmpDirectory= path.join(TMP_DIRECTORY);
+    fs::create_dir_all(&tmpDirectory)?;
+
+    let tmpVersionPath= tmpDirectory.join(VERSION_FILE);
+    let mut file= File::create(&tmpVersionPath)?;
+    file.write_all(FORMAT_VERSION.to_string( ).as_bytes( ))?;
+    file.sync_all( )?;
+
+    fs::rename(&tmpVersionPath, path.join(VERSION_FILE))?;
+    File::open(path)?.sync_all( )?;
+
+    info!("Initialized data directory {} (format version {})", path.display( ), FORMAT_VERSION);
+    Ok(( ))
+  }
+
+  // Removes the files (and directories) left in the tmp directory, by a crashed run.
+  fn sweepTmpDirectory(&self) -> Result<( )> {
+    let mut removed= 0;
+
+    for entry in fs::read_dir(self.tmpDirectory( ))? {
+      let entry= entry?;
+      match entry.file_type( )?.is_dir( ) {
+        true => fs::remove_dir_all(entry.path( ))?,
+        false => fs::remove_file(entry.path( ))?
+      }
+      removed += 1;
+    }
+
+    if removed > 0 {
+      info!("Removed {} orphaned temporary files (or directories) from {}", removed, self.tmpDirectory( ).display( ));}
+
+    Ok(( ))
+  }
+}
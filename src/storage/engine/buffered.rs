@@ -0,0 +1,168 @@
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display, iter::Peekable};
+use crate::result::{Error, Result};
+use super::{Direction, KeyRange, PendingFlush, ScanIterator, StorageEngine, StorageEngineStatus, WriteBatch};
+
+/*
+  Wraps a storage engine, so that a group of writes can be made atomic : while a batch is open (see
+  begin( )), the writes are buffered in memory instead of going to the engine. They're then written
+  to the engine all at once, as a single write batch (see commit( )), or thrown away (see
+  discard( )). Reads see the buffered writes.
+
+  Outside a batch, the writes go straight to the engine.
+*/
+pub struct Buffered<E: StorageEngine> {
+  engine: E,
+
+  // The writes buffered in the open batch (if any), where a None value deletes the key.
+  buffer: Option<BTreeMap<Vec<u8>, Option<Vec<u8>>>>
+}
+
+impl<E: StorageEngine> Buffered<E> {
+  pub fn new(engine: E) -> Self {
+    Self { engine, buffer: None }
+  }
+
+  // Opens a batch. The writes are buffered, until it's committed or discarded.
+  pub fn begin(&mut self) -> Result<( )> {
+    if self.buffer.is_some( ) {
+      return Err(Error::Internal("A write batch is already open".to_string( )))}
+
+    self.buffer= Some(BTreeMap::new( ));
+    Ok(( ))
+  }
+
+  // Writes the buffered writes to the engine as a single batch, and closes the batch.
+  pub fn commit(&mut self) -> Result<( )> {
+    let buffer= self.buffer.take( ).ok_or_else(| | Error::Internal("No write batch is open".to_string( )))?;
+    self.engine.writeBatch(buffer.into_iter( ).collect( ))
+  }
+
+  // Throws away the buffered writes, and closes the batch.
+  pub fn discard(&mut self) {
+    self.buffer= None;
+  }
+}
+
+impl<E: StorageEngine> Display for Buffered<E> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    self.engine.fmt(f)
+  }
+}
+
+impl<E: StorageEngine> StorageEngine for Buffered<E> {
+  fn set(&mut self, key: &[u8], value: Vec<u8>) -> Result<( )> {
+    match &mut self.buffer {
+      Some(buffer) => {
+        buffer.insert(key.to_vec( ), Some(value));
+        Ok(( ))
+      },
+      None => self.engine.set(key, value)
+    }
+  }
+
+  // NOTE : The buffered writes aren't flushed, since they aren't written to the engine until the
+  // batch is committed.
+  fn flush(&mut self) -> Result<( )> {
+    self.engine.flush( )
+  }
+
+  fn beginFlush(&mut self) -> Result<PendingFlush> {
+    self.engine.beginFlush( )
+  }
+
+  fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    match self.buffer.as_ref( ).and_then(|buffer| buffer.get(key)) {
+      Some(value) => Ok(value.clone( )),
+      None => self.engine.get(key)
+    }
+  }
+
+  fn delete(&mut self, key: &[u8]) -> Result<( )> {
+    match &mut self.buffer {
+      Some(buffer) => {
+        buffer.insert(key.to_vec( ), None);
+        Ok(( ))
+      },
+      None => self.engine.delete(key)
+    }
+  }
+
+  fn writeBatch(&mut self, batch: WriteBatch) -> Result<( )> {
+    match &mut self.buffer {
+      Some(buffer) => {
+        buffer.extend(batch);
+        Ok(( ))
+      },
+      None => self.engine.writeBatch(batch)
+    }
+  }
+
+  fn scan(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    match &self.buffer {
+      Some(buffer) => Box::new(MergedScan {
+        engine: self.engine.scan(range.clone( )).peekable( ),
+        buffer: (Box::new(buffer.range(range)) as BufferIterator<'_>).peekable( ),
+        direction: Direction::Forward
+      }),
+      None => self.engine.scan(range)
+    }
+  }
+
+  fn scanRev(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    match &self.buffer {
+      Some(buffer) => Box::new(MergedScan {
+        engine: self.engine.scanRev(range.clone( )).peekable( ),
+        buffer: (Box::new(buffer.range(range).rev( )) as BufferIterator<'_>).peekable( ),
+        direction: Direction::Reverse
+      }),
+      None => self.engine.scanRev(range)
+    }
+  }
+
+  // NOTE : The buffered writes aren't accounted for.
+  fn status(&self) -> Result<StorageEngineStatus> {
+    self.engine.status( )
+  }
+}
+
+type BufferIterator<'a>= Box<dyn Iterator<Item= (&'a Vec<u8>, &'a Option<Vec<u8>>)> + 'a>;
+
+// Merges a scan of the engine with the buffered writes in the same range (both iterated in the
+// given direction). A buffered write shadows the pair of the same key stored in the engine.
+struct MergedScan<'a> {
+  engine: Peekable<ScanIterator<'a>>,
+  buffer: Peekable<BufferIterator<'a>>,
+  direction: Direction
+}
+
+impl Iterator for MergedScan<'_> {
+  type Item= Result<(Vec<u8>, Vec<u8>)>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let ordering= match (self.engine.peek( ), self.buffer.peek( )) {
+        (None, None) => return None,
+        (Some(_), None) | (Some(Err(_)), _) => return self.engine.next( ),
+        (None, Some(_)) => Ordering::Greater,
+
+        (Some(Ok((engineKey, _))), Some((bufferKey, _))) => match self.direction {
+          Direction::Forward => engineKey.cmp(bufferKey),
+          Direction::Reverse => bufferKey.cmp(&engineKey)
+        }
+      };
+
+      match ordering {
+        Ordering::Less => return self.engine.next( ),
+
+        // The buffered write shadows the stored pair.
+        Ordering::Equal => { self.engine.next( ); },
+
+        Ordering::Greater => { }
+      }
+
+      // A buffered deletion hides the key.
+      if let Some((key, Some(value)))= self.buffer.next( ) {
+        return Some(Ok((key.clone( ), value.clone( ))))}
+    }
+  }
+}
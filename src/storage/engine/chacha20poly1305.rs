@@ -0,0 +1,313 @@
+/*
+  The ChaCha20-Poly1305 AEAD cipher, as specified by RFC 8439. It's used by the EncryptedEngine to
+  encrypt (and authenticate) the values.
+
+  ChaCha20 is a stream cipher : the plaintext is XORed with a keystream, generated from the key and a
+  nonce. Poly1305 then computes a tag over the additional data and the ciphertext, with a one-time
+  key derived from the same keystream. So, a (key, nonce) pair must never be used twice.
+
+  NOTE : The computations don't branch on secret data (except for the lengths), and the tags are
+  compared in constant time.
+*/
+
+pub const KEY_SIZE: usize= 32;
+pub const NONCE_SIZE: usize= 12;
+pub const TAG_SIZE: usize= 16;
+
+// Encrypts the plaintext, and returns the ciphertext followed by the tag (which authenticates both
+// the ciphertext and the additional data).
+pub fn seal(key: &[u8; KEY_SIZE], nonce: &[u8; NONCE_SIZE], additionalData: &[u8], plaintext: &[u8]) -> Vec<u8> {
+  let mut sealed= plaintext.to_vec( );
+  applyKeystream(key, nonce, 1, &mut sealed);
+
+  let tag= computeTag(key, nonce, additionalData, &sealed);
+  sealed.extend_from_slice(&tag);
+  sealed
+}
+
+// Decrypts the output of seal( ). Returns None if the tag doesn't match, i.e. the ciphertext or the
+// additional data has been tampered with, or a different key / nonce was used to seal it.
+pub fn open(key: &[u8; KEY_SIZE], nonce: &[u8; NONCE_SIZE], additionalData: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+  if sealed.len( ) < TAG_SIZE {
+    return None}
+
+  let (ciphertext, tag)= sealed.split_at(sealed.len( ) - TAG_SIZE);
+
+  let expectedTag= computeTag(key, nonce, additionalData, ciphertext);
+  let difference= expectedTag.iter( ).zip(tag).fold(0, |difference, (a, b)| difference | (a ^ b));
+  if difference != 0 {
+    return None}
+
+  let mut plaintext= ciphertext.to_vec( );
+  applyKeystream(key, nonce, 1, &mut plaintext);
+  Some(plaintext)
+}
+
+// XORs the data with the ChaCha20 keystream, starting from the given block.
+fn applyKeystream(key: &[u8; KEY_SIZE], nonce: &[u8; NONCE_SIZE], firstBlock: u32, data: &mut [u8]) {
+  for (i, chunk) in data.chunks_mut(64).enumerate( ) {
+    let keystream= chacha20Block(key, nonce, firstBlock.wrapping_add(i as u32));
+    for (byte, keystreamByte) in chunk.iter_mut( ).zip(keystream) {
+      *byte ^= keystreamByte;
+    }
+  }
+}
+
+// Computes the Poly1305 tag over the additional data and the ciphertext (each padded to 16 bytes),
+// followed by their lengths. The Poly1305 key is the start of the first ChaCha20 block.
+fn computeTag(key: &[u8; KEY_SIZE], nonce: &[u8; NONCE_SIZE], additionalData: &[u8], ciphertext: &[u8]) -> [u8; TAG_SIZE] {
+  let block= chacha20Block(key, nonce, 0);
+  let mut poly1305= Poly1305::new(block[ ..32].try_into( ).unwrap( ));
+
+  poly1305.updatePadded(additionalData);
+  poly1305.updatePadded(ciphertext);
+
+  let mut lengths= [0u8; 16];
+  lengths[ ..8].copy_from_slice(&(additionalData.len( ) as u64).to_le_bytes( ));
+  lengths[8..].copy_from_slice(&(ciphertext.len( ) as u64).to_le_bytes( ));
+  poly1305.block(&lengths);
+
+  poly1305.finish( )
+}
+
+fn quarterRound(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+  state[a]= state[a].wrapping_add(state[b]); state[d]= (state[d] ^ state[a]).rotate_left(16);
+  state[c]= state[c].wrapping_add(state[d]); state[b]= (state[b] ^ state[c]).rotate_left(12);
+  state[a]= state[a].wrapping_add(state[b]); state[d]= (state[d] ^ state[a]).rotate_left(8);
+  state[c]= state[c].wrapping_add(state[d]); state[b]= (state[b] ^ state[c]).rotate_left(7);
+}
+
+// Returns the 64 byte keystream block, for the given block counter.
+fn chacha20Block(key: &[u8; KEY_SIZE], nonce: &[u8; NONCE_SIZE], counter: u32) -> [u8; 64] {
+  let word= |bytes: &[u8], i: usize| u32::from_le_bytes(bytes[4*i .. 4*i + 4].try_into( ).unwrap( ));
+
+  // The constants spell "expand 32-byte k".
+  let mut initial= [0u32; 16];
+  initial[ ..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+  for i in 0..8 {
+    initial[4 + i]= word(key, i);
+  }
+  initial[12]= counter;
+  for i in 0..3 {
+    initial[13 + i]= word(nonce, i);
+  }
+
+  let mut state= initial;
+  for _ in 0..10 {
+    // Column rounds.
+    quarterRound(&mut state, 0, 4, 8, 12);
+    quarterRound(&mut state, 1, 5, 9, 13);
+    quarterRound(&mut state, 2, 6, 10, 14);
+    quarterRound(&mut state, 3, 7, 11, 15);
+
+    // Diagonal rounds.
+    quarterRound(&mut state, 0, 5, 10, 15);
+    quarterRound(&mut state, 1, 6, 11, 12);
+    quarterRound(&mut state, 2, 7, 8, 13);
+    quarterRound(&mut state, 3, 4, 9, 14);
+  }
+
+  let mut block= [0u8; 64];
+  for i in 0..16 {
+    block[4*i .. 4*i + 4].copy_from_slice(&state[i].wrapping_add(initial[i]).to_le_bytes( ));
+  }
+  block
+}
+
+/*
+  The Poly1305 MAC. The accumulator h and the key r are numbers modulo 2^130 - 5, each stored as five
+  26 bit limbs, so that the products of the limbs fit in a u64.
+
+  NOTE : Only whole 16 byte blocks are processed, which is all the AEAD construction needs (since it
+  pads its inputs).
+*/
+struct Poly1305 {
+  r: [u64; 5],
+  h: [u64; 5],
+  pad: [u32; 4]
+}
+
+const LIMB_MASK: u64= 0x3ffffff;
+
+fn le32(bytes: &[u8], offset: usize) -> u64 {
+  u32::from_le_bytes(bytes[offset .. offset + 4].try_into( ).unwrap( )) as u64
+}
+
+impl Poly1305 {
+  fn new(key: &[u8; 32]) -> Self {
+    // r is clamped, as the specification requires.
+    let r= [
+      le32(key, 0) & 0x3ffffff,
+      (le32(key, 3) >> 2) & 0x3ffff03,
+      (le32(key, 6) >> 4) & 0x3ffc0ff,
+      (le32(key, 9) >> 6) & 0x3f03fff,
+      (le32(key, 12) >> 8) & 0x00fffff
+    ];
+    let pad= [16, 20, 24, 28].map(|offset| le32(key, offset) as u32);
+
+    Self { r, h: [0; 5], pad }
+  }
+
+  // Processes the data, padding its last block with zeroes.
+  fn updatePadded(&mut self, data: &[u8]) {
+    for chunk in data.chunks(16) {
+      let mut block= [0u8; 16];
+      block[ ..chunk.len( )].copy_from_slice(chunk);
+      self.block(&block);
+    }
+  }
+
+  // Computes h = (h + block) * r, where the block has a 1 bit appended.
+  fn block(&mut self, block: &[u8; 16]) {
+    let [r0, r1, r2, r3, r4]= self.r;
+    let [s1, s2, s3, s4]= [r1 * 5, r2 * 5, r3 * 5, r4 * 5];
+
+    let h= &mut self.h;
+    h[0] += le32(block, 0) & LIMB_MASK;
+    h[1] += (le32(block, 3) >> 2) & LIMB_MASK;
+    h[2] += (le32(block, 6) >> 4) & LIMB_MASK;
+    h[3] += (le32(block, 9) >> 6) & LIMB_MASK;
+    h[4] += (le32(block, 12) >> 8) | (1 << 24);
+
+    let d= [
+      h[0] * r0 + h[1] * s4 + h[2] * s3 + h[3] * s2 + h[4] * s1,
+      h[0] * r1 + h[1] * r0 + h[2] * s4 + h[3] * s3 + h[4] * s2,
+      h[0] * r2 + h[1] * r1 + h[2] * r0 + h[3] * s4 + h[4] * s3,
+      h[0] * r3 + h[1] * r2 + h[2] * r1 + h[3] * r0 + h[4] * s4,
+      h[0] * r4 + h[1] * r3 + h[2] * r2 + h[3] * r1 + h[4] * r0
+    ];
+
+    // Carries the limbs back into 26 bits, where the carry out of the top limb wraps around
+    // multiplied by 5 (since 2^130 = 5 modulo 2^130 - 5).
+    let mut carry= 0;
+    for i in 0..5 {
+      let limb= d[i] + carry;
+      h[i]= limb & LIMB_MASK;
+      carry= limb >> 26;
+    }
+    h[0] += carry * 5;
+    h[1] += h[0] >> 26;
+    h[0] &= LIMB_MASK;
+  }
+
+  // Fully reduces h modulo 2^130 - 5, and returns (h + pad) modulo 2^128.
+  fn finish(mut self) -> [u8; TAG_SIZE] {
+    let h= &mut self.h;
+
+    let mut carry= 0;
+    for limb in h.iter_mut( ).skip(1) {
+      *limb += carry;
+      carry= *limb >> 26;
+      *limb &= LIMB_MASK;
+    }
+    h[0] += carry * 5;
+    h[1] += h[0] >> 26;
+    h[0] &= LIMB_MASK;
+
+    // Computes g = h - (2^130 - 5), and picks it instead of h if it didn't underflow.
+    let mut g= [0u64; 5];
+    let mut carry= 5;
+    for i in 0..5 {
+      let limb= h[i] + carry;
+      g[i]= limb & LIMB_MASK;
+      carry= limb >> 26;
+    }
+    let underflowed= (carry as i64 - 1) >> 63; // All 1s if h < 2^130 - 5, else all 0s.
+    let pickG= !(underflowed as u64);
+    for i in 0..5 {
+      h[i]= (h[i] & !pickG) | (g[i] & pickG);
+    }
+
+    let words= [
+      (h[0] | (h[1] << 26)) & 0xffffffff,
+      ((h[1] >> 6) | (h[2] << 20)) & 0xffffffff,
+      ((h[2] >> 12) | (h[3] << 14)) & 0xffffffff,
+      ((h[3] >> 18) | (h[4] << 8)) & 0xffffffff
+    ];
+
+    let mut tag= [0u8; TAG_SIZE];
+    let mut carry= 0u64;
+    for i in 0..4 {
+      let word= words[i] + self.pad[i] as u64 + carry;
+      tag[4*i .. 4*i + 4].copy_from_slice(&(word as u32).to_le_bytes( ));
+      carry= word >> 32;
+    }
+    tag
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Parses the hex digits, ignoring the whitespace between them.
+  fn hex(digits: &str) -> Vec<u8> {
+    let digits= digits.split_whitespace( ).collect::<String>( );
+    (0..digits.len( )).step_by(2)
+                      .map(|i| u8::from_str_radix(&digits[i .. i + 2], 16).unwrap( ))
+                      .collect( )
+  }
+
+  struct TestVector {
+    key: [u8; KEY_SIZE],
+    nonce: [u8; NONCE_SIZE],
+    additionalData: Vec<u8>,
+    plaintext: Vec<u8>,
+    sealed: Vec<u8>
+  }
+
+  // The AEAD test vector of RFC 8439 (section 2.8.2).
+  fn rfc8439Vector( ) -> TestVector {
+    let key= hex("808182838485868788898a8b8c8d8e8f 909192939495969798999a9b9c9d9e9f");
+    let nonce= hex("070000004041424344454647");
+    let additionalData= hex("50515253c0c1c2c3c4c5c6c7");
+    let plaintext= b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.".to_vec( );
+
+    let sealed= hex("
+      d31a8d34648e60db7b86afbc53ef7ec2 a4aded51296e08fea9e2b5a736ee62d6
+      3dbea45e8ca9671282fafb69da92728b 1a71de0a9e060b2905d6a5b67ecd3b36
+      92ddbd7f2d778b8c9803aee328091b58 fab324e4fad675945585808b4831d7bc
+      3ff4def08e4b7a9de576d26586cec64b 6116
+      1ae10b594f09e26a7e902ecbd0600691
+    ");
+
+    TestVector { key: key.try_into( ).unwrap( ), nonce: nonce.try_into( ).unwrap( ), additionalData, plaintext, sealed }
+  }
+
+  #[test]
+  fn sealsRfc8439Vector( ) {
+    let TestVector { key, nonce, additionalData, plaintext, sealed }= rfc8439Vector( );
+
+    assert_eq!(seal(&key, &nonce, &additionalData, &plaintext), sealed);
+    assert_eq!(open(&key, &nonce, &additionalData, &sealed), Some(plaintext));
+  }
+
+  #[test]
+  fn rejectsTamperedInput( ) {
+    let TestVector { key, nonce, additionalData, sealed, .. }= rfc8439Vector( );
+
+    // Flipping a bit anywhere in the ciphertext, the tag or the additional data fails the
+    // authentication.
+    for i in 0..sealed.len( ) {
+      let mut tampered= sealed.clone( );
+      tampered[i] ^= 1;
+      assert_eq!(open(&key, &nonce, &additionalData, &tampered), None, "byte {} of the sealed value", i);
+    }
+    for i in 0..additionalData.len( ) {
+      let mut tampered= additionalData.clone( );
+      tampered[i] ^= 1;
+      assert_eq!(open(&key, &nonce, &tampered, &sealed), None, "byte {} of the additional data", i);
+    }
+
+    // So does a different key or nonce, or a truncated value.
+    let mut otherKey= key;
+    otherKey[0] ^= 1;
+    assert_eq!(open(&otherKey, &nonce, &additionalData, &sealed), None);
+
+    let mut otherNonce= nonce;
+    otherNonce[0] ^= 1;
+    assert_eq!(open(&key, &otherNonce, &additionalData, &sealed), None);
+
+    assert_eq!(open(&key, &nonce, &additionalData, &sealed[ ..TAG_SIZE - 1]), None);
+  }
+}
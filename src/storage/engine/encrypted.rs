@@ -0,0 +1,328 @@
+use std::{fmt::Display, ops::Bound};
+use rand::{rngs::OsRng, RngCore};
+use crate::result::{Error, Result};
+use super::{
+  chacha20poly1305::{self, KEY_SIZE, NONCE_SIZE},
+  KeyRange, PendingFlush, ScanIterator, StorageEngine, StorageEngineStatus, WriteBatch
+};
+
+/*
+  Wraps a storage engine, encrypting the values before they reach it (and decrypting them when
+  they're read back), so that the data at rest is unreadable without the key.
+
+  The values are encrypted with ChaCha20-Poly1305, an AEAD cipher. Each value is stored as :
+
+    nonce (12 B) | ciphertext | tag (16 B)
+
+  where the nonce is picked randomly for each write. The key (of the key-value pair) is passed as
+  the additional data, so a value moved to another key (or tampered with in any way) fails to
+  authenticate. Reading such a value fails with Error::Corruption, instead of returning garbage.
+
+  While the key is being rotated (see rewrap( )), the values are read with the new key, or else with
+  the previous one.
+
+  NOTE : Only the values are encrypted, the keys are stored as they are. Encrypting the keys would
+  destroy their ordering (which the scans rely on), unless an order preserving scheme is used, and
+  those leak a lot about the plaintext. So, data must not be put in the keys if it needs to be kept
+  secret. The sizes of the values are also visible (each grows by 28 B).
+*/
+pub struct EncryptedEngine<E: StorageEngine> {
+  engine: E,
+  key: EncryptionKey,
+
+  // The key being rotated away from, which the values not yet rewrapped are encrypted with.
+  previousKey: Option<EncryptionKey>
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; KEY_SIZE]);
+
+impl EncryptionKey {
+  pub fn new(bytes: [u8; KEY_SIZE]) -> Self {
+    Self(bytes)
+  }
+
+  // Generates a random key.
+  pub fn generate( ) -> Self {
+    let mut bytes= [0u8; KEY_SIZE];
+    OsRng.fill_bytes(&mut bytes);
+    Self(bytes)
+  }
+}
+
+// NOTE : The key is kept out of the debug output, so that it doesn't end up in the logs.
+impl std::fmt::Debug for EncryptionKey {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("EncryptionKey(..)")
+  }
+}
+
+// Number of key-value pairs rewrapped per write batch (see EncryptedEngine::rewrap( )).
+const REWRAP_BATCH_SIZE: usize= 1000;
+
+impl<E: StorageEngine> EncryptedEngine<E> {
+  pub fn new(engine: E, key: EncryptionKey) -> Self {
+    Self { engine, key, previousKey: None }
+  }
+
+  // Wraps an engine whose key rotation (from the old key to the new one) was interrupted, like by a
+  // crash. The values are read with either key, until rewrap( ) completes the rotation.
+  pub fn newRotating(engine: E, oldKey: EncryptionKey, newKey: EncryptionKey) -> Self {
+    Self { engine, key: newKey, previousKey: Some(oldKey) }
+  }
+
+  /*
+    Rotates the key : re-encrypts all the values stored with the old key, using the new key. The
+    key-value pairs are read using scans, and written back using write batches (of
+    REWRAP_BATCH_SIZE pairs each). Once it returns, the rewrapped values are flushed, and the old key
+    can be discarded.
+
+    NOTE : The rotation isn't atomic. If it's interrupted, some values will be encrypted with the old
+    key and some with the new one. So, the new key is used for the subsequent operations right away,
+    with the values being read with the old key as a fallback, until the rotation completes. After a
+    restart, the engine must be wrapped using newRotating( ) with both the keys, and the rotation is
+    completed by running it again (a value which already authenticates with the new key is skipped).
+  */
+  pub fn rewrap(&mut self, oldKey: &EncryptionKey, newKey: EncryptionKey) -> Result<( )> {
+    self.previousKey= Some(oldKey.clone( ));
+    self.key= newKey;
+
+    let mut start= Bound::Unbounded;
+    loop {
+      let pairs= self.engine.scan((start, Bound::Unbounded))
+                            .take(REWRAP_BATCH_SIZE)
+                            .collect::<Result<Vec<_>>>( )?;
+      let Some((lastKey, _))= pairs.last( ) else { break };
+      start= Bound::Excluded(lastKey.clone( ));
+
+      let mut batch= WriteBatch::with_capacity(pairs.len( ));
+      for (key, record) in pairs {
+        let value= match decrypt(oldKey, &key, &record) {
+          Ok(value) => value,
+          Err(error) => match decrypt(&self.key, &key, &record) {
+            // Already rewrapped, by an interrupted rotation.
+            Ok(_) => continue,
+            Err(_) => return Err(error)
+          }
+        };
+        let record= encrypt(&self.key, &key, &value);
+        batch.push((key, Some(record)));
+      }
+      self.engine.writeBatch(batch)?;
+    }
+    self.engine.flush( )?;
+
+    self.previousKey= None;
+    Ok(( ))
+  }
+}
+
+// Encrypts the value stored against the given key, and returns the record to be stored.
+fn encrypt(encryptionKey: &EncryptionKey, key: &[u8], value: &[u8]) -> Vec<u8> {
+  let mut nonce= [0u8; NONCE_SIZE];
+  OsRng.fill_bytes(&mut nonce);
+
+  let mut record= nonce.to_vec( );
+  record.extend(chacha20poly1305::seal(&encryptionKey.0, &nonce, key, value));
+  record
+}
+
+// Decrypts a record stored against the given key, with the encryption key or else (during a key
+// rotation) with the previous one. Fails with Error::Corruption if it doesn't authenticate with
+// either of them.
+fn decryptRotating(encryptionKey: &EncryptionKey, previousKey: Option<&EncryptionKey>, key: &[u8], record: &[u8]) -> Result<Vec<u8>> {
+  match (decrypt(encryptionKey, key, record), previousKey) {
+    (Err(error), Some(previousKey)) => decrypt(previousKey, key, record).map_err(|_| error),
+    (result, _) => result
+  }
+}
+
+// Decrypts a record stored against the given key. Fails with Error::Corruption if it doesn't
+// authenticate.
+fn decrypt(encryptionKey: &EncryptionKey, key: &[u8], record: &[u8]) -> Result<Vec<u8>> {
+  let corruption= | | Error::Corruption(format!("The value stored against key {:?} failed to authenticate : it has been tampered with, or the encryption key is wrong", key));
+
+  if record.len( ) < NONCE_SIZE {
+    return Err(corruption( ))}
+
+  let (nonce, sealed)= record.split_at(NONCE_SIZE);
+  chacha20poly1305::open(&encryptionKey.0, nonce.try_into( ).unwrap( ), key, sealed).ok_or_else(corruption)
+}
+
+impl<E: StorageEngine> Display for EncryptedEngine<E> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "encrypted({})", self.engine)
+  }
+}
+
+impl<E: StorageEngine> StorageEngine for EncryptedEngine<E> {
+  fn set(&mut self, key: &[u8], value: Vec<u8>) -> Result<( )> {
+    let record= encrypt(&self.key, key, &value);
+    self.engine.set(key, record)
+  }
+
+  fn flush(&mut self) -> Result<( )> {
+    self.engine.flush( )
+  }
+
+  fn beginFlush(&mut self) -> Result<PendingFlush> {
+    self.engine.beginFlush( )
+  }
+
+  fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    self.engine.get(key)?
+               .map(|record| decryptRotating(&self.key, self.previousKey.as_ref( ), key, &record))
+               .transpose( )
+  }
+
+  fn delete(&mut self, key: &[u8]) -> Result<( )> {
+    self.engine.delete(key)
+  }
+
+  fn writeBatch(&mut self, batch: WriteBatch) -> Result<( )> {
+    let batch= batch.into_iter( )
+                    .map(|(key, value)| {
+                      let record= value.map(|value| encrypt(&self.key, &key, &value));
+                      (key, record)
+                    })
+                    .collect( );
+    self.engine.writeBatch(batch)
+  }
+
+  fn scan(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    let Self { engine, key: encryptionKey, previousKey }= self;
+    Box::new(engine.scan(range).map(|pair| decryptPair(encryptionKey, previousKey.as_ref( ), pair?)))
+  }
+
+  fn scanRev(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    let Self { engine, key: encryptionKey, previousKey }= self;
+    Box::new(engine.scanRev(range).map(|pair| decryptPair(encryptionKey, previousKey.as_ref( ), pair?)))
+  }
+
+  // NOTE : The sizes reported are of the encrypted values.
+  fn status(&self) -> Result<StorageEngineStatus> {
+    let status= self.engine.status( )?;
+    Ok(StorageEngineStatus { name: format!("encrypted({})", status.name), ..status })
+  }
+}
+
+fn decryptPair(encryptionKey: &EncryptionKey, previousKey: Option<&EncryptionKey>, (key, record): (Vec<u8>, Vec<u8>)) -> Result<(Vec<u8>, Vec<u8>)> {
+  let value= decryptRotating(encryptionKey, previousKey, &key, &record)?;
+  Ok((key, value))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{super::memory::Memory, *};
+
+  const UNBOUNDED: KeyRange= (Bound::Unbounded, Bound::Unbounded);
+
+  fn key(n: usize) -> Vec<u8> {
+    format!("key-{:05}", n).into_bytes( )
+  }
+
+  fn value(n: usize) -> Vec<u8> {
+    format!("plaintext value {}", n).into_bytes( )
+  }
+
+  // Returns an engine holding the given number of pairs, encrypted with the given key.
+  fn newEngine(encryptionKey: &EncryptionKey, pairs: usize) -> Result<EncryptedEngine<Memory>> {
+    let mut engine= EncryptedEngine::new(Memory::new( ), encryptionKey.clone( ));
+    engine.writeBatch((0..pairs).map(|n| (key(n), Some(value(n)))).collect( ))?;
+    Ok(engine)
+  }
+
+  fn pairs(range: impl Iterator<Item= usize>) -> Vec<(Vec<u8>, Vec<u8>)> {
+    range.map(|n| (key(n), value(n))).collect( )
+  }
+
+  #[test]
+  fn roundTripsValues( ) -> Result<( )> {
+    let mut engine= newEngine(&EncryptionKey::generate( ), 10)?;
+
+    engine.set(&key(10), value(10))?;
+    engine.delete(&key(0))?;
+    engine.writeBatch(vec![(key(1), None), (key(11), Some(value(11)))])?;
+
+    assert_eq!(engine.get(&key(5))?, Some(value(5)));
+    assert_eq!(engine.get(&key(0))?, None);
+
+    assert_eq!(engine.scan(UNBOUNDED).collect::<Result<Vec<_>>>( )?, pairs(2..12));
+    assert_eq!(engine.scanRev(UNBOUNDED).collect::<Result<Vec<_>>>( )?, pairs((2..12).rev( )));
+    Ok(( ))
+  }
+
+  #[test]
+  fn failsTamperedValuesWithCorruption( ) -> Result<( )> {
+    let encryptionKey= EncryptionKey::generate( );
+    let mut engine= newEngine(&encryptionKey, 3)?;
+
+    // A bit of the stored record is flipped.
+    let mut record= engine.engine.get(&key(0))?.unwrap( );
+    record[NONCE_SIZE] ^= 1;
+    engine.engine.set(&key(0), record)?;
+
+    // A record is moved to another key.
+    let record= engine.engine.get(&key(2))?.unwrap( );
+    engine.engine.set(&key(1), record)?;
+
+    for n in 0..2 {
+      assert!(matches!(engine.get(&key(n)), Err(Error::Corruption(_))), "key {}", n);}
+    assert!(matches!(engine.scan(UNBOUNDED).next( ), Some(Err(Error::Corruption(_)))));
+    assert_eq!(engine.get(&key(2))?, Some(value(2)));
+
+    // And so does reading with the wrong key.
+    let mut engine= EncryptedEngine::new(engine.engine, EncryptionKey::generate( ));
+    assert!(matches!(engine.get(&key(2)), Err(Error::Corruption(_))));
+    Ok(( ))
+  }
+
+  #[test]
+  fn storesNoPlaintext( ) -> Result<( )> {
+    let mut engine= newEngine(&EncryptionKey::generate( ), 100)?;
+    engine.set(&key(100), value(100))?;
+
+    // Not even the prefix which all the values share reaches the wrapped engine.
+    let prefix= b"plaintext";
+    for pair in engine.engine.scan(UNBOUNDED) {
+      let (key, record)= pair?;
+      assert!(!record.windows(prefix.len( )).any(|window| window == prefix), "value of key {:?}", key);
+    }
+    Ok(( ))
+  }
+
+  #[test]
+  fn completesInterruptedRotation( ) -> Result<( )> {
+    let (oldKey, newKey)= (EncryptionKey::generate( ), EncryptionKey::generate( ));
+    let size= 2*REWRAP_BATCH_SIZE + 10;
+    let mut engine= newEngine(&oldKey, size)?;
+
+    // The rotation is interrupted after rewrapping the first batch, like by a crash.
+    let rewrapped= engine.engine.scan(UNBOUNDED)
+                                .take(REWRAP_BATCH_SIZE)
+                                .map(|pair| pair.and_then(|(key, record)| Ok((key.clone( ), Some(encrypt(&newKey, &key, &decrypt(&oldKey, &key, &record)?))))))
+                                .collect::<Result<WriteBatch>>( )?;
+    engine.engine.writeBatch(rewrapped)?;
+
+    // Neither of the keys reads all the values on its own.
+    assert!(matches!(engine.get(&key(0)), Err(Error::Corruption(_))));
+
+    let mut engine= EncryptedEngine::new(engine.engine, newKey.clone( ));
+    assert!(matches!(engine.get(&key(size - 1)), Err(Error::Corruption(_))));
+
+    // Wrapped with both the keys, every value is read, while the writes use the new key.
+    let mut engine= EncryptedEngine::newRotating(engine.engine, oldKey.clone( ), newKey.clone( ));
+    assert_eq!(engine.scan(UNBOUNDED).collect::<Result<Vec<_>>>( )?, pairs(0..size));
+    engine.set(&key(size), value(size))?;
+
+    // Running the rotation again completes it, after which the values are read with just the new key.
+    engine.rewrap(&oldKey, newKey.clone( ))?;
+
+    let mut engine= EncryptedEngine::new(engine.engine, newKey);
+    assert_eq!(engine.scan(UNBOUNDED).collect::<Result<Vec<_>>>( )?, pairs(0..size + 1));
+
+    let mut engine= EncryptedEngine::new(engine.engine, oldKey);
+    assert!(matches!(engine.get(&key(0)), Err(Error::Corruption(_))));
+    Ok(( ))
+  }
+}
@@ -0,0 +1,255 @@
+use std::{fmt::Display, time::{Duration, Instant}};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, warn, Span};
+use crate::result::Result;
+use super::{KeyRange, ScanIterator, StorageEngine, StorageEngineStatus, WriteBatch};
+
+/*
+  Wraps a storage engine, counting the operations made on it and the bytes read and written, and
+  recording how long the flushes take. The metrics are reported along with the status of the engine
+  (see StorageEngineStatus::metrics).
+
+  The flushes (and the scans) are traced as well : they run within spans, and the ones slower than
+  SLOW_OPERATION_THRESHOLD are logged as warnings, with their durations.
+
+  NOTE : The metrics are kept in memory, so they start from zero when the node restarts.
+
+  NOTE : The flushes begun by beginFlush( ) are done whole right away (see StorageEngine), so that
+  they're timed. So, the writes to the engine wait for them.
+*/
+pub struct InstrumentedEngine<E: StorageEngine> {
+  engine: E,
+  metrics: StorageEngineMetrics
+}
+
+// A flush (or a scan) taking longer than this is logged as a warning.
+const SLOW_OPERATION_THRESHOLD: Duration= Duration::from_millis(100);
+
+// Upper bounds of the buckets of the flush latency histogram, along with their labels. Flushes slower
+// than the last bound go in an extra bucket.
+const FLUSH_LATENCY_BUCKETS: [(Duration, &str); 5]= [
+  (Duration::from_micros(100), "100us"),
+  (Duration::from_millis(1), "1ms"),
+  (Duration::from_millis(10), "10ms"),
+  (Duration::from_millis(100), "100ms"),
+  (Duration::from_secs(1), "1s")
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageEngineMetrics {
+  pub gets: u64,
+  pub sets: u64,
+  pub deletes: u64,
+  pub scans: u64,
+  pub flushes: u64,
+
+  // Number of write batches. Their writes are counted as sets and deletes too.
+  pub writeBatches: u64,
+
+  // Sizes of the keys and values read (only the values, for gets) and written (only the keys, for
+  // deletes).
+  pub bytesRead: u64,
+  pub bytesWritten: u64,
+
+  // Latencies of the flushes.
+  pub flushLatencies: LatencyHistogram
+}
+
+impl StorageEngineMetrics {
+  // Returns the metrics as (name, value) pairs, the histogram having a pair per bucket.
+  pub fn counters(&self) -> Vec<(String, u64)> {
+    let counters= [
+      ("gets", self.gets),
+      ("sets", self.sets),
+      ("deletes", self.deletes),
+      ("scans", self.scans),
+      ("flushes", self.flushes),
+      ("write_batches", self.writeBatches),
+      ("bytes_read", self.bytesRead),
+      ("bytes_written", self.bytesWritten)
+    ];
+
+    counters.into_iter( )
+            .map(|(name, value)| (name.to_string( ), value))
+            .chain(self.flushLatencies.buckets( ).into_iter( ).map(|(label, count)| (format!("flush_latency_{}", label), count)))
+            .collect( )
+  }
+}
+
+// Number of the recorded latencies falling in each bucket (see FLUSH_LATENCY_BUCKETS).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+  counts: Vec<u64>
+}
+
+impl Default for LatencyHistogram {
+  fn default( ) -> Self {
+    Self { counts: vec![0; FLUSH_LATENCY_BUCKETS.len( ) + 1] }
+  }
+}
+
+impl LatencyHistogram {
+  fn record(&mut self, latency: Duration) {
+    let bucket= FLUSH_LATENCY_BUCKETS.iter( )
+                                     .position(|(bound, _)| latency <= *bound)
+                                     .unwrap_or(FLUSH_LATENCY_BUCKETS.len( ));
+    self.counts[bucket] += 1;
+  }
+
+  // Returns the (label, count) pairs of the buckets, where a label like le_1ms stands for latencies
+  // upto 1 ms.
+  pub fn buckets(&self) -> Vec<(String, u64)> {
+    let labels= FLUSH_LATENCY_BUCKETS.iter( )
+                                     .map(|(_, label)| format!("le_{}", label))
+                                     .chain([format!("gt_{}", FLUSH_LATENCY_BUCKETS[FLUSH_LATENCY_BUCKETS.len( ) - 1].1)]);
+
+    labels.zip(self.counts.iter( ).copied( )).collect( )
+  }
+}
+
+impl<E: StorageEngine> InstrumentedEngine<E> {
+  pub fn new(engine: E) -> Self {
+    Self { engine, metrics: StorageEngineMetrics::default( ) }
+  }
+
+  pub fn metrics(&self) -> &StorageEngineMetrics {
+    &self.metrics
+  }
+
+  // Flushes within a span, and records the latency.
+  fn timeFlush(&mut self) -> Result<( )> {
+    let span= debug_span!("flush", engine= %self.engine);
+    let _entered= span.enter( );
+
+    let start= Instant::now( );
+    let result= self.engine.flush( );
+    let latency= start.elapsed( );
+
+    self.metrics.flushLatencies.record(latency);
+    match latency > SLOW_OPERATION_THRESHOLD {
+      true => warn!(?latency, "Slow flush"),
+      false => debug!(?latency, "Flushed")
+    }
+
+    result
+  }
+}
+
+impl<E: StorageEngine> Display for InstrumentedEngine<E> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    self.engine.fmt(f)
+  }
+}
+
+impl<E: StorageEngine> StorageEngine for InstrumentedEngine<E> {
+  fn set(&mut self, key: &[u8], value: Vec<u8>) -> Result<( )> {
+    self.metrics.sets += 1;
+    self.metrics.bytesWritten += (key.len( ) + value.len( )) as u64;
+
+    self.engine.set(key, value)
+  }
+
+  fn flush(&mut self) -> Result<( )> {
+    self.metrics.flushes += 1;
+    self.timeFlush( )
+  }
+
+  fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    let value= self.engine.get(key)?;
+
+    self.metrics.gets += 1;
+    self.metrics.bytesRead += value.as_ref( ).map_or(0, |value| value.len( ) as u64);
+
+    Ok(value)
+  }
+
+  fn delete(&mut self, key: &[u8]) -> Result<( )> {
+    self.metrics.deletes += 1;
+    self.metrics.bytesWritten += key.len( ) as u64;
+
+    self.engine.delete(key)
+  }
+
+  fn writeBatch(&mut self, batch: WriteBatch) -> Result<( )> {
+    self.metrics.writeBatches += 1;
+    for (key, value) in &batch {
+      match value {
+        Some(value) => {
+          self.metrics.sets += 1;
+          self.metrics.bytesWritten += (key.len( ) + value.len( )) as u64;
+        },
+        None => {
+          self.metrics.deletes += 1;
+          self.metrics.bytesWritten += key.len( ) as u64;
+        }
+      }
+    }
+
+    self.engine.writeBatch(batch)
+  }
+
+  fn scan(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    let span= debug_span!("scan", engine= %self.engine, reverse= false);
+
+    let Self { engine, metrics }= self;
+    metrics.scans += 1;
+
+    Box::new(InstrumentedScan::new(engine.scan(range), &mut metrics.bytesRead, span))
+  }
+
+  fn scanRev(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    let span= debug_span!("scan", engine= %self.engine, reverse= true);
+
+    let Self { engine, metrics }= self;
+    metrics.scans += 1;
+
+    Box::new(InstrumentedScan::new(engine.scanRev(range), &mut metrics.bytesRead, span))
+  }
+
+  fn status(&self) -> Result<StorageEngineStatus> {
+    Ok(StorageEngineStatus { metrics: Some(self.metrics.clone( )), ..self.engine.status( )? })
+  }
+}
+
+// Counts the bytes read by a scan. The scan is logged once it's dropped, along with how long it was
+// iterated over.
+struct InstrumentedScan<'a> {
+  scan: ScanIterator<'a>,
+  bytesRead: &'a mut u64,
+
+  span: Span,
+  start: Instant,
+  pairs: u64
+}
+
+impl<'a> InstrumentedScan<'a> {
+  fn new(scan: ScanIterator<'a>, bytesRead: &'a mut u64, span: Span) -> Self {
+    Self { scan, bytesRead, span, start: Instant::now( ), pairs: 0 }
+  }
+}
+
+impl Iterator for InstrumentedScan<'_> {
+  type Item= Result<(Vec<u8>, Vec<u8>)>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let item= self.span.in_scope(| | self.scan.next( ));
+
+    if let Some(Ok((key, value)))= &item {
+      self.pairs += 1;
+      *self.bytesRead += (key.len( ) + value.len( )) as u64;
+    }
+    item
+  }
+}
+
+impl Drop for InstrumentedScan<'_> {
+  fn drop(&mut self) {
+    let duration= self.start.elapsed( );
+    let pairs= self.pairs;
+
+    self.span.in_scope(| | match duration > SLOW_OPERATION_THRESHOLD {
+      true => warn!(pairs, ?duration, "Slow scan"),
+      false => debug!(pairs, ?duration, "Scanned")
+    });
+  }
+}
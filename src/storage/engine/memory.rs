@@ -0,0 +1,84 @@
+use std::{collections::BTreeMap, fmt::Display};
+use crate::result::Result;
+use super::{KeyRange, ScanIterator, StorageEngine, StorageEngineStatus, WriteBatch};
+
+/*
+  An in-memory storage engine backed by a BTreeMap (which keeps the keys sorted).
+
+  Nothing is persisted, so it's only useful for testing and for nodes which don't need their data
+  to survive restarts. flush( ) is a no-op.
+*/
+#[derive(Default)]
+pub struct Memory {
+  data: BTreeMap<Vec<u8>, Vec<u8>>
+}
+
+impl Memory {
+  pub fn new( ) -> Self {
+    Self::default( )
+  }
+}
+
+impl Display for Memory {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("memory")
+  }
+}
+
+impl StorageEngine for Memory {
+  fn set(&mut self, key: &[u8], value: Vec<u8>) -> Result<( )> {
+    self.data.insert(key.to_vec( ), value);
+    Ok(( ))
+  }
+
+  fn flush(&mut self) -> Result<( )> {
+    Ok(( ))
+  }
+
+  fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    Ok(self.data.get(key).cloned( ))
+  }
+
+  fn delete(&mut self, key: &[u8]) -> Result<( )> {
+    self.data.remove(key);
+    Ok(( ))
+  }
+
+  fn writeBatch(&mut self, batch: WriteBatch) -> Result<( )> {
+    for (key, value) in batch {
+      match value {
+        Some(value) => self.data.insert(key, value),
+        None => self.data.remove(&key)
+      };
+    }
+
+    Ok(( ))
+  }
+
+  fn scan(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    Box::new(self.data.range(range).map(|(key, value)| Ok((key.clone( ), value.clone( )))))
+  }
+
+  fn scanRev(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    Box::new(self.data.range(range).rev( ).map(|(key, value)| Ok((key.clone( ), value.clone( )))))
+  }
+
+  fn status(&self) -> Result<StorageEngineStatus> {
+    let logicalSize= self.data.iter( )
+                              .map(|(key, value)| (key.len( ) + value.len( )) as u64)
+                              .sum( );
+
+    Ok(StorageEngineStatus {
+      name: self.to_string( ),
+      keyCount: self.data.len( ) as u64,
+      logicalSize,
+
+      // Nothing is stored on disk.
+      diskSize: 0,
+      garbageDiskSize: 0,
+      totalDiskSize: 0,
+
+      metrics: None
+    })
+  }
+}
@@ -1,5 +1,13 @@
-use std::fmt::Display;
+use std::{fmt::Display, ops::Bound};
+use serde::{Deserialize, Serialize};
 use crate::result::Result;
+use self::instrumented::StorageEngineMetrics;
+
+pub mod buffered;
+pub mod chacha20poly1305;
+pub mod encrypted;
+pub mod instrumented;
+pub mod memory;
 
 /*
   Represents a KV storage engine, where both keys and values are arbitrary byte strings between
@@ -9,7 +17,8 @@ use crate::result::Result;
   NOTE : Lexicographical ordering is essentially alphabetical ordering, but for byte strings. The
   keys will be arranged in ascending order based on their byte values.
 
-  Writes are only guaranteed durable, after they're flushed (using flush( )).
+  Writes are only guaranteed durable, after they're flushed (using flush( )). The stores on top
+  decide when to flush, according to their sync policy (see storage::sync::Syncer).
 */
 pub trait StorageEngine
   : Display + Send + Sync
@@ -21,6 +30,18 @@ pub trait StorageEngine
   // Flushes any buffered (in-memory) data to the underlying storage medium.
   fn flush(&mut self) -> Result<( )>;
 
+  /*
+    Begins flushing the writes applied so far, and returns the rest of the flush : the slow part of
+    it (like the fsync of a duplicated file handle), which runs without borrowing the engine. So,
+    the writes can go on while it runs - letting the concurrent commits share a flush (see Syncer).
+
+    By default, the whole flush is done right away.
+  */
+  fn beginFlush(&mut self) -> Result<PendingFlush> {
+    self.flush( )?;
+    Ok(Box::new(| | Ok(( ))))
+  }
+
   // Returns the value stored against the given key (if it exists).
   fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>>;
 
@@ -28,10 +49,44 @@ pub trait StorageEngine
   // NOTE : Does nothing if the key doesn't exist.
   fn delete(&mut self, key: &[u8]) -> Result<( )>;
 
+  // Applies the operations of the batch in order, atomically. Like the other writes, they're durable
+  // once flushed.
+  fn writeBatch(&mut self, batch: WriteBatch) -> Result<( )>;
+
+  // Iterates over the key-value pairs whose keys lie in the given range, in ascending key order.
+  fn scan(&mut self, range: KeyRange) -> ScanIterator<'_>;
+
+  // Iterates over the key-value pairs whose keys lie in the given range, in descending key order.
+  fn scanRev(&mut self, range: KeyRange) -> ScanIterator<'_>;
+
   // Returns the status of the storage engine.
   fn status(&self) -> Result<StorageEngineStatus>;
 }
 
+// Represents a range of keys. It's a (start bound, end bound) pair, which can directly be used as
+// a RangeBounds by the BTreeMap based engines.
+pub type KeyRange= (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+// A batch of writes : key-value pairs to be stored, where a None value deletes the key.
+pub type WriteBatch= Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+// The rest of a flush, begun by StorageEngine::beginFlush( ).
+pub type PendingFlush= Box<dyn FnOnce( ) -> Result<( )> + Send>;
+
+// The order in which a scan iterates over the keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Direction {
+  // In ascending key order.
+  #[default]
+  Forward,
+
+  // In descending key order.
+  Reverse
+}
+
+pub type ScanIterator<'a>= Box<dyn Iterator<Item= Result<(Vec<u8>, Vec<u8>)>> + 'a>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StorageEngineStatus {
   pub name: String,
 
@@ -50,5 +105,8 @@ pub struct StorageEngineStatus {
 
   pub diskSize: u64, // On-disk size of live (usefull) key-value pairs.
   pub garbageDiskSize: u64,
-  pub totalDiskSize: u64
-}
\ No newline at end of file
+  pub totalDiskSize: u64,
+
+  // Metrics of the operations made on the engine, if it's instrumented (see InstrumentedEngine).
+  pub metrics: Option<StorageEngineMetrics>
+}
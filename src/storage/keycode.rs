@@ -0,0 +1,529 @@
+use std::ops::Bound;
+use serde::{
+  de::{DeserializeSeed, EnumAccess, IntoDeserializer, SeqAccess, VariantAccess, Visitor},
+  ser::{Impossible, SerializeSeq, SerializeTuple, SerializeTupleVariant},
+  Deserialize, Serialize
+};
+use crate::result::{Error, Result};
+use super::engine::KeyRange;
+
+/*
+  An order-preserving encoding for keys : the lexicographical (byte-wise) order of the encoded keys
+  matches the logical order of the original keys. This lets the storage engines (which order keys
+  byte-wise) keep structured keys (like MVCC versioned keys or SQL primary keys) in their logical
+  order, and lets us scan all the keys sharing a prefix.
+
+  The encoding is implemented as a serde (de)serializer and supports these types :
+
+    bool : 0x00 for false, 0x01 for true.
+
+    u64 : big-endian bytes.
+
+    i64 : big-endian bytes, with the sign bit flipped (so negative numbers come before positive
+    ones).
+
+    f64 : big-endian bytes, with the sign bit flipped for positive numbers and all the bits flipped
+    for negative numbers. -0.0 is encoded as 0.0, and every NaN as the same positive NaN. So the
+    floats are totally ordered as -inf < negatives < -0.0 = 0.0 < positives < +inf < NaN, just like
+    SQL values.
+
+    Vec<u8> (using serde_bytes) and String : 0x00 is escaped as 0x00 0xff, and the value is
+    terminated with 0x00 0x00. This way, a shorter value comes before a longer one which it's a
+    prefix of.
+
+    Enum variants : the variant index as a single byte, followed by the fields (if any). So the
+    variants are ordered as they're declared.
+
+    Tuples and sequences : the elements concatenated together, without any length prefix.
+*/
+
+pub fn serialize<T: Serialize>(key: &T) -> Result<Vec<u8>> {
+  let mut serializer= Serializer { output: vec![ ] };
+  key.serialize(&mut serializer)?;
+
+  Ok(serializer.output)
+}
+
+pub fn deserialize<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
+  let mut deserializer= Deserializer { input };
+  let key= T::deserialize(&mut deserializer)?;
+
+  if !deserializer.input.is_empty( ) {
+    return Err(Error::Internal(format!(
+      "Found {} unexpected trailing bytes while decoding key", deserializer.input.len( )
+    )))}
+
+  Ok(key)
+}
+
+// Returns the range of keys starting with the given (encoded) prefix.
+pub fn prefixRange(prefix: &[u8]) -> KeyRange {
+  let start= Bound::Included(prefix.to_vec( ));
+
+  // The end bound is the prefix, with its last byte (which isn't 0xff) incremented. If all the bytes
+  // are 0xff, then there's no end bound.
+  let end= match prefix.iter( ).rposition(|byte| *byte != 0xff) {
+    Some(position) => {
+      let mut end= prefix[..=position].to_vec( );
+      end[position] += 1;
+      Bound::Excluded(end)
+    },
+
+    None => Bound::Unbounded
+  };
+
+  (start, end)
+}
+
+struct Serializer {
+  output: Vec<u8>
+}
+
+impl Serializer {
+  fn unsupported<T>(typeName: &str) -> Result<T> {
+    Err(Error::Internal(format!("Key encoding doesn't support {}", typeName)))
+  }
+}
+
+impl serde::Serializer for &mut Serializer {
+  type Ok= ( );
+  type Error= Error;
+
+  type SerializeSeq= Self;
+  type SerializeTuple= Self;
+  type SerializeTupleStruct= Impossible<( ), Error>;
+  type SerializeTupleVariant= Self;
+  type SerializeMap= Impossible<( ), Error>;
+  type SerializeStruct= Impossible<( ), Error>;
+  type SerializeStructVariant= Impossible<( ), Error>;
+
+  fn serialize_bool(self, v: bool) -> Result<( )> {
+    self.output.push(v as u8);
+    Ok(( ))
+  }
+
+  fn serialize_i8(self, _: i8) -> Result<( )> { Serializer::unsupported("i8") }
+  fn serialize_i16(self, _: i16) -> Result<( )> { Serializer::unsupported("i16") }
+  fn serialize_i32(self, _: i32) -> Result<( )> { Serializer::unsupported("i32") }
+
+  fn serialize_i64(self, v: i64) -> Result<( )> {
+    let mut bytes= v.to_be_bytes( );
+    bytes[0] ^= 1 << 7; // Flip the sign bit.
+
+    self.output.extend(bytes);
+    Ok(( ))
+  }
+
+  fn serialize_u8(self, _: u8) -> Result<( )> { Serializer::unsupported("u8") }
+  fn serialize_u16(self, _: u16) -> Result<( )> { Serializer::unsupported("u16") }
+  fn serialize_u32(self, _: u32) -> Result<( )> { Serializer::unsupported("u32") }
+
+  fn serialize_u64(self, v: u64) -> Result<( )> {
+    self.output.extend(v.to_be_bytes( ));
+    Ok(( ))
+  }
+
+  fn serialize_f32(self, _: f32) -> Result<( )> { Serializer::unsupported("f32") }
+
+  fn serialize_f64(self, v: f64) -> Result<( )> {
+    // The sign of f64::NAN isn't guaranteed, so the canonical NaN is built from its bits.
+    let v= match v {
+      v if v.is_nan( ) => f64::from_bits(0x7ff8_0000_0000_0000),
+      0.0 => 0.0, // Matches -0.0 as well.
+      v => v
+    };
+
+    let mut bytes= v.to_be_bytes( );
+
+    match v.is_sign_negative( ) {
+      false => bytes[0] ^= 1 << 7, // Flip the sign bit.
+      true => bytes.iter_mut( ).for_each(|byte| *byte= !*byte) // Flip all the bits.
+    }
+
+    self.output.extend(bytes);
+    Ok(( ))
+  }
+
+  fn serialize_char(self, _: char) -> Result<( )> { Serializer::unsupported("char") }
+
+  fn serialize_str(self, v: &str) -> Result<( )> {
+    self.serialize_bytes(v.as_bytes( ))
+  }
+
+  fn serialize_bytes(self, v: &[u8]) -> Result<( )> {
+    for byte in v {
+      match byte {
+        0x00 => self.output.extend([0x00, 0xff]),
+        byte => self.output.push(*byte)
+      }
+    }
+
+    self.output.extend([0x00, 0x00]);
+    Ok(( ))
+  }
+
+  fn serialize_none(self) -> Result<( )> { Serializer::unsupported("Option") }
+
+  fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<( )> { Serializer::unsupported("Option") }
+
+  fn serialize_unit(self) -> Result<( )> { Serializer::unsupported("( )") }
+
+  fn serialize_unit_struct(self, _: &'static str) -> Result<( )> { Serializer::unsupported("unit struct") }
+
+  fn serialize_unit_variant(self, _: &'static str, variantIndex: u32, _: &'static str) -> Result<( )> {
+    let variantIndex= u8::try_from(variantIndex)
+                        .map_err(|_| Error::Internal(format!("Enum variant index {} is too large", variantIndex)))?;
+
+    self.output.push(variantIndex);
+    Ok(( ))
+  }
+
+  fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T) -> Result<( )> {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T: ?Sized + Serialize>(self,
+                                                      name: &'static str,
+                                                      variantIndex: u32,
+                                                      variant: &'static str,
+                                                      value: &T) -> Result<( )>
+  {
+    self.serialize_unit_variant(name, variantIndex, variant)?;
+    value.serialize(self)
+  }
+
+  fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
+    Ok(self)
+  }
+
+  fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple> {
+    Ok(self)
+  }
+
+  fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct> {
+    Serializer::unsupported("tuple struct")
+  }
+
+  fn serialize_tuple_variant(self,
+                             name: &'static str,
+                             variantIndex: u32,
+                             variant: &'static str,
+                             _: usize) -> Result<Self::SerializeTupleVariant>
+  {
+    self.serialize_unit_variant(name, variantIndex, variant)?;
+    Ok(self)
+  }
+
+  fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
+    Serializer::unsupported("map")
+  }
+
+  fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
+    Serializer::unsupported("struct")
+  }
+
+  fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeStructVariant> {
+    Serializer::unsupported("struct variant")
+  }
+}
+
+impl SerializeSeq for &mut Serializer {
+  type Ok= ( );
+  type Error= Error;
+
+  fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<( )> {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<( )> {
+    Ok(( ))
+  }
+}
+
+impl SerializeTuple for &mut Serializer {
+  type Ok= ( );
+  type Error= Error;
+
+  fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<( )> {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<( )> {
+    Ok(( ))
+  }
+}
+
+impl SerializeTupleVariant for &mut Serializer {
+  type Ok= ( );
+  type Error= Error;
+
+  fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<( )> {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<( )> {
+    Ok(( ))
+  }
+}
+
+struct Deserializer<'de> {
+  input: &'de [u8]
+}
+
+impl<'de> Deserializer<'de> {
+  fn unsupported<T>(typeName: &str) -> Result<T> {
+    Err(Error::Internal(format!("Key decoding doesn't support {}", typeName)))
+  }
+
+  // Consumes and returns the next n bytes.
+  fn take(&mut self, n: usize) -> Result<&'de [u8]> {
+    if self.input.len( ) < n {
+      return Err(Error::Internal(format!(
+        "Expected {} more bytes while decoding key, found {}", n, self.input.len( )
+      )))}
+
+    let (bytes, remaining)= self.input.split_at(n);
+    self.input= remaining;
+
+    Ok(bytes)
+  }
+
+  fn takeArray<const N: usize>(&mut self) -> Result<[u8; N]> {
+    let mut bytes= [0; N];
+    bytes.copy_from_slice(self.take(N)?);
+
+    Ok(bytes)
+  }
+
+  // Consumes and returns an escaped and terminated byte string.
+  fn takeBytes(&mut self) -> Result<Vec<u8>> {
+    let mut bytes= vec![ ];
+
+    loop {
+      match self.take(1)?[0] {
+        0x00 => match self.take(1)?[0] {
+          0x00 => return Ok(bytes),
+          0xff => bytes.push(0x00),
+          byte => return Err(Error::Internal(format!("Invalid escape sequence 0x00 {:#04x} in key", byte)))
+        },
+
+        byte => bytes.push(byte)
+      }
+    }
+  }
+}
+
+impl<'de> serde::Deserializer<'de> for &mut Deserializer<'de> {
+  type Error= Error;
+
+  fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value> {
+    Deserializer::unsupported("self-describing types")
+  }
+
+  fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    match self.take(1)?[0] {
+      0x00 => visitor.visit_bool(false),
+      0x01 => visitor.visit_bool(true),
+      byte => Err(Error::Internal(format!("Invalid boolean {:#04x} in key", byte)))
+    }
+  }
+
+  fn deserialize_i8<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("i8") }
+  fn deserialize_i16<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("i16") }
+  fn deserialize_i32<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("i32") }
+
+  fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    let mut bytes= self.takeArray::<8>( )?;
+    bytes[0] ^= 1 << 7;
+
+    visitor.visit_i64(i64::from_be_bytes(bytes))
+  }
+
+  fn deserialize_u8<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("u8") }
+  fn deserialize_u16<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("u16") }
+  fn deserialize_u32<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("u32") }
+
+  fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    visitor.visit_u64(u64::from_be_bytes(self.takeArray::<8>( )?))
+  }
+
+  fn deserialize_f32<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("f32") }
+
+  fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    let mut bytes= self.takeArray::<8>( )?;
+
+    // The sign bit of a positive number was flipped (to 1).
+    match bytes[0] >> 7 {
+      1 => bytes[0] ^= 1 << 7,
+      _ => bytes.iter_mut( ).for_each(|byte| *byte= !*byte)
+    }
+
+    visitor.visit_f64(f64::from_be_bytes(bytes))
+  }
+
+  fn deserialize_char<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("char") }
+
+  fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    self.deserialize_string(visitor)
+  }
+
+  fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    let bytes= self.takeBytes( )?;
+    let string= String::from_utf8(bytes)
+                  .map_err(|error| Error::Internal(format!("Invalid UTF-8 string in key : {}", error)))?;
+
+    visitor.visit_string(string)
+  }
+
+  fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    self.deserialize_byte_buf(visitor)
+  }
+
+  fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    visitor.visit_byte_buf(self.takeBytes( )?)
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("Option") }
+
+  fn deserialize_unit<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("( )") }
+
+  fn deserialize_unit_struct<V: Visitor<'de>>(self, _: &'static str, _: V) -> Result<V::Value> {
+    Deserializer::unsupported("unit struct")
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value> {
+    visitor.visit_newtype_struct(self)
+  }
+
+  // A sequence spans the rest of the input (since it isn't length prefixed).
+  fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    visitor.visit_seq(SequenceAccess { deserializer: self, remaining: None })
+  }
+
+  fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+    visitor.visit_seq(SequenceAccess { deserializer: self, remaining: Some(len) })
+  }
+
+  fn deserialize_tuple_struct<V: Visitor<'de>>(self, _: &'static str, _: usize, _: V) -> Result<V::Value> {
+    Deserializer::unsupported("tuple struct")
+  }
+
+  fn deserialize_map<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("map") }
+
+  fn deserialize_struct<V: Visitor<'de>>(self, _: &'static str, _: &'static [&'static str], _: V) -> Result<V::Value> {
+    Deserializer::unsupported("struct")
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(self, _: &'static str, _: &'static [&'static str], visitor: V) -> Result<V::Value> {
+    visitor.visit_enum(self)
+  }
+
+  fn deserialize_identifier<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("identifier") }
+
+  fn deserialize_ignored_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value> { Deserializer::unsupported("ignored any") }
+}
+
+struct SequenceAccess<'a, 'de> {
+  deserializer: &'a mut Deserializer<'de>,
+
+  // Number of elements remaining, for tuples. A sequence has no fixed length.
+  remaining: Option<usize>
+}
+
+impl<'de, 'a> SeqAccess<'de> for SequenceAccess<'a, 'de> {
+  type Error= Error;
+
+  fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+    match &mut self.remaining {
+      Some(0) => return Ok(None),
+      Some(remaining) => *remaining -= 1,
+
+      None =>
+        if self.deserializer.input.is_empty( ) {
+          return Ok(None)}
+    }
+
+    seed.deserialize(&mut *self.deserializer).map(Some)
+  }
+}
+
+impl<'de> EnumAccess<'de> for &mut Deserializer<'de> {
+  type Error= Error;
+  type Variant= Self;
+
+  fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+    let variantIndex= self.take(1)?[0] as u32;
+    let variant= seed.deserialize(IntoDeserializer::<Error>::into_deserializer(variantIndex))?;
+
+    Ok((variant, self))
+  }
+}
+
+impl<'de> VariantAccess<'de> for &mut Deserializer<'de> {
+  type Error= Error;
+
+  fn unit_variant(self) -> Result<( )> {
+    Ok(( ))
+  }
+
+  fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+    seed.deserialize(self)
+  }
+
+  fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+    visitor.visit_seq(SequenceAccess { deserializer: self, remaining: Some(len) })
+  }
+
+  fn struct_variant<V: Visitor<'de>>(self, _: &'static [&'static str], _: V) -> Result<V::Value> {
+    Deserializer::unsupported("struct variant")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use rand::{rngs::StdRng, Rng, SeedableRng};
+  use crate::sql::types::Value;
+  use super::*;
+
+  // Floats whose order is easy to get wrong.
+  const SPECIAL_FLOATS: [f64; 12]= [
+    f64::NAN, -f64::NAN, 0.0, -0.0, f64::INFINITY, f64::NEG_INFINITY, f64::MIN_POSITIVE,
+    -f64::MIN_POSITIVE, f64::MAX, f64::MIN, 1.0, -1.0
+  ];
+
+  // Returns a special float, a float of random bits (which may be a NaN of any payload or a
+  // subnormal), or a float of a small random magnitude.
+  fn randomFloat(rng: &mut StdRng) -> f64 {
+    match rng.gen_range(0..3) {
+      0 => SPECIAL_FLOATS[rng.gen_range(0..SPECIAL_FLOATS.len( ))],
+      1 => f64::from_bits(rng.gen( )),
+      _ => rng.gen_range(-100.0..100.0)
+    }
+  }
+
+  #[test]
+  fn floatKeysAreOrderedLikeValues( ) -> Result<( )> {
+    let mut rng= StdRng::seed_from_u64(1057);
+
+    for _ in 0..10_000 {
+      let (lhs, rhs)= (Value::Float(randomFloat(&mut rng)), Value::Float(randomFloat(&mut rng)));
+      let (lhsKey, rhsKey)= (serialize(&lhs)?, serialize(&rhs)?);
+
+      assert_eq!(lhsKey.cmp(&rhsKey), lhs.cmp(&rhs), "{:?} and {:?}", lhs, rhs);
+      assert_eq!(deserialize::<Value>(&lhsKey)?, lhs);
+    }
+    Ok(( ))
+  }
+
+  #[test]
+  fn zerosAndNaNsHaveSingleKey( ) -> Result<( )> {
+    assert_eq!(serialize(&Value::Float(-0.0))?, serialize(&Value::Float(0.0))?);
+    assert_eq!(serialize(&Value::Float(-f64::NAN))?, serialize(&Value::Float(f64::NAN))?);
+    assert_eq!(serialize(&Value::Float(f64::from_bits(0x7ff0_0000_0000_0001)))?, serialize(&Value::Float(f64::NAN))?);
+
+    // NaN comes last.
+    assert!(serialize(&Value::Float(f64::INFINITY))? < serialize(&Value::Float(f64::NAN))?);
+    assert!(serialize(&Value::Float(f64::NEG_INFINITY))? < serialize(&Value::Float(f64::MIN))?);
+    Ok(( ))
+  }
+}
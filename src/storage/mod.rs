@@ -1 +1,5 @@
-pub mod engine;
\ No newline at end of file
+pub mod engine;
+pub mod data_dir;
+pub mod keycode;
+pub mod mvcc;
+pub mod sync;
@@ -0,0 +1,922 @@
+use std::{collections::HashSet, ops::Bound, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, MutexGuard}, thread};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use crate::result::{Error, ErrorCode, Result};
+use super::{
+  engine::{buffered::Buffered, Direction, KeyRange, StorageEngine, StorageEngineStatus, WriteBatch},
+  keycode,
+  sync::{SyncPolicy, SyncStatus, Syncer}
+};
+
+/*
+  MVCC (Multi-Version Concurrency Control) provides ACID transactions with snapshot isolation, on
+  top of a storage engine.
+
+  Every read-write transaction gets a unique and monotonically increasing version. Instead of
+  overwriting a key, a transaction writes a new version of it : the key suffixed with the
+  transaction's version. A transaction only sees the versions written by transactions which had
+  committed before it began (and its own writes). It takes a snapshot of the set of transactions
+  active at that moment, and ignores the versions written by them (or by any later transaction).
+
+  Two concurrent transactions writing the same key would lead to a lost update. So if a transaction
+  tries to write a key, which has a version invisible to it (written by a concurrent or a later
+  transaction), then the write fails with Error::Serialization and the transaction should be
+  retried.
+
+  So the isolation level is snapshot isolation, with these guarantees (for concurrent transactions
+  T1 and T2) :
+
+    No dirty reads : T2 doesn't see the writes of T1 until T1 commits - and not even then, if T2
+    began before that.
+
+    No non-repeatable (or phantom) reads : T2 reads from the same snapshot throughout, whatever T1
+    commits meanwhile.
+
+    First writer wins : if T1 writes a key first, then T2 writing that key fails right away (even
+    if T1 hasn't committed yet, or never does). Conflicts are detected on writes rather than on
+    commits, so a transaction never blocks on another one, and deadlocks can't happen. Since a
+    rolled back transaction removes its versions, retrying T2 then succeeds.
+
+  Write skew is permitted : if T1 and T2 both read keys A and B, and T1 writes A while T2 writes B
+  (say, each based on the sum of A and B), then both commit - though no serial order of them would
+  have produced that result.
+
+  A transaction keeps track of the keys it wrote, so that the writes can be undone if it's rolled
+  back.
+
+  The uncommitted writes of a transaction are capped (see WriteLimits), so that a single huge
+  transaction can't pile up versions (and write records) without bounds. Exceeding the limits fails
+  the write, and the transaction is expected to be rolled back.
+
+  The commits are flushed according to the sync policy (see Syncer). Under the interval policy, a
+  background thread flushes them, for as long as the store exists.
+*/
+pub struct MVCC<E: StorageEngine> {
+  engine: Arc<Mutex<Buffered<E>>>,
+  metrics: Arc<Metrics>,
+  syncer: Arc<Syncer>,
+
+  // Limits of the read-write transactions begun from now on.
+  writeLimits: WriteLimits
+}
+
+// Counters of the MVCC layer, reported along with its status. They're kept in memory, so they start
+// from zero when the node restarts.
+#[derive(Default)]
+struct Metrics {
+  // Number of writes which failed with Error::Serialization.
+  conflicts: AtomicU64,
+
+  // Number of versions removed by garbage collection.
+  gcVersions: AtomicU64
+}
+
+pub type Version= u64;
+
+// A key-value pair, along with the version the value was written at.
+pub type VersionedPair= (Vec<u8>, Version, Vec<u8>);
+
+// Keys used by the MVCC layer. They're encoded using the order-preserving key encoding, so that the
+// versions of a key are stored contiguously and in ascending order.
+#[derive(Debug, Serialize, Deserialize)]
+enum Key {
+  // The version to be assigned to the next read-write transaction.
+  NextVersion,
+
+  // Marks a read-write transaction as active (uncommitted). The value is the WriteTally of the
+  // transaction (empty, until it writes).
+  TxnActive(Version),
+
+  // The set of transactions which were active, when a read-write transaction began. Used to
+  // reconstruct the visibility of past versions (for time-travel reads).
+  TxnActiveSnapshot(Version),
+
+  // Records a key written by a transaction, so that the write can be undone on rollback.
+  TxnWrite(Version, #[serde(with= "serde_bytes")] Vec<u8>),
+
+  // A version of a user key. The value is a serialized Option<Vec<u8>>, where None is a tombstone
+  // (marking the key as deleted).
+  Version(#[serde(with= "serde_bytes")] Vec<u8>, Version),
+
+  // A user key, which isn't versioned (like metadata which is written outside transactions).
+  Unversioned(#[serde(with= "serde_bytes")] Vec<u8>),
+
+  // Number of active read-only transactions, whose oldest invisible version is the given version.
+  // Used to prevent garbage collection from removing versions visible to them.
+  TxnReadOnly(Version)
+}
+
+impl Key {
+  fn encode(&self) -> Result<Vec<u8>> {
+    keycode::serialize(self)
+  }
+}
+
+// Prefixes of the MVCC keys, used for prefix scans. Must match the variants of Key.
+#[derive(Debug, Serialize)]
+enum KeyPrefix {
+  NextVersion,
+  TxnActive,
+  TxnActiveSnapshot,
+  TxnWrite(Version),
+  Version(#[serde(with= "serde_bytes")] Vec<u8>),
+  Unversioned,
+  TxnReadOnly
+}
+
+impl KeyPrefix {
+  fn encode(&self) -> Result<Vec<u8>> {
+    keycode::serialize(self)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MVCCStatus {
+  // Number of read-write transactions which have begun so far.
+  pub versions: u64,
+
+  // Number of active (uncommitted) read-write transactions.
+  pub activeTxns: u64,
+
+  // Number of writes which failed due to a conflicting concurrent write, and number of versions
+  // removed by garbage collection, since the node started.
+  pub conflicts: u64,
+  pub gcVersions: u64,
+
+  // The sync policy, along with the counters of the commits and the flushes.
+  pub sync: SyncStatus,
+
+  // Status of the underlying storage engine.
+  pub storage: StorageEngineStatus
+}
+
+/*
+  Limits on the uncommitted writes of a read-write transaction : the number of distinct keys it
+  writes, and the total size (in bytes) of the keys and values it writes. Overwriting a key counts
+  its size again.
+
+  The limits are carried in the state of the transaction. So when the transaction is resumed on
+  another replica (like by the Raft engine), that replica enforces the same limits.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WriteLimits {
+  pub maxKeys: u64,
+  pub maxBytes: u64
+}
+
+impl Default for WriteLimits {
+  fn default( ) -> Self {
+    Self { maxKeys: 1_000_000, maxBytes: 256 * 1024 * 1024 }
+  }
+}
+
+// The uncommitted writes of a transaction, counted against its write limits.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WriteTally {
+  keys: u64,
+  bytes: u64
+}
+
+impl<E: StorageEngine> Clone for MVCC<E> {
+  fn clone(&self) -> Self {
+    Self { engine: self.engine.clone( ), metrics: self.metrics.clone( ), syncer: self.syncer.clone( ), writeLimits: self.writeLimits }
+  }
+}
+
+impl<E: StorageEngine + 'static> MVCC<E> {
+  pub fn new(engine: E, writeLimits: WriteLimits, syncPolicy: SyncPolicy) -> Self {
+    let mvcc= Self {
+      engine: Arc::new(Mutex::new(Buffered::new(engine))),
+      metrics: Arc::default( ),
+      syncer: Arc::new(Syncer::new(syncPolicy)),
+      writeLimits
+    };
+
+    if let SyncPolicy::Interval(_)= syncPolicy {
+      mvcc.spawnSyncThread( );}
+    mvcc
+  }
+
+  // Spawns the thread flushing the commits under the interval policy. It holds weak references, so
+  // that it exits once the store is dropped.
+  fn spawnSyncThread(&self) {
+    let (engine, syncer)= (Arc::downgrade(&self.engine), Arc::downgrade(&self.syncer));
+
+    thread::spawn(move || {
+      while let Some(untilDue)= syncer.upgrade( ).and_then(|syncer| syncer.untilFlushDue( ).ok( ).flatten( )) {
+        thread::sleep(untilDue);
+
+        let (Some(engine), Some(syncer))= (engine.upgrade( ), syncer.upgrade( )) else {
+          break
+        };
+        if let Err(error)= syncer.flushIfDue(| | flushEngine(&engine)) {
+          error!("Failed flushing the SQL data : {}", error);}
+      }
+    });
+  }
+}
+
+impl<E: StorageEngine> MVCC<E> {
+
+  // Begins a new read-write transaction.
+  pub fn begin(&self) -> Result<Transaction<E>> {
+    Transaction::begin(self.engine.clone( ), self.metrics.clone( ), self.syncer.clone( ), self.writeLimits)
+  }
+
+  // Begins a new read-only transaction, which sees the latest committed versions.
+  pub fn beginReadOnly(&self) -> Result<Transaction<E>> {
+    Transaction::beginReadOnly(self.engine.clone( ), self.metrics.clone( ), self.syncer.clone( ), None)
+  }
+
+  // Begins a new read-only transaction, which sees the database as it was when the given version
+  // began.
+  pub fn beginAsOf(&self, version: Version) -> Result<Transaction<E>> {
+    Transaction::beginReadOnly(self.engine.clone( ), self.metrics.clone( ), self.syncer.clone( ), Some(version))
+  }
+
+  /*
+    Returns the state of a read-only transaction (seeing the latest committed versions, or the
+    versions as of the given version), without beginning it : unlike beginReadOnly( ), nothing gets
+    written to the store. The transaction can then be resumed from the state, but must not be
+    committed or rolled back.
+
+    NOTE : Since the transaction doesn't hold back garbage collection, the versions it sees may get
+    removed while it's in use.
+  */
+  pub fn readOnlyState(&self, asOf: Option<Version>) -> Result<TransactionState> {
+    readOnlyState(&mut *lockEngine(&self.engine)?, asOf)
+  }
+
+  // Resumes a transaction, from its state.
+  pub fn resume(&self, state: TransactionState) -> Result<Transaction<E>> {
+    Transaction::resume(self.engine.clone( ), self.metrics.clone( ), self.syncer.clone( ), state)
+  }
+
+  // Returns the value of an unversioned key.
+  pub fn getUnversioned(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    lockEngine(&self.engine)?.get(&Key::Unversioned(key.to_vec( )).encode( )?)
+  }
+
+  // Sets the value of an unversioned key.
+  pub fn setUnversioned(&self, key: &[u8], value: Vec<u8>) -> Result<( )> {
+    lockEngine(&self.engine)?.set(&Key::Unversioned(key.to_vec( )).encode( )?, value)
+  }
+
+  /*
+    Runs the operation, making all the writes it does (through the MVCC store or its transactions)
+    atomic : they're buffered, and written to the storage engine as a single write batch once the
+    operation succeeds. If the operation fails, none of them are written.
+
+    NOTE : Writes done meanwhile by other threads end up in the same batch. Operations can't be
+    nested.
+  */
+  pub fn atomically<T>(&self, operation: impl FnOnce( ) -> Result<T>) -> Result<T> {
+    lockEngine(&self.engine)?.begin( )?;
+
+    match operation( ) {
+      Ok(value) => {
+        lockEngine(&self.engine)?.commit( )?;
+        Ok(value)
+      },
+
+      Err(error) => {
+        lockEngine(&self.engine)?.discard( );
+        Err(error)
+      }
+    }
+  }
+
+  /*
+    Garbage collects the versions, which no transaction (active or future) can see anymore : for
+    each key, only the latest version older than the GC horizon is kept (or none, if it's a
+    tombstone). The GC horizon is the given version, or the oldest version invisible to any active
+    transaction, if that's older.
+
+    NOTE : Time-travel reads as of a version before the given version, may not see the database as
+    it was.
+  */
+  pub fn gc(&self, beforeVersion: Version) -> Result<GCStatus> {
+    let mut engine= lockEngine(&self.engine)?;
+
+    let mut horizon= beforeVersion;
+
+    // Versions invisible to active read-write transactions.
+    for version in scanActiveVersions(&mut *engine)? {
+      horizon= horizon.min(version);
+
+      if let Some(activeVersions)= engine.get(&Key::TxnActiveSnapshot(version).encode( )?)? {
+        let activeVersions: HashSet<Version>= bincode::deserialize(&activeVersions)?;
+        horizon= activeVersions.into_iter( ).fold(horizon, Version::min);
+      }
+    }
+
+    // Versions invisible to active read-only transactions.
+    for item in engine.scan(keycode::prefixRange(&KeyPrefix::TxnReadOnly.encode( )?)) {
+      let (key, _)= item?;
+
+      match keycode::deserialize(&key)? {
+        Key::TxnReadOnly(version) => horizon= horizon.min(version),
+        key => return Err(Error::Internal(format!("Expected TxnReadOnly key, found {:?}", key)))
+      }
+    }
+
+    let range= (
+      Bound::Included(Key::Version(vec![ ], 0).encode( )?),
+      Bound::Excluded(KeyPrefix::Unversioned.encode( )?)
+    );
+
+    // Collect the versions to be removed : for each key, all the versions older than the horizon,
+    // except the latest one (which is removed too, if it's a tombstone).
+    let mut garbage= vec![ ];
+    let mut latest: Option<(Vec<u8>, Vec<u8>, bool)>= None; // (user key, versioned key, is tombstone)
+
+    for item in engine.scan(range) {
+      let (versionedKey, value)= item?;
+
+      let (key, version)= match keycode::deserialize(&versionedKey)? {
+        Key::Version(key, version) => (key, version),
+        key => return Err(Error::Internal(format!("Expected Version key, found {:?}", key)))
+      };
+      if version >= horizon {
+        continue}
+
+      if let Some((latestKey, latestVersionedKey, isTombstone))= latest.take( ) {
+        // An older version of the same key is garbage. The latest version of a different key is
+        // garbage, only if it's a tombstone.
+        if latestKey == key || isTombstone {
+          garbage.push(latestVersionedKey);}
+      }
+
+      let isTombstone= bincode::deserialize::<Option<Vec<u8>>>(&value)?.is_none( );
+      latest= Some((key, versionedKey, isTombstone));
+    }
+
+    if let Some((_, latestVersionedKey, true))= latest {
+      garbage.push(latestVersionedKey);}
+
+    let removedVersions= garbage.len( ) as u64;
+    engine.writeBatch(garbage.into_iter( ).map(|versionedKey| (versionedKey, None)).collect( ))?;
+    self.metrics.gcVersions.fetch_add(removedVersions, Ordering::Relaxed);
+
+    Ok(GCStatus { horizon, removedVersions })
+  }
+
+  // Returns all the raw key-value pairs stored by the MVCC store (including its transaction metadata
+  // and the unversioned keys). Used to take a snapshot of it.
+  pub fn export(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    lockEngine(&self.engine)?.scan((Bound::Unbounded, Bound::Unbounded)).collect( )
+  }
+
+  // Replaces everything stored by the MVCC store, by the given raw key-value pairs (an export).
+  pub fn import(&self, pairs: Vec<(Vec<u8>, Vec<u8>)>) -> Result<( )> {
+    let mut engine= lockEngine(&self.engine)?;
+
+    let mut batch= engine.scan((Bound::Unbounded, Bound::Unbounded))
+                         .map(|item| item.map(|(key, _)| (key, None)))
+                         .collect::<Result<WriteBatch>>( )?;
+    batch.extend(pairs.into_iter( ).map(|(key, value)| (key, Some(value))));
+
+    engine.writeBatch(batch)?;
+    drop(engine);
+
+    self.syncer.commit(| | flushEngine(&self.engine))
+  }
+
+  // Flushes the writes buffered by the storage engine (whatever the sync policy), making them
+  // durable.
+  pub fn flush(&self) -> Result<( )> {
+    self.syncer.flush(| | flushEngine(&self.engine))
+  }
+
+  pub fn status(&self) -> Result<MVCCStatus> {
+    let mut engine= lockEngine(&self.engine)?;
+
+    let versions= match engine.get(&Key::NextVersion.encode( )?)? {
+      Some(nextVersion) => bincode::deserialize::<Version>(&nextVersion)? - 1,
+      None => 0
+    };
+
+    let activeTxns= engine.scan(keycode::prefixRange(&KeyPrefix::TxnActive.encode( )?))
+                          .try_fold(0, |count, item| item.map(|_| count + 1))?;
+
+    Ok(MVCCStatus {
+      versions,
+      activeTxns,
+      conflicts: self.metrics.conflicts.load(Ordering::Relaxed),
+      gcVersions: self.metrics.gcVersions.load(Ordering::Relaxed),
+      sync: self.syncer.status( )?,
+      storage: engine.status( )?
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GCStatus {
+  // Versions older than this were garbage collected.
+  pub horizon: Version,
+
+  pub removedVersions: u64
+}
+
+// The state of a transaction. Can be used to resume the transaction (like across requests).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionState {
+  pub version: Version,
+
+  pub readOnly: bool,
+
+  // Versions of the transactions which were active, when this transaction began.
+  pub activeVersions: HashSet<Version>,
+
+  // Limits on the uncommitted writes of the transaction (ignored, if it's read-only).
+  pub writeLimits: WriteLimits
+}
+
+impl TransactionState {
+  // Returns whether the given version is visible to this transaction.
+  fn isVisible(&self, version: Version) -> bool {
+    if self.activeVersions.contains(&version) {
+      return false}
+
+    // A read-only transaction shares its version with the next read-write transaction, and
+    // shouldn't see it.
+    match self.readOnly {
+      true => version < self.version,
+      false => version <= self.version
+    }
+  }
+
+  // Returns the oldest version, which is invisible to this transaction.
+  fn oldestInvisibleVersion(&self) -> Version {
+    let oldestInvisibleVersion= match self.readOnly {
+      true => self.version,
+      false => self.version + 1
+    };
+
+    self.activeVersions.iter( ).copied( ).fold(oldestInvisibleVersion, Version::min)
+  }
+}
+
+pub struct Transaction<E: StorageEngine> {
+  engine: Arc<Mutex<Buffered<E>>>,
+  metrics: Arc<Metrics>,
+  syncer: Arc<Syncer>,
+  state: TransactionState
+}
+
+impl<E: StorageEngine> Transaction<E> {
+  fn begin(engine: Arc<Mutex<Buffered<E>>>, metrics: Arc<Metrics>, syncer: Arc<Syncer>, writeLimits: WriteLimits) -> Result<Self> {
+    let mut session= lockEngine(&engine)?;
+
+    let version= match session.get(&Key::NextVersion.encode( )?)? {
+      Some(version) => bincode::deserialize(&version)?,
+      None => 1
+    };
+    session.set(&Key::NextVersion.encode( )?, bincode::serialize(&(version + 1))?)?;
+
+    let activeVersions= scanActiveVersions(&mut *session)?;
+    if !activeVersions.is_empty( ) {
+      session.set(&Key::TxnActiveSnapshot(version).encode( )?, bincode::serialize(&activeVersions)?)?;}
+
+    session.set(&Key::TxnActive(version).encode( )?, vec![ ])?;
+    drop(session);
+
+    Ok(Self { engine, metrics, syncer, state: TransactionState { version, readOnly: false, activeVersions, writeLimits } })
+  }
+
+  fn beginReadOnly(engine: Arc<Mutex<Buffered<E>>>, metrics: Arc<Metrics>, syncer: Arc<Syncer>, asOf: Option<Version>) -> Result<Self> {
+    let mut session= lockEngine(&engine)?;
+
+    let state= readOnlyState(&mut *session, asOf)?;
+    updateReadOnlyTxnCount(&mut *session, state.oldestInvisibleVersion( ), 1)?;
+    drop(session);
+
+    Ok(Self { engine, metrics, syncer, state })
+  }
+
+  fn resume(engine: Arc<Mutex<Buffered<E>>>, metrics: Arc<Metrics>, syncer: Arc<Syncer>, state: TransactionState) -> Result<Self> {
+    // A read-write transaction can only be resumed, if it's still active.
+    if !state.readOnly && lockEngine(&engine)?.get(&Key::TxnActive(state.version).encode( )?)?.is_none( ) {
+      return Err(Error::Value(ErrorCode::NoActiveTransaction, format!("Transaction {} isn't active", state.version)))}
+
+    Ok(Self { engine, metrics, syncer, state })
+  }
+
+  pub fn version(&self) -> Version {
+    self.state.version
+  }
+
+  pub fn isReadOnly(&self) -> bool {
+    self.state.readOnly
+  }
+
+  pub fn state(&self) -> &TransactionState {
+    &self.state
+  }
+
+  /*
+    Commits the transaction, making its writes visible to the transactions beginning after this.
+    Returns once the commit is flushed, under the always sync policy.
+
+    NOTE : The engine is unlocked while waiting for the flush, so that the concurrent commits apply
+    their writes meanwhile (and then share a flush, see Syncer).
+  */
+  pub fn commit(self) -> Result<( )> {
+    let mut session= lockEngine(&self.engine)?;
+
+    if self.state.readOnly {
+      return updateReadOnlyTxnCount(&mut *session, self.state.oldestInvisibleVersion( ), -1)}
+
+    // The write records are only needed for rollbacks. They're removed along with the active
+    // transaction marker (which makes the writes visible) in a single batch.
+    let mut batch= session.scan(keycode::prefixRange(&KeyPrefix::TxnWrite(self.state.version).encode( )?))
+                          .map(|item| item.map(|(key, _)| (key, None)))
+                          .collect::<Result<WriteBatch>>( )?;
+
+    batch.push((Key::TxnActive(self.state.version).encode( )?, None));
+    session.writeBatch(batch)?;
+    drop(session);
+
+    self.syncer.commit(| | flushEngine(&self.engine))
+  }
+
+  // Rolls back the transaction, undoing all of its writes.
+  pub fn rollback(self) -> Result<( )> {
+    let mut session= lockEngine(&self.engine)?;
+
+    if self.state.readOnly {
+      return updateReadOnlyTxnCount(&mut *session, self.state.oldestInvisibleVersion( ), -1)}
+
+    let writeKeys= session.scan(keycode::prefixRange(&KeyPrefix::TxnWrite(self.state.version).encode( )?))
+                          .map(|item| item.map(|(key, _)| key))
+                          .collect::<Result<Vec<_>>>( )?;
+
+    let mut batch= WriteBatch::new( );
+    for writeKey in writeKeys {
+      let key= match keycode::deserialize(&writeKey)? {
+        Key::TxnWrite(_, key) => key,
+        key => return Err(Error::Internal(format!("Expected TxnWrite key, found {:?}", key)))
+      };
+
+      batch.push((Key::Version(key, self.state.version).encode( )?, None));
+      batch.push((writeKey, None));
+    }
+
+    batch.push((Key::TxnActive(self.state.version).encode( )?, None));
+    session.writeBatch(batch)
+  }
+
+  pub fn set(&mut self, key: &[u8], value: Vec<u8>) -> Result<( )> {
+    self.write(key, Some(value))
+  }
+
+  pub fn delete(&mut self, key: &[u8]) -> Result<( )> {
+    self.write(key, None)
+  }
+
+  // Returns the latest version of the key, visible to this transaction.
+  pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    Ok(self.getVersioned(key)?.map(|(_, value)| value))
+  }
+
+  // Like get( ), but returns the version the value was written at as well.
+  pub fn getVersioned(&self, key: &[u8]) -> Result<Option<(Version, Vec<u8>)>> {
+    let mut session= lockEngine(&self.engine)?;
+
+    let range= (
+      Bound::Included(Key::Version(key.to_vec( ), 0).encode( )?),
+      Bound::Included(Key::Version(key.to_vec( ), self.state.version).encode( )?)
+    );
+
+    let mut latestValue= None;
+    for item in session.scan(range) {
+      let (versionedKey, value)= item?;
+
+      match keycode::deserialize(&versionedKey)? {
+        Key::Version(_, version) =>
+          if self.state.isVisible(version) {
+            latestValue= bincode::deserialize::<Option<Vec<u8>>>(&value)?.map(|value| (version, value));},
+
+        key => return Err(Error::Internal(format!("Expected Version key, found {:?}", key)))
+      }
+    }
+
+    Ok(latestValue)
+  }
+
+  // Iterates over the latest visible versions of the keys in the given range, in key order. Deleted
+  // keys are skipped.
+  pub fn scan(&self, range: KeyRange) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    Ok(stripVersions(self.scanVersioned(range, Direction::Forward, None)?))
+  }
+
+  // Like scan( ), but iterates in the given direction, stops after the given number of (visible and
+  // not deleted) keys, and returns the version every value was written at as well.
+  pub fn scanVersioned(&self, range: KeyRange, direction: Direction, limit: Option<usize>) -> Result<Vec<VersionedPair>> {
+    // An excluded start key is skipped along with all its versions, and so is an excluded end key.
+    let start= match range.0 {
+      Bound::Included(key) => Bound::Included(Key::Version(key, 0).encode( )?),
+      Bound::Excluded(key) => keycode::prefixRange(&KeyPrefix::Version(key).encode( )?).1,
+      Bound::Unbounded => Bound::Included(Key::Version(vec![ ], 0).encode( )?)
+    };
+
+    let end= match range.1 {
+      Bound::Included(key) => keycode::prefixRange(&KeyPrefix::Version(key).encode( )?).1,
+      Bound::Excluded(key) => Bound::Excluded(Key::Version(key, 0).encode( )?),
+      Bound::Unbounded => Bound::Excluded(KeyPrefix::Unversioned.encode( )?)
+    };
+
+    // The storage engines can't scan a range starting after its end.
+    if isEmptyRange(&start, &end) {
+      return Ok(vec![ ])}
+
+    match direction {
+      Direction::Forward => self.scanVersions((start, end), limit),
+      Direction::Reverse => self.scanVersionsRev((start, end), limit)
+    }
+  }
+
+  // Iterates over the latest visible versions of the keys starting with the given prefix, in key
+  // order. Deleted keys are skipped.
+  pub fn scanPrefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    self.scanPrefixDirected(prefix, Direction::Forward, None)
+  }
+
+  // Like scanPrefix( ), but iterates in the given direction, and stops after the given number of
+  // (visible and not deleted) keys.
+  pub fn scanPrefixDirected(&self, prefix: &[u8], direction: Direction, limit: Option<usize>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    Ok(stripVersions(self.scanPrefixVersioned(prefix, direction, limit)?))
+  }
+
+  // Like scanPrefixDirected( ), but returns the version every value was written at as well.
+  pub fn scanPrefixVersioned(&self, prefix: &[u8], direction: Direction, limit: Option<usize>) -> Result<Vec<VersionedPair>> {
+    // The encoded prefix must not include the terminator of the user key.
+    let mut encodedPrefix= KeyPrefix::Version(prefix.to_vec( )).encode( )?;
+    encodedPrefix.truncate(encodedPrefix.len( ) - 2);
+
+    let range= keycode::prefixRange(&encodedPrefix);
+    match direction {
+      Direction::Forward => self.scanVersions(range, limit),
+      Direction::Reverse => self.scanVersionsRev(range, limit)
+    }
+  }
+
+  fn scanVersions(&self, range: KeyRange, limit: Option<usize>) -> Result<Vec<VersionedPair>> {
+    let mut session= lockEngine(&self.engine)?;
+
+    let mut keyValuePairs= vec![ ];
+    let mut latest: Option<(Vec<u8>, Version, Option<Vec<u8>>)>= None;
+
+    for item in session.scan(range) {
+      let (versionedKey, value)= item?;
+
+      let (key, version)= match keycode::deserialize(&versionedKey)? {
+        Key::Version(key, version) => (key, version),
+        key => return Err(Error::Internal(format!("Expected Version key, found {:?}", key)))
+      };
+      if !self.state.isVisible(version) {
+        continue}
+
+      let value= bincode::deserialize::<Option<Vec<u8>>>(&value)?;
+
+      // The versions of a key are ordered. So a newer version replaces the previous one, while a
+      // different key means that the previous key has been resolved.
+      if let Some((latestKey, latestVersion, latestValue))= latest.take( ) {
+        if latestKey != key {
+          if let Some(latestValue)= latestValue {
+            keyValuePairs.push((latestKey, latestVersion, latestValue));}
+
+          if limit.is_some_and(|limit| keyValuePairs.len( ) >= limit) {
+            return Ok(keyValuePairs)}
+        }
+      }
+      latest= Some((key, version, value));
+    }
+
+    if let Some((latestKey, latestVersion, Some(latestValue)))= latest {
+      keyValuePairs.push((latestKey, latestVersion, latestValue));}
+
+    keyValuePairs.truncate(limit.unwrap_or(usize::MAX));
+    Ok(keyValuePairs)
+  }
+
+  // Like scanVersions( ), but in descending key order. The versions of a key are then met newest
+  // first, so the first visible version of a key is its latest one.
+  fn scanVersionsRev(&self, range: KeyRange, limit: Option<usize>) -> Result<Vec<VersionedPair>> {
+    let mut session= lockEngine(&self.engine)?;
+
+    let mut keyValuePairs= vec![ ];
+    let mut resolvedKey: Option<Vec<u8>>= None;
+
+    for item in session.scanRev(range) {
+      if limit.is_some_and(|limit| keyValuePairs.len( ) >= limit) {
+        break}
+
+      let (versionedKey, value)= item?;
+
+      let (key, version)= match keycode::deserialize(&versionedKey)? {
+        Key::Version(key, version) => (key, version),
+        key => return Err(Error::Internal(format!("Expected Version key, found {:?}", key)))
+      };
+      if !self.state.isVisible(version) || resolvedKey.as_ref( ) == Some(&key) {
+        continue}
+
+      if let Some(value)= bincode::deserialize::<Option<Vec<u8>>>(&value)? {
+        keyValuePairs.push((key.clone( ), version, value));}
+      resolvedKey= Some(key);
+    }
+
+    Ok(keyValuePairs)
+  }
+
+  // Writes a new version of the key. A None value represents a deletion.
+  fn write(&mut self, key: &[u8], value: Option<Vec<u8>>) -> Result<( )> {
+    if self.state.readOnly {
+      return Err(Error::ReadOnly)}
+
+    let mut session= lockEngine(&self.engine)?;
+    self.checkConflict(&mut *session, key)?;
+
+    let value= bincode::serialize(&value)?;
+    let mut tally= self.getWriteTally(&mut *session)?;
+    self.countWrite(&mut *session, &mut tally, key, &value)?;
+
+    session.set(&Key::TxnActive(self.state.version).encode( )?, bincode::serialize(&tally)?)?;
+    session.set(&Key::TxnWrite(self.state.version, key.to_vec( )).encode( )?, vec![ ])?;
+    session.set(&Key::Version(key.to_vec( ), self.state.version).encode( )?, value)
+  }
+
+  /*
+    Writes new versions of the keys (a None value representing a deletion), using a single batch of
+    the storage engine. Nothing is written, if any of the keys conflicts.
+
+    It's meant for bulk loads, where writing the keys one by one (each write locking the engine on
+    its own) is too slow.
+  */
+  pub fn writeBatch(&mut self, writes: WriteBatch) -> Result<( )> {
+    if self.state.readOnly {
+      return Err(Error::ReadOnly)}
+
+    let mut session= lockEngine(&self.engine)?;
+    let mut tally= self.getWriteTally(&mut *session)?;
+
+    let mut batch= WriteBatch::with_capacity(writes.len( ) * 2 + 1);
+    for (key, value) in writes {
+      self.checkConflict(&mut *session, &key)?;
+
+      let value= bincode::serialize(&value)?;
+      self.countWrite(&mut *session, &mut tally, &key, &value)?;
+
+      batch.push((Key::TxnWrite(self.state.version, key.clone( )).encode( )?, Some(vec![ ])));
+      batch.push((Key::Version(key, self.state.version).encode( )?, Some(value)));
+    }
+
+    batch.push((Key::TxnActive(self.state.version).encode( )?, Some(bincode::serialize(&tally)?)));
+    session.writeBatch(batch)
+  }
+
+  // Returns the uncommitted writes of the transaction so far.
+  fn getWriteTally(&self, session: &mut Buffered<E>) -> Result<WriteTally> {
+    match session.get(&Key::TxnActive(self.state.version).encode( )?)? {
+      Some(tally) if !tally.is_empty( ) => Ok(bincode::deserialize(&tally)?),
+      _ => Ok(WriteTally::default( ))
+    }
+  }
+
+  // Counts a write of the key against the write limits of the transaction. Fails (without counting
+  // it), if the write would exceed them.
+  // NOTE : A key written earlier in a batch isn't stored yet, so it's counted again by a later write
+  // in the same batch.
+  fn countWrite(&self, session: &mut Buffered<E>, tally: &mut WriteTally, key: &[u8], value: &[u8]) -> Result<( )> {
+    let isNewKey= session.get(&Key::TxnWrite(self.state.version, key.to_vec( )).encode( )?)?.is_none( );
+
+    let keys= tally.keys + u64::from(isNewKey);
+    let bytes= tally.bytes + (key.len( ) + value.len( )) as u64;
+
+    let WriteLimits { maxKeys, maxBytes }= self.state.writeLimits;
+    if keys > maxKeys || bytes > maxBytes {
+      return Err(Error::Value(ErrorCode::ProgramLimitExceeded, format!(
+        "Transaction {} exceeded its write limits ({} keys, {} bytes) : split the writes into smaller transactions",
+        self.state.version, maxKeys, maxBytes
+      )))}
+
+    *tally= WriteTally { keys, bytes };
+    Ok(( ))
+  }
+
+  // Fails with Error::Serialization, if the key has been written by a concurrent (or a later)
+  // transaction, like a write of the key would. The value this transaction reads is then stale.
+  pub fn checkUnchanged(&self, key: &[u8]) -> Result<( )> {
+    let mut session= lockEngine(&self.engine)?;
+    self.checkConflict(&mut *session, key)
+  }
+
+  // Fails, if the latest version of the key was written by a concurrent or a later transaction. Only
+  // the latest version needs to be checked, and it can't be older than the oldest active transaction
+  // (versions older than that are visible).
+  fn checkConflict(&self, session: &mut Buffered<E>, key: &[u8]) -> Result<( )> {
+    let range= (
+      Bound::Included(Key::Version(key.to_vec( ), self.state.oldestInvisibleVersion( )).encode( )?),
+      Bound::Included(Key::Version(key.to_vec( ), Version::MAX).encode( )?)
+    );
+
+    if let Some(item)= session.scanRev(range).next( ) {
+      let (versionedKey, _)= item?;
+
+      match keycode::deserialize(&versionedKey)? {
+        Key::Version(_, version) =>
+          if !self.state.isVisible(version) {
+            self.metrics.conflicts.fetch_add(1, Ordering::Relaxed);
+            return Err(Error::Serialization)
+          },
+
+        key => return Err(Error::Internal(format!("Expected Version key, found {:?}", key)))
+      }
+    }
+
+    Ok(( ))
+  }
+}
+
+// Returns whether there's no key between the bounds.
+fn isEmptyRange(start: &Bound<Vec<u8>>, end: &Bound<Vec<u8>>) -> bool {
+  match (start, end) {
+    (Bound::Included(start), Bound::Included(end)) => start > end,
+    (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => start >= end,
+    _ => false
+  }
+}
+
+fn stripVersions(pairs: Vec<VersionedPair>) -> Vec<(Vec<u8>, Vec<u8>)> {
+  pairs.into_iter( ).map(|(key, _, value)| (key, value)).collect( )
+}
+
+fn lockEngine<E: StorageEngine>(engine: &Arc<Mutex<Buffered<E>>>) -> Result<MutexGuard<'_, Buffered<E>>> {
+  engine.lock( )
+        .map_err(|error| Error::Internal(format!("Storage engine lock is poisoned : {}", error)))
+}
+
+// Flushes the engine. It's only locked while the flush begins, so that the writes can go on while
+// the rest of it runs (see StorageEngine::beginFlush( )).
+fn flushEngine<E: StorageEngine>(engine: &Arc<Mutex<Buffered<E>>>) -> Result<( )> {
+  let pendingFlush= lockEngine(engine)?.beginFlush( )?;
+  pendingFlush( )
+}
+
+// Returns the state of a read-only transaction, which sees the latest committed versions (or the
+// versions as of the given version).
+fn readOnlyState<E: StorageEngine + ?Sized>(engine: &mut E, asOf: Option<Version>) -> Result<TransactionState> {
+  let mut version= match engine.get(&Key::NextVersion.encode( )?)? {
+    Some(version) => bincode::deserialize(&version)?,
+    None => 1
+  };
+
+  let activeVersions= match asOf {
+    Some(asOf) => {
+      if asOf >= version {
+        return Err(Error::Value(ErrorCode::InvalidParameterValue, format!("Version {} doesn't exist yet", asOf)))}
+      version= asOf;
+
+      match engine.get(&Key::TxnActiveSnapshot(version).encode( )?)? {
+        Some(activeVersions) => bincode::deserialize(&activeVersions)?,
+        None => HashSet::new( )
+      }
+    },
+
+    None => scanActiveVersions(engine)?
+  };
+
+  Ok(TransactionState { version, readOnly: true, activeVersions, writeLimits: WriteLimits::default( ) })
+}
+
+// Increments / decrements the number of active read-only transactions with the given oldest
+// invisible version.
+fn updateReadOnlyTxnCount<E: StorageEngine + ?Sized>(engine: &mut E, version: Version, delta: i64) -> Result<( )> {
+  let key= Key::TxnReadOnly(version).encode( )?;
+
+  let count= match engine.get(&key)? {
+    Some(count) => bincode::deserialize::<i64>(&count)?,
+    None => 0
+  } + delta;
+
+  match count > 0 {
+    true => engine.set(&key, bincode::serialize(&count)?),
+    false => engine.delete(&key)
+  }
+}
+
+// Returns the versions of the active read-write transactions.
+fn scanActiveVersions<E: StorageEngine + ?Sized>(engine: &mut E) -> Result<HashSet<Version>> {
+  let mut activeVersions= HashSet::new( );
+
+  for item in engine.scan(keycode::prefixRange(&KeyPrefix::TxnActive.encode( )?)) {
+    let (key, _)= item?;
+
+    match keycode::deserialize(&key)? {
+      Key::TxnActive(version) => activeVersions.insert(version),
+      key => return Err(Error::Internal(format!("Expected TxnActive key, found {:?}", key)))
+    };
+  }
+
+  Ok(activeVersions)
+}
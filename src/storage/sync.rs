@@ -0,0 +1,254 @@
+use std::{
+  fmt::Display,
+  str::FromStr,
+  sync::{Condvar, Mutex, MutexGuard},
+  time::{Duration, Instant}
+};
+use serde::{Deserialize, Serialize};
+use crate::result::{Error, ErrorCode, Result};
+
+/*
+  When the writes to a store (the SQL data, or the Raft log) get flushed, trading durability for
+  throughput :
+
+    always : a commit is flushed before it's acknowledged. Concurrent commits share a flush (see
+    Syncer), so that a busy store doesn't flush once per commit.
+
+    interval(<milliseconds>) : a commit is acknowledged right away, and a background task flushes
+    the commits every given milliseconds. A crash loses the commits acknowledged since the last
+    flush.
+
+    never : the commits are only flushed when asked to explicitly (like on shutdown). A crash loses
+    every commit acknowledged since the last flush.
+
+  NOTE : Some writes are flushed explicitly, whatever the policy (like the vote cast by a Raft node,
+  which it mustn't forget across a crash).
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SyncPolicy {
+  #[default]
+  Always,
+  Interval(Duration),
+  Never
+}
+
+impl SyncPolicy {
+  // Describes which acknowledged commits can be lost on a crash, as shown in the status.
+  pub fn durability(&self) -> String {
+    match self {
+      Self::Always => "every acknowledged commit is durable".to_string( ),
+      Self::Interval(interval) => format!("commits acknowledged in the last {} ms can be lost on a crash", interval.as_millis( )),
+      Self::Never => "commits acknowledged since the last explicit flush can be lost on a crash".to_string( )
+    }
+  }
+}
+
+impl Display for SyncPolicy {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Always => write!(f, "always"),
+      Self::Interval(interval) => write!(f, "interval({})", interval.as_millis( )),
+      Self::Never => write!(f, "never")
+    }
+  }
+}
+
+impl FromStr for SyncPolicy {
+  type Err= Error;
+
+  fn from_str(value: &str) -> Result<Self> {
+    let interval= value.strip_prefix("interval(")
+                       .and_then(|value| value.strip_suffix(')'))
+                       .and_then(|millis| millis.trim( ).parse::<u64>( ).ok( ));
+
+    match (value, interval) {
+      ("always", _) => Ok(Self::Always),
+      ("never", _) => Ok(Self::Never),
+      (_, Some(millis)) if millis > 0 => Ok(Self::Interval(Duration::from_millis(millis))),
+
+      _ => Err(Error::Value(ErrorCode::InvalidParameterValue, format!(
+        "Invalid sync policy {}, expected always, interval(<milliseconds>) or never", value
+      )))
+    }
+  }
+}
+
+/*
+  Flushes the commits to a store, according to its sync policy. The store calls commit( ) after
+  applying the writes of a commit (without flushing them), which returns once they're flushed under
+  the always policy.
+
+  Group commit : only one flush runs at a time. The commits arriving while it runs queue up, waiting
+  for it to end. Then, the first of them flushes for all of them : a flush covers every commit
+  applied before it began. So, the number of flushes is bounded by how fast the store flushes,
+  rather than growing with the number of concurrent commits.
+
+  The Syncer doesn't own the store, so the callers pass in the flush (which must flush every write
+  applied to the store before it's called).
+*/
+pub struct Syncer {
+  policy: SyncPolicy,
+  state: Mutex<SyncState>,
+
+  // Notified whenever a flush ends.
+  flushEnded: Condvar
+}
+
+struct SyncState {
+  // The commits and the explicit flushes are sync points, numbered in the order they arrive. The
+  // sync points upto flushedSyncPoint are flushed.
+  lastSyncPoint: u64,
+  flushedSyncPoint: u64,
+
+  // Whether a flush is running.
+  flushing: bool,
+
+  commits: u64,
+  flushedCommits: u64,
+  fsyncs: u64,
+
+  // When the last flush ended (even if it failed, so that the background task doesn't retry it right
+  // away).
+  lastFlush: Instant
+}
+
+// The sync policy of a store, along with the counters of its commits and flushes (which start from
+// zero when the node restarts).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncStatus {
+  pub policy: SyncPolicy,
+
+  pub commits: u64,
+  // Number of flushes. Under the always policy, it's less than the number of commits when
+  // concurrent commits share flushes.
+  pub fsyncs: u64,
+
+  // Number of acknowledged commits which aren't flushed yet, and would be lost on a crash.
+  pub unflushedCommits: u64
+}
+
+impl Syncer {
+  pub fn new(policy: SyncPolicy) -> Self {
+    let state= SyncState {
+      lastSyncPoint: 0,
+      flushedSyncPoint: 0,
+      flushing: false,
+      commits: 0,
+      flushedCommits: 0,
+      fsyncs: 0,
+      lastFlush: Instant::now( )
+    };
+    Self { policy, state: Mutex::new(state), flushEnded: Condvar::new( ) }
+  }
+
+  pub fn policy(&self) -> SyncPolicy {
+    self.policy
+  }
+
+  // Called once the writes of a commit are applied to the store. Under the always policy, waits for
+  // them to be flushed (see Syncer).
+  pub fn commit(&self, flush: impl FnOnce( ) -> Result<( )>) -> Result<( )> {
+    let mut state= self.lock( )?;
+    state.commits += 1;
+    state.lastSyncPoint += 1;
+
+    match self.policy {
+      SyncPolicy::Always => {
+        let syncPoint= state.lastSyncPoint;
+        self.waitFlushed(state, syncPoint, flush)
+      },
+      SyncPolicy::Interval(_) | SyncPolicy::Never => Ok(( ))
+    }
+  }
+
+  // Flushes the writes applied to the store so far, whatever the policy.
+  pub fn flush(&self, flush: impl FnOnce( ) -> Result<( )>) -> Result<( )> {
+    let mut state= self.lock( )?;
+    state.lastSyncPoint += 1;
+
+    let syncPoint= state.lastSyncPoint;
+    self.waitFlushed(state, syncPoint, flush)
+  }
+
+  // Flushes the unflushed commits under the interval policy, once the interval has passed since the
+  // last flush. Called periodically, by the background task of the store.
+  pub fn flushIfDue(&self, flush: impl FnOnce( ) -> Result<( )>) -> Result<( )> {
+    let state= self.lock( )?;
+
+    match Self::untilDue(self.policy, &state) {
+      Some(Duration::ZERO) => {
+        let syncPoint= state.lastSyncPoint;
+        self.waitFlushed(state, syncPoint, flush)
+      },
+      _ => Ok(( ))
+    }
+  }
+
+  // Returns how long until a flush is due under the interval policy (the interval, if there's
+  // nothing to flush). None under the other policies.
+  pub fn untilFlushDue(&self) -> Result<Option<Duration>> {
+    Ok(Self::untilDue(self.policy, &*self.lock( )?))
+  }
+
+  pub fn status(&self) -> Result<SyncStatus> {
+    let state= self.lock( )?;
+
+    Ok(SyncStatus {
+      policy: self.policy,
+      commits: state.commits,
+      fsyncs: state.fsyncs,
+      unflushedCommits: state.commits - state.flushedCommits
+    })
+  }
+
+  fn untilDue(policy: SyncPolicy, state: &SyncState) -> Option<Duration> {
+    let SyncPolicy::Interval(interval)= policy else {
+      return None
+    };
+
+    match state.flushedSyncPoint < state.lastSyncPoint {
+      true => Some(interval.saturating_sub(state.lastFlush.elapsed( ))),
+      false => Some(interval)
+    }
+  }
+
+  /*
+    Waits for the given sync point to be flushed. If no flush is running, this flushes (for all the
+    sync points so far) instead. A failed flush fails only the caller which ran it : the waiting
+    ones wake up, and one of them flushes again.
+  */
+  fn waitFlushed(&self, mut state: MutexGuard<'_, SyncState>, syncPoint: u64, flush: impl FnOnce( ) -> Result<( )>) -> Result<( )> {
+    loop {
+      if state.flushedSyncPoint >= syncPoint {
+        return Ok(( ))}
+
+      if state.flushing {
+        state= self.flushEnded.wait(state).map_err(|error| Error::Internal(format!("Sync state lock is poisoned : {}", error)))?;
+        continue
+      }
+
+      let (lastSyncPoint, commits)= (state.lastSyncPoint, state.commits);
+      state.flushing= true;
+      drop(state);
+
+      let result= flush( );
+
+      let mut state= self.lock( )?;
+      state.flushing= false;
+      if result.is_ok( ) {
+        state.flushedSyncPoint= lastSyncPoint;
+        state.flushedCommits= commits;
+        state.fsyncs += 1;
+      }
+      state.lastFlush= Instant::now( );
+      self.flushEnded.notify_all( );
+
+      return result
+    }
+  }
+
+  fn lock(&self) -> Result<MutexGuard<'_, SyncState>> {
+    self.state.lock( )
+              .map_err(|error| Error::Internal(format!("Sync state lock is poisoned : {}", error)))
+  }
+}
@@ -0,0 +1,78 @@
+use std::{fs, io, path::{Path, PathBuf}};
+use rand::Rng;
+use crate::{result::ErrorCode, storage::data_dir::FORMAT_VERSION};
+use super::pick;
+
+/*
+  The contents of a data directory (see DataDir), as a crashed run - or a server of another version,
+  or a careless operator - could leave it behind.
+*/
+#[derive(Debug, Clone)]
+pub struct DataDirLayout {
+  // Contents of the VERSION file, if there's one.
+  pub version: Option<String>,
+
+  // Whether a LOCK file is left behind (by a crashed run, so it isn't locked anymore).
+  pub staleLock: bool,
+
+  // The files left in tmp/, relative to it.
+  pub orphans: Vec<PathBuf>,
+
+  // Whether there's a file which doesn't belong to the layout, in the directory itself.
+  pub strayFile: bool
+}
+
+impl DataDirLayout {
+  // Creates the layout in the given (non-existing) directory.
+  pub fn create(&self, path: &Path) -> io::Result<( )> {
+    fs::create_dir_all(path)?;
+
+    if let Some(version)= &self.version {
+      fs::write(path.join("VERSION"), version)?;}
+    if self.staleLock {
+      fs::write(path.join("LOCK"), "4194304")?;}
+    if self.strayFile {
+      fs::write(path.join("notes.txt"), "")?;}
+
+    for orphan in &self.orphans {
+      let orphan= path.join("tmp").join(orphan);
+      fs::create_dir_all(orphan.parent( ).expect("An orphan is in tmp/"))?;
+      fs::write(orphan, "orphaned")?;
+    }
+    Ok(( ))
+  }
+
+  // Returns the code of the error opening the directory fails with, or None if it opens.
+  pub fn expectedError(&self) -> Option<ErrorCode> {
+    match &self.version {
+      None if self.strayFile => Some(ErrorCode::ObjectNotInPrerequisiteState),
+      None => None,
+
+      Some(version) => match version.trim( ).parse::<u32>( ) {
+        Ok(FORMAT_VERSION) => None,
+        Ok(_) => Some(ErrorCode::ObjectNotInPrerequisiteState),
+        Err(_) => Some(ErrorCode::DataCorrupted)
+      }
+    }
+  }
+}
+
+// Generates the contents of a data directory (see DataDirLayout).
+pub fn generateDataDirLayout<R: Rng>(rng: &mut R) -> DataDirLayout {
+  let version= match rng.gen_range(0..6) {
+    0 | 1 => None,
+    2 => Some(FORMAT_VERSION.to_string( )),
+    3 => Some(format!(" {}\n", FORMAT_VERSION)),
+    4 => Some(rng.gen_range(0..=FORMAT_VERSION + 3).to_string( )),
+    _ => Some(pick(rng, &["", "v1", "-1", "4294967296", "1.0"]).to_string( ))
+  };
+
+  let orphans= (0..rng.gen_range(0..5)).map(|i| match rng.gen_range(0..3) {
+                                          0 => PathBuf::from(format!("{}.spill", i)),
+                                          1 => PathBuf::from("spill").join(format!("{}.spill", i)),
+                                          _ => PathBuf::from(format!("orphan-{}", i)).join("nested").join("file")
+                                        })
+                                        .collect( );
+
+  DataDirLayout { version, staleLock: rng.gen( ), orphans, strayFile: rng.gen_bool(0.2) }
+}
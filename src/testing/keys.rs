@@ -0,0 +1,189 @@
+use std::cmp::Ordering;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::sql::types::{compareFloats, Value};
+use super::{generateString, pick};
+
+/*
+  A part of a composite key, covering every type supported by the key encoding (see keycode). Its
+  logical order (see KeyPart::compare) is the one the encoding must preserve : the parts are ordered
+  first by their variant (as they're declared), and then by their values.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum KeyPart {
+  Boolean(bool),
+  Unsigned(u64),
+  Integer(i64),
+  Float(f64),
+  Bytes(#[serde(with= "serde_bytes")] Vec<u8>),
+  String(String),
+  Value(Value)
+}
+
+// A key made up of parts, like the (table name, primary key) keys of the rows. Composite keys are
+// ordered lexicographically by their parts, a key coming before the longer keys it's a prefix of.
+pub type CompositeKey= Vec<KeyPart>;
+
+impl KeyPart {
+  fn rank(&self) -> u8 {
+    match self {
+      Self::Boolean(_) => 0,
+      Self::Unsigned(_) => 1,
+      Self::Integer(_) => 2,
+      Self::Float(_) => 3,
+      Self::Bytes(_) => 4,
+      Self::String(_) => 5,
+      Self::Value(_) => 6
+    }
+  }
+
+  pub fn compare(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs.cmp(rhs),
+      (Self::Unsigned(lhs), Self::Unsigned(rhs)) => lhs.cmp(rhs),
+      (Self::Integer(lhs), Self::Integer(rhs)) => lhs.cmp(rhs),
+      (Self::Float(lhs), Self::Float(rhs)) => compareFloats(*lhs, *rhs),
+      (Self::Bytes(lhs), Self::Bytes(rhs)) => lhs.cmp(rhs),
+      (Self::String(lhs), Self::String(rhs)) => lhs.cmp(rhs),
+      (Self::Value(lhs), Self::Value(rhs)) => lhs.cmp(rhs),
+
+      (lhs, rhs) => lhs.rank( ).cmp(&rhs.rank( ))
+    }
+  }
+}
+
+// Compares composite keys logically.
+pub fn compareKeys(lhs: &[KeyPart], rhs: &[KeyPart]) -> Ordering {
+  lhs.iter( )
+     .zip(rhs)
+     .map(|(lhs, rhs)| lhs.compare(rhs))
+     .find(|ordering| ordering.is_ne( ))
+     .unwrap_or_else(| | lhs.len( ).cmp(&rhs.len( )))
+}
+
+// Generates a composite key of upto 4 parts.
+pub fn generateKey<R: Rng>(rng: &mut R) -> CompositeKey {
+  let length= rng.gen_range(0..=4);
+  (0..length).map(|_| generateKeyPart(rng)).collect( )
+}
+
+/*
+  Generates a pair of composite keys to be compared. Two independently generated keys mostly differ
+  in their first part, so the second key is usually derived from the first one instead : it's the
+  same key, or the key truncated / extended, or with its last part replaced by a nearby value.
+*/
+pub fn generateKeyPair<R: Rng>(rng: &mut R) -> (CompositeKey, CompositeKey) {
+  let key= generateKey(rng);
+
+  let mut other= key.clone( );
+  match rng.gen_range(0..5) {
+    0 => other= generateKey(rng),
+    1 => { },
+    2 => other.truncate(rng.gen_range(0..=key.len( ))),
+    3 => other.push(generateKeyPart(rng)),
+
+    _ => match other.pop( ) {
+      Some(part) => other.push(generateNearbyKeyPart(rng, &part)),
+      None => other.push(generateKeyPart(rng))
+    }
+  }
+
+  match rng.gen( ) {
+    true => (key, other),
+    false => (other, key)
+  }
+}
+
+pub fn generateKeyPart<R: Rng>(rng: &mut R) -> KeyPart {
+  match rng.gen_range(0..7) {
+    0 => KeyPart::Boolean(rng.gen( )),
+    1 => KeyPart::Unsigned(generateUnsigned(rng)),
+    2 => KeyPart::Integer(generateInteger(rng)),
+    3 => KeyPart::Float(generateFloat(rng)),
+    4 => KeyPart::Bytes(generateBytes(rng)),
+    5 => KeyPart::String(generateString(rng, 8)),
+    _ => KeyPart::Value(generateValue(rng))
+  }
+}
+
+// Generates a key part of the same variant, whose value is close to the given one (like an integer
+// off by one, or a string with a character appended).
+fn generateNearbyKeyPart<R: Rng>(rng: &mut R, part: &KeyPart) -> KeyPart {
+  let delta= rng.gen_range(-1..=1);
+
+  match part {
+    KeyPart::Boolean(value) => KeyPart::Boolean(!value),
+    KeyPart::Unsigned(value) => KeyPart::Unsigned(value.wrapping_add_signed(delta)),
+    KeyPart::Integer(value) => KeyPart::Integer(value.wrapping_add(delta)),
+    KeyPart::Float(value) => KeyPart::Float(match delta {
+      0 => -value,
+      _ => f64::from_bits(value.to_bits( ).wrapping_add_signed(delta))
+    }),
+
+    KeyPart::Bytes(value) => {
+      let mut value= value.clone( );
+      match value.pop( ) {
+        Some(byte) if rng.gen( ) => value.push(byte.wrapping_add(delta as u8)),
+        _ => value.push(*pick(rng, &[0x00, 0x01, 0xff]))
+      }
+      KeyPart::Bytes(value)
+    },
+    KeyPart::String(value) => KeyPart::String(format!("{}{}", value, generateString(rng, 1))),
+
+    KeyPart::Value(_) => KeyPart::Value(generateValue(rng))
+  }
+}
+
+// Generates a SQL value, of any type.
+pub fn generateValue<R: Rng>(rng: &mut R) -> Value {
+  match rng.gen_range(0..7) {
+    0 => Value::Null,
+    1 => Value::Boolean(rng.gen( )),
+    2 => Value::Integer(generateInteger(rng)),
+    3 => Value::Float(generateFloat(rng)),
+    4 => Value::String(generateString(rng, 8)),
+    5 => Value::Date(rng.gen_range(-1_000_000..1_000_000)),
+    _ => Value::Timestamp(generateInteger(rng))
+  }
+}
+
+// The integers are biased towards the edge cases (around 0 and the limits).
+fn generateInteger<R: Rng>(rng: &mut R) -> i64 {
+  match rng.gen_range(0..3) {
+    0 => *pick(rng, &[0, 1, -1, i64::MIN, i64::MIN + 1, i64::MAX, i64::MAX - 1]),
+    1 => rng.gen_range(-256..256),
+    _ => rng.gen( )
+  }
+}
+
+fn generateUnsigned<R: Rng>(rng: &mut R) -> u64 {
+  match rng.gen_range(0..3) {
+    0 => *pick(rng, &[0, 1, u64::MAX, u64::MAX - 1, 1 << 63]),
+    1 => rng.gen_range(0..256),
+    _ => rng.gen( )
+  }
+}
+
+// The floats include -0.0, the infinities, NaNs (of either sign) and subnormal numbers.
+fn generateFloat<R: Rng>(rng: &mut R) -> f64 {
+  match rng.gen_range(0..4) {
+    0 => *pick(rng, &[
+      0.0, -0.0, 1.0, -1.0,
+      f64::INFINITY, f64::NEG_INFINITY, f64::NAN, -f64::NAN,
+      f64::MIN, f64::MAX, f64::MIN_POSITIVE, -f64::MIN_POSITIVE, f64::EPSILON
+    ]),
+    1 => rng.gen_range(-100.0..100.0),
+    2 => f64::from_bits(rng.gen( )),
+    _ => rng.gen_range(-1000..1000) as f64
+  }
+}
+
+// The bytes are biased towards 0x00 and 0xff, which the encoding escapes / uses as terminators.
+fn generateBytes<R: Rng>(rng: &mut R) -> Vec<u8> {
+  let length= rng.gen_range(0..=8);
+  (0..length).map(|_| match rng.gen_range(0..3) {
+               0 => *pick(rng, &[0x00, 0x01, 0xfe, 0xff]),
+               _ => rng.gen( )
+             })
+             .collect( )
+}
@@ -0,0 +1,119 @@
+use std::ops::Bound;
+use rand::Rng;
+use crate::{
+  raft::{cluster_config::ClusterConfig, log::{Log, LogEntry, Snapshot}, types::{LogEntryIndex, Term}},
+  result::Result
+};
+
+/*
+  An operation on the Raft log. The operations are generated against the current state of a log, so
+  that most of them are valid (like splicing entries which don't conflict with the committed ones),
+  but some are meant to fail (like committing beyond the last stored entry).
+*/
+#[derive(Debug, Clone)]
+pub enum LogOperation {
+  Append(Term),
+  AppendConfig(Term),
+  Splice(Vec<LogEntry>),
+  Truncate(LogEntryIndex),
+  Commit(LogEntryIndex),
+  Snapshot(LogEntryIndex),
+  InstallSnapshot(LogEntryIndex, Term),
+
+  Get(LogEntryIndex),
+  GetTerm(LogEntryIndex),
+  Has(LogEntryIndex, Term),
+  Scan(Bound<LogEntryIndex>, Bound<LogEntryIndex>),
+  ScanUnapplied(LogEntryIndex)
+}
+
+impl LogOperation {
+  // Applies the operation to the log, and returns a description of its outcome (including the read
+  // entries, and the state of the log afterwards). Logs which behave the same give the same outcomes.
+  pub fn apply(&self, log: &mut Log) -> String {
+    let outcome= match self {
+      Self::Append(term) => format!("{:?}", log.append(*term, Some(term.to_be_bytes( ).to_vec( )), None)),
+      Self::AppendConfig(term) => format!("{:?}", log.appendConfig(*term, ClusterConfig::default( ))),
+      Self::Splice(entries) => format!("{:?}", log.splice(entries.clone( ))),
+      Self::Truncate(index) => format!("{:?}", log.truncate(*index)),
+      Self::Commit(index) => format!("{:?}", log.commit(*index)),
+      Self::Snapshot(index) => format!("{:?}", log.snapshot(*index, index.to_be_bytes( ).to_vec( ))),
+      Self::InstallSnapshot(index, term) => {
+        let snapshot= Snapshot { index: *index, term: *term, config: ClusterConfig::default( ), state: Vec::new( ) };
+        format!("{:?}", log.installSnapshot(snapshot))
+      },
+
+      Self::Get(index) => format!("{:?}", log.get(*index)),
+      Self::GetTerm(index) => format!("{:?}", log.getTerm(*index)),
+      Self::Has(index, term) => format!("{:?}", log.has(*index, *term)),
+      Self::Scan(start, end) => format!("{:?}", log.scan((*start, *end)).collect::<Result<Vec<_>>>( )),
+      Self::ScanUnapplied(appliedIndex) => format!("{:?}", log.scanUnapplied(*appliedIndex).collect::<Result<Vec<_>>>( ))
+    };
+
+    format!("{} -> last stored {:?}, commit {:?}, snapshot {:?}, config {:?}",
+            outcome, log.getLastStoredEntryIndexAndTerm( ), log.getCommitIndexAndTerm( ),
+            log.getSnapshotIndexAndTerm( ), log.getConfig( ).0)
+  }
+}
+
+// Generates an operation on the given log (see LogOperation).
+pub fn generateLogOperation<R: Rng>(rng: &mut R, log: &mut Log) -> Result<LogOperation> {
+  let (lastIndex, lastTerm)= log.getLastStoredEntryIndexAndTerm( );
+  let (commitIndex, _)= log.getCommitIndexAndTerm( );
+  let (snapshotIndex, _)= log.getSnapshotIndexAndTerm( );
+
+  // An index around the stored entries, including a few beyond them.
+  let anyIndex= |rng: &mut R| rng.gen_range(0..=lastIndex + 2);
+
+  let operation= match rng.gen_range(0..12) {
+    0 => LogOperation::Append(lastTerm + rng.gen_range(0..=1)),
+    1 => LogOperation::AppendConfig(lastTerm + rng.gen_range(0..=1)),
+
+    // Entries following an uncommitted (or the last committed) entry, whose terms don't regress.
+    2 | 3 => {
+      let firstIndex= rng.gen_range(commitIndex + 1..=lastIndex + 1);
+      let mut term= log.getTerm(firstIndex - 1)?.unwrap_or_default( ) + rng.gen_range(0..=2);
+
+      let entries= (firstIndex..firstIndex + rng.gen_range(1..=6))
+        .map(|index| {
+          term += rng.gen_range(0..=1);
+          let config= rng.gen_bool(0.1).then(ClusterConfig::default);
+          let command= config.is_none( ).then(| | vec![rng.gen( )]);
+          LogEntry { index, term, command, config, requestId: None }
+        })
+        .collect( );
+      LogOperation::Splice(entries)
+    },
+
+    4 => LogOperation::Truncate(rng.gen_range(commitIndex..=lastIndex + 1)),
+    5 => LogOperation::Commit(rng.gen_range(commitIndex..=lastIndex + 1)),
+    6 => LogOperation::Snapshot(rng.gen_range(snapshotIndex..=commitIndex)),
+
+    // A snapshot matching a stored entry (so that the entries following it are retained), or one
+    // which doesn't.
+    7 => {
+      let index= rng.gen_range(commitIndex + 1..=lastIndex + 2);
+      let term= match log.getTerm(index)? {
+        Some(term) if rng.gen_bool(0.5) => term,
+        _ => lastTerm + 1
+      };
+      LogOperation::InstallSnapshot(index, term)
+    },
+
+    8 => LogOperation::Get(anyIndex(rng)),
+    9 => LogOperation::GetTerm(anyIndex(rng)),
+    10 => LogOperation::Has(anyIndex(rng), rng.gen_range(0..=lastTerm + 1)),
+    _ => match rng.gen_range(0..4) {
+      0 => LogOperation::ScanUnapplied(rng.gen_range(snapshotIndex..=commitIndex)),
+      _ => {
+        let mut bound= |rng: &mut R| match rng.gen_range(0..3) {
+          0 => Bound::Included(anyIndex(rng)),
+          1 => Bound::Excluded(anyIndex(rng)),
+          _ => Bound::Unbounded
+        };
+        LogOperation::Scan(bound(rng), bound(rng))
+      }
+    }
+  };
+  Ok(operation)
+}
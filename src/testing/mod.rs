@@ -0,0 +1,34 @@
+/*
+  Support for the property tests (see tests/fuzz.rs) : generators of random, but structurally
+  valid, inputs. They're shared, so that other tests can reuse them.
+
+  The generators take the random number generator to draw from, so that an input can be reproduced
+  from the seed of the generator.
+*/
+
+pub mod statements;
+pub mod keys;
+pub mod log;
+pub mod numeric;
+pub mod data_dir;
+pub mod sync;
+
+use rand::{seq::SliceRandom, Rng};
+
+// Returns one of the given items, picked uniformly.
+fn pick<'a, T, R: Rng>(rng: &mut R, items: &'a [T]) -> &'a T {
+  items.choose(rng).expect("Can't pick from no items")
+}
+
+// Generates a string of upto the given number of characters. The characters are mostly ASCII, but
+// include the ones which usually need escaping or are multi-byte in UTF-8.
+pub fn generateString<R: Rng>(rng: &mut R, maxLength: usize) -> String {
+  const SPECIAL: &[char]= &['\0', '\'', '"', '\\', '\n', '\t', ' ', '%', '_', 'é', 'ß', '日', '😀', '\u{ff}', '\u{fffd}'];
+
+  let length= rng.gen_range(0..=maxLength);
+  (0..length).map(|_| match rng.gen_range(0..4) {
+                0 => *pick(rng, SPECIAL),
+                _ => rng.gen_range(' '..='~')
+              })
+              .collect( )
+}
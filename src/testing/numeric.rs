@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+use rand::Rng;
+use crate::{
+  result::{Error, ErrorCode, Result},
+  sql::{numeric, types::Value}
+};
+use super::pick;
+
+/*
+  An arithmetic operation on integers (see sql::numeric), along with a reference implementation of
+  it over i128 - which holds the exact result of every operation on i64 operands (exponentiation
+  bails out, once the result leaves the range of i64). It's slow, but obviously correct.
+*/
+#[derive(Debug, Clone, Copy)]
+pub enum ArithmeticOperation {
+  Add(i64, i64),
+  Subtract(i64, i64),
+  Multiply(i64, i64),
+  Divide(i64, i64),
+  Remainder(i64, i64),
+  Exponentiate(i64, i64),
+  Negate(i64),
+  Factorial(i64)
+}
+
+// The outcome of an arithmetic operation on integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticOutcome {
+  Integer(i64),
+  Float,
+  Overflow,
+  DivisionByZero,
+  Undefined
+}
+
+impl ArithmeticOperation {
+  pub fn evaluate(&self) -> Result<Value> {
+    match *self {
+      Self::Add(lhs, rhs) => numeric::add(lhs.into( ), rhs.into( )),
+      Self::Subtract(lhs, rhs) => numeric::subtract(lhs.into( ), rhs.into( )),
+      Self::Multiply(lhs, rhs) => numeric::multiply(lhs.into( ), rhs.into( )),
+      Self::Divide(lhs, rhs) => numeric::divide(lhs.into( ), rhs.into( )),
+      Self::Remainder(lhs, rhs) => numeric::remainder(lhs.into( ), rhs.into( )),
+      Self::Exponentiate(lhs, rhs) => numeric::exponentiate(lhs.into( ), rhs.into( )),
+      Self::Negate(operand) => numeric::negate(operand.into( )),
+      Self::Factorial(operand) => numeric::factorial(operand.into( ))
+    }
+  }
+
+  // Computes the outcome of the operation, using the reference implementation.
+  pub fn expected(&self) -> ArithmeticOutcome {
+    let inRange= |result: i128| i64::try_from(result).map_or(ArithmeticOutcome::Overflow, ArithmeticOutcome::Integer);
+
+    match *self {
+      Self::Add(lhs, rhs) => inRange(i128::from(lhs) + i128::from(rhs)),
+      Self::Subtract(lhs, rhs) => inRange(i128::from(lhs) - i128::from(rhs)),
+      Self::Multiply(lhs, rhs) => inRange(i128::from(lhs) * i128::from(rhs)),
+
+      Self::Divide(_, 0) | Self::Remainder(_, 0) => ArithmeticOutcome::DivisionByZero,
+      Self::Divide(lhs, rhs) => inRange(i128::from(lhs) / i128::from(rhs)),
+      Self::Remainder(lhs, rhs) => inRange(i128::from(lhs) % i128::from(rhs)),
+
+      Self::Exponentiate(_, rhs) if u32::try_from(rhs).is_err( ) => ArithmeticOutcome::Float,
+      Self::Exponentiate(lhs, rhs) => match lhs {
+        0 => ArithmeticOutcome::Integer(i64::from(rhs == 0)),
+        1 => ArithmeticOutcome::Integer(1),
+        -1 => ArithmeticOutcome::Integer(if rhs % 2 == 0 { 1 } else { -1 }),
+
+        // The magnitude at least doubles with every factor, so this takes atmost 64 multiplications.
+        _ => {
+          let mut power= 1i128;
+          for _ in 0..rhs {
+            power *= i128::from(lhs);
+            if power.abs( ) > 1 << 63 {
+              return ArithmeticOutcome::Overflow}
+          }
+          inRange(power)
+        }
+      },
+
+      Self::Negate(operand) => inRange(-i128::from(operand)),
+
+      Self::Factorial(operand) if operand < 0 => ArithmeticOutcome::Undefined,
+      Self::Factorial(operand) => {
+        let mut product= 1i128;
+        for factor in 1..=i128::from(operand) {
+          product *= factor;
+          if product > i128::from(i64::MAX) {
+            return ArithmeticOutcome::Overflow}
+        }
+        inRange(product)
+      }
+    }
+  }
+}
+
+impl ArithmeticOutcome {
+  // Returns whether the result of the operation is this outcome.
+  pub fn matches(&self, result: &Result<Value>) -> bool {
+    match (self, result) {
+      (Self::Integer(expected), Ok(Value::Integer(actual))) => expected == actual,
+      (Self::Float, Ok(Value::Float(_))) => true,
+      (Self::Overflow, Err(Error::Value(ErrorCode::NumericValueOutOfRange, _))) => true,
+      (Self::DivisionByZero, Err(Error::Value(ErrorCode::DivisionByZero, _))) => true,
+      (Self::Undefined, Err(Error::Value(ErrorCode::InvalidParameterValue, _))) => true,
+      _ => false
+    }
+  }
+}
+
+// Generates an arithmetic operation on integers, mostly from around the edges of i64.
+pub fn generateArithmeticOperation<R: Rng>(rng: &mut R) -> ArithmeticOperation {
+  let (lhs, rhs)= (generateInteger(rng), generateInteger(rng));
+
+  match rng.gen_range(0..8) {
+    0 => ArithmeticOperation::Add(lhs, rhs),
+    1 => ArithmeticOperation::Subtract(lhs, rhs),
+    2 => ArithmeticOperation::Multiply(lhs, rhs),
+    3 => ArithmeticOperation::Divide(lhs, rhs),
+    4 => ArithmeticOperation::Remainder(lhs, rhs),
+
+    // Mostly small exponents, which the result of can be in range.
+    5 => match rng.gen_bool(0.8) {
+      true => ArithmeticOperation::Exponentiate(lhs, rng.gen_range(-2..=70)),
+      false => ArithmeticOperation::Exponentiate(lhs, rhs)
+    },
+
+    6 => ArithmeticOperation::Negate(lhs),
+    _ => ArithmeticOperation::Factorial(rng.gen_range(-3..=25))
+  }
+}
+
+/*
+  Compares an integer with a float exactly, using a reference implementation : the float is decoded
+  into its mantissa and exponent (float = mantissa * 2^exponent), and then both sides are scaled to
+  integers over i128.
+*/
+pub fn compareIntegerWithFloat(integer: i64, float: f64) -> Option<Ordering> {
+  if float.is_nan( ) {
+    return None}
+  if float.is_infinite( ) {
+    return Some(if float > 0.0 { Ordering::Less } else { Ordering::Greater })}
+
+  let bits= float.to_bits( );
+  let biasedExponent= ((bits >> 52) & 0x7ff) as i32;
+  let fraction= i128::from(bits & ((1 << 52) - 1));
+
+  // Subnormal floats don't have the implicit leading 1.
+  let (mantissa, exponent)= match biasedExponent {
+    0 => (fraction, -1074),
+    _ => (fraction | (1 << 52), biasedExponent - 1075)
+  };
+  let mantissa= if bits >> 63 == 1 { -mantissa } else { mantissa };
+  let integer= i128::from(integer);
+
+  // NOTE : |mantissa| < 2^53 and |integer| <= 2^63.
+  Some(match exponent {
+    // Beyond 2^64, the float is out of the range of i64 (its mantissa isn't 0, since it's normal).
+    exponent if exponent > 64 => 0.cmp(&mantissa),
+    exponent if exponent >= 0 => integer.cmp(&(mantissa << exponent)),
+
+    // Otherwise, integer * 2^-exponent is compared with the mantissa. Scaled by 2^53 (or more), a
+    // non-zero integer is beyond the mantissa.
+    exponent if -exponent >= 53 && integer != 0 => integer.cmp(&0),
+    exponent if -exponent >= 53 => 0.cmp(&mantissa),
+    exponent => (integer << -exponent).cmp(&mantissa)
+  })
+}
+
+/*
+  Generates an integer and a float to compare, mostly close to each other : the float is often the
+  nearest one to the integer (or one adjacent to it), so that they compare equal or differ only by
+  rounding.
+*/
+pub fn generateComparison<R: Rng>(rng: &mut R) -> (i64, f64) {
+  let integer= generateInteger(rng);
+
+  let float= match rng.gen_range(0..6) {
+    0 => integer as f64,
+    1 => f64::from_bits((integer as f64).to_bits( ).saturating_add(rng.gen_range(1..=3))),
+    2 => f64::from_bits((integer as f64).to_bits( ).saturating_sub(rng.gen_range(1..=3))),
+    3 => integer as f64 + rng.gen_range(-1.0..1.0),
+
+    4 => *pick(rng, &[
+      0.0, -0.0, 0.5, -0.5, f64::INFINITY, f64::NEG_INFINITY, f64::NAN, f64::MIN_POSITIVE, 5e-324,
+      9_223_372_036_854_775_808.0, -9_223_372_036_854_775_808.0, 9_007_199_254_740_992.0, f64::MAX, f64::MIN
+    ]),
+    _ => f64::from_bits(rng.gen( ))
+  };
+  (integer, float)
+}
+
+// Generates an integer, mostly from around the edges : the bounds of i64, and the powers of two
+// where the floats can't represent every integer anymore (2^53) or where i64 ends (2^63).
+fn generateInteger<R: Rng>(rng: &mut R) -> i64 {
+  const EDGES: &[i64]= &[
+    0, 1, -1, 2, -2, 20, 21,
+    i64::MAX, i64::MIN, i64::MAX - 1, i64::MIN + 1,
+    1 << 31, 1 << 32, 1 << 53, (1 << 53) + 1, -(1 << 53) - 1, 1 << 62, -(1 << 62),
+    3_037_000_499, -3_037_000_500
+  ];
+
+  match rng.gen_range(0..4) {
+    0 => *pick(rng, EDGES),
+    1 => pick(rng, EDGES).saturating_add(rng.gen_range(-3..=3)),
+    2 => rng.gen_range(-1000..=1000),
+    _ => rng.gen( )
+  }
+}
@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+use rand::Rng;
+use crate::sql::{
+  datetime,
+  parser::ast::{AlterTableAction, Column, DataType, Expression, InsertSource, JoinConstraint, JoinType, Literal, OnConflict, Operation, Order, SearchField, Statement},
+  schema::ReferentialAction
+};
+use super::{generateString, pick};
+
+/*
+  Generators of statements, which render (using their Display implementations) to SQL that parses
+  back into the same statements.
+
+  So, only the ASTs the parser can produce are generated. For example, an integer literal is never
+  negative (the parser produces the negation of a positive literal instead), the right side of IN is
+  always a subquery, and a parameter placeholder is always numbered (the ? placeholders are parsed
+  into numbered ones too).
+
+  The depth bounds how deeply the expressions (and the subqueries in them) are nested.
+*/
+
+// Depth of the expressions generated by generateStatement( ).
+const EXPRESSION_DEPTH: usize= 4;
+
+// Most of the identifiers can be written unquoted. The rest (keywords, mixed-case ones and the ones
+// with special characters) must be quoted when the statement is rendered.
+const IDENTIFIERS: &[&str]= &[
+  "id", "name", "email", "amount", "price", "total", "users", "orders", "items", "a", "b", "x", "y", "created_at", "_version", "ünïcode",
+  "order", "limit", "key", "Name", "CamelCase", "first name", "say \"hi\"", "1st"
+];
+
+// NOTE : POSITION isn't generated, since its first argument binds only operators with a higher
+// precedance than IN's (see Parser::parseExpressionOperand).
+const FUNCTIONS: &[&str]= &[
+  "count", "sum", "avg", "min", "max", "lower", "upper", "abs", "coalesce", "now", "length", "substr", "trim", "ltrim", "rtrim", "replace"
+];
+
+// Generates an identifier, which is sometimes suffixed with a number.
+pub fn generateIdentifier<R: Rng>(rng: &mut R) -> String {
+  let identifier= pick(rng, IDENTIFIERS).to_string( );
+  match rng.gen_ratio(1, 4) {
+    true => format!("{}{}", identifier, rng.gen_range(0..100)),
+    false => identifier
+  }
+}
+
+// Generates a statement of any kind.
+pub fn generateStatement<R: Rng>(rng: &mut R) -> Statement {
+  match rng.gen_range(0..10) {
+    0 => Statement::Explain { statement: Box::new(generateExplainableStatement(rng)), analyze: rng.gen( ) },
+    1 => generateOtherStatement(rng),
+    _ => generateExplainableStatement(rng)
+  }
+}
+
+// Generates a statement which can be explained (any statement but EXPLAIN).
+fn generateExplainableStatement<R: Rng>(rng: &mut R) -> Statement {
+  match rng.gen_range(0..10) {
+    0 => generateCreateTable(rng),
+    1 => Statement::CreateIndex {
+      name: generateIdentifier(rng),
+      table: generateIdentifier(rng),
+      expression: generateExpression(rng, 2)
+    },
+    2 => generateInsert(rng, EXPRESSION_DEPTH),
+    3 => generateUpdate(rng, EXPRESSION_DEPTH),
+    4 => Statement::Delete { table: generateIdentifier(rng), r#where: generateOptionalExpression(rng, EXPRESSION_DEPTH) },
+    5 => generateOtherStatement(rng),
+    _ => generateSelect(rng, EXPRESSION_DEPTH)
+  }
+}
+
+// Generates one of the statements without any expressions (except SET).
+fn generateOtherStatement<R: Rng>(rng: &mut R) -> Statement {
+  match rng.gen_range(0..14) {
+    // The version is lexed as an integer literal, so it can't exceed i64::MAX.
+    0 => Statement::Begin {
+      readonly: rng.gen( ),
+      asOfVersion: rng.gen_ratio(1, 3).then(|| rng.gen_range(0..=i64::MAX as u64))
+    },
+    1 => Statement::Commit,
+    2 => Statement::Rollback,
+    3 => Statement::DropTable { name: generateIdentifier(rng), ifExists: rng.gen( ) },
+    4 => Statement::ShowTables,
+    5 => Statement::DescribeTable(generateIdentifier(rng)),
+    6 => Statement::Status,
+    7 => Statement::Truncate(generateIdentifier(rng)),
+    8 => Statement::Copy { table: generateIdentifier(rng) },
+    9 => {
+      let action= match rng.gen( ) {
+        true => AlterTableAction::RenameTo(generateIdentifier(rng)),
+        false => AlterTableAction::RenameColumn { column: generateIdentifier(rng), newName: generateIdentifier(rng) }
+      };
+      Statement::AlterTable { name: generateIdentifier(rng), action }
+    },
+    10 => Statement::ShowVariable(generateIdentifier(rng)),
+    11 => Statement::ShowVariables,
+    12 => Statement::Analyze(rng.gen::<bool>( ).then(|| generateIdentifier(rng))),
+    _ => Statement::Set { variable: generateIdentifier(rng), value: generateExpression(rng, 2) }
+  }
+}
+
+fn generateCreateTable<R: Rng>(rng: &mut R) -> Statement {
+  let columns= (0..rng.gen_range(1..=4)).map(|_| generateColumn(rng)).collect( );
+  Statement::CreateTable { name: generateIdentifier(rng), columns, ifNotExists: rng.gen( ) }
+}
+
+fn generateColumn<R: Rng>(rng: &mut R) -> Column {
+  let references= rng.gen_ratio(1, 4).then(|| generateIdentifier(rng));
+
+  // ON DELETE can only follow REFERENCES.
+  let onDelete= match references {
+    Some(_) => *pick(rng, &[ReferentialAction::Restrict, ReferentialAction::Cascade, ReferentialAction::SetNull]),
+    None => ReferentialAction::Restrict
+  };
+
+  Column {
+    name: generateIdentifier(rng),
+    dataType: pick(rng, &[DataType::Boolean, DataType::Integer, DataType::Float, DataType::String, DataType::Date, DataType::Timestamp]).clone( ),
+    unique: rng.gen( ),
+    nullable: *pick(rng, &[None, Some(true), Some(false)]),
+    default: rng.gen_ratio(1, 3).then(|| generateExpression(rng, 2)),
+    primaryKey: rng.gen_ratio(1, 4),
+    index: rng.gen_ratio(1, 4),
+    references,
+    onDelete
+  }
+}
+
+fn generateInsert<R: Rng>(rng: &mut R, depth: usize) -> Statement {
+  let columns= rng.gen_bool(0.5).then(|| generateList(rng, 1, 3, generateIdentifier));
+
+  let source= match rng.gen_ratio(1, 4) {
+    true => InsertSource::Select(Box::new(generateSelect(rng, depth - 1))),
+    false => InsertSource::Values(generateList(rng, 1, 3, |rng| generateList(rng, 1, 3, |rng| generateExpression(rng, depth - 1))))
+  };
+
+  let onConflict= rng.gen_ratio(1, 4).then(|| OnConflict {
+    column: generateIdentifier(rng),
+    updates: rng.gen_bool(0.5).then(|| generateUpdates(rng, depth))
+  });
+
+  Statement::Insert { table: generateIdentifier(rng), columns, source, onConflict }
+}
+
+fn generateUpdate<R: Rng>(rng: &mut R, depth: usize) -> Statement {
+  let updates= generateUpdates(rng, depth);
+
+  Statement::Update { table: generateIdentifier(rng), updates, r#where: generateOptionalExpression(rng, depth - 1) }
+}
+
+// Generates the column = expression list of an UPDATE SET.
+fn generateUpdates<R: Rng>(rng: &mut R, depth: usize) -> BTreeMap<String, Expression> {
+  (0..rng.gen_range(1..=3)).map(|_| (generateIdentifier(rng), generateExpression(rng, depth - 1)))
+                           .collect( )
+}
+
+// Generates a SELECT statement, whose expressions are nested upto the given depth.
+pub fn generateSelect<R: Rng>(rng: &mut R, depth: usize) -> Statement {
+  let depth= depth.max(1);
+
+  // No selections stand for SELECT *. The columns of a table (table.*) can't be aliased.
+  let selections= match rng.gen_ratio(1, 5) {
+    true => vec![ ],
+    false => generateList(rng, 1, 4, |rng| match rng.gen_ratio(1, 8) {
+      true => (Expression::QualifiedWildcard(generateIdentifier(rng)), None),
+      false => (generateExpression(rng, depth - 1), rng.gen_bool(0.5).then(|| generateIdentifier(rng)))
+    })
+  };
+
+  let from= match rng.gen_ratio(1, 5) {
+    true => vec![ ],
+    false => generateList(rng, 1, 2, |rng| generateSearchField(rng, depth - 1))
+  };
+
+  let groupBy= match rng.gen_ratio(1, 4) {
+    true => generateList(rng, 1, 2, |rng| generateExpression(rng, depth - 1)),
+    false => vec![ ]
+  };
+
+  let order= match rng.gen_ratio(1, 4) {
+    true => generateList(rng, 1, 2, |rng| (generateExpression(rng, depth - 1), *pick(rng, &[Order::Ascending, Order::Descending]))),
+    false => vec![ ]
+  };
+
+  Statement::Select {
+    distinct: rng.gen_ratio(1, 5),
+    selections,
+    from,
+    r#where: generateOptionalExpression(rng, depth - 1),
+    groupBy,
+    having: generateOptionalExpression(rng, depth - 1),
+    order,
+    limit: generateOptionalExpression(rng, 1),
+    offset: generateOptionalExpression(rng, 1)
+  }
+}
+
+// Generates a table, or a chain of joins (which the parser nests on the left).
+fn generateSearchField<R: Rng>(rng: &mut R, depth: usize) -> SearchField {
+  let mut searchField= generateTable(rng);
+
+  for _ in 0..rng.gen_range(0..=2) {
+    let r#type= pick(rng, &[JoinType::Cross, JoinType::Inner, JoinType::Left, JoinType::Right]).clone( );
+    let constraint= match (&r#type, rng.gen_range(0..4)) {
+      (JoinType::Cross, _) => JoinConstraint::None,
+      (_, 0) => JoinConstraint::Natural,
+      (_, 1) => JoinConstraint::Using((0..rng.gen_range(1..=2)).map(|_| generateIdentifier(rng)).collect( )),
+      _ => JoinConstraint::On(generateExpression(rng, depth))
+    };
+
+    searchField= SearchField::Join { left: Box::new(searchField), right: Box::new(generateTable(rng)), r#type, constraint };
+  }
+  searchField
+}
+
+fn generateTable<R: Rng>(rng: &mut R) -> SearchField {
+  SearchField::Table { name: generateIdentifier(rng), alias: rng.gen_ratio(1, 3).then(|| generateIdentifier(rng)) }
+}
+
+// Generates an expression (half the time), nested upto the given depth.
+fn generateOptionalExpression<R: Rng>(rng: &mut R, depth: usize) -> Option<Expression> {
+  rng.gen_bool(0.5).then(|| generateExpression(rng, depth))
+}
+
+// Generates an expression, nested upto the given depth.
+pub fn generateExpression<R: Rng>(rng: &mut R, depth: usize) -> Expression {
+  if depth == 0 || rng.gen_ratio(1, 4) {
+    return generateOperand(rng)}
+
+  let operand= |rng: &mut R| Box::new(generateExpression(rng, depth - 1));
+
+  let lhs= operand(rng);
+  let operation= match rng.gen_range(0..25) {
+    0 => Operation::And(lhs, operand(rng)),
+    1 => Operation::Or(lhs, operand(rng)),
+    2 => Operation::Not(lhs),
+
+    3 => Operation::Equal(lhs, operand(rng)),
+    4 => Operation::NotEqual(lhs, operand(rng)),
+    5 => Operation::GreaterThan(lhs, operand(rng)),
+    6 => Operation::GreaterThanOrEqual(lhs, operand(rng)),
+    7 => Operation::LessThan(lhs, operand(rng)),
+    8 => Operation::LessThanOrEqual(lhs, operand(rng)),
+    9 => Operation::IsNull(lhs),
+
+    10 => Operation::Add(lhs, operand(rng)),
+    11 => Operation::Subtract(lhs, operand(rng)),
+    12 => Operation::Multiply(lhs, operand(rng)),
+    13 => Operation::Divide(lhs, operand(rng)),
+    14 => Operation::Modulo(lhs, operand(rng)),
+    15 => Operation::Exponentiate(lhs, operand(rng)),
+    16 => Operation::Negate(lhs),
+    17 => Operation::Assert(lhs),
+    18 => Operation::Factorial(lhs),
+
+    19 => Operation::Like(lhs, operand(rng), None),
+    20 => Operation::Like(lhs, operand(rng), Some(operand(rng))),
+    21 => Operation::Concatenate(lhs, operand(rng)),
+
+    22 => Operation::In(lhs, Box::new(Expression::Subquery(Box::new(generateSelect(rng, depth - 1))))),
+
+    23 => return generateFunctionCall(rng, depth - 1),
+    _ => return generateCase(rng, depth - 1)
+  };
+  operation.into( )
+}
+
+fn generateFunctionCall<R: Rng>(rng: &mut R, depth: usize) -> Expression {
+  let name= pick(rng, FUNCTIONS).to_string( );
+
+  let mut arguments= (0..rng.gen_range(0..=3)).map(|_| generateExpression(rng, depth)).collect::<Vec<_>>( );
+
+  // Only the first argument can be DISTINCT.
+  if let Some(first)= arguments.first_mut( ) {
+    if rng.gen_ratio(1, 4) {
+      *first= Expression::Distinct(Box::new(first.clone( )));}
+  }
+
+  Expression::FunctionCall(name, arguments)
+}
+
+fn generateCase<R: Rng>(rng: &mut R, depth: usize) -> Expression {
+  let operand= rng.gen_bool(0.5).then(|| Box::new(generateExpression(rng, depth)));
+  let branches= generateList(rng, 1, 3, |rng| (generateExpression(rng, depth), generateExpression(rng, depth)));
+  let otherwise= rng.gen_bool(0.5).then(|| Box::new(generateExpression(rng, depth)));
+
+  Operation::Case { operand, branches, otherwise }.into( )
+}
+
+// Generates an expression without operators : a field, a literal, a parameter or a subquery.
+fn generateOperand<R: Rng>(rng: &mut R) -> Expression {
+  match rng.gen_range(0..20) {
+    0..=5 => Expression::Field(None, generateIdentifier(rng)),
+    6..=7 => Expression::Field(Some(generateIdentifier(rng)), generateIdentifier(rng)),
+    8 => Expression::Parameter(rng.gen_range(1..=9)),
+    9 => Expression::Subquery(Box::new(generateSelect(rng, 1))),
+    _ => generateLiteral(rng).into( )
+  }
+}
+
+pub fn generateLiteral<R: Rng>(rng: &mut R) -> Literal {
+  match rng.gen_range(0..8) {
+    0 => Literal::Null,
+    1 => Literal::Boolean(rng.gen( )),
+
+    // Negative numbers are parsed as negations.
+    2 => Literal::Integer(match rng.gen( ) {
+      true => *pick(rng, &[0, 1, 42, i64::MAX]),
+      false => rng.gen_range(0..i64::MAX)
+    }),
+    3 => Literal::Float(match rng.gen_range(0..3) {
+      0 => *pick(rng, &[0.0, 0.5, 1.0, 1e-300, 1e300, f64::MAX, f64::MIN_POSITIVE, f64::INFINITY, f64::NAN]),
+      1 => rng.gen_range(0.0..1e6),
+      _ => f64::from_bits(rng.gen_range(0..f64::INFINITY.to_bits( )))
+    }),
+
+    4..=5 => Literal::String(generateString(rng, 12)),
+
+    6 => Literal::Date(rng.gen_range(minDate( )..=maxDate( ))),
+    _ => Literal::Timestamp(rng.gen_range(minDate( ) * datetime::MICROSECONDS_PER_DAY .. (maxDate( ) + 1) * datetime::MICROSECONDS_PER_DAY))
+  }
+}
+
+fn minDate( ) -> i64 {
+  datetime::parseDate("0001-01-01").expect("The first supported date doesn't parse")
+}
+
+fn maxDate( ) -> i64 {
+  datetime::parseDate("9999-12-31").expect("The last supported date doesn't parse")
+}
+
+// Generates a list of items, with their number in the given (inclusive) range.
+fn generateList<R: Rng, T>(rng: &mut R, min: usize, max: usize, mut generate: impl FnMut(&mut R) -> T) -> Vec<T> {
+  (0..rng.gen_range(min..=max)).map(|_| generate(rng)).collect( )
+}
+
+// Fragments of SQL, which arbitrary inputs are made up of (see generateInput( )). They're mostly
+// the ones starting / ending tokens, comments and numbers, where the lexer has edge cases.
+const FRAGMENTS: &[&str]= &[
+  "SELECT", "FROM", "WHERE", "GROUP BY", "HAVING", "ORDER BY", "LIMIT", "OFFSET", "INSERT INTO", "VALUES",
+  "UPDATE", "SET", "DELETE", "CREATE TABLE", "CREATE INDEX", "ON", "PRIMARY KEY", "DEFAULT", "REFERENCES",
+  "BEGIN", "AS OF SYSTEM TIME", "EXPLAIN", "ANALYZE", "NOT", "IN", "IS", "NULL", "AND", "OR", "LIKE", "ESCAPE",
+  "CASE", "WHEN", "THEN", "ELSE", "END", "DISTINCT", "DATE", "TIMESTAMP", "JOIN", "LEFT", "CROSS", "INT",
+  "(", ")", ",", ";", ".", "*", "+", "-", "/", "^", "%", "!", "=", "!=", "<>", "<=", ">=", "<", ">", "?",
+  "$", "$0", "$1", "$99999999999", "'", "''", "'text'", "\"", "\"\"", "\"Quoted\"", "--", "/*", "*/", "\n",
+  "0", "1", "0x", "0xFF", "0x8000000000000000", "1_000", "1__0", "_1", "1.", ".5", "1.5.2", "1e", "1e+", "1e308",
+  "1e309", "9223372036854775807", "9223372036854775808", "NAN", "INFINITY", "count(*)", "now()", "t.a", "t.*", "x"
+];
+
+/*
+  Generates an arbitrary input for the parser, which is
+  (a) a random string,
+  (b) a random sequence of SQL fragments,
+  (c) the SQL of a generated statement, with parts of it deleted, repeated or replaced,
+  (d) or a deeply nested expression (like (((... or NOT NOT ...).
+*/
+pub fn generateInput<R: Rng>(rng: &mut R) -> String {
+  match rng.gen_range(0..8) {
+    0 => generateString(rng, 64),
+
+    1..=2 => (0..rng.gen_range(0..=24)).map(|_| *pick(rng, FRAGMENTS))
+                                        .collect::<Vec<_>>( )
+                                        .join(match rng.gen( ) { true => " ", false => "" }),
+
+    3..=6 => {
+      let mut input= generateStatement(rng).to_string( ).chars( ).collect::<Vec<_>>( );
+      for _ in 0..rng.gen_range(1..=3) {
+        let start= rng.gen_range(0..=input.len( ));
+        let end= rng.gen_range(start..=input.len( ).min(start + 8));
+
+        match rng.gen_range(0..3) {
+          0 => { input.drain(start..end); },
+          1 => { input.splice(start..start, input[start..end].to_vec( )); },
+          _ => { input.splice(start..end, pick(rng, FRAGMENTS).chars( )); }
+        }
+      }
+      input.into_iter( ).collect( )
+    },
+
+    _ => {
+      let (prefix, suffix)= *pick(rng, &[("(", ")"), ("-", ""), ("NOT ", ""), ("+", ""), ("2 ^ ", ""), ("1 + ", ""), ("(SELECT ", ")"), ("CASE WHEN ", " THEN 1 END"), ("x IN (SELECT ", ")")]);
+      let nesting= rng.gen_range(1..=10_000);
+      format!("SELECT {}1{}", prefix.repeat(nesting), suffix.repeat(nesting))
+    }
+  }
+}
@@ -0,0 +1,157 @@
+use std::{
+  fmt::Display,
+  ops::Bound,
+  sync::{Arc, Mutex, MutexGuard},
+  thread,
+  time::Duration
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::{
+  result::{Error, Result},
+  storage::{
+    engine::{memory::Memory, KeyRange, PendingFlush, ScanIterator, StorageEngine, StorageEngineStatus, WriteBatch},
+    sync::SyncPolicy
+  }
+};
+
+/*
+  A storage engine for checking the durability of the stores on top of it (see SyncPolicy). It keeps
+  the writes in memory, along with a copy of them as of the last successful flush : that copy is all
+  that survives a crash (see crash( )).
+
+  The flushes take the given latency (like an fsync does), and fail at the given rate (or all of
+  them, while failFlushes( ) is on) - leaving the copy as it was, like a failed fsync leaves the
+  writes in doubt. The engine isn't locked while a flush takes its latency (see
+  StorageEngine::beginFlush( )).
+
+  Its clones share the state, so that a clone can be crashed while a store owns the engine.
+*/
+#[derive(Clone)]
+pub struct FaultyEngine {
+  state: Arc<Mutex<FaultyEngineState>>,
+
+  flushLatency: Duration,
+  flushFailureRate: f64
+}
+
+struct FaultyEngineState {
+  live: Memory,
+  durable: Memory,
+
+  // Number of successful flushes.
+  flushes: u64,
+
+  // Whether every flush fails, whatever the failure rate.
+  failingFlushes: bool,
+
+  rng: StdRng
+}
+
+impl FaultyEngine {
+  pub fn new(seed: u64, flushLatency: Duration, flushFailureRate: f64) -> Self {
+    let state= FaultyEngineState { live: Memory::new( ), durable: Memory::new( ), flushes: 0, failingFlushes: false, rng: StdRng::seed_from_u64(seed) };
+    Self { state: Arc::new(Mutex::new(state)), flushLatency, flushFailureRate }
+  }
+
+  // Returns a new engine, holding only the writes which were flushed (without any faults).
+  pub fn crash(&self) -> Result<Self> {
+    let durable= self.lock( ).durable.scan((Bound::Unbounded, Bound::Unbounded))
+                                     .map(|item| item.map(|(key, value)| (key, Some(value))))
+                                     .collect::<Result<WriteBatch>>( )?;
+
+    let engine= Self::new(0, Duration::ZERO, 0.0);
+    let mut state= engine.lock( );
+    state.live.writeBatch(durable.clone( ))?;
+    state.durable.writeBatch(durable)?;
+    drop(state);
+
+    Ok(engine)
+  }
+
+  pub fn flushes(&self) -> u64 {
+    self.lock( ).flushes
+  }
+
+  // Makes every flush fail (like once the disk is full), until it's turned off.
+  pub fn failFlushes(&self, failing: bool) {
+    self.lock( ).failingFlushes= failing;
+  }
+
+  fn lock(&self) -> MutexGuard<'_, FaultyEngineState> {
+    self.state.lock( ).expect("The state of the faulty engine is poisoned")
+  }
+}
+
+impl Display for FaultyEngine {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("faulty(memory)")
+  }
+}
+
+impl StorageEngine for FaultyEngine {
+  fn set(&mut self, key: &[u8], value: Vec<u8>) -> Result<( )> {
+    self.lock( ).live.set(key, value)
+  }
+
+  fn flush(&mut self) -> Result<( )> {
+    self.beginFlush( )?( )
+  }
+
+  // Takes a copy of the writes, which becomes the durable one once the flush has taken its latency
+  // (unless it fails). The engine isn't locked meanwhile.
+  fn beginFlush(&mut self) -> Result<PendingFlush> {
+    let live= self.lock( ).live.scan((Bound::Unbounded, Bound::Unbounded))
+                               .map(|item| item.map(|(key, value)| (key, Some(value))))
+                               .collect::<Result<WriteBatch>>( )?;
+
+    let (state, flushLatency, flushFailureRate)= (self.state.clone( ), self.flushLatency, self.flushFailureRate);
+    Ok(Box::new(move | | {
+      thread::sleep(flushLatency);
+
+      let mut state= state.lock( ).expect("The state of the faulty engine is poisoned");
+      if state.failingFlushes || state.rng.gen_bool(flushFailureRate) {
+        return Err(Error::Internal("Injected flush failure".to_string( )))}
+
+      state.durable= Memory::new( );
+      state.durable.writeBatch(live)?;
+      state.flushes += 1;
+      Ok(( ))
+    }))
+  }
+
+  fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    self.lock( ).live.get(key)
+  }
+
+  fn delete(&mut self, key: &[u8]) -> Result<( )> {
+    self.lock( ).live.delete(key)
+  }
+
+  fn writeBatch(&mut self, batch: WriteBatch) -> Result<( )> {
+    self.lock( ).live.writeBatch(batch)
+  }
+
+  // The pairs are collected, since the iterator can't hold the lock.
+  fn scan(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    let pairs= self.lock( ).live.scan(range).collect::<Vec<_>>( );
+    Box::new(pairs.into_iter( ))
+  }
+
+  fn scanRev(&mut self, range: KeyRange) -> ScanIterator<'_> {
+    let pairs= self.lock( ).live.scanRev(range).collect::<Vec<_>>( );
+    Box::new(pairs.into_iter( ))
+  }
+
+  fn status(&self) -> Result<StorageEngineStatus> {
+    Ok(StorageEngineStatus { name: self.to_string( ), ..self.lock( ).live.status( )? })
+  }
+}
+
+// Generates a sync policy, with a short interval (of a few milliseconds) for the interval policy.
+pub fn generateSyncPolicy<R: Rng>(rng: &mut R) -> SyncPolicy {
+  match rng.gen_range(0..3) {
+    0 => SyncPolicy::Always,
+    1 => SyncPolicy::Interval(Duration::from_millis(rng.gen_range(1..=3))),
+    _ => SyncPolicy::Never
+  }
+}
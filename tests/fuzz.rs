@@ -0,0 +1,545 @@
+#![allow(non_snake_case)]
+
+use std::{
+  cell::{Cell, RefCell},
+  cmp::Ordering,
+  collections::BTreeMap,
+  ops::Bound,
+  panic::{self, AssertUnwindSafe},
+  sync::{Barrier, Once},
+  thread,
+  time::{Duration, Instant}
+};
+use distributed_sql_based_database_in_rust::{
+  raft::log::Log,
+  sql::{numeric, parser::{ast::{Expression, Literal, Operation}, Parser}, types::Value},
+  result::{Error, ErrorCode},
+  storage::{data_dir::{DataDir, FORMAT_VERSION}, engine::memory::Memory, keycode, mvcc::{WriteLimits, MVCC}, sync::SyncPolicy},
+  testing::{
+    data_dir::generateDataDirLayout,
+    keys::{compareKeys, generateKeyPair, CompositeKey},
+    log::generateLogOperation,
+    numeric::{compareIntegerWithFloat, generateArithmeticOperation, generateComparison},
+    statements::{generateInput, generateStatement},
+    sync::{generateSyncPolicy, FaultyEngine}
+  }
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// Environment variables, which set the seed the cases are generated from (a random one, unless
+// it's set) and the number of cases checked per target.
+const SEED_VARIABLE: &str= "FUZZ_SEED";
+const ITERATIONS_VARIABLE: &str= "FUZZ_ITERATIONS";
+
+// Number of cases checked per target, unless FUZZ_ITERATIONS is set. It's kept low, so that cargo
+// test stays quick. Longer runs set it (along with --release).
+const DEFAULT_ITERATIONS: u64= 200;
+
+// Number of operations applied to the logs, in a case of the log target.
+const LOG_OPERATIONS: usize= 100;
+
+// Number of commits made to the store, in a case of the sync target.
+const SYNC_COMMITS: u64= 20;
+
+// How long a flush of the storage engine takes, in a case of the groupcommit target.
+const GROUP_COMMIT_FLUSH_LATENCY: Duration= Duration::from_micros(200);
+
+// A property checked against randomly generated cases. The check generates a case using the given
+// random number generator, and returns a description of the case if the property doesn't hold.
+struct Target {
+  name: &'static str,
+  check: fn(&mut StdRng) -> Option<String>
+}
+
+thread_local! {
+  // Whether the panics of the thread are being caught (see catchPanic( )), and the message of the
+  // last one caught, along with its location.
+  static CATCHING: Cell<bool>= const { Cell::new(false) };
+  static PANIC: RefCell<Option<String>>= const { RefCell::new(None) };
+}
+
+static PANIC_HOOK: Once= Once::new( );
+
+/*
+  Runs property tests (like a fuzzer, but without coverage guidance) against randomly generated
+  cases. The targets are :
+
+    parse : the parser never panics on arbitrary input (it returns either a statement or an error).
+
+    roundtrip : a generated statement renders to SQL (see the Display implementations of the AST)
+    which parses back into the same statement.
+
+    keycode : the byte-wise order of the encodings of two composite keys matches the logical order
+    of the keys, and the encodings decode back into the keys.
+
+    log : the Raft log behaves the same with its cache of the recent entries (of a small capacity,
+    so that they're evicted often) as without it, across interleaved appends, splices, truncations,
+    compactions and reads. And the terms it keeps in memory match the stored entries.
+
+    arithmetic : integer arithmetic (see sql::numeric) matches a reference implementation over i128,
+    erroring out exactly when the result is out of the range of i64.
+
+    comparison : integers and floats compare (in SQL, as well) the same as by a reference
+    implementation, which decodes the float into its mantissa and exponent. And an integer equal to
+    a float converts to it, and back, exactly.
+
+    datadir : a data directory left behind by a crashed run (or by a server of another version)
+    opens, only if it's of the supported format version (or a new one). Once opened, it has the
+    standard subdirectories and none of the orphaned temporary files, and it can't be opened again
+    until it's closed.
+
+    sync : commits to a store under a sync policy (see SyncPolicy), over an engine whose flushes
+    fail at times, survive a crash once they're flushed : every commit acknowledged under the always
+    policy, every commit before an explicit flush, and every commit once the background task of the
+    interval policy has caught up. The store recovers to the state after some prefix of its commits.
+
+    groupcommit : concurrent writers committing under the always policy share the flushes, so that
+    there are fewer flushes than commits. And every commit survives a crash.
+
+  Usage : [FUZZ_SEED=<seed>] [FUZZ_ITERATIONS=<count>] cargo test [--release] --test fuzz [<target>]
+
+  Each target is a test of its own. Each case is generated from a seed of its own (the seed given,
+  plus the number of the case), which is reported along with the failing case. So, it can be checked
+  again alone, with FUZZ_SEED=<seed of the case> FUZZ_ITERATIONS=1. Without FUZZ_SEED, a random seed
+  is picked.
+
+  A panic is reported as a failure of the case. The failing input of the parse target is minimized
+  (by removing the characters which aren't needed to cause the panic) before it's reported.
+*/
+#[test]
+fn parse( ) {
+  fuzz(&Target { name: "parse", check: checkParse })
+}
+
+#[test]
+fn roundtrip( ) {
+  fuzz(&Target { name: "roundtrip", check: checkRoundtrip })
+}
+
+#[test]
+fn keycode( ) {
+  fuzz(&Target { name: "keycode", check: checkKeycode })
+}
+
+#[test]
+fn log( ) {
+  fuzz(&Target { name: "log", check: checkLog })
+}
+
+#[test]
+fn arithmetic( ) {
+  fuzz(&Target { name: "arithmetic", check: checkArithmetic })
+}
+
+#[test]
+fn comparison( ) {
+  fuzz(&Target { name: "comparison", check: checkComparison })
+}
+
+#[test]
+fn datadir( ) {
+  fuzz(&Target { name: "datadir", check: checkDataDir })
+}
+
+#[test]
+fn sync( ) {
+  fuzz(&Target { name: "sync", check: checkSync })
+}
+
+#[test]
+fn groupcommit( ) {
+  fuzz(&Target { name: "groupcommit", check: checkGroupCommit })
+}
+
+// Checks the cases of the target, failing with the first one the property doesn't hold for.
+fn fuzz(target: &Target) {
+  let seed= numberVariable(SEED_VARIABLE).unwrap_or_else(|| rand::thread_rng( ).gen( ));
+  let iterations= numberVariable(ITERATIONS_VARIABLE).unwrap_or(DEFAULT_ITERATIONS);
+
+  // The panics of the cases are reported along with the cases, instead of being printed as they
+  // occur.
+  PANIC_HOOK.call_once(|| {
+    let defaultHook= panic::take_hook( );
+    panic::set_hook(Box::new(move |info| match CATCHING.with(Cell::get) {
+      true => PANIC.with(|panic| *panic.borrow_mut( )= Some(info.to_string( ))),
+      false => defaultHook(info)
+    }));
+  });
+
+  if let Some((case, failure))= (0..iterations).find_map(|case| checkCase(target, seed.wrapping_add(case)).map(|failure| (case, failure))) {
+    panic!("{} failed (case seed {})\n{}", target.name, seed.wrapping_add(case), failure)}
+
+  println!("ok      {} ({} cases, seed {})", target.name, iterations, seed);
+}
+
+// Checks the case generated from the seed, returning a description of the failure, if any.
+fn checkCase(target: &Target, seed: u64) -> Option<String> {
+  let mut rng= StdRng::seed_from_u64(seed);
+  match catchPanic(|| (target.check)(&mut rng)) {
+    Ok(failure) => failure,
+    Err(panic) if target.name == "parse" => {
+      let input= minimize(&generateInput(&mut StdRng::seed_from_u64(seed)), |input| catchPanic(|| parseInput(input)).is_err( ));
+      Some(format!("{}\nminimized input : {:?}", panic, input))
+    },
+    Err(panic) => Some(panic)
+  }
+}
+
+fn checkParse(rng: &mut StdRng) -> Option<String> {
+  parseInput(&generateInput(rng));
+  None
+}
+
+fn parseInput(input: &str) {
+  let _= Parser::new(input).parse( );
+}
+
+fn checkRoundtrip(rng: &mut StdRng) -> Option<String> {
+  let statement= generateStatement(rng);
+  let sql= statement.to_string( );
+
+  match Parser::new(&sql).parse( ) {
+    Ok(parsed) if parsed == statement => None,
+    Ok(parsed) => Some(format!("{}\nparsed into a different statement :\n  generated : {:?}\n  parsed    : {:?}", sql, statement, parsed)),
+    Err(error) => Some(format!("{}\nfailed to parse : {}", sql, error))
+  }
+}
+
+fn checkKeycode(rng: &mut StdRng) -> Option<String> {
+  let (lhs, rhs)= generateKeyPair(rng);
+  let describe= |problem: String| Some(format!("{}\n  lhs : {:?}\n  rhs : {:?}", problem, lhs, rhs));
+
+  let (encodedLhs, encodedRhs)= match (keycode::serialize(&lhs), keycode::serialize(&rhs)) {
+    (Ok(encodedLhs), Ok(encodedRhs)) => (encodedLhs, encodedRhs),
+    (Err(error), _) | (_, Err(error)) => return describe(format!("failed to encode : {}", error))
+  };
+
+  let (expected, actual)= (compareKeys(&lhs, &rhs), encodedLhs.cmp(&encodedRhs));
+  if expected != actual {
+    return describe(format!("keys compare {:?}, but their encodings compare {:?}\n  encoded lhs : {:x?}\n  encoded rhs : {:x?}", expected, actual, encodedLhs, encodedRhs))}
+
+  for (key, encoded) in [(&lhs, &encodedLhs), (&rhs, &encodedRhs)] {
+    match keycode::deserialize::<CompositeKey>(encoded) {
+      Ok(decoded) if compareKeys(&decoded, key).is_eq( ) => { },
+      Ok(decoded) => return describe(format!("{:?} decoded into {:?}", key, decoded)),
+      Err(error) => return describe(format!("{:?} failed to decode : {}", key, error))
+    }
+
+    // Decoding a truncated key must fail (or succeed, if the cut is at a part boundary) without
+    // panicking.
+    let _= keycode::deserialize::<CompositeKey>(&encoded[ ..rng.gen_range(0..=encoded.len( ))]);
+  }
+  None
+}
+
+fn checkLog(rng: &mut StdRng) -> Option<String> {
+  let cacheCapacity= rng.gen_range(1..=8);
+
+  let (mut cached, mut uncached)= match (Log::new(Box::new(Memory::new( )), cacheCapacity, SyncPolicy::default( )), Log::new(Box::new(Memory::new( )), 0, SyncPolicy::default( ))) {
+    (Ok(cached), Ok(uncached)) => (cached, uncached),
+    (Err(error), _) | (_, Err(error)) => return Some(format!("failed to create the log : {}", error))
+  };
+
+  let mut operations= Vec::new( );
+  let describe= |operations: &Vec<_>, problem: String| Some(format!("{}
+  cache capacity : {}
+  operations : {:#?}", problem, cacheCapacity, operations));
+
+  for _ in 0..LOG_OPERATIONS {
+    let operation= match generateLogOperation(rng, &mut uncached) {
+      Ok(operation) => operation,
+      Err(error) => return describe(&operations, format!("failed to generate an operation : {}", error))
+    };
+    operations.push(operation.clone( ));
+
+    let (expected, actual)= (operation.apply(&mut uncached), operation.apply(&mut cached));
+    if expected != actual {
+      return describe(&operations, format!("the logs diverged\n  without the cache : {}\n  with the cache    : {}", expected, actual))}
+
+    let ((snapshotIndex, _), (lastIndex, _))= (uncached.getSnapshotIndexAndTerm( ), uncached.getLastStoredEntryIndexAndTerm( ));
+    for index in (snapshotIndex + 1)..=lastIndex {
+      let (term, entry)= (uncached.getTerm(index), uncached.get(index));
+      match (term, entry) {
+        (Ok(Some(term)), Ok(Some(entry))) if term == entry.term => { },
+        (term, entry) => return describe(&operations, format!("the term {:?} of index {} doesn't match the stored entry {:?}", term, index, entry))
+      }
+    }
+  }
+  None
+}
+
+fn checkArithmetic(rng: &mut StdRng) -> Option<String> {
+  let operation= generateArithmeticOperation(rng);
+  let (expected, actual)= (operation.expected( ), operation.evaluate( ));
+
+  match expected.matches(&actual) {
+    true => None,
+    false => Some(format!("{:?}
+  expected : {:?}
+  actual   : {:?}", operation, expected, actual))
+  }
+}
+
+fn checkComparison(rng: &mut StdRng) -> Option<String> {
+  let (integer, float)= generateComparison(rng);
+  let describe= |problem: String| Some(format!("{}
+  integer : {}
+  float   : {:?}", problem, integer, float));
+
+  let expected= compareIntegerWithFloat(integer, float);
+  let actual= numeric::compareIntegerWithFloat(integer, float);
+  if expected != actual {
+    return describe(format!("expected {:?}, but compared {:?}", expected, actual))}
+
+  // Evaluated in SQL, with the integer on either side.
+  let (integerLiteral, floatLiteral)= (Expression::from(Literal::Integer(integer)), Expression::from(Literal::Float(float)));
+  let comparisons= [
+    (Operation::LessThan(Box::new(integerLiteral.clone( )), Box::new(floatLiteral.clone( ))), expected == Some(Ordering::Less)),
+    (Operation::LessThan(Box::new(floatLiteral.clone( )), Box::new(integerLiteral.clone( ))), expected == Some(Ordering::Greater)),
+    (Operation::Equal(Box::new(floatLiteral), Box::new(integerLiteral)), expected == Some(Ordering::Equal))
+  ];
+  for (comparison, expected) in comparisons {
+    let comparison= Expression::Operation(comparison);
+    match comparison.evaluate(None) {
+      Ok(Value::Boolean(actual)) if actual == expected => { },
+      result => return describe(format!("{} evaluated to {:?}, expected {}", comparison, result, expected))
+    }
+  }
+
+  let equal= expected == Some(Ordering::Equal);
+  if equal != (numeric::integralFloat(float) == Some(integer)) {
+    return describe(format!("the float converts to the integer {:?}", numeric::integralFloat(float)))}
+  if equal && numeric::exactFloat(integer) != Some(float) {
+    return describe(format!("the integer converts to the float {:?}", numeric::exactFloat(integer)))}
+
+  None
+}
+
+fn checkDataDir(rng: &mut StdRng) -> Option<String> {
+  let layout= generateDataDirLayout(rng);
+  let path= std::env::temp_dir( ).join(format!("fuzz-datadir-{}-{:x}", std::process::id( ), rng.gen::<u64>( )));
+
+  let failure= match layout.create(&path) {
+    Ok(( )) => checkOpeningDataDir(&path, layout.expectedError( )),
+    Err(error) => Some(format!("failed to create the layout : {}", error))
+  };
+  let _= std::fs::remove_dir_all(&path);
+
+  failure.map(|failure| format!("{}
+  layout : {:?}", failure, layout))
+}
+
+fn checkOpeningDataDir(path: &std::path::Path, expectedError: Option<ErrorCode>) -> Option<String> {
+  let dataDir= match (DataDir::open(path), expectedError) {
+    (Ok(dataDir), None) => dataDir,
+    (Err(error), Some(expected)) if error.code( ) == expected => return None,
+
+    (Ok(_), Some(expected)) => return Some(format!("opened, but expected to fail with {:?}", expected)),
+    (Err(error), _) => return Some(format!("failed with {:?}, but expected {:?}", error, expectedError))
+  };
+
+  match std::fs::read_to_string(path.join("VERSION")) {
+    Ok(version) if version.trim( ) == FORMAT_VERSION.to_string( ) => { },
+    version => return Some(format!("has VERSION {:?} once opened", version))
+  }
+
+  for directory in [dataDir.raftDirectory( ), dataDir.dataDirectory( ), dataDir.snapshotsDirectory( )] {
+    if !directory.is_dir( ) {
+      return Some(format!("{} is missing once opened", directory.display( )))}
+  }
+  match std::fs::read_dir(dataDir.tmpDirectory( )).map(Iterator::count) {
+    Ok(0) => { },
+    entries => return Some(format!("tmp/ has {:?} entries once opened", entries))
+  }
+
+  match DataDir::open(path) {
+    Err(error) if error.code( ) == ErrorCode::ObjectInUse => { },
+    Err(error) => return Some(format!("opening it again failed with {:?}, rather than ObjectInUse", error)),
+    Ok(_) => return Some("opened again, while it was open already".to_string( ))
+  }
+
+  drop(dataDir);
+  DataDir::open(path).err( ).map(|error| format!("reopening it once closed failed with {:?}", error))
+}
+
+fn checkSync(rng: &mut StdRng) -> Option<String> {
+  let policy= generateSyncPolicy(rng);
+  let flushFailureRate= if rng.gen_bool(0.5) { 0.2 } else { 0.0 };
+
+  let mut operations= Vec::new( );
+  let failure= match commitAndCrash(rng, policy, flushFailureRate, &mut operations) {
+    Ok(failure) => failure,
+    Err(error) => Some(format!("failed : {}", error))
+  };
+
+  failure.map(|failure| format!("{}
+  policy : {}
+  flush failure rate : {}
+  operations : {:#?}", failure, policy, flushFailureRate, operations))
+}
+
+// Commits to a store (flushing it explicitly at times), and then crashes it. Returns the problem
+// with the state it recovers to, if any.
+fn commitAndCrash(rng: &mut StdRng, policy: SyncPolicy, flushFailureRate: f64, operations: &mut Vec<String>) -> Result<Option<String>, Error> {
+  let engine= FaultyEngine::new(rng.gen( ), Duration::ZERO, flushFailureRate);
+  let mvcc= MVCC::new(engine.clone( ), WriteLimits::default( ), policy);
+
+  // The state after each commit, and the one after the last commit known to be flushed.
+  let mut states= vec![BTreeMap::new( )];
+  let mut flushedState= 0;
+  let mut explicitFlushes= 0;
+
+  for commit in 1..=SYNC_COMMITS {
+    if rng.gen_bool(0.1) {
+      let result= mvcc.flush( );
+      operations.push(format!("flush : {:?}", result));
+
+      explicitFlushes += 1;
+      if result.is_ok( ) {
+        flushedState= states.len( ) - 1;}
+    }
+
+    let (key, value)= (vec![rng.gen_range(0..4u8)], commit.to_be_bytes( ).to_vec( ));
+    let mut txn= mvcc.begin( )?;
+    txn.set(&key, value.clone( ))?;
+    let result= txn.commit( );
+    operations.push(format!("commit {} of key {:?} : {:?}", commit, key, result));
+
+    let mut state= states[states.len( ) - 1].clone( );
+    state.insert(key, value);
+    states.push(state);
+
+    // A failed commit (whose flush failed) may be flushed later, along with the next one.
+    match (policy, result) {
+      (SyncPolicy::Always, Ok(( ))) => flushedState= states.len( ) - 1,
+      (SyncPolicy::Always, Err(_)) => { },
+
+      (_, Ok(( ))) => { },
+      (_, Err(error)) => return Ok(Some(format!("commit {} failed, though it isn't flushed : {}", commit, error)))
+    }
+  }
+
+  if let SyncPolicy::Interval(interval)= policy {
+    let deadline= Instant::now( ) + interval * 100;
+    while mvcc.status( )?.sync.unflushedCommits > 0 {
+      if Instant::now( ) > deadline {
+        return Ok(Some("the background task didn't flush the commits".to_string( )))}
+      thread::sleep(interval);
+    }
+    flushedState= states.len( ) - 1;
+  }
+
+  let status= mvcc.status( )?.sync;
+  if status.commits != SYNC_COMMITS {
+    return Ok(Some(format!("counted {} commits, rather than {}", status.commits, SYNC_COMMITS)))}
+  if status.fsyncs != engine.flushes( ) {
+    return Ok(Some(format!("counted {} flushes, but the engine flushed {} times", status.fsyncs, engine.flushes( ))))}
+  if policy == SyncPolicy::Never && status.fsyncs > explicitFlushes {
+    return Ok(Some(format!("flushed {} times, though only {} flushes were asked for", status.fsyncs, explicitFlushes)))}
+
+  let recovered= MVCC::new(engine.crash( )?, WriteLimits::default( ), SyncPolicy::Never);
+  let recoveredState= recovered.beginReadOnly( )?
+                               .scan((Bound::Unbounded, Bound::Unbounded))?
+                               .into_iter( )
+                               .collect::<BTreeMap<_, _>>( );
+
+  Ok(match states.iter( ).rposition(|state| *state == recoveredState) {
+    Some(recoveredCommits) if recoveredCommits >= flushedState => None,
+    Some(recoveredCommits) => Some(format!("recovered the first {} commits, losing flushed ones (upto commit {})", recoveredCommits, flushedState)),
+    None => Some(format!("recovered {:?}, which isn't the state after any prefix of the commits", recoveredState))
+  })
+}
+
+fn checkGroupCommit(rng: &mut StdRng) -> Option<String> {
+  let (writers, commitsPerWriter)= (rng.gen_range(4..=8u8), rng.gen_range(2..=6u8));
+  let describe= |problem: String| Some(format!("{}
+  writers : {}
+  commits per writer : {}", problem, writers, commitsPerWriter));
+
+  let engine= FaultyEngine::new(rng.gen( ), GROUP_COMMIT_FLUSH_LATENCY, 0.0);
+  let mvcc= MVCC::new(engine.clone( ), WriteLimits::default( ), SyncPolicy::Always);
+
+  // The writers start together, so that their commits contend.
+  let barrier= Barrier::new(writers as usize);
+  let results= thread::scope(|scope| {
+    let writers= (0..writers).map(|writer| {
+                               let (mvcc, barrier)= (mvcc.clone( ), &barrier);
+                               scope.spawn(move || -> Result<( ), Error> {
+                                 barrier.wait( );
+                                 for commit in 0..commitsPerWriter {
+                                   let mut txn= mvcc.begin( )?;
+                                   txn.set(&[writer, commit], vec![ ])?;
+                                   txn.commit( )?;
+                                 }
+                                 Ok(( ))
+                               })
+                             })
+                             .collect::<Vec<_>>( );
+    writers.into_iter( ).map(|writer| writer.join( )).collect::<Vec<_>>( )
+  });
+
+  for result in results {
+    match result {
+      Ok(Ok(( ))) => { },
+      Ok(Err(error)) => return describe(format!("a writer failed : {}", error)),
+      Err(_) => return describe("a writer panicked".to_string( ))
+    }
+  }
+
+  let commits= u64::from(writers) * u64::from(commitsPerWriter);
+  let status= match mvcc.status( ) {
+    Ok(status) => status.sync,
+    Err(error) => return describe(format!("failed getting the status : {}", error))
+  };
+
+  if status.commits != commits || status.unflushedCommits > 0 {
+    return describe(format!("counted {} commits ({} of them unflushed), rather than {} flushed ones", status.commits, status.unflushedCommits, commits))}
+  if status.fsyncs != engine.flushes( ) {
+    return describe(format!("counted {} flushes, but the engine flushed {} times", status.fsyncs, engine.flushes( )))}
+  if status.fsyncs >= commits {
+    return describe(format!("the {} commits took {} flushes, rather than sharing them", commits, status.fsyncs))}
+
+  let recovered= engine.crash( ).map(|engine| MVCC::new(engine, WriteLimits::default( ), SyncPolicy::Never));
+  match recovered.and_then(|mvcc| mvcc.beginReadOnly( )?.scan((Bound::Unbounded, Bound::Unbounded))) {
+    Ok(pairs) if pairs.len( ) as u64 == commits => None,
+    Ok(pairs) => describe(format!("{} of the {} commits survived a crash", pairs.len( ), commits)),
+    Err(error) => describe(format!("failed recovering from a crash : {}", error))
+  }
+}
+
+// Runs the function, catching the panic (along with its message) if it panics.
+fn catchPanic<T>(function: impl FnOnce( ) -> T) -> Result<T, String> {
+  CATCHING.with(|catching| catching.set(true));
+  let result= panic::catch_unwind(AssertUnwindSafe(function));
+  CATCHING.with(|catching| catching.set(false));
+
+  result.map_err(|_| PANIC.with(|panic| panic.borrow_mut( ).take( )).unwrap_or_else(|| "panicked".to_string( )))
+}
+
+// Removes chunks of characters from the input (halving the chunk size, down to single characters),
+// as long as the input still fails.
+fn minimize(input: &str, fails: impl Fn(&str) -> bool) -> String {
+  let mut input= input.chars( ).collect::<Vec<_>>( );
+
+  let mut chunkSize= input.len( ) / 2;
+  while chunkSize > 0 {
+    let mut start= 0;
+    while start < input.len( ) {
+      let mut candidate= input.clone( );
+      candidate.drain(start..(start + chunkSize).min(input.len( )));
+
+      match fails(&candidate.iter( ).collect::<String>( )) {
+        true => input= candidate,
+        false => start += chunkSize
+      }
+    }
+    chunkSize /= 2;
+  }
+  input.into_iter( ).collect( )
+}
+
+// Returns the number the environment variable is set to, if it's set.
+fn numberVariable(name: &str) -> Option<u64> {
+  let value= std::env::var(name).ok( )?;
+  Some(value.parse( ).unwrap_or_else(|_| panic!("{} must be a number, not {:?}", name, value)))
+}
@@ -0,0 +1,115 @@
+#![allow(non_snake_case)]
+
+use std::net::SocketAddr;
+use distributed_sql_based_database_in_rust::{
+  client::Client,
+  result::{Error, Result},
+  server::{Config, Server, StatementResult},
+  sql::{execution::WorkMemory, types::{Row, Value}},
+  storage::engine::memory::Memory
+};
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
+
+// A single node server, listening for SQL clients on an ephemeral port.
+struct TestServer {
+  address: SocketAddr,
+  shutdown: oneshot::Sender<( )>,
+  server: JoinHandle<Result<( )>>
+}
+
+impl TestServer {
+  async fn start( ) -> Result<Self> {
+    // The Raft port picked by the OS is released, for the node's transport to listen on.
+    let raftAddress= std::net::TcpListener::bind("127.0.0.1:0")?.local_addr( )?;
+    let spillDirectory= std::env::temp_dir( ).join(format!("server-test-spill-{}-{}", std::process::id( ), raftAddress.port( )));
+
+    let config= Config::parse(&format!("id = 1\nlistenSQL = 127.0.0.1:0\nnode.1 = {}\nspillDirectory = {}",
+                                       raftAddress, spillDirectory.display( )))?;
+
+    let server= Server::new(config.id, config.clusterConfig, config.join, config.replicationLimits, config.maxMessageSize, config.tickInterval,
+                            config.clientRouting, config.readYourWritesTimeout, WorkMemory::new(config.workMemory, spillDirectory)?, config.rowBatchSize,
+                            config.writeLimits, config.logCacheEntries, config.syncPolicy, Box::new(Memory::new( )), Memory::new( ))?;
+
+    let listener= TcpListener::bind(&config.listenSQL).await?;
+    let address= listener.local_addr( )?;
+
+    let (shutdown, shutdownReceiver)= oneshot::channel( );
+    let server= tokio::spawn(server.serve(listener, async move { let _= shutdownReceiver.await; }));
+
+    Ok(Self { address, shutdown, server })
+  }
+
+  // Shuts the server down gracefully, like on SIGTERM.
+  async fn stop(self) -> Result<( )> {
+    let _= self.shutdown.send(( ));
+    self.server.await.map_err(|error| Error::Internal(error.to_string( )))?
+  }
+
+  // Runs the given function against a client connected to the server. The client blocks, so it's
+  // run on a blocking thread.
+  async fn withClient<T: Send + 'static>(&self, run: impl FnOnce(&mut Client) -> Result<T> + Send + 'static) -> Result<T> {
+    let address= self.address;
+    tokio::task::spawn_blocking(move || run(&mut Client::connect(address)?))
+      .await
+      .map_err(|error| Error::Internal(error.to_string( )))?
+  }
+}
+
+fn rows(result: StatementResult) -> Vec<Row> {
+  match result {
+    StatementResult::Query { rows, .. } => rows,
+    result => panic!("Expected a query result, got {:?}", result)
+  }
+}
+
+fn row(id: i64, name: &str) -> Row {
+  vec![Value::Integer(id), Value::String(name.to_string( ))]
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn executesStatementsOverSocket( ) -> Result<( )> {
+  let server= TestServer::start( ).await?;
+
+  server.withClient(|client| {
+    assert!(matches!(client.execute("CREATE TABLE users (id INT PRIMARY KEY, name STRING)")?,
+                     StatementResult::CreateTable { name } if name == "users"));
+    assert!(matches!(client.execute("INSERT INTO users VALUES (1, 'ann'), (2, 'bob')")?, StatementResult::Insert { count: 2 }));
+
+    assert_eq!(rows(client.execute("SELECT * FROM users")?), vec![row(1, "ann"), row(2, "bob")]);
+    Ok(( ))
+  }).await?;
+
+  server.stop( ).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn servesEachConnectionInSessionOfItsOwn( ) -> Result<( )> {
+  let server= TestServer::start( ).await?;
+  let address= server.address;
+
+  server.withClient(move |a| {
+    a.execute("CREATE TABLE users (id INT PRIMARY KEY, name STRING)")?;
+
+    // Both connections are open at once, each with a transaction of its own.
+    let mut b= Client::connect(address)?;
+
+    a.execute("BEGIN")?;
+    a.execute("INSERT INTO users VALUES (1, 'ann')")?;
+
+    b.execute("BEGIN")?;
+    b.execute("INSERT INTO users VALUES (2, 'bob')")?;
+
+    // Neither sees the uncommitted write of the other.
+    assert_eq!(rows(a.execute("SELECT * FROM users")?), vec![row(1, "ann")]);
+    assert_eq!(rows(b.execute("SELECT * FROM users")?), vec![row(2, "bob")]);
+
+    // Rolling back one transaction leaves the other one open.
+    assert!(matches!(b.execute("ROLLBACK")?, StatementResult::Rollback { .. }));
+    assert!(matches!(a.execute("COMMIT")?, StatementResult::Commit { .. }));
+
+    assert_eq!(rows(b.execute("SELECT * FROM users")?), vec![row(1, "ann")]);
+    Ok(( ))
+  }).await?;
+
+  server.stop( ).await
+}
@@ -0,0 +1,473 @@
+#![allow(non_snake_case)]
+
+use distributed_sql_based_database_in_rust::{
+  raft::{
+    message::{Request, RequestId, Response},
+    node::{leader::{ReplicationLimits, HEARTBEAT_INTERVAL}, Status},
+    simulation::{Network, Simulation},
+    types::NodeId
+  },
+  result::{Error, ErrorCode, Result}
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+// Environment variables, which set the seed the runs are simulated from (a random one, unless it's
+// set) and the number of runs of each scenario.
+const SEED_VARIABLE: &str= "SIMULATE_SEED";
+const RUNS_VARIABLE: &str= "SIMULATE_RUNS";
+
+// Number of runs of each scenario, unless SIMULATE_RUNS is set. It's kept low, so that cargo test
+// stays quick.
+const DEFAULT_RUNS: u64= 20;
+
+// Number of ticks a cluster is given to elect a leader.
+const ELECTION_TICKS: u64= 1000;
+
+// A scenario, run against a simulated cluster. The run is decided by the seed given to it, and
+// returns error if the cluster misbehaves.
+struct Scenario {
+  name: &'static str,
+  run: fn(u64) -> Result<( )>
+}
+
+/*
+  Runs scenarios against deterministic simulations of Raft clusters (see raft::simulation). The
+  scenarios are :
+
+    election : a leader is elected, and keeps its leadership while the network is reliable. Once it's
+    cut off from the cluster, the rest of the nodes elect a new leader.
+
+    replication : commands are submitted to random nodes (and retried, until they succeed) over a
+    network which drops and reorders messages. Each command gets applied exactly once, on every
+    node.
+
+    partition : the leader ends up on the minority side of a partition. The majority elects a new
+    leader and keeps serving requests, while the old leader steps down without committing anything.
+    Once the partition heals, the nodes converge on the majority's log.
+
+    transfer : a leadership transfer to a node cut off from the leader times out, and the leader
+    keeps its leadership. A transfer to a reachable node, while commands are being submitted, makes
+    that node the leader with the cluster going without a leader for less than a heartbeat
+    interval. Each acknowledged command gets applied exactly once, on every node.
+
+    overload : a flood of commands is submitted to a cluster whose leader admits only a few
+    proposals at a time. The leader sheds the excess with ServerBusy, so that its uncommitted log
+    stays bounded, while the rejected commands get through once they're retried. Each acknowledged
+    command gets applied exactly once, on every node.
+
+    read-your-writes : a command is submitted through a follower whose replica lags behind. A read
+    served by the follower's replica, at the index the command was acknowledged with, waits for the
+    replica to apply the command, rather than returning stale data.
+
+  On top of the checks of each scenario, the simulation checks the safety properties of Raft after
+  every tick.
+
+  Usage : [SIMULATE_SEED=<seed>] [SIMULATE_RUNS=<count>] cargo test --test simulate [<scenario>]
+
+  Each scenario is a test of its own. Each run is simulated from a seed of its own (the seed given,
+  plus the number of the run), which is reported along with the failure. So, a failing run can be
+  reproduced alone, with SIMULATE_SEED=<seed of the run> SIMULATE_RUNS=1. Without SIMULATE_SEED, a
+  random seed is picked.
+*/
+#[test]
+fn election( ) {
+  simulate(&Scenario { name: "election", run: simulateElections })
+}
+
+#[test]
+fn replication( ) {
+  simulate(&Scenario { name: "replication", run: simulateReplication })
+}
+
+#[test]
+fn partition( ) {
+  simulate(&Scenario { name: "partition", run: simulatePartition })
+}
+
+#[test]
+fn transfer( ) {
+  simulate(&Scenario { name: "transfer", run: simulateTransfer })
+}
+
+#[test]
+fn overload( ) {
+  simulate(&Scenario { name: "overload", run: simulateOverload })
+}
+
+#[test]
+fn readYourWrites( ) {
+  simulate(&Scenario { name: "read-your-writes", run: simulateReadYourWrites })
+}
+
+// Runs the scenario, failing with the first run in which the cluster misbehaves.
+fn simulate(scenario: &Scenario) {
+  let seed= numberVariable(SEED_VARIABLE).unwrap_or_else(|| rand::thread_rng( ).gen( ));
+  let runs= numberVariable(RUNS_VARIABLE).unwrap_or(DEFAULT_RUNS);
+
+  if let Some((run, error))= (0..runs).find_map(|run| (scenario.run)(seed.wrapping_add(run)).err( ).map(|error| (run, error))) {
+    panic!("{} failed (run seed {})\n{}", scenario.name, seed.wrapping_add(run), error)}
+
+  println!("ok      {} ({} runs, seed {})", scenario.name, runs, seed);
+}
+
+fn simulateElections(seed: u64) -> Result<( )> {
+  let mut simulation= Simulation::new(5, seed, Network { dropProbability: 0.0, delay: 1..=3 }, ReplicationLimits::default( ))?;
+
+  for _ in 0..3 {
+    let leader= elect(&mut simulation)?;
+    let term= simulation.currentTerm(leader);
+
+    // The leadership is stable, while the network is reliable.
+    simulation.run(500)?;
+    if simulation.leader( ) != Some(leader) || simulation.currentTerm(leader) != term {
+      return fail(&simulation, format!("leader {} of term {} lost its leadership over a reliable network", leader, term))}
+
+    // Once the leader is cut off, the rest elect a new leader.
+    let others= simulation.nodeIds( ).into_iter( ).filter(|&id| id != leader).collect::<Vec<_>>( );
+    simulation.partition(&[&others]);
+
+    if !simulation.runUntil(ELECTION_TICKS, |simulation| simulation.leader( ).is_some_and(|newLeader| newLeader != leader))? {
+      return fail(&simulation, format!("no new leader was elected, after leader {} was cut off", leader))}
+
+    simulation.heal( );
+  }
+  Ok(( ))
+}
+
+// Number of the commands submitted by the replication scenario.
+const COMMANDS: usize= 30;
+
+// Number of ticks a client waits for the response to a request, before retrying it.
+const RETRY_TICKS: u64= 200;
+
+fn simulateReplication(seed: u64) -> Result<( )> {
+  let mut simulation= Simulation::new(3, seed, Network { dropProbability: 0.2, delay: 1..=10 }, ReplicationLimits::default( ))?;
+  let mut rng= StdRng::seed_from_u64(seed);
+
+  let commands= (0..COMMANDS).map(|i| format!("command {}", i).into_bytes( )).collect::<Vec<_>>( );
+
+  let mut pending= PendingCommands::default( );
+  for command in &commands {
+    pending.submit(&mut simulation, &mut rng, command.clone( ))?;}
+
+  while !pending.isEmpty( ) {
+    if simulation.now( ) > 100 * RETRY_TICKS {
+      return fail(&simulation, format!("{} commands weren't acknowledged", pending.len( )))}
+
+    simulation.step( )?;
+    pending.poll(&mut simulation, &mut rng)?;
+  }
+
+  // Once the network is reliable again, every node catches up.
+  simulation.setNetwork(Network::default( ));
+  checkApplied(&mut simulation, &commands)
+}
+
+// Commands submitted by the client of a scenario, which are awaiting their responses.
+#[derive(Default)]
+struct PendingCommands {
+  // The commands, along with their request ids and when they were (last) sent.
+  commands: Vec<(Vec<u8>, RequestId, u64)>
+}
+
+impl PendingCommands {
+  // Submits the command to a random node.
+  fn submit(&mut self, simulation: &mut Simulation, rng: &mut StdRng, command: Vec<u8>) -> Result<( )> {
+    let id= simulation.request(*simulation.nodeIds( ).choose(rng).unwrap( ), Request::Mutate(command.clone( )))?;
+    self.commands.push((command, id, simulation.now( )));
+    Ok(( ))
+  }
+
+  // Collects the responses which have arrived, retrying the commands which failed or which have
+  // been waiting for too long.
+  fn poll(&mut self, simulation: &mut Simulation, rng: &mut StdRng) -> Result<( )> {
+    let mut stillPending= Vec::new( );
+    for (command, id, sentAt) in std::mem::take(&mut self.commands) {
+      match simulation.takeResponse(&id) {
+        Some(Ok(Response::Mutate { result, .. })) if result == command => continue,
+        Some(Ok(response)) => return fail(simulation, format!("unexpected response {:?} to command {:?}", response, command)),
+
+        // The request is retried with the same id, so that it's applied only once.
+        response if response.is_some( ) || (simulation.now( ) - sentAt > RETRY_TICKS) => {
+          simulation.requestWithId(*simulation.nodeIds( ).choose(rng).unwrap( ), id.clone( ), Request::Mutate(command.clone( )))?;
+          stillPending.push((command, id, simulation.now( )));
+        },
+
+        _ => stillPending.push((command, id, sentAt))
+      }
+    }
+    self.commands= stillPending;
+    Ok(( ))
+  }
+
+  fn isEmpty(&self) -> bool {
+    self.commands.is_empty( )
+  }
+
+  fn len(&self) -> usize {
+    self.commands.len( )
+  }
+}
+
+// Runs the simulation until every node has applied the (acknowledged) commands, and checks that
+// each of them was applied exactly once.
+fn checkApplied(simulation: &mut Simulation, commands: &[Vec<u8>]) -> Result<( )> {
+  let nodeIds= simulation.nodeIds( );
+
+  let appliedAll= |simulation: &Simulation| nodeIds.iter( ).all(|&id| commands.iter( ).all(|command| simulation.appliedCommands(id).contains(command)));
+  if !simulation.runUntil(ELECTION_TICKS, appliedAll)? {
+    return fail(simulation, "the nodes didn't catch up with the acknowledged commands".to_string( ))}
+
+  for id in nodeIds {
+    let applied= simulation.appliedCommands(id);
+    if let Some(command)= commands.iter( ).find(|command| applied.iter( ).filter(|applied| applied == command).count( ) != 1) {
+      return fail(simulation, format!("node {} applied command {:?} more than once", id, String::from_utf8_lossy(command)))}
+  }
+  Ok(( ))
+}
+
+fn simulatePartition(seed: u64) -> Result<( )> {
+  let mut simulation= Simulation::new(5, seed, Network { dropProbability: 0.0, delay: 1..=3 }, ReplicationLimits::default( ))?;
+
+  let oldLeader= elect(&mut simulation)?;
+  let oldTerm= simulation.currentTerm(oldLeader);
+
+  let others= simulation.nodeIds( ).into_iter( ).filter(|&id| id != oldLeader).collect::<Vec<_>>( );
+  let (minority, majority)= ([oldLeader, others[0]], &others[1..]);
+  simulation.partition(&[&minority, majority]);
+
+  // The old leader can't commit the command, since it can't reach a quorum.
+  let minorityCommand= b"minority".to_vec( );
+  let minorityRequest= simulation.request(oldLeader, Request::Mutate(minorityCommand.clone( )))?;
+
+  let electedByMajority= |simulation: &Simulation| simulation.leaders( ).iter( ).any(|(id, term)| majority.contains(id) && *term > oldTerm);
+  if !simulation.runUntil(ELECTION_TICKS, electedByMajority)? {
+    return fail(&simulation, "the majority didn't elect a new leader".to_string( ))}
+
+  // The old leader steps down (once its lease expires) before a new leader is elected. Otherwise,
+  // it could serve stale reads.
+  if simulation.leaders( ).iter( ).any(|(id, _)| minority.contains(id)) {
+    return fail(&simulation, format!("old leader {} still leads, after the majority elected a new leader", oldLeader))}
+
+  // The majority keeps serving requests.
+  let majorityCommand= b"majority".to_vec( );
+  let majorityRequest= simulation.request(majority[0], Request::Mutate(majorityCommand.clone( )))?;
+
+  match awaitResponse(&mut simulation, &majorityRequest)? {
+    Some(Ok(Response::Mutate { result, .. })) if result == majorityCommand => { },
+    response => return fail(&simulation, format!("unexpected response {:?} to the command sent to the majority", response))
+  }
+
+  // Once the partition heals, the old leader catches up with the majority, and its uncommitted
+  // command is discarded.
+  simulation.heal( );
+  let converged= |simulation: &Simulation| {
+    simulation.leader( ).is_some( ) && simulation.nodeIds( ).iter( ).all(|&id| simulation.appliedCommands(id).contains(&majorityCommand))
+  };
+  if !simulation.runUntil(ELECTION_TICKS, converged)? {
+    return fail(&simulation, "the nodes didn't converge, after the partition healed".to_string( ))}
+
+  if let Some(Ok(response))= simulation.takeResponse(&minorityRequest) {
+    return fail(&simulation, format!("the command sent to the minority was acknowledged with {:?}", response))}
+
+  if let Some(id)= simulation.nodeIds( ).into_iter( ).find(|&id| simulation.appliedCommands(id).contains(&minorityCommand)) {
+    return fail(&simulation, format!("node {} applied the command sent to the minority", id))}
+
+  Ok(( ))
+}
+
+fn simulateTransfer(seed: u64) -> Result<( )> {
+  let mut simulation= Simulation::new(5, seed, Network::default( ), ReplicationLimits::default( ))?;
+  let mut rng= StdRng::seed_from_u64(seed);
+
+  let leader= elect(&mut simulation)?;
+  let term= simulation.currentTerm(leader);
+  let followers= simulation.nodeIds( ).into_iter( ).filter(|&id| id != leader).collect::<Vec<_>>( );
+
+  // A transfer to a node which the leader can't reach, times out. And the leader resumes leading.
+  let unreachable= *followers.choose(&mut rng).unwrap( );
+  let reachable= simulation.nodeIds( ).into_iter( ).filter(|&id| id != unreachable).collect::<Vec<_>>( );
+  simulation.partition(&[&reachable]);
+
+  let transfer= simulation.request(leader, Request::TransferLeadership(Some(unreachable)))?;
+  match awaitResponse(&mut simulation, &transfer)? {
+    Some(Err(Error::Value(ErrorCode::ObjectNotInPrerequisiteState, _))) => { },
+    response => return fail(&simulation, format!("unexpected response {:?} to the transfer to unreachable node {}", response, unreachable))
+  }
+  if simulation.leader( ) != Some(leader) || simulation.currentTerm(leader) != term {
+    return fail(&simulation, format!("leader {} of term {} lost its leadership, after the transfer timed out", leader, term))}
+
+  simulation.heal( );
+
+  // A transfer to a reachable node, while commands are being submitted.
+  let target= *followers.choose(&mut rng).unwrap( );
+  let commands= (0..COMMANDS).map(|i| format!("command {}", i).into_bytes( )).collect::<Vec<_>>( );
+  let (before, after)= commands.split_at(COMMANDS / 2);
+
+  let mut pending= PendingCommands::default( );
+  for command in before {
+    pending.submit(&mut simulation, &mut rng, command.clone( ))?;}
+
+  let transfer= simulation.request(leader, Request::TransferLeadership(Some(target)))?;
+
+  for command in after {
+    pending.submit(&mut simulation, &mut rng, command.clone( ))?;}
+
+  let mut transferResponse= None;
+  let mut leaderlessTicks= 0u64;
+  while transferResponse.is_none( ) || !pending.isEmpty( ) {
+    if simulation.now( ) > 100 * RETRY_TICKS {
+      return fail(&simulation, format!("the transfer responded with {:?}, and {} commands weren't acknowledged", transferResponse, pending.len( )))}
+
+    simulation.step( )?;
+    if simulation.leaders( ).is_empty( ) {
+      leaderlessTicks += 1;}
+
+    transferResponse= transferResponse.or_else(|| simulation.takeResponse(&transfer));
+    pending.poll(&mut simulation, &mut rng)?;
+  }
+
+  match transferResponse {
+    Some(Ok(Response::TransferLeadership)) => { },
+    response => return fail(&simulation, format!("unexpected response {:?} to the transfer to node {}", response, target))
+  }
+  if simulation.leader( ) != Some(target) {
+    return fail(&simulation, format!("node {} didn't become the leader, after the leadership was transferred to it", target))}
+
+  if leaderlessTicks >= u64::from(HEARTBEAT_INTERVAL) {
+    return fail(&simulation, format!("the cluster went without a leader for {} ticks, during the transfer", leaderlessTicks))}
+
+  checkApplied(&mut simulation, &commands)
+}
+
+// Maximum number of proposals the leader has in flight in the overload scenario, and the number of
+// the commands flooding it.
+const OVERLOAD_PENDING_PROPOSALS: usize= 8;
+const OVERLOAD_COMMANDS: usize= 100;
+
+fn simulateOverload(seed: u64) -> Result<( )> {
+  let replicationLimits= ReplicationLimits { maxPendingProposals: OVERLOAD_PENDING_PROPOSALS, ..ReplicationLimits::default( ) };
+  let mut simulation= Simulation::new(3, seed, Network { dropProbability: 0.0, delay: 1..=5 }, replicationLimits)?;
+  let mut rng= StdRng::seed_from_u64(seed);
+
+  // The entries upto the noop of the leader's term aren't counted against its limit.
+  let leader= elect(&mut simulation)?;
+  let termStartIndex= status(&mut simulation, leader)?.lastLogIndex;
+
+  let commands= (0..OVERLOAD_COMMANDS).map(|i| format!("command {}", i).into_bytes( )).collect::<Vec<_>>( );
+
+  let mut pending= PendingCommands::default( );
+  for command in &commands {
+    pending.submit(&mut simulation, &mut rng, command.clone( ))?;}
+
+  while !pending.isEmpty( ) {
+    if simulation.now( ) > 100 * RETRY_TICKS {
+      return fail(&simulation, format!("{} commands weren't acknowledged", pending.len( )))}
+
+    simulation.step( )?;
+    pending.poll(&mut simulation, &mut rng)?;
+
+    // The entries the leader appended in its term, which aren't committed yet, are the admitted
+    // proposals.
+    if simulation.leader( ) == Some(leader) {
+      let status= status(&mut simulation, leader)?;
+      let uncommitted= status.lastLogIndex - status.commitIndex.max(termStartIndex);
+
+      if uncommitted > OVERLOAD_PENDING_PROPOSALS as u64 {
+        return fail(&simulation, format!("leader {} has {} uncommitted entries, while it's overloaded", leader, uncommitted))}
+    }
+  }
+
+  let mut rejected= 0;
+  for id in simulation.nodeIds( ) {
+    rejected += status(&mut simulation, id)?.rejectedProposals;}
+
+  if rejected == 0 {
+    return fail(&simulation, format!("none of the {} commands were rejected by the overloaded leader", OVERLOAD_COMMANDS))}
+
+  checkApplied(&mut simulation, &commands)
+}
+
+fn simulateReadYourWrites(seed: u64) -> Result<( )> {
+  let mut simulation= Simulation::new(3, seed, Network { dropProbability: 0.0, delay: 1..=3 }, ReplicationLimits::default( ))?;
+  let mut rng= StdRng::seed_from_u64(seed);
+
+  let leader= elect(&mut simulation)?;
+  let followers= simulation.nodeIds( ).into_iter( ).filter(|&id| id != leader).collect::<Vec<_>>( );
+  let follower= *followers.choose(&mut rng).unwrap( );
+
+  // The follower's replica lags behind, not applying the committed entries.
+  simulation.pauseApplying(follower);
+
+  // The command is forwarded to the leader, and acknowledged along with the index of its entry.
+  let command= b"write".to_vec( );
+  let mutation= simulation.request(follower, Request::Mutate(command.clone( )))?;
+
+  let index= match awaitResponse(&mut simulation, &mutation)? {
+    Some(Ok(Response::Mutate { result, index })) if result == command => index,
+    response => return fail(&simulation, format!("unexpected response {:?} to the command sent to node {}", response, follower))
+  };
+  if simulation.appliedCommands(follower).contains(&command) {
+    return fail(&simulation, format!("node {} applied the command, while applying was paused", follower))}
+
+  // The read waits for the follower's replica to catch up with the command.
+  let query= simulation.request(follower, Request::LocalQuery { command: Vec::new( ), index })?;
+  simulation.run(RETRY_TICKS)?;
+
+  if let Some(response)= simulation.takeResponse(&query) {
+    return fail(&simulation, format!("the read was answered with {:?}, before node {} applied the command", response, follower))}
+
+  simulation.resumeApplying(follower)?;
+
+  match awaitResponse(&mut simulation, &query)? {
+    Some(Ok(Response::Query(result))) => {
+      let appliedEntries: u64= bincode::deserialize(&result)?;
+      if appliedEntries < index {
+        return fail(&simulation, format!("the read observed {} applied entries, missing the command at index {}", appliedEntries, index))}
+    },
+    response => return fail(&simulation, format!("unexpected response {:?} to the read sent to node {}", response, follower))
+  }
+
+  Ok(( ))
+}
+
+// Returns the status of the node.
+fn status(simulation: &mut Simulation, nodeId: NodeId) -> Result<Status> {
+  let id= simulation.request(nodeId, Request::Status)?;
+
+  match simulation.takeResponse(&id) {
+    Some(Ok(Response::Status(status))) => Ok(*status),
+    response => fail(simulation, format!("unexpected response {:?} to the status request to node {}", response, nodeId))
+  }
+}
+
+// Runs the simulation until the response to the client request arrives (for atmost ELECTION_TICKS
+// ticks), and returns the response.
+fn awaitResponse(simulation: &mut Simulation, id: &RequestId) -> Result<Option<Result<Response>>> {
+  for _ in 0..ELECTION_TICKS {
+    if let Some(response)= simulation.takeResponse(id) {
+      return Ok(Some(response))}
+
+    simulation.step( )?;
+  }
+  Ok(simulation.takeResponse(id))
+}
+
+// Runs the simulation until a leader is elected, and returns the leader.
+fn elect(simulation: &mut Simulation) -> Result<NodeId> {
+  match simulation.runUntil(ELECTION_TICKS, |simulation| simulation.leader( ).is_some( ))? {
+    true => Ok(simulation.leader( ).unwrap( )),
+    false => fail(simulation, "no leader was elected".to_string( ))
+  }
+}
+
+// Fails the run, reporting the tick it failed at.
+fn fail<T>(simulation: &Simulation, message: String) -> Result<T> {
+  Err(Error::Value(ErrorCode::DataException, format!("at tick {} : {}", simulation.now( ), message)))
+}
+
+// Returns the number the environment variable is set to, if it's set.
+fn numberVariable(name: &str) -> Option<u64> {
+  let value= std::env::var(name).ok( )?;
+  Some(value.parse( ).unwrap_or_else(|_| panic!("{} must be a number, not {:?}", name, value)))
+}
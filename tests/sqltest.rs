@@ -0,0 +1,490 @@
+#![allow(non_snake_case)]
+
+use std::{collections::BTreeMap, path::{Path, PathBuf}, time::{Duration, Instant}};
+use distributed_sql_based_database_in_rust::{
+  raft::{self, cluster_config::ClusterConfig, log::{Log, DEFAULT_CACHE_CAPACITY}, node::leader::ReplicationLimits, server::DEFAULT_TICK_INTERVAL, types::NodeId},
+  result::{Error, ErrorCode, Result},
+  server::{ResultFrames, Response, StatementResult, StreamMetrics, DEFAULT_ROW_BATCH_SIZE},
+  sql::{
+    csv,
+    engine::{kv::KV, raft::{Raft, StateMachine, DEFAULT_READ_YOUR_WRITES_TIMEOUT}, Engine, PlanCache, Session, Status},
+    execution::{ResultSet, WorkMemory, DEFAULT_WORK_MEMORY}
+  },
+  storage::{engine::memory::Memory, mvcc::WriteLimits, sync::SyncPolicy}
+};
+use tokio::sync::mpsc::{channel, unbounded_channel};
+
+// Environment variable, which (when set) rewrites the mismatching expected outputs in the scripts.
+const BLESS_VARIABLE: &str= "SQLTEST_BLESS";
+
+// Directory of the scripts, relative to the root of the crate.
+const SCRIPTS_DIRECTORY: &str= "sqltests";
+
+// Extension of the script files, looked for in the scripts directory.
+const SCRIPT_EXTENSION: &str= "sql";
+
+// Separates a statement from its expected output.
+const OUTPUT_SEPARATOR: &str= "----";
+
+// An expected output line, which matches any number of lines.
+const ELLIPSIS: &str= "...";
+
+// Directive switching the connection the following statements are executed in.
+const CONNECTION_DIRECTIVE: &str= "-- connection:";
+
+// Directive setting the write limits of the transactions of the script.
+const WRITE_LIMITS_DIRECTIVE: &str= "-- write limits:";
+
+// Connection the statements are executed in, until the first connection directive.
+const DEFAULT_CONNECTION: &str= "default";
+
+// Id of the node of the single-node Raft cluster.
+const NODE_ID: NodeId= NodeId(1);
+
+// How long to wait for the single-node Raft cluster to elect its leader.
+const LEADER_ELECTION_TIMEOUT: Duration= Duration::from_secs(5);
+
+/*
+  Runs the SQL test scripts (under sqltests/), comparing the output of each statement with the one
+  expected by the script.
+
+  Usage : cargo test --test sqltest
+
+  A script is a sequence of statements, each followed by a ---- line and its expected output (upto a
+  blank line or the end of the script). A statement can span multiple lines, the last one ending
+  with ; :
+
+    CREATE TABLE t (id INT PRIMARY KEY, name STRING);
+    ----
+    Created table t
+
+    SELECT * FROM t
+      WHERE id = 1;
+    ----
+    id,name
+
+  The rows of a query are written as CSV lines (see csv::formatRows), preceded by the column labels.
+  An error is written as its variant and its code (see ErrorCode) followed by its message, like
+  "error: Value 42P01: Table t doesn't exist". When expecting an error, the code can be left out,
+  and the message can be shortened to any part of it or left out entirely ("error: Value"). A line
+  with just ... in the expected output matches any number of lines (like the parts of the STATUS
+  output which vary).
+
+  Lines starting with -- are comments, except the directive "-- connection: <name>". It switches the
+  connection (each having a session of its own) which the following statements are executed in, so
+  that a script can interleave the statements of concurrent transactions deterministically. And the
+  directive "-- write limits: <keys> <bytes>", which sets the limits on the uncommitted writes of the
+  transactions of the whole script (see WriteLimits).
+
+  A line starting with ! is a command of the client's shell, executed in the session (without a
+  terminating ;). Only !backup <file> and !restore <file> [--force] are supported, whose files are
+  kept in a temporary directory of the run, and !status (whose rows are written as CSV lines).
+
+  The results are read like a client reads them from the server : the rows of a query are streamed
+  in batches (see ResultFrames), counted by the streaming rows of !status.
+
+  Every script is run against a fresh in-memory engine, and then against a fresh single-node Raft
+  cluster, whose outputs must match the expected ones too.
+
+  When SQLTEST_BLESS is set, mismatching expected outputs are rewritten in the scripts, instead of
+  failing. The expected errors which still match are kept as they are.
+*/
+#[tokio::test(flavor = "multi_thread")]
+async fn scripts( ) -> Result<( )> {
+  let bless= std::env::var_os(BLESS_VARIABLE).is_some( );
+
+  let root= Path::new(env!("CARGO_MANIFEST_DIR"));
+  let mut scripts= vec![ ];
+  findScripts(&root.join(SCRIPTS_DIRECTORY), &mut scripts)?;
+
+  // The rows are spilled (and the backups are written) to a directory of the process's own, which is
+  // removed once the scripts have run.
+  let directory= std::env::temp_dir( ).join(format!("sqltest-{}", std::process::id( )));
+  let workMemory= WorkMemory::new(DEFAULT_WORK_MEMORY, &directory)?;
+
+  let mut failed= vec![ ];
+  for path in scripts {
+    let mut script= Script::parse(&std::fs::read_to_string(&path)?)?;
+    let name= path.strip_prefix(root).unwrap_or(&path).display( ).to_string( );
+
+    let mut mismatches= script.run(KV::new(Memory::new( ), script.writeLimits, SyncPolicy::default( )), &workMemory, &directory).await?;
+    mismatches += script.run(singleNodeRaft(script.writeLimits).await?, &workMemory, &directory).await?;
+
+    match mismatches {
+      0 => println!("ok      {}", name),
+
+      _ if bless => {
+        std::fs::write(&path, script.to_string( ))?;
+        println!("blessed {}", name);
+      },
+
+      _ => {
+        println!("FAILED  {}", name);
+        script.reportMismatches( );
+        failed.push(name);
+      }
+    }
+  }
+
+  std::fs::remove_dir_all(&directory)?;
+  assert!(failed.is_empty( ), "{} scripts failed : {:?}", failed.len( ), failed);
+  Ok(( ))
+}
+
+// Collects the scripts at the path : the path itself if it's a file, or else the scripts in the
+// directory (recursively), in the order of their paths.
+fn findScripts(path: &Path, scripts: &mut Vec<PathBuf>) -> Result<( )> {
+  if !path.is_dir( ) {
+    scripts.push(path.to_path_buf( ));
+    return Ok(( ))
+  }
+
+  let mut entries= std::fs::read_dir(path)?
+                     .map(|entry| entry.map(|entry| entry.path( )))
+                     .collect::<std::io::Result<Vec<_>>>( )?;
+  entries.sort( );
+
+  for entry in entries {
+    if entry.is_dir( ) || entry.extension( ).is_some_and(|extension| extension == SCRIPT_EXTENSION) {
+      findScripts(&entry, scripts)?;
+    }
+  }
+  Ok(( ))
+}
+
+/*
+  Starts a single-node Raft cluster, returning its SQL engine once the node has become the leader.
+
+  The node has no peers, so it's run without a transport : the channels of the inbound and outbound
+  messages are just kept open. The node is stopped once the engine (and its clones) are dropped,
+  since the client requests channel closes then.
+*/
+async fn singleNodeRaft(writeLimits: WriteLimits) -> Result<Raft> {
+  let config= ClusterConfig::new(BTreeMap::from([(NODE_ID, "127.0.0.1:0".to_string( ))]));
+  let server= raft::server::Server::new(NODE_ID,
+                                        Some(config),
+                                        Log::new(Box::new(Memory::new( )), DEFAULT_CACHE_CAPACITY, SyncPolicy::default( ))?,
+                                        Box::new(StateMachine::new(KV::new(Memory::new( ), writeLimits, SyncPolicy::default( )))?),
+                                        ReplicationLimits::default( ),
+                                        DEFAULT_TICK_INTERVAL)?;
+
+  let (inboundMessageSender, inboundMessageReceiver)= unbounded_channel( );
+  let (outboundMessageSender, mut outboundMessageReceiver)= unbounded_channel( );
+  let (clientRequestSender, clientRequestReceiver)= channel(raft::server::CLIENT_REQUEST_CHANNEL_CAPACITY);
+
+  let appliedIndex= server.watchAppliedIndex( );
+  tokio::spawn(async move {
+    let _inboundMessageSender= inboundMessageSender;
+    tokio::spawn(async move { while outboundMessageReceiver.recv( ).await.is_some( ) { } });
+
+    if let Err(error)= server.serve(inboundMessageReceiver, outboundMessageSender, clientRequestReceiver).await {
+      eprintln!("Raft node failed : {}", error);}
+  });
+
+  let client= raft::server::Client::new(clientRequestSender, appliedIndex);
+  let start= Instant::now( );
+  loop {
+    let statusClient= client.clone( );
+    let status= tokio::task::spawn_blocking(move || statusClient.status( )).await
+                  .map_err(|error| Error::Internal(error.to_string( )))?;
+
+    match status {
+      Ok(status) if status.leader == Some(NODE_ID) => return Ok(Raft::new(client, DEFAULT_READ_YOUR_WRITES_TIMEOUT)),
+
+      _ if start.elapsed( ) > LEADER_ELECTION_TIMEOUT =>
+        return Err(Error::Internal("The single-node Raft cluster didn't elect a leader".to_string( ))),
+
+      _ => tokio::time::sleep(Duration::from_millis(10)).await
+    }
+  }
+}
+
+// A test script (see scripts( ) for its format).
+struct Script {
+  items: Vec<Item>,
+  writeLimits: WriteLimits
+}
+
+enum Item {
+  // A blank line or a comment, kept as it is.
+  Line(String),
+
+  Connection(String),
+
+  Statement {
+    statement: String,
+    expected: Option<Vec<String>>,
+
+    // The output of the last run, if it didn't match the expected one.
+    mismatch: Option<Vec<String>>
+  }
+}
+
+impl Script {
+  fn parse(input: &str) -> Result<Self> {
+    let mut items= vec![ ];
+    let mut writeLimits= WriteLimits::default( );
+    let mut lines= input.lines( ).enumerate( ).peekable( );
+
+    while let Some((index, line))= lines.next( ) {
+      // The directive is kept as a line (below), since it applies to the whole script.
+      if let Some(limits)= line.strip_prefix(WRITE_LIMITS_DIRECTIVE) {
+        let limits= limits.split_whitespace( ).map(str::parse::<u64>).collect::<Vec<_>>( );
+        let [Ok(maxKeys), Ok(maxBytes)]= limits.as_slice( ) else {
+          return Err(Error::Parse(format!("Expected {} <keys> <bytes> at line {}", WRITE_LIMITS_DIRECTIVE, index + 1)))
+        };
+        writeLimits= WriteLimits { maxKeys: *maxKeys, maxBytes: *maxBytes };
+      }
+
+      if let Some(connection)= line.strip_prefix(CONNECTION_DIRECTIVE) {
+        items.push(Item::Connection(connection.trim( ).to_string( )));
+        continue
+      }
+
+      if line.trim( ).is_empty( ) || line.starts_with("--") {
+        items.push(Item::Line(line.to_string( )));
+        continue
+      }
+
+      let mut statement= vec![line];
+      while !line.starts_with('!') && !statement[statement.len( ) - 1].trim_end( ).ends_with(';') {
+        match lines.next( ) {
+          Some((_, line)) => statement.push(line),
+          None => return Err(Error::Parse(format!("Statement at line {} doesn't end with ;", index + 1)))
+        }
+      }
+
+      let expected= match lines.peek( ) {
+        Some((_, line)) if *line == OUTPUT_SEPARATOR => {
+          lines.next( );
+
+          let mut expected= vec![ ];
+          while let Some((_, line))= lines.next_if(|(_, line)| !line.trim( ).is_empty( )) {
+            expected.push(line.to_string( ));}
+          Some(expected)
+        },
+
+        _ => None
+      };
+
+      items.push(Item::Statement { statement: statement.join("\n"), expected, mismatch: None });
+    }
+
+    Ok(Self { items, writeLimits })
+  }
+
+  /*
+    Runs the statements against the engine, each in the session of its connection. Returns the
+    number of statements whose output didn't match the expected one. Their outputs are recorded,
+    replacing the expected ones when the script is blessed.
+
+    NOTE : The sessions block (like on Raft requests), so they're run on a blocking thread.
+  */
+  async fn run<E: Engine + Send + 'static>(&mut self, engine: E, workMemory: &WorkMemory, directory: &Path) -> Result<usize> {
+    let mut items= std::mem::take(&mut self.items);
+    let workMemory= workMemory.clone( );
+    let directory= directory.to_path_buf( );
+
+    let (items, mismatches)= tokio::task::spawn_blocking(move || {
+      let mut sessions= BTreeMap::new( );
+      let planCache= PlanCache::default( );
+      let streamMetrics= StreamMetrics::default( );
+      let mut connection= DEFAULT_CONNECTION.to_string( );
+      let mut mismatches= 0;
+
+      for item in &mut items {
+        match item {
+          Item::Line(_) => { },
+
+          Item::Connection(name) => connection= name.clone( ),
+
+          Item::Statement { statement, expected, mismatch } => {
+            let session= sessions.entry(connection.clone( )).or_insert_with(| | Session::new(engine.clone( ), planCache.clone( ), workMemory.clone( )));
+            let output= match statement.strip_prefix('!') {
+              Some(command) => executeCommand(session, command, &directory, &streamMetrics),
+              None => render(session.execute(statement).and_then(|resultSet| readResult(resultSet, &streamMetrics)))
+            };
+
+            if !expected.as_ref( ).is_some_and(|expected| matches(expected, &output)) {
+              mismatches += 1;
+              *mismatch= Some(output);
+            }
+          }
+        }
+      }
+
+      (items, mismatches)
+    }).await.map_err(|error| Error::Internal(error.to_string( )))?;
+
+    self.items= items;
+    Ok(mismatches)
+  }
+
+  // Prints the statements whose output didn't match the expected one, along with both the outputs.
+  fn reportMismatches(&self) {
+    for item in &self.items {
+      if let Item::Statement { statement, expected, mismatch: Some(output) }= item {
+        println!("\n{}", statement);
+        match expected {
+          Some(expected) => {
+            for line in expected {
+              println!("- {}", line);}
+          },
+          None => println!("(no expected output)")
+        }
+        for line in output {
+          println!("+ {}", line);}
+      }
+    }
+    println!( );
+  }
+}
+
+// Writes the script back, with the mismatching expected outputs replaced by the actual ones.
+impl std::fmt::Display for Script {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for item in &self.items {
+      match item {
+        Item::Line(line) => writeln!(f, "{}", line)?,
+
+        Item::Connection(name) => writeln!(f, "{} {}", CONNECTION_DIRECTIVE, name)?,
+
+        Item::Statement { statement, expected, mismatch } => {
+          writeln!(f, "{}\n{}", statement, OUTPUT_SEPARATOR)?;
+          for line in mismatch.as_ref( ).or(expected.as_ref( )).into_iter( ).flatten( ) {
+            writeln!(f, "{}", line)?;}
+        }
+      }
+    }
+    Ok(( ))
+  }
+}
+
+// Renders the result of a statement as lines of output. Messages are the ones the client shows.
+fn render(result: Result<StatementResult>) -> Vec<String> {
+  let output= match result {
+    Ok(StatementResult::Begin { version, readonly: true, .. }) => format!("Began read-only transaction at version {}", version),
+    Ok(StatementResult::Begin { version, readonly: false, .. }) => format!("Began transaction {}", version),
+    Ok(StatementResult::Commit { version }) => format!("Committed transaction {}", version),
+    Ok(StatementResult::Rollback { version }) => format!("Rolled back transaction {}", version),
+
+    Ok(StatementResult::CreateTable { name }) => format!("Created table {}", name),
+    Ok(StatementResult::CreateIndex { name }) => format!("Created index {}", name),
+    Ok(StatementResult::DropTable { name }) => format!("Dropped table {}", name),
+    Ok(StatementResult::AlterTable { name }) => format!("Altered table {}", name),
+
+    Ok(StatementResult::Analyze { count }) => match count {
+      1 => "Analyzed 1 table".to_string( ),
+      count => format!("Analyzed {} tables", count)
+    },
+
+    Ok(StatementResult::Insert { count }) => format!("Inserted {}", pluralizeRows(count)),
+    Ok(StatementResult::Update { count }) => format!("Updated {}", pluralizeRows(count)),
+    Ok(StatementResult::Delete { count }) => format!("Deleted {}", pluralizeRows(count)),
+
+    Ok(StatementResult::CopyIn { table }) => format!("Copying rows into table {}", table),
+    Ok(StatementResult::Copy { count }) => format!("Copied {}", pluralizeRows(count)),
+
+    Ok(StatementResult::Set { variable }) => format!("Set {}", variable),
+
+    Ok(StatementResult::Query { columns, rows }) => csv::formatRows(&columns, &rows),
+
+    Ok(StatementResult::Explain(plan)) => plan,
+
+    Err(error) => {
+      let (variant, message)= describeError(&error);
+      format!("error: {} {}: {}", variant, error.code( ), message)
+    }
+  };
+
+  output.lines( ).map(str::to_string).collect( )
+}
+
+// Reads the result of a statement from the response frames it's streamed in (see ResultFrames),
+// materializing the rows of a query.
+fn readResult(resultSet: ResultSet, streamMetrics: &StreamMetrics) -> Result<StatementResult> {
+  let mut frames= ResultFrames::new(resultSet, DEFAULT_ROW_BATCH_SIZE, streamMetrics.clone( ));
+
+  let columns= match frames.next( ).transpose( )? {
+    Some(Response::Execute(result)) => return Ok(result),
+    Some(Response::QueryHeader(columns)) => columns,
+    response => return Err(Error::Internal(format!("Unexpected response {:?} to a statement", response)))
+  };
+
+  let mut rows= Vec::new( );
+  for frame in frames {
+    match frame? {
+      Response::Rows(batch) => rows.extend(batch),
+      Response::QueryEnd { count } if count == rows.len( ) as u64 => { },
+      response => return Err(Error::Internal(format!("Unexpected response {:?} while reading {} rows of a query", response, rows.len( ))))
+    }
+  }
+  Ok(StatementResult::Query { columns, rows })
+}
+
+// Executes a command of the client's shell in the session, rendering its result like render( ). The
+// files are kept in the given directory.
+fn executeCommand<E: Engine>(session: &mut Session<E>, command: &str, directory: &Path, streamMetrics: &StreamMetrics) -> Vec<String> {
+  let result= match command.split_whitespace( ).collect::<Vec<_>>( ).as_slice( ) {
+    ["status"] => session.status( )
+                        .map(|status| Status { streaming: Some(streamMetrics.status( )), ..status })
+                        .map(|status| status.rows( ).iter( ).map(csv::formatRow).collect::<Vec<_>>( ).join("\n")),
+
+    ["backup", file] => session.backup(&directory.join(file), |_, _| { })
+                               .map(|summary| format!("Backed up {} tables ({})", summary.tables, pluralizeRows(summary.rows))),
+
+    ["restore", file, flags @ ..] if flags.is_empty( ) || flags == ["--force"] =>
+      session.restore(&directory.join(file), !flags.is_empty( ), |_, _| { })
+             .map(|summary| format!("Restored {} tables ({})", summary.tables, pluralizeRows(summary.rows))),
+
+    _ => Err(Error::Value(ErrorCode::UndefinedObject, format!("Unknown command !{}", command)))
+  };
+
+  match result {
+    Ok(message) => message.lines( ).map(str::to_string).collect( ),
+    Err(error) => render(Err(error))
+  }
+}
+
+fn pluralizeRows(count: u64) -> String {
+  match count {
+    1 => "1 row".to_string( ),
+    count => format!("{} rows", count)
+  }
+}
+
+// Returns the name of the error's variant, along with its message.
+fn describeError(error: &Error) -> (&'static str, String) {
+  match error {
+    Error::Abort => ("Abort", error.to_string( )),
+    Error::Internal(message) => ("Internal", message.clone( )),
+    Error::Corruption(message) => ("Corruption", message.clone( )),
+    Error::Parse(message) => ("Parse", message.clone( )),
+    Error::ParseAt { line, column, message } => ("ParseAt", format!("line {}, column {}: {}", line, column, message)),
+    Error::Value(_, message) => ("Value", message.clone( )),
+    Error::Serialization => ("Serialization", error.to_string( )),
+    Error::ReadOnly => ("ReadOnly", error.to_string( )),
+    Error::NotLeader { .. } => ("NotLeader", error.to_string( )),
+    Error::Cancelled(message) => ("Cancelled", message.clone( ))
+  }
+}
+
+// Returns whether the output matches the expected one. An expected error only needs to match the
+// variant (and optionally the code), and a part of the message (see scripts( )).
+fn matches(expected: &[String], output: &[String]) -> bool {
+  if let ([expected], [output])= (expected, output) {
+    if let (Some(expected), Some(output))= (expected.strip_prefix("error: "), output.strip_prefix("error: ")) {
+      let (expectedVariant, expectedMessage)= expected.split_once(": ").unwrap_or((expected, ""));
+      let (variant, message)= output.split_once(": ").unwrap_or((output, ""));
+
+      let variantMatches= expectedVariant == variant || variant.split(' ').next( ) == Some(expectedVariant);
+      return variantMatches && message.contains(expectedMessage)
+    }
+  }
+
+  match expected.split_first( ) {
+    Some((first, rest)) if first == ELLIPSIS => (0..=output.len( )).any(|skipped| matches(rest, &output[skipped..])),
+    Some((first, rest)) => output.first( ) == Some(first) && matches(rest, &output[1..]),
+    None => output.is_empty( )
+  }
+}
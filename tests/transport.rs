@@ -0,0 +1,161 @@
+#![allow(non_snake_case)]
+
+use std::{collections::BTreeMap, net::TcpListener, time::Duration};
+use distributed_sql_based_database_in_rust::{
+  raft::{
+    cluster_config::ClusterConfig,
+    message::{Message, MessageAddress, MessagePayload},
+    transport::{Transport, DEFAULT_MAX_MESSAGE_SIZE},
+    types::NodeId
+  },
+  result::{Error, Result}
+};
+use tokio::{
+  sync::{mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender}, watch},
+  task::JoinHandle,
+  time::{timeout, Instant}
+};
+
+// How long a message is resent for, before it's considered undeliverable.
+const DELIVERY_TIMEOUT: Duration= Duration::from_secs(10);
+
+// How long a sent message is waited for, before it's sent again.
+const RESEND_INTERVAL: Duration= Duration::from_millis(100);
+
+// Returns the configuration of a cluster of the given size, where each node listens on a free
+// localhost port.
+fn newClusterConfig(size: u64) -> Result<ClusterConfig> {
+  let mut nodes= BTreeMap::new( );
+  for id in 1..=size {
+    // The port picked by the OS is released, for the node's transport to listen on.
+    let port= TcpListener::bind("127.0.0.1:0")?.local_addr( )?.port( );
+    nodes.insert(NodeId(id), format!("127.0.0.1:{}", port));
+  }
+
+  Ok(ClusterConfig::new(nodes))
+}
+
+// The transport of a node, along with the other ends of the node's channels.
+struct TestNode {
+  outbound: UnboundedSender<Message>,
+  inbound: UnboundedReceiver<Message>,
+  transport: JoinHandle<Result<( )>>
+}
+
+impl TestNode {
+  fn start(id: u64, config: &ClusterConfig) -> Self {
+    let (inboundSender, inbound)= unbounded_channel( );
+    let (outbound, outboundReceiver)= unbounded_channel( );
+    let (_, configReceiver)= watch::channel(config.clone( ));
+
+    let transport= Transport::new(NodeId(id), config.clone( ), DEFAULT_MAX_MESSAGE_SIZE);
+    let transport= tokio::spawn(transport.run(inboundSender, outboundReceiver, configReceiver));
+
+    Self { outbound, inbound, transport }
+  }
+
+  // Stops the transport, as if the node crashed. The connections to it are closed once its peers
+  // send messages over them.
+  async fn stop(self) -> Result<( )> {
+    drop(self.outbound);
+    drop(self.inbound);
+
+    self.transport.await.map_err(|error| Error::Internal(error.to_string( )))?
+  }
+
+  fn send(&self, message: &Message) -> Result<( )> {
+    self.outbound.send(message.clone( )).map_err(|error| Error::Internal(error.to_string( )))
+  }
+
+  async fn receive(&mut self, within: Duration) -> Option<Message> {
+    timeout(within, self.inbound.recv( )).await.ok( ).flatten( )
+  }
+}
+
+fn message(from: u64, to: MessageAddress, payload: MessagePayload) -> Message {
+  Message { currentTermOfSender: 1, from: MessageAddress::Node(NodeId(from)), to, payload }
+}
+
+fn heartbeat(from: u64, to: MessageAddress) -> Message {
+  message(from, to, MessagePayload::Heartbeat { commitIndex: 0, commitTerm: 0, readSequence: 1 })
+}
+
+// Sends the message until the receiver gets it, since the messages sent while the sender isn't
+// connected to the receiver are dropped.
+async fn deliver(sender: &TestNode, receiver: &mut TestNode, message: Message) -> Result<( )> {
+  let deadline= Instant::now( ) + DELIVERY_TIMEOUT;
+
+  while Instant::now( ) < deadline {
+    sender.send(&message)?;
+
+    if let Some(received)= receiver.receive(RESEND_INTERVAL).await {
+      assert_eq!(received, message);
+      return Ok(( ))
+    }
+  }
+
+  Err(Error::Internal(format!("Message {:?} wasn't delivered", message)))
+}
+
+#[tokio::test]
+async fn exchangesHeartbeats( ) -> Result<( )> {
+  let config= newClusterConfig(2)?;
+  let (mut leader, mut follower)= (TestNode::start(1, &config), TestNode::start(2, &config));
+
+  deliver(&leader, &mut follower, heartbeat(1, MessageAddress::Node(NodeId(2)))).await?;
+
+  let response= message(2, MessageAddress::Node(NodeId(1)), MessagePayload::HeartbeatResponse { readSequence: 1 });
+  deliver(&follower, &mut leader, response).await?;
+
+  leader.stop( ).await?;
+  follower.stop( ).await
+}
+
+#[tokio::test]
+async fn reconnectsAfterPeerRestarts( ) -> Result<( )> {
+  let config= newClusterConfig(2)?;
+  let (leader, mut follower)= (TestNode::start(1, &config), TestNode::start(2, &config));
+
+  deliver(&leader, &mut follower, heartbeat(1, MessageAddress::Node(NodeId(2)))).await?;
+
+  // The follower restarts on the same address, once its listener is closed.
+  follower.stop( ).await?;
+  tokio::time::sleep(RESEND_INTERVAL).await;
+  let mut follower= TestNode::start(2, &config);
+
+  // The leader's connection to the old listener fails, and it reconnects to the new one.
+  deliver(&leader, &mut follower, heartbeat(1, MessageAddress::Node(NodeId(2)))).await?;
+
+  leader.stop( ).await?;
+  follower.stop( ).await
+}
+
+#[tokio::test]
+async fn fansOutBroadcastToPeers( ) -> Result<( )> {
+  let config= newClusterConfig(3)?;
+  let mut nodes= (1..=3).map(|id| TestNode::start(id, &config)).collect::<Vec<_>>( );
+
+  // The heartbeat is broadcasted until every peer has received it.
+  let broadcast= heartbeat(1, MessageAddress::Broadcast);
+  let mut receivedBy= [false; 3];
+  let deadline= Instant::now( ) + DELIVERY_TIMEOUT;
+
+  while !(receivedBy[1] && receivedBy[2]) {
+    assert!(Instant::now( ) < deadline, "Broadcast wasn't delivered to every peer");
+    nodes[0].send(&broadcast)?;
+
+    for (index, node) in nodes.iter_mut( ).enumerate( ).skip(1) {
+      if let Some(received)= node.receive(RESEND_INTERVAL).await {
+        assert_eq!(received, broadcast);
+        receivedBy[index]= true;
+      }
+    }
+  }
+
+  // The sender doesn't receive its own broadcast.
+  assert_eq!(nodes[0].receive(RESEND_INTERVAL).await, None);
+
+  for node in nodes {
+    node.stop( ).await?;}
+  Ok(( ))
+}